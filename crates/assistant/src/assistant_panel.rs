@@ -83,6 +83,7 @@ use ui::{
 };
 use util::{maybe, ResultExt};
 use workspace::{
+    assistant_delegate::AssistantDelegate,
     dock::{DockPosition, Panel, PanelEvent},
     item::{self, FollowableItem, Item, ItemHandle},
     notifications::NotificationId,
@@ -96,6 +97,7 @@ use zed_actions::InlineAssist;
 
 pub fn init(cx: &mut AppContext) {
     workspace::FollowableViewRegistry::register::<ContextEditor>(cx);
+    <dyn AssistantDelegate>::set_global(Arc::new(ContextEditorDelegate), cx);
     cx.observe_new_views(
         |workspace: &mut Workspace, _cx: &mut ViewContext<Workspace>| {
             workspace
@@ -128,6 +130,28 @@ pub fn init(cx: &mut AppContext) {
     .detach();
 }
 
+/// Gives items outside of this crate access to the active context, see [`AssistantDelegate`].
+struct ContextEditorDelegate;
+
+impl AssistantDelegate for ContextEditorDelegate {
+    fn quote_creases(
+        &self,
+        workspace: &mut Workspace,
+        creases: Vec<(String, String)>,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        ContextEditor::quote_creases(workspace, creases, cx);
+    }
+
+    fn selection_or_code_block(
+        &self,
+        workspace: &mut Workspace,
+        cx: &mut ViewContext<Workspace>,
+    ) -> Option<String> {
+        ContextEditor::selection_or_code_block(workspace, cx)
+    }
+}
+
 pub enum AssistantPanelEvent {
     ContextEdited,
 }
@@ -2923,6 +2947,16 @@ impl ContextEditor {
         })
     }
 
+    /// The selected text in the active context, or the code block under the cursor.
+    pub fn selection_or_code_block(
+        workspace: &mut Workspace,
+        cx: &mut ViewContext<Workspace>,
+    ) -> Option<String> {
+        let panel = workspace.panel::<AssistantPanel>(cx)?;
+        let context_editor_view = panel.read(cx).active_context_editor(cx)?;
+        Self::get_selection_or_code_block(&context_editor_view, cx).map(|(text, _)| text)
+    }
+
     fn insert_selection(
         workspace: &mut Workspace,
         _: &InsertIntoEditor,
@@ -3058,13 +3092,26 @@ impl ContextEditor {
         _: &QuoteSelection,
         cx: &mut ViewContext<Workspace>,
     ) {
-        let Some(panel) = workspace.panel::<AssistantPanel>(cx) else {
+        if workspace.panel::<AssistantPanel>(cx).is_none() {
             return;
-        };
+        }
 
         let Some(creases) = selections_creases(workspace, cx) else {
             return;
         };
+        Self::quote_creases(workspace, creases, cx);
+    }
+
+    /// Quotes text into the active context, or a new one, folded under the title paired with it.
+    /// This lets items that aren't editors, like notebooks, quote their content.
+    pub fn quote_creases(
+        workspace: &mut Workspace,
+        creases: Vec<(String, String)>,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(panel) = workspace.panel::<AssistantPanel>(cx) else {
+            return;
+        };
 
         if creases.is_empty() {
             return;
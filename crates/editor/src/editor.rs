@@ -75,7 +75,7 @@ use gpui::{
     div, impl_actions, point, prelude::*, px, relative, size, uniform_list, Action, AnyElement,
     AppContext, AsyncWindowContext, AvailableSpace, BackgroundExecutor, Bounds, ClipboardEntry,
     ClipboardItem, Context, DispatchPhase, ElementId, EventEmitter, FocusHandle, FocusOutEvent,
-    FocusableView, FontId, FontWeight, HighlightStyle, Hsla, InteractiveText, KeyContext,
+    FocusableView, FontId, FontWeight, Global, HighlightStyle, Hsla, InteractiveText, KeyContext,
     ListSizingBehavior, Model, ModelContext, MouseButton, PaintQuad, ParentElement, Pixels, Render,
     ScrollStrategy, SharedString, Size, StrikethroughStyle, Styled, StyledText, Subscription, Task,
     TextStyle, TextStyleRefinement, UTF16Selection, UnderlineStyle, UniformListScrollHandle, View,
@@ -123,6 +123,7 @@ use multi_buffer::{
 use ordered_float::OrderedFloat;
 use parking_lot::{Mutex, RwLock};
 use project::{
+    lsp_command::SignatureHelp,
     lsp_store::{FormatTarget, FormatTrigger},
     project_settings::{GitGutterSetting, ProjectSettings},
     CodeAction, Completion, CompletionIntent, DocumentHighlight, InlayHint, Item, Location,
@@ -166,7 +167,7 @@ use util::{defer, maybe, post_inc, RangeExt, ResultExt, TryFutureExt};
 use workspace::item::{ItemHandle, PreviewTabsSettings};
 use workspace::notifications::{DetachAndPromptErr, NotificationId, NotifyTaskExt};
 use workspace::{
-    searchable::SearchEvent, ItemNavHistory, SplitDirection, ViewId, Workspace, WorkspaceId,
+    searchable::SearchEvent, ItemNavHistory, Pane, SplitDirection, ViewId, Workspace, WorkspaceId,
 };
 use workspace::{Item as WorkspaceItem, OpenInTerminal, OpenTerminal, TabBarSettings, Toast};
 
@@ -295,6 +296,41 @@ impl Navigated {
     }
 }
 
+/// Opens the excerpts of a buffer in something other than an editor, like a notebook in the
+/// notebook view, when they're opened from a multibuffer. Returns `false` for buffers it doesn't
+/// open, so they're opened in an editor.
+pub type OpenExcerptsHandler = Rc<
+    dyn Fn(
+        &Model<Buffer>,
+        &[Range<usize>],
+        &View<Pane>,
+        &mut Workspace,
+        &mut ViewContext<Workspace>,
+    ) -> bool,
+>;
+
+#[derive(Default)]
+struct OpenExcerptsHandlers(Vec<OpenExcerptsHandler>);
+
+impl Global for OpenExcerptsHandlers {}
+
+/// Lets `handler` open excerpts before they're opened in an editor, see [`OpenExcerptsHandler`].
+pub fn register_open_excerpts_handler(
+    handler: impl Fn(
+            &Model<Buffer>,
+            &[Range<usize>],
+            &View<Pane>,
+            &mut Workspace,
+            &mut ViewContext<Workspace>,
+        ) -> bool
+        + 'static,
+    cx: &mut AppContext,
+) {
+    cx.default_global::<OpenExcerptsHandlers>()
+        .0
+        .push(Rc::new(handler));
+}
+
 pub fn init_settings(cx: &mut AppContext) {
     EditorSettings::register(cx);
 }
@@ -12887,7 +12923,17 @@ impl Editor {
                     workspace.active_pane().clone()
                 };
 
+                let handlers = cx
+                    .try_global::<OpenExcerptsHandlers>()
+                    .map(|handlers| handlers.0.clone())
+                    .unwrap_or_default();
                 for (buffer, (ranges, scroll_offset)) in new_selections_by_buffer {
+                    if handlers
+                        .iter()
+                        .any(|handler| handler(&buffer, &ranges, &pane, workspace, cx))
+                    {
+                        continue;
+                    }
                     let editor =
                         workspace.open_project_item::<Self>(pane.clone(), buffer, true, true, cx);
                     editor.update(cx, |editor, cx| {
@@ -13670,6 +13716,13 @@ pub trait SemanticsProvider {
         cx: &mut AppContext,
     ) -> Option<Task<Vec<project::Hover>>>;
 
+    fn signature_help(
+        &self,
+        buffer: &Model<Buffer>,
+        position: text::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<Task<Vec<SignatureHelp>>>;
+
     fn inlay_hints(
         &self,
         buffer_handle: Model<Buffer>,
@@ -13969,6 +14022,17 @@ impl SemanticsProvider for Model<Project> {
         Some(self.update(cx, |project, cx| project.hover(buffer, position, cx)))
     }
 
+    fn signature_help(
+        &self,
+        buffer: &Model<Buffer>,
+        position: text::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<Task<Vec<SignatureHelp>>> {
+        Some(self.update(cx, |project, cx| {
+            project.signature_help(buffer, position, cx)
+        }))
+    }
+
     fn document_highlights(
         &self,
         buffer: &Model<Buffer>,
@@ -297,6 +297,7 @@ impl FollowableItem for Editor {
                 }
                 _ => false,
             },
+            _ => false,
         }
     }
 
@@ -306,7 +307,9 @@ impl FollowableItem for Editor {
         message: update_view::Variant,
         cx: &mut ViewContext<Self>,
     ) -> Task<Result<()>> {
-        let update_view::Variant::Editor(message) = message;
+        let update_view::Variant::Editor(message) = message else {
+            return Task::ready(Ok(()));
+        };
         let project = project.clone();
         cx.spawn(|this, mut cx| async move {
             update_editor_from_message(this, project, message, &mut cx).await
@@ -396,6 +396,16 @@ impl SemanticsProvider for BranchBufferSemanticsProvider {
         self.0.hover(&buffer, position, cx)
     }
 
+    fn signature_help(
+        &self,
+        buffer: &Model<Buffer>,
+        position: text::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<Task<Vec<project::lsp_command::SignatureHelp>>> {
+        let buffer = self.to_base(buffer, &[position], cx)?;
+        self.0.signature_help(&buffer, position, cx)
+    }
+
     fn inlay_hints(
         &self,
         buffer: Model<Buffer>,
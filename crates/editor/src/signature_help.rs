@@ -165,16 +165,12 @@ impl Editor {
                 let signature_help = editor
                     .update(&mut cx, |editor, cx| {
                         let language = editor.language_at(position, cx);
-                        let project = editor.project.clone()?;
-                        let (markdown, language_registry) = {
-                            project.update(cx, |project, cx| {
-                                let language_registry = project.languages().clone();
-                                (
-                                    project.signature_help(&buffer, buffer_position, cx),
-                                    language_registry,
-                                )
-                            })
+                        let provider = editor.semantics_provider.clone()?;
+                        let language_registry = match &editor.project {
+                            Some(project) => project.read(cx).languages().clone(),
+                            None => buffer.read(cx).language_registry()?,
                         };
+                        let markdown = provider.signature_help(&buffer, buffer_position, cx)?;
                         Some((markdown, language_registry, language))
                     })
                     .ok()
@@ -30,6 +30,9 @@ pub trait GitRepository: Send + Sync {
     /// Note that for symlink entries, this will return the contents of the symlink, not the target.
     fn load_index_text(&self, relative_file_path: &Path) -> Option<String>;
 
+    /// Loads a file's contents as of the HEAD commit.
+    fn load_committed_text(&self, relative_file_path: &Path) -> Option<String>;
+
     /// Returns the URL of the remote with the given name.
     fn remote_url(&self, name: &str) -> Option<String>;
     fn branch_name(&self) -> Option<String>;
@@ -114,6 +117,27 @@ impl GitRepository for RealGitRepository {
         None
     }
 
+    fn load_committed_text(&self, relative_file_path: &Path) -> Option<String> {
+        fn logic(repo: &git2::Repository, relative_file_path: &Path) -> Result<Option<String>> {
+            check_path_to_repo_path_errors(relative_file_path)?;
+
+            let tree = repo.head()?.peel_to_tree()?;
+            let entry = match tree.get_path(relative_file_path) {
+                Ok(entry) if entry.filemode() != GIT_MODE_SYMLINK as i32 => entry,
+                _ => return Ok(None),
+            };
+
+            let content = repo.find_blob(entry.id())?.content().to_owned();
+            Ok(Some(String::from_utf8(content)?))
+        }
+
+        match logic(&self.repository.lock(), relative_file_path) {
+            Ok(value) => return value,
+            Err(err) => log::error!("Error loading committed text: {:?}", err),
+        }
+        None
+    }
+
     fn remote_url(&self, name: &str) -> Option<String> {
         let repo = self.repository.lock();
         let remote = repo.find_remote(name).ok()?;
@@ -236,6 +260,7 @@ pub struct FakeGitRepositoryState {
     pub path: PathBuf,
     pub event_emitter: smol::channel::Sender<PathBuf>,
     pub index_contents: HashMap<PathBuf, String>,
+    pub committed_contents: HashMap<PathBuf, String>,
     pub blames: HashMap<PathBuf, Blame>,
     pub worktree_statuses: HashMap<RepoPath, GitFileStatus>,
     pub current_branch_name: Option<String>,
@@ -254,6 +279,7 @@ impl FakeGitRepositoryState {
             path,
             event_emitter,
             index_contents: Default::default(),
+            committed_contents: Default::default(),
             blames: Default::default(),
             worktree_statuses: Default::default(),
             current_branch_name: Default::default(),
@@ -270,6 +296,11 @@ impl GitRepository for FakeGitRepository {
         state.index_contents.get(path).cloned()
     }
 
+    fn load_committed_text(&self, path: &Path) -> Option<String> {
+        let state = self.state.lock();
+        state.committed_contents.get(path).cloned()
+    }
+
     fn remote_url(&self, _name: &str) -> Option<String> {
         None
     }
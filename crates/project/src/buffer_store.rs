@@ -92,6 +92,8 @@ pub struct BufferStore {
     >,
     worktree_store: Model<WorktreeStore>,
     opened_buffers: HashMap<BufferId, OpenBuffer>,
+    /// Open buffers that back something else rather than being edited themselves.
+    hidden_buffers: HashSet<BufferId>,
     downstream_client: Option<(AnyProtoClient, u64)>,
     shared_buffers: HashMap<proto::PeerId, HashSet<Model<Buffer>>>,
 }
@@ -976,6 +978,7 @@ impl BufferStore {
             })),
             downstream_client: None,
             opened_buffers: Default::default(),
+            hidden_buffers: Default::default(),
             shared_buffers: Default::default(),
             loading_buffers_by_path: Default::default(),
             worktree_store,
@@ -1001,6 +1004,7 @@ impl BufferStore {
             })),
             downstream_client: None,
             opened_buffers: Default::default(),
+            hidden_buffers: Default::default(),
             loading_buffers_by_path: Default::default(),
             shared_buffers: Default::default(),
             worktree_store,
@@ -1233,7 +1237,8 @@ impl BufferStore {
         buffer.update(cx, move |_, cx| {
             cx.on_release(move |buffer, cx| {
                 handle
-                    .update(cx, |_, cx| {
+                    .update(cx, |this, cx| {
+                        this.hidden_buffers.remove(&buffer.remote_id());
                         cx.emit(BufferStoreEvent::BufferDropped(buffer.remote_id()))
                     })
                     .ok();
@@ -1271,6 +1276,20 @@ impl BufferStore {
             .filter_map(|buffer| buffer.upgrade())
     }
 
+    /// Keeps a buffer out of project search and the lists of open buffers. Language servers
+    /// still see it, so it can back something else, like a notebook's cell document.
+    pub fn hide_buffer(&mut self, buffer_id: BufferId) {
+        self.hidden_buffers.insert(buffer_id);
+    }
+
+    /// The open buffers that aren't hidden.
+    pub fn visible_buffers(&self) -> impl '_ + Iterator<Item = Model<Buffer>> {
+        self.opened_buffers
+            .iter()
+            .filter(|(buffer_id, _)| !self.hidden_buffers.contains(buffer_id))
+            .filter_map(|(_, buffer)| buffer.upgrade())
+    }
+
     pub fn loading_buffers(
         &self,
     ) -> impl Iterator<
@@ -1395,7 +1414,7 @@ impl BufferStore {
         let (tx, rx) = smol::channel::unbounded();
         let mut open_buffers = HashSet::default();
         let mut unnamed_buffers = Vec::new();
-        for handle in self.buffers() {
+        for handle in self.visible_buffers() {
             let buffer = handle.read(cx);
             if let Some(entry_id) = buffer.entry_id(cx) {
                 open_buffers.insert(entry_id);
@@ -0,0 +1,725 @@
+//! Jupyter kernels for SSH projects. They run on the remote host, next to the project's files, and
+//! the remote server relays their messages over the SSH connection.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context as _, Result};
+use collections::HashMap;
+use fs::Fs;
+use futures::{
+    channel::{mpsc, oneshot},
+    io::BufReader,
+    AsyncBufReadExt as _, SinkExt as _, StreamExt as _,
+};
+use gpui::{AppContext, AsyncAppContext, Model, ModelContext, Task};
+use language::LanguageName;
+use rpc::{proto, AnyProtoClient, TypedEnvelope};
+use runtimelib::{dirs, ConnectionInfo, JupyterKernelspec, JupyterMessage, JupyterMessageContent};
+use settings::WorktreeId;
+use smol::{net::TcpListener, process::Command};
+use util::ResultExt as _;
+
+use crate::ToolchainStore;
+
+/// A kernel that can be started on the project's host.
+#[derive(Clone, Debug)]
+pub struct KernelSpecification {
+    pub name: String,
+    /// The kernelspec's directory, or the interpreter of a Python environment.
+    pub path: PathBuf,
+    pub kernelspec: JupyterKernelspec,
+}
+
+impl KernelSpecification {
+    fn to_proto(&self) -> Result<proto::KernelSpecification> {
+        Ok(proto::KernelSpecification {
+            name: self.name.clone(),
+            path: self.path.to_string_lossy().to_string(),
+            kernelspec: serde_json::to_string(&self.kernelspec)?,
+        })
+    }
+
+    fn from_proto(specification: proto::KernelSpecification) -> Result<Self> {
+        Ok(Self {
+            name: specification.name,
+            path: specification.path.into(),
+            kernelspec: serde_json::from_str(&specification.kernelspec)?,
+        })
+    }
+}
+
+/// A kernel started on the project's host.
+pub struct KernelConnection {
+    /// Requests for the kernel. It's shut down once every sender is dropped.
+    pub request_tx: mpsc::Sender<JupyterMessage>,
+    /// Everything the kernel sends, on any of its channels.
+    pub messages: mpsc::Receiver<JupyterMessage>,
+    /// Resolves when the kernel stops, with an error unless it exited successfully.
+    pub stopped: oneshot::Receiver<Option<String>>,
+}
+
+pub struct KernelStore {
+    mode: KernelStoreMode,
+}
+
+enum KernelStoreMode {
+    Local(LocalKernelStore),
+    Remote(RemoteKernelStore),
+}
+
+struct LocalKernelStore {
+    fs: Arc<dyn Fs>,
+    toolchain_store: Model<ToolchainStore>,
+    kernels: HashMap<u64, LocalKernel>,
+    next_kernel_id: u64,
+    downstream_client: Option<(AnyProtoClient, u64)>,
+}
+
+struct LocalKernel {
+    request_tx: mpsc::Sender<JupyterMessage>,
+    process: smol::process::Child,
+    connection_path: PathBuf,
+    _tasks: Vec<Task<Result<()>>>,
+}
+
+impl Drop for LocalKernel {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.connection_path).ok();
+        self.request_tx.close_channel();
+        self.process.kill().ok();
+    }
+}
+
+struct RemoteKernelStore {
+    upstream_client: AnyProtoClient,
+    project_id: u64,
+    kernels: HashMap<u64, RemoteKernel>,
+}
+
+struct RemoteKernel {
+    messages_tx: mpsc::Sender<JupyterMessage>,
+    stopped_tx: Option<oneshot::Sender<Option<String>>>,
+}
+
+impl KernelStore {
+    pub fn init(client: &AnyProtoClient) {
+        client.add_model_request_handler(Self::handle_list_kernel_specifications);
+        client.add_model_request_handler(Self::handle_start_kernel);
+        client.add_model_request_handler(Self::handle_shutdown_kernel);
+        client.add_model_message_handler(Self::handle_send_kernel_message);
+        client.add_model_message_handler(Self::handle_kernel_message);
+        client.add_model_message_handler(Self::handle_kernel_stopped);
+    }
+
+    pub fn local(fs: Arc<dyn Fs>, toolchain_store: Model<ToolchainStore>) -> Self {
+        Self {
+            mode: KernelStoreMode::Local(LocalKernelStore {
+                fs,
+                toolchain_store,
+                kernels: HashMap::default(),
+                next_kernel_id: 0,
+                downstream_client: None,
+            }),
+        }
+    }
+
+    pub fn remote(project_id: u64, upstream_client: AnyProtoClient) -> Self {
+        Self {
+            mode: KernelStoreMode::Remote(RemoteKernelStore {
+                upstream_client,
+                project_id,
+                kernels: HashMap::default(),
+            }),
+        }
+    }
+
+    pub fn shared(&mut self, project_id: u64, downstream_client: AnyProtoClient) {
+        if let KernelStoreMode::Local(local) = &mut self.mode {
+            local.downstream_client = Some((downstream_client, project_id));
+        }
+    }
+
+    /// The kernels that can be started for a worktree: the kernelspecs installed on the host, and
+    /// the Python environments of the worktree that have `ipykernel`.
+    pub fn kernel_specifications(
+        &self,
+        worktree_id: WorktreeId,
+        cx: &AppContext,
+    ) -> Task<Result<Vec<KernelSpecification>>> {
+        match &self.mode {
+            KernelStoreMode::Local(local) => {
+                let fs = local.fs.clone();
+                let toolchains = local.toolchain_store.read(cx).list_toolchains(
+                    worktree_id,
+                    LanguageName::new("Python"),
+                    cx,
+                );
+                cx.background_executor().spawn(async move {
+                    let mut specifications = installed_kernel_specifications(fs.as_ref()).await;
+                    if let Some(toolchains) = toolchains.await {
+                        let python_paths = toolchains
+                            .toolchains
+                            .into_iter()
+                            .map(|toolchain| (toolchain.name.to_string(), toolchain.path));
+                        for (name, python_path) in python_paths {
+                            if let Some(specification) =
+                                python_env_kernel_specification(name, python_path.to_string()).await
+                            {
+                                specifications.push(specification);
+                            }
+                        }
+                    }
+                    Ok(specifications)
+                })
+            }
+            KernelStoreMode::Remote(remote) => {
+                let request = remote
+                    .upstream_client
+                    .request(proto::ListKernelSpecifications {
+                        project_id: remote.project_id,
+                        worktree_id: worktree_id.to_proto(),
+                    });
+                cx.background_executor().spawn(async move {
+                    request
+                        .await?
+                        .kernels
+                        .into_iter()
+                        .map(KernelSpecification::from_proto)
+                        .collect()
+                })
+            }
+        }
+    }
+
+    /// Starts a kernel on the project's host.
+    pub fn start_kernel(
+        &mut self,
+        specification: KernelSpecification,
+        working_directory: PathBuf,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<KernelConnection>> {
+        let KernelStoreMode::Remote(remote) = &self.mode else {
+            return Task::ready(Err(anyhow!(
+                "kernels of local projects are started by the REPL"
+            )));
+        };
+
+        let client = remote.upstream_client.clone();
+        let project_id = remote.project_id;
+        let kernel = match specification.to_proto() {
+            Ok(kernel) => kernel,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|this, mut cx| async move {
+            let response = client
+                .request(proto::StartKernel {
+                    project_id,
+                    kernel: Some(kernel),
+                    working_directory: working_directory.to_string_lossy().to_string(),
+                })
+                .await?;
+            let kernel_id = response.kernel_id;
+
+            let (request_tx, mut request_rx) = mpsc::channel::<JupyterMessage>(100);
+            let (messages_tx, messages) = mpsc::channel(100);
+            let (stopped_tx, stopped) = oneshot::channel();
+
+            this.update(&mut cx, |this, _| {
+                if let KernelStoreMode::Remote(remote) = &mut this.mode {
+                    remote.kernels.insert(
+                        kernel_id,
+                        RemoteKernel {
+                            messages_tx,
+                            stopped_tx: Some(stopped_tx),
+                        },
+                    );
+                }
+            })?;
+
+            cx.spawn(|mut cx| async move {
+                while let Some(message) = request_rx.next().await {
+                    let Some(message) = serde_json::to_string(&message).log_err() else {
+                        continue;
+                    };
+                    client
+                        .send(proto::SendKernelMessage {
+                            project_id,
+                            kernel_id,
+                            message,
+                        })
+                        .log_err();
+                }
+
+                client
+                    .request(proto::ShutdownKernel {
+                        project_id,
+                        kernel_id,
+                    })
+                    .await
+                    .log_err();
+                this.update(&mut cx, |this, _| {
+                    if let KernelStoreMode::Remote(remote) = &mut this.mode {
+                        remote.kernels.remove(&kernel_id);
+                    }
+                })
+                .ok();
+            })
+            .detach();
+
+            Ok(KernelConnection {
+                request_tx,
+                messages,
+                stopped,
+            })
+        })
+    }
+
+    async fn handle_list_kernel_specifications(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::ListKernelSpecifications>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::ListKernelSpecificationsResponse> {
+        let worktree_id = WorktreeId::from_proto(envelope.payload.worktree_id);
+        let specifications = this
+            .update(&mut cx, |this, cx| {
+                this.kernel_specifications(worktree_id, cx)
+            })?
+            .await?;
+
+        Ok(proto::ListKernelSpecificationsResponse {
+            kernels: specifications
+                .iter()
+                .filter_map(|specification| specification.to_proto().log_err())
+                .collect(),
+        })
+    }
+
+    async fn handle_start_kernel(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::StartKernel>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::StartKernelResponse> {
+        let specification = KernelSpecification::from_proto(
+            envelope
+                .payload
+                .kernel
+                .context("Missing `kernel` in payload")?,
+        )?;
+        let working_directory = PathBuf::from(envelope.payload.working_directory);
+
+        let kernel_id = this
+            .update(&mut cx, |this, cx| {
+                this.start_local_kernel(specification, working_directory, cx)
+            })?
+            .await?;
+        Ok(proto::StartKernelResponse { kernel_id })
+    }
+
+    async fn handle_shutdown_kernel(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::ShutdownKernel>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::Ack> {
+        this.update(&mut cx, |this, _| {
+            if let KernelStoreMode::Local(local) = &mut this.mode {
+                local.kernels.remove(&envelope.payload.kernel_id);
+            }
+        })?;
+        Ok(proto::Ack {})
+    }
+
+    async fn handle_send_kernel_message(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::SendKernelMessage>,
+        mut cx: AsyncAppContext,
+    ) -> Result<()> {
+        let message: JupyterMessage = serde_json::from_str(&envelope.payload.message)?;
+        let request_tx = this.update(&mut cx, |this, _| match &this.mode {
+            KernelStoreMode::Local(local) => local
+                .kernels
+                .get(&envelope.payload.kernel_id)
+                .map(|kernel| kernel.request_tx.clone()),
+            KernelStoreMode::Remote(_) => None,
+        })?;
+
+        if let Some(mut request_tx) = request_tx {
+            request_tx.send(message).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_kernel_message(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::KernelMessage>,
+        mut cx: AsyncAppContext,
+    ) -> Result<()> {
+        let message: JupyterMessage = serde_json::from_str(&envelope.payload.message)?;
+        let messages_tx = this.update(&mut cx, |this, _| match &this.mode {
+            KernelStoreMode::Remote(remote) => remote
+                .kernels
+                .get(&envelope.payload.kernel_id)
+                .map(|kernel| kernel.messages_tx.clone()),
+            KernelStoreMode::Local(_) => None,
+        })?;
+
+        // Each clone of a sender has a slot of its own, so this doesn't wait, and messages are
+        // delivered in the order they were received
+        if let Some(mut messages_tx) = messages_tx {
+            messages_tx.send(message).await.ok();
+        }
+        Ok(())
+    }
+
+    async fn handle_kernel_stopped(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::KernelStopped>,
+        mut cx: AsyncAppContext,
+    ) -> Result<()> {
+        this.update(&mut cx, |this, _| {
+            if let KernelStoreMode::Remote(remote) = &mut this.mode {
+                if let Some(mut kernel) = remote.kernels.remove(&envelope.payload.kernel_id) {
+                    if let Some(stopped_tx) = kernel.stopped_tx.take() {
+                        stopped_tx.send(envelope.payload.error).ok();
+                    }
+                }
+            }
+        })
+    }
+
+    /// Launches a kernel on this machine for a client, and relays its messages to the client.
+    fn start_local_kernel(
+        &mut self,
+        specification: KernelSpecification,
+        working_directory: PathBuf,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<u64>> {
+        let KernelStoreMode::Local(local) = &mut self.mode else {
+            return Task::ready(Err(anyhow!("kernels are started on the project's host")));
+        };
+        let Some((client, project_id)) = local.downstream_client.clone() else {
+            return Task::ready(Err(anyhow!("no client to relay kernel messages to")));
+        };
+        let fs = local.fs.clone();
+        let kernel_id = local.next_kernel_id;
+        local.next_kernel_id += 1;
+
+        cx.spawn(|this, mut cx| async move {
+            let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+            let ports = peek_ports(ip).await?;
+            let connection_info = ConnectionInfo {
+                transport: "tcp".to_string(),
+                ip: ip.to_string(),
+                stdin_port: ports[0],
+                control_port: ports[1],
+                hb_port: ports[2],
+                shell_port: ports[3],
+                iopub_port: ports[4],
+                signature_scheme: "hmac-sha256".to_string(),
+                key: uuid::Uuid::new_v4().to_string(),
+                kernel_name: Some(format!("zed-{}", specification.name)),
+            };
+
+            let runtime_dir = dirs::runtime_dir();
+            fs.create_dir(&runtime_dir)
+                .await
+                .with_context(|| format!("Failed to create jupyter runtime dir {runtime_dir:?}"))?;
+            let connection_path = runtime_dir.join(format!("kernel-zed-remote-{kernel_id}.json"));
+            fs.atomic_write(
+                connection_path.clone(),
+                serde_json::to_string(&connection_info)?,
+            )
+            .await?;
+
+            let mut process = kernel_command(&specification, &connection_path)?
+                .current_dir(&working_directory)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .context("failed to start the kernel process")?;
+            let stderr = process.stderr.take();
+            let status = process.status();
+
+            let session_id = uuid::Uuid::new_v4().to_string();
+            let mut iopub_socket =
+                runtimelib::create_client_iopub_connection(&connection_info, "", &session_id)
+                    .await?;
+            let mut shell_socket =
+                runtimelib::create_client_shell_connection(&connection_info, &session_id).await?;
+            let mut control_socket =
+                runtimelib::create_client_control_connection(&connection_info, &session_id).await?;
+            let mut stdin_socket =
+                runtimelib::create_client_stdin_connection(&connection_info, &session_id).await?;
+
+            let (request_tx, mut request_rx) = mpsc::channel::<JupyterMessage>(100);
+            let (mut shell_request_tx, mut shell_request_rx) = mpsc::channel(100);
+            let (mut control_request_tx, mut control_request_rx) = mpsc::channel(100);
+            let (mut stdin_reply_tx, mut stdin_reply_rx) = mpsc::channel(100);
+            let (messages_tx, mut messages_rx) = mpsc::channel::<JupyterMessage>(100);
+
+            let executor = cx.background_executor().clone();
+            let mut tasks = Vec::new();
+
+            tasks.push(executor.spawn(async move {
+                while let Some(message) = request_rx.next().await {
+                    match message.content {
+                        JupyterMessageContent::DebugRequest(_)
+                        | JupyterMessageContent::InterruptRequest(_)
+                        | JupyterMessageContent::ShutdownRequest(_) => {
+                            control_request_tx.send(message).await?;
+                        }
+                        JupyterMessageContent::InputReply(_) => {
+                            stdin_reply_tx.send(message).await?;
+                        }
+                        _ => {
+                            shell_request_tx.send(message).await?;
+                        }
+                    }
+                }
+                anyhow::Ok(())
+            }));
+
+            tasks.push(executor.spawn({
+                let mut messages_tx = messages_tx.clone();
+                async move {
+                    while let Ok(message) = iopub_socket.read().await {
+                        messages_tx.send(message).await?;
+                    }
+                    anyhow::Ok(())
+                }
+            }));
+
+            tasks.push(executor.spawn({
+                let mut messages_tx = messages_tx.clone();
+                async move {
+                    while let Some(message) = shell_request_rx.next().await {
+                        shell_socket.send(message).await.ok();
+                        let reply = shell_socket.read().await?;
+                        messages_tx.send(reply).await?;
+                    }
+                    anyhow::Ok(())
+                }
+            }));
+
+            tasks.push(executor.spawn({
+                let mut messages_tx = messages_tx.clone();
+                async move {
+                    while let Some(message) = control_request_rx.next().await {
+                        control_socket.send(message).await.ok();
+                        let reply = control_socket.read().await?;
+                        messages_tx.send(reply).await?;
+                    }
+                    anyhow::Ok(())
+                }
+            }));
+
+            tasks.push(executor.spawn({
+                let mut messages_tx = messages_tx;
+                async move {
+                    while let Ok(request) = stdin_socket.read().await {
+                        messages_tx.send(request).await?;
+                        let Some(reply) = stdin_reply_rx.next().await else {
+                            break;
+                        };
+                        stdin_socket.send(reply).await.ok();
+                    }
+                    anyhow::Ok(())
+                }
+            }));
+
+            tasks.push(executor.spawn({
+                let client = client.clone();
+                async move {
+                    while let Some(message) = messages_rx.next().await {
+                        let Some(message) = serde_json::to_string(&message).log_err() else {
+                            continue;
+                        };
+                        client.send(proto::KernelMessage {
+                            project_id,
+                            kernel_id,
+                            message,
+                        })?;
+                    }
+                    anyhow::Ok(())
+                }
+            }));
+
+            if let Some(stderr) = stderr {
+                tasks.push(executor.spawn(async move {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Some(Ok(line)) = lines.next().await {
+                        log::error!("kernel: {}", line);
+                    }
+                    anyhow::Ok(())
+                }));
+            }
+
+            tasks.push(cx.spawn({
+                let this = this.clone();
+                |mut cx| async move {
+                    let error = match status.await {
+                        Ok(status) if status.success() => None,
+                        Ok(status) => {
+                            Some(format!("kernel process exited with status: {status:?}"))
+                        }
+                        Err(error) => Some(format!("kernel process exited with error: {error:?}")),
+                    };
+
+                    this.update(&mut cx, |this, _| {
+                        if let KernelStoreMode::Local(local) = &mut this.mode {
+                            // The kernel was already removed if the client shut it down
+                            if local.kernels.remove(&kernel_id).is_some() {
+                                client.send(proto::KernelStopped {
+                                    project_id,
+                                    kernel_id,
+                                    error,
+                                })?;
+                            }
+                        }
+                        anyhow::Ok(())
+                    })?
+                }
+            }));
+
+            this.update(&mut cx, |this, _| {
+                if let KernelStoreMode::Local(local) = &mut this.mode {
+                    local.kernels.insert(
+                        kernel_id,
+                        LocalKernel {
+                            request_tx,
+                            process,
+                            connection_path,
+                            _tasks: tasks,
+                        },
+                    );
+                }
+            })?;
+
+            Ok(kernel_id)
+        })
+    }
+}
+
+fn kernel_command(specification: &KernelSpecification, connection_path: &Path) -> Result<Command> {
+    let argv = &specification.kernelspec.argv;
+    anyhow::ensure!(
+        argv.len() >= 2,
+        "Invalid argv in kernelspec {}",
+        specification.name
+    );
+    anyhow::ensure!(
+        argv.iter().any(|arg| arg == "{connection_file}"),
+        "Missing 'connection_file' in argv in kernelspec {}",
+        specification.name
+    );
+
+    let mut command = Command::new(&argv[0]);
+    for arg in &argv[1..] {
+        if arg == "{connection_file}" {
+            command.arg(connection_path);
+        } else {
+            command.arg(arg);
+        }
+    }
+    if let Some(env) = &specification.kernelspec.env {
+        command.envs(env);
+    }
+    Ok(command)
+}
+
+// Find a set of open ports. There's a race between closing the listeners and the kernel binding
+// the ports, but it's inherent to the Jupyter protocol.
+async fn peek_ports(ip: IpAddr) -> Result<[u16; 5]> {
+    let mut ports = [0; 5];
+    for port in &mut ports {
+        let listener = TcpListener::bind(SocketAddr::new(ip, 0)).await?;
+        *port = listener.local_addr()?.port();
+    }
+    Ok(ports)
+}
+
+/// The kernelspecs in the Jupyter data directories of this machine, including those of the base
+/// Python and conda environments.
+async fn installed_kernel_specifications(fs: &dyn Fs) -> Vec<KernelSpecification> {
+    let mut data_dirs = dirs::data_dirs();
+    if let Ok(conda_prefix) = std::env::var("CONDA_PREFIX") {
+        data_dirs.push(PathBuf::from(conda_prefix).join("share").join("jupyter"));
+    }
+    let python_prefix = Command::new("python")
+        .args(["-c", "import sys; print(sys.prefix)"])
+        .output()
+        .await;
+    if let Ok(output) = python_prefix {
+        if output.status.success() {
+            if let Ok(prefix) = String::from_utf8(output.stdout) {
+                data_dirs.push(PathBuf::from(prefix.trim()).join("share").join("jupyter"));
+            }
+        }
+    }
+
+    let mut specifications = Vec::new();
+    for kernels_dir in data_dirs.into_iter().map(|dir| dir.join("kernels")) {
+        let Ok(mut kernel_dirs) = fs.read_dir(&kernels_dir).await else {
+            continue;
+        };
+        while let Some(Ok(kernel_dir)) = kernel_dirs.next().await {
+            let Some(name) = kernel_dir.file_name() else {
+                continue;
+            };
+            let name = name.to_string_lossy().to_string();
+            let Ok(kernel_json) = fs.load(&kernel_dir.join("kernel.json")).await else {
+                continue;
+            };
+            if let Some(kernelspec) =
+                serde_json::from_str::<JupyterKernelspec>(&kernel_json).log_err()
+            {
+                specifications.push(KernelSpecification {
+                    name,
+                    path: kernel_dir,
+                    kernelspec,
+                });
+            }
+        }
+    }
+    specifications
+}
+
+/// A kernel for a Python environment, if it has `ipykernel` installed.
+async fn python_env_kernel_specification(
+    name: String,
+    python_path: String,
+) -> Option<KernelSpecification> {
+    let ipykernel_check = Command::new(&python_path)
+        .args(["-c", "import ipykernel"])
+        .output()
+        .await
+        .ok()?;
+    if !ipykernel_check.status.success() {
+        return None;
+    }
+
+    Some(KernelSpecification {
+        name: name.clone(),
+        path: PathBuf::from(&python_path),
+        kernelspec: JupyterKernelspec {
+            argv: vec![
+                python_path,
+                "-m".to_string(),
+                "ipykernel_launcher".to_string(),
+                "-f".to_string(),
+                "{connection_file}".to_string(),
+            ],
+            display_name: name,
+            language: "python".to_string(),
+            interrupt_mode: None,
+            metadata: None,
+            env: None,
+        },
+    })
+}
@@ -0,0 +1,376 @@
+//! Notebooks are saved as JSON, where each cell's source is split into escaped strings next to
+//! its outputs and metadata. Project search looks through the cells' sources instead of the
+//! file's text, so queries match what's shown in the notebook, and the matches are mapped back to
+//! the JSON they were saved as.
+
+use std::{ops::Range, path::Path};
+
+use crate::search::SearchQuery;
+
+/// The source of a notebook cell, as found in the notebook's JSON.
+#[derive(Debug)]
+pub struct NotebookSource {
+    /// The index of the cell in the notebook.
+    pub cell_index: usize,
+    pub text: String,
+    /// The range of the JSON each byte of `text` was read from, e.g. the whole `\n` escape.
+    json_ranges: Vec<Range<usize>>,
+}
+
+impl NotebookSource {
+    /// The range of the JSON a range of the source was read from.
+    /// An empty range at the end of the source maps to the end of its last character.
+    pub fn json_range(&self, range: Range<usize>) -> Range<usize> {
+        let start = match self.json_ranges.get(range.start) {
+            Some(json_range) => json_range.start,
+            None => self.json_ranges.last().map_or(0, |last| last.end),
+        };
+        if range.is_empty() {
+            return start..start;
+        }
+        start..self.json_ranges[range.end - 1].end
+    }
+
+    /// The offset in the source read from `json_offset`, if it's inside the source.
+    pub fn source_offset(&self, json_offset: usize) -> Option<usize> {
+        let (first, last) = (self.json_ranges.first()?, self.json_ranges.last()?);
+        if json_offset < first.start || json_offset > last.end {
+            return None;
+        }
+        Some(
+            self.json_ranges
+                .partition_point(|json_range| json_range.start < json_offset),
+        )
+    }
+}
+
+pub fn is_notebook_path(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == "ipynb")
+}
+
+/// The sources of the notebook's cells, or `None` if `json` isn't a notebook.
+pub fn notebook_sources(json: &str) -> Option<Vec<NotebookSource>> {
+    let mut scanner = Scanner { json, position: 0 };
+    let mut sources = Vec::new();
+    scanner.object(|scanner, key| {
+        if key != "cells" {
+            return scanner.skip_value();
+        }
+        let mut cell_index = 0;
+        scanner.array(|scanner| {
+            let mut source = NotebookSource {
+                cell_index,
+                text: String::new(),
+                json_ranges: Vec::new(),
+            };
+            scanner.object(|scanner, key| {
+                if key != "source" {
+                    return scanner.skip_value();
+                }
+                scanner.skip_whitespace();
+                if scanner.peek()? == b'[' {
+                    scanner.array(|scanner| scanner.string(Some(&mut source)).map(|_| ()))
+                } else {
+                    scanner.string(Some(&mut source)).map(|_| ())
+                }
+            })?;
+            sources.push(source);
+            cell_index += 1;
+            Some(())
+        })
+    })?;
+    Some(sources)
+}
+
+/// The ranges of the notebook's JSON that `query` matches in its cells' sources, or `None` if
+/// `json` isn't a notebook.
+pub fn notebook_matches(query: &SearchQuery, json: &str) -> Option<Vec<Range<usize>>> {
+    let sources = notebook_sources(json)?;
+    Some(
+        sources
+            .iter()
+            // An empty source has nowhere in the JSON to put an empty match
+            .filter(|source| !source.text.is_empty())
+            .flat_map(|source| {
+                query
+                    .search_str(&source.text)
+                    .into_iter()
+                    .map(|range| source.json_range(range))
+            })
+            .collect(),
+    )
+}
+
+/// Reads through JSON, keeping track of where it is so strings can be mapped back to it.
+struct Scanner<'a> {
+    json: &'a str,
+    position: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek()? != byte {
+            return None;
+        }
+        self.position += 1;
+        Some(())
+    }
+
+    /// Reads an object, calling `field` with each key once the scanner is at its value.
+    fn object(&mut self, mut field: impl FnMut(&mut Self, String) -> Option<()>) -> Option<()> {
+        self.expect(b'{')?;
+        self.skip_whitespace();
+        if self.peek()? == b'}' {
+            self.position += 1;
+            return Some(());
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string(None)?;
+            self.expect(b':')?;
+            field(self, key)?;
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.position += 1,
+                b'}' => {
+                    self.position += 1;
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Reads an array, calling `element` once the scanner is at each element.
+    fn array(&mut self, mut element: impl FnMut(&mut Self) -> Option<()>) -> Option<()> {
+        self.expect(b'[')?;
+        self.skip_whitespace();
+        if self.peek()? == b']' {
+            self.position += 1;
+            return Some(());
+        }
+        loop {
+            element(self)?;
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.position += 1,
+                b']' => {
+                    self.position += 1;
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Reads a string, appending it to `source` if given and returning it otherwise.
+    fn string(&mut self, source: Option<&mut NotebookSource>) -> Option<String> {
+        self.expect(b'"')?;
+        let mut text = String::new();
+        let mut json_ranges = Vec::new();
+        loop {
+            let start = self.position;
+            let char = self.json[start..].chars().next()?;
+            let char = match char {
+                '"' => break,
+                '\\' => {
+                    self.position += 1;
+                    let escape = self.peek()?;
+                    self.position += 1;
+                    match escape {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => self.unicode_escape()?,
+                        _ => escape as char,
+                    }
+                }
+                char => {
+                    self.position += char.len_utf8();
+                    char
+                }
+            };
+            text.push(char);
+            json_ranges.extend(std::iter::repeat(start..self.position).take(char.len_utf8()));
+        }
+        self.position += 1;
+        match source {
+            Some(source) => {
+                source.text.push_str(&text);
+                source.json_ranges.extend(json_ranges);
+                Some(String::new())
+            }
+            None => Some(text),
+        }
+    }
+
+    /// Reads the four hex digits of a `\u` escape, and a second escape for surrogate pairs.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.code_unit()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high as u32);
+        }
+        if self.json.get(self.position..self.position + 2)? != "\\u" {
+            return None;
+        }
+        self.position += 2;
+        let low = self.code_unit()?;
+        char::decode_utf16([high, low]).next()?.ok()
+    }
+
+    fn code_unit(&mut self) -> Option<u16> {
+        let digits = self.json.get(self.position..self.position + 4)?;
+        self.position += 4;
+        u16::from_str_radix(digits, 16).ok()
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => self.string(None).map(|_| ()),
+            b'{' => self.object(|scanner, _| scanner.skip_value()),
+            b'[' => self.array(|scanner| scanner.skip_value()),
+            _ => {
+                while self
+                    .peek()
+                    .map_or(false, |byte| !matches!(byte, b',' | b'}' | b']'))
+                {
+                    self.position += 1;
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {"source": "not this"},
+   "source": ["# Caf\u00e9 \"sales\"\n", "Totals"]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": ["total = 3\n"]}],
+   "source": "total = sum(sales)\nprint(total)"
+  }
+ ],
+ "metadata": {},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"##;
+
+    #[test]
+    fn test_notebook_sources() {
+        let sources = notebook_sources(NOTEBOOK).unwrap();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].text, "# Café \"sales\"\nTotals");
+        assert_eq!(sources[1].cell_index, 1);
+        assert_eq!(sources[1].text, "total = sum(sales)\nprint(total)");
+
+        // Escaped characters map back to their whole escape
+        let range = sources[0].text.find('é').unwrap();
+        let json_range = sources[0].json_range(range..range + 'é'.len_utf8());
+        assert_eq!(&NOTEBOOK[json_range.clone()], "\\u00e9");
+        assert_eq!(
+            sources[0].source_offset(json_range.start),
+            Some(range),
+            "offsets map both ways"
+        );
+
+        assert!(notebook_sources("{\"cells\": [").is_none());
+    }
+
+    #[test]
+    fn test_notebook_matches() {
+        let query = SearchQuery::text(
+            "total",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let matches = notebook_matches(&query, NOTEBOOK).unwrap();
+        // The output's text isn't searched
+        assert_eq!(matches.len(), 2);
+        for range in matches {
+            assert_eq!(&NOTEBOOK[range], "total");
+        }
+
+        let query = SearchQuery::text(
+            "Café \"sales\"\nTotals",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let matches = notebook_matches(&query, NOTEBOOK).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            &NOTEBOOK[matches[0].clone()],
+            "Caf\\u00e9 \\\"sales\\\"\\n\", \"Totals"
+        );
+    }
+
+    #[test]
+    fn test_empty_notebook_matches() {
+        let query = SearchQuery::regex(
+            "$",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let matches = notebook_matches(&query, NOTEBOOK).unwrap();
+        assert!(matches.iter().all(|range| range.is_empty()));
+        // Matches at the end of a source are just before its closing quote
+        assert!(matches
+            .iter()
+            .any(|range| NOTEBOOK[..range.start].ends_with("\"Totals")));
+        assert!(matches
+            .iter()
+            .any(|range| NOTEBOOK[..range.start].ends_with("print(total)")));
+
+        let query = SearchQuery::regex(
+            "^",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let notebook = r#"{"cells": [{"cell_type": "code", "source": ""}, {"cell_type": "code", "source": []}]}"#;
+        assert_eq!(notebook_matches(&query, notebook), Some(Vec::new()));
+    }
+}
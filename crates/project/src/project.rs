@@ -3,9 +3,11 @@ mod color_extractor;
 pub mod connection_manager;
 pub mod debounced_delay;
 pub mod image_store;
+pub mod kernel_store;
 pub mod lsp_command;
 pub mod lsp_ext_command;
 pub mod lsp_store;
+pub mod notebook_search;
 pub mod prettier_store;
 pub mod project_settings;
 pub mod search;
@@ -33,7 +35,7 @@ use debounced_delay::DebouncedDelay;
 pub use environment::ProjectEnvironment;
 use futures::{
     channel::mpsc::{self, UnboundedReceiver},
-    future::try_join_all,
+    future::{try_join_all, Shared},
     StreamExt,
 };
 pub use image_store::{ImageItem, ImageStore};
@@ -57,6 +59,7 @@ use lsp::{
 };
 use lsp_command::*;
 use node_runtime::NodeRuntime;
+use notebook_search::{is_notebook_path, notebook_matches};
 use parking_lot::{Mutex, RwLock};
 pub use prettier_store::PrettierStore;
 use project_settings::{ProjectSettings, SettingsObserver, SettingsObserverEvent};
@@ -101,6 +104,7 @@ pub use worktree::{
 };
 
 pub use buffer_store::ProjectTransaction;
+pub use kernel_store::KernelStore;
 pub use lsp_store::{
     DiagnosticSummary, LanguageServerLogType, LanguageServerProgress, LanguageServerPromptRequest,
     LanguageServerStatus, LanguageServerToQuery, LspStore, LspStoreEvent,
@@ -165,6 +169,7 @@ pub struct Project {
     environment: Model<ProjectEnvironment>,
     settings_observer: Model<SettingsObserver>,
     toolchain_store: Option<Model<ToolchainStore>>,
+    kernel_store: Option<Model<KernelStore>>,
 }
 
 #[derive(Default)]
@@ -706,6 +711,7 @@ impl Project {
                 search_excluded_history: Self::new_search_history(),
 
                 toolchain_store: Some(toolchain_store),
+                kernel_store: None,
             }
         })
     }
@@ -754,6 +760,8 @@ impl Project {
             let toolchain_store = cx.new_model(|cx| {
                 ToolchainStore::remote(SSH_PROJECT_ID, ssh.read(cx).proto_client(), cx)
             });
+            let kernel_store =
+                cx.new_model(|_| KernelStore::remote(SSH_PROJECT_ID, ssh_proto.clone()));
             let task_store = cx.new_model(|cx| {
                 TaskStore::remote(
                     fs.clone(),
@@ -839,6 +847,7 @@ impl Project {
                 search_excluded_history: Self::new_search_history(),
 
                 toolchain_store: Some(toolchain_store),
+                kernel_store: Some(kernel_store.clone()),
             };
 
             let ssh = ssh.read(cx);
@@ -847,6 +856,7 @@ impl Project {
             ssh.subscribe_to_entity(SSH_PROJECT_ID, &this.worktree_store);
             ssh.subscribe_to_entity(SSH_PROJECT_ID, &this.lsp_store);
             ssh.subscribe_to_entity(SSH_PROJECT_ID, &this.settings_observer);
+            ssh.subscribe_to_entity(SSH_PROJECT_ID, &kernel_store);
 
             ssh_proto.add_model_message_handler(Self::handle_create_buffer_for_peer);
             ssh_proto.add_model_message_handler(Self::handle_update_worktree);
@@ -860,6 +870,7 @@ impl Project {
             SettingsObserver::init(&ssh_proto);
             TaskStore::init(Some(&ssh_proto));
             ToolchainStore::init(&ssh_proto);
+            KernelStore::init(&ssh_proto);
 
             this
         })
@@ -1042,6 +1053,7 @@ impl Project {
                 environment: ProjectEnvironment::new(&worktree_store, None, cx),
                 remotely_created_models: Arc::new(Mutex::new(RemotelyCreatedModels::default())),
                 toolchain_store: None,
+                kernel_store: None,
             };
             this.set_role(role, cx);
             for worktree in worktrees {
@@ -1248,13 +1260,36 @@ impl Project {
     }
 
     pub fn opened_buffers(&self, cx: &AppContext) -> Vec<Model<Buffer>> {
-        self.buffer_store.read(cx).buffers().collect()
+        self.buffer_store.read(cx).visible_buffers().collect()
+    }
+
+    /// Keeps an open buffer out of project search and the lists of open buffers, while language
+    /// servers still see it.
+    pub fn hide_buffer(&mut self, buffer: &Model<Buffer>, cx: &mut ModelContext<Self>) {
+        let buffer_id = buffer.read(cx).remote_id();
+        self.buffer_store
+            .update(cx, |buffer_store, _| buffer_store.hide_buffer(buffer_id));
     }
 
     pub fn cli_environment(&self, cx: &AppContext) -> Option<HashMap<String, String>> {
         self.environment.read(cx).get_cli_environment()
     }
 
+    /// The environment language servers and tasks in the worktree are started with: the one Zed
+    /// was opened with from the CLI, or else that of a shell in the worktree's directory.
+    pub fn worktree_environment(
+        &self,
+        worktree_id: Option<WorktreeId>,
+        cx: &mut AppContext,
+    ) -> Shared<Task<Option<HashMap<String, String>>>> {
+        let worktree_abs_path = worktree_id
+            .and_then(|worktree_id| self.worktree_for_id(worktree_id, cx))
+            .map(|worktree| worktree.read(cx).abs_path());
+        self.environment.update(cx, |environment, cx| {
+            environment.get_environment(worktree_id, worktree_abs_path, cx)
+        })
+    }
+
     pub fn shell_environment_errors<'a>(
         &'a self,
         cx: &'a AppContext,
@@ -1340,6 +1375,12 @@ impl Project {
         &self.task_store
     }
 
+    /// Starts kernels on the host of an SSH project. Kernels of other projects are started by the
+    /// REPL itself.
+    pub fn kernel_store(&self) -> Option<&Model<KernelStore>> {
+        self.kernel_store.as_ref()
+    }
+
     pub fn snippets(&self) -> &Model<SnippetProvider> {
         &self.snippets
     }
@@ -1674,7 +1715,8 @@ impl Project {
             }
 
             *capability = new_capability;
-            for buffer in self.opened_buffers(cx) {
+            let buffers = self.buffer_store.read(cx).buffers().collect::<Vec<_>>();
+            for buffer in buffers {
                 buffer.update(cx, |buffer, cx| buffer.set_capability(new_capability, cx));
             }
         }
@@ -2973,11 +3015,22 @@ impl Project {
                 for buffer in matching_buffer_chunk {
                     let buffer = buffer.clone();
                     let query = query.clone();
-                    let snapshot = buffer.read_with(&cx, |buffer, _| buffer.snapshot())?;
+                    let (snapshot, is_notebook) = buffer.read_with(&cx, |buffer, _| {
+                        let is_notebook = buffer
+                            .file()
+                            .map_or(false, |file| is_notebook_path(file.path()));
+                        (buffer.snapshot(), is_notebook)
+                    })?;
                     chunk_results.push(cx.background_executor().spawn(async move {
-                        let ranges = query
-                            .search(&snapshot, None)
-                            .await
+                        // Notebooks are searched by their cells' sources rather than their JSON
+                        let notebook_ranges = is_notebook
+                            .then(|| notebook_matches(&query, &snapshot.text()))
+                            .flatten();
+                        let ranges = match notebook_ranges {
+                            Some(ranges) => ranges,
+                            None => query.search(&snapshot, None).await,
+                        };
+                        let ranges = ranges
                             .iter()
                             .map(|range| {
                                 snapshot.anchor_before(range.start)
@@ -4060,14 +4113,17 @@ impl Project {
         &'a self,
         cx: &'a AppContext,
     ) -> impl Iterator<Item = ProjectPath> + 'a {
-        self.buffer_store.read(cx).buffers().filter_map(|buf| {
-            let buf = buf.read(cx);
-            if buf.is_dirty() {
-                buf.project_path(cx)
-            } else {
-                None
-            }
-        })
+        self.buffer_store
+            .read(cx)
+            .visible_buffers()
+            .filter_map(|buf| {
+                let buf = buf.read(cx);
+                if buf.is_dirty() {
+                    buf.project_path(cx)
+                } else {
+                    None
+                }
+            })
     }
 
     fn set_worktrees_from_proto(
@@ -4553,6 +4553,57 @@ async fn test_search_multiple_worktrees_with_inclusions(cx: &mut gpui::TestAppCo
     );
 }
 
+#[gpui::test]
+async fn test_search_skips_hidden_buffers(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+
+    // A buffer that's never saved, like a notebook's cell document
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/.hidden.rs", cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| {
+        buffer.set_text("const ONE: usize = 2;", cx)
+    });
+    project.update(cx, |project, cx| project.hide_buffer(&buffer, cx));
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "ONE",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([("dir/one.rs".to_string(), vec![6..9])])
+    );
+    project.update(cx, |project, cx| {
+        assert!(!project.opened_buffers(cx).contains(&buffer));
+        assert_eq!(project.dirty_buffers(cx).count(), 0);
+    });
+}
+
 #[gpui::test]
 async fn test_search_in_gitignored_dirs(cx: &mut gpui::TestAppContext) {
     init_test(cx);
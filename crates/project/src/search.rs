@@ -364,6 +364,54 @@ impl SearchQuery {
         matches
     }
 
+    /// The ranges of `text` the query matches, for text that isn't in a buffer, like the
+    /// source of a notebook cell. Whole words are told apart by alphanumeric characters and `_`.
+    pub fn search_str(&self, text: &str) -> Vec<Range<usize>> {
+        if self.as_str().is_empty() {
+            return Vec::new();
+        }
+
+        match self {
+            Self::Text {
+                search, whole_word, ..
+            } => {
+                let is_word = |char: char| char.is_alphanumeric() || char == '_';
+                search
+                    .find_iter(text)
+                    .filter(|mat| {
+                        if !*whole_word {
+                            return true;
+                        }
+                        let before = text[..mat.start()].chars().next_back();
+                        let after = text[mat.end()..].chars().next();
+                        !before.map_or(false, is_word) && !after.map_or(false, is_word)
+                    })
+                    .map(|mat| mat.start()..mat.end())
+                    .collect()
+            }
+            Self::Regex {
+                regex, multiline, ..
+            } => {
+                if *multiline {
+                    return regex
+                        .find_iter(text)
+                        .flatten()
+                        .map(|mat| mat.start()..mat.end())
+                        .collect();
+                }
+                let mut matches = Vec::new();
+                let mut line_offset = 0;
+                for line in text.split('\n') {
+                    for mat in regex.find_iter(line).flatten() {
+                        matches.push(line_offset + mat.start()..line_offset + mat.end());
+                    }
+                    line_offset += line.len() + 1;
+                }
+                matches
+            }
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.as_str().is_empty()
     }
@@ -26,7 +26,11 @@ use text::ReplicaId;
 use util::ResultExt;
 use worktree::{Entry, ProjectEntryId, Worktree, WorktreeId, WorktreeSettings};
 
-use crate::{search::SearchQuery, ProjectPath};
+use crate::{
+    notebook_search::{is_notebook_path, notebook_matches},
+    search::SearchQuery,
+    ProjectPath,
+};
 
 struct MatchingEntry {
     worktree_path: Arc<Path>,
@@ -973,6 +977,20 @@ impl WorktreeStore {
     ) -> Result<()> {
         while let Some(mut entry) = input.next().await {
             let abs_path = entry.worktree_path.join(&entry.path.path);
+            // Notebooks match by their cells' sources rather than their JSON
+            if is_notebook_path(&entry.path.path) {
+                let Some(json) = fs.load(&abs_path).await.log_err() else {
+                    continue;
+                };
+                let matches = match notebook_matches(query, &json) {
+                    Some(matches) => !matches.is_empty(),
+                    None => query.detect(json.as_bytes()).unwrap_or(false),
+                };
+                if matches {
+                    entry.respond.send(entry.path).await?
+                }
+                continue;
+            }
             let Some(file) = fs.open_sync(&abs_path).await.log_err() else {
                 continue;
             };
@@ -368,6 +368,15 @@ messages!(
     (GetPanicFiles, Background),
     (GetPanicFilesResponse, Background),
     (CancelLanguageServerWork, Foreground),
+    (ListKernelSpecifications, Background),
+    (ListKernelSpecificationsResponse, Background),
+    (StartKernel, Foreground),
+    (StartKernelResponse, Foreground),
+    (SendKernelMessage, Foreground),
+    (KernelMessage, Foreground),
+    (ShutdownKernel, Foreground),
+    (KernelStopped, Foreground),
+    (RunNotebookCell, Foreground),
 );
 
 request_messages!(
@@ -491,6 +500,10 @@ request_messages!(
     (GetPathMetadata, GetPathMetadataResponse),
     (GetPanicFiles, GetPanicFilesResponse),
     (CancelLanguageServerWork, Ack),
+    (ListKernelSpecifications, ListKernelSpecificationsResponse),
+    (StartKernel, StartKernelResponse),
+    (ShutdownKernel, Ack),
+    (RunNotebookCell, Ack),
 );
 
 entity_messages!(
@@ -575,6 +588,13 @@ entity_messages!(
     ActiveToolchain,
     GetPathMetadata,
     CancelLanguageServerWork,
+    ListKernelSpecifications,
+    StartKernel,
+    SendKernelMessage,
+    KernelMessage,
+    ShutdownKernel,
+    KernelStopped,
+    RunNotebookCell,
 );
 
 entity_messages!(
@@ -146,6 +146,12 @@ impl QuickActionBar {
                             }
                         },
                     )
+                    .custom_entry(move |_cx| Label::new("Run File").into_any_element(), {
+                        let editor = editor.clone();
+                        move |cx| {
+                            repl::run_file(editor.clone(), cx).log_err();
+                        }
+                    })
                     .custom_entry(
                         move |_cx| {
                             Label::new("Interrupt")
@@ -392,6 +398,15 @@ fn session_state(session: View<Session>, cx: &WindowContext) -> ReplMenuState {
         }
     };
 
+    if let Some(notebook_name) = session.attached_notebook_name(cx) {
+        return ReplMenuState {
+            tooltip: format!("Run code on the kernel of {}", notebook_name).into(),
+            indicator: Some(Indicator::dot().color(Color::Success)),
+            status: KernelStatus::Idle,
+            ..fill_fields()
+        };
+    }
+
     match &session.kernel {
         Kernel::Restarting => ReplMenuState {
             tooltip: format!("Restarting {}", kernel_name).into(),
@@ -427,7 +442,7 @@ fn session_state(session: View<Session>, cx: &WindowContext) -> ReplMenuState {
             status: session.kernel.status(),
             ..fill_fields()
         },
-        Kernel::ErroredLaunch(e) => ReplMenuState {
+        Kernel::ErroredLaunch(e) | Kernel::Died(e) => ReplMenuState {
             tooltip: format!("Error with kernel {}: {}", kernel_name, e).into(),
             popover_disabled: false,
             indicator: Some(Indicator::dot().color(Color::Error)),
@@ -10,7 +10,7 @@ use project::{
     search::SearchQuery,
     task_store::TaskStore,
     worktree_store::WorktreeStore,
-    LspStore, LspStoreEvent, PrettierStore, ProjectPath, ToolchainStore, WorktreeId,
+    KernelStore, LspStore, LspStoreEvent, PrettierStore, ProjectPath, ToolchainStore, WorktreeId,
 };
 use remote::ssh_session::ChannelClient;
 use rpc::{
@@ -34,6 +34,7 @@ pub struct HeadlessProject {
     pub buffer_store: Model<BufferStore>,
     pub lsp_store: Model<LspStore>,
     pub task_store: Model<TaskStore>,
+    pub kernel_store: Model<KernelStore>,
     pub settings_observer: Model<SettingsObserver>,
     pub next_entry_id: Arc<AtomicUsize>,
     pub languages: Arc<LanguageRegistry>,
@@ -95,6 +96,12 @@ impl HeadlessProject {
             )
         });
 
+        let kernel_store = cx.new_model(|_| {
+            let mut kernel_store = KernelStore::local(fs.clone(), toolchain_store.clone());
+            kernel_store.shared(SSH_PROJECT_ID, session.clone().into());
+            kernel_store
+        });
+
         let task_store = cx.new_model(|cx| {
             let mut task_store = TaskStore::local(
                 fs.clone(),
@@ -155,6 +162,7 @@ impl HeadlessProject {
         session.subscribe_to_entity(SSH_PROJECT_ID, &lsp_store);
         session.subscribe_to_entity(SSH_PROJECT_ID, &task_store);
         session.subscribe_to_entity(SSH_PROJECT_ID, &toolchain_store);
+        session.subscribe_to_entity(SSH_PROJECT_ID, &kernel_store);
         session.subscribe_to_entity(SSH_PROJECT_ID, &settings_observer);
 
         client.add_request_handler(cx.weak_model(), Self::handle_list_remote_directory);
@@ -179,6 +187,7 @@ impl HeadlessProject {
         LspStore::init(&client);
         TaskStore::init(Some(&client));
         ToolchainStore::init(&client);
+        KernelStore::init(&client);
 
         HeadlessProject {
             session: client,
@@ -188,6 +197,7 @@ impl HeadlessProject {
             buffer_store,
             lsp_store,
             task_store,
+            kernel_store,
             next_entry_id: Default::default(),
             languages,
         }
@@ -0,0 +1,163 @@
+//! # Cell Markers
+//!
+//! Scripts split into cells with Jupytext markers, like `# %%` in Python, get a separator above
+//! each marker with a button to run the cell below it on the editor's kernel session. This is the
+//! same as `repl: run cell` with the cursor in the cell.
+
+use std::sync::Arc;
+
+use collections::HashSet;
+use editor::{
+    display_map::{BlockContext, BlockPlacement, BlockProperties, BlockStyle, CustomBlockId},
+    Anchor, Editor, MultiBuffer, ToPoint as _,
+};
+use gpui::{Model, Subscription, View, WeakView};
+use language::Point;
+use ui::{prelude::*, IconButtonShape, Tooltip};
+use util::ResultExt as _;
+
+use crate::{JupyterSettings, RunCell};
+
+/// Shows separators above the cell markers of an editor, for as long as the editor is open.
+pub(crate) fn register(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let editor_handle = cx.view().downgrade();
+    let buffer = editor.buffer().clone();
+    let markers = cx.new_view(|cx| CellMarkers::new(editor_handle, buffer, cx));
+    editor.register_addon(CellMarkersAddon { _markers: markers });
+}
+
+struct CellMarkersAddon {
+    _markers: View<CellMarkers>,
+}
+
+impl editor::Addon for CellMarkersAddon {
+    fn to_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct CellMarkers {
+    editor: WeakView<Editor>,
+    /// The start of each marker line, and the separator shown above it.
+    markers: Vec<(Anchor, CustomBlockId)>,
+    _buffer_subscription: Subscription,
+}
+
+impl CellMarkers {
+    fn new(
+        editor: WeakView<Editor>,
+        buffer: Model<MultiBuffer>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscription = cx.subscribe(&buffer, |this, _, event, cx| match event {
+            multi_buffer::Event::Edited { .. } | multi_buffer::Event::LanguageChanged(_) => {
+                this.refresh(cx)
+            }
+            _ => {}
+        });
+        // The editor is still being set up, so it can't take blocks yet
+        cx.defer(|this, cx| this.refresh(cx));
+
+        Self {
+            editor,
+            markers: Vec::new(),
+            _buffer_subscription: subscription,
+        }
+    }
+
+    /// Replaces the separators when markers were added or removed.
+    fn refresh(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(editor) = self.editor.upgrade() else {
+            return;
+        };
+        let multibuffer = editor.read(cx).buffer().clone();
+        let Some(buffer) = multibuffer.read(cx).as_singleton() else {
+            return;
+        };
+
+        let rows = if JupyterSettings::enabled(cx) {
+            crate::repl_editor::jupytext_marker_rows(&buffer.read(cx).snapshot())
+        } else {
+            Vec::new()
+        };
+        let snapshot = multibuffer.read(cx).snapshot(cx);
+        let shown_rows = self
+            .markers
+            .iter()
+            .map(|(anchor, _)| anchor.to_point(&snapshot).row)
+            .collect::<Vec<_>>();
+        if rows == shown_rows {
+            return;
+        }
+
+        let anchors = rows
+            .into_iter()
+            .map(|row| snapshot.anchor_before(Point::new(row, 0)))
+            .collect::<Vec<_>>();
+        let blocks = anchors
+            .iter()
+            .map(|anchor| BlockProperties {
+                placement: BlockPlacement::Above(*anchor),
+                height: 1,
+                style: BlockStyle::Sticky,
+                render: render_separator(self.editor.clone(), *anchor),
+                priority: 0,
+            })
+            .collect::<Vec<_>>();
+        let removed_blocks = self
+            .markers
+            .drain(..)
+            .map(|(_, block_id)| block_id)
+            .collect::<HashSet<_>>();
+
+        let block_ids = editor.update(cx, |editor, cx| {
+            editor.remove_blocks(removed_blocks, None, cx);
+            editor.insert_blocks(blocks, None, cx)
+        });
+        self.markers = anchors.into_iter().zip(block_ids).collect();
+    }
+}
+
+fn render_separator(editor: WeakView<Editor>, marker: Anchor) -> editor::display_map::RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let editor = editor.clone();
+        let gutter = cx.gutter_dimensions;
+
+        h_flex()
+            .id(cx.block_id)
+            .block_mouse_down()
+            .w_full()
+            .h(cx.line_height)
+            .child(
+                h_flex()
+                    .flex_none()
+                    .w(gutter.full_width())
+                    .justify_end()
+                    .pr(gutter.margin)
+                    .child(
+                        IconButton::new("run-cell", IconName::Play)
+                            .icon_size(IconSize::XSmall)
+                            .icon_color(Color::Muted)
+                            .shape(IconButtonShape::Square)
+                            .tooltip(|cx| Tooltip::for_action("Run Cell", &RunCell, cx))
+                            .on_click(move |_, cx| {
+                                crate::run_cell_at(editor.clone(), marker, cx).log_err();
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .h_px()
+                    .mr(gutter.width)
+                    .bg(cx.theme().colors().border_variant),
+            )
+            .into_any_element()
+    })
+}
+
+impl Render for CellMarkers {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        gpui::Empty
+    }
+}
@@ -1,7 +1,23 @@
 use gpui::AnyElement;
+use runtimelib::ExecutionState;
 use ui::{prelude::*, Indicator, ListItem};
 
-use crate::KernelSpecification;
+use crate::{Kernel, KernelSpecification};
+
+/// The color of the dot showing a kernel's status.
+pub fn kernel_status_color(kernel: &Kernel) -> Color {
+    match kernel {
+        Kernel::RunningKernel(kernel) => match kernel.execution_state() {
+            ExecutionState::Idle => Color::Success,
+            ExecutionState::Busy => Color::Modified,
+        },
+        Kernel::StartingKernel(_) => Color::Modified,
+        Kernel::ErroredLaunch(_) | Kernel::Died(_) => Color::Error,
+        Kernel::ShuttingDown => Color::Modified,
+        Kernel::Shutdown => Color::Disabled,
+        Kernel::Restarting => Color::Modified,
+    }
+}
 
 #[derive(IntoElement)]
 pub struct KernelListItem {
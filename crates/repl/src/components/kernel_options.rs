@@ -56,6 +56,18 @@ impl<T: PopoverTrigger> KernelSelector<T> {
     }
 }
 
+impl KernelPickerDelegate {
+    /// A picker of `kernels` on its own, for when there's no trigger to show it from.
+    pub fn new(kernels: Vec<KernelSpecification>, on_select: OnSelect) -> Self {
+        Self {
+            selected_kernelspec: kernels.first().cloned(),
+            all_kernels: kernels.clone(),
+            filtered_kernels: kernels,
+            on_select,
+        }
+    }
+}
+
 impl PickerDelegate for KernelPickerDelegate {
     type ListItem = ListItem;
 
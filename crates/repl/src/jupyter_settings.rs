@@ -9,6 +9,11 @@ use settings::{Settings, SettingsSources};
 #[derive(Debug, Default)]
 pub struct JupyterSettings {
     pub kernel_selections: HashMap<String, String>,
+    pub nbconvert_command: String,
+    pub nbconvert_exports: Vec<NbconvertExport>,
+    pub kernel_env: HashMap<String, String>,
+    pub load_dotenv: bool,
+    pub theme_plots: bool,
 }
 
 impl JupyterSettings {
@@ -18,6 +23,60 @@ impl JupyterSettings {
         // This allows the editor to set up context for key bindings/actions.
         EditorSettings::jupyter_enabled(cx)
     }
+
+    /// The name of the kernel selected for `language`, if one is. Languages are matched
+    /// case-insensitively.
+    pub fn kernel_selection(&self, language: &str) -> Option<&str> {
+        self.kernel_selections
+            .iter()
+            .find(|(kernel_language, _)| kernel_language.eq_ignore_ascii_case(language))
+            .map(|(_, kernel)| kernel.as_str())
+    }
+}
+
+/// A format notebooks can be exported to with `jupyter nbconvert`.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct NbconvertExport {
+    /// The format passed to `--to`, like `html` or `latex`.
+    pub format: String,
+    /// The template passed to `--template`, like `lab` or `classic`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Any other arguments to pass to nbconvert.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl NbconvertExport {
+    /// The formats listed when the settings don't name any.
+    fn defaults() -> Vec<Self> {
+        ["html", "markdown", "latex", "script"]
+            .into_iter()
+            .map(|format| Self {
+                format: format.to_string(),
+                template: None,
+                args: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// The export `notebook: export to pdf` uses when the settings don't list a PDF format. It
+    /// prints the notebook's HTML export with Chromium, so it needs no LaTeX installation.
+    pub(crate) fn pdf() -> Self {
+        Self {
+            format: "webpdf".to_string(),
+            template: None,
+            args: Vec::new(),
+        }
+    }
+
+    /// How the export is listed, e.g. `html (classic)`.
+    pub fn label(&self) -> String {
+        match &self.template {
+            Some(template) => format!("{} ({template})", self.format),
+            None => self.format.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
@@ -26,12 +85,41 @@ pub struct JupyterSettingsContent {
     ///
     /// Default: `{}`
     pub kernel_selections: Option<HashMap<String, String>>,
+    /// The command nbconvert is run with, as `<command> nbconvert`.
+    ///
+    /// Default: `"jupyter"`
+    pub nbconvert_command: Option<String>,
+    /// The formats notebooks can be exported to with nbconvert, in the order they're
+    /// listed.
+    ///
+    /// Default: `[{"format": "html"}, {"format": "markdown"}, {"format": "latex"}, {"format": "script"}]`
+    pub nbconvert_exports: Option<Vec<NbconvertExport>>,
+    /// Environment variables to start kernels with. They take precedence over the
+    /// project's environment, its `.env` file and the kernelspec's `env`.
+    ///
+    /// Default: `{}`
+    pub kernel_env: Option<HashMap<String, String>>,
+    /// Whether kernels are started with the variables of the `.env` file at the
+    /// root of the project.
+    ///
+    /// Default: `true`
+    pub load_dotenv: Option<bool>,
+    /// Whether Python kernels style matplotlib and plotly plots with the colors
+    /// and font of the current theme when they start, and when the theme changes.
+    ///
+    /// Default: `false`
+    pub theme_plots: Option<bool>,
 }
 
 impl Default for JupyterSettingsContent {
     fn default() -> Self {
         JupyterSettingsContent {
             kernel_selections: Some(HashMap::new()),
+            nbconvert_command: Some("jupyter".to_string()),
+            nbconvert_exports: Some(NbconvertExport::defaults()),
+            kernel_env: Some(HashMap::new()),
+            load_dotenv: Some(true),
+            theme_plots: Some(false),
         }
     }
 }
@@ -48,7 +136,12 @@ impl Settings for JupyterSettings {
     where
         Self: Sized,
     {
-        let mut settings = JupyterSettings::default();
+        let mut settings = JupyterSettings {
+            nbconvert_command: "jupyter".to_string(),
+            nbconvert_exports: NbconvertExport::defaults(),
+            load_dotenv: true,
+            ..Default::default()
+        };
 
         for value in sources.defaults_and_customizations() {
             if let Some(source) = &value.kernel_selections {
@@ -56,6 +149,28 @@ impl Settings for JupyterSettings {
                     settings.kernel_selections.insert(k.clone(), v.clone());
                 }
             }
+
+            if let Some(source) = &value.nbconvert_command {
+                settings.nbconvert_command = source.clone();
+            }
+
+            if let Some(source) = &value.nbconvert_exports {
+                settings.nbconvert_exports = source.clone();
+            }
+
+            if let Some(source) = &value.kernel_env {
+                for (k, v) in source {
+                    settings.kernel_env.insert(k.clone(), v.clone());
+                }
+            }
+
+            if let Some(source) = value.load_dotenv {
+                settings.load_dotenv = source;
+            }
+
+            if let Some(source) = value.theme_plots {
+                settings.theme_plots = source;
+            }
         }
 
         Ok(settings)
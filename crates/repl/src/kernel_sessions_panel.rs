@@ -0,0 +1,529 @@
+//! A panel listing the kernels running in a workspace, both the ones notebooks started and the
+//! ones started to run code from editors, with controls for each. Kernels other Jupyter clients
+//! started are listed below them, so editors can connect to them.
+
+use std::path::PathBuf;
+
+use collections::HashMap;
+use editor::Editor;
+use gpui::{
+    actions, Action, AnyElement, AppContext, EntityId, EventEmitter, FocusHandle, FocusableView,
+    Pixels, Subscription, View, WeakView,
+};
+use ui::{prelude::*, Tooltip};
+use util::ResultExt as _;
+use workspace::dock::{DockPosition, Panel, PanelEvent};
+use workspace::{ItemHandle, Workspace};
+
+use crate::components::{kernel_status_color, KernelListItem};
+use crate::kernels::ExternalKernelSpecification;
+use crate::notebook::NotebookEditor;
+use crate::repl_store::ReplStore;
+use crate::{Kernel, KernelSpecification, Session};
+
+actions!(repl, [ToggleKernelSessions]);
+
+const DEFAULT_PANEL_SIZE: f32 = 360.;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(KernelSessionsPanel::toggle);
+    })
+    .detach();
+}
+
+/// What a kernel in the panel was started for.
+#[derive(Clone)]
+enum KernelOwner {
+    Notebook(View<NotebookEditor>),
+    Editor {
+        editor: View<Editor>,
+        session: View<Session>,
+    },
+}
+
+impl KernelOwner {
+    fn entity_id(&self) -> EntityId {
+        match self {
+            KernelOwner::Notebook(notebook) => notebook.entity_id(),
+            KernelOwner::Editor { session, .. } => session.entity_id(),
+        }
+    }
+
+    fn item(&self) -> Box<dyn ItemHandle> {
+        match self {
+            KernelOwner::Notebook(notebook) => Box::new(notebook.clone()),
+            KernelOwner::Editor { editor, .. } => Box::new(editor.clone()),
+        }
+    }
+
+    fn title(&self, cx: &AppContext) -> String {
+        match self {
+            KernelOwner::Notebook(notebook) => notebook.read(cx).file_name(cx),
+            KernelOwner::Editor { editor, .. } => {
+                editor.read(cx).buffer().read(cx).title(cx).to_string()
+            }
+        }
+    }
+
+    fn kernel<'a>(&self, cx: &'a AppContext) -> &'a Kernel {
+        match self {
+            KernelOwner::Notebook(notebook) => notebook.read(cx).kernel(),
+            KernelOwner::Editor { session, .. } => &session.read(cx).kernel,
+        }
+    }
+
+    fn kernel_specification(&self, cx: &AppContext) -> Option<KernelSpecification> {
+        match self {
+            KernelOwner::Notebook(notebook) => notebook.read(cx).kernel_specification(cx),
+            KernelOwner::Editor { session, .. } => {
+                Some(session.read(cx).kernel_specification.clone())
+            }
+        }
+    }
+
+    fn interrupt(&self, cx: &mut WindowContext) {
+        match self {
+            KernelOwner::Notebook(notebook) => {
+                notebook.update(cx, |notebook, cx| notebook.interrupt_kernel(cx))
+            }
+            KernelOwner::Editor { session, .. } => {
+                session.update(cx, |session, cx| session.interrupt(cx))
+            }
+        }
+    }
+
+    fn restart(&self, cx: &mut WindowContext) {
+        match self {
+            KernelOwner::Notebook(notebook) => {
+                notebook.update(cx, |notebook, cx| notebook.restart_kernel(cx))
+            }
+            KernelOwner::Editor { session, .. } => {
+                session.update(cx, |session, cx| session.restart(cx))
+            }
+        }
+    }
+
+    fn shutdown(&self, cx: &mut WindowContext) {
+        match self {
+            KernelOwner::Notebook(notebook) => {
+                notebook.update(cx, |notebook, cx| notebook.shutdown_kernel(cx))
+            }
+            KernelOwner::Editor { session, .. } => {
+                session.update(cx, |session, cx| session.shutdown(cx))
+            }
+        }
+    }
+}
+
+pub struct KernelSessionsPanel {
+    workspace: WeakView<Workspace>,
+    focus_handle: FocusHandle,
+    position: DockPosition,
+    size: Option<Pixels>,
+    /// Re-renders the panel when one of the workspace's notebooks or sessions changes.
+    owner_subscriptions: HashMap<EntityId, Subscription>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl KernelSessionsPanel {
+    fn new(workspace: View<Workspace>, cx: &mut ViewContext<Self>) -> Self {
+        let store = ReplStore::global(cx);
+        let subscriptions = vec![
+            cx.observe(&store, |this, _, cx| this.refresh(cx)),
+            cx.subscribe(&workspace, |this, _, event, cx| match event {
+                workspace::Event::ItemAdded { .. }
+                | workspace::Event::ItemRemoved
+                | workspace::Event::PaneRemoved => this.refresh(cx),
+                // External kernels can only be connected to from an active editor
+                workspace::Event::ActiveItemChanged => cx.notify(),
+                _ => {}
+            }),
+        ];
+
+        Self {
+            workspace: workspace.downgrade(),
+            focus_handle: cx.focus_handle(),
+            position: DockPosition::Right,
+            size: None,
+            owner_subscriptions: HashMap::default(),
+            _subscriptions: subscriptions,
+        }
+    }
+
+    /// Opens the panel, adding it to the workspace the first time it's used.
+    fn toggle(
+        workspace: &mut Workspace,
+        _: &ToggleKernelSessions,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        if workspace.panel::<KernelSessionsPanel>(cx).is_none() {
+            let workspace_view = cx.view().clone();
+            let panel = cx.new_view(|cx| KernelSessionsPanel::new(workspace_view, cx));
+            workspace.add_panel(panel, cx);
+        }
+        workspace.toggle_panel_focus::<KernelSessionsPanel>(cx);
+    }
+
+    /// The notebooks of the workspace and its editors with a session, in the order they're listed.
+    fn owners(&self, cx: &AppContext) -> Vec<KernelOwner> {
+        let Some(workspace) = self.workspace.upgrade() else {
+            return Vec::new();
+        };
+
+        let notebooks = workspace
+            .read(cx)
+            .items_of_type::<NotebookEditor>(cx)
+            .map(KernelOwner::Notebook);
+
+        let workspace_id = workspace.entity_id();
+        let sessions = ReplStore::global(cx)
+            .read(cx)
+            .sessions()
+            .filter_map(|session| {
+                let editor = session.read(cx).editor().upgrade()?;
+                let in_workspace = editor
+                    .read(cx)
+                    .workspace()
+                    .map_or(false, |workspace| workspace.entity_id() == workspace_id);
+                in_workspace.then(|| KernelOwner::Editor {
+                    editor,
+                    session: session.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        notebooks.chain(sessions).collect()
+    }
+
+    /// Watches the workspace's notebooks and sessions, so the panel shows their kernels starting
+    /// and stopping, and re-renders.
+    fn refresh(&mut self, cx: &mut ViewContext<Self>) {
+        let owners = self.owners(cx);
+        let mut subscriptions = HashMap::default();
+        for owner in owners {
+            let entity_id = owner.entity_id();
+            let subscription = self
+                .owner_subscriptions
+                .remove(&entity_id)
+                .unwrap_or_else(|| match &owner {
+                    KernelOwner::Notebook(notebook) => cx.observe(notebook, |_, _, cx| cx.notify()),
+                    KernelOwner::Editor { session, .. } => {
+                        cx.observe(session, |_, _, cx| cx.notify())
+                    }
+                });
+            subscriptions.insert(entity_id, subscription);
+        }
+        self.owner_subscriptions = subscriptions;
+        cx.notify();
+    }
+
+    fn jump_to(&self, owner: &KernelOwner, cx: &mut ViewContext<Self>) {
+        let item = owner.item();
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace.activate_item(item.as_ref(), true, true, cx);
+            })
+            .ok();
+    }
+
+    /// Opens the kernel's connection file, which other Jupyter clients like `jupyter console
+    /// --existing` can use to connect to the same kernel.
+    fn open_connection_file(&self, owner: &KernelOwner, cx: &mut ViewContext<Self>) {
+        let Kernel::RunningKernel(kernel) = owner.kernel(cx) else {
+            return;
+        };
+        let Some(path) = kernel.connection_path().cloned() else {
+            return;
+        };
+        self.open_path(path, cx);
+    }
+
+    fn open_path(&self, path: PathBuf, cx: &mut ViewContext<Self>) {
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace
+                    .open_abs_path(path, true, cx)
+                    .detach_and_log_err(cx);
+            })
+            .ok();
+    }
+
+    /// The editor in the workspace's active pane, if it's showing one.
+    fn active_editor(&self, cx: &AppContext) -> Option<View<Editor>> {
+        self.workspace
+            .upgrade()?
+            .read(cx)
+            .active_item(cx)?
+            .act_as::<Editor>(cx)
+    }
+
+    /// Runs the active editor's code on a kernel another client started.
+    fn connect_active_editor(
+        &self,
+        kernel_specification: KernelSpecification,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(editor) = self.active_editor(cx) else {
+            return;
+        };
+        crate::assign_kernelspec(kernel_specification, editor.downgrade(), cx).log_err();
+    }
+
+    fn render_external_kernel(
+        &self,
+        index: usize,
+        kernel_specification: ExternalKernelSpecification,
+        has_active_editor: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> AnyElement {
+        let connection_path = kernel_specification.connection_path.clone();
+        let language = kernel_specification.language.clone();
+        let kernel_specification = KernelSpecification::External(kernel_specification);
+
+        KernelListItem::new(kernel_specification.clone())
+            .status_color(Color::Success)
+            .child(
+                v_flex()
+                    .child(Label::new(kernel_specification.name()))
+                    .when(!language.is_empty(), |this| {
+                        this.child(
+                            Label::new(language)
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                    }),
+            )
+            .button(
+                IconButton::new(("connect-external-kernel", index), IconName::Link)
+                    .icon_size(IconSize::Small)
+                    .disabled(!has_active_editor)
+                    .tooltip(|cx| Tooltip::text("Run Active Editor on This Kernel", cx))
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.connect_active_editor(kernel_specification.clone(), cx)
+                    })),
+            )
+            .button(
+                IconButton::new(("external-connection-file", index), IconName::FileCode)
+                    .icon_size(IconSize::Small)
+                    .tooltip(|cx| Tooltip::text("Open Connection File", cx))
+                    .on_click(
+                        cx.listener(move |this, _, cx| this.open_path(connection_path.clone(), cx)),
+                    ),
+            )
+            .into_any_element()
+    }
+
+    fn render_owner(
+        &self,
+        index: usize,
+        owner: KernelOwner,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<AnyElement> {
+        let kernel = owner.kernel(cx);
+        // Notebooks only start a kernel once a cell runs
+        if let (KernelOwner::Notebook(_), Kernel::Shutdown) = (&owner, kernel) {
+            return None;
+        }
+        let kernel_specification = owner.kernel_specification(cx)?;
+        let title = owner.title(cx);
+        let status_color = kernel_status_color(kernel);
+        let status = kernel.status().to_string();
+        let is_running = matches!(kernel, Kernel::RunningKernel(_));
+        let is_shutting_down = kernel.is_shutting_down();
+        let has_connection_file = match kernel {
+            Kernel::RunningKernel(kernel) => kernel.connection_path().is_some(),
+            _ => false,
+        };
+        let attached_notebook = match &owner {
+            KernelOwner::Editor { session, .. } => session.read(cx).attached_notebook_name(cx),
+            KernelOwner::Notebook(_) => None,
+        };
+        let status = match attached_notebook {
+            Some(notebook_name) => format!("Attached to {notebook_name}"),
+            None => status,
+        };
+
+        Some(
+            KernelListItem::new(kernel_specification.clone())
+                .status_color(status_color)
+                .child(
+                    v_flex()
+                        .child(
+                            Button::new(("jump-to-owner", index), title)
+                                .style(ButtonStyle::Subtle)
+                                .tooltip(|cx| Tooltip::text("Go to File", cx))
+                                .on_click(cx.listener({
+                                    let owner = owner.clone();
+                                    move |this, _, cx| this.jump_to(&owner, cx)
+                                })),
+                        )
+                        .child(
+                            Label::new(format!("{} ({status})", kernel_specification.name()))
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        ),
+                )
+                .button(
+                    IconButton::new(("interrupt-kernel", index), IconName::Stop)
+                        .icon_size(IconSize::Small)
+                        .disabled(!is_running)
+                        .tooltip(|cx| Tooltip::text("Interrupt", cx))
+                        .on_click({
+                            let owner = owner.clone();
+                            move |_, cx| owner.interrupt(cx)
+                        }),
+                )
+                .button(
+                    IconButton::new(("restart-kernel", index), IconName::RotateCw)
+                        .icon_size(IconSize::Small)
+                        .disabled(is_shutting_down)
+                        .tooltip(|cx| Tooltip::text("Restart", cx))
+                        .on_click({
+                            let owner = owner.clone();
+                            move |_, cx| owner.restart(cx)
+                        }),
+                )
+                .button(
+                    IconButton::new(("shutdown-kernel", index), IconName::Close)
+                        .icon_size(IconSize::Small)
+                        .disabled(is_shutting_down)
+                        .tooltip(|cx| Tooltip::text("Shut Down", cx))
+                        .on_click({
+                            let owner = owner.clone();
+                            move |_, cx| owner.shutdown(cx)
+                        }),
+                )
+                .button(
+                    IconButton::new(("kernel-connection-file", index), IconName::FileCode)
+                        .icon_size(IconSize::Small)
+                        .disabled(!has_connection_file)
+                        .tooltip(|cx| Tooltip::text("Open Connection File", cx))
+                        .on_click(
+                            cx.listener(move |this, _, cx| this.open_connection_file(&owner, cx)),
+                        ),
+                )
+                .into_any_element(),
+        )
+    }
+}
+
+impl FocusableView for KernelSessionsPanel {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<PanelEvent> for KernelSessionsPanel {}
+
+impl Panel for KernelSessionsPanel {
+    fn persistent_name() -> &'static str {
+        "Kernel Sessions"
+    }
+
+    fn position(&self, _: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, _: DockPosition) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _: &WindowContext) -> Pixels {
+        self.size.unwrap_or_else(|| px(DEFAULT_PANEL_SIZE))
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.size = size;
+        cx.notify();
+    }
+
+    fn icon(&self, _: &WindowContext) -> Option<IconName> {
+        Some(IconName::ReplNeutral)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("Kernel Sessions")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleKernelSessions)
+    }
+
+    fn set_active(&mut self, active: bool, cx: &mut ViewContext<Self>) {
+        // The dock opens the panel while the workspace is being updated, so it can't be read yet
+        if active {
+            cx.defer(|this, cx| this.refresh(cx));
+            // Kernels other clients started may have stopped without removing their files
+            ReplStore::global(cx).update(cx, |store, cx| {
+                store
+                    .refresh_external_kernelspecs(cx)
+                    .detach_and_log_err(cx)
+            });
+        }
+    }
+}
+
+impl Render for KernelSessionsPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let rows = self
+            .owners(cx)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, owner)| self.render_owner(index, owner, cx))
+            .collect::<Vec<_>>();
+
+        let has_active_editor = self.active_editor(cx).is_some();
+        let external_kernels = ReplStore::global(cx)
+            .read(cx)
+            .external_kernel_specifications()
+            .filter_map(|kernel_specification| match kernel_specification {
+                KernelSpecification::External(kernel_specification) => {
+                    Some(kernel_specification.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let external_rows = external_kernels
+            .into_iter()
+            .enumerate()
+            .map(|(index, kernel_specification)| {
+                self.render_external_kernel(index, kernel_specification, has_active_editor, cx)
+            })
+            .collect::<Vec<_>>();
+
+        v_flex()
+            .id("kernel-sessions")
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .p_2()
+            .gap_1()
+            .overflow_y_scroll()
+            .bg(cx.theme().colors().panel_background)
+            .map(|panel| {
+                if rows.is_empty() {
+                    panel.child(
+                        Label::new("No kernels are running in this workspace").color(Color::Muted),
+                    )
+                } else {
+                    panel.children(rows)
+                }
+            })
+            .when(!external_rows.is_empty(), |panel| {
+                panel
+                    .child(
+                        div().mt_2().child(
+                            Label::new("External Kernels")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        ),
+                    )
+                    .children(external_rows)
+            })
+    }
+}
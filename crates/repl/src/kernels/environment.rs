@@ -0,0 +1,127 @@
+use collections::HashMap;
+use gpui::{AppContext, Model, Task};
+use project::{Project, WorktreeId};
+use settings::Settings as _;
+
+use crate::JupyterSettings;
+
+/// The environment a kernel for a file in the worktree is started in, before the kernelspec's
+/// `env` and the `jupyter.kernel_env` setting are applied: the project's environment, as used for
+/// language servers and tasks, with the variables of the `.env` file at the worktree's root on top.
+pub fn kernel_environment(
+    project: &Model<Project>,
+    worktree_id: Option<WorktreeId>,
+    cx: &mut AppContext,
+) -> Task<HashMap<String, String>> {
+    if !project.read(cx).is_local() {
+        return Task::ready(HashMap::default());
+    }
+    let dotenv_path = JupyterSettings::get_global(cx)
+        .load_dotenv
+        .then(|| {
+            let worktree = project.read(cx).worktree_for_id(worktree_id?, cx)?;
+            Some(worktree.read(cx).abs_path().join(".env"))
+        })
+        .flatten();
+    let fs = project.read(cx).fs().clone();
+    let project_environment = project.update(cx, |project, cx| {
+        project.worktree_environment(worktree_id, cx)
+    });
+
+    cx.background_executor().spawn(async move {
+        let mut environment = project_environment.await.unwrap_or_default();
+        if let Some(dotenv_path) = dotenv_path {
+            if let Ok(text) = fs.load(&dotenv_path).await {
+                environment.extend(parse_dotenv(&text));
+            }
+        }
+        environment
+    })
+}
+
+/// Reads the variables of a `.env` file. Lines may start with `export`, and values may be quoted:
+/// escapes like `\n` are only expanded in double quotes, and `#` only starts a comment outside of
+/// quotes. Variables aren't interpolated.
+pub fn parse_dotenv(text: &str) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            continue;
+        }
+        variables.push((key.to_string(), dotenv_value(value.trim())));
+    }
+    variables
+}
+
+fn dotenv_value(value: &str) -> String {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted
+            .split_once('\'')
+            .map_or(quoted, |(quoted, _)| quoted)
+            .to_string();
+    }
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = quoted.chars();
+        while let Some(char) = chars.next() {
+            match char {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => unescaped.push('\n'),
+                    Some('t') => unescaped.push('\t'),
+                    Some('r') => unescaped.push('\r'),
+                    Some(char) => unescaped.push(char),
+                    None => unescaped.push('\\'),
+                },
+                char => unescaped.push(char),
+            }
+        }
+        return unescaped;
+    }
+
+    match value.find(" #") {
+        Some(comment_start) => value[..comment_start].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let text = r#"
+# Local services
+DATABASE_URL=postgres://localhost:5432/analytics
+export API_KEY = "abc\"123"  # rotated monthly
+GREETING="hello\nworld"
+RAW='no\nescapes # here'
+PORT=8080 # the dev server
+EMPTY=
+not a variable
+"#;
+        assert_eq!(
+            parse_dotenv(text),
+            [
+                ("DATABASE_URL", "postgres://localhost:5432/analytics"),
+                ("API_KEY", "abc\"123"),
+                ("GREETING", "hello\nworld"),
+                ("RAW", "no\\nescapes # here"),
+                ("PORT", "8080"),
+                ("EMPTY", ""),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+    }
+}
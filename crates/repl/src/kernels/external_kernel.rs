@@ -0,0 +1,324 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result};
+use futures::{channel::mpsc, StreamExt as _};
+use gpui::{AppContext, BackgroundExecutor, Task};
+use jupyter_protocol::{ExecutionState, JupyterMessage, KernelInfoReply};
+use project::Fs;
+use runtimelib::{dirs, ConnectionInfo};
+
+use super::{
+    heartbeat_lost, JupyterMessageChannel, KernelConnection, LocalKernelSpecification,
+    RunningKernel,
+};
+
+/// How long a kernel has to answer a heartbeat to be listed.
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A kernel another Jupyter client started, like `jupyter console` or VS Code, found through the
+/// connection file it left in the Jupyter runtime directory.
+#[derive(Debug, Clone)]
+pub struct ExternalKernelSpecification {
+    pub name: String,
+    pub connection_path: PathBuf,
+    pub connection_info: ConnectionInfo,
+    /// The language of the installed kernelspec the kernel was started from, if it's known.
+    pub language: String,
+}
+
+impl PartialEq for ExternalKernelSpecification {
+    fn eq(&self, other: &Self) -> bool {
+        self.connection_path == other.connection_path
+    }
+}
+
+impl Eq for ExternalKernelSpecification {}
+
+/// Whether a file in the runtime directory is the connection file of a kernel another client
+/// started. The kernels Zed starts write theirs there too, as `kernel-zed-*.json`.
+pub(crate) fn is_external_connection_file(path: &Path) -> bool {
+    let Some(file_name) = path.file_name().and_then(|file_name| file_name.to_str()) else {
+        return false;
+    };
+    file_name.starts_with("kernel-")
+        && file_name.ends_with(".json")
+        && !file_name.starts_with("kernel-zed-")
+}
+
+async fn read_connection_file(
+    connection_path: PathBuf,
+    kernelspecs: &[LocalKernelSpecification],
+    fs: &dyn Fs,
+) -> Result<ExternalKernelSpecification> {
+    let content = fs.load(&connection_path).await?;
+    let connection_info = serde_json::from_str::<ConnectionInfo>(&content)
+        .with_context(|| format!("Invalid connection file {connection_path:?}"))?;
+
+    let kernel_name = connection_info.kernel_name.clone().unwrap_or_default();
+    let language = kernelspecs
+        .iter()
+        .find(|kernelspec| kernelspec.name == kernel_name)
+        .map(|kernelspec| kernelspec.kernelspec.language.clone())
+        .unwrap_or_default();
+    let id = connection_path
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy())
+        .unwrap_or_default();
+    let id = id.trim_start_matches("kernel-");
+    let name = if kernel_name.is_empty() {
+        format!("kernel ({id})")
+    } else {
+        format!("{kernel_name} ({id})")
+    };
+
+    Ok(ExternalKernelSpecification {
+        name,
+        connection_path,
+        connection_info,
+        language,
+    })
+}
+
+/// Whether the kernel answers a heartbeat. Clients don't always remove the connection files of
+/// the kernels they started, for example when they crash.
+async fn is_alive(connection_info: &ConnectionInfo, executor: &BackgroundExecutor) -> bool {
+    smol::future::or(
+        async {
+            match runtimelib::create_client_heartbeat_connection(connection_info).await {
+                Ok(mut heartbeat) => heartbeat.single_heartbeat().await.is_ok(),
+                Err(_) => false,
+            }
+        },
+        async {
+            executor.timer(LIVENESS_TIMEOUT).await;
+            false
+        },
+    )
+    .await
+}
+
+/// Lists the live kernels other clients started, from the connection files in the Jupyter
+/// runtime directory (`$JUPYTER_RUNTIME_DIR` when it's set). Their language is looked up in the
+/// installed kernelspecs.
+pub async fn external_kernel_specifications(
+    kernelspecs: Vec<LocalKernelSpecification>,
+    fs: Arc<dyn Fs>,
+    executor: BackgroundExecutor,
+) -> Result<Vec<ExternalKernelSpecification>> {
+    let runtime_dir = dirs::runtime_dir();
+    if !fs.is_dir(&runtime_dir).await {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = fs.read_dir(&runtime_dir).await?;
+    let mut specifications = Vec::new();
+    while let Some(path) = paths.next().await {
+        match path {
+            Ok(path) if is_external_connection_file(&path) => {
+                match read_connection_file(path, &kernelspecs, fs.as_ref()).await {
+                    Ok(specification) => specifications.push(specification),
+                    Err(err) => log::warn!("Error reading connection file: {err:?}"),
+                }
+            }
+            Ok(_) => {}
+            Err(err) => log::warn!("Error reading jupyter runtime directory: {err:?}"),
+        }
+    }
+
+    let liveness = futures::future::join_all(
+        specifications
+            .iter()
+            .map(|specification| is_alive(&specification.connection_info, &executor)),
+    )
+    .await;
+
+    Ok(specifications
+        .into_iter()
+        .zip(liveness)
+        .filter_map(|(specification, is_alive)| is_alive.then_some(specification))
+        .collect())
+}
+
+/// A connection to a kernel another client started. The kernel keeps running after Zed
+/// disconnects from it.
+pub struct ExternalRunningKernel {
+    _connection: KernelConnection,
+    connection_path: PathBuf,
+    connection_info: ConnectionInfo,
+    pub working_directory: PathBuf,
+    pub request_tx: mpsc::Sender<JupyterMessage>,
+    pub execution_state: ExecutionState,
+    pub kernel_info: Option<KernelInfoReply>,
+}
+
+impl Debug for ExternalRunningKernel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalRunningKernel")
+            .field("connection_path", &self.connection_path)
+            .finish()
+    }
+}
+
+impl ExternalRunningKernel {
+    pub fn new(
+        kernel_specification: ExternalKernelSpecification,
+        working_directory: PathBuf,
+        cx: &mut AppContext,
+    ) -> Task<Result<(Self, JupyterMessageChannel)>> {
+        cx.spawn(|cx| async move {
+            let connection_info = kernel_specification.connection_info;
+            let (connection, messages_rx) =
+                KernelConnection::connect(&connection_info, cx.background_executor())
+                    .await
+                    .context("failed to connect to the kernel")?;
+
+            anyhow::Ok((
+                Self {
+                    request_tx: connection.request_tx.clone(),
+                    _connection: connection,
+                    connection_path: kernel_specification.connection_path,
+                    connection_info,
+                    working_directory,
+                    execution_state: ExecutionState::Idle,
+                    kernel_info: None,
+                },
+                messages_rx,
+            ))
+        })
+    }
+
+    /// Resolves once the kernel stops answering heartbeats, which is how Zed notices that the
+    /// client that started it shut it down.
+    pub fn heartbeat_lost(&self, cx: &AppContext) -> Task<()> {
+        heartbeat_lost(self.connection_info.clone(), cx)
+    }
+}
+
+impl RunningKernel for ExternalRunningKernel {
+    fn request_tx(&self) -> mpsc::Sender<JupyterMessage> {
+        self.request_tx.clone()
+    }
+
+    fn working_directory(&self) -> &PathBuf {
+        &self.working_directory
+    }
+
+    fn execution_state(&self) -> &ExecutionState {
+        &self.execution_state
+    }
+
+    fn set_execution_state(&mut self, state: ExecutionState) {
+        self.execution_state = state;
+    }
+
+    fn kernel_info(&self) -> Option<&KernelInfoReply> {
+        self.kernel_info.as_ref()
+    }
+
+    fn set_kernel_info(&mut self, info: KernelInfoReply) {
+        self.kernel_info = Some(info);
+    }
+
+    fn force_shutdown(&mut self) -> anyhow::Result<()> {
+        // The kernel belongs to the client that started it, so only disconnect
+        self.request_tx.close_channel();
+        Ok(())
+    }
+
+    fn connection_path(&self) -> Option<&PathBuf> {
+        Some(&self.connection_path)
+    }
+}
+
+impl Drop for ExternalRunningKernel {
+    fn drop(&mut self) {
+        self.request_tx.close_channel();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use gpui::TestAppContext;
+    use project::FakeFs;
+    use runtimelib::JupyterKernelspec;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_external_connection_file() {
+        assert!(is_external_connection_file(Path::new(
+            "/runtime/kernel-4821.json"
+        )));
+        assert!(is_external_connection_file(Path::new(
+            "/runtime/kernel-v2-1234abcd.json"
+        )));
+        assert!(!is_external_connection_file(Path::new(
+            "/runtime/kernel-zed-12.json"
+        )));
+        assert!(!is_external_connection_file(Path::new(
+            "/runtime/jpserver-4821.json"
+        )));
+    }
+
+    #[gpui::test]
+    async fn test_read_connection_file(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/runtime",
+            json!({
+                "kernel-4821.json": r#"{
+                    "shell_port": 50001,
+                    "iopub_port": 50002,
+                    "stdin_port": 50003,
+                    "control_port": 50004,
+                    "hb_port": 50005,
+                    "ip": "127.0.0.1",
+                    "key": "a0436f6c-1916-498b-8eb9-e81ab9368e84",
+                    "transport": "tcp",
+                    "signature_scheme": "hmac-sha256",
+                    "kernel_name": "python3"
+                }"#,
+                "kernel-4822.json": "not a connection file",
+            }),
+        )
+        .await;
+
+        let kernelspecs = vec![LocalKernelSpecification {
+            name: "python3".to_string(),
+            path: PathBuf::from("/jupyter/kernels/python3"),
+            kernelspec: JupyterKernelspec {
+                argv: vec![],
+                display_name: "Python 3".to_string(),
+                language: "python".to_string(),
+                interrupt_mode: None,
+                metadata: None,
+                env: None,
+            },
+        }];
+
+        let specification = read_connection_file(
+            PathBuf::from("/runtime/kernel-4821.json"),
+            &kernelspecs,
+            fs.as_ref(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(specification.name, "python3 (4821)");
+        assert_eq!(specification.language, "python");
+        assert_eq!(specification.connection_info.shell_port, 50001);
+
+        assert!(read_connection_file(
+            PathBuf::from("/runtime/kernel-4822.json"),
+            &kernelspecs,
+            fs.as_ref(),
+        )
+        .await
+        .is_err());
+    }
+}
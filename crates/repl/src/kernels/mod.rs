@@ -1,6 +1,10 @@
+mod environment;
+mod external_kernel;
 mod native_kernel;
 use std::{fmt::Debug, future::Future, path::PathBuf};
 
+pub use environment::*;
+pub use external_kernel::*;
 use futures::{
     channel::mpsc::{self, Receiver},
     future::Shared,
@@ -14,6 +18,9 @@ mod remote_kernels;
 use project::{Project, WorktreeId};
 pub use remote_kernels::*;
 
+mod ssh_kernel;
+pub use ssh_kernel::*;
+
 use anyhow::Result;
 use runtimelib::{ExecutionState, JupyterKernelspec, JupyterMessage, KernelInfoReply};
 use smol::process::Command;
@@ -26,6 +33,9 @@ pub enum KernelSpecification {
     Remote(RemoteKernelSpecification),
     Jupyter(LocalKernelSpecification),
     PythonEnv(LocalKernelSpecification),
+    Ssh(SshKernelSpecification),
+    /// A kernel another Jupyter client started, which is connected to rather than launched.
+    External(ExternalKernelSpecification),
 }
 
 impl KernelSpecification {
@@ -34,6 +44,8 @@ impl KernelSpecification {
             Self::Jupyter(spec) => spec.name.clone().into(),
             Self::PythonEnv(spec) => spec.name.clone().into(),
             Self::Remote(spec) => spec.name.clone().into(),
+            Self::Ssh(spec) => spec.name.clone().into(),
+            Self::External(spec) => spec.name.clone().into(),
         }
     }
 
@@ -42,6 +54,8 @@ impl KernelSpecification {
             Self::Jupyter(_) => "Jupyter".into(),
             Self::PythonEnv(_) => "Python Environment".into(),
             Self::Remote(_) => "Remote".into(),
+            Self::Ssh(_) => "SSH".into(),
+            Self::External(_) => "External".into(),
         }
     }
 
@@ -50,6 +64,8 @@ impl KernelSpecification {
             Self::Jupyter(spec) => spec.path.to_string_lossy().to_string(),
             Self::PythonEnv(spec) => spec.path.to_string_lossy().to_string(),
             Self::Remote(spec) => spec.url.to_string(),
+            Self::Ssh(spec) => spec.path.to_string_lossy().to_string(),
+            Self::External(spec) => spec.connection_path.to_string_lossy().to_string(),
         })
     }
 
@@ -58,8 +74,15 @@ impl KernelSpecification {
             Self::Jupyter(spec) => spec.kernelspec.language.clone(),
             Self::PythonEnv(spec) => spec.kernelspec.language.clone(),
             Self::Remote(spec) => spec.kernelspec.language.clone(),
+            Self::Ssh(spec) => spec.kernelspec.language.clone(),
+            Self::External(spec) => spec.language.clone(),
         })
     }
+
+    /// Whether the kernel was started by another client, so Zed only connects to it.
+    pub fn is_external(&self) -> bool {
+        matches!(self, Self::External(_))
+    }
 }
 
 pub fn python_env_kernel_specifications(
@@ -136,6 +159,9 @@ pub trait RunningKernel: Send + Debug {
     fn kernel_info(&self) -> Option<&KernelInfoReply>;
     fn set_kernel_info(&mut self, info: KernelInfoReply);
     fn force_shutdown(&mut self) -> anyhow::Result<()>;
+    /// The connection file other Jupyter clients can use to connect to the kernel, if it has one
+    /// on this machine.
+    fn connection_path(&self) -> Option<&PathBuf>;
 }
 
 #[derive(Debug, Clone)]
@@ -177,6 +203,8 @@ pub enum Kernel {
     RunningKernel(Box<dyn RunningKernel>),
     StartingKernel(Shared<Task<()>>),
     ErroredLaunch(String),
+    /// The kernel stopped responding or exited while it was running.
+    Died(String),
     ShuttingDown,
     Shutdown,
     Restarting,
@@ -190,7 +218,7 @@ impl From<&Kernel> for KernelStatus {
                 ExecutionState::Busy => KernelStatus::Busy,
             },
             Kernel::StartingKernel(_) => KernelStatus::Starting,
-            Kernel::ErroredLaunch(_) => KernelStatus::Error,
+            Kernel::ErroredLaunch(_) | Kernel::Died(_) => KernelStatus::Error,
             Kernel::ShuttingDown => KernelStatus::ShuttingDown,
             Kernel::Shutdown => KernelStatus::Shutdown,
             Kernel::Restarting => KernelStatus::Restarting,
@@ -221,6 +249,7 @@ impl Kernel {
             Kernel::RunningKernel(_)
             | Kernel::StartingKernel(_)
             | Kernel::ErroredLaunch(_)
+            | Kernel::Died(_)
             | Kernel::Shutdown => false,
         }
     }
@@ -1,13 +1,16 @@
 use anyhow::{Context as _, Result};
+use collections::HashMap;
 use futures::{
     channel::mpsc::{self},
+    io::BufReader,
     stream::{SelectAll, StreamExt},
-    SinkExt as _,
+    AsyncBufReadExt as _, SinkExt as _,
 };
-use gpui::{AppContext, EntityId, Task};
+use gpui::{AppContext, BackgroundExecutor, EntityId, Task};
 use jupyter_protocol::{JupyterMessage, JupyterMessageContent, KernelInfoReply};
 use project::Fs;
 use runtimelib::{dirs, ConnectionInfo, ExecutionState, JupyterKernelspec};
+use settings::Settings as _;
 use smol::{net::TcpListener, process::Command};
 use std::{
     env,
@@ -15,10 +18,12 @@ use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 use uuid::Uuid;
 
 use super::{JupyterMessageChannel, RunningKernel};
+use crate::JupyterSettings;
 
 #[derive(Debug, Clone)]
 pub struct LocalKernelSpecification {
@@ -36,8 +41,15 @@ impl PartialEq for LocalKernelSpecification {
 impl Eq for LocalKernelSpecification {}
 
 impl LocalKernelSpecification {
+    /// The command starting the kernel, in `environment` with the kernelspec's `env` and then
+    /// `kernel_env` on top.
     #[must_use]
-    fn command(&self, connection_path: &PathBuf) -> Result<Command> {
+    fn command(
+        &self,
+        connection_path: &PathBuf,
+        environment: &HashMap<String, String>,
+        kernel_env: &HashMap<String, String>,
+    ) -> Result<Command> {
         let argv = &self.kernelspec.argv;
 
         anyhow::ensure!(!argv.is_empty(), "Empty argv in kernelspec {}", self.name);
@@ -58,9 +70,11 @@ impl LocalKernelSpecification {
             }
         }
 
+        cmd.envs(environment);
         if let Some(env) = &self.kernelspec.env {
             cmd.envs(env);
         }
+        cmd.envs(kernel_env);
 
         #[cfg(windows)]
         {
@@ -86,13 +100,210 @@ async fn peek_ports(ip: IpAddr) -> Result<[u16; 5]> {
     Ok(ports)
 }
 
-pub struct NativeRunningKernel {
-    pub process: smol::process::Child,
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The tasks relaying messages between a kernel's sockets and the channels the frontend uses.
+/// Dropping them disconnects from the kernel.
+pub(crate) struct KernelConnection {
+    pub request_tx: mpsc::Sender<JupyterMessage>,
     _shell_task: Task<Result<()>>,
     _iopub_task: Task<Result<()>>,
     _control_task: Task<Result<()>>,
+    _stdin_task: Task<Result<()>>,
     _routing_task: Task<Result<()>>,
+}
+
+impl KernelConnection {
+    /// Connects to the sockets of the kernel listening at `connection_info`.
+    pub(crate) async fn connect(
+        connection_info: &ConnectionInfo,
+        executor: &BackgroundExecutor,
+    ) -> Result<(Self, JupyterMessageChannel)> {
+        let session_id = Uuid::new_v4().to_string();
+
+        let mut iopub_socket =
+            runtimelib::create_client_iopub_connection(connection_info, "", &session_id).await?;
+        let mut shell_socket =
+            runtimelib::create_client_shell_connection(connection_info, &session_id).await?;
+        let mut control_socket =
+            runtimelib::create_client_control_connection(connection_info, &session_id).await?;
+        let stdin_socket =
+            runtimelib::create_client_stdin_connection(connection_info, &session_id).await?;
+
+        let (mut iopub, iosub) = futures::channel::mpsc::channel(100);
+
+        let (request_tx, mut request_rx) = futures::channel::mpsc::channel::<JupyterMessage>(100);
+
+        let (mut control_reply_tx, control_reply_rx) = futures::channel::mpsc::channel(100);
+        let (mut shell_reply_tx, shell_reply_rx) = futures::channel::mpsc::channel(100);
+        let (stdin_request_tx, stdin_request_rx) = futures::channel::mpsc::channel(100);
+
+        let mut messages_rx = SelectAll::new();
+        messages_rx.push(iosub);
+        messages_rx.push(control_reply_rx);
+        messages_rx.push(shell_reply_rx);
+        messages_rx.push(stdin_request_rx);
+
+        let iopub_task = executor.spawn({
+            async move {
+                while let Ok(message) = iopub_socket.read().await {
+                    iopub.send(message).await?;
+                }
+                anyhow::Ok(())
+            }
+        });
+
+        let (mut control_request_tx, mut control_request_rx) = futures::channel::mpsc::channel(100);
+        let (mut shell_request_tx, mut shell_request_rx) = futures::channel::mpsc::channel(100);
+        let (mut stdin_reply_tx, stdin_reply_rx) = futures::channel::mpsc::channel(100);
+
+        let routing_task = executor.spawn({
+            async move {
+                while let Some(message) = request_rx.next().await {
+                    match message.content {
+                        JupyterMessageContent::DebugRequest(_)
+                        | JupyterMessageContent::InterruptRequest(_)
+                        | JupyterMessageContent::ShutdownRequest(_) => {
+                            control_request_tx.send(message).await?;
+                        }
+                        JupyterMessageContent::InputReply(_) => {
+                            stdin_reply_tx.send(message).await?;
+                        }
+                        _ => {
+                            shell_request_tx.send(message).await?;
+                        }
+                    }
+                }
+                anyhow::Ok(())
+            }
+        });
+
+        let shell_task = executor.spawn({
+            async move {
+                while let Some(message) = shell_request_rx.next().await {
+                    shell_socket.send(message).await.ok();
+                    let reply = shell_socket.read().await?;
+                    shell_reply_tx.send(reply).await?;
+                }
+                anyhow::Ok(())
+            }
+        });
+
+        let control_task = executor.spawn({
+            async move {
+                while let Some(message) = control_request_rx.next().await {
+                    control_socket.send(message).await.ok();
+                    let reply = control_socket.read().await?;
+                    control_reply_tx.send(reply).await?;
+                }
+                anyhow::Ok(())
+            }
+        });
+
+        let stdin_task =
+            executor.spawn(relay_stdin(stdin_socket, stdin_request_tx, stdin_reply_rx));
+
+        anyhow::Ok((
+            Self {
+                request_tx,
+                _shell_task: shell_task,
+                _iopub_task: iopub_task,
+                _control_task: control_task,
+                _stdin_task: stdin_task,
+                _routing_task: routing_task,
+            },
+            messages_rx,
+        ))
+    }
+}
+
+/// The parts of the stdin socket [`relay_stdin`] uses.
+trait StdinSocket {
+    async fn read(&mut self) -> Result<JupyterMessage>;
+    async fn send(&mut self, message: JupyterMessage) -> Result<()>;
+}
+
+impl StdinSocket for runtimelib::ClientStdinConnection {
+    async fn read(&mut self) -> Result<JupyterMessage> {
+        runtimelib::ClientStdinConnection::read(self).await
+    }
+
+    async fn send(&mut self, message: JupyterMessage) -> Result<()> {
+        runtimelib::ClientStdinConnection::send(self, message).await
+    }
+}
+
+/// The kernel asks for input on the stdin channel (e.g. Python's `input()`) and waits for the
+/// frontend to answer before it continues executing. Requests and replies are relayed as they
+/// come rather than in pairs: a prompt that's dropped without an answer, e.g. because its
+/// execution was interrupted, mustn't hold back the requests after it.
+async fn relay_stdin(
+    mut socket: impl StdinSocket,
+    mut request_tx: mpsc::Sender<JupyterMessage>,
+    mut reply_rx: mpsc::Receiver<JupyterMessage>,
+) -> Result<()> {
+    enum Relayed {
+        Request(Result<JupyterMessage>),
+        Reply(Option<JupyterMessage>),
+    }
+
+    loop {
+        let relayed = smol::future::or(async { Relayed::Request(socket.read().await) }, async {
+            Relayed::Reply(reply_rx.next().await)
+        })
+        .await;
+        match relayed {
+            Relayed::Request(Ok(request)) => request_tx.send(request).await?,
+            Relayed::Reply(Some(reply)) => {
+                socket.send(reply).await.ok();
+            }
+            Relayed::Request(Err(_)) | Relayed::Reply(None) => return Ok(()),
+        }
+    }
+}
+
+/// Resolves once the kernel at `connection_info` stops answering heartbeats. Kernels answer them
+/// from their own thread even while busy executing, so a missed heartbeat means the process is
+/// hung or gone.
+pub(crate) fn heartbeat_lost(connection_info: ConnectionInfo, cx: &AppContext) -> Task<()> {
+    let executor = cx.background_executor().clone();
+
+    cx.background_executor().spawn(async move {
+        let heartbeat = runtimelib::create_client_heartbeat_connection(&connection_info).await;
+        let mut heartbeat = match heartbeat {
+            Ok(heartbeat) => heartbeat,
+            Err(error) => {
+                log::error!("failed to connect to the kernel heartbeat: {error:?}");
+                return futures::future::pending().await;
+            }
+        };
+
+        loop {
+            executor.timer(HEARTBEAT_INTERVAL).await;
+
+            let answered = smol::future::or(
+                async { heartbeat.single_heartbeat().await.is_ok() },
+                async {
+                    executor.timer(HEARTBEAT_TIMEOUT).await;
+                    false
+                },
+            )
+            .await;
+
+            if !answered {
+                log::error!("kernel stopped answering heartbeats");
+                return;
+            }
+        }
+    })
+}
+
+pub struct NativeRunningKernel {
+    pub process: smol::process::Child,
+    _connection: KernelConnection,
     connection_path: PathBuf,
+    connection_info: ConnectionInfo,
     pub working_directory: PathBuf,
     pub request_tx: mpsc::Sender<JupyterMessage>,
     pub execution_state: ExecutionState,
@@ -112,9 +323,15 @@ impl NativeRunningKernel {
         kernel_specification: LocalKernelSpecification,
         entity_id: EntityId,
         working_directory: PathBuf,
+        environment: Task<HashMap<String, String>>,
         fs: Arc<dyn Fs>,
         cx: &mut AppContext,
     ) -> Task<Result<(Self, JupyterMessageChannel)>> {
+        let kernel_env = JupyterSettings::get_global(cx)
+            .kernel_env
+            .clone()
+            .into_iter()
+            .collect::<HashMap<_, _>>();
         cx.spawn(|cx| async move {
             let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
             let ports = peek_ports(ip).await?;
@@ -140,7 +357,9 @@ impl NativeRunningKernel {
             let content = serde_json::to_string(&connection_info)?;
             fs.atomic_write(connection_path.clone(), content).await?;
 
-            let mut cmd = kernel_specification.command(&connection_path)?;
+            let environment = environment.await;
+            let mut cmd =
+                kernel_specification.command(&connection_path, &environment, &kernel_env)?;
 
             let process = cmd
                 .current_dir(&working_directory)
@@ -151,92 +370,17 @@ impl NativeRunningKernel {
                 .spawn()
                 .context("failed to start the kernel process")?;
 
-            let session_id = Uuid::new_v4().to_string();
-
-            let mut iopub_socket =
-                runtimelib::create_client_iopub_connection(&connection_info, "", &session_id)
-                    .await?;
-            let mut shell_socket =
-                runtimelib::create_client_shell_connection(&connection_info, &session_id).await?;
-            let mut control_socket =
-                runtimelib::create_client_control_connection(&connection_info, &session_id).await?;
-
-            let (mut iopub, iosub) = futures::channel::mpsc::channel(100);
-
-            let (request_tx, mut request_rx) =
-                futures::channel::mpsc::channel::<JupyterMessage>(100);
-
-            let (mut control_reply_tx, control_reply_rx) = futures::channel::mpsc::channel(100);
-            let (mut shell_reply_tx, shell_reply_rx) = futures::channel::mpsc::channel(100);
-
-            let mut messages_rx = SelectAll::new();
-            messages_rx.push(iosub);
-            messages_rx.push(control_reply_rx);
-            messages_rx.push(shell_reply_rx);
-
-            let iopub_task = cx.background_executor().spawn({
-                async move {
-                    while let Ok(message) = iopub_socket.read().await {
-                        iopub.send(message).await?;
-                    }
-                    anyhow::Ok(())
-                }
-            });
-
-            let (mut control_request_tx, mut control_request_rx) =
-                futures::channel::mpsc::channel(100);
-            let (mut shell_request_tx, mut shell_request_rx) = futures::channel::mpsc::channel(100);
-
-            let routing_task = cx.background_executor().spawn({
-                async move {
-                    while let Some(message) = request_rx.next().await {
-                        match message.content {
-                            JupyterMessageContent::DebugRequest(_)
-                            | JupyterMessageContent::InterruptRequest(_)
-                            | JupyterMessageContent::ShutdownRequest(_) => {
-                                control_request_tx.send(message).await?;
-                            }
-                            _ => {
-                                shell_request_tx.send(message).await?;
-                            }
-                        }
-                    }
-                    anyhow::Ok(())
-                }
-            });
-
-            let shell_task = cx.background_executor().spawn({
-                async move {
-                    while let Some(message) = shell_request_rx.next().await {
-                        shell_socket.send(message).await.ok();
-                        let reply = shell_socket.read().await?;
-                        shell_reply_tx.send(reply).await?;
-                    }
-                    anyhow::Ok(())
-                }
-            });
-
-            let control_task = cx.background_executor().spawn({
-                async move {
-                    while let Some(message) = control_request_rx.next().await {
-                        control_socket.send(message).await.ok();
-                        let reply = control_socket.read().await?;
-                        control_reply_tx.send(reply).await?;
-                    }
-                    anyhow::Ok(())
-                }
-            });
+            let (connection, messages_rx) =
+                KernelConnection::connect(&connection_info, cx.background_executor()).await?;
 
             anyhow::Ok((
                 Self {
                     process,
-                    request_tx,
+                    request_tx: connection.request_tx.clone(),
                     working_directory,
-                    _shell_task: shell_task,
-                    _iopub_task: iopub_task,
-                    _control_task: control_task,
-                    _routing_task: routing_task,
+                    _connection: connection,
                     connection_path,
+                    connection_info,
                     execution_state: ExecutionState::Idle,
                     kernel_info: None,
                 },
@@ -244,6 +388,38 @@ impl NativeRunningKernel {
             ))
         })
     }
+
+    /// Resolves once the kernel stops answering heartbeats.
+    pub fn heartbeat_lost(&self, cx: &AppContext) -> Task<()> {
+        heartbeat_lost(self.connection_info.clone(), cx)
+    }
+
+    /// Forwards the kernel process's stdout and stderr to the log, which also keeps
+    /// the pipes drained so a chatty kernel never blocks on a full buffer.
+    pub fn log_process_output(&mut self, cx: &AppContext) {
+        if let Some(stderr) = self.process.stderr.take() {
+            cx.background_executor()
+                .spawn(async move {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Some(Ok(line)) = lines.next().await {
+                        // todo!(): Log stdout and stderr to something the session can show
+                        log::error!("kernel: {}", line);
+                    }
+                })
+                .detach();
+        }
+
+        if let Some(stdout) = self.process.stdout.take() {
+            cx.background_executor()
+                .spawn(async move {
+                    let mut lines = BufReader::new(stdout).lines();
+                    while let Some(Ok(line)) = lines.next().await {
+                        log::info!("kernel: {}", line);
+                    }
+                })
+                .detach();
+        }
+    }
 }
 
 impl RunningKernel for NativeRunningKernel {
@@ -280,6 +456,10 @@ impl RunningKernel for NativeRunningKernel {
             )),
         }
     }
+
+    fn connection_path(&self) -> Option<&PathBuf> {
+        Some(&self.connection_path)
+    }
 }
 
 impl Drop for NativeRunningKernel {
@@ -395,6 +575,7 @@ mod test {
     use std::path::PathBuf;
 
     use gpui::TestAppContext;
+    use jupyter_protocol::{InputReply, InputRequest, ReplyStatus};
     use project::FakeFs;
     use serde_json::json;
 
@@ -445,4 +626,76 @@ mod test {
             vec!["deno", "python"]
         );
     }
+
+    struct FakeStdinSocket {
+        requests: mpsc::UnboundedReceiver<JupyterMessage>,
+        replies: mpsc::UnboundedSender<JupyterMessage>,
+    }
+
+    impl StdinSocket for FakeStdinSocket {
+        async fn read(&mut self) -> Result<JupyterMessage> {
+            self.requests.next().await.context("socket closed")
+        }
+
+        async fn send(&mut self, message: JupyterMessage) -> Result<()> {
+            Ok(self.replies.unbounded_send(message)?)
+        }
+    }
+
+    fn input_request(prompt: &str) -> JupyterMessage {
+        InputRequest {
+            prompt: prompt.to_string(),
+            password: false,
+        }
+        .into()
+    }
+
+    #[gpui::test]
+    async fn test_relay_stdin_after_dropped_prompt(cx: &mut TestAppContext) {
+        let (kernel_requests_tx, kernel_requests_rx) = mpsc::unbounded();
+        let (kernel_replies_tx, mut kernel_replies_rx) = mpsc::unbounded();
+        let (request_tx, mut request_rx) = mpsc::channel(100);
+        let (mut reply_tx, reply_rx) = mpsc::channel(100);
+        let socket = FakeStdinSocket {
+            requests: kernel_requests_rx,
+            replies: kernel_replies_tx,
+        };
+        let _relay = cx
+            .executor()
+            .spawn(relay_stdin(socket, request_tx, reply_rx));
+
+        // The first prompt is dropped without an answer, e.g. because its cell was interrupted
+        kernel_requests_tx
+            .unbounded_send(input_request("first: "))
+            .unwrap();
+        let first = request_rx.next().await.unwrap();
+        assert!(matches!(
+            &first.content,
+            JupyterMessageContent::InputRequest(request) if request.prompt == "first: "
+        ));
+
+        kernel_requests_tx
+            .unbounded_send(input_request("second: "))
+            .unwrap();
+        let second = request_rx.next().await.unwrap();
+        assert!(matches!(
+            &second.content,
+            JupyterMessageContent::InputRequest(request) if request.prompt == "second: "
+        ));
+
+        let reply = JupyterMessage::new(
+            InputReply {
+                value: "answer".to_string(),
+                status: ReplyStatus::Ok,
+                error: None,
+            },
+            Some(&second),
+        );
+        reply_tx.send(reply).await.unwrap();
+        let relayed = kernel_replies_rx.next().await.unwrap();
+        assert_eq!(
+            relayed.parent_header.map(|header| header.msg_id),
+            Some(second.header.msg_id)
+        );
+    }
 }
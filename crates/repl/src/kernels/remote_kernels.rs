@@ -119,4 +119,8 @@ impl RunningKernel for RemoteRunningKernel {
     fn force_shutdown(&mut self) -> anyhow::Result<()> {
         unimplemented!("force_shutdown")
     }
+
+    fn connection_path(&self) -> Option<&std::path::PathBuf> {
+        None
+    }
 }
@@ -0,0 +1,158 @@
+use std::{fmt::Debug, future::Future, path::PathBuf};
+
+use anyhow::{Context as _, Result};
+use futures::{
+    channel::{mpsc, oneshot},
+    stream::SelectAll,
+};
+use gpui::{AppContext, Model, Task};
+use jupyter_protocol::{ExecutionState, JupyterMessage, KernelInfoReply};
+use project::{kernel_store, Project};
+use runtimelib::JupyterKernelspec;
+
+use super::{JupyterMessageChannel, RunningKernel};
+
+/// A kernel on the host of an SSH project.
+#[derive(Debug, Clone)]
+pub struct SshKernelSpecification {
+    pub name: String,
+    pub path: PathBuf,
+    pub kernelspec: JupyterKernelspec,
+}
+
+impl PartialEq for SshKernelSpecification {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.path == other.path
+    }
+}
+
+impl Eq for SshKernelSpecification {}
+
+impl From<kernel_store::KernelSpecification> for SshKernelSpecification {
+    fn from(specification: kernel_store::KernelSpecification) -> Self {
+        Self {
+            name: specification.name,
+            path: specification.path,
+            kernelspec: specification.kernelspec,
+        }
+    }
+}
+
+impl From<SshKernelSpecification> for kernel_store::KernelSpecification {
+    fn from(specification: SshKernelSpecification) -> Self {
+        Self {
+            name: specification.name,
+            path: specification.path,
+            kernelspec: specification.kernelspec,
+        }
+    }
+}
+
+/// A kernel running on the host of an SSH project. The remote server launches it, and relays its
+/// messages over the SSH connection.
+pub struct SshRunningKernel {
+    pub working_directory: PathBuf,
+    pub request_tx: mpsc::Sender<JupyterMessage>,
+    pub execution_state: ExecutionState,
+    pub kernel_info: Option<KernelInfoReply>,
+    stopped: Option<oneshot::Receiver<Option<String>>>,
+}
+
+impl Debug for SshRunningKernel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshRunningKernel")
+            .field("working_directory", &self.working_directory)
+            .finish()
+    }
+}
+
+impl SshRunningKernel {
+    pub fn new(
+        kernel_specification: SshKernelSpecification,
+        working_directory: PathBuf,
+        project: &Model<Project>,
+        cx: &mut AppContext,
+    ) -> Task<Result<(Self, JupyterMessageChannel)>> {
+        let Some(kernel_store) = project.read(cx).kernel_store().cloned() else {
+            return Task::ready(Err(anyhow::anyhow!("Not an SSH project")));
+        };
+        let connection = kernel_store.update(cx, |kernel_store, cx| {
+            kernel_store.start_kernel(kernel_specification.into(), working_directory.clone(), cx)
+        });
+
+        cx.spawn(|_| async move {
+            let connection = connection
+                .await
+                .context("failed to start the kernel on the remote host")?;
+
+            let mut messages_rx = SelectAll::new();
+            messages_rx.push(connection.messages);
+
+            anyhow::Ok((
+                Self {
+                    working_directory,
+                    request_tx: connection.request_tx,
+                    execution_state: ExecutionState::Idle,
+                    kernel_info: None,
+                    stopped: Some(connection.stopped),
+                },
+                messages_rx,
+            ))
+        })
+    }
+
+    /// Resolves when the kernel stops on the remote host, with an error unless it exited
+    /// successfully.
+    pub fn stopped(&mut self) -> impl Future<Output = Option<String>> {
+        let stopped = self.stopped.take();
+        async move {
+            match stopped {
+                Some(stopped) => stopped.await.unwrap_or(None),
+                None => futures::future::pending().await,
+            }
+        }
+    }
+}
+
+impl RunningKernel for SshRunningKernel {
+    fn request_tx(&self) -> mpsc::Sender<JupyterMessage> {
+        self.request_tx.clone()
+    }
+
+    fn working_directory(&self) -> &PathBuf {
+        &self.working_directory
+    }
+
+    fn execution_state(&self) -> &ExecutionState {
+        &self.execution_state
+    }
+
+    fn set_execution_state(&mut self, state: ExecutionState) {
+        self.execution_state = state;
+    }
+
+    fn kernel_info(&self) -> Option<&KernelInfoReply> {
+        self.kernel_info.as_ref()
+    }
+
+    fn set_kernel_info(&mut self, info: KernelInfoReply) {
+        self.kernel_info = Some(info);
+    }
+
+    fn force_shutdown(&mut self) -> anyhow::Result<()> {
+        // Closing the channel shuts the kernel down on the remote host
+        self.request_tx.close_channel();
+        Ok(())
+    }
+
+    fn connection_path(&self) -> Option<&PathBuf> {
+        // The connection file is on the remote host
+        None
+    }
+}
+
+impl Drop for SshRunningKernel {
+    fn drop(&mut self) {
+        self.request_tx.close_channel();
+    }
+}
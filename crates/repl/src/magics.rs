@@ -0,0 +1,159 @@
+//! # IPython Magics
+//!
+//! IPython kernels run magics, like `%timeit` or `%%bash`, instead of Python code. Line magics
+//! start with `%` and use the rest of their line, and cell magics start with `%%` on the first
+//! line of a cell and use the whole cell. Neither is valid Python, so they're highlighted on
+//! their own, hidden from language servers and completed from a list of common ones.
+
+use std::ops::Range;
+
+use editor::Editor;
+use gpui::{AppContext, HighlightStyle, ViewContext};
+use ui::ActiveTheme as _;
+
+/// Magics common enough to complete before the kernel is asked, with what they do.
+pub(crate) const COMMON_MAGICS: &[(&str, &str)] = &[
+    ("%autoreload", "Reload modules before running code"),
+    ("%cd", "Change the working directory"),
+    ("%conda", "Run conda in the kernel's environment"),
+    ("%debug", "Start the debugger on the last exception"),
+    ("%env", "Get or set environment variables"),
+    ("%history", "Print the input history"),
+    ("%load", "Load code from a file into the cell"),
+    ("%load_ext", "Load an IPython extension"),
+    ("%matplotlib", "Set up matplotlib's backend"),
+    ("%pip", "Run pip in the kernel's environment"),
+    ("%pwd", "Print the working directory"),
+    ("%reset", "Remove all the names you defined"),
+    ("%run", "Run a Python script"),
+    ("%time", "Time one execution of a statement"),
+    ("%timeit", "Time many executions of a statement"),
+    ("%who", "List the variables you defined"),
+    ("%whos", "List the variables you defined, with their types"),
+    ("%%bash", "Run the cell with bash"),
+    ("%%capture", "Capture the cell's output"),
+    ("%%html", "Render the cell as HTML"),
+    ("%%javascript", "Run the cell as JavaScript"),
+    ("%%latex", "Render the cell as LaTeX"),
+    ("%%markdown", "Render the cell as Markdown"),
+    ("%%sh", "Run the cell with sh"),
+    ("%%time", "Time one execution of the cell"),
+    ("%%timeit", "Time many executions of the cell"),
+    ("%%writefile", "Write the cell to a file"),
+];
+
+/// Marks the magics highlighted in an editor.
+enum MagicHighlight {}
+
+/// The ranges of the magics' names in `text`, like `%timeit` in `%timeit sum(range(100))`.
+/// A magic has to be the first thing on its line.
+pub(crate) fn magic_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let indent = line.len() - line.trim_start().len();
+        let rest = &line[indent..];
+        let percent_len = rest.len() - rest.trim_start_matches('%').len();
+        let name_len = rest[percent_len..]
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(rest.len() - percent_len);
+        if (1..=2).contains(&percent_len) && name_len > 0 {
+            let start = line_start + indent;
+            ranges.push(start..start + percent_len + name_len);
+        }
+        line_start += line.len() + 1;
+    }
+    ranges
+}
+
+/// Whether a cell's source starts with a cell magic, which makes the whole cell its input.
+pub(crate) fn is_cell_magic(source: &str) -> bool {
+    source
+        .lines()
+        .map(str::trim_start)
+        .find(|line| !line.is_empty())
+        .map_or(false, |line| line.starts_with("%%"))
+}
+
+/// The magic being typed at the end of `line_prefix`, the text of a line up to the cursor.
+pub(crate) fn magic_prefix(line_prefix: &str) -> Option<&str> {
+    let prefix = line_prefix.trim_start();
+    let name = prefix.trim_start_matches('%');
+    let is_magic = prefix.starts_with('%')
+        && prefix.len() - name.len() <= 2
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    is_magic.then_some(prefix)
+}
+
+/// The common magics that complete `prefix`. Cell magics are only offered on a cell's first line.
+pub(crate) fn matching_magics(
+    prefix: &str,
+    on_first_line: bool,
+) -> impl Iterator<Item = &'static (&'static str, &'static str)> + '_ {
+    COMMON_MAGICS.iter().filter(move |(name, _)| {
+        name.starts_with(prefix) && (on_first_line || !name.starts_with("%%"))
+    })
+}
+
+fn magic_highlight_style(cx: &AppContext) -> HighlightStyle {
+    let syntax = cx.theme().syntax();
+    let style = syntax.get("preproc");
+    if style == HighlightStyle::default() {
+        syntax.get("keyword")
+    } else {
+        style
+    }
+}
+
+/// Highlights the magics in an editor, replacing the ones highlighted before.
+pub(crate) fn highlight_magics(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let snapshot = editor.buffer().read(cx).snapshot(cx);
+    let ranges = magic_ranges(&snapshot.text())
+        .into_iter()
+        .map(|range| snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end))
+        .collect::<Vec<_>>();
+    if ranges.is_empty() {
+        editor.clear_highlights::<MagicHighlight>(cx);
+    } else {
+        editor.highlight_text::<MagicHighlight>(ranges, magic_highlight_style(cx), cx);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_magic_ranges() {
+        let text = "%%timeit\nx = 10 % 3\n  %matplotlib inline\n%\n%%%odd\ny = 1";
+        let magics = magic_ranges(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect::<Vec<_>>();
+        assert_eq!(magics, ["%%timeit", "%matplotlib"]);
+    }
+
+    #[test]
+    fn test_is_cell_magic() {
+        assert!(is_cell_magic("\n%%bash\nls"));
+        assert!(!is_cell_magic("%timeit f()"));
+        assert!(!is_cell_magic("x = 1\n%%bash"));
+    }
+
+    #[test]
+    fn test_matching_magics() {
+        assert_eq!(magic_prefix("    %ti"), Some("%ti"));
+        assert_eq!(magic_prefix("%%"), Some("%%"));
+        assert_eq!(magic_prefix("x = 10 %"), None);
+        assert_eq!(magic_prefix("%run script"), None);
+
+        let names = |prefix, on_first_line| {
+            matching_magics(prefix, on_first_line)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("%ti", true), ["%time", "%timeit"]);
+        assert_eq!(names("%%b", true), ["%%bash"]);
+        assert!(names("%%b", false).is_empty());
+    }
+}
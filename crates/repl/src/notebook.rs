@@ -1,4 +1,33 @@
+mod assistant_context;
+mod attachments;
+mod auto_run;
+mod blame;
 mod cell;
+mod cell_document;
+mod collab;
+mod comments;
+mod completions;
+mod console;
+mod duplicate_outputs;
+mod external_outputs;
+mod filter;
+mod find;
+mod git_status;
+mod lazy_outputs;
+mod markdown_copy;
+mod metadata_editor;
+mod nbconvert;
 mod notebook_ui;
+mod outline;
+mod output_limits;
+mod output_spill;
+mod package_install;
+mod persistence;
+mod quarto;
+mod semantics_provider;
+mod slideshow;
+mod tags;
+mod trust;
+mod unknown_fields;
 pub use cell::*;
 pub use notebook_ui::*;
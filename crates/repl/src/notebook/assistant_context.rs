@@ -0,0 +1,127 @@
+//! # Assistant Context
+//!
+//! `notebook: ask assistant` quotes the selected code cell into the assistant panel with what the
+//! model needs to explain or fix it: the notebook's language, the cell's source, and what its last
+//! run printed, including the traceback of any error. `notebook: insert assistant suggestion`
+//! brings the code block under the cursor in the assistant panel back into the cell.
+
+use nbformat::v4::Output;
+use runtimelib::MimeType;
+
+use crate::outputs::user_error::strip_ansi_codes;
+
+/// Outputs longer than this are cut down to their end, where errors are, so one noisy cell doesn't
+/// fill the model's context.
+const MAX_OUTPUT_LEN: usize = 4000;
+
+/// The text of an output, as it would be read in a terminal.
+pub(crate) fn output_text(output: &Output) -> Option<String> {
+    match output {
+        Output::Stream { text, .. } => Some(text.0.clone()),
+        Output::DisplayData(display_data) => plain_text(&display_data.data.content),
+        Output::ExecuteResult(execute_result) => plain_text(&execute_result.data.content),
+        Output::Error(error) if error.traceback.is_empty() => {
+            Some(format!("{}: {}", error.ename, error.evalue))
+        }
+        Output::Error(error) => Some(strip_ansi_codes(&error.traceback.join("\n"))),
+    }
+}
+
+fn plain_text(content: &[MimeType]) -> Option<String> {
+    content.iter().find_map(|mime_type| match mime_type {
+        MimeType::Plain(text) => Some(text.clone()),
+        _ => None,
+    })
+}
+
+fn truncate_start(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut start = text.len() - max_len;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("…\n{}", &text[start..])
+}
+
+/// The title the quoted cell is folded under in the assistant panel, e.g. `Cell [3]`.
+pub(crate) fn cell_context_title(execution_count: Option<i32>) -> String {
+    match execution_count {
+        Some(count) => format!("Cell [{count}]"),
+        None => "Cell".to_string(),
+    }
+}
+
+/// Describes a code cell and its outputs as markdown for the assistant.
+pub(crate) fn cell_context(
+    language: Option<&str>,
+    execution_count: Option<i32>,
+    source: &str,
+    outputs: &[Output],
+) -> String {
+    let fence_language = language.unwrap_or_default().to_lowercase();
+    let mut context = match language {
+        Some(language) => format!(
+            "{} of a {language} notebook:\n\n",
+            cell_context_title(execution_count)
+        ),
+        None => format!("{} of a notebook:\n\n", cell_context_title(execution_count)),
+    };
+    context.push_str(&format!(
+        "```{fence_language}\n{}\n```\n",
+        source.trim_end()
+    ));
+
+    let output = outputs
+        .iter()
+        .filter_map(output_text)
+        .map(|text| text.trim_end().to_string())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !output.is_empty() {
+        context.push_str(&format!(
+            "\nOutput:\n\n```\n{}\n```\n",
+            truncate_start(&output, MAX_OUTPUT_LEN)
+        ));
+    }
+
+    context
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_cell_context_includes_outputs_and_tracebacks() {
+        let outputs: Vec<Output> = serde_json::from_value(json!([
+            {"output_type": "stream", "name": "stdout", "text": "loading\n"},
+            {
+                "output_type": "error",
+                "ename": "KeyError",
+                "evalue": "'price'",
+                "traceback": ["\u{1b}[0;31mKeyError\u{1b}[0m: 'price'"]
+            }
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            cell_context(Some("Python"), Some(3), "df['price']\n", &outputs),
+            "Cell [3] of a Python notebook:\n\n```python\ndf['price']\n```\n\nOutput:\n\n```\nloading\nKeyError: 'price'\n```\n"
+        );
+        assert_eq!(
+            cell_context(None, None, "x = 1", &[]),
+            "Cell of a notebook:\n\n```\nx = 1\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_long_outputs_keep_their_end() {
+        let text = format!("{}end", "é".repeat(10));
+        assert_eq!(truncate_start(&text, 6), "…\néend");
+    }
+}
@@ -0,0 +1,138 @@
+//! # Markdown Attachments
+//!
+//! Jupyter saves images pasted into a markdown cell in the cell's `attachments`, keyed by name and
+//! then by MIME type, and references them from the source with an `attachment:` URL:
+//!
+//! ```json
+//! "attachments": { "plot.png": { "image/png": "iVBORw0KGgo…" } },
+//! "source": ["![plot.png](attachment:plot.png)"]
+//! ```
+//!
+//! Images are rendered where they're on a line of their own, which is how Jupyter inserts them.
+//! The rest of the source is rendered as markdown around them.
+
+use collections::HashMap;
+use serde_json::Value;
+
+/// A part of a markdown cell's source, split around the lines showing attached images.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum MarkdownSegment {
+    Markdown(String),
+    Attachment { name: String, alt: String },
+}
+
+/// Reads the base64 encoded images of a cell's attachments, by attachment name.
+pub(crate) fn attachment_images(attachments: &Value) -> HashMap<String, String> {
+    let Some(attachments) = attachments.as_object() else {
+        return HashMap::default();
+    };
+
+    attachments
+        .iter()
+        .filter_map(|(name, bundle)| {
+            let (_, data) = bundle
+                .as_object()?
+                .iter()
+                .find(|(mime_type, _)| mime_type.starts_with("image/"))?;
+            Some((name.clone(), data.as_str()?.to_string()))
+        })
+        .collect()
+}
+
+/// Splits markdown source into the lines that show attached images and the markdown between them.
+/// Lines in fenced code blocks are left alone.
+pub(crate) fn split_attachments(source: &str) -> Vec<MarkdownSegment> {
+    let mut segments = Vec::new();
+    let mut markdown = String::new();
+    let mut in_fence = false;
+
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        let images = (!in_fence).then(|| attachment_line(trimmed)).flatten();
+        match images {
+            Some(images) => {
+                if !markdown.trim().is_empty() {
+                    segments.push(MarkdownSegment::Markdown(std::mem::take(&mut markdown)));
+                }
+                markdown.clear();
+                segments.extend(
+                    images
+                        .into_iter()
+                        .map(|(alt, name)| MarkdownSegment::Attachment { name, alt }),
+                );
+            }
+            None => markdown.push_str(line),
+        }
+    }
+    if !markdown.trim().is_empty() {
+        segments.push(MarkdownSegment::Markdown(markdown));
+    }
+
+    segments
+}
+
+/// Parses a line made up only of images with `attachment:` URLs, returning their alt text and
+/// attachment names.
+fn attachment_line(line: &str) -> Option<Vec<(String, String)>> {
+    let mut images = Vec::new();
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        let after_bang = rest.strip_prefix("![")?;
+        let (alt, after_alt) = after_bang.split_once("](")?;
+        let after_scheme = after_alt.strip_prefix("attachment:")?;
+        let (target, after_target) = after_scheme.split_once(')')?;
+        // The URL can be followed by a title, as in `(attachment:plot.png "Plot")`
+        let name = target.split_whitespace().next()?;
+
+        images.push((alt.to_string(), name.to_string()));
+        rest = after_target.trim_start();
+    }
+
+    (!images.is_empty()).then_some(images)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_attachments() {
+        let source = "# Results\n\n![plot.png](attachment:plot.png)\nThe plot above.\n";
+        assert_eq!(
+            split_attachments(source),
+            vec![
+                MarkdownSegment::Markdown("# Results\n\n".into()),
+                MarkdownSegment::Attachment {
+                    name: "plot.png".into(),
+                    alt: "plot.png".into(),
+                },
+                MarkdownSegment::Markdown("The plot above.\n".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attachments_in_code_blocks_and_text_are_left_alone() {
+        let source = "```\n![a](attachment:a.png)\n```\nSee ![b](attachment:b.png) here\n";
+        assert_eq!(
+            split_attachments(source),
+            vec![MarkdownSegment::Markdown(source.into())]
+        );
+    }
+
+    #[test]
+    fn test_attachment_images() {
+        let attachments = serde_json::json!({
+            "plot.png": { "image/png": "iVBORw0KGgo=" },
+            "notes.txt": { "text/plain": "hello" },
+        });
+        let images = attachment_images(&attachments);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images["plot.png"], "iVBORw0KGgo=");
+    }
+}
@@ -0,0 +1,74 @@
+//! # Auto-Run
+//!
+//! Dashboard-style notebooks are meant to be looked at with fresh outputs. A notebook can opt in
+//! to starting its kernel and running all of its cells as soon as it's opened, which is saved in
+//! its metadata:
+//!
+//! ```json
+//! "metadata": { "zed": { "auto_run": true } }
+//! ```
+//!
+//! Since that runs code without asking, it's only done for notebooks the user trusted, or saved
+//! in Zed, as they are. A notebook that was changed elsewhere, or downloaded with the flag set,
+//! doesn't run until it's trusted, see [`super::trust`].
+
+use serde_json::{Map, Value};
+
+/// Whether a notebook's metadata opts in to running all cells when it's opened.
+pub(crate) fn auto_run(metadata: &Value) -> bool {
+    metadata
+        .get("zed")
+        .and_then(|zed| zed.get("auto_run"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Opts a notebook's metadata in or out of running all cells when it's opened. Opting out
+/// removes the flag, and the `zed` key when nothing else is kept there.
+pub(crate) fn set_auto_run(metadata: &mut Value, auto_run: bool) {
+    let Some(metadata) = metadata.as_object_mut() else {
+        return;
+    };
+    if auto_run {
+        let zed = metadata
+            .entry("zed")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !zed.is_object() {
+            *zed = Value::Object(Map::new());
+        }
+        if let Some(zed) = zed.as_object_mut() {
+            zed.insert("auto_run".into(), Value::Bool(true));
+        }
+    } else if let Some(zed) = metadata.get_mut("zed").and_then(Value::as_object_mut) {
+        zed.remove("auto_run");
+        if zed.is_empty() {
+            metadata.remove("zed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_set_auto_run() {
+        let mut metadata = json!({ "kernelspec": { "name": "python3" } });
+        assert!(!auto_run(&metadata));
+
+        set_auto_run(&mut metadata, true);
+        assert!(auto_run(&metadata));
+        assert_eq!(metadata["zed"], json!({ "auto_run": true }));
+
+        metadata["zed"]["other"] = json!(1);
+        set_auto_run(&mut metadata, false);
+        assert!(!auto_run(&metadata));
+        assert_eq!(metadata["zed"], json!({ "other": 1 }));
+
+        metadata["zed"] = json!({ "auto_run": true });
+        set_auto_run(&mut metadata, false);
+        assert_eq!(metadata, json!({ "kernelspec": { "name": "python3" } }));
+    }
+}
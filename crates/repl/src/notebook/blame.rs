@@ -0,0 +1,272 @@
+//! # Cell Blame
+//!
+//! `git blame` works on the lines of the notebook's JSON, where a cell's source is a few lines
+//! among its outputs and metadata. The notebook is serialized as it would be saved and blamed, and
+//! only the lines holding the cell's source are kept, so the commits listed for a cell are the ones
+//! that changed its code rather than its outputs. Lines that haven't been committed are blamed on
+//! no commit, and listed first.
+
+use std::{ops::Range, rc::Rc};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use git::{blame::Blame, Oid};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Task, WindowContext,
+};
+use nbformat::v4::CellId;
+use ui::prelude::*;
+
+/// Loads the commits that touched a cell's source, as shown by [`CellBlameView`].
+pub type BlameLoader = Rc<dyn Fn(&CellId, &mut WindowContext) -> Task<Result<Vec<CellCommit>>>>;
+
+/// A commit that last touched some of a cell's lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellCommit {
+    /// `None` for lines that haven't been committed yet.
+    pub(crate) sha: Option<Oid>,
+    pub(crate) author: Option<String>,
+    pub(crate) time: Option<DateTime<Utc>>,
+    pub(crate) message: Option<String>,
+    pub(crate) permalink: Option<String>,
+    pub(crate) line_count: u32,
+}
+
+/// The lines of `json` that `range` spans.
+pub(crate) fn json_lines(json: &str, range: Range<usize>) -> Range<u32> {
+    let line_of = |offset: usize| json[..offset].matches('\n').count() as u32;
+    line_of(range.start)..line_of(range.end) + 1
+}
+
+/// The commits that last touched `lines`, with the lines that aren't committed first and the rest
+/// from the most recent.
+pub(crate) fn cell_commits(blame: &Blame, lines: Range<u32>) -> Vec<CellCommit> {
+    let mut commits = Vec::<CellCommit>::new();
+    for entry in &blame.entries {
+        let overlap = entry.range.start.max(lines.start)..entry.range.end.min(lines.end);
+        if overlap.is_empty() {
+            continue;
+        }
+        let line_count = overlap.end - overlap.start;
+        let sha = Some(entry.sha).filter(|sha| sha.as_bytes().iter().any(|byte| *byte != 0));
+        if let Some(commit) = commits.iter_mut().find(|commit| commit.sha == sha) {
+            commit.line_count += line_count;
+            continue;
+        }
+        commits.push(CellCommit {
+            sha,
+            author: entry.author.clone(),
+            time: entry
+                .author_time
+                .and_then(|time| DateTime::from_timestamp(time, 0)),
+            message: sha
+                .and_then(|sha| blame.messages.get(&sha).cloned())
+                .or_else(|| entry.summary.clone()),
+            permalink: sha
+                .and_then(|sha| blame.permalinks.get(&sha))
+                .map(|url| url.to_string()),
+            line_count,
+        });
+    }
+    commits.sort_by(|a, b| match (a.sha, b.sha) {
+        (None, Some(_)) => std::cmp::Ordering::Less,
+        (Some(_), None) => std::cmp::Ordering::Greater,
+        _ => b.time.cmp(&a.time),
+    });
+    commits
+}
+
+/// The commits that touched a cell, shown in a popover from its gutter.
+pub struct CellBlameView {
+    commits: Option<Result<Vec<CellCommit>, SharedString>>,
+    focus_handle: FocusHandle,
+    _load_task: Task<()>,
+}
+
+impl CellBlameView {
+    pub fn new(load: Task<Result<Vec<CellCommit>>>, cx: &mut ViewContext<Self>) -> Self {
+        let load_task = cx.spawn(|this, mut cx| async move {
+            let commits = load.await.map_err(|error| error.to_string().into());
+            this.update(&mut cx, |this, cx| {
+                this.commits = Some(commits);
+                cx.notify();
+            })
+            .ok();
+        });
+        Self {
+            commits: None,
+            focus_handle: cx.focus_handle(),
+            _load_task: load_task,
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_commit(&self, index: usize, commit: &CellCommit, cx: &ViewContext<Self>) -> Div {
+        let author = match (&commit.sha, &commit.author) {
+            (None, _) => "Not Committed Yet".to_string(),
+            (Some(_), Some(author)) => author.clone(),
+            (Some(_), None) => "Unknown Author".to_string(),
+        };
+        let time = commit.time.map(|time| {
+            time.with_timezone(&Local)
+                .format("%b %-d, %Y %H:%M")
+                .to_string()
+        });
+        let lines = match commit.line_count {
+            1 => "1 line".to_string(),
+            count => format!("{count} lines"),
+        };
+        let permalink = commit.permalink.clone();
+
+        v_flex()
+            .p_2()
+            .gap_1()
+            .rounded_md()
+            .border_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .justify_between()
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .min_w_0()
+                            .child(Label::new(author).size(LabelSize::Small).single_line())
+                            .children(time.map(|time| {
+                                Label::new(time).size(LabelSize::XSmall).color(Color::Muted)
+                            }))
+                            .child(
+                                Label::new(lines)
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted),
+                            ),
+                    )
+                    .children(commit.sha.map(|sha| {
+                        Button::new(("cell-blame-sha", index), sha.display_short())
+                            .label_size(LabelSize::XSmall)
+                            .color(Color::Muted)
+                            .disabled(permalink.is_none())
+                            .on_click(move |_, cx| {
+                                if let Some(permalink) = &permalink {
+                                    cx.open_url(permalink);
+                                }
+                            })
+                    })),
+            )
+            .children(commit.message.as_ref().map(|message| {
+                div()
+                    .text_size(TextSize::Small.rems(cx))
+                    .child(message.trim().to_string())
+            }))
+    }
+}
+
+impl EventEmitter<DismissEvent> for CellBlameView {}
+
+impl FocusableView for CellBlameView {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for CellBlameView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content = match &self.commits {
+            None => Label::new("Loading blame…")
+                .size(LabelSize::Small)
+                .color(Color::Muted)
+                .into_any_element(),
+            Some(Err(error)) => Label::new(error.clone())
+                .size(LabelSize::Small)
+                .color(Color::Error)
+                .into_any_element(),
+            Some(Ok(commits)) if commits.is_empty() => {
+                Label::new("The cell has no source to blame")
+                    .size(LabelSize::Small)
+                    .color(Color::Muted)
+                    .into_any_element()
+            }
+            Some(Ok(commits)) => v_flex()
+                .id("cell-blame-commits")
+                .gap_2()
+                .overflow_y_scroll()
+                .children(
+                    commits
+                        .iter()
+                        .enumerate()
+                        .map(|(index, commit)| self.render_commit(index, commit, cx)),
+                )
+                .into_any_element(),
+        };
+
+        v_flex()
+            .key_context("CellBlame")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::cancel))
+            .w(rems(24.))
+            .max_h(rems(32.))
+            .p_2()
+            .gap_2()
+            .elevation_2(cx)
+            .child(content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use git::blame::BlameEntry;
+
+    use super::*;
+
+    #[test]
+    fn test_json_lines() {
+        let json = "{\n \"source\": [\"a\\n\",\n  \"b\"]\n}";
+        let start = json.find('a').unwrap();
+        let end = json.find("b\"").unwrap() + 1;
+        assert_eq!(json_lines(json, start..end), 1..3);
+    }
+
+    #[test]
+    fn test_cell_commits() {
+        let older: Oid = "1111111111111111111111111111111111111111".parse().unwrap();
+        let newer: Oid = "2222222222222222222222222222222222222222".parse().unwrap();
+        let entry = |sha: Oid, range: Range<u32>, author: &str, time: i64| BlameEntry {
+            sha,
+            range,
+            author: Some(author.to_string()),
+            author_time: Some(time),
+            ..Default::default()
+        };
+        let blame = Blame {
+            entries: vec![
+                entry(older, 0..4, "ada", 1_000),
+                entry(newer, 4..6, "grace", 2_000),
+                entry(Oid::default(), 6..7, "Not Committed Yet", 3_000),
+                entry(older, 7..9, "ada", 1_000),
+                entry(newer, 20..30, "grace", 2_000),
+            ],
+            messages: [(newer, "Drop outliers\n\nThey skewed the mean.".to_string())]
+                .into_iter()
+                .collect(),
+            permalinks: Default::default(),
+            remote_url: None,
+        };
+
+        let commits = cell_commits(&blame, 3..8);
+        assert_eq!(
+            commits
+                .iter()
+                .map(|commit| (commit.sha, commit.line_count))
+                .collect::<Vec<_>>(),
+            [(None, 1), (Some(newer), 2), (Some(older), 2)]
+        );
+        assert_eq!(
+            commits[1].message.as_deref(),
+            Some("Drop outliers\n\nThey skewed the mean.")
+        );
+    }
+}
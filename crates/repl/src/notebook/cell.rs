@@ -1,22 +1,69 @@
 #![allow(unused, dead_code)]
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use editor::{Editor, EditorMode, MultiBuffer};
+use chrono::{DateTime, SecondsFormat, Utc};
+use client::proto;
+use collections::HashMap;
+use editor::{Editor, EditorEvent, EditorMode, MultiBuffer};
 use futures::future::Shared;
-use gpui::{prelude::*, AppContext, Hsla, Task, TextStyleRefinement, View};
+use gpui::{
+    prelude::*, AnchorCorner, AnyElement, AnyView, AppContext, EntityId, EventEmitter, Hsla, Model,
+    StyleRefinement, Subscription, Task, TextStyleRefinement, View,
+};
 use language::{Buffer, Language, LanguageRegistry};
-use markdown_preview::{markdown_parser::parse_markdown, markdown_renderer::render_markdown_block};
+use markdown_preview::markdown_parser::parse_markdown;
 use nbformat::v4::{CellId, CellMetadata, CellType};
+use runtimelib::{
+    ExecutionState, InputReply, InputRequest, JupyterMessage, JupyterMessageContent, ReplyStatus,
+};
 use settings::Settings as _;
 use theme::ThemeSettings;
-use ui::{prelude::*, IconButtonShape};
+use ui::{prelude::*, ContextMenu, IconButtonShape, PopoverMenu, PopoverMenuHandle, Tooltip};
 use util::ResultExt;
 
+use super::assistant_context::output_text;
+use super::attachments::{attachment_images, split_attachments, MarkdownSegment};
+use super::blame::{BlameLoader, CellBlameView};
+use super::collab::{cell_id_to_proto, execution_status_from_proto, execution_status_to_proto};
+use super::comments::{CellComments, CellCommentsView};
+use super::completions::is_python;
+use super::git_status::{CellGitStatus, CellOutputs};
+use super::lazy_outputs::{deferred_payloads, DeferredOutputs, DeferredPayload};
+use super::output_limits::StreamLimiter;
+use super::output_spill::{
+    output_size, read_spilled_output, spill_output, spilled_output_path, OutputSpillDir,
+    SPILL_MIN_SIZE,
+};
+use super::package_install::{
+    missing_package, reports_install_error, InstallStatus, PackageInstall, PackageManager,
+};
+use super::slideshow::{cell_slide_type, set_cell_slide_type, SlideType};
+use super::tags::{tag_chip, CellTags, CellTagsView};
 use crate::{
-    notebook::{CODE_BLOCK_INSET, GUTTER_WIDTH},
-    outputs::{plain::TerminalOutput, user_error::ErrorView, Output},
+    magics::highlight_magics,
+    notebook::{
+        AskAssistant, ClearCellOutput, CopyCellAsMarkdown, CopyCellWithOutputAsMarkdown,
+        RevertCellAndOutputsToHead, RevertCellToHead, ShowCellBlame, ToggleCellDeletable,
+        ToggleCellEditable, ToggleOutputsCollapsed, ToggleOutputsScrolled, CODE_BLOCK_INSET,
+        EXECUTION_COUNT_WIDTH, GUTTER_WIDTH, SCROLLED_OUTPUT_HEIGHT,
+    },
+    notebook_settings::NotebookSettings,
+    outputs::{
+        image::ImageView,
+        markdown::render_parsed_markdown,
+        mime_type_display_name,
+        plain::{collapse_carriage_returns, TerminalOutput},
+        user_error::ErrorView,
+        ExecutionStatus, Output,
+    },
 };
 
+/// The shortest time between two refreshes of a cell while its outputs arrive. Kernels can send
+/// hundreds of messages a second, and the messages in between are shown together.
+const OUTPUT_REFRESH_INTERVAL: Duration = Duration::from_millis(33);
+
 #[derive(Copy, Clone, PartialEq, PartialOrd)]
 pub enum CellPosition {
     First,
@@ -26,6 +73,7 @@ pub enum CellPosition {
 
 pub enum CellControlType {
     RunCell,
+    CancelQueuedCell,
     RerunCell,
     ClearCell,
     CellOptions,
@@ -37,6 +85,7 @@ impl CellControlType {
     fn icon_name(&self) -> IconName {
         match self {
             CellControlType::RunCell => IconName::Play,
+            CellControlType::CancelQueuedCell => IconName::CountdownTimer,
             CellControlType::RerunCell => IconName::ArrowCircle,
             CellControlType::ClearCell => IconName::ListX,
             CellControlType::CellOptions => IconName::Ellipsis,
@@ -59,6 +108,11 @@ impl CellControl {
             .shape(IconButtonShape::Square);
         Self { button }
     }
+
+    fn tooltip(self, text: &'static str) -> Self {
+        let button = self.button.tooltip(move |cx| Tooltip::text(text, cx));
+        Self { button }
+    }
 }
 
 impl Clickable for CellControl {
@@ -72,6 +126,56 @@ impl Clickable for CellControl {
     }
 }
 
+pub enum CellEvent {
+    /// The user asked for this cell to be executed from the cell itself, e.g. the gutter run button.
+    Run(CellId),
+    /// The user asked for a queued cell to be removed from the execution queue.
+    CancelExecution(CellId),
+    /// The user answered an input request from the kernel. Holds the reply to send on the stdin channel.
+    SubmitInput(JupyterMessage),
+    /// The cell's editor was focused, e.g. by clicking into it, so the notebook should select the cell.
+    EditorFocused(CellId),
+    /// The cell's source was edited, so its git status may have changed.
+    SourceEdited(CellId),
+    /// The user asked to restore the cell's committed version, with its outputs if
+    /// `include_outputs` is set.
+    RevertToHead {
+        cell_id: CellId,
+        include_outputs: bool,
+    },
+    /// The user asked to quote the cell and its outputs into the assistant panel.
+    AskAssistant(CellId),
+    /// The user asked to copy the cell as markdown, with the text of its outputs if
+    /// `include_output` is set.
+    CopyAsMarkdown {
+        cell_id: CellId,
+        include_output: bool,
+    },
+    /// The execution state or outputs of the cell changed, so collaborators following the notebook
+    /// should be sent them.
+    ExecutionChanged(CellId),
+    /// The user asked to install the package the cell's last run was missing.
+    InstallPackage {
+        cell_id: CellId,
+        package: String,
+        manager: PackageManager,
+    },
+    /// The user asked to load a saved output whose payloads were set aside when the notebook was
+    /// opened, see [`super::lazy_outputs`].
+    LoadOutput {
+        cell_id: CellId,
+        output_index: usize,
+    },
+}
+
+/// A pending request from the kernel for the user to type something in, e.g. from Python's `input()`.
+struct InputPrompt {
+    request: JupyterMessage,
+    prompt: String,
+    password: bool,
+    editor: View<Editor>,
+}
+
 /// A notebook cell
 #[derive(Clone)]
 pub enum Cell {
@@ -80,33 +184,604 @@ pub enum Cell {
     Raw(View<RawCell>),
 }
 
-fn convert_outputs(outputs: &Vec<nbformat::v4::Output>, cx: &mut WindowContext) -> Vec<Output> {
+/// Creates the editor a cell's source is edited in, sized to fit its contents.
+fn cell_editor<V: 'static>(
+    buffer: Model<Buffer>,
+    cell_type: CellType,
+    cx: &mut ViewContext<V>,
+) -> View<Editor> {
+    let multi_buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+
+    cx.new_view(|cx| {
+        let mut editor = Editor::new(
+            EditorMode::AutoHeight { max_lines: 1024 },
+            multi_buffer,
+            None,
+            false,
+            cx,
+        );
+
+        apply_cell_editor_settings(&mut editor, cell_type, None, cx);
+        // Cells are edited like any other code, so vim mode applies to them too
+        editor.set_use_modal_editing(true);
+        editor
+    })
+}
+
+/// Styles a cell's editor with the `notebook` settings for cell sources: their font size, how
+/// they're soft wrapped, and whether line numbers are shown in a gutter. `show_line_numbers`
+/// overrides the setting for the cell's notebook.
+pub(crate) fn apply_cell_editor_settings(
+    editor: &mut Editor,
+    cell_type: CellType,
+    show_line_numbers: Option<bool>,
+    cx: &mut ViewContext<Editor>,
+) {
+    let theme = ThemeSettings::get_global(cx);
+    let settings = NotebookSettings::get_global(cx);
+
+    let refinement = TextStyleRefinement {
+        font_family: Some(theme.buffer_font.family.clone()),
+        font_size: Some(
+            settings
+                .code_font_size
+                .unwrap_or(theme.buffer_font_size)
+                .into(),
+        ),
+        color: Some(cx.theme().colors().editor_foreground),
+        background_color: Some(gpui::transparent_black()),
+        ..Default::default()
+    };
+
+    let show_line_numbers = show_line_numbers.unwrap_or(settings.show_line_numbers);
+    let soft_wrap = match cell_type {
+        CellType::Markdown => settings.markdown_soft_wrap,
+        CellType::Code | CellType::Raw => settings.code_soft_wrap,
+    };
+    editor.set_soft_wrap_mode(soft_wrap, cx);
+    editor.set_show_gutter(show_line_numbers, cx);
+    if show_line_numbers {
+        editor.set_show_line_numbers(true, cx);
+        editor.set_show_git_diff_gutter(false, cx);
+        editor.set_show_code_actions(false, cx);
+        editor.set_show_runnables(false, cx);
+    }
+    editor.set_text_style_refinement(refinement);
+    cx.notify();
+}
+
+/// Creates the views of saved outputs. Outputs of notebooks that aren't trusted leave out the
+/// representations that need trust.
+fn convert_outputs(
+    outputs: &Vec<nbformat::v4::Output>,
+    trusted: bool,
+    cx: &mut WindowContext,
+) -> Vec<Output> {
     outputs
         .into_iter()
         .map(|output| match output {
             nbformat::v4::Output::Stream { text, .. } => Output::Stream {
                 content: cx.new_view(|cx| TerminalOutput::from(&text.0, cx)),
             },
-            nbformat::v4::Output::DisplayData(display_data) => {
+            nbformat::v4::Output::DisplayData(display_data) if trusted => {
                 Output::new(&display_data.data, None, cx)
             }
-            nbformat::v4::Output::ExecuteResult(execute_result) => {
+            nbformat::v4::Output::DisplayData(display_data) => {
+                Output::untrusted(&display_data.data, cx)
+            }
+            nbformat::v4::Output::ExecuteResult(execute_result) if trusted => {
                 Output::new(&execute_result.data, None, cx)
             }
-            nbformat::v4::Output::Error(error) => Output::ErrorOutput(ErrorView {
-                ename: error.ename.clone(),
-                evalue: error.evalue.clone(),
-                traceback: cx.new_view(|cx| TerminalOutput::from(&error.traceback.join("\n"), cx)),
-            }),
+            nbformat::v4::Output::ExecuteResult(execute_result) => {
+                Output::untrusted(&execute_result.data, cx)
+            }
+            nbformat::v4::Output::Error(error) => Output::ErrorOutput(ErrorView::new(
+                &error.ename,
+                &error.evalue,
+                &error.traceback,
+                cx,
+            )),
         })
         .collect()
 }
 
+/// Splits cell text into the line-per-entry form nbformat uses for sources.
+fn source_lines(text: &str) -> Vec<String> {
+    text.split_inclusive('\n')
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Converts a kernel output message into the form it is saved in the notebook file.
+fn nbformat_output(content: &JupyterMessageContent) -> Option<nbformat::v4::Output> {
+    let (output_type, output) = match content {
+        JupyterMessageContent::StreamContent(stream) => ("stream", serde_json::to_value(stream)),
+        JupyterMessageContent::DisplayData(display_data) => {
+            ("display_data", serde_json::to_value(display_data))
+        }
+        JupyterMessageContent::ExecuteResult(result) => {
+            ("execute_result", serde_json::to_value(result))
+        }
+        JupyterMessageContent::ErrorOutput(error) => ("error", serde_json::to_value(error)),
+        // Updates are saved as the display data they replace
+        JupyterMessageContent::UpdateDisplayData(update) => {
+            ("display_data", serde_json::to_value(update))
+        }
+        _ => return None,
+    };
+
+    let mut output = output.log_err()?;
+    let fields = output.as_object_mut()?;
+    fields.insert("output_type".into(), output_type.into());
+    fields.remove("transient");
+    serde_json::from_value(output).log_err()
+}
+
+/// Reads a boolean cell metadata field, which is false when unset.
+fn metadata_flag(metadata: &CellMetadata, key: &str) -> bool {
+    metadata_bool(metadata, key).unwrap_or(false)
+}
+
+/// Reads a boolean cell metadata field. Jupyter also writes `"auto"` for `scrolled`, which counts as unset.
+fn metadata_bool(metadata: &CellMetadata, key: &str) -> Option<bool> {
+    serde_json::to_value(metadata)
+        .ok()
+        .and_then(|metadata| metadata.get(key)?.as_bool())
+}
+
+/// Reads the `editable` or `deletable` cell metadata field, which Jupyter treats as true when unset.
+fn metadata_permission(metadata: &CellMetadata, key: &str) -> bool {
+    serde_json::to_value(metadata)
+        .ok()
+        .and_then(|metadata| metadata.get(key)?.as_bool())
+        .unwrap_or(true)
+}
+
+/// Removes a cell metadata field, keeping the fields nbformat doesn't know about.
+fn remove_metadata_field(metadata: &mut CellMetadata, key: &str) {
+    let Some(mut value_metadata) = serde_json::to_value(&*metadata).log_err() else {
+        return;
+    };
+    if let Some(fields) = value_metadata.as_object_mut() {
+        fields.remove(key);
+    }
+
+    if let Some(updated) = serde_json::from_value(value_metadata).log_err() {
+        *metadata = updated;
+    }
+}
+
+/// Sets a cell metadata field, keeping the fields nbformat doesn't know about.
+fn write_metadata_field(metadata: &mut CellMetadata, key: &str, value: serde_json::Value) {
+    let Some(mut value_metadata) = serde_json::to_value(&*metadata).log_err() else {
+        return;
+    };
+    let Some(fields) = value_metadata.as_object_mut() else {
+        return;
+    };
+    fields.insert(key.into(), value);
+
+    if let Some(updated) = serde_json::from_value(value_metadata).log_err() {
+        *metadata = updated;
+    }
+}
+
+fn execution_metadata(
+    metadata: &CellMetadata,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let metadata = serde_json::to_value(metadata).ok()?;
+    metadata.get("execution")?.as_object().cloned()
+}
+
+/// Reads how long the cell's last run took from the timestamps Jupyter saves in its metadata.
+fn execution_duration_from_metadata(metadata: &CellMetadata) -> Option<Duration> {
+    let execution = execution_metadata(metadata)?;
+    let timestamp = |key: &str| {
+        execution
+            .get(key)
+            .and_then(|value| value.as_str())
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+    };
+
+    let started = timestamp("iopub.status.busy").or_else(|| timestamp("iopub.execute_input"))?;
+    let finished = timestamp("iopub.status.idle").or_else(|| timestamp("shell.execute_reply"))?;
+    (finished - started).to_std().ok()
+}
+
+/// Generates an id for a new cell. nbformat 4.5 allows 1 to 64 letters, digits, `-` and `_`,
+/// which a UUID always satisfies.
+pub(crate) fn new_cell_id() -> CellId {
+    serde_json::from_value(uuid::Uuid::new_v4().to_string().into())
+        .expect("a UUID is always a valid cell id")
+}
+
+/// Replaces the id of a cell in its nbformat representation.
+pub(crate) fn set_nbformat_cell_id(cell: &mut nbformat::v4::Cell, new_id: CellId) {
+    match cell {
+        nbformat::v4::Cell::Code { id, .. }
+        | nbformat::v4::Cell::Markdown { id, .. }
+        | nbformat::v4::Cell::Raw { id, .. } => *id = new_id,
+    }
+}
+
+/// A cell as it's saved, with the outputs of a code cell shared with the cell rather than copied.
+/// Copying them can take a while when they're large, so it's left to whoever needs the
+/// nbformat cell, see [`CellSnapshot::into_nbformat_cell`].
+pub struct CellSnapshot {
+    cell: nbformat::v4::Cell,
+    outputs: Arc<Vec<nbformat::v4::Output>>,
+}
+
+impl CellSnapshot {
+    pub fn into_nbformat_cell(self) -> nbformat::v4::Cell {
+        let mut cell = self.cell;
+        if let nbformat::v4::Cell::Code { outputs, .. } = &mut cell {
+            *outputs = Arc::unwrap_or_clone(self.outputs);
+        }
+        cell
+    }
+}
+
+fn format_elapsed_time(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else if seconds >= 1 {
+        format!("{:.1}s", elapsed.as_secs_f32())
+    } else {
+        format!("{}ms", elapsed.as_millis())
+    }
+}
+
 impl Cell {
+    /// The id of the cell's view, which tells a cell apart from one that replaced it under the
+    /// same cell id.
+    pub fn entity_id(&self) -> EntityId {
+        match self {
+            Cell::Code(cell) => cell.entity_id(),
+            Cell::Markdown(cell) => cell.entity_id(),
+            Cell::Raw(cell) => cell.entity_id(),
+        }
+    }
+
+    pub fn comments(&self, cx: &AppContext) -> Model<CellComments> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).comments().clone(),
+            Cell::Markdown(cell) => cell.read(cx).comments().clone(),
+            Cell::Raw(cell) => cell.read(cx).comments().clone(),
+        }
+    }
+
+    /// Stops the cell from being edited or run, while still showing its source and outputs.
+    pub fn set_read_only(&self, read_only: bool, cx: &mut WindowContext) {
+        match self {
+            Cell::Code(cell) => cell.update(cx, |cell, cx| cell.set_read_only(read_only, cx)),
+            Cell::Markdown(cell) => cell.update(cx, |cell, cx| cell.set_read_only(read_only, cx)),
+            Cell::Raw(cell) => cell.update(cx, |cell, cx| cell.set_read_only(read_only, cx)),
+        }
+    }
+
+    /// The cell's type and the current contents of its editor.
+    pub fn current_source(&self, cx: &AppContext) -> (CellType, String) {
+        match self {
+            Cell::Code(cell) => (CellType::Code, cell.read(cx).current_source(cx)),
+            Cell::Markdown(cell) => (CellType::Markdown, cell.read(cx).current_source(cx)),
+            Cell::Raw(cell) => (CellType::Raw, cell.read(cx).current_source(cx)),
+        }
+    }
+
+    /// The execution count and outputs of a code cell, to compare with the committed ones.
+    pub fn git_outputs(&self, cx: &AppContext) -> Option<CellOutputs> {
+        match self {
+            Cell::Code(cell) => {
+                let cell = cell.read(cx);
+                Some(CellOutputs::new(
+                    cell.execution_count,
+                    &cell.nbformat_outputs,
+                ))
+            }
+            Cell::Markdown(_) | Cell::Raw(_) => None,
+        }
+    }
+
+    /// The text of each of a code cell's outputs, searched by the find bar when it searches
+    /// outputs.
+    pub fn output_texts(&self, cx: &AppContext) -> Option<Vec<Option<String>>> {
+        match self {
+            Cell::Code(cell) => Some(
+                cell.read(cx)
+                    .nbformat_outputs
+                    .iter()
+                    .map(output_text)
+                    .collect(),
+            ),
+            Cell::Markdown(_) | Cell::Raw(_) => None,
+        }
+    }
+
+    pub fn cell_type(&self) -> CellType {
+        match self {
+            Cell::Code(_) => CellType::Code,
+            Cell::Markdown(_) => CellType::Markdown,
+            Cell::Raw(_) => CellType::Raw,
+        }
+    }
+
+    /// The editor of the cell's source. Markdown cells only show theirs while they're edited.
+    pub fn editor(&self, cx: &AppContext) -> View<Editor> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).editor().clone(),
+            Cell::Markdown(cell) => cell.read(cx).editor().clone(),
+            Cell::Raw(cell) => cell.read(cx).editor().clone(),
+        }
+    }
+
+    /// The buffer holding the cell's source.
+    pub fn buffer(&self, cx: &AppContext) -> Option<Model<Buffer>> {
+        self.editor(cx).read(cx).buffer().read(cx).as_singleton()
+    }
+
+    /// Marks the cell in its gutter as added or modified since HEAD.
+    pub fn set_git_status(&self, status: Option<CellGitStatus>, cx: &mut WindowContext) {
+        match self {
+            Cell::Code(cell) => cell.update(cx, |cell, cx| cell.set_git_status(status, cx)),
+            Cell::Markdown(cell) => cell.update(cx, |cell, cx| cell.set_git_status(status, cx)),
+            Cell::Raw(cell) => cell.update(cx, |cell, cx| cell.set_git_status(status, cx)),
+        }
+    }
+
+    /// Sets how the commits that touched the cell are loaded, or `None` when it isn't tracked.
+    pub fn set_blame_loader(&self, loader: Option<BlameLoader>, cx: &mut WindowContext) {
+        match self {
+            Cell::Code(cell) => cell.update(cx, |cell, cx| cell.set_blame_loader(loader, cx)),
+            Cell::Markdown(cell) => cell.update(cx, |cell, cx| cell.set_blame_loader(loader, cx)),
+            Cell::Raw(cell) => cell.update(cx, |cell, cx| cell.set_blame_loader(loader, cx)),
+        }
+    }
+
+    pub fn has_blame_loader(&self, cx: &AppContext) -> bool {
+        match self {
+            Cell::Code(cell) => cell.read(cx).blame_loader().is_some(),
+            Cell::Markdown(cell) => cell.read(cx).blame_loader().is_some(),
+            Cell::Raw(cell) => cell.read(cx).blame_loader().is_some(),
+        }
+    }
+
+    pub fn blame_menu_handle(&self, cx: &AppContext) -> PopoverMenuHandle<CellBlameView> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).blame_menu_handle().clone(),
+            Cell::Markdown(cell) => cell.read(cx).blame_menu_handle().clone(),
+            Cell::Raw(cell) => cell.read(cx).blame_menu_handle().clone(),
+        }
+    }
+
+    pub fn comments_menu_handle(&self, cx: &AppContext) -> PopoverMenuHandle<CellCommentsView> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).comments_menu_handle().clone(),
+            Cell::Markdown(cell) => cell.read(cx).comments_menu_handle().clone(),
+            Cell::Raw(cell) => cell.read(cx).comments_menu_handle().clone(),
+        }
+    }
+
+    /// Whether the cell's source can be changed, from its `editable` metadata.
+    pub fn is_editable(&self, cx: &AppContext) -> bool {
+        match self {
+            Cell::Code(cell) => cell.read(cx).is_editable(),
+            Cell::Markdown(cell) => cell.read(cx).is_editable(),
+            Cell::Raw(cell) => cell.read(cx).is_editable(),
+        }
+    }
+
+    /// Whether the cell can be removed from the notebook, from its `deletable` metadata.
+    pub fn is_deletable(&self, cx: &AppContext) -> bool {
+        match self {
+            Cell::Code(cell) => cell.read(cx).is_deletable(),
+            Cell::Markdown(cell) => cell.read(cx).is_deletable(),
+            Cell::Raw(cell) => cell.read(cx).is_deletable(),
+        }
+    }
+
+    pub fn set_editable(&self, editable: bool, cx: &mut WindowContext) {
+        match self {
+            Cell::Code(cell) => cell.update(cx, |cell, cx| cell.set_editable(editable, cx)),
+            Cell::Markdown(cell) => cell.update(cx, |cell, cx| cell.set_editable(editable, cx)),
+            Cell::Raw(cell) => cell.update(cx, |cell, cx| cell.set_editable(editable, cx)),
+        }
+    }
+
+    pub fn set_deletable(&self, deletable: bool, cx: &mut WindowContext) {
+        match self {
+            Cell::Code(cell) => cell.update(cx, |cell, cx| cell.set_deletable(deletable, cx)),
+            Cell::Markdown(cell) => cell.update(cx, |cell, cx| cell.set_deletable(deletable, cx)),
+            Cell::Raw(cell) => cell.update(cx, |cell, cx| cell.set_deletable(deletable, cx)),
+        }
+    }
+
+    /// The part the cell plays in a slideshow, from its `slideshow` metadata.
+    pub fn slide_type(&self, cx: &AppContext) -> Option<SlideType> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).slide_type(),
+            Cell::Markdown(cell) => cell.read(cx).slide_type(),
+            Cell::Raw(cell) => cell.read(cx).slide_type(),
+        }
+    }
+
+    pub fn tags(&self, cx: &AppContext) -> Model<CellTags> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).tags().clone(),
+            Cell::Markdown(cell) => cell.read(cx).tags().clone(),
+            Cell::Raw(cell) => cell.read(cx).tags().clone(),
+        }
+    }
+
+    pub fn tags_menu_handle(&self, cx: &AppContext) -> PopoverMenuHandle<CellTagsView> {
+        match self {
+            Cell::Code(cell) => cell.read(cx).tags_menu_handle().clone(),
+            Cell::Markdown(cell) => cell.read(cx).tags_menu_handle().clone(),
+            Cell::Raw(cell) => cell.read(cx).tags_menu_handle().clone(),
+        }
+    }
+
+    /// Creates the nbformat representation of a new, empty cell with a freshly generated id.
+    pub fn empty_nbformat_cell(cell_type: CellType) -> nbformat::v4::Cell {
+        let id = new_cell_id();
+        let cell = match cell_type {
+            CellType::Code => serde_json::json!({
+                "cell_type": "code",
+                "id": id,
+                "metadata": {},
+                "execution_count": null,
+                "source": [],
+                "outputs": [],
+            }),
+            CellType::Markdown => serde_json::json!({
+                "cell_type": "markdown",
+                "id": id,
+                "metadata": {},
+                "source": [],
+            }),
+            CellType::Raw => serde_json::json!({
+                "cell_type": "raw",
+                "id": id,
+                "metadata": {},
+                "source": [],
+            }),
+        };
+
+        serde_json::from_value(cell).expect("an empty cell is always valid nbformat")
+    }
+
+    /// Serializes cells for the clipboard as the list of cells found in `.ipynb` files, so they
+    /// can be pasted into other Jupyter frontends too.
+    pub fn clipboard_text(cells: &[nbformat::v4::Cell]) -> Option<String> {
+        serde_json::to_string_pretty(cells).log_err()
+    }
+
+    /// Reads cells from the clipboard, accepting a single cell or a list of cells. Each cell gets
+    /// a fresh id, so pasting the same cells twice doesn't create duplicate ids.
+    pub fn nbformat_cells_from_clipboard(text: &str) -> Option<Vec<nbformat::v4::Cell>> {
+        let values = match serde_json::from_str::<serde_json::Value>(text).ok()? {
+            serde_json::Value::Array(values) => values,
+            value @ serde_json::Value::Object(_) => vec![value],
+            _ => return None,
+        };
+        if values.is_empty() {
+            return None;
+        }
+
+        values
+            .into_iter()
+            .map(|mut value| {
+                let cell = value.as_object_mut()?;
+                cell.get("cell_type")?;
+                cell.insert("id".into(), serde_json::to_value(new_cell_id()).ok()?);
+                serde_json::from_value(value).ok()
+            })
+            .collect()
+    }
+
+    /// Converts the cell into nbformat as a cell of another type, keeping its id, metadata and
+    /// source. Outputs and the execution count are dropped when converting away from code.
+    pub fn to_nbformat_cell_of_type(
+        &self,
+        cell_type: CellType,
+        cx: &AppContext,
+    ) -> nbformat::v4::Cell {
+        let (id, metadata, source) = match self.to_nbformat_cell(cx) {
+            nbformat::v4::Cell::Code {
+                id,
+                metadata,
+                source,
+                ..
+            }
+            | nbformat::v4::Cell::Markdown {
+                id,
+                metadata,
+                source,
+                ..
+            }
+            | nbformat::v4::Cell::Raw {
+                id,
+                metadata,
+                source,
+            } => (id, metadata, source),
+        };
+
+        match cell_type {
+            CellType::Code => nbformat::v4::Cell::Code {
+                id,
+                metadata,
+                execution_count: None,
+                source,
+                outputs: Vec::new(),
+            },
+            CellType::Markdown => nbformat::v4::Cell::Markdown {
+                id,
+                metadata,
+                source,
+                attachments: None,
+            },
+            CellType::Raw => nbformat::v4::Cell::Raw {
+                id,
+                metadata,
+                source,
+            },
+        }
+    }
+
+    /// Converts the cell back into nbformat for saving, using the current contents of its editor.
+    pub fn to_nbformat_cell(&self, cx: &AppContext) -> nbformat::v4::Cell {
+        self.snapshot(cx).into_nbformat_cell()
+    }
+
+    /// Takes what [`Self::to_nbformat_cell`] saves, without copying the outputs.
+    pub fn snapshot(&self, cx: &AppContext) -> CellSnapshot {
+        let outputs = match self {
+            Cell::Code(cell) => cell.read(cx).nbformat_outputs.clone(),
+            Cell::Markdown(_) | Cell::Raw(_) => Arc::default(),
+        };
+        let cell = match self {
+            Cell::Code(cell) => {
+                let cell = cell.read(cx);
+                nbformat::v4::Cell::Code {
+                    id: cell.id.clone(),
+                    metadata: cell.saved_metadata(cx),
+                    execution_count: cell.execution_count,
+                    source: source_lines(&cell.current_source(cx)),
+                    outputs: Vec::new(),
+                }
+            }
+            Cell::Markdown(cell) => {
+                let cell = cell.read(cx);
+                nbformat::v4::Cell::Markdown {
+                    id: cell.id.clone(),
+                    metadata: cell.saved_metadata(cx),
+                    source: source_lines(&cell.current_source(cx)),
+                    attachments: cell
+                        .attachments
+                        .clone()
+                        .and_then(|attachments| serde_json::from_value(attachments).log_err()),
+                }
+            }
+            Cell::Raw(cell) => {
+                let cell = cell.read(cx);
+                nbformat::v4::Cell::Raw {
+                    id: cell.id.clone(),
+                    metadata: cell.saved_metadata(cx),
+                    source: source_lines(&cell.current_source(cx)),
+                }
+            }
+        };
+        CellSnapshot { cell, outputs }
+    }
+
+    /// Creates a cell from its saved form. `trusted` is whether the notebook is trusted, which
+    /// decides how saved outputs are shown.
     pub fn load(
         cell: &nbformat::v4::Cell,
         languages: &Arc<LanguageRegistry>,
         notebook_language: Shared<Task<Option<Arc<Language>>>>,
+        trusted: bool,
         cx: &mut WindowContext,
     ) -> Self {
         match cell {
@@ -114,40 +789,82 @@ impl Cell {
                 id,
                 metadata,
                 source,
-                attachments: _,
+                attachments,
             } => {
                 let source = source.join("");
+                let attachments = serde_json::to_value(attachments)
+                    .log_err()
+                    .filter(|attachments| !attachments.is_null());
 
                 let view = cx.new_view(|cx| {
-                    let markdown_parsing_task = {
-                        let languages = languages.clone();
-                        let source = source.clone();
-
-                        cx.spawn(|this, mut cx| async move {
-                            let parsed_markdown = cx
-                                .background_executor()
-                                .spawn(async move {
-                                    parse_markdown(&source, None, Some(languages)).await
-                                })
-                                .await;
+                    let attachment_images = attachments
+                        .as_ref()
+                        .map(attachment_images)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|(name, data)| {
+                            let image = ImageView::from(&data).log_err()?;
+                            Some((name, cx.new_view(|_| image)))
+                        })
+                        .collect();
+
+                    let buffer = cx.new_model(|cx| Buffer::local(source.clone(), cx));
+                    let editor = cell_editor(buffer.clone(), CellType::Markdown, cx);
+                    let editable = metadata_permission(metadata, "editable");
+                    editor.update(cx, |editor, _| editor.set_read_only(!editable));
+
+                    let cell_id = id.clone();
+                    let editor_subscription = cx.subscribe(
+                        &editor,
+                        move |this, _, event: &EditorEvent, cx| match event {
+                            EditorEvent::Focused => {
+                                cx.emit(CellEvent::EditorFocused(cell_id.clone()))
+                            }
+                            EditorEvent::Blurred => this.finish_editing(cx),
+                            EditorEvent::BufferEdited => {
+                                cx.emit(CellEvent::SourceEdited(cell_id.clone()))
+                            }
+                            _ => {}
+                        },
+                    );
 
-                            this.update(&mut cx, |cell: &mut MarkdownCell, _| {
-                                cell.parsed_markdown = Some(parsed_markdown);
-                            })
-                            .log_err();
+                    let language_task = {
+                        let languages = languages.clone();
+                        cx.spawn(|_, mut cx| async move {
+                            let language = languages.language_for_name("Markdown").await.log_err();
+                            buffer
+                                .update(&mut cx, |buffer, cx| buffer.set_language(language, cx))
+                                .log_err();
                         })
                     };
 
-                    MarkdownCell {
-                        markdown_parsing_task,
+                    let mut cell = MarkdownCell {
+                        markdown_parsing_task: Task::ready(()),
                         languages: languages.clone(),
                         id: id.clone(),
                         metadata: metadata.clone(),
+                        modified: false,
                         source: source.clone(),
-                        parsed_markdown: None,
+                        attachments,
+                        attachment_images,
+                        rendered_markdown: None,
+                        editor,
+                        editing: false,
+                        _editor_subscription: editor_subscription,
+                        language_task,
+                        comments: cx.new_model(|_| CellComments::new(metadata)),
+                        comments_menu_handle: PopoverMenuHandle::default(),
+                        tags: cx.new_model(|_| CellTags::new(metadata)),
+                        tags_menu_handle: PopoverMenuHandle::default(),
+                        read_only: false,
                         selected: false,
                         cell_position: None,
-                    }
+                        git_status: None,
+                        blame_loader: None,
+                        blame_menu_handle: PopoverMenuHandle::default(),
+                    };
+                    cell.parse_markdown(cx);
+                    cell
                 });
 
                 Cell::Markdown(view)
@@ -161,79 +878,152 @@ impl Cell {
             } => Cell::Code(cx.new_view(|cx| {
                 let text = source.join("");
 
-                let buffer = cx.new_model(|cx| Buffer::local(text.clone(), cx));
-                let multi_buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer.clone(), cx));
-
-                let editor_view = cx.new_view(|cx| {
-                    let mut editor = Editor::new(
-                        EditorMode::AutoHeight { max_lines: 1024 },
-                        multi_buffer,
-                        None,
-                        false,
-                        cx,
-                    );
-
-                    let theme = ThemeSettings::get_global(cx);
-
-                    let refinement = TextStyleRefinement {
-                        font_family: Some(theme.buffer_font.family.clone()),
-                        font_size: Some(theme.buffer_font_size.into()),
-                        color: Some(cx.theme().colors().editor_foreground),
-                        background_color: Some(gpui::transparent_black()),
-                        ..Default::default()
-                    };
-
-                    editor.set_text(text, cx);
-                    editor.set_show_gutter(false, cx);
-                    editor.set_text_style_refinement(refinement);
-
-                    // editor.set_read_only(true);
-                    editor
+                let buffer = cx.new_model(|cx| {
+                    let mut buffer = Buffer::local(text, cx);
+                    // Signature help and other documentation is highlighted with the registry
+                    buffer.set_language_registry(languages.clone());
+                    buffer
                 });
-
-                let buffer = buffer.clone();
-                let language_task = cx.spawn(|this, mut cx| async move {
-                    let language = notebook_language.await;
-
-                    buffer.update(&mut cx, |buffer, cx| {
-                        buffer.set_language(language.clone(), cx);
-                    });
+                let editor_view = cell_editor(buffer.clone(), CellType::Code, cx);
+                let editable = metadata_permission(metadata, "editable");
+                editor_view.update(cx, |editor, _| editor.set_read_only(!editable));
+
+                let cell_id = id.clone();
+                let editor_subscription = cx.subscribe(
+                    &editor_view,
+                    move |this: &mut CodeCell, _, event, cx| match event {
+                        EditorEvent::Focused => cx.emit(CellEvent::EditorFocused(cell_id.clone())),
+                        EditorEvent::BufferEdited => {
+                            this.highlight_magics(cx);
+                            cx.emit(CellEvent::SourceEdited(cell_id.clone()));
+                        }
+                        _ => {}
+                    },
+                );
+
+                let language_task = cx.spawn({
+                    let buffer = buffer.clone();
+                    |this, mut cx| async move {
+                        let language = notebook_language.await;
+
+                        buffer.update(&mut cx, |buffer, cx| {
+                            buffer.set_language(language.clone(), cx);
+                        });
+                        this.update(&mut cx, |this, cx| this.highlight_magics(cx))
+                            .ok();
+                    }
                 });
 
                 CodeCell {
                     id: id.clone(),
                     metadata: metadata.clone(),
+                    modified: false,
                     execution_count: *execution_count,
                     source: source.join(""),
+                    buffer,
                     editor: editor_view,
-                    outputs: convert_outputs(outputs, cx),
+                    _editor_subscription: editor_subscription,
+                    outputs: convert_outputs(outputs, trusted, cx),
+                    nbformat_outputs: Arc::new(outputs.clone()),
+                    nbformat_display_ids: vec![None; outputs.len()],
+                    output_memory: None,
+                    stream_limiter: StreamLimiter::default(),
+                    rehydrate_task: None,
+                    execution_status: ExecutionStatus::Unknown,
+                    execution_started: None,
+                    execution_duration: execution_duration_from_metadata(metadata),
+                    elapsed_timer_task: None,
+                    last_refresh: None,
+                    refresh_task: None,
+                    input_prompt: None,
+                    package_install: None,
+                    executed_by: None,
+                    trusted,
+                    comments: cx.new_model(|_| CellComments::new(metadata)),
+                    comments_menu_handle: PopoverMenuHandle::default(),
+                    tags: cx.new_model(|_| CellTags::new(metadata)),
+                    tags_menu_handle: PopoverMenuHandle::default(),
+                    read_only: false,
                     selected: false,
                     language_task,
                     cell_position: None,
+                    git_status: None,
+                    blame_loader: None,
+                    blame_menu_handle: PopoverMenuHandle::default(),
                 }
             })),
             nbformat::v4::Cell::Raw {
                 id,
                 metadata,
                 source,
-            } => Cell::Raw(cx.new_view(|_| RawCell {
-                id: id.clone(),
-                metadata: metadata.clone(),
-                source: source.join(""),
-                selected: false,
-                cell_position: None,
+            } => Cell::Raw(cx.new_view(|cx| {
+                let source = source.join("");
+                let buffer = cx.new_model(|cx| Buffer::local(source.clone(), cx));
+                let editor = cell_editor(buffer.clone(), CellType::Raw, cx);
+                let editable = metadata_permission(metadata, "editable");
+                editor.update(cx, |editor, _| editor.set_read_only(!editable));
+
+                let cell_id = id.clone();
+                let editor_subscription = cx.subscribe(
+                    &editor,
+                    move |this: &mut RawCell, _, event: &EditorEvent, cx| match event {
+                        EditorEvent::Focused => cx.emit(CellEvent::EditorFocused(cell_id.clone())),
+                        EditorEvent::BufferEdited => {
+                            this.source = this.current_source(cx);
+                            cx.emit(CellEvent::SourceEdited(cell_id.clone()));
+                        }
+                        _ => {}
+                    },
+                );
+
+                let mut cell = RawCell {
+                    id: id.clone(),
+                    metadata: metadata.clone(),
+                    modified: false,
+                    source,
+                    buffer,
+                    editor,
+                    _editor_subscription: editor_subscription,
+                    languages: languages.clone(),
+                    language_task: Task::ready(()),
+                    comments: cx.new_model(|_| CellComments::new(metadata)),
+                    comments_menu_handle: PopoverMenuHandle::default(),
+                    tags: cx.new_model(|_| CellTags::new(metadata)),
+                    tags_menu_handle: PopoverMenuHandle::default(),
+                    read_only: false,
+                    selected: false,
+                    cell_position: None,
+                    git_status: None,
+                    blame_loader: None,
+                    blame_menu_handle: PopoverMenuHandle::default(),
+                };
+                cell.update_language(cx);
+                cell
             })),
         }
     }
 }
 
-pub trait RenderableCell: Render {
+pub trait RenderableCell: Render + EventEmitter<CellEvent> {
     const CELL_TYPE: CellType;
 
     fn id(&self) -> &CellId;
     fn cell_type(&self) -> CellType;
     fn metadata(&self) -> &CellMetadata;
     fn source(&self) -> &String;
+    fn comments(&self) -> &Model<CellComments>;
+    fn comments_menu_handle(&self) -> &PopoverMenuHandle<CellCommentsView>;
+    fn tags(&self) -> &Model<CellTags>;
+    fn tags_menu_handle(&self) -> &PopoverMenuHandle<CellTagsView>;
+    fn read_only(&self) -> bool;
+    fn git_status(&self) -> Option<CellGitStatus>;
+    fn set_git_status(&mut self, status: Option<CellGitStatus>, cx: &mut ViewContext<Self>);
+    fn blame_loader(&self) -> Option<&BlameLoader>;
+    fn set_blame_loader(&mut self, loader: Option<BlameLoader>, cx: &mut ViewContext<Self>);
+    fn blame_menu_handle(&self) -> &PopoverMenuHandle<CellBlameView>;
+    fn set_editable(&mut self, editable: bool, cx: &mut ViewContext<Self>);
+    fn set_deletable(&mut self, deletable: bool, cx: &mut ViewContext<Self>);
+    fn set_slide_type(&mut self, slide_type: Option<SlideType>, cx: &mut ViewContext<Self>);
     fn selected(&self) -> bool;
     fn set_selected(&mut self, selected: bool) -> &mut Self;
     fn selected_bg_color(&self, cx: &ViewContext<Self>) -> Hsla {
@@ -250,28 +1040,345 @@ pub trait RenderableCell: Render {
         None
     }
 
-    fn cell_position_spacer(
-        &self,
-        is_first: bool,
-        cx: &ViewContext<Self>,
-    ) -> Option<impl IntoElement> {
-        let cell_position = self.cell_position();
+    fn is_editable(&self) -> bool {
+        metadata_permission(self.metadata(), "editable")
+    }
 
-        if (cell_position == Some(&CellPosition::First) && is_first)
-            || (cell_position == Some(&CellPosition::Last) && !is_first)
-        {
-            Some(div().flex().w_full().h(DynamicSpacing::Base12.px(cx)))
-        } else {
-            None
+    fn is_deletable(&self) -> bool {
+        metadata_permission(self.metadata(), "deletable")
+    }
+
+    fn slide_type(&self) -> Option<SlideType> {
+        cell_slide_type(self.metadata())
+    }
+
+    /// The gutter marker of a cell that can't be edited or deleted, with a menu to change that.
+    /// It's also shown on the selected cell so it can be locked.
+    fn lock_marker(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let editable = self.is_editable();
+        let deletable = self.is_deletable();
+        let locked = !editable || !deletable;
+        if !locked && !(self.selected() && !self.read_only()) {
+            return None;
         }
+
+        let tooltip = match (editable, deletable) {
+            (false, false) => "Can't Be Edited or Deleted",
+            (false, true) => "Can't Be Edited",
+            (true, false) => "Can't Be Deleted",
+            (true, true) => "Lock Cell",
+        };
+        let this = cx.view().downgrade();
+
+        Some(
+            PopoverMenu::new("cell-lock")
+                .menu(move |cx| {
+                    let this = this.clone();
+                    Some(ContextMenu::build(cx, move |menu, _| {
+                        menu.toggleable_entry(
+                            "Editable",
+                            editable,
+                            IconPosition::End,
+                            Some(Box::new(ToggleCellEditable)),
+                            {
+                                let this = this.clone();
+                                move |cx| {
+                                    this.update(cx, |this, cx| this.set_editable(!editable, cx))
+                                        .ok();
+                                }
+                            },
+                        )
+                        .toggleable_entry(
+                            "Deletable",
+                            deletable,
+                            IconPosition::End,
+                            Some(Box::new(ToggleCellDeletable)),
+                            move |cx| {
+                                this.update(cx, |this, cx| this.set_deletable(!deletable, cx))
+                                    .ok();
+                            },
+                        )
+                    }))
+                })
+                .trigger(
+                    IconButton::new("cell-lock-marker", IconName::FileLock)
+                        .shape(IconButtonShape::Square)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Muted)
+                        .disabled(self.read_only())
+                        .tooltip(move |cx| Tooltip::text(tooltip, cx)),
+                )
+                .anchor(AnchorCorner::TopLeft)
+                .attach(AnchorCorner::TopRight),
+        )
     }
 
-    fn gutter(&self, cx: &ViewContext<Self>) -> impl IntoElement {
-        let is_selected = self.selected();
+    /// The cell's metadata with its comment threads and tags written in, as it is saved.
+    fn saved_metadata(&self, cx: &AppContext) -> CellMetadata {
+        let mut metadata = self.metadata().clone();
+        self.comments().read(cx).write_to(&mut metadata);
+        self.tags().read(cx).write_to(&mut metadata);
+        metadata
+    }
 
-        div()
-            .relative()
-            .h_full()
+    /// The cell's tags as chips under it, with a button opening the tag editor. The row is shown
+    /// on cells with tags, and on the selected cell so tags can be added.
+    fn render_tags(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let tags = self.tags().clone();
+        let menu_handle = self.tags_menu_handle().clone();
+        let tag_list = tags.read(cx).tags().to_vec();
+        let slide_type = self.slide_type();
+        let editable = !self.read_only();
+        if tag_list.is_empty()
+            && slide_type.is_none()
+            && !(editable && (self.selected() || menu_handle.is_deployed()))
+        {
+            return None;
+        }
+
+        Some(
+            h_flex()
+                .w_full()
+                .pl(px(GUTTER_WIDTH))
+                .pr_6()
+                .pb_1()
+                .gap_1()
+                .flex_wrap()
+                .bg(self.selected_bg_color(cx))
+                .when(
+                    slide_type.is_some() || (editable && self.selected()),
+                    |this| this.child(self.render_slide_type(slide_type, cx)),
+                )
+                .children(tag_list.into_iter().map(|tag| tag_chip(tag, cx)))
+                .when(editable, |this| {
+                    this.child(
+                        PopoverMenu::new("cell-tags")
+                            .with_handle(menu_handle)
+                            .menu(move |cx| {
+                                let tags = tags.clone();
+                                Some(cx.new_view(|cx| CellTagsView::new(tags, cx)))
+                            })
+                            .trigger(
+                                IconButton::new("cell-tags-edit", IconName::Hash)
+                                    .shape(IconButtonShape::Square)
+                                    .icon_size(IconSize::XSmall)
+                                    .icon_color(Color::Muted)
+                                    .tooltip(|cx| Tooltip::text("Edit Tags", cx)),
+                            )
+                            .anchor(AnchorCorner::TopLeft)
+                            .attach(AnchorCorner::BottomLeft),
+                    )
+                }),
+        )
+    }
+
+    /// The cell's slide type, with a menu to change it.
+    fn render_slide_type(
+        &self,
+        slide_type: Option<SlideType>,
+        cx: &ViewContext<Self>,
+    ) -> impl IntoElement {
+        let this = cx.view().downgrade();
+
+        PopoverMenu::new("cell-slide-type")
+            .menu(move |cx| {
+                let this = this.clone();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    let menu = menu.toggleable_entry(
+                        "None",
+                        slide_type.is_none(),
+                        IconPosition::End,
+                        None,
+                        {
+                            let this = this.clone();
+                            move |cx| {
+                                this.update(cx, |this, cx| this.set_slide_type(None, cx))
+                                    .ok();
+                            }
+                        },
+                    );
+                    SlideType::ALL.into_iter().fold(menu, |menu, option| {
+                        let this = this.clone();
+                        menu.toggleable_entry(
+                            option.label(),
+                            slide_type == Some(option),
+                            IconPosition::End,
+                            None,
+                            move |cx| {
+                                this.update(cx, |this, cx| this.set_slide_type(Some(option), cx))
+                                    .ok();
+                            },
+                        )
+                    })
+                }))
+            })
+            .trigger(
+                Button::new(
+                    "cell-slide-type-trigger",
+                    slide_type.map_or("Slide Type", |slide_type| slide_type.label()),
+                )
+                .icon(IconName::Screen)
+                .icon_position(IconPosition::Start)
+                .icon_size(IconSize::XSmall)
+                .icon_color(Color::Muted)
+                .label_size(LabelSize::XSmall)
+                .color(Color::Muted)
+                .disabled(self.read_only())
+                .tooltip(|cx| Tooltip::text("Slide Type", cx)),
+            )
+            .anchor(AnchorCorner::TopLeft)
+            .attach(AnchorCorner::BottomLeft)
+    }
+
+    /// The gutter marker opening the cell's comment threads. It's shown on cells with comments,
+    /// and on the selected cell so a thread can be started.
+    fn comments_marker(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let comments = self.comments().clone();
+        let menu_handle = self.comments_menu_handle().clone();
+        let thread_count = comments.read(cx).threads().len();
+        let unresolved_count = comments.read(cx).unresolved_count();
+        if thread_count == 0 && !self.selected() && !menu_handle.is_deployed() {
+            return None;
+        }
+
+        let tooltip: SharedString = match (thread_count, unresolved_count) {
+            (0, _) => "Comment on Cell".into(),
+            (_, 0) => "Resolved Comments".into(),
+            (_, 1) => "1 Unresolved Thread".into(),
+            (_, count) => format!("{count} Unresolved Threads").into(),
+        };
+
+        Some(
+            PopoverMenu::new("cell-comments")
+                .with_handle(menu_handle)
+                .menu(move |cx| {
+                    let comments = comments.clone();
+                    Some(cx.new_view(|cx| CellCommentsView::new(comments, cx)))
+                })
+                .trigger(
+                    IconButton::new("cell-comments-marker", IconName::MessageBubbles)
+                        .shape(IconButtonShape::Square)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(if unresolved_count > 0 {
+                            Color::Accent
+                        } else {
+                            Color::Muted
+                        })
+                        .tooltip(move |cx| Tooltip::text(tooltip.clone(), cx)),
+                )
+                .anchor(AnchorCorner::TopLeft)
+                .attach(AnchorCorner::TopRight),
+        )
+    }
+
+    fn cell_position_spacer(
+        &self,
+        is_first: bool,
+        cx: &ViewContext<Self>,
+    ) -> Option<impl IntoElement> {
+        let cell_position = self.cell_position();
+
+        if (cell_position == Some(&CellPosition::First) && is_first)
+            || (cell_position == Some(&CellPosition::Last) && !is_first)
+        {
+            Some(div().flex().w_full().h(DynamicSpacing::Base12.px(cx)))
+        } else {
+            None
+        }
+    }
+
+    /// The gutter marker of a cell modified since HEAD, with a menu to revert it.
+    fn git_marker(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.git_status() != Some(CellGitStatus::Modified) || self.read_only() {
+            return None;
+        }
+
+        let cell_id = self.id().clone();
+        let is_code = matches!(self.cell_type(), CellType::Code);
+        let this = cx.view().downgrade();
+
+        Some(
+            PopoverMenu::new("cell-git")
+                .menu(move |cx| {
+                    let this = this.clone();
+                    let cell_id = cell_id.clone();
+                    Some(ContextMenu::build(cx, move |menu, _| {
+                        let revert = move |include_outputs: bool| {
+                            let this = this.clone();
+                            let cell_id = cell_id.clone();
+                            move |cx: &mut WindowContext| {
+                                this.update(cx, |_, cx| {
+                                    cx.emit(CellEvent::RevertToHead {
+                                        cell_id: cell_id.clone(),
+                                        include_outputs,
+                                    })
+                                })
+                                .ok();
+                            }
+                        };
+                        menu.entry(
+                            "Revert Cell to HEAD",
+                            Some(Box::new(RevertCellToHead)),
+                            revert(false),
+                        )
+                        .when(is_code, |menu| {
+                            menu.entry(
+                                "Revert Cell and Outputs to HEAD",
+                                Some(Box::new(RevertCellAndOutputsToHead)),
+                                revert(true),
+                            )
+                        })
+                    }))
+                })
+                .trigger(
+                    IconButton::new("cell-git-marker", IconName::FileGit)
+                        .shape(IconButtonShape::Square)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Modified)
+                        .tooltip(|cx| Tooltip::text("Modified Since HEAD", cx)),
+                )
+                .anchor(AnchorCorner::TopLeft)
+                .attach(AnchorCorner::TopRight),
+        )
+    }
+
+    /// The gutter marker opening the commits that touched the cell. It's shown on the selected
+    /// cell while the notebook is tracked by git.
+    fn blame_marker(&self) -> Option<impl IntoElement> {
+        let loader = self.blame_loader()?.clone();
+        let menu_handle = self.blame_menu_handle().clone();
+        if !self.selected() && !menu_handle.is_deployed() {
+            return None;
+        }
+
+        let cell_id = self.id().clone();
+        Some(
+            PopoverMenu::new("cell-blame")
+                .with_handle(menu_handle)
+                .menu(move |cx| {
+                    let load = loader(&cell_id, cx);
+                    Some(cx.new_view(|cx| CellBlameView::new(load, cx)))
+                })
+                .trigger(
+                    IconButton::new("cell-blame-marker", IconName::HistoryRerun)
+                        .shape(IconButtonShape::Square)
+                        .icon_size(IconSize::XSmall)
+                        .icon_color(Color::Muted)
+                        .tooltip(|cx| {
+                            Tooltip::for_action("Show Blame for Cell", &ShowCellBlame, cx)
+                        }),
+                )
+                .anchor(AnchorCorner::TopLeft)
+                .attach(AnchorCorner::TopRight),
+        )
+    }
+
+    fn gutter(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let is_selected = self.selected();
+
+        div()
+            .relative()
+            .h_full()
             .w(px(GUTTER_WIDTH))
             .child(
                 div()
@@ -286,7 +1393,14 @@ pub trait RenderableCell: Render {
                             .w(px(1.))
                             .h_full()
                             .when(is_selected, |this| this.bg(cx.theme().colors().icon_accent))
-                            .when(!is_selected, |this| this.bg(cx.theme().colors().border)),
+                            .when(!is_selected, |this| this.bg(cx.theme().colors().border))
+                            .when_some(self.git_status(), |this, status| {
+                                let color = match status {
+                                    CellGitStatus::Added => cx.theme().status().created,
+                                    CellGitStatus::Modified => cx.theme().status().modified,
+                                };
+                                this.w(px(3.)).bg(color)
+                            }),
                     ),
             )
             .when_some(self.control(cx), |this, control| {
@@ -305,135 +1419,1427 @@ pub trait RenderableCell: Render {
                         .child(control.button),
                 )
             })
+            .when_some(self.comments_marker(cx), |this, marker| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(CODE_BLOCK_INSET + GUTTER_WIDTH + 14.0))
+                        .left_0()
+                        .flex()
+                        .flex_none()
+                        .w(px(GUTTER_WIDTH))
+                        .justify_center()
+                        .bg(cx.theme().colors().tab_bar_background)
+                        .child(marker),
+                )
+            })
+            .when_some(self.lock_marker(cx), |this, marker| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(CODE_BLOCK_INSET + 2.0 * (GUTTER_WIDTH + 14.0)))
+                        .left_0()
+                        .flex()
+                        .flex_none()
+                        .w(px(GUTTER_WIDTH))
+                        .justify_center()
+                        .bg(cx.theme().colors().tab_bar_background)
+                        .child(marker),
+                )
+            })
+            .when_some(self.git_marker(cx), |this, marker| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(CODE_BLOCK_INSET + 3.0 * (GUTTER_WIDTH + 14.0)))
+                        .left_0()
+                        .flex()
+                        .flex_none()
+                        .w(px(GUTTER_WIDTH))
+                        .justify_center()
+                        .bg(cx.theme().colors().tab_bar_background)
+                        .child(marker),
+                )
+            })
+            .when_some(self.blame_marker(), |this, marker| {
+                // Below the git marker when it's shown
+                let slot =
+                    if self.git_status() == Some(CellGitStatus::Modified) && !self.read_only() {
+                        4.0
+                    } else {
+                        3.0
+                    };
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(CODE_BLOCK_INSET + slot * (GUTTER_WIDTH + 14.0)))
+                        .left_0()
+                        .flex()
+                        .flex_none()
+                        .w(px(GUTTER_WIDTH))
+                        .justify_center()
+                        .bg(cx.theme().colors().tab_bar_background)
+                        .child(marker),
+                )
+            })
     }
 
     fn cell_position(&self) -> Option<&CellPosition>;
     fn set_cell_position(&mut self, position: CellPosition) -> &mut Self;
 }
 
-pub trait RunnableCell: RenderableCell {
-    fn execution_count(&self) -> Option<i32>;
-    fn set_execution_count(&mut self, count: i32) -> &mut Self;
-    fn run(&mut self, cx: &mut ViewContext<Self>) -> ();
-}
+pub trait RunnableCell: RenderableCell {
+    fn execution_count(&self) -> Option<i32>;
+    fn set_execution_count(&mut self, count: i32) -> &mut Self;
+    fn run(&mut self, cx: &mut ViewContext<Self>) -> ();
+}
+
+/// A part of a markdown cell as it's rendered, see [`split_attachments`].
+enum ParsedSegment {
+    Markdown(markdown_preview::markdown_elements::ParsedMarkdown),
+    Attachment { name: String, alt: String },
+}
+
+/// The parsed markdown of a cell. It's a view of its own so it can be cached: the cell re-renders
+/// whenever it's selected or edited, and the markdown only needs to when its source changes.
+struct RenderedMarkdown {
+    segments: Vec<ParsedSegment>,
+    attachment_images: HashMap<String, View<ImageView>>,
+}
+
+impl Render for RenderedMarkdown {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .w_full()
+            .gap_2()
+            .children(self.segments.iter().map(|segment| {
+                match segment {
+                    ParsedSegment::Markdown(parsed) => render_parsed_markdown(parsed, cx),
+                    ParsedSegment::Attachment { name, alt } => {
+                        match self.attachment_images.get(name) {
+                            Some(image) => div().child(image.clone()).into_any_element(),
+                            None => Label::new(format!("{alt} (missing attachment {name})"))
+                                .color(Color::Muted)
+                                .into_any_element(),
+                        }
+                    }
+                }
+            }))
+    }
+}
+
+pub struct MarkdownCell {
+    id: CellId,
+    metadata: CellMetadata,
+    /// Whether the cell's metadata changed since the last save, e.g. it was locked.
+    modified: bool,
+    source: String,
+    /// The images saved with the cell, as they're written back to the notebook file.
+    attachments: Option<serde_json::Value>,
+    attachment_images: HashMap<String, View<ImageView>>,
+    /// The markdown as it was last parsed, `None` until the source has been parsed once.
+    rendered_markdown: Option<View<RenderedMarkdown>>,
+    markdown_parsing_task: Task<()>,
+    /// Edits the cell's source, shown in place of the rendered markdown while `editing`.
+    editor: View<Editor>,
+    editing: bool,
+    _editor_subscription: Subscription,
+    language_task: Task<()>,
+    comments: Model<CellComments>,
+    comments_menu_handle: PopoverMenuHandle<CellCommentsView>,
+    tags: Model<CellTags>,
+    tags_menu_handle: PopoverMenuHandle<CellTagsView>,
+    read_only: bool,
+    selected: bool,
+    cell_position: Option<CellPosition>,
+    /// How the cell differs from HEAD, shown in its gutter.
+    git_status: Option<CellGitStatus>,
+    /// Loads the commits that touched the cell, set while the notebook is tracked by git.
+    blame_loader: Option<BlameLoader>,
+    blame_menu_handle: PopoverMenuHandle<CellBlameView>,
+    languages: Arc<LanguageRegistry>,
+}
+
+impl EventEmitter<CellEvent> for MarkdownCell {}
+
+impl MarkdownCell {
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    pub fn editor(&self) -> &View<Editor> {
+        &self.editor
+    }
+
+    /// The current contents of the cell's editor, which may not have been rendered yet.
+    pub fn current_source(&self, cx: &AppContext) -> String {
+        self.editor.read(cx).text(cx)
+    }
+
+    pub fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.modified
+            || self.comments.read(cx).is_modified()
+            || self.tags.read(cx).is_modified()
+            || self.editor.read(cx).buffer().read(cx).is_dirty(cx)
+    }
+
+    /// Marks the cell's buffer as saved after the notebook has been written to disk.
+    pub fn did_save(&mut self, cx: &mut ViewContext<Self>) {
+        self.modified = false;
+        self.comments.update(cx, |comments, _| comments.did_save());
+        self.tags.update(cx, |tags, _| tags.did_save());
+        if let Some(buffer) = self.editor.read(cx).buffer().read(cx).as_singleton() {
+            buffer.update(cx, |buffer, cx| {
+                let version = buffer.version();
+                buffer.did_save(version, None, cx);
+            });
+        }
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut ViewContext<Self>) {
+        self.read_only = read_only;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    /// Locks the editor while the notebook is read-only or the cell isn't editable.
+    fn update_editor_read_only(&mut self, cx: &mut ViewContext<Self>) {
+        let read_only = self.read_only || !self.is_editable();
+        self.editor
+            .update(cx, |editor, _| editor.set_read_only(read_only));
+        if read_only {
+            self.finish_editing(cx);
+        }
+    }
+
+    /// Replaces the rendered markdown with the cell's source.
+    pub fn start_editing(&mut self, cx: &mut ViewContext<Self>) {
+        if self.read_only || !self.is_editable() {
+            return;
+        }
+        self.editing = true;
+        cx.focus_view(&self.editor);
+        cx.notify();
+    }
+
+    /// Renders the cell's source again, e.g. when it's run or the editor loses focus.
+    pub fn finish_editing(&mut self, cx: &mut ViewContext<Self>) {
+        if !self.editing {
+            return;
+        }
+        self.editing = false;
+
+        let source = self.current_source(cx);
+        if source != self.source {
+            self.source = source;
+            self.parse_markdown(cx);
+        }
+        cx.notify();
+    }
+
+    fn parse_markdown(&mut self, cx: &mut ViewContext<Self>) {
+        let languages = self.languages.clone();
+        let source = self.source.clone();
+
+        self.markdown_parsing_task = cx.spawn(|this, mut cx| async move {
+            let parsed_segments = cx
+                .background_executor()
+                .spawn(async move {
+                    let mut parsed_segments = Vec::new();
+                    for segment in split_attachments(&source) {
+                        parsed_segments.push(match segment {
+                            MarkdownSegment::Markdown(markdown) => ParsedSegment::Markdown(
+                                parse_markdown(&markdown, None, Some(languages.clone())).await,
+                            ),
+                            MarkdownSegment::Attachment { name, alt } => {
+                                ParsedSegment::Attachment { name, alt }
+                            }
+                        });
+                    }
+                    parsed_segments
+                })
+                .await;
+
+            this.update(&mut cx, |cell: &mut MarkdownCell, cx| {
+                match cell.rendered_markdown.as_ref() {
+                    Some(rendered_markdown) => rendered_markdown.update(cx, |rendered, cx| {
+                        rendered.segments = parsed_segments;
+                        cx.notify();
+                    }),
+                    None => {
+                        let attachment_images = cell.attachment_images.clone();
+                        cell.rendered_markdown = Some(cx.new_view(|_| RenderedMarkdown {
+                            segments: parsed_segments,
+                            attachment_images,
+                        }));
+                    }
+                }
+                cx.notify();
+            })
+            .log_err();
+        });
+    }
+
+    fn render_editor(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .size_full()
+            .flex_1()
+            .my_1p5()
+            .py_3()
+            .px_5()
+            .rounded_lg()
+            .border_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().editor_background)
+            .child(
+                div()
+                    .key_context("CellEditor")
+                    .w_full()
+                    .child(self.editor.clone()),
+            )
+    }
+
+    fn render_markdown(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content = match self.rendered_markdown.as_ref() {
+            Some(rendered_markdown) if !self.source.trim().is_empty() => {
+                AnyView::from(rendered_markdown.clone())
+                    .cached(StyleRefinement::default().w_full())
+                    .into_any_element()
+            }
+            _ => Label::new("Double-click to edit Markdown")
+                .color(Color::Placeholder)
+                .into_any_element(),
+        };
+
+        div()
+            .id("markdown-cell")
+            .size_full()
+            .flex_1()
+            .p_3()
+            .when_some(
+                NotebookSettings::get_global(cx).markdown_font_size,
+                |this, font_size| this.text_size(font_size),
+            )
+            .on_click(cx.listener(|this, event: &gpui::ClickEvent, cx| {
+                if event.up.click_count == 2 {
+                    this.start_editing(cx);
+                }
+            }))
+            .child(content)
+    }
+}
+
+impl RenderableCell for MarkdownCell {
+    const CELL_TYPE: CellType = CellType::Markdown;
+
+    fn id(&self) -> &CellId {
+        &self.id
+    }
+
+    fn cell_type(&self) -> CellType {
+        CellType::Markdown
+    }
+
+    fn metadata(&self) -> &CellMetadata {
+        &self.metadata
+    }
+
+    fn source(&self) -> &String {
+        &self.source
+    }
+
+    fn comments(&self) -> &Model<CellComments> {
+        &self.comments
+    }
+
+    fn comments_menu_handle(&self) -> &PopoverMenuHandle<CellCommentsView> {
+        &self.comments_menu_handle
+    }
+
+    fn tags(&self) -> &Model<CellTags> {
+        &self.tags
+    }
+
+    fn tags_menu_handle(&self) -> &PopoverMenuHandle<CellTagsView> {
+        &self.tags_menu_handle
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn git_status(&self) -> Option<CellGitStatus> {
+        self.git_status
+    }
+
+    fn set_git_status(&mut self, status: Option<CellGitStatus>, cx: &mut ViewContext<Self>) {
+        if self.git_status != status {
+            self.git_status = status;
+            cx.notify();
+        }
+    }
+
+    fn blame_loader(&self) -> Option<&BlameLoader> {
+        self.blame_loader.as_ref()
+    }
+
+    fn set_blame_loader(&mut self, loader: Option<BlameLoader>, cx: &mut ViewContext<Self>) {
+        self.blame_loader = loader;
+        cx.notify();
+    }
+
+    fn blame_menu_handle(&self) -> &PopoverMenuHandle<CellBlameView> {
+        &self.blame_menu_handle
+    }
+
+    fn set_editable(&mut self, editable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "editable", editable.into());
+        self.modified = true;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    fn set_deletable(&mut self, deletable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "deletable", deletable.into());
+        self.modified = true;
+        cx.notify();
+    }
+
+    fn set_slide_type(&mut self, slide_type: Option<SlideType>, cx: &mut ViewContext<Self>) {
+        set_cell_slide_type(&mut self.metadata, slide_type);
+        self.modified = true;
+        cx.notify();
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) -> &mut Self {
+        self.selected = selected;
+        self
+    }
+
+    fn control(&self, _: &ViewContext<Self>) -> Option<CellControl> {
+        None
+    }
+
+    fn cell_position(&self) -> Option<&CellPosition> {
+        self.cell_position.as_ref()
+    }
+
+    fn set_cell_position(&mut self, cell_position: CellPosition) -> &mut Self {
+        self.cell_position = Some(cell_position);
+        self
+    }
+}
+
+impl Render for MarkdownCell {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content = if self.editing {
+            self.render_editor(cx).into_any_element()
+        } else {
+            self.render_markdown(cx).into_any_element()
+        };
+
+        v_flex()
+            .size_full()
+            // TODO: Move base cell render into trait impl so we don't have to repeat this
+            .children(self.cell_position_spacer(true, cx))
+            .child(
+                h_flex()
+                    .w_full()
+                    .pr_6()
+                    .rounded_sm()
+                    .items_start()
+                    .gap(DynamicSpacing::Base08.rems(cx))
+                    .bg(self.selected_bg_color(cx))
+                    .child(self.gutter(cx))
+                    .child(content),
+            )
+            .children(self.render_tags(cx))
+            // TODO: Move base cell render into trait impl so we don't have to repeat this
+            .children(self.cell_position_spacer(false, cx))
+    }
+}
+
+pub struct CodeCell {
+    id: CellId,
+    metadata: CellMetadata,
+    /// Whether the cell changed outside of its editor since the last save, e.g. its outputs were cleared or collapsed.
+    modified: bool,
+    execution_count: Option<i32>,
+    source: String,
+    buffer: Model<Buffer>,
+    editor: View<editor::Editor>,
+    _editor_subscription: Subscription,
+    outputs: Vec<Output>,
+    /// The outputs as they will be written back to the notebook file. They're shared with
+    /// snapshots of the cell being saved, and copied when they change during a save.
+    nbformat_outputs: Arc<Vec<nbformat::v4::Output>>,
+    /// The display id each of `nbformat_outputs` was shown with, so updates to it can be saved.
+    nbformat_display_ids: Vec<Option<String>>,
+    /// How many bytes `nbformat_outputs` take, until they change.
+    output_memory: Option<usize>,
+    /// Drops the stream output of a run past `notebook.output_max_lines` and `output_max_bytes`.
+    stream_limiter: StreamLimiter,
+    /// Reads outputs spilled to disk back in, see [`CodeCell::rehydrate_spilled_outputs`].
+    rehydrate_task: Option<Task<()>>,
+    execution_status: ExecutionStatus,
+    execution_started: Option<Instant>,
+    execution_duration: Option<Duration>,
+    elapsed_timer_task: Option<Task<()>>,
+    /// When the cell was last refreshed for new outputs, see [`CodeCell::refresh_outputs`].
+    last_refresh: Option<Instant>,
+    refresh_task: Option<Task<()>>,
+    input_prompt: Option<InputPrompt>,
+    /// A package install started from the cell. It's dropped when the cell runs again.
+    package_install: Option<PackageInstall>,
+    /// The login of the participant who last ran the cell, shown in shared projects.
+    executed_by: Option<SharedString>,
+    /// Whether the notebook is trusted, so saved outputs are shown in full.
+    trusted: bool,
+    comments: Model<CellComments>,
+    comments_menu_handle: PopoverMenuHandle<CellCommentsView>,
+    tags: Model<CellTags>,
+    tags_menu_handle: PopoverMenuHandle<CellTagsView>,
+    /// Whether the cell can't be edited or run, see [`Cell::set_read_only`].
+    read_only: bool,
+    selected: bool,
+    cell_position: Option<CellPosition>,
+    /// How the cell differs from HEAD, shown in its gutter.
+    git_status: Option<CellGitStatus>,
+    /// Loads the commits that touched the cell, set while the notebook is tracked by git.
+    blame_loader: Option<BlameLoader>,
+    blame_menu_handle: PopoverMenuHandle<CellBlameView>,
+    language_task: Task<()>,
+}
+
+impl EventEmitter<CellEvent> for CodeCell {}
+
+impl CodeCell {
+    pub fn editor(&self) -> &View<editor::Editor> {
+        &self.editor
+    }
+
+    pub fn buffer(&self) -> &Model<Buffer> {
+        &self.buffer
+    }
+
+    /// Highlights the IPython magics in the cell, once it's known to be Python.
+    fn highlight_magics(&self, cx: &mut ViewContext<Self>) {
+        if is_python(&self.buffer, cx) {
+            self.editor.update(cx, highlight_magics);
+        }
+    }
+
+    /// The current contents of the cell's editor, which may differ from the source it was loaded with.
+    pub fn current_source(&self, cx: &AppContext) -> String {
+        // Read from the buffer, since this is also called while the editor is being updated
+        self.buffer.read(cx).text()
+    }
+
+    pub fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.modified
+            || self.comments.read(cx).is_modified()
+            || self.tags.read(cx).is_modified()
+            || self.editor.read(cx).buffer().read(cx).is_dirty(cx)
+    }
+
+    /// Marks the cell's buffer as saved after the notebook has been written to disk.
+    pub fn did_save(&mut self, cx: &mut ViewContext<Self>) {
+        self.modified = false;
+        self.comments.update(cx, |comments, _| comments.did_save());
+        self.tags.update(cx, |tags, _| tags.did_save());
+        if let Some(buffer) = self.editor.read(cx).buffer().read(cx).as_singleton() {
+            buffer.update(cx, |buffer, cx| {
+                let version = buffer.version();
+                buffer.did_save(version, None, cx);
+            });
+        }
+    }
+
+    pub fn execution_status(&self) -> &ExecutionStatus {
+        &self.execution_status
+    }
+
+    pub fn set_execution_status(&mut self, status: ExecutionStatus, cx: &mut ViewContext<Self>) {
+        self.execution_status = status;
+        cx.emit(CellEvent::ExecutionChanged(self.id.clone()));
+        cx.notify();
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut ViewContext<Self>) {
+        self.read_only = read_only;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    /// Locks the editor while the notebook is read-only or the cell isn't editable.
+    fn update_editor_read_only(&mut self, cx: &mut ViewContext<Self>) {
+        let read_only = self.read_only || !self.is_editable();
+        self.editor
+            .update(cx, |editor, _| editor.set_read_only(read_only));
+    }
+
+    pub fn executed_by(&self) -> Option<&SharedString> {
+        self.executed_by.as_ref()
+    }
+
+    pub fn set_executed_by(&mut self, login: Option<SharedString>, cx: &mut ViewContext<Self>) {
+        self.executed_by = login;
+        cx.notify();
+    }
+
+    /// Prepares the cell for a new execution, dropping the outputs of the previous one.
+    pub fn start_execution(&mut self, status: ExecutionStatus, cx: &mut ViewContext<Self>) {
+        self.clear_outputs();
+        self.input_prompt = None;
+        self.package_install = None;
+        self.execution_status = status;
+        cx.emit(CellEvent::ExecutionChanged(self.id.clone()));
+        cx.notify();
+    }
+
+    /// Marks a queued or running execution as failed, e.g. because the kernel died before finishing it.
+    pub fn fail_execution(&mut self, message: String, cx: &mut ViewContext<Self>) {
+        if let Some(started) = self.execution_started.take() {
+            self.execution_duration = Some(started.elapsed());
+        }
+        self.elapsed_timer_task = None;
+        self.input_prompt = None;
+        self.execution_status = ExecutionStatus::KernelErrored(message);
+        cx.emit(CellEvent::ExecutionChanged(self.id.clone()));
+        cx.notify();
+    }
+
+    /// Stops a queued or running execution the notebook no longer waits on, e.g. because the cell
+    /// was deleted, leaving the cell idle with the outputs it got so far.
+    pub fn abandon_execution(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(started) = self.execution_started.take() {
+            self.execution_duration = Some(started.elapsed());
+        }
+        self.elapsed_timer_task = None;
+        self.input_prompt = None;
+        self.set_execution_status(ExecutionStatus::Unknown, cx);
+    }
+
+    /// Accept a Jupyter message sent in response to this cell's execute request.
+    pub fn handle_message(&mut self, message: &JupyterMessage, cx: &mut ViewContext<Self>) {
+        match &message.content {
+            JupyterMessageContent::Status(status) => match status.execution_state {
+                ExecutionState::Busy => {
+                    self.set_execution_metadata(serde_json::Map::new());
+                    self.record_execution_timestamp("iopub.status.busy");
+                    self.execution_started = Some(Instant::now());
+                    self.execution_duration = None;
+                    self.stream_limiter.reset();
+                    self.start_elapsed_timer(cx);
+                }
+                ExecutionState::Idle => {
+                    self.record_execution_timestamp("iopub.status.idle");
+                    if let Some(started) = self.execution_started.take() {
+                        self.execution_duration = Some(started.elapsed());
+                    }
+                    self.elapsed_timer_task = None;
+                    // The kernel won't read a reply once the execution is over, e.g. after an interrupt
+                    self.input_prompt = None;
+                }
+            },
+            JupyterMessageContent::InputRequest(request) => {
+                self.show_input_prompt(message, request, cx);
+            }
+            JupyterMessageContent::ExecuteInput(input) => {
+                self.execution_count = Some(input.execution_count.0 as i32);
+                self.record_execution_timestamp("iopub.execute_input");
+            }
+            JupyterMessageContent::ExecuteReply(reply) => {
+                self.execution_count = Some(reply.execution_count.0 as i32);
+                self.record_execution_timestamp("shell.execute_reply");
+            }
+            JupyterMessageContent::ClearOutput(options) if !options.wait => {
+                self.nbformat_outputs = Arc::default();
+                self.nbformat_display_ids.clear();
+                self.output_memory = None;
+                self.stream_limiter.reset();
+            }
+            _ => {}
+        }
+
+        let limited_content;
+        let content = match &message.content {
+            JupyterMessageContent::StreamContent(stream) => {
+                let settings = NotebookSettings::get_global(cx);
+                let Some(text) = self.stream_limiter.limit(
+                    &stream.text,
+                    settings.output_max_lines,
+                    settings.output_max_bytes,
+                ) else {
+                    return;
+                };
+                let mut stream = stream.clone();
+                stream.text = text;
+                limited_content = JupyterMessageContent::StreamContent(stream);
+                &limited_content
+            }
+            content => content,
+        };
+
+        self.push_nbformat_output(content);
+
+        crate::outputs::push_message(&mut self.outputs, &mut self.execution_status, content, cx);
+        self.refresh_outputs(cx);
+    }
+
+    /// Shows the outputs that arrived since the last refresh, and tells the notebook they changed.
+    /// Refreshes are at least [`OUTPUT_REFRESH_INTERVAL`] apart; outputs arriving sooner wait for
+    /// the next one.
+    fn refresh_outputs(&mut self, cx: &mut ViewContext<Self>) {
+        // The scheduled refresh will show these outputs too
+        if self.refresh_task.is_some() {
+            return;
+        }
+
+        let since_last_refresh = self.last_refresh.map(|last_refresh| last_refresh.elapsed());
+        match since_last_refresh {
+            Some(elapsed) if elapsed < OUTPUT_REFRESH_INTERVAL => {
+                let delay = OUTPUT_REFRESH_INTERVAL - elapsed;
+                self.refresh_task = Some(cx.spawn(|this, mut cx| async move {
+                    cx.background_executor().timer(delay).await;
+                    this.update(&mut cx, |this, cx| {
+                        this.refresh_task = None;
+                        this.flush_refresh(cx);
+                    })
+                    .ok();
+                }));
+            }
+            _ => self.flush_refresh(cx),
+        }
+    }
+
+    fn flush_refresh(&mut self, cx: &mut ViewContext<Self>) {
+        self.last_refresh = Some(Instant::now());
+        cx.emit(CellEvent::ExecutionChanged(self.id.clone()));
+        cx.notify();
+    }
+
+    pub fn has_outputs(&self) -> bool {
+        !self.outputs.is_empty()
+    }
+
+    pub fn nbformat_outputs(&self) -> &[nbformat::v4::Output] {
+        &self.nbformat_outputs
+    }
+
+    /// The execution state and outputs of the cell, for collaborators following the notebook.
+    pub fn to_proto(&self) -> proto::NotebookCell {
+        let (status, error) = execution_status_to_proto(&self.execution_status);
+        proto::NotebookCell {
+            id: cell_id_to_proto(&self.id),
+            execution_count: self.execution_count,
+            status: status as i32,
+            error,
+            outputs: serde_json::to_string(self.nbformat_outputs.as_slice()).unwrap_or_default(),
+            executed_by: self.executed_by.as_ref().map(ToString::to_string),
+        }
+    }
+
+    /// Shows the execution state and outputs of the leader's copy of the cell.
+    pub fn apply_proto(&mut self, cell: proto::NotebookCell, cx: &mut ViewContext<Self>) {
+        let status = proto::NotebookExecutionStatus::from_i32(cell.status)
+            .unwrap_or(proto::NotebookExecutionStatus::Idle);
+        self.execution_status = execution_status_from_proto(status, cell.error);
+        self.execution_count = cell.execution_count;
+        self.executed_by = cell.executed_by.map(SharedString::from);
+
+        if let Ok(outputs) = serde_json::from_str::<Vec<nbformat::v4::Output>>(&cell.outputs) {
+            self.outputs = convert_outputs(&outputs, self.trusted, cx);
+            self.nbformat_display_ids = vec![None; outputs.len()];
+            self.nbformat_outputs = Arc::new(outputs);
+            self.output_memory = None;
+        }
+        cx.notify();
+    }
+
+    /// Shows the saved outputs in full once the notebook is trusted, or hides the parts that
+    /// need trust again.
+    pub fn set_trusted(&mut self, trusted: bool, cx: &mut ViewContext<Self>) {
+        if trusted == self.trusted {
+            return;
+        }
+        self.trusted = trusted;
+        self.outputs = convert_outputs(&self.nbformat_outputs, trusted, cx);
+        cx.notify();
+    }
+
+    /// Loads the payloads of a saved output that were set aside when the notebook was opened.
+    pub fn load_deferred_output(
+        &mut self,
+        output_index: usize,
+        deferred_outputs: &DeferredOutputs,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(output) = self.nbformat_outputs.get(output_index) else {
+            return;
+        };
+        let Some(mut value) = serde_json::to_value(output).log_err() else {
+            return;
+        };
+        let loaded = value
+            .as_object_mut()
+            .map_or(false, |output| deferred_outputs.load(output));
+        if !loaded {
+            return;
+        }
+        let Some(output) = serde_json::from_value::<nbformat::v4::Output>(value).log_err() else {
+            return;
+        };
+
+        if let Some(shown) = self.outputs.get_mut(output_index) {
+            if let Some(loaded) = convert_outputs(&vec![output.clone()], self.trusted, cx).pop() {
+                *shown = loaded;
+            }
+        }
+        Arc::make_mut(&mut self.nbformat_outputs)[output_index] = output;
+        self.output_memory = None;
+        cx.notify();
+    }
+
+    /// Loads every saved output of the cell that has payloads set aside.
+    pub fn load_deferred_outputs(
+        &mut self,
+        deferred_outputs: &DeferredOutputs,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let deferred_indices = self
+            .nbformat_outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| !deferred_payloads(output).is_empty())
+            .map(|(output_index, _)| output_index)
+            .collect::<Vec<_>>();
+        for output_index in deferred_indices {
+            self.load_deferred_output(output_index, deferred_outputs, cx);
+        }
+    }
+
+    /// How many bytes the outputs of the cell take, see [`output_size`].
+    pub fn output_memory(&mut self) -> usize {
+        *self
+            .output_memory
+            .get_or_insert_with(|| self.nbformat_outputs.iter().map(output_size).sum())
+    }
+
+    /// Moves the large outputs of the cell out of memory, returning the files they have to be
+    /// written to. Outputs that can still be updated by their display id are kept.
+    pub fn spill_outputs(
+        &mut self,
+        spill_dir: &OutputSpillDir,
+        cx: &mut ViewContext<Self>,
+    ) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+        for (output_index, output) in Arc::make_mut(&mut self.nbformat_outputs)
+            .iter_mut()
+            .enumerate()
+        {
+            let has_display_id = self
+                .nbformat_display_ids
+                .get(output_index)
+                .map_or(false, Option::is_some);
+            if has_display_id
+                || !deferred_payloads(output).is_empty()
+                || output_size(output) < SPILL_MIN_SIZE
+            {
+                continue;
+            }
+            let path = spill_dir.output_path();
+            let Some((placeholder, contents)) = spill_output(output, &path) else {
+                continue;
+            };
+            *output = placeholder;
+            if let Some(shown) = self.outputs.get_mut(output_index) {
+                *shown = Output::Message("Loading output…".to_string());
+            }
+            files.push((path, contents));
+        }
+
+        if !files.is_empty() {
+            self.output_memory = None;
+            cx.notify();
+        }
+        files
+    }
+
+    /// Whether any output of the cell was spilled to disk.
+    pub fn has_spilled_outputs(&self) -> bool {
+        self.nbformat_outputs
+            .iter()
+            .any(|output| spilled_output_path(output).is_some())
+    }
+
+    /// Reads the outputs of the cell that were spilled to disk back in, once it's scrolled into
+    /// view. Outputs that can't be read yet keep their placeholder and are tried again later.
+    pub fn rehydrate_spilled_outputs(&mut self, cx: &mut ViewContext<Self>) {
+        if self.rehydrate_task.is_some() {
+            return;
+        }
+        let spilled = self
+            .nbformat_outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(output_index, output)| Some((output_index, spilled_output_path(output)?)))
+            .collect::<Vec<_>>();
+        if spilled.is_empty() {
+            return;
+        }
+
+        let read = cx.background_executor().spawn(async move {
+            spilled
+                .into_iter()
+                .filter_map(|(output_index, path)| {
+                    let output = read_spilled_output(&path).log_err()?;
+                    Some((output_index, path, output))
+                })
+                .collect::<Vec<_>>()
+        });
+        self.rehydrate_task = Some(cx.spawn(|this, mut cx| async move {
+            let outputs = read.await;
+            this.update(&mut cx, |this, cx| {
+                this.rehydrate_task = None;
+                for (output_index, path, output) in outputs {
+                    // The output may have been cleared or replaced while it was read
+                    let still_spilled = this
+                        .nbformat_outputs
+                        .get(output_index)
+                        .and_then(spilled_output_path)
+                        .map_or(false, |spilled_path| spilled_path == path);
+                    if !still_spilled {
+                        continue;
+                    }
+                    if let Some(shown) = this.outputs.get_mut(output_index) {
+                        if let Some(loaded) =
+                            convert_outputs(&vec![output.clone()], this.trusted, cx).pop()
+                        {
+                            *shown = loaded;
+                        }
+                    }
+                    Arc::make_mut(&mut this.nbformat_outputs)[output_index] = output;
+                }
+                this.output_memory = None;
+                cx.notify();
+            })
+            .log_err();
+        }));
+    }
+
+    /// The package to install for a `ModuleNotFoundError` raised by the last execution.
+    pub fn missing_package(&self) -> Option<String> {
+        self.nbformat_outputs
+            .iter()
+            .find_map(|output| match output {
+                nbformat::v4::Output::Error(error) => missing_package(&error.ename, &error.evalue),
+                _ => None,
+            })
+    }
+
+    pub fn start_package_install(
+        &mut self,
+        package: String,
+        manager: PackageManager,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.package_install = Some(PackageInstall {
+            package,
+            manager,
+            output: cx.new_view(|cx| TerminalOutput::new(cx)),
+            status: InstallStatus::Running,
+            reported_error: false,
+        });
+        cx.notify();
+    }
+
+    /// Accept a Jupyter message sent in response to the cell's package install.
+    pub fn handle_install_message(&mut self, message: &JupyterMessage, cx: &mut ViewContext<Self>) {
+        let Some(install) = self.package_install.as_mut() else {
+            return;
+        };
+
+        match &message.content {
+            JupyterMessageContent::StreamContent(stream) => {
+                install.reported_error |= reports_install_error(&stream.text);
+                install
+                    .output
+                    .update(cx, |output, cx| output.append_text(&stream.text, cx));
+            }
+            JupyterMessageContent::ErrorOutput(error) => {
+                install.reported_error = true;
+                let text = format!("{}: {}\n", error.ename, error.evalue);
+                install
+                    .output
+                    .update(cx, |output, cx| output.append_text(&text, cx));
+            }
+            JupyterMessageContent::Status(status)
+                if matches!(status.execution_state, ExecutionState::Idle) =>
+            {
+                install.status = if install.reported_error {
+                    InstallStatus::Failed
+                } else {
+                    InstallStatus::Succeeded
+                };
+            }
+            _ => return,
+        }
+        cx.notify();
+    }
+
+    /// Marks a running install as failed, e.g. because the kernel was restarted before it finished.
+    pub fn fail_package_install(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(install) = self.package_install.as_mut() {
+            if install.status == InstallStatus::Running {
+                install.status = InstallStatus::Failed;
+                cx.notify();
+            }
+        }
+    }
+
+    /// Whether the last execution of the cell raised an error.
+    pub fn has_error(&self) -> bool {
+        matches!(self.execution_status, ExecutionStatus::KernelErrored(_))
+            || self
+                .outputs
+                .iter()
+                .any(|output| matches!(output, Output::ErrorOutput(_)))
+    }
+
+    pub fn clear_outputs(&mut self) {
+        self.outputs.clear();
+        self.nbformat_outputs = Arc::default();
+        self.nbformat_display_ids.clear();
+        self.output_memory = None;
+        self.stream_limiter.reset();
+    }
+
+    /// Replaces every output shown with `display_id` in place, e.g. a progress bar or a live plot.
+    ///
+    /// Updates can come from the execution of any cell, not just the one that created the display.
+    pub fn update_display_data(
+        &mut self,
+        message: &JupyterMessageContent,
+        display_id: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let JupyterMessageContent::UpdateDisplayData(update) = message else {
+            return;
+        };
+
+        if let Some(output) = nbformat_output(message) {
+            self.replace_nbformat_display(display_id, &output);
+        }
+
+        if crate::outputs::update_display_data(&mut self.outputs, &update.data, display_id, cx) {
+            self.refresh_outputs(cx);
+        }
+    }
+
+    /// Clears the outputs and execution count, like Jupyter's "Clear Output".
+    pub fn clear(&mut self, cx: &mut ViewContext<Self>) {
+        if !self.has_outputs() && self.execution_count.is_none() {
+            return;
+        }
+
+        self.clear_outputs();
+        self.execution_count = None;
+        self.execution_duration = None;
+        self.package_install = None;
+        self.executed_by = None;
+        self.modified = true;
+        cx.emit(CellEvent::ExecutionChanged(self.id.clone()));
+        cx.notify();
+    }
+
+    fn show_input_prompt(
+        &mut self,
+        message: &JupyterMessage,
+        request: &InputRequest,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_masked(request.password, cx);
+            editor
+        });
+        cx.focus_view(&editor);
+
+        self.input_prompt = Some(InputPrompt {
+            request: message.clone(),
+            prompt: request.prompt.clone(),
+            password: request.password,
+            editor,
+        });
+    }
+
+    fn submit_input(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let Some(input_prompt) = self.input_prompt.take() else {
+            return;
+        };
+
+        let value = input_prompt.editor.read(cx).text(cx);
+        let echoed_value = if input_prompt.password {
+            "•".repeat(value.chars().count())
+        } else {
+            value.clone()
+        };
+        self.outputs.push(Output::Message(format!(
+            "{}{}",
+            input_prompt.prompt, echoed_value
+        )));
+
+        let reply = JupyterMessage::new(
+            InputReply {
+                value,
+                status: ReplyStatus::Ok,
+                error: None,
+            },
+            Some(&input_prompt.request),
+        );
+        cx.emit(CellEvent::SubmitInput(reply));
+        cx.focus_view(&self.editor);
+        cx.notify();
+    }
+
+    fn render_execution_error(&self) -> Option<impl IntoElement> {
+        let ExecutionStatus::KernelErrored(message) = &self.execution_status else {
+            return None;
+        };
+
+        Some(
+            h_flex()
+                .gap_1()
+                .child(
+                    Icon::new(IconName::XCircle)
+                        .size(IconSize::Small)
+                        .color(Color::Error),
+                )
+                .child(
+                    Label::new(message.clone())
+                        .size(LabelSize::Small)
+                        .color(Color::Error),
+                ),
+        )
+    }
+
+    /// Offers to install the package the last run was missing, or shows the install once started.
+    fn render_package_install(&self, cx: &ViewContext<Self>) -> Option<AnyElement> {
+        let Some(install) = &self.package_install else {
+            if self.read_only {
+                return None;
+            }
+            let package = self.missing_package()?;
+            return Some(
+                h_flex()
+                    .gap_2()
+                    .child(
+                        Label::new(format!(
+                            "{package} isn't installed in the kernel's environment"
+                        ))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                    )
+                    .children([PackageManager::Pip, PackageManager::Conda].map(|manager| {
+                        let package = package.clone();
+                        Button::new(
+                            manager.name(),
+                            format!("{} install {package}", manager.name()),
+                        )
+                        .label_size(LabelSize::Small)
+                        .on_click(cx.listener(move |this, _, cx| {
+                            cx.emit(CellEvent::InstallPackage {
+                                cell_id: this.id.clone(),
+                                package: package.clone(),
+                                manager,
+                            })
+                        }))
+                    }))
+                    .into_any_element(),
+            );
+        };
+
+        let (icon, color, label) = match install.status {
+            InstallStatus::Running => (
+                IconName::ArrowCircle,
+                Color::Muted,
+                format!(
+                    "Installing {} with {}…",
+                    install.package,
+                    install.manager.name()
+                ),
+            ),
+            InstallStatus::Succeeded => (
+                IconName::Check,
+                Color::Success,
+                format!("Installed {}", install.package),
+            ),
+            InstallStatus::Failed => (
+                IconName::XCircle,
+                Color::Error,
+                format!("Couldn't install {}", install.package),
+            ),
+        };
+
+        Some(
+            v_flex()
+                .gap_1()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(Icon::new(icon).size(IconSize::Small).color(color))
+                        .child(Label::new(label).size(LabelSize::Small).color(color))
+                        .when(install.status == InstallStatus::Succeeded, |this| {
+                            this.child(
+                                Button::new("rerun-after-install", "Re-run Cell")
+                                    .label_size(LabelSize::Small)
+                                    .on_click(cx.listener(|this, _, cx| {
+                                        cx.emit(CellEvent::Run(this.id.clone()))
+                                    })),
+                            )
+                        })
+                        .when(install.status != InstallStatus::Running, |this| {
+                            this.child(
+                                IconButton::new("dismiss-install", IconName::Close)
+                                    .icon_size(IconSize::Small)
+                                    .tooltip(|cx| Tooltip::text("Dismiss", cx))
+                                    .on_click(cx.listener(|this, _, cx| {
+                                        this.package_install = None;
+                                        cx.notify();
+                                    })),
+                            )
+                        }),
+                )
+                .child(install.output.clone())
+                .into_any_element(),
+        )
+    }
+
+    fn render_input_prompt(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let input_prompt = self.input_prompt.as_ref()?;
+
+        Some(
+            h_flex()
+                .key_context("NotebookInputPrompt")
+                .on_action(cx.listener(Self::submit_input))
+                .w_full()
+                .gap_2()
+                .font_buffer(cx)
+                .child(Label::new(input_prompt.prompt.clone()).size(LabelSize::Small))
+                .child(
+                    div()
+                        .flex_1()
+                        .px_2()
+                        .py_1()
+                        .rounded_md()
+                        .border_1()
+                        .border_color(cx.theme().colors().border_focused)
+                        .bg(cx.theme().colors().editor_background)
+                        .child(input_prompt.editor.clone()),
+                ),
+        )
+    }
+
+    fn push_nbformat_output(&mut self, content: &JupyterMessageContent) {
+        let Some(mut output) = nbformat_output(content) else {
+            return;
+        };
+        self.output_memory = None;
+
+        // Consecutive writes to the same stream are saved as a single output, like Jupyter does
+        if let (
+            nbformat::v4::Output::Stream { name, text },
+            Some(nbformat::v4::Output::Stream {
+                name: last_name,
+                text: last_text,
+            }),
+        ) = (
+            &output,
+            Arc::make_mut(&mut self.nbformat_outputs).last_mut(),
+        ) {
+            if name == last_name {
+                // Lines before the last newline are final, so only the line being written is
+                // collapsed again. This keeps long streams from getting slower with every message.
+                let tail_start = last_text.0.rfind('\n').map_or(0, |ix| ix + 1);
+                let mut tail = last_text.0.split_off(tail_start);
+                tail.push_str(&text.0);
+                last_text.0.push_str(&collapse_carriage_returns(&tail));
+                return;
+            }
+        }
+
+        if let nbformat::v4::Output::Stream { text, .. } = &mut output {
+            text.0 = collapse_carriage_returns(&text.0);
+        }
 
-pub struct MarkdownCell {
-    id: CellId,
-    metadata: CellMetadata,
-    source: String,
-    parsed_markdown: Option<markdown_preview::markdown_elements::ParsedMarkdown>,
-    markdown_parsing_task: Task<()>,
-    selected: bool,
-    cell_position: Option<CellPosition>,
-    languages: Arc<LanguageRegistry>,
-}
+        let display_id = match content {
+            JupyterMessageContent::DisplayData(display_data) => {
+                display_data.transient.display_id.clone()
+            }
+            JupyterMessageContent::ExecuteResult(result) => result
+                .transient
+                .as_ref()
+                .and_then(|transient| transient.display_id.clone()),
+            _ => None,
+        };
 
-impl RenderableCell for MarkdownCell {
-    const CELL_TYPE: CellType = CellType::Markdown;
+        // Showing a display again replaces it, matching what the outputs view does
+        if let Some(display_id) = display_id.as_deref() {
+            if self.replace_nbformat_display(display_id, &output) {
+                return;
+            }
+        }
 
-    fn id(&self) -> &CellId {
-        &self.id
+        Arc::make_mut(&mut self.nbformat_outputs).push(output);
+        self.nbformat_display_ids.push(display_id);
     }
 
-    fn cell_type(&self) -> CellType {
-        CellType::Markdown
+    /// Replaces the saved outputs shown with `display_id`. Returns whether there were any.
+    fn replace_nbformat_display(
+        &mut self,
+        display_id: &str,
+        output: &nbformat::v4::Output,
+    ) -> bool {
+        self.output_memory = None;
+        let mut replaced = false;
+        for (saved_output, saved_display_id) in Arc::make_mut(&mut self.nbformat_outputs)
+            .iter_mut()
+            .zip(self.nbformat_display_ids.iter())
+        {
+            if saved_display_id.as_deref() == Some(display_id) {
+                *saved_output = output.clone();
+                replaced = true;
+            }
+        }
+        replaced
     }
 
-    fn metadata(&self) -> &CellMetadata {
-        &self.metadata
+    /// Replaces the `execution` cell metadata, where Jupyter records the timing of the last run.
+    fn set_execution_metadata(&mut self, execution: serde_json::Map<String, serde_json::Value>) {
+        self.set_metadata_field("execution", execution.into());
     }
 
-    fn source(&self) -> &String {
-        &self.source
+    fn set_metadata_field(&mut self, key: &str, value: serde_json::Value) {
+        write_metadata_field(&mut self.metadata, key, value);
     }
 
-    fn selected(&self) -> bool {
-        self.selected
+    /// Whether the outputs are collapsed into a one line summary, from the `collapsed` cell metadata.
+    /// Cells without it follow `notebook.collapse_outputs`.
+    pub fn outputs_collapsed(&self, cx: &AppContext) -> bool {
+        metadata_bool(&self.metadata, "collapsed")
+            .unwrap_or_else(|| NotebookSettings::get_global(cx).collapse_outputs)
     }
 
-    fn set_selected(&mut self, selected: bool) -> &mut Self {
-        self.selected = selected;
-        self
+    /// Whether the outputs are shown in a fixed-height scrollable region, from the `scrolled` cell metadata.
+    pub fn outputs_scrolled(&self) -> bool {
+        metadata_flag(&self.metadata, "scrolled")
     }
 
-    fn control(&self, _: &ViewContext<Self>) -> Option<CellControl> {
-        None
+    pub fn toggle_outputs_collapsed(&mut self, cx: &mut ViewContext<Self>) {
+        let collapsed = !self.outputs_collapsed(cx);
+        self.set_metadata_field("collapsed", collapsed.into());
+        self.modified = true;
+        cx.notify();
     }
 
-    fn cell_position(&self) -> Option<&CellPosition> {
-        self.cell_position.as_ref()
+    pub fn toggle_outputs_scrolled(&mut self, cx: &mut ViewContext<Self>) {
+        let scrolled = !self.outputs_scrolled();
+        self.set_metadata_field("scrolled", scrolled.into());
+        self.modified = true;
+        cx.notify();
     }
 
-    fn set_cell_position(&mut self, cell_position: CellPosition) -> &mut Self {
-        self.cell_position = Some(cell_position);
-        self
+    fn record_execution_timestamp(&mut self, key: &str) {
+        let mut execution = execution_metadata(&self.metadata).unwrap_or_default();
+        execution.insert(
+            key.into(),
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Micros, true)
+                .into(),
+        );
+        self.set_execution_metadata(execution);
     }
-}
-
-impl Render for MarkdownCell {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        let Some(parsed) = self.parsed_markdown.as_ref() else {
-            return div();
-        };
 
-        let mut markdown_render_context =
-            markdown_preview::markdown_renderer::RenderContext::new(None, cx);
+    /// Re-renders the cell periodically so the elapsed time badge stays current while it runs.
+    fn start_elapsed_timer(&mut self, cx: &mut ViewContext<Self>) {
+        self.elapsed_timer_task = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor()
+                    .timer(Duration::from_millis(100))
+                    .await;
+                if this.update(&mut cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        }));
+    }
 
-        v_flex()
-            .size_full()
-            // TODO: Move base cell render into trait impl so we don't have to repeat this
-            .children(self.cell_position_spacer(true, cx))
-            .child(
-                h_flex()
-                    .w_full()
-                    .pr_6()
-                    .rounded_sm()
-                    .items_start()
-                    .gap(DynamicSpacing::Base08.rems(cx))
-                    .bg(self.selected_bg_color(cx))
-                    .child(self.gutter(cx))
-                    .child(
-                        v_flex()
-                            .size_full()
-                            .flex_1()
-                            .p_3()
-                            .font_ui(cx)
-                            .text_size(TextSize::Default.rems(cx))
-                            //
-                            .children(parsed.children.iter().map(|child| {
-                                div().relative().child(div().relative().child(
-                                    render_markdown_block(child, &mut markdown_render_context),
-                                ))
-                            })),
-                    ),
-            )
-            // TODO: Move base cell render into trait impl so we don't have to repeat this
-            .children(self.cell_position_spacer(false, cx))
+    fn elapsed_time(&self) -> Option<Duration> {
+        self.execution_started
+            .map(|started| started.elapsed())
+            .or(self.execution_duration)
     }
-}
 
-pub struct CodeCell {
-    id: CellId,
-    metadata: CellMetadata,
-    execution_count: Option<i32>,
-    source: String,
-    editor: View<editor::Editor>,
-    outputs: Vec<Output>,
-    selected: bool,
-    cell_position: Option<CellPosition>,
-    language_task: Task<()>,
-}
+    fn render_elapsed_time(&self) -> Option<impl IntoElement> {
+        let elapsed = self.elapsed_time()?;
+        let is_running = self.execution_started.is_some();
+
+        Some(
+            Label::new(format_elapsed_time(elapsed))
+                .size(LabelSize::XSmall)
+                .color(if is_running {
+                    Color::Accent
+                } else {
+                    Color::Muted
+                }),
+        )
+    }
 
-impl CodeCell {
-    pub fn is_dirty(&self, cx: &AppContext) -> bool {
-        self.editor.read(cx).buffer().read(cx).is_dirty(cx)
+    fn render_executed_by(&self) -> Option<impl IntoElement> {
+        let login = self.executed_by.clone()?;
+        Some(
+            Label::new(format!("@{login}"))
+                .size(LabelSize::XSmall)
+                .color(Color::Muted),
+        )
     }
-    pub fn has_outputs(&self) -> bool {
-        !self.outputs.is_empty()
+
+    /// The `[n]` part of the `In [n]` / `Out [n]` prompts, or `[*]` while the cell is waiting on the kernel.
+    fn execution_count_text(&self) -> String {
+        match self.execution_status {
+            ExecutionStatus::ConnectingToKernel
+            | ExecutionStatus::Queued
+            | ExecutionStatus::Executing => "[*]".to_string(),
+            _ => match self.execution_count() {
+                Some(count) => format!("[{count}]"),
+                None => "[ ]".to_string(),
+            },
+        }
     }
 
-    pub fn clear_outputs(&mut self) {
-        self.outputs.clear();
+    fn render_execution_count(
+        &self,
+        prefix: Option<&str>,
+        cx: &ViewContext<Self>,
+    ) -> impl IntoElement {
+        div()
+            .flex_none()
+            .w(px(EXECUTION_COUNT_WIDTH))
+            .pt(px(CODE_BLOCK_INSET + 6.0))
+            .font_buffer(cx)
+            .when_some(prefix, |this, prefix| {
+                this.child(
+                    Label::new(format!("{prefix} {}:", self.execution_count_text()))
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
     }
 
     fn output_control(&self) -> Option<CellControlType> {
@@ -467,7 +2873,7 @@ impl CodeCell {
                             .when(!is_selected, |this| this.bg(cx.theme().colors().border)),
                     ),
             )
-            .when(self.has_outputs(), |this| {
+            .when(self.has_outputs() && !self.read_only, |this| {
                 this.child(
                     div()
                         .absolute()
@@ -480,9 +2886,234 @@ impl CodeCell {
                         .items_center()
                         .justify_center()
                         .bg(cx.theme().colors().tab_bar_background)
-                        .child(IconButton::new("control", IconName::Ellipsis)),
+                        .child(self.render_output_options(cx)),
+                )
+            })
+    }
+
+    /// The gutter menu with actions for the cell's outputs.
+    fn render_output_options(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let this = cx.view().downgrade();
+        let collapsed = self.outputs_collapsed(cx);
+        let scrolled = self.outputs_scrolled();
+
+        PopoverMenu::new("output-options")
+            .menu(move |cx| {
+                let this = this.clone();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    menu.entry("Clear Output", Some(Box::new(ClearCellOutput)), {
+                        let this = this.clone();
+                        move |cx| {
+                            this.update(cx, |this, cx| this.clear(cx)).ok();
+                        }
+                    })
+                    .entry(
+                        if collapsed {
+                            "Expand Output"
+                        } else {
+                            "Collapse Output"
+                        },
+                        Some(Box::new(ToggleOutputsCollapsed)),
+                        {
+                            let this = this.clone();
+                            move |cx| {
+                                this.update(cx, |this, cx| this.toggle_outputs_collapsed(cx))
+                                    .ok();
+                            }
+                        },
+                    )
+                    .toggleable_entry(
+                        "Scroll Output",
+                        scrolled,
+                        IconPosition::End,
+                        Some(Box::new(ToggleOutputsScrolled)),
+                        {
+                            let this = this.clone();
+                            move |cx| {
+                                this.update(cx, |this, cx| this.toggle_outputs_scrolled(cx))
+                                    .ok();
+                            }
+                        },
+                    )
+                    .separator()
+                    .entry("Copy as Markdown", Some(Box::new(CopyCellAsMarkdown)), {
+                        let this = this.clone();
+                        move |cx| {
+                            this.update(cx, |this, cx| {
+                                cx.emit(CellEvent::CopyAsMarkdown {
+                                    cell_id: this.id.clone(),
+                                    include_output: false,
+                                })
+                            })
+                            .ok();
+                        }
+                    })
+                    .entry(
+                        "Copy with Output as Markdown",
+                        Some(Box::new(CopyCellWithOutputAsMarkdown)),
+                        {
+                            let this = this.clone();
+                            move |cx| {
+                                this.update(cx, |this, cx| {
+                                    cx.emit(CellEvent::CopyAsMarkdown {
+                                        cell_id: this.id.clone(),
+                                        include_output: true,
+                                    })
+                                })
+                                .ok();
+                            }
+                        },
+                    )
+                    .separator()
+                    .entry(
+                        "Ask Assistant About Cell",
+                        Some(Box::new(AskAssistant)),
+                        move |cx| {
+                            this.update(cx, |this, cx| {
+                                cx.emit(CellEvent::AskAssistant(this.id.clone()))
+                            })
+                            .ok();
+                        },
+                    )
+                }))
+            })
+            .trigger(
+                IconButton::new(
+                    "output-options-trigger",
+                    CellControlType::CellOptions.icon_name(),
                 )
+                .icon_size(IconSize::Small)
+                .shape(IconButtonShape::Square)
+                .tooltip(|cx| Tooltip::text("Output Options", cx)),
+            )
+    }
+
+    /// The one line summary shown in place of collapsed outputs. Clicking it expands them.
+    fn render_collapsed_outputs(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let summary = match self.outputs.len() {
+            1 => "1 output hidden".to_string(),
+            count => format!("{count} outputs hidden"),
+        };
+
+        h_flex()
+            .id("collapsed-outputs")
+            .gap_1()
+            .cursor_pointer()
+            .child(
+                Icon::new(IconName::ChevronRight)
+                    .size(IconSize::XSmall)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(summary)
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .tooltip(|cx| Tooltip::text("Expand Output", cx))
+            .on_click(cx.listener(|this, _, cx| this.toggle_outputs_collapsed(cx)))
+    }
+
+    /// Whether a saved output has no representations loaded.
+    fn nbformat_output_is_empty(&self, output_index: usize) -> bool {
+        match self.nbformat_outputs.get(output_index) {
+            Some(nbformat::v4::Output::DisplayData(display_data)) => {
+                display_data.data.content.is_empty()
+            }
+            Some(nbformat::v4::Output::ExecuteResult(execute_result)) => {
+                execute_result.data.content.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// Lists the payloads of an output that haven't been loaded, with a button to load them.
+    fn render_deferred_payloads(
+        &self,
+        output_index: usize,
+        deferred: &[DeferredPayload],
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let size: usize = deferred.iter().map(|payload| payload.size).sum();
+        let mime_types = deferred
+            .iter()
+            .map(|payload| mime_type_display_name(&payload.mime_type))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let cell_id = self.id.clone();
+
+        h_flex()
+            .gap_2()
+            .py_1()
+            .child(
+                Label::new(format!(
+                    "{mime_types} output not loaded ({:.1} MB)",
+                    size as f64 / (1024. * 1024.)
+                ))
+                .size(LabelSize::Small)
+                .color(Color::Muted),
+            )
+            .child(
+                Button::new(("load-output", output_index), "Load")
+                    .label_size(LabelSize::Small)
+                    .on_click(cx.listener(move |_, _, cx| {
+                        cx.emit(CellEvent::LoadOutput {
+                            cell_id: cell_id.clone(),
+                            output_index,
+                        })
+                    })),
+            )
+    }
+
+    fn render_outputs(&self, cx: &mut ViewContext<Self>) -> AnyElement {
+        if self.outputs_collapsed(cx) && self.has_outputs() {
+            return self.render_collapsed_outputs(cx).into_any_element();
+        }
+
+        let outputs = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let deferred = self
+                    .nbformat_outputs
+                    .get(index)
+                    .map(deferred_payloads)
+                    .unwrap_or_default();
+                // An output with nothing loaded would only say its media type isn't supported
+                let content = (deferred.is_empty() || !self.nbformat_output_is_empty(index))
+                    .then(|| output.content(cx))
+                    .flatten()
+                    .map(|content| {
+                        output.with_context_menu(("output-context-menu", index), content, cx)
+                    });
+                let deferred = (!deferred.is_empty())
+                    .then(|| self.render_deferred_payloads(index, &deferred, cx));
+
+                div()
+                    // .w_full()
+                    // .mt_3()
+                    // .p_3()
+                    // .rounded_md()
+                    // .bg(cx.theme().colors().editor_background)
+                    // .border(px(1.))
+                    // .border_color(cx.theme().colors().border)
+                    // .shadow_sm()
+                    .children(content)
+                    .children(deferred)
             })
+            .collect::<Vec<_>>();
+
+        if self.outputs_scrolled() {
+            div()
+                .id("scrolled-outputs")
+                .w_full()
+                .max_h(px(SCROLLED_OUTPUT_HEIGHT))
+                .overflow_y_scroll()
+                .children(outputs)
+                .into_any_element()
+        } else {
+            div().w_full().children(outputs).into_any_element()
+        }
     }
 }
 
@@ -505,13 +3136,89 @@ impl RenderableCell for CodeCell {
         &self.source
     }
 
+    fn comments(&self) -> &Model<CellComments> {
+        &self.comments
+    }
+
+    fn comments_menu_handle(&self) -> &PopoverMenuHandle<CellCommentsView> {
+        &self.comments_menu_handle
+    }
+
+    fn tags(&self) -> &Model<CellTags> {
+        &self.tags
+    }
+
+    fn tags_menu_handle(&self) -> &PopoverMenuHandle<CellTagsView> {
+        &self.tags_menu_handle
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn git_status(&self) -> Option<CellGitStatus> {
+        self.git_status
+    }
+
+    fn set_git_status(&mut self, status: Option<CellGitStatus>, cx: &mut ViewContext<Self>) {
+        if self.git_status != status {
+            self.git_status = status;
+            cx.notify();
+        }
+    }
+
+    fn blame_loader(&self) -> Option<&BlameLoader> {
+        self.blame_loader.as_ref()
+    }
+
+    fn set_blame_loader(&mut self, loader: Option<BlameLoader>, cx: &mut ViewContext<Self>) {
+        self.blame_loader = loader;
+        cx.notify();
+    }
+
+    fn blame_menu_handle(&self) -> &PopoverMenuHandle<CellBlameView> {
+        &self.blame_menu_handle
+    }
+
+    fn set_editable(&mut self, editable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "editable", editable.into());
+        self.modified = true;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    fn set_deletable(&mut self, deletable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "deletable", deletable.into());
+        self.modified = true;
+        cx.notify();
+    }
+
+    fn set_slide_type(&mut self, slide_type: Option<SlideType>, cx: &mut ViewContext<Self>) {
+        set_cell_slide_type(&mut self.metadata, slide_type);
+        self.modified = true;
+        cx.notify();
+    }
+
     fn control(&self, cx: &ViewContext<Self>) -> Option<CellControl> {
-        let cell_control = if self.has_outputs() {
-            CellControl::new("rerun-cell", CellControlType::RerunCell)
-        } else {
-            CellControl::new("run-cell", CellControlType::RunCell)
-                .on_click(cx.listener(move |this, _, cx| this.run(cx)))
-        };
+        if self.read_only {
+            return None;
+        }
+
+        let cell_control =
+            if let ExecutionStatus::Queued = self.execution_status {
+                let cell_id = self.id.clone();
+                CellControl::new("cancel-queued-cell", CellControlType::CancelQueuedCell)
+                    .tooltip("Queued, click to cancel")
+                    .on_click(cx.listener(move |_, _, cx| {
+                        cx.emit(CellEvent::CancelExecution(cell_id.clone()))
+                    }))
+            } else if self.has_outputs() {
+                CellControl::new("rerun-cell", CellControlType::RerunCell)
+                    .on_click(cx.listener(move |this, _, cx| this.run(cx)))
+            } else {
+                CellControl::new("run-cell", CellControlType::RunCell)
+                    .on_click(cx.listener(move |this, _, cx| this.run(cx)))
+            };
 
         Some(cell_control)
     }
@@ -537,7 +3244,7 @@ impl RenderableCell for CodeCell {
 
 impl RunnableCell for CodeCell {
     fn run(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Running code cell: {}", self.id);
+        cx.emit(CellEvent::Run(self.id.clone()));
     }
 
     fn execution_count(&self) -> Option<i32> {
@@ -567,6 +3274,7 @@ impl Render for CodeCell {
                     .gap(DynamicSpacing::Base08.rems(cx))
                     .bg(self.selected_bg_color(cx))
                     .child(self.gutter(cx))
+                    .child(self.render_execution_count(Some("In"), cx))
                     .child(
                         div().py_1p5().w_full().child(
                             div()
@@ -579,7 +3287,12 @@ impl Render for CodeCell {
                                 .border_1()
                                 .border_color(cx.theme().colors().border)
                                 .bg(cx.theme().colors().editor_background)
-                                .child(div().w_full().child(self.editor.clone())),
+                                .child(
+                                    div()
+                                        .key_context("CellEditor")
+                                        .w_full()
+                                        .child(self.editor.clone()),
+                                ),
                         ),
                     ),
             )
@@ -593,6 +3306,7 @@ impl Render for CodeCell {
                     .gap(DynamicSpacing::Base08.rems(cx))
                     .bg(self.selected_bg_color(cx))
                     .child(self.gutter_output(cx))
+                    .child(self.render_execution_count(self.has_outputs().then_some("Out"), cx))
                     .child(
                         div().py_1p5().w_full().child(
                             div()
@@ -605,59 +3319,238 @@ impl Render for CodeCell {
                                 .border_1()
                                 // .border_color(cx.theme().colors().border)
                                 // .bg(cx.theme().colors().editor_background)
-                                .child(div().w_full().children(self.outputs.iter().map(
-                                    |output| {
-                                        let content = match output {
-                                            Output::Plain { content, .. } => {
-                                                Some(content.clone().into_any_element())
-                                            }
-                                            Output::Markdown { content, .. } => {
-                                                Some(content.clone().into_any_element())
-                                            }
-                                            Output::Stream { content, .. } => {
-                                                Some(content.clone().into_any_element())
-                                            }
-                                            Output::Image { content, .. } => {
-                                                Some(content.clone().into_any_element())
-                                            }
-                                            Output::Message(message) => Some(
-                                                div().child(message.clone()).into_any_element(),
-                                            ),
-                                            Output::Table { content, .. } => {
-                                                Some(content.clone().into_any_element())
-                                            }
-                                            Output::ErrorOutput(error_view) => {
-                                                error_view.render(cx)
-                                            }
-                                            Output::ClearOutputWaitMarker => None,
-                                        };
-
-                                        div()
-                                            // .w_full()
-                                            // .mt_3()
-                                            // .p_3()
-                                            // .rounded_md()
-                                            // .bg(cx.theme().colors().editor_background)
-                                            // .border(px(1.))
-                                            // .border_color(cx.theme().colors().border)
-                                            // .shadow_sm()
-                                            .children(content)
-                                    },
-                                ))),
+                                .child(
+                                    div()
+                                        .w_full()
+                                        .child(self.render_outputs(cx))
+                                        .children(self.render_execution_error())
+                                        .children(self.render_package_install(cx))
+                                        .children(self.render_input_prompt(cx)),
+                                ),
                         ),
                     ),
             )
+            .when(
+                self.executed_by.is_some() || self.elapsed_time().is_some(),
+                |this| {
+                    this.child(
+                        h_flex()
+                            .w_full()
+                            .pr_6()
+                            .gap_2()
+                            .justify_end()
+                            .bg(self.selected_bg_color(cx))
+                            .children(self.render_executed_by())
+                            .children(self.render_elapsed_time()),
+                    )
+                },
+            )
+            .children(self.render_tags(cx))
             // TODO: Move base cell render into trait impl so we don't have to repeat this
             .children(self.cell_position_spacer(false, cx))
     }
 }
 
+/// Formats raw cells are commonly converted to, offered when choosing a cell's format.
+const RAW_CELL_FORMATS: &[(&str, &str)] = &[
+    ("reStructuredText", "text/restructuredtext"),
+    ("HTML", "text/html"),
+    ("LaTeX", "text/latex"),
+    ("Markdown", "text/markdown"),
+    ("Python", "text/x-python"),
+    ("AsciiDoc", "text/asciidoc"),
+];
+
+/// The language to highlight a raw cell with, for the formats Zed has a language for.
+fn raw_format_language(format: &str) -> Option<&'static str> {
+    match format {
+        "text/html" => Some("HTML"),
+        "text/markdown" => Some("Markdown"),
+        "text/x-python" => Some("Python"),
+        _ => None,
+    }
+}
+
+/// Reads a raw cell's format from its metadata, preferring `format` over `raw_mimetype`.
+fn raw_cell_format(metadata: &CellMetadata) -> Option<String> {
+    let metadata = serde_json::to_value(metadata).log_err()?;
+    ["format", "raw_mimetype"].iter().find_map(|key| {
+        metadata
+            .get(*key)
+            .and_then(|format| format.as_str())
+            .filter(|format| !format.is_empty())
+            .map(ToString::to_string)
+    })
+}
+
+/// A cell that's passed through untouched when the notebook is converted, e.g. by nbconvert.
+/// The format it's meant for is saved in the `format` metadata, or `raw_mimetype` by older
+/// versions of Jupyter.
 pub struct RawCell {
     id: CellId,
     metadata: CellMetadata,
+    /// Whether the cell's metadata changed since the last save, e.g. it was locked.
+    modified: bool,
     source: String,
+    buffer: Model<Buffer>,
+    editor: View<Editor>,
+    _editor_subscription: Subscription,
+    languages: Arc<LanguageRegistry>,
+    language_task: Task<()>,
+    comments: Model<CellComments>,
+    comments_menu_handle: PopoverMenuHandle<CellCommentsView>,
+    tags: Model<CellTags>,
+    tags_menu_handle: PopoverMenuHandle<CellTagsView>,
+    read_only: bool,
     selected: bool,
     cell_position: Option<CellPosition>,
+    /// How the cell differs from HEAD, shown in its gutter.
+    git_status: Option<CellGitStatus>,
+    /// Loads the commits that touched the cell, set while the notebook is tracked by git.
+    blame_loader: Option<BlameLoader>,
+    blame_menu_handle: PopoverMenuHandle<CellBlameView>,
+}
+
+impl EventEmitter<CellEvent> for RawCell {}
+
+impl RawCell {
+    pub fn editor(&self) -> &View<Editor> {
+        &self.editor
+    }
+
+    /// The current contents of the cell's editor.
+    pub fn current_source(&self, cx: &AppContext) -> String {
+        self.buffer.read(cx).text()
+    }
+
+    pub fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.modified
+            || self.comments.read(cx).is_modified()
+            || self.tags.read(cx).is_modified()
+            || self.buffer.read(cx).is_dirty()
+    }
+
+    pub fn did_save(&mut self, cx: &mut ViewContext<Self>) {
+        self.modified = false;
+        self.comments.update(cx, |comments, _| comments.did_save());
+        self.tags.update(cx, |tags, _| tags.did_save());
+        self.buffer.update(cx, |buffer, cx| {
+            let version = buffer.version();
+            buffer.did_save(version, None, cx);
+        });
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut ViewContext<Self>) {
+        self.read_only = read_only;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    /// Locks the editor while the notebook is read-only or the cell isn't editable.
+    fn update_editor_read_only(&mut self, cx: &mut ViewContext<Self>) {
+        let read_only = self.read_only || !self.is_editable();
+        self.editor
+            .update(cx, |editor, _| editor.set_read_only(read_only));
+    }
+
+    /// The MIME type of the format the cell is meant for, if it has one.
+    pub fn format(&self) -> Option<String> {
+        raw_cell_format(&self.metadata)
+    }
+
+    /// Sets the format the cell is meant for, keeping `raw_mimetype` in sync for older versions
+    /// of Jupyter when the cell has it.
+    pub fn set_format(&mut self, format: Option<&str>, cx: &mut ViewContext<Self>) {
+        let has_raw_mimetype = serde_json::to_value(&self.metadata)
+            .ok()
+            .map_or(false, |metadata| metadata.get("raw_mimetype").is_some());
+        for key in ["format", "raw_mimetype"] {
+            if key == "raw_mimetype" && !has_raw_mimetype {
+                continue;
+            }
+            match format {
+                Some(format) => write_metadata_field(&mut self.metadata, key, format.into()),
+                None => remove_metadata_field(&mut self.metadata, key),
+            }
+        }
+        self.modified = true;
+        self.update_language(cx);
+        cx.notify();
+    }
+
+    fn update_language(&mut self, cx: &mut ViewContext<Self>) {
+        let language_name = self.format().as_deref().and_then(raw_format_language);
+        let languages = self.languages.clone();
+        let buffer = self.buffer.clone();
+        self.language_task = cx.spawn(|_, mut cx| async move {
+            let language = match language_name {
+                Some(name) => languages.language_for_name(name).await.log_err(),
+                None => None,
+            };
+            buffer
+                .update(&mut cx, |buffer, cx| buffer.set_language(language, cx))
+                .log_err();
+        });
+    }
+
+    /// The label showing the cell's format, with a menu to change it.
+    fn render_format(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let format = self.format();
+        let label: SharedString = match format.as_deref() {
+            Some(format) => RAW_CELL_FORMATS
+                .iter()
+                .find(|(_, mime_type)| *mime_type == format)
+                .map_or_else(|| format.to_string(), |(name, _)| name.to_string())
+                .into(),
+            None => "Raw".into(),
+        };
+        let this = cx.view().downgrade();
+
+        PopoverMenu::new("raw-cell-format")
+            .menu(move |cx| {
+                let this = this.clone();
+                let format = format.clone();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    let menu = menu.toggleable_entry(
+                        "Unformatted",
+                        format.is_none(),
+                        IconPosition::End,
+                        None,
+                        {
+                            let this = this.clone();
+                            move |cx| {
+                                this.update(cx, |this, cx| this.set_format(None, cx)).ok();
+                            }
+                        },
+                    );
+                    RAW_CELL_FORMATS
+                        .iter()
+                        .fold(menu, |menu, (name, mime_type)| {
+                            let this = this.clone();
+                            menu.toggleable_entry(
+                                *name,
+                                format.as_deref() == Some(*mime_type),
+                                IconPosition::End,
+                                None,
+                                move |cx| {
+                                    this.update(cx, |this, cx| {
+                                        this.set_format(Some(*mime_type), cx)
+                                    })
+                                    .ok();
+                                },
+                            )
+                        })
+                }))
+            })
+            .trigger(
+                Button::new("raw-cell-format-trigger", label)
+                    .label_size(LabelSize::XSmall)
+                    .color(Color::Muted)
+                    .disabled(self.read_only)
+                    .tooltip(|cx| Tooltip::text("Raw Cell Format", cx)),
+            )
+            .anchor(AnchorCorner::TopRight)
+    }
 }
 
 impl RenderableCell for RawCell {
@@ -679,6 +3572,69 @@ impl RenderableCell for RawCell {
         &self.source
     }
 
+    fn comments(&self) -> &Model<CellComments> {
+        &self.comments
+    }
+
+    fn comments_menu_handle(&self) -> &PopoverMenuHandle<CellCommentsView> {
+        &self.comments_menu_handle
+    }
+
+    fn tags(&self) -> &Model<CellTags> {
+        &self.tags
+    }
+
+    fn tags_menu_handle(&self) -> &PopoverMenuHandle<CellTagsView> {
+        &self.tags_menu_handle
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn git_status(&self) -> Option<CellGitStatus> {
+        self.git_status
+    }
+
+    fn set_git_status(&mut self, status: Option<CellGitStatus>, cx: &mut ViewContext<Self>) {
+        if self.git_status != status {
+            self.git_status = status;
+            cx.notify();
+        }
+    }
+
+    fn blame_loader(&self) -> Option<&BlameLoader> {
+        self.blame_loader.as_ref()
+    }
+
+    fn set_blame_loader(&mut self, loader: Option<BlameLoader>, cx: &mut ViewContext<Self>) {
+        self.blame_loader = loader;
+        cx.notify();
+    }
+
+    fn blame_menu_handle(&self) -> &PopoverMenuHandle<CellBlameView> {
+        &self.blame_menu_handle
+    }
+
+    fn set_editable(&mut self, editable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "editable", editable.into());
+        self.modified = true;
+        self.update_editor_read_only(cx);
+        cx.notify();
+    }
+
+    fn set_deletable(&mut self, deletable: bool, cx: &mut ViewContext<Self>) {
+        write_metadata_field(&mut self.metadata, "deletable", deletable.into());
+        self.modified = true;
+        cx.notify();
+    }
+
+    fn set_slide_type(&mut self, slide_type: Option<SlideType>, cx: &mut ViewContext<Self>) {
+        set_cell_slide_type(&mut self.metadata, slide_type);
+        self.modified = true;
+        cx.notify();
+    }
+
     fn selected(&self) -> bool {
         self.selected
     }
@@ -714,17 +3670,116 @@ impl Render for RawCell {
                     .bg(self.selected_bg_color(cx))
                     .child(self.gutter(cx))
                     .child(
-                        div()
-                            .flex()
-                            .size_full()
-                            .flex_1()
-                            .p_3()
-                            .font_ui(cx)
-                            .text_size(TextSize::Default.rems(cx))
-                            .child(self.source.clone()),
+                        v_flex().py_1p5().w_full().child(
+                            v_flex()
+                                .size_full()
+                                .flex_1()
+                                .pt_1()
+                                .pb_3()
+                                .px_5()
+                                .rounded_lg()
+                                .border_1()
+                                .border_color(cx.theme().colors().border)
+                                .child(
+                                    h_flex()
+                                        .w_full()
+                                        .justify_end()
+                                        .child(self.render_format(cx)),
+                                )
+                                .child(
+                                    div()
+                                        .key_context("CellEditor")
+                                        .w_full()
+                                        .child(self.editor.clone()),
+                                ),
+                        ),
                     ),
             )
+            .children(self.render_tags(cx))
             // TODO: Move base cell render into trait impl so we don't have to repeat this
             .children(self.cell_position_spacer(false, cx))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cells_round_trip_through_clipboard() {
+        let cells = vec![
+            Cell::empty_nbformat_cell(CellType::Markdown),
+            Cell::empty_nbformat_cell(CellType::Code),
+        ];
+        let text = Cell::clipboard_text(&cells).unwrap();
+
+        let pasted = Cell::nbformat_cells_from_clipboard(&text).unwrap();
+        assert_eq!(pasted.len(), 2);
+        assert!(matches!(pasted[0], nbformat::v4::Cell::Markdown { .. }));
+        assert!(matches!(pasted[1], nbformat::v4::Cell::Code { .. }));
+        assert_ne!(pasted[0].id(), cells[0].id());
+    }
+
+    #[test]
+    fn test_new_cell_ids() {
+        let id = new_cell_id();
+        assert_ne!(id, new_cell_id());
+
+        let value = serde_json::to_value(&id).unwrap();
+        let id_text = value.as_str().unwrap();
+        assert!(id_text.len() <= 64);
+        assert!(id_text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let mut cell = Cell::empty_nbformat_cell(CellType::Code);
+        set_nbformat_cell_id(&mut cell, id.clone());
+        assert_eq!(cell.id(), &id);
+    }
+
+    #[test]
+    fn test_clipboard_text_that_is_not_cells() {
+        assert!(Cell::nbformat_cells_from_clipboard("print('hello')").is_none());
+        assert!(Cell::nbformat_cells_from_clipboard("[]").is_none());
+        assert!(Cell::nbformat_cells_from_clipboard(r#"{"name": "value"}"#).is_none());
+    }
+
+    #[test]
+    fn test_cells_are_editable_and_deletable_unless_locked() {
+        let mut metadata: CellMetadata = serde_json::from_value(serde_json::json!({
+            "tags": ["parameters"]
+        }))
+        .unwrap();
+        assert!(metadata_permission(&metadata, "editable"));
+        assert!(metadata_permission(&metadata, "deletable"));
+
+        write_metadata_field(&mut metadata, "deletable", false.into());
+        assert!(metadata_permission(&metadata, "editable"));
+        assert!(!metadata_permission(&metadata, "deletable"));
+
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value["tags"], serde_json::json!(["parameters"]));
+    }
+
+    #[test]
+    fn test_raw_cell_format() {
+        let metadata = |value| serde_json::from_value::<CellMetadata>(value).unwrap();
+
+        assert_eq!(raw_cell_format(&metadata(serde_json::json!({}))), None);
+        assert_eq!(
+            raw_cell_format(&metadata(serde_json::json!({"raw_mimetype": "text/latex"}))),
+            Some("text/latex".to_string())
+        );
+        assert_eq!(
+            raw_cell_format(&metadata(serde_json::json!({
+                "format": "text/html",
+                "raw_mimetype": "text/latex"
+            }))),
+            Some("text/html".to_string())
+        );
+
+        let mut cell_metadata = metadata(serde_json::json!({"format": "text/html"}));
+        remove_metadata_field(&mut cell_metadata, "format");
+        assert_eq!(raw_cell_format(&cell_metadata), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! # Cell Document
+//!
+//! Language servers work on files, not notebooks. To give code cells diagnostics that know about
+//! the symbols defined in earlier cells, the notebook's code cells are joined into one document,
+//! which is opened next to the notebook without ever being saved, e.g. `.analysis.ipynb.py` for
+//! `analysis.ipynb`. The diagnostics language servers report for it are mapped back to the cells
+//! they fall in.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use language::PointUtf16;
+use nbformat::v4::CellId;
+
+use crate::magics::is_cell_magic;
+
+/// Where a cell's source starts in the document, and how many rows it spans.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CellRange {
+    pub cell_id: CellId,
+    pub start_row: u32,
+    pub row_count: u32,
+}
+
+/// The code cells of a notebook as one document, one after the other.
+#[derive(Default)]
+pub(crate) struct CellDocument {
+    text: String,
+    cells: Vec<CellRange>,
+}
+
+impl CellDocument {
+    /// Joins the source of cells. With `mask_magics`, IPython magics and shell commands like
+    /// `%matplotlib inline` or `!pip install` are commented out, so they aren't reported as
+    /// syntax errors, as are whole cells that start with a cell magic like `%%bash`. Masking
+    /// keeps every column where it was.
+    pub fn new<'a>(cells: impl IntoIterator<Item = (CellId, &'a str)>, mask_magics: bool) -> Self {
+        let mut text = String::new();
+        let mut ranges = Vec::new();
+        let mut row = 0;
+
+        for (cell_id, source) in cells {
+            let mut row_count = 0;
+            let is_cell_magic = mask_magics && is_cell_magic(source);
+            for line in source.split('\n') {
+                if is_cell_magic {
+                    text.push_str(&masked_line(line));
+                } else if mask_magics {
+                    text.push_str(&masked_magic(line));
+                } else {
+                    text.push_str(line);
+                }
+                text.push('\n');
+                row_count += 1;
+            }
+
+            ranges.push(CellRange {
+                cell_id,
+                start_row: row,
+                row_count,
+            });
+            row += row_count;
+        }
+
+        Self {
+            text,
+            cells: ranges,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cells(&self) -> &[CellRange] {
+        &self.cells
+    }
+
+    /// Maps a range in the document to the cell it starts in, and the same range within that
+    /// cell. Ranges running past the end of the cell are cut off at its last row.
+    pub fn cell_range(&self, range: Range<PointUtf16>) -> Option<(&CellId, Range<PointUtf16>)> {
+        let index = self
+            .cells
+            .partition_point(|cell| cell.start_row + cell.row_count <= range.start.row);
+        let cell = self.cells.get(index)?;
+        if range.start.row < cell.start_row {
+            return None;
+        }
+
+        let last_row = cell.row_count - 1;
+        let start = PointUtf16::new(range.start.row - cell.start_row, range.start.column);
+        let end = if range.end.row - cell.start_row > last_row {
+            PointUtf16::new(last_row, u32::MAX)
+        } else {
+            PointUtf16::new(range.end.row - cell.start_row, range.end.column)
+        };
+
+        Some((&cell.cell_id, start..end))
+    }
+}
+
+fn masked_line(line: &str) -> String {
+    let indent = line.len() - line.trim_start().len();
+    match line[indent..].chars().next() {
+        Some(char) => format!("{}#{}", &line[..indent], &line[indent + char.len_utf8()..]),
+        None => line.to_string(),
+    }
+}
+
+fn masked_magic(line: &str) -> String {
+    let indent = line.len() - line.trim_start().len();
+    match line[indent..].chars().next() {
+        Some('%') | Some('!') => format!("{}#{}", &line[..indent], &line[indent + 1..]),
+        _ => line.to_string(),
+    }
+}
+
+/// The path the document of a notebook is opened at, hidden next to the notebook.
+pub(crate) fn cell_document_path(notebook_path: &Path, extension: &str) -> PathBuf {
+    let file_name = notebook_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "notebook".to_string());
+    notebook_path.with_file_name(format!(".{file_name}.{extension}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::collab::test_cell_id;
+    use super::*;
+
+    #[test]
+    fn test_cell_document_maps_rows_to_cells() {
+        let document = CellDocument::new(
+            [
+                (test_cell_id("imports"), "%matplotlib inline\nimport os"),
+                (test_cell_id("body"), "print(os.getcwd())\n  !ls\nx = ("),
+            ],
+            true,
+        );
+
+        assert_eq!(
+            document.text(),
+            "#matplotlib inline\nimport os\nprint(os.getcwd())\n  #ls\nx = (\n"
+        );
+
+        let (id, range) = document
+            .cell_range(PointUtf16::new(2, 6)..PointUtf16::new(2, 8))
+            .unwrap();
+        assert_eq!(id, &test_cell_id("body"));
+        assert_eq!(range, PointUtf16::new(0, 6)..PointUtf16::new(0, 8));
+
+        let (id, range) = document
+            .cell_range(PointUtf16::new(1, 0)..PointUtf16::new(2, 3))
+            .unwrap();
+        assert_eq!(id, &test_cell_id("imports"));
+        assert_eq!(range, PointUtf16::new(1, 0)..PointUtf16::new(1, u32::MAX));
+
+        assert!(document
+            .cell_range(PointUtf16::new(5, 0)..PointUtf16::new(5, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_cell_document_masks_cell_magics() {
+        let document = CellDocument::new(
+            [
+                (test_cell_id("shell"), "%%bash\nls -la\n\necho done"),
+                (test_cell_id("code"), "x = 1"),
+            ],
+            true,
+        );
+        assert_eq!(document.text(), "#%bash\n#s -la\n\n#cho done\nx = 1\n");
+
+        let document = CellDocument::new([(test_cell_id("shell"), "%%bash\nls")], false);
+        assert_eq!(document.text(), "%%bash\nls\n");
+    }
+
+    #[test]
+    fn test_cell_document_path() {
+        assert_eq!(
+            cell_document_path(Path::new("notebooks/analysis.ipynb"), "py"),
+            PathBuf::from("notebooks/.analysis.ipynb.py")
+        );
+    }
+}
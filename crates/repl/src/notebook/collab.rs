@@ -0,0 +1,163 @@
+//! # Following Notebooks
+//!
+//! Collaborators following someone in a notebook see its cells run: which cells are queued or
+//! executing, their outputs as they stream in, and their execution counts. The leader sends the
+//! whole notebook when it's first followed, then the state of each code cell whose execution
+//! changes.
+//!
+//! The kernel runs on the leader's machine, so guests of a shared project can only run cells when
+//! the host lets them: running a cell then asks the host to run it on its kernel. Each execution
+//! records the login of whoever ran it, so everyone can tell whose code produced which outputs.
+
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use client::{proto, AnyProtoClient, TypedEnvelope};
+use gpui::{AsyncAppContext, Model, SharedString};
+use nbformat::v4::CellId;
+use project::{Project, ProjectPath, WorktreeId};
+use workspace::{ItemHandle as _, Workspace};
+
+use super::NotebookEditor;
+use crate::outputs::ExecutionStatus;
+
+pub(crate) fn init(client: &AnyProtoClient) {
+    client.add_model_request_handler(handle_run_notebook_cell);
+}
+
+/// Runs a cell on the host's kernel for a guest, in the host's editor for the notebook.
+async fn handle_run_notebook_cell(
+    project: Model<Project>,
+    envelope: TypedEnvelope<proto::RunNotebookCell>,
+    mut cx: AsyncAppContext,
+) -> Result<proto::Ack> {
+    let peer_id = envelope
+        .original_sender_id
+        .context("missing original_sender_id on RunNotebookCell request")?;
+    let cell_id = cell_id_from_proto(envelope.payload.cell_id).context("invalid cell id")?;
+    let project_path = ProjectPath {
+        worktree_id: WorktreeId::from_proto(envelope.payload.worktree_id),
+        path: Path::new(&envelope.payload.path).into(),
+    };
+
+    cx.update(|cx| {
+        let user_store = project.read(cx).user_store();
+        let login = project
+            .read(cx)
+            .collaborators()
+            .get(&peer_id)
+            .and_then(|collaborator| user_store.read(cx).get_cached_user(collaborator.user_id))
+            .map(|user| SharedString::from(user.github_login.clone()))
+            .context("unknown collaborator")?;
+
+        for window in cx.windows() {
+            let Some(workspace) = window.downcast::<Workspace>() else {
+                continue;
+            };
+            let Some(editor) = workspace
+                .read(cx)
+                .ok()
+                .filter(|workspace| workspace.project() == &project)
+                .and_then(|workspace| {
+                    workspace
+                        .items_of_type::<NotebookEditor>(cx)
+                        .find(|editor| editor.project_path(cx).as_ref() == Some(&project_path))
+                })
+            else {
+                continue;
+            };
+
+            return workspace.update(cx, |_, cx| {
+                editor.update(cx, |editor, cx| {
+                    editor.execute_cell_for_guest(cell_id, login, cx)
+                })
+            })?;
+        }
+
+        anyhow::bail!("the notebook isn't open on the host")
+    })??;
+
+    Ok(proto::Ack {})
+}
+
+pub(crate) fn cell_id_to_proto(cell_id: &CellId) -> String {
+    match serde_json::to_value(cell_id) {
+        Ok(serde_json::Value::String(id)) => id,
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn cell_id_from_proto(id: String) -> Option<CellId> {
+    serde_json::from_value(serde_json::Value::String(id)).ok()
+}
+
+/// Parses a cell id written out in a test.
+#[cfg(test)]
+pub(crate) fn test_cell_id(id: &str) -> CellId {
+    cell_id_from_proto(id.into()).expect("cell ids written in tests are valid")
+}
+
+/// The status to show followers, with the error of a failed execution.
+pub(crate) fn execution_status_to_proto(
+    status: &ExecutionStatus,
+) -> (proto::NotebookExecutionStatus, Option<String>) {
+    match status {
+        ExecutionStatus::Queued => (proto::NotebookExecutionStatus::Queued, None),
+        ExecutionStatus::ConnectingToKernel | ExecutionStatus::Executing => {
+            (proto::NotebookExecutionStatus::Executing, None)
+        }
+        ExecutionStatus::Finished => (proto::NotebookExecutionStatus::Finished, None),
+        ExecutionStatus::KernelErrored(error) => {
+            (proto::NotebookExecutionStatus::Errored, Some(error.clone()))
+        }
+        ExecutionStatus::Unknown
+        | ExecutionStatus::ShuttingDown
+        | ExecutionStatus::Shutdown
+        | ExecutionStatus::Restarting => (proto::NotebookExecutionStatus::Idle, None),
+    }
+}
+
+pub(crate) fn execution_status_from_proto(
+    status: proto::NotebookExecutionStatus,
+    error: Option<String>,
+) -> ExecutionStatus {
+    match status {
+        proto::NotebookExecutionStatus::Idle => ExecutionStatus::Unknown,
+        proto::NotebookExecutionStatus::Queued => ExecutionStatus::Queued,
+        proto::NotebookExecutionStatus::Executing => ExecutionStatus::Executing,
+        proto::NotebookExecutionStatus::Finished => ExecutionStatus::Finished,
+        proto::NotebookExecutionStatus::Errored => {
+            ExecutionStatus::KernelErrored(error.unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_execution_status_round_trip() {
+        for status in [
+            ExecutionStatus::Queued,
+            ExecutionStatus::Executing,
+            ExecutionStatus::Finished,
+            ExecutionStatus::KernelErrored("Kernel died".into()),
+        ] {
+            let (proto_status, error) = execution_status_to_proto(&status);
+            assert_eq!(execution_status_from_proto(proto_status, error), status);
+        }
+
+        let (proto_status, error) = execution_status_to_proto(&ExecutionStatus::Restarting);
+        assert_eq!(
+            execution_status_from_proto(proto_status, error),
+            ExecutionStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_cell_id_round_trip() {
+        let id = cell_id_from_proto("a1b2c3".into()).unwrap();
+        assert_eq!(cell_id_to_proto(&id), "a1b2c3");
+    }
+}
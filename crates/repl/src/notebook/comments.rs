@@ -0,0 +1,452 @@
+//! # Cell Comments
+//!
+//! Threads of comments attached to a cell, for reviewing a notebook asynchronously. They're saved
+//! in the cell's metadata under `zed.comments`, so they travel with the notebook file and other
+//! Jupyter frontends leave them alone:
+//!
+//! ```json
+//! "metadata": { "zed": { "comments": [
+//!     { "id": "…", "resolved": false, "comments": [
+//!         { "id": "…", "author": "octocat", "created_at": "2024-11-02T10:15:00Z", "body": "Why 0.3?" }
+//!     ] }
+//! ] } }
+//! ```
+//!
+//! A cell with comments shows a marker in its gutter, which opens the cell's threads in a popover.
+
+use chrono::{DateTime, Local, Utc};
+use editor::Editor;
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model, ModelContext,
+    Subscription, View,
+};
+use nbformat::v4::CellMetadata;
+use serde::{Deserialize, Serialize};
+use ui::{prelude::*, IconButtonShape, Tooltip};
+use util::ResultExt as _;
+use workspace::AppState;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CellComment {
+    pub id: String,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+    pub body: String,
+}
+
+/// A comment on a cell and the replies to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommentThread {
+    pub id: String,
+    #[serde(default)]
+    pub resolved: bool,
+    pub comments: Vec<CellComment>,
+}
+
+/// Reads the comment threads saved in a cell's metadata. Threads that can't be parsed are skipped.
+pub(crate) fn comment_threads(metadata: &CellMetadata) -> Vec<CommentThread> {
+    let Some(metadata) = serde_json::to_value(metadata).log_err() else {
+        return Vec::new();
+    };
+    let Some(threads) = metadata
+        .get("zed")
+        .and_then(|zed| zed.get("comments"))
+        .and_then(|comments| comments.as_array())
+    else {
+        return Vec::new();
+    };
+
+    threads
+        .iter()
+        .filter_map(|thread| serde_json::from_value(thread.clone()).ok())
+        .collect()
+}
+
+/// Saves comment threads in a cell's metadata, leaving out the `zed` field when there are none.
+pub(crate) fn set_comment_threads(metadata: &mut CellMetadata, threads: &[CommentThread]) {
+    let Some(mut value) = serde_json::to_value(&*metadata).log_err() else {
+        return;
+    };
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+
+    let zed = fields
+        .entry("zed")
+        .or_insert_with(|| serde_json::Value::Object(Default::default()));
+    if let Some(zed) = zed.as_object_mut() {
+        if threads.is_empty() {
+            zed.remove("comments");
+        } else if let Some(threads) = serde_json::to_value(threads).log_err() {
+            zed.insert("comments".into(), threads);
+        }
+    }
+    if fields
+        .get("zed")
+        .and_then(|zed| zed.as_object())
+        .map_or(false, |zed| zed.is_empty())
+    {
+        fields.remove("zed");
+    }
+
+    if let Some(updated) = serde_json::from_value(value).log_err() {
+        *metadata = updated;
+    }
+}
+
+/// The name comments are posted under: the GitHub login of the signed in user, if there is one.
+fn current_author(cx: &AppContext) -> String {
+    AppState::try_global(cx)
+        .and_then(|app_state| app_state.upgrade())
+        .and_then(|app_state| app_state.user_store.read(cx).current_user())
+        .map(|user| user.github_login.clone())
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+/// The comment threads of a cell, shared between the cell and its popover.
+pub struct CellComments {
+    threads: Vec<CommentThread>,
+    /// Whether the threads changed since the notebook was last saved.
+    modified: bool,
+}
+
+impl CellComments {
+    pub fn new(metadata: &CellMetadata) -> Self {
+        Self {
+            threads: comment_threads(metadata),
+            modified: false,
+        }
+    }
+
+    pub fn threads(&self) -> &[CommentThread] {
+        &self.threads
+    }
+
+    pub fn unresolved_count(&self) -> usize {
+        self.threads
+            .iter()
+            .filter(|thread| !thread.resolved)
+            .count()
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn did_save(&mut self) {
+        self.modified = false;
+    }
+
+    /// Writes the threads into the metadata the cell is saved with.
+    pub fn write_to(&self, metadata: &mut CellMetadata) {
+        set_comment_threads(metadata, &self.threads);
+    }
+
+    fn new_comment(body: String, cx: &AppContext) -> CellComment {
+        CellComment {
+            id: uuid::Uuid::new_v4().to_string(),
+            author: current_author(cx),
+            created_at: Utc::now(),
+            body,
+        }
+    }
+
+    pub fn start_thread(&mut self, body: String, cx: &mut ModelContext<Self>) {
+        self.threads.push(CommentThread {
+            id: uuid::Uuid::new_v4().to_string(),
+            resolved: false,
+            comments: vec![Self::new_comment(body, cx)],
+        });
+        self.modified = true;
+        cx.notify();
+    }
+
+    /// Adds a reply to a thread, reopening it if it was resolved.
+    pub fn reply(&mut self, thread_id: &str, body: String, cx: &mut ModelContext<Self>) {
+        let comment = Self::new_comment(body, cx);
+        let Some(thread) = self
+            .threads
+            .iter_mut()
+            .find(|thread| thread.id == thread_id)
+        else {
+            return;
+        };
+        thread.comments.push(comment);
+        thread.resolved = false;
+        self.modified = true;
+        cx.notify();
+    }
+
+    pub fn set_resolved(&mut self, thread_id: &str, resolved: bool, cx: &mut ModelContext<Self>) {
+        let Some(thread) = self
+            .threads
+            .iter_mut()
+            .find(|thread| thread.id == thread_id)
+        else {
+            return;
+        };
+        if thread.resolved != resolved {
+            thread.resolved = resolved;
+            self.modified = true;
+            cx.notify();
+        }
+    }
+
+    pub fn delete_thread(&mut self, thread_id: &str, cx: &mut ModelContext<Self>) {
+        let thread_count = self.threads.len();
+        self.threads.retain(|thread| thread.id != thread_id);
+        if self.threads.len() != thread_count {
+            self.modified = true;
+            cx.notify();
+        }
+    }
+}
+
+/// The popover listing a cell's comment threads, with an editor to start a thread or reply to one.
+pub struct CellCommentsView {
+    comments: Model<CellComments>,
+    editor: View<Editor>,
+    /// The thread the editor replies to, or `None` to start a new thread.
+    replying_to: Option<String>,
+    _subscription: Subscription,
+}
+
+impl CellCommentsView {
+    pub fn new(comments: Model<CellComments>, cx: &mut ViewContext<Self>) -> Self {
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::auto_height(6, cx);
+            editor.set_placeholder_text("Add a comment…", cx);
+            editor
+        });
+        cx.focus_view(&editor);
+        let subscription = cx.observe(&comments, |_, _, cx| cx.notify());
+
+        Self {
+            comments,
+            editor,
+            replying_to: None,
+            _subscription: subscription,
+        }
+    }
+
+    fn reply_to(&mut self, thread_id: Option<String>, cx: &mut ViewContext<Self>) {
+        let placeholder = if thread_id.is_some() {
+            "Reply…"
+        } else {
+            "Add a comment…"
+        };
+        self.replying_to = thread_id;
+        self.editor.update(cx, |editor, cx| {
+            editor.set_placeholder_text(placeholder, cx);
+        });
+        cx.focus_view(&self.editor);
+        cx.notify();
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let body = self.editor.read(cx).text(cx).trim().to_string();
+        if body.is_empty() {
+            return;
+        }
+
+        let replying_to = self.replying_to.clone();
+        self.comments.update(cx, |comments, cx| match replying_to {
+            Some(thread_id) => comments.reply(&thread_id, body, cx),
+            None => comments.start_thread(body, cx),
+        });
+        self.editor.update(cx, |editor, cx| editor.clear(cx));
+        self.reply_to(None, cx);
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
+        if self.replying_to.is_some() {
+            self.reply_to(None, cx);
+        } else {
+            cx.emit(DismissEvent);
+        }
+    }
+
+    fn render_comment(&self, comment: &CellComment, cx: &ViewContext<Self>) -> impl IntoElement {
+        let created_at = comment
+            .created_at
+            .with_timezone(&Local)
+            .format("%b %-d, %H:%M")
+            .to_string();
+
+        v_flex()
+            .gap_0p5()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Label::new(comment.author.clone()).size(LabelSize::Small))
+                    .child(
+                        Label::new(created_at)
+                            .size(LabelSize::XSmall)
+                            .color(Color::Muted),
+                    ),
+            )
+            .child(
+                div()
+                    .text_size(TextSize::Small.rems(cx))
+                    .child(comment.body.clone()),
+            )
+    }
+
+    fn render_thread(&self, thread: &CommentThread, cx: &ViewContext<Self>) -> impl IntoElement {
+        let thread_id = thread.id.clone();
+        let resolved = thread.resolved;
+        let is_reply_target = self.replying_to.as_deref() == Some(thread.id.as_str());
+
+        v_flex()
+            .p_2()
+            .gap_2()
+            .rounded_md()
+            .border_1()
+            .border_color(if is_reply_target {
+                cx.theme().colors().border_focused
+            } else {
+                cx.theme().colors().border_variant
+            })
+            .when(resolved, |this| this.opacity(0.6))
+            .children(
+                thread
+                    .comments
+                    .iter()
+                    .map(|comment| self.render_comment(comment, cx)),
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .justify_end()
+                    .child(
+                        Button::new(SharedString::from(format!("reply-{thread_id}")), "Reply")
+                            .label_size(LabelSize::Small)
+                            .on_click(cx.listener({
+                                let thread_id = thread_id.clone();
+                                move |this, _, cx| this.reply_to(Some(thread_id.clone()), cx)
+                            })),
+                    )
+                    .child(
+                        Button::new(
+                            SharedString::from(format!("resolve-{thread_id}")),
+                            if resolved { "Reopen" } else { "Resolve" },
+                        )
+                        .label_size(LabelSize::Small)
+                        .on_click(cx.listener({
+                            let thread_id = thread_id.clone();
+                            move |this, _, cx| {
+                                this.comments.update(cx, |comments, cx| {
+                                    comments.set_resolved(&thread_id, !resolved, cx)
+                                });
+                            }
+                        })),
+                    )
+                    .child(
+                        IconButton::new(
+                            SharedString::from(format!("delete-{thread_id}")),
+                            IconName::Trash,
+                        )
+                        .shape(IconButtonShape::Square)
+                        .icon_size(IconSize::Small)
+                        .tooltip(|cx| Tooltip::text("Delete Thread", cx))
+                        .on_click(cx.listener(move |this, _, cx| {
+                            if this.replying_to.as_deref() == Some(thread_id.as_str()) {
+                                this.reply_to(None, cx);
+                            }
+                            this.comments
+                                .update(cx, |comments, cx| comments.delete_thread(&thread_id, cx));
+                        })),
+                    ),
+            )
+    }
+}
+
+impl EventEmitter<DismissEvent> for CellCommentsView {}
+
+impl FocusableView for CellCommentsView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl Render for CellCommentsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let threads = self.comments.read(cx).threads().to_vec();
+
+        v_flex()
+            .key_context("CellComments")
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .w(rems(24.))
+            .max_h(rems(32.))
+            .p_2()
+            .gap_2()
+            .elevation_2(cx)
+            .child(
+                v_flex()
+                    .id("cell-comment-threads")
+                    .gap_2()
+                    .overflow_y_scroll()
+                    .children(threads.iter().map(|thread| self.render_thread(thread, cx))),
+            )
+            .child(
+                div()
+                    .p_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(cx.theme().colors().border)
+                    .bg(cx.theme().colors().editor_background)
+                    .child(self.editor.clone()),
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(value: serde_json::Value) -> CellMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_comment_threads_round_trip_through_metadata() {
+        let mut cell_metadata = metadata(serde_json::json!({"tags": ["parameters"]}));
+        let threads = vec![CommentThread {
+            id: "thread-1".into(),
+            resolved: false,
+            comments: vec![CellComment {
+                id: "comment-1".into(),
+                author: "octocat".into(),
+                created_at: Utc::now(),
+                body: "Why 0.3?".into(),
+            }],
+        }];
+
+        set_comment_threads(&mut cell_metadata, &threads);
+        assert_eq!(comment_threads(&cell_metadata), threads);
+
+        let value = serde_json::to_value(&cell_metadata).unwrap();
+        assert_eq!(value["tags"], serde_json::json!(["parameters"]));
+    }
+
+    #[test]
+    fn test_removing_the_last_thread_drops_the_zed_field() {
+        let mut cell_metadata = metadata(serde_json::json!({
+            "zed": {"comments": [{"id": "thread-1", "comments": []}]}
+        }));
+        assert_eq!(comment_threads(&cell_metadata).len(), 1);
+
+        set_comment_threads(&mut cell_metadata, &[]);
+        let value = serde_json::to_value(&cell_metadata).unwrap();
+        assert!(value.get("zed").is_none());
+    }
+
+    #[test]
+    fn test_other_zed_metadata_is_kept() {
+        let mut cell_metadata = metadata(serde_json::json!({"zed": {"other": true}}));
+        set_comment_threads(&mut cell_metadata, &[]);
+        let value = serde_json::to_value(&cell_metadata).unwrap();
+        assert_eq!(value["zed"]["other"], serde_json::json!(true));
+    }
+}
@@ -0,0 +1,215 @@
+//! # Cell Completions
+//!
+//! Completions in code cells come from two places. The kernel knows about runtime objects, like
+//! the columns of a dataframe or attributes created dynamically, and the language server knows
+//! everything static analysis can find in the notebook's [`CellDocument`](super::cell_document).
+//! The completion menu lists both, along with common IPython [magics](crate::magics) in Python
+//! notebooks, which the cell document hides from the language server.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use editor::{CompletionProvider, Editor, EditorSettings};
+use gpui::{AppContext, Model, Task, ViewContext, WeakView};
+use language::{
+    Anchor, Buffer, BufferSnapshot, CodeLabel, Documentation, LanguageServerId, Point, ToOffset,
+};
+use nbformat::v4::CellId;
+use parking_lot::RwLock;
+use project::Completion;
+use runtimelib::CompleteReply;
+use settings::Settings as _;
+
+use super::NotebookEditor;
+use crate::magics::{magic_prefix, matching_magics};
+
+/// Kernel and magic completions aren't from a language server, so they use an id no server will
+/// have.
+pub(crate) const KERNEL_COMPLETION_SERVER_ID: LanguageServerId = LanguageServerId(usize::MAX);
+
+/// Jupyter counts cursor positions in code points, rather than bytes.
+pub(crate) fn code_point_offset(text: &str, byte_offset: usize) -> usize {
+    text[..byte_offset].chars().count()
+}
+
+pub(crate) fn byte_offset(text: &str, code_point_offset: usize) -> usize {
+    text.char_indices()
+        .nth(code_point_offset)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
+/// Turns the matches of a `complete_reply` into completions for the cell's buffer, as it was when
+/// the request was sent.
+pub(crate) fn kernel_completions(
+    reply: &CompleteReply,
+    snapshot: &BufferSnapshot,
+) -> Vec<Completion> {
+    let text = snapshot.text();
+    let start = byte_offset(&text, reply.cursor_start);
+    let end = byte_offset(&text, reply.cursor_end).max(start);
+    let old_range = snapshot.anchor_before(start)..snapshot.anchor_after(end);
+
+    reply
+        .matches
+        .iter()
+        .map(|completion| Completion {
+            old_range: old_range.clone(),
+            new_text: completion.clone(),
+            label: CodeLabel::plain(completion.clone(), None),
+            server_id: KERNEL_COMPLETION_SERVER_ID,
+            documentation: None,
+            lsp_completion: Default::default(),
+            confirm: None,
+        })
+        .collect()
+}
+
+/// The common magics that complete the one being typed at `position`, if any.
+pub(crate) fn magic_completions(snapshot: &BufferSnapshot, position: Anchor) -> Vec<Completion> {
+    let end = position.to_offset(snapshot);
+    let (line_start, line_prefix) = line_prefix(snapshot, end);
+    let Some(prefix) = magic_prefix(&line_prefix) else {
+        return Vec::new();
+    };
+
+    let on_first_line = snapshot
+        .text_for_range(0..line_start)
+        .all(|chunk| chunk.trim().is_empty());
+    let old_range = snapshot.anchor_before(end - prefix.len())..snapshot.anchor_after(end);
+    matching_magics(prefix, on_first_line)
+        .map(|(name, description)| Completion {
+            old_range: old_range.clone(),
+            new_text: name.to_string(),
+            label: CodeLabel::plain(name.to_string(), None),
+            server_id: KERNEL_COMPLETION_SERVER_ID,
+            documentation: Some(Documentation::SingleLine(description.to_string())),
+            lsp_completion: Default::default(),
+            confirm: None,
+        })
+        .collect()
+}
+
+/// Where the line `offset` is on starts, and its text up to `offset`.
+fn line_prefix(snapshot: &BufferSnapshot, offset: usize) -> (usize, String) {
+    let row = snapshot.offset_to_point(offset).row;
+    let line_start = snapshot.point_to_offset(Point::new(row, 0));
+    let text = snapshot.text_for_range(line_start..offset).collect();
+    (line_start, text)
+}
+
+pub(crate) struct CellCompletionProvider {
+    notebook: WeakView<NotebookEditor>,
+    cell_id: CellId,
+}
+
+impl CellCompletionProvider {
+    pub fn new(notebook: WeakView<NotebookEditor>, cell_id: CellId) -> Self {
+        Self { notebook, cell_id }
+    }
+}
+
+impl CompletionProvider for CellCompletionProvider {
+    fn completions(
+        &self,
+        buffer: &Model<Buffer>,
+        buffer_position: Anchor,
+        trigger: editor::CompletionContext,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let Some(notebook) = self.notebook.upgrade() else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        notebook.update(cx, |notebook, cx| {
+            notebook.cell_completions(&self.cell_id, buffer, buffer_position, trigger, cx)
+        })
+    }
+
+    fn resolve_completions(
+        &self,
+        _buffer: Model<Buffer>,
+        completion_indices: Vec<usize>,
+        completions: Arc<RwLock<Box<[Completion]>>>,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<bool>> {
+        let Some(notebook) = self.notebook.upgrade() else {
+            return Task::ready(Ok(false));
+        };
+        notebook.update(cx, |notebook, cx| {
+            notebook.resolve_cell_completions(completion_indices, completions, cx)
+        })
+    }
+
+    fn apply_additional_edits_for_completion(
+        &self,
+        _buffer: Model<Buffer>,
+        _completion: Completion,
+        _push_to_history: bool,
+        _cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Option<language::Transaction>>> {
+        // Additional edits, like auto-imports, refer to the cell document rather than the cell
+        Task::ready(Ok(None))
+    }
+
+    fn is_completion_trigger(
+        &self,
+        buffer: &Model<Buffer>,
+        position: Anchor,
+        text: &str,
+        trigger_in_words: bool,
+        cx: &mut ViewContext<Editor>,
+    ) -> bool {
+        if !EditorSettings::get_global(cx).show_completions_on_input {
+            return false;
+        }
+
+        let mut chars = text.chars();
+        let (Some(char), None) = (chars.next(), chars.next()) else {
+            return false;
+        };
+
+        let classifier = buffer
+            .read(cx)
+            .snapshot()
+            .char_classifier_at(position)
+            .for_completion(true);
+        if trigger_in_words && classifier.is_word(char) {
+            return true;
+        }
+
+        // Attribute access is where the kernel's completions help the most, e.g. `df.`
+        text == "."
+            || (text == "%" && is_typing_magic(buffer, position, cx))
+            || self.notebook.upgrade().map_or(false, |notebook| {
+                notebook.read(cx).is_document_completion_trigger(text, cx)
+            })
+    }
+}
+
+pub(crate) fn is_python(buffer: &Model<Buffer>, cx: &AppContext) -> bool {
+    buffer
+        .read(cx)
+        .language()
+        .map_or(false, |language| language.name().0.as_ref() == "Python")
+}
+
+fn is_typing_magic(buffer: &Model<Buffer>, position: Anchor, cx: &AppContext) -> bool {
+    let snapshot = buffer.read(cx).snapshot();
+    let (_, line_prefix) = line_prefix(&snapshot, position.to_offset(&snapshot));
+    is_python(buffer, cx) && magic_prefix(&line_prefix).is_some()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_code_point_offsets() {
+        let text = "naïve = 'é'\nnaïve.up";
+        let offset = text.find(".up").unwrap() + 1;
+
+        let code_points = code_point_offset(text, offset);
+        assert_eq!(code_points, 18);
+        assert_eq!(byte_offset(text, code_points), offset);
+        assert_eq!(byte_offset(text, 100), text.len());
+    }
+}
@@ -0,0 +1,364 @@
+//! # Notebook Console
+//!
+//! A dockable panel for running quick expressions on the active notebook's kernel without adding
+//! cells to the notebook. Inputs run in the same session as the notebook's cells, so they see the
+//! same variables, and their outputs are shown with the same renderers.
+//!
+//! Earlier inputs can be recalled with up and down, like in a terminal. Each notebook keeps its own
+//! transcript in the console; the history of inputs is shared between them.
+
+use editor::{Editor, MoveDown, MoveToEnd, MoveUp};
+use gpui::{
+    actions, Action, AppContext, EntityId, EventEmitter, FocusHandle, FocusableView, Pixels,
+    ScrollHandle, Subscription, View, WeakView,
+};
+use language::Point;
+use ui::{prelude::*, Tooltip};
+use workspace::dock::{DockPosition, Panel, PanelEvent};
+use workspace::Workspace;
+
+use super::NotebookEditor;
+use crate::outputs::{ExecutionStatus, ExecutionView};
+
+actions!(
+    notebook,
+    [
+        ToggleConsole,
+        RunConsoleInput,
+        PreviousConsoleInput,
+        NextConsoleInput,
+        ClearConsole
+    ]
+);
+
+const DEFAULT_CONSOLE_SIZE: f32 = 320.;
+
+pub(crate) fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(NotebookConsole::toggle);
+    })
+    .detach();
+}
+
+/// An input run from the console, and its outputs.
+struct ConsoleEntry {
+    /// The notebook whose kernel ran the input.
+    notebook_id: EntityId,
+    code: String,
+    execution: View<ExecutionView>,
+}
+
+pub struct NotebookConsole {
+    workspace: WeakView<Workspace>,
+    prompt: View<Editor>,
+    notebook: Option<WeakView<NotebookEditor>>,
+    entries: Vec<ConsoleEntry>,
+    /// Inputs run from the console, oldest first.
+    history: Vec<String>,
+    /// The input recalled from the history, if any. Inputs typed since are dropped when another
+    /// is recalled.
+    history_index: Option<usize>,
+    scroll_handle: ScrollHandle,
+    position: DockPosition,
+    size: Option<Pixels>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl NotebookConsole {
+    fn new(
+        workspace: View<Workspace>,
+        notebook: Option<View<NotebookEditor>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let prompt = cx.new_view(|cx| {
+            let mut editor = Editor::auto_height(10, cx);
+            editor.set_placeholder_text("Run an expression on the notebook's kernel…", cx);
+            editor
+        });
+
+        let subscriptions = vec![cx.subscribe(&workspace, |this, workspace, event, cx| {
+            if let workspace::Event::ActiveItemChanged = event {
+                let notebook = active_notebook(workspace.read(cx), cx);
+                this.follow_notebook(notebook, cx);
+            }
+        })];
+
+        Self {
+            workspace: workspace.downgrade(),
+            prompt,
+            notebook: notebook.map(|notebook| notebook.downgrade()),
+            entries: Vec::new(),
+            history: Vec::new(),
+            history_index: None,
+            scroll_handle: ScrollHandle::new(),
+            position: DockPosition::Bottom,
+            size: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    /// Opens the console, adding it to the workspace the first time it's used.
+    fn toggle(workspace: &mut Workspace, _: &ToggleConsole, cx: &mut ViewContext<Workspace>) {
+        if workspace.panel::<NotebookConsole>(cx).is_none() {
+            let notebook = active_notebook(workspace, cx);
+            let workspace_view = cx.view().clone();
+            let console = cx.new_view(|cx| NotebookConsole::new(workspace_view, notebook, cx));
+            workspace.add_panel(console, cx);
+        }
+        workspace.toggle_panel_focus::<NotebookConsole>(cx);
+    }
+
+    /// Switches to the active notebook's kernel. The console stays on the last notebook when
+    /// something else becomes active, so it can be used next to other files.
+    fn follow_notebook(
+        &mut self,
+        notebook: Option<View<NotebookEditor>>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let Some(notebook) = notebook {
+            self.notebook = Some(notebook.downgrade());
+            cx.notify();
+        } else if self.notebook().is_none() && self.notebook.take().is_some() {
+            cx.notify();
+        }
+    }
+
+    fn notebook(&self) -> Option<View<NotebookEditor>> {
+        self.notebook.as_ref()?.upgrade()
+    }
+
+    fn run(&mut self, _: &RunConsoleInput, cx: &mut ViewContext<Self>) {
+        let code = self.prompt.read(cx).text(cx);
+        if code.trim().is_empty() {
+            return;
+        }
+        let Some(notebook) = self.notebook() else {
+            return;
+        };
+
+        let execution = cx
+            .new_view(|cx| ExecutionView::new(ExecutionStatus::Queued, self.workspace.clone(), cx));
+        let ran = notebook.update(cx, |notebook, cx| {
+            notebook.execute_detached(code.clone(), execution.downgrade(), cx)
+        });
+        if !ran {
+            return;
+        }
+
+        if self.history.last() != Some(&code) {
+            self.history.push(code.clone());
+        }
+        self.history_index = None;
+        self.entries.push(ConsoleEntry {
+            notebook_id: notebook.entity_id(),
+            code,
+            execution,
+        });
+        let shown_entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.notebook_id == notebook.entity_id())
+            .count();
+        self.scroll_handle
+            .set_logical_scroll_top(shown_entries - 1, px(0.));
+        self.prompt.update(cx, |prompt, cx| prompt.set_text("", cx));
+        cx.notify();
+    }
+
+    /// Recalls the previous input when the cursor is on the prompt's first line, and moves the
+    /// cursor up otherwise.
+    fn previous_input(&mut self, _: &PreviousConsoleInput, cx: &mut ViewContext<Self>) {
+        let on_first_line = self.prompt.update(cx, |prompt, cx| {
+            prompt.selections.newest::<Point>(cx).head().row == 0
+        });
+        if !on_first_line || self.history.is_empty() {
+            self.prompt
+                .update(cx, |prompt, cx| prompt.move_up(&MoveUp, cx));
+            return;
+        }
+
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+        self.recall(Some(index), cx);
+    }
+
+    /// Recalls the next input when the cursor is on the prompt's last line, and moves the cursor
+    /// down otherwise. Going past the newest input clears the prompt.
+    fn next_input(&mut self, _: &NextConsoleInput, cx: &mut ViewContext<Self>) {
+        let on_last_line = self.prompt.update(cx, |prompt, cx| {
+            let max_row = prompt.buffer().read(cx).snapshot(cx).max_point().row;
+            prompt.selections.newest::<Point>(cx).head().row == max_row
+        });
+        let Some(index) = self.history_index.filter(|_| on_last_line) else {
+            self.prompt
+                .update(cx, |prompt, cx| prompt.move_down(&MoveDown, cx));
+            return;
+        };
+
+        let next = Some(index + 1).filter(|next| *next < self.history.len());
+        self.recall(next, cx);
+    }
+
+    fn recall(&mut self, index: Option<usize>, cx: &mut ViewContext<Self>) {
+        self.history_index = index;
+        let text = index
+            .and_then(|index| self.history.get(index))
+            .cloned()
+            .unwrap_or_default();
+        self.prompt.update(cx, |prompt, cx| {
+            prompt.set_text(text, cx);
+            prompt.move_to_end(&MoveToEnd, cx);
+        });
+    }
+
+    /// Clears the active notebook's transcript. The history of inputs is kept.
+    fn clear(&mut self, _: &ClearConsole, cx: &mut ViewContext<Self>) {
+        let notebook_id = self.notebook().map(|notebook| notebook.entity_id());
+        self.entries
+            .retain(|entry| Some(entry.notebook_id) != notebook_id);
+        cx.notify();
+    }
+
+    fn render_entry(&self, entry: &ConsoleEntry, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .w_full()
+            .gap_1()
+            .py_1()
+            .border_b_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .items_start()
+                    .child(Label::new("›").color(Color::Muted))
+                    .child(div().font_buffer(cx).text_sm().child(entry.code.clone())),
+            )
+            .child(div().pl_4().child(entry.execution.clone()))
+    }
+}
+
+/// The notebook in the workspace's active pane, if it's showing one.
+fn active_notebook(workspace: &Workspace, cx: &AppContext) -> Option<View<NotebookEditor>> {
+    workspace
+        .active_item(cx)
+        .and_then(|item| item.downcast::<NotebookEditor>())
+}
+
+impl FocusableView for NotebookConsole {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.prompt.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<PanelEvent> for NotebookConsole {}
+
+impl Panel for NotebookConsole {
+    fn persistent_name() -> &'static str {
+        "Notebook Console"
+    }
+
+    fn position(&self, _: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, _: DockPosition) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _: &WindowContext) -> Pixels {
+        self.size.unwrap_or_else(|| px(DEFAULT_CONSOLE_SIZE))
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.size = size;
+        cx.notify();
+    }
+
+    fn icon(&self, _: &WindowContext) -> Option<IconName> {
+        Some(IconName::ReplNeutral)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("Notebook Console")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleConsole)
+    }
+}
+
+impl Render for NotebookConsole {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let notebook = self.notebook();
+        let title = notebook
+            .as_ref()
+            .map(|notebook| notebook.read(cx).file_name(cx))
+            .unwrap_or_else(|| "No notebook".into());
+        let notebook_id = notebook.as_ref().map(|notebook| notebook.entity_id());
+
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| Some(entry.notebook_id) == notebook_id)
+            .map(|entry| self.render_entry(entry, cx).into_any_element())
+            .collect::<Vec<_>>();
+
+        v_flex()
+            .key_context("NotebookConsole")
+            .on_action(cx.listener(Self::run))
+            .on_action(cx.listener(Self::previous_input))
+            .on_action(cx.listener(Self::next_input))
+            .on_action(cx.listener(Self::clear))
+            .size_full()
+            .bg(cx.theme().colors().panel_background)
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .justify_between()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(Label::new(title).size(LabelSize::Small).color(Color::Muted))
+                    .child(
+                        IconButton::new("clear-console", IconName::ListX)
+                            .icon_size(IconSize::Small)
+                            .tooltip(|cx| Tooltip::for_action("Clear Console", &ClearConsole, cx))
+                            .on_click(cx.listener(|this, _, cx| this.clear(&ClearConsole, cx))),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("console-entries")
+                    .flex_1()
+                    .px_2()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .children(entries),
+            )
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_2()
+                    .items_start()
+                    .border_t_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(Label::new("›").color(Color::Accent))
+                    .child(div().flex_1().child(self.prompt.clone()))
+                    .when(notebook.is_none(), |prompt| {
+                        prompt.opacity(0.5).child(
+                            Label::new("Open a notebook to use its kernel")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                    }),
+            )
+    }
+}
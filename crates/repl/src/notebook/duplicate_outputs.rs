@@ -0,0 +1,275 @@
+//! # Duplicate Outputs
+//!
+//! Re-running a cell usually shows the same plot again, and notebooks end up saving identical
+//! payloads many times over. When `notebook.dedupe_outputs` is enabled, a payload over
+//! [`DUPLICATE_OUTPUT_MIN_SIZE`] that an earlier output already saved is replaced with the hash of
+//! its contents:
+//!
+//! ```json
+//! "metadata": { "zed": { "duplicate_data": { "image/png": "3f2a…" } } }
+//! ```
+//!
+//! The first output with a payload always keeps it, so the hash can be resolved from within the
+//! notebook when it's opened. Duplicates of payloads that were set aside while opening the
+//! notebook share them, so they're only held in memory once.
+
+use collections::{HashMap, HashSet};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use super::external_outputs::outputs_mut;
+use super::lazy_outputs::{deferred_references, insert_deferred_references, DeferredOutputs};
+
+/// Payloads smaller than this are saved every time, since a reference wouldn't save much.
+pub(crate) const DUPLICATE_OUTPUT_MIN_SIZE: usize = 16 * 1024;
+
+/// The field under the output's `zed` metadata with the hashes of its duplicate payloads.
+const DUPLICATE_DATA: &str = "duplicate_data";
+
+/// The hash of a payload as it's serialized, if it's at least `min_size` bytes.
+fn payload_hash(payload: &Value, min_size: usize) -> Option<String> {
+    let bytes = serde_json::to_vec(payload).ok()?;
+    (bytes.len() >= min_size).then(|| format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// The `duplicate_data` references of a serialized output, by MIME type.
+fn duplicate_references(output: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    output
+        .get("metadata")?
+        .get("zed")?
+        .get(DUPLICATE_DATA)?
+        .as_object()
+}
+
+/// Replaces payloads of a serialized notebook that an earlier output already has with references
+/// to them.
+pub(crate) fn dedupe(notebook: &mut Value, min_size: usize) {
+    let mut saved = HashSet::default();
+
+    for output in outputs_mut(notebook) {
+        let Some(data) = output.get_mut("data").and_then(Value::as_object_mut) else {
+            continue;
+        };
+
+        let mut references = Map::new();
+        data.retain(|mime_type, payload| {
+            let Some(hash) = payload_hash(payload, min_size) else {
+                return true;
+            };
+            if saved.insert(hash.clone()) {
+                return true;
+            }
+            references.insert(mime_type.clone(), Value::String(hash));
+            false
+        });
+
+        if references.is_empty() {
+            continue;
+        }
+
+        let metadata = output
+            .entry("metadata")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(metadata) = metadata.as_object_mut() {
+            let zed = metadata
+                .entry("zed")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Some(zed) = zed.as_object_mut() {
+                // Keep references that couldn't be resolved when the notebook was opened
+                match zed.get_mut(DUPLICATE_DATA).and_then(Value::as_object_mut) {
+                    Some(existing) => existing.extend(references),
+                    None => {
+                        zed.insert(DUPLICATE_DATA.into(), Value::Object(references));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Where the first copy of a duplicated payload is.
+enum Original {
+    Data(Value),
+    /// A `deferred_data` reference, which duplicates share.
+    Deferred(Value),
+}
+
+/// Puts duplicate payloads back into a serialized notebook, and removes the references to them.
+/// References whose payload can't be found are left in place, so saving the notebook doesn't
+/// lose them.
+pub(crate) fn resolve_duplicates(notebook: &mut Value, deferred_outputs: &DeferredOutputs) {
+    let referenced = outputs_mut(notebook)
+        .filter_map(|output| duplicate_references(output))
+        .flat_map(|references| references.values())
+        .filter_map(|hash| hash.as_str().map(ToString::to_string))
+        .collect::<HashSet<_>>();
+    if referenced.is_empty() {
+        return;
+    }
+
+    let mut originals = HashMap::<String, Original>::default();
+    for output in outputs_mut(notebook) {
+        for payload in output
+            .get("data")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|data| data.values())
+        {
+            if let Some(hash) = payload_hash(payload, 0).filter(|hash| referenced.contains(hash)) {
+                originals
+                    .entry(hash)
+                    .or_insert_with(|| Original::Data(payload.clone()));
+            }
+        }
+        for reference in deferred_references(output)
+            .into_iter()
+            .flat_map(|references| references.values())
+        {
+            let hash = deferred_outputs
+                .payload(reference)
+                .and_then(|payload| payload_hash(&payload, 0))
+                .filter(|hash| referenced.contains(hash));
+            if let Some(hash) = hash {
+                originals
+                    .entry(hash)
+                    .or_insert_with(|| Original::Deferred(reference.clone()));
+            }
+        }
+    }
+
+    for output in outputs_mut(notebook) {
+        let Some(references) = output
+            .get_mut("metadata")
+            .and_then(|metadata| metadata.get_mut("zed"))
+            .and_then(Value::as_object_mut)
+            .and_then(|zed| zed.remove(DUPLICATE_DATA))
+        else {
+            continue;
+        };
+
+        let mut data = Map::new();
+        let mut deferred = Map::new();
+        let mut missing = Map::new();
+        for (mime_type, hash) in references.as_object().into_iter().flatten() {
+            match hash.as_str().and_then(|hash| originals.get(hash)) {
+                Some(Original::Data(payload)) => {
+                    data.insert(mime_type.clone(), payload.clone());
+                }
+                Some(Original::Deferred(reference)) => {
+                    deferred.insert(mime_type.clone(), reference.clone());
+                }
+                None => {
+                    missing.insert(mime_type.clone(), hash.clone());
+                }
+            }
+        }
+
+        if let Some(metadata) = output.get_mut("metadata").and_then(Value::as_object_mut) {
+            if let Some(zed) = metadata.get_mut("zed").and_then(Value::as_object_mut) {
+                if !missing.is_empty() {
+                    zed.insert(DUPLICATE_DATA.into(), Value::Object(missing));
+                }
+            }
+            if metadata
+                .get("zed")
+                .and_then(Value::as_object)
+                .map_or(false, |zed| zed.is_empty())
+            {
+                metadata.remove("zed");
+            }
+        }
+        if !deferred.is_empty() {
+            insert_deferred_references(output, deferred);
+        }
+        if !data.is_empty() {
+            let output_data = output
+                .entry("data")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Some(output_data) = output_data.as_object_mut() {
+                output_data.extend(data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn notebook() -> Value {
+        let plot = json!({
+            "output_type": "display_data",
+            "metadata": {},
+            "data": {
+                "image/png": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk",
+                "text/plain": ["<Figure>"]
+            }
+        });
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {},
+            "cells": [
+                {
+                    "id": "a",
+                    "cell_type": "code",
+                    "execution_count": 1,
+                    "metadata": {},
+                    "source": ["plot()"],
+                    "outputs": [plot.clone()]
+                },
+                {
+                    "id": "b",
+                    "cell_type": "code",
+                    "execution_count": 2,
+                    "metadata": {},
+                    "source": ["plot()"],
+                    "outputs": [plot]
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_dedupe_and_resolve() {
+        let mut value = notebook();
+        dedupe(&mut value, 32);
+
+        assert_eq!(value["cells"][0], notebook()["cells"][0]);
+        let duplicate = &value["cells"][1]["outputs"][0];
+        assert_eq!(duplicate["data"], json!({ "text/plain": ["<Figure>"] }));
+        assert!(duplicate["metadata"]["zed"][DUPLICATE_DATA]["image/png"].is_string());
+
+        resolve_duplicates(&mut value, &DeferredOutputs::default());
+        assert_eq!(value, notebook());
+    }
+
+    #[test]
+    fn test_duplicates_share_deferred_payloads() {
+        let mut value = notebook();
+        dedupe(&mut value, 32);
+        let text = serde_json::to_string(&value).unwrap();
+        let (mut value, deferred) = DeferredOutputs::parse(&text, 32).unwrap();
+
+        resolve_duplicates(&mut value, &deferred);
+        assert_eq!(
+            value["cells"][1]["outputs"][0]["metadata"],
+            value["cells"][0]["outputs"][0]["metadata"]
+        );
+        deferred.restore(&mut value);
+        assert_eq!(value, notebook());
+    }
+
+    #[test]
+    fn test_unresolved_duplicates_are_kept() {
+        let mut value = notebook();
+        dedupe(&mut value, 32);
+        value["cells"][0]["outputs"] = json!([]);
+        let expected = value.clone();
+
+        resolve_duplicates(&mut value, &DeferredOutputs::default());
+        assert_eq!(value, expected);
+    }
+}
@@ -0,0 +1,302 @@
+//! # External Outputs
+//!
+//! Notebooks with many plots embed megabytes of base64 in their JSON, which makes them slow to
+//! open and painful to diff. When `notebook.external_outputs` is enabled, output payloads over
+//! [`EXTERNAL_OUTPUT_MIN_SIZE`] are saved in a directory next to the notebook instead, named after
+//! the hash of their contents, and the notebook keeps a reference to them in the output's metadata:
+//!
+//! ```json
+//! "metadata": { "zed": { "external_data": { "image/png": "analysis.outputs/3f2a….png" } } }
+//! ```
+//!
+//! Only the referenced representation moves out, so tools that don't know about the directory
+//! still show the output's `text/plain`. Files are read back in when a notebook is opened, even
+//! with the setting turned off.
+
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use collections::HashMap;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Payloads smaller than this stay in the notebook.
+pub(crate) const EXTERNAL_OUTPUT_MIN_SIZE: usize = 64 * 1024;
+
+/// The directory external outputs of a notebook are stored in, e.g. `analysis.outputs` for
+/// `analysis.ipynb`.
+pub(crate) fn outputs_dir_name(notebook_path: &Path) -> String {
+    let stem = notebook_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "notebook".to_string());
+    format!("{stem}.outputs")
+}
+
+/// Image payloads are stored decoded, so they can be opened like any other image.
+fn image_extension(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        _ => None,
+    }
+}
+
+/// Jupyter may split text into a list of lines.
+fn joined_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(lines) => lines
+            .iter()
+            .map(|line| line.as_str())
+            .collect::<Option<String>>(),
+        _ => None,
+    }
+}
+
+/// Encodes a payload as it will be stored on disk, with the file extension to use.
+fn encode_payload(mime_type: &str, value: &Value) -> Option<(Vec<u8>, &'static str)> {
+    if let Some(extension) = image_extension(mime_type) {
+        let base64 = joined_text(value)?;
+        let filtered = base64.replace(|ch: char| ch.is_ascii_whitespace(), "");
+        let bytes = STANDARD.decode(filtered).ok()?;
+        return Some((bytes, extension));
+    }
+    Some((serde_json::to_vec(value).ok()?, "json"))
+}
+
+fn decode_payload(mime_type: &str, bytes: &[u8]) -> Option<Value> {
+    if image_extension(mime_type).is_some() {
+        return Some(Value::String(STANDARD.encode(bytes)));
+    }
+    serde_json::from_slice(bytes).ok()
+}
+
+pub(crate) fn outputs_mut(notebook: &mut Value) -> impl Iterator<Item = &mut Map<String, Value>> {
+    notebook
+        .get_mut("cells")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|cell| cell.get_mut("outputs")?.as_array_mut())
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Moves large output payloads out of a serialized notebook.
+///
+/// Returns the files to write, keyed by their path relative to the notebook's directory.
+pub(crate) fn externalize(
+    notebook: &mut Value,
+    outputs_dir_name: &str,
+    min_size: usize,
+) -> HashMap<String, Vec<u8>> {
+    let mut files = HashMap::default();
+
+    for output in outputs_mut(notebook) {
+        let Some(data) = output.get_mut("data").and_then(Value::as_object_mut) else {
+            continue;
+        };
+
+        let mut references = Map::new();
+        data.retain(|mime_type, value| {
+            let is_large = serde_json::to_string(value)
+                .map_or(false, |serialized| serialized.len() >= min_size);
+            if !is_large {
+                return true;
+            }
+            let Some((bytes, extension)) = encode_payload(mime_type, value) else {
+                return true;
+            };
+
+            let hash = format!("{:x}", Sha256::digest(&bytes));
+            let path = format!("{outputs_dir_name}/{hash}.{extension}");
+            references.insert(mime_type.clone(), Value::String(path.clone()));
+            files.insert(path, bytes);
+            false
+        });
+
+        if references.is_empty() {
+            continue;
+        }
+
+        let metadata = output
+            .entry("metadata")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(metadata) = metadata.as_object_mut() {
+            let zed = metadata
+                .entry("zed")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Some(zed) = zed.as_object_mut() {
+                // Keep references that couldn't be read back in when the notebook was opened
+                match zed.get_mut("external_data").and_then(Value::as_object_mut) {
+                    Some(existing) => existing.extend(references),
+                    None => {
+                        zed.insert("external_data".into(), Value::Object(references));
+                    }
+                }
+            }
+        }
+    }
+
+    files
+}
+
+/// The files a notebook refers to, relative to its directory.
+pub(crate) fn external_references(notebook: &mut Value) -> Vec<String> {
+    outputs_mut(notebook)
+        .filter_map(|output| {
+            let references = output.get("metadata")?.get("zed")?.get("external_data")?;
+            Some(
+                references
+                    .as_object()?
+                    .values()
+                    .filter_map(|path| path.as_str().map(ToString::to_string))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+/// Puts externally stored payloads back into a serialized notebook, and removes the references
+/// to them. References to files that couldn't be read are left in place, so saving the notebook
+/// doesn't lose them.
+pub(crate) fn rehydrate(notebook: &mut Value, files: &HashMap<String, Vec<u8>>) {
+    for output in outputs_mut(notebook) {
+        let Some(references) = output
+            .get_mut("metadata")
+            .and_then(|metadata| metadata.get_mut("zed"))
+            .and_then(|zed| zed.get_mut("external_data"))
+            .and_then(Value::as_object_mut)
+        else {
+            continue;
+        };
+
+        let mut restored = Vec::new();
+        references.retain(|mime_type, path| {
+            let payload = path
+                .as_str()
+                .and_then(|path| files.get(path))
+                .and_then(|bytes| decode_payload(mime_type, bytes));
+            match payload {
+                Some(payload) => {
+                    restored.push((mime_type.clone(), payload));
+                    false
+                }
+                None => true,
+            }
+        });
+        let is_done = references.is_empty();
+
+        if is_done {
+            if let Some(metadata) = output.get_mut("metadata").and_then(Value::as_object_mut) {
+                if let Some(zed) = metadata.get_mut("zed").and_then(Value::as_object_mut) {
+                    zed.remove("external_data");
+                    if zed.is_empty() {
+                        metadata.remove("zed");
+                    }
+                }
+            }
+        }
+
+        if let Some(data) = output
+            .entry("data")
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+        {
+            data.extend(restored);
+        }
+    }
+}
+
+/// Resolves a reference relative to the directory of the notebook.
+pub(crate) fn reference_path(notebook_path: &Path, reference: &str) -> PathBuf {
+    notebook_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(reference)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn notebook_with_output(data: Value) -> Value {
+        json!({
+            "cells": [{
+                "cell_type": "code",
+                "outputs": [{
+                    "output_type": "display_data",
+                    "data": data,
+                    "metadata": {}
+                }]
+            }]
+        })
+    }
+
+    #[test]
+    fn test_outputs_dir_name() {
+        assert_eq!(
+            outputs_dir_name(Path::new("/work/analysis.ipynb")),
+            "analysis.outputs"
+        );
+    }
+
+    #[test]
+    fn test_externalize_and_rehydrate_round_trip() {
+        let png = STANDARD.encode(vec![7u8; 300]);
+        let original = notebook_with_output(json!({
+            "image/png": png,
+            "text/plain": ["<Figure size 640x480>"]
+        }));
+
+        let mut notebook = original.clone();
+        let files = externalize(&mut notebook, "analysis.outputs", 100);
+
+        assert_eq!(files.len(), 1);
+        let (path, bytes) = files.iter().next().unwrap();
+        assert!(path.starts_with("analysis.outputs/"));
+        assert!(path.ends_with(".png"));
+        assert_eq!(bytes, &vec![7u8; 300]);
+
+        let output = &notebook["cells"][0]["outputs"][0];
+        assert!(output["data"].get("image/png").is_none());
+        assert_eq!(
+            output["data"]["text/plain"],
+            json!(["<Figure size 640x480>"])
+        );
+        assert_eq!(
+            output["metadata"]["zed"]["external_data"]["image/png"],
+            json!(path)
+        );
+
+        assert_eq!(external_references(&mut notebook), vec![path.clone()]);
+        rehydrate(&mut notebook, &files);
+
+        let output = &notebook["cells"][0]["outputs"][0];
+        assert_eq!(output["data"]["image/png"], original_png(&original));
+        assert_eq!(output["metadata"], json!({}));
+    }
+
+    fn original_png(notebook: &Value) -> Value {
+        notebook["cells"][0]["outputs"][0]["data"]["image/png"].clone()
+    }
+
+    #[test]
+    fn test_rehydrate_keeps_missing_references() {
+        let mut notebook = notebook_with_output(json!({"text/plain": "x"}));
+        notebook["cells"][0]["outputs"][0]["metadata"] =
+            json!({"zed": {"external_data": {"text/html": "gone.outputs/abc.json"}}});
+
+        rehydrate(&mut notebook, &HashMap::default());
+
+        assert_eq!(
+            notebook["cells"][0]["outputs"][0]["metadata"]["zed"]["external_data"]["text/html"],
+            json!("gone.outputs/abc.json")
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! # Filtering Cells
+//!
+//! A filter temporarily hides the cells that don't match it, e.g. to look only at the cells whose
+//! last run raised an error, or at the cells with a tag. The cells are matched when the filter is
+//! chosen, so cells that are added or edited afterwards stay in view until it's chosen again, and
+//! a hidden cell is shown again once it's selected, e.g. by the find bar. A banner above the cells
+//! says what's filtered and shows all cells again.
+
+use std::sync::Arc;
+
+use nbformat::v4::CellType;
+use project::search::SearchQuery;
+
+#[derive(Clone)]
+pub(crate) enum CellFilter {
+    /// Code cells whose last run raised an error.
+    Errors,
+    Code,
+    Tag(String),
+    /// Cells whose source matches the find bar's query.
+    Query(Arc<SearchQuery>),
+}
+
+/// What a filter is matched against in a cell.
+pub(crate) struct FilteredCell<'a> {
+    pub cell_type: CellType,
+    pub has_error: bool,
+    pub tags: &'a [String],
+    pub source: &'a str,
+}
+
+impl CellFilter {
+    /// Describes the cells the filter shows, e.g. "cells tagged “setup”".
+    pub(crate) fn label(&self) -> String {
+        match self {
+            CellFilter::Errors => "cells with errors".to_string(),
+            CellFilter::Code => "code cells".to_string(),
+            CellFilter::Tag(tag) => format!("cells tagged “{tag}”"),
+            CellFilter::Query(query) => format!("cells matching “{}”", query.as_str()),
+        }
+    }
+
+    pub(crate) fn matches(&self, cell: &FilteredCell) -> bool {
+        match self {
+            CellFilter::Errors => cell.has_error,
+            CellFilter::Code => matches!(cell.cell_type, CellType::Code),
+            CellFilter::Tag(tag) => cell.tags.contains(tag),
+            CellFilter::Query(query) => !query.search_str(cell.source).is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cell_filters() {
+        let tags = ["setup".to_string()];
+        let setup = FilteredCell {
+            cell_type: CellType::Code,
+            has_error: false,
+            tags: &tags,
+            source: "import pandas as pd",
+        };
+        let failed = FilteredCell {
+            cell_type: CellType::Code,
+            has_error: true,
+            tags: &[],
+            source: "df = pd.read_csv(path)",
+        };
+        let notes = FilteredCell {
+            cell_type: CellType::Markdown,
+            has_error: false,
+            tags: &[],
+            source: "Reads the data with pandas.",
+        };
+        let cells = [setup, failed, notes];
+        let shown = |filter: &CellFilter| {
+            cells
+                .iter()
+                .map(|cell| filter.matches(cell))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(shown(&CellFilter::Errors), [false, true, false]);
+        assert_eq!(shown(&CellFilter::Code), [true, true, false]);
+        assert_eq!(
+            shown(&CellFilter::Tag("setup".to_string())),
+            [true, false, false]
+        );
+
+        let query = SearchQuery::text(
+            "pandas",
+            false,
+            false,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let filter = CellFilter::Query(Arc::new(query));
+        assert_eq!(shown(&filter), [true, false, true]);
+        assert_eq!(filter.label(), "cells matching “pandas”");
+    }
+}
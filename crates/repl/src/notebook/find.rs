@@ -0,0 +1,121 @@
+//! # Finding in Notebooks
+//!
+//! The find bar searches the sources of all of a notebook's cells, with the same case, whole word
+//! and regex options as in an editor. Matches are highlighted in the cells' editors and stepped
+//! through in notebook order. The text of outputs is searched too when `notebook: toggle search
+//! outputs` is on, but a match in an output only selects its cell, since outputs aren't editors.
+//!
+//! Replacing all matches edits every cell they're in as a single operation, so renaming a column
+//! used across thirty cells is undone with one `notebook: undo cell operation`. Matches in outputs
+//! and in cells that can't be edited are left alone.
+
+use std::ops::Range;
+
+use collections::HashMap;
+use nbformat::v4::CellId;
+use project::search::SearchQuery;
+
+/// Where in its cell a match was found.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum MatchLocation {
+    /// A range of the cell's source.
+    Source(Range<usize>),
+    /// The output with this index.
+    Output(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotebookMatch {
+    pub(crate) cell_id: CellId,
+    pub(crate) location: MatchLocation,
+}
+
+/// Highlights the matches in the cells' editors.
+pub(crate) enum NotebookSearchHighlights {}
+
+/// The matches of `query` in a cell, its source first and then each of its outputs. `outputs`
+/// holds the text of each output, and is only given when outputs are searched.
+pub(crate) fn cell_matches(
+    query: &SearchQuery,
+    cell_id: &CellId,
+    source: &str,
+    outputs: Option<&[Option<String>]>,
+) -> Vec<NotebookMatch> {
+    let mut matches = query
+        .search_str(source)
+        .into_iter()
+        .map(|range| NotebookMatch {
+            cell_id: cell_id.clone(),
+            location: MatchLocation::Source(range),
+        })
+        .collect::<Vec<_>>();
+    for (output_index, text) in outputs.unwrap_or_default().iter().enumerate() {
+        let Some(text) = text else {
+            continue;
+        };
+        if !query.search_str(text).is_empty() {
+            matches.push(NotebookMatch {
+                cell_id: cell_id.clone(),
+                location: MatchLocation::Output(output_index),
+            });
+        }
+    }
+    matches
+}
+
+/// The ranges of the matches in each cell's source.
+pub(crate) fn source_ranges_by_cell<'a>(
+    matches: impl IntoIterator<Item = &'a NotebookMatch>,
+) -> HashMap<CellId, Vec<Range<usize>>> {
+    let mut ranges_by_cell = HashMap::<CellId, Vec<Range<usize>>>::default();
+    for mat in matches {
+        if let MatchLocation::Source(range) = &mat.location {
+            ranges_by_cell
+                .entry(mat.cell_id.clone())
+                .or_default()
+                .push(range.clone());
+        }
+    }
+    ranges_by_cell
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::collab::test_cell_id;
+    use super::*;
+
+    #[test]
+    fn test_cell_matches() {
+        let query = SearchQuery::regex(
+            "total\\w*",
+            false,
+            false,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap();
+        let id = test_cell_id("sum");
+        let source = "totals = sum(xs)\nprint(totals)";
+        let outputs = [Some("totals: 42\n".to_string()), None, Some("done".into())];
+
+        let matches = cell_matches(&query, &id, source, None);
+        assert_eq!(
+            matches
+                .iter()
+                .map(|mat| mat.location.clone())
+                .collect::<Vec<_>>(),
+            [MatchLocation::Source(0..6), MatchLocation::Source(23..29)]
+        );
+
+        let matches = cell_matches(&query, &id, source, Some(&outputs));
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[2].location, MatchLocation::Output(0));
+        assert_eq!(matches[2].cell_id, id);
+
+        let ranges_by_cell = source_ranges_by_cell(&matches);
+        assert_eq!(ranges_by_cell.len(), 1);
+        assert_eq!(ranges_by_cell[&id], [0..6, 23..29]);
+    }
+}
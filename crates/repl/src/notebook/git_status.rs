@@ -0,0 +1,352 @@
+//! # Git Changes in Cells
+//!
+//! Line diffs of the notebook file don't say much about a notebook: outputs and metadata are
+//! interleaved with the source, and the cells are spread across many lines of JSON. Instead the
+//! cells committed at HEAD are parsed, and each cell is compared with the committed cell that has
+//! the same id. Cells whose source or type changed are marked as modified in the gutter, and cells
+//! that weren't committed as added. Notebooks written by tools that don't keep ids still line up,
+//! since a cell with the same source as a committed one isn't marked.
+//!
+//! Outputs and execution counts change every time a notebook is run, so they're ignored unless
+//! `notebook.diff_ignores_outputs` is turned off. Then code cells whose execution count or the text
+//! of whose outputs changed are marked as modified too.
+//!
+//! A modified cell can be reverted to its committed version from the marker in its gutter, leaving
+//! the rest of the notebook alone. Its outputs are only reverted when asked to.
+
+use collections::{HashMap, HashSet};
+use nbformat::v4::{Cell, CellId, CellType, Notebook, Output};
+
+use super::assistant_context::output_text;
+
+/// How a cell differs from the notebook committed at HEAD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellGitStatus {
+    Added,
+    Modified,
+}
+
+/// What's compared of a code cell's outputs: its execution count and the text of each output.
+/// Images and other rich outputs only count through their plain text representation.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CellOutputs {
+    execution_count: Option<i32>,
+    texts: Vec<Option<String>>,
+}
+
+impl CellOutputs {
+    pub(crate) fn new(execution_count: Option<i32>, outputs: &[Output]) -> Self {
+        Self {
+            execution_count,
+            texts: outputs.iter().map(output_text).collect(),
+        }
+    }
+}
+
+struct CommittedCell {
+    cell: Cell,
+    cell_type: CellType,
+    source: String,
+    outputs: Option<CellOutputs>,
+}
+
+/// The cells of the notebook as committed at HEAD, to compare the open cells against.
+pub(crate) struct CommittedCells {
+    cells: HashMap<CellId, CommittedCell>,
+    sources: HashSet<String>,
+}
+
+impl CommittedCells {
+    pub(crate) fn new(notebook: &Notebook) -> Self {
+        let mut cells = HashMap::default();
+        let mut sources = HashSet::default();
+        for cell in &notebook.cells {
+            let (id, cell_type, source, outputs) = match cell {
+                Cell::Code {
+                    id,
+                    source,
+                    execution_count,
+                    outputs,
+                    ..
+                } => (
+                    id,
+                    CellType::Code,
+                    source,
+                    Some(CellOutputs::new(*execution_count, outputs)),
+                ),
+                Cell::Markdown { id, source, .. } => (id, CellType::Markdown, source, None),
+                Cell::Raw { id, source, .. } => (id, CellType::Raw, source, None),
+            };
+            let source = source.join("");
+            sources.insert(source.clone());
+            cells.insert(
+                id.clone(),
+                CommittedCell {
+                    cell: cell.clone(),
+                    cell_type,
+                    source,
+                    outputs,
+                },
+            );
+        }
+        Self { cells, sources }
+    }
+
+    /// The committed version of the cell with `id`.
+    pub(crate) fn cell(&self, id: &CellId) -> Option<&Cell> {
+        Some(&self.cells.get(id)?.cell)
+    }
+
+    /// How the cell with `id`, `cell_type` and `source` differs from the committed notebook.
+    /// The outputs of code cells are only compared when they're given.
+    pub(crate) fn status(
+        &self,
+        id: &CellId,
+        cell_type: CellType,
+        source: &str,
+        outputs: Option<&CellOutputs>,
+    ) -> Option<CellGitStatus> {
+        match self.cells.get(id) {
+            Some(committed) => {
+                let same_type = matches!(
+                    (&committed.cell_type, cell_type),
+                    (CellType::Code, CellType::Code)
+                        | (CellType::Markdown, CellType::Markdown)
+                        | (CellType::Raw, CellType::Raw)
+                );
+                let same_outputs = match (outputs, &committed.outputs) {
+                    (Some(outputs), Some(committed_outputs)) => outputs == committed_outputs,
+                    _ => true,
+                };
+                let modified = !same_type || committed.source != source || !same_outputs;
+                modified.then_some(CellGitStatus::Modified)
+            }
+            None if self.sources.contains(source) => None,
+            None => Some(CellGitStatus::Added),
+        }
+    }
+}
+
+/// The `current` cell with the source and type of its `committed` version. Its id and metadata,
+/// like comments and tags, are kept. Its execution count and outputs are only taken from the
+/// committed cell when `include_outputs` is set.
+pub(crate) fn reverted_cell(current: &Cell, committed: &Cell, include_outputs: bool) -> Cell {
+    let (id, metadata) = match current {
+        Cell::Code { id, metadata, .. }
+        | Cell::Markdown { id, metadata, .. }
+        | Cell::Raw { id, metadata, .. } => (id.clone(), metadata.clone()),
+    };
+    match committed {
+        Cell::Code {
+            source,
+            execution_count,
+            outputs,
+            ..
+        } => {
+            let (execution_count, outputs) = match current {
+                _ if include_outputs => (*execution_count, outputs.clone()),
+                Cell::Code {
+                    execution_count,
+                    outputs,
+                    ..
+                } => (*execution_count, outputs.clone()),
+                Cell::Markdown { .. } | Cell::Raw { .. } => (None, Vec::new()),
+            };
+            Cell::Code {
+                id,
+                metadata,
+                execution_count,
+                source: source.clone(),
+                outputs,
+            }
+        }
+        Cell::Markdown {
+            source,
+            attachments,
+            ..
+        } => Cell::Markdown {
+            id,
+            metadata,
+            source: source.clone(),
+            attachments: attachments.clone(),
+        },
+        Cell::Raw { source, .. } => Cell::Raw {
+            id,
+            metadata,
+            source: source.clone(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::super::collab::test_cell_id;
+    use super::super::tags::cell_tags;
+    use super::*;
+
+    #[test]
+    fn test_cell_git_status() {
+        let notebook: Notebook = serde_json::from_value(json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {},
+            "cells": [
+                {
+                    "id": "intro",
+                    "cell_type": "markdown",
+                    "metadata": {},
+                    "source": ["# Analysis\n", "Loading the data."],
+                },
+                {
+                    "id": "load",
+                    "cell_type": "code",
+                    "metadata": {},
+                    "execution_count": 1,
+                    "source": ["df = load()"],
+                    "outputs": [],
+                },
+            ],
+        }))
+        .unwrap();
+        let committed = CommittedCells::new(&notebook);
+
+        assert_eq!(
+            committed.status(
+                &test_cell_id("intro"),
+                CellType::Markdown,
+                "# Analysis\nLoading the data.",
+                None
+            ),
+            None
+        );
+        assert_eq!(
+            committed.status(
+                &test_cell_id("load"),
+                CellType::Code,
+                "df = load(cache=True)",
+                None
+            ),
+            Some(CellGitStatus::Modified)
+        );
+        assert_eq!(
+            committed.status(&test_cell_id("load"), CellType::Raw, "df = load()", None),
+            Some(CellGitStatus::Modified)
+        );
+        assert_eq!(
+            committed.status(&test_cell_id("plot"), CellType::Code, "df.plot()", None),
+            Some(CellGitStatus::Added)
+        );
+        // A cell whose id was regenerated still matches by its source
+        assert_eq!(
+            committed.status(
+                &test_cell_id("regenerated"),
+                CellType::Code,
+                "df = load()",
+                None
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cell_git_status_with_outputs() {
+        let notebook: Notebook = serde_json::from_value(json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {},
+            "cells": [{
+                "id": "total",
+                "cell_type": "code",
+                "metadata": {},
+                "execution_count": 3,
+                "source": ["print(total)"],
+                "outputs": [{"output_type": "stream", "name": "stdout", "text": ["42\n"]}],
+            }],
+        }))
+        .unwrap();
+        let committed = CommittedCells::new(&notebook);
+        let id = test_cell_id("total");
+        let output = |text: &str| -> Output {
+            serde_json::from_value(json!({"output_type": "stream", "name": "stdout", "text": text}))
+                .unwrap()
+        };
+
+        let rerun = CellOutputs::new(Some(7), &[output("42\n")]);
+        assert_eq!(
+            committed.status(&id, CellType::Code, "print(total)", None),
+            None
+        );
+        assert_eq!(
+            committed.status(&id, CellType::Code, "print(total)", Some(&rerun)),
+            Some(CellGitStatus::Modified)
+        );
+
+        let changed = CellOutputs::new(Some(3), &[output("43\n")]);
+        assert_eq!(
+            committed.status(&id, CellType::Code, "print(total)", Some(&changed)),
+            Some(CellGitStatus::Modified)
+        );
+
+        let same = CellOutputs::new(Some(3), &[output("42\n")]);
+        assert_eq!(
+            committed.status(&id, CellType::Code, "print(total)", Some(&same)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reverted_cell() {
+        let committed: Cell = serde_json::from_value(json!({
+            "id": "total",
+            "cell_type": "code",
+            "metadata": {},
+            "execution_count": 3,
+            "source": ["print(total)"],
+            "outputs": [{"output_type": "stream", "name": "stdout", "text": "42\n"}],
+        }))
+        .unwrap();
+        let current: Cell = serde_json::from_value(json!({
+            "id": "total",
+            "cell_type": "code",
+            "metadata": {"tags": ["report"]},
+            "execution_count": 8,
+            "source": ["print(total * 2)"],
+            "outputs": [{"output_type": "stream", "name": "stdout", "text": "84\n"}],
+        }))
+        .unwrap();
+
+        let Cell::Code {
+            source,
+            execution_count,
+            outputs,
+            metadata,
+            ..
+        } = reverted_cell(&current, &committed, false)
+        else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(source.join(""), "print(total)");
+        assert_eq!(execution_count, Some(8));
+        assert_eq!(
+            outputs.iter().filter_map(output_text).collect::<Vec<_>>(),
+            ["84\n"]
+        );
+        assert_eq!(cell_tags(&metadata), ["report"]);
+
+        let Cell::Code {
+            execution_count,
+            outputs,
+            ..
+        } = reverted_cell(&current, &committed, true)
+        else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(execution_count, Some(3));
+        assert_eq!(
+            outputs.iter().filter_map(output_text).collect::<Vec<_>>(),
+            ["42\n"]
+        );
+    }
+}
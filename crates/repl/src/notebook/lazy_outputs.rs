@@ -0,0 +1,435 @@
+//! # Lazy Outputs
+//!
+//! Notebooks can embed hundreds of megabytes of outputs, mostly images and HTML, and turning all
+//! of it into outputs before showing a single cell makes them take minutes to open. Notebooks are
+//! instead read in a single pass that builds the cells' sources and metadata, and sets output
+//! payloads over [`LAZY_OUTPUT_MIN_SIZE`] aside as the JSON they were saved as. Until a payload is
+//! loaded, its output keeps a placeholder in its metadata:
+//!
+//! ```json
+//! "metadata": { "zed": { "deferred_data": { "image/png": { "payload": 0, "size": 48213055 } } } }
+//! ```
+//!
+//! Payloads are loaded when the user asks to see them. Those still set aside are written back in
+//! place when the notebook is saved, so placeholders never reach the file.
+
+use std::fmt;
+
+use nbformat::v4::Output;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+use super::external_outputs::outputs_mut;
+
+/// Payloads smaller than this are parsed with the rest of the notebook.
+pub(crate) const LAZY_OUTPUT_MIN_SIZE: usize = 1024 * 1024;
+
+/// The field under the output's `zed` metadata listing its payloads that aren't loaded.
+const DEFERRED_DATA: &str = "deferred_data";
+
+/// The output payloads set aside while a notebook was parsed.
+#[derive(Debug, Default)]
+pub(crate) struct DeferredOutputs {
+    payloads: Vec<Box<RawValue>>,
+}
+
+/// A representation of an output that hasn't been loaded yet.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DeferredPayload {
+    pub mime_type: String,
+    /// The size of the payload as it was saved, in bytes.
+    pub size: usize,
+}
+
+impl DeferredOutputs {
+    /// Parses a notebook file, setting aside output payloads of at least `min_size` bytes.
+    pub(crate) fn parse(text: &str, min_size: usize) -> serde_json::Result<(Value, Self)> {
+        let mut deferred = Self::default();
+        let mut deserializer = serde_json::Deserializer::from_str(text);
+        let value = Node {
+            position: Position::Notebook,
+            payloads: &mut deferred.payloads,
+            min_size,
+        }
+        .deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok((value, deferred))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.payloads.is_empty()
+    }
+
+    /// Parses the payload a `deferred_data` reference points to.
+    pub(crate) fn payload(&self, reference: &Value) -> Option<Value> {
+        let index = reference.get("payload")?.as_u64()?;
+        let payload = self.payloads.get(index as usize)?;
+        serde_json::from_str(payload.get()).ok()
+    }
+
+    /// Puts the payloads set aside from a serialized output back into its data. Returns whether
+    /// any were loaded.
+    pub(crate) fn load(&self, output: &mut Map<String, Value>) -> bool {
+        let Some(references) = output
+            .get_mut("metadata")
+            .and_then(|metadata| metadata.get_mut("zed"))
+            .and_then(Value::as_object_mut)
+            .and_then(|zed| zed.remove(DEFERRED_DATA))
+        else {
+            return false;
+        };
+
+        let mut loaded = Map::new();
+        let mut missing = Map::new();
+        for (mime_type, reference) in references.as_object().into_iter().flatten() {
+            match self.payload(reference) {
+                Some(payload) => {
+                    loaded.insert(mime_type.clone(), payload);
+                }
+                None => {
+                    missing.insert(mime_type.clone(), reference.clone());
+                }
+            }
+        }
+
+        if let Some(zed) = output
+            .get_mut("metadata")
+            .and_then(Value::as_object_mut)
+            .and_then(|metadata| metadata.get_mut("zed"))
+            .and_then(Value::as_object_mut)
+        {
+            // Payloads that can't be found stay deferred, rather than being dropped on save
+            if !missing.is_empty() {
+                zed.insert(DEFERRED_DATA.into(), Value::Object(missing));
+            }
+        }
+        if let Some(metadata) = output.get_mut("metadata").and_then(Value::as_object_mut) {
+            if metadata
+                .get("zed")
+                .and_then(Value::as_object)
+                .map_or(false, |zed| zed.is_empty())
+            {
+                metadata.remove("zed");
+            }
+        }
+
+        if loaded.is_empty() {
+            return false;
+        }
+        let data = output
+            .entry("data")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(data) = data.as_object_mut() {
+            data.extend(loaded);
+        }
+        true
+    }
+
+    /// Loads every payload still set aside in a serialized notebook.
+    pub(crate) fn restore(&self, notebook: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        for output in outputs_mut(notebook) {
+            self.load(output);
+        }
+    }
+}
+
+/// The `deferred_data` references of a serialized output, by MIME type.
+pub(crate) fn deferred_references(output: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    output
+        .get("metadata")?
+        .get("zed")?
+        .get(DEFERRED_DATA)?
+        .as_object()
+}
+
+/// Adds `deferred_data` references to a serialized output.
+pub(crate) fn insert_deferred_references(
+    output: &mut Map<String, Value>,
+    references: Map<String, Value>,
+) {
+    let metadata = output
+        .entry("metadata")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Some(metadata) = metadata.as_object_mut() {
+        let zed = metadata
+            .entry("zed")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(zed) = zed.as_object_mut() {
+            match zed.get_mut(DEFERRED_DATA).and_then(Value::as_object_mut) {
+                Some(existing) => existing.extend(references),
+                None => {
+                    zed.insert(DEFERRED_DATA.into(), Value::Object(references));
+                }
+            }
+        }
+    }
+}
+
+/// The representations of a saved output that haven't been loaded yet.
+pub(crate) fn deferred_payloads(output: &Output) -> Vec<DeferredPayload> {
+    let metadata = match output {
+        Output::DisplayData(display_data) => serde_json::to_value(&display_data.metadata),
+        Output::ExecuteResult(execute_result) => serde_json::to_value(&execute_result.metadata),
+        Output::Stream { .. } | Output::Error(_) => return Vec::new(),
+    };
+    let Ok(metadata) = metadata else {
+        return Vec::new();
+    };
+
+    metadata
+        .get("zed")
+        .and_then(|zed| zed.get(DEFERRED_DATA))
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(mime_type, reference)| DeferredPayload {
+            mime_type: mime_type.clone(),
+            size: reference
+                .get("size")
+                .and_then(Value::as_u64)
+                .unwrap_or_default() as usize,
+        })
+        .collect()
+}
+
+/// Where a value is in the notebook, which decides how it's parsed. Values anywhere else are
+/// parsed as they are.
+#[derive(Clone, Copy)]
+enum Position {
+    Notebook,
+    Cells,
+    Cell,
+    Outputs,
+    Output,
+}
+
+struct Node<'a> {
+    position: Position,
+    payloads: &'a mut Vec<Box<RawValue>>,
+    min_size: usize,
+}
+
+impl<'a> Node<'a> {
+    fn child(&mut self, position: Position) -> Node<'_> {
+        Node {
+            position,
+            payloads: &mut *self.payloads,
+            min_size: self.min_size,
+        }
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for Node<'a> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        match self.position {
+            Position::Cells | Position::Outputs => deserializer.deserialize_seq(self),
+            Position::Notebook | Position::Cell | Position::Output => {
+                deserializer.deserialize_map(self)
+            }
+        }
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for Node<'a> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Position::Cells | Position::Outputs => formatter.write_str("an array"),
+            _ => formatter.write_str("an object"),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Value, A::Error> {
+        let position = match self.position {
+            Position::Cells => Position::Cell,
+            _ => Position::Output,
+        };
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element_seed(self.child(position))? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Value, A::Error> {
+        let mut fields = Map::new();
+        let mut references = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value = match (self.position, key.as_str()) {
+                (Position::Notebook, "cells") => {
+                    map.next_value_seed(self.child(Position::Cells))?
+                }
+                (Position::Cell, "outputs") => {
+                    map.next_value_seed(self.child(Position::Outputs))?
+                }
+                (Position::Output, "data") => {
+                    let (data, deferred) = map.next_value_seed(Data {
+                        payloads: &mut *self.payloads,
+                        min_size: self.min_size,
+                    })?;
+                    references = deferred;
+                    Value::Object(data)
+                }
+                _ => map.next_value()?,
+            };
+            fields.insert(key, value);
+        }
+
+        if !references.is_empty() {
+            insert_deferred_references(&mut fields, references);
+        }
+        Ok(Value::Object(fields))
+    }
+}
+
+/// An output's MIME bundle, parsed into the payloads kept and references to those set aside.
+struct Data<'a> {
+    payloads: &'a mut Vec<Box<RawValue>>,
+    min_size: usize,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for Data<'a> {
+    type Value = (Map<String, Value>, Map<String, Value>);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for Data<'a> {
+    type Value = (Map<String, Value>, Map<String, Value>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a MIME bundle")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut data = Map::new();
+        let mut references = Map::new();
+        while let Some(mime_type) = map.next_key::<String>()? {
+            // Payloads are read without being parsed, so setting them aside is just a copy
+            let payload: Box<RawValue> = map.next_value()?;
+            let size = payload.get().len();
+            if size >= self.min_size {
+                // Identical payloads, like the same plot shown by several cells, share one copy
+                let index = match self
+                    .payloads
+                    .iter()
+                    .position(|existing| existing.get() == payload.get())
+                {
+                    Some(index) => index,
+                    None => {
+                        self.payloads.push(payload);
+                        self.payloads.len() - 1
+                    }
+                };
+                references.insert(
+                    mime_type,
+                    serde_json::json!({ "payload": index, "size": size }),
+                );
+            } else {
+                let value = serde_json::from_str(payload.get()).map_err(de::Error::custom)?;
+                data.insert(mime_type, value);
+            }
+        }
+        Ok((data, references))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn notebook() -> Value {
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": { "kernelspec": { "name": "python3", "display_name": "Python 3" } },
+            "cells": [{
+                "id": "a",
+                "cell_type": "code",
+                "execution_count": 1,
+                "metadata": {},
+                "source": ["plot()"],
+                "outputs": [{
+                    "output_type": "display_data",
+                    "metadata": {},
+                    "data": {
+                        "image/png": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk",
+                        "text/plain": ["<Figure>"]
+                    }
+                }]
+            }]
+        })
+    }
+
+    #[test]
+    fn test_large_payloads_are_set_aside() {
+        let text = serde_json::to_string(&notebook()).unwrap();
+        let (mut value, deferred) = DeferredOutputs::parse(&text, 32).unwrap();
+
+        let output = &value["cells"][0]["outputs"][0];
+        assert_eq!(output["data"], json!({ "text/plain": ["<Figure>"] }));
+        assert_eq!(value["cells"][0]["source"], json!(["plot()"]));
+        let output: Output = serde_json::from_value(output.clone()).unwrap();
+        assert_eq!(
+            deferred_payloads(&output),
+            vec![DeferredPayload {
+                mime_type: "image/png".into(),
+                size: 62,
+            }]
+        );
+
+        deferred.restore(&mut value);
+        assert_eq!(value, notebook());
+    }
+
+    #[test]
+    fn test_small_notebooks_parse_as_they_are() {
+        let text = serde_json::to_string(&notebook()).unwrap();
+        let (value, deferred) = DeferredOutputs::parse(&text, LAZY_OUTPUT_MIN_SIZE).unwrap();
+        assert!(deferred.is_empty());
+        assert_eq!(value, notebook());
+
+        assert!(DeferredOutputs::parse("{\"cells\": {}}", 32).is_err());
+        assert!(DeferredOutputs::parse("{} {}", 32).is_err());
+    }
+
+    #[test]
+    fn test_identical_payloads_are_shared() {
+        let mut notebook = notebook();
+        let output = notebook["cells"][0]["outputs"][0].clone();
+        notebook["cells"][0]["outputs"] = json!([output.clone(), output]);
+        let text = serde_json::to_string(&notebook).unwrap();
+        let (mut value, deferred) = DeferredOutputs::parse(&text, 32).unwrap();
+        assert_eq!(deferred.payloads.len(), 1);
+
+        deferred.restore(&mut value);
+        assert_eq!(value, notebook);
+    }
+
+    #[test]
+    fn test_load_output() {
+        let text = serde_json::to_string(&notebook()).unwrap();
+        let (value, deferred) = DeferredOutputs::parse(&text, 32).unwrap();
+        let mut output = value["cells"][0]["outputs"][0].as_object().unwrap().clone();
+
+        assert!(deferred.load(&mut output));
+        assert_eq!(
+            Value::Object(output.clone()),
+            notebook()["cells"][0]["outputs"][0]
+        );
+        assert!(!deferred.load(&mut output));
+        let output: Output = serde_json::from_value(Value::Object(output)).unwrap();
+        assert!(deferred_payloads(&output).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! # Copying Cells as Markdown
+//!
+//! `notebook: copy cell as markdown` copies the selected cell as GitHub-flavored markdown, ready
+//! to paste into an issue or a chat. Code cells become a fenced code block in the notebook's
+//! language, and `notebook: copy cell with output as markdown` adds the text of their outputs in
+//! a second block. Markdown cells are copied as they are, and raw cells as a plain code block.
+//! Images and other rich outputs are left out, since they can't be pasted as text.
+
+use nbformat::v4::Cell;
+
+use super::assistant_context::output_text;
+
+/// A fenced code block holding `text`. The fence is longer than any run of backticks in `text`,
+/// so code that contains fences itself doesn't end the block early.
+fn fenced(text: &str, info: &str) -> String {
+    let longest_run = text
+        .split(|char| char != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}{info}\n{}\n{fence}\n", text.trim_end())
+}
+
+/// The markdown a cell is copied as. `language` is the notebook's language, e.g. `Python`.
+pub(crate) fn cell_markdown(cell: &Cell, language: Option<&str>, include_output: bool) -> String {
+    match cell {
+        Cell::Markdown { source, .. } => format!("{}\n", source.join("").trim_end()),
+        Cell::Raw { source, .. } => fenced(&source.join(""), ""),
+        Cell::Code {
+            source, outputs, ..
+        } => {
+            let language = language.unwrap_or_default().to_lowercase();
+            let mut markdown = fenced(&source.join(""), &language);
+            let output = outputs
+                .iter()
+                .filter_map(output_text)
+                .map(|text| text.trim_end().to_string())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if include_output && !output.is_empty() {
+                markdown.push_str("\nOutput:\n\n");
+                markdown.push_str(&fenced(&output, ""));
+            }
+            markdown
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn code_cell(source: &str, outputs: serde_json::Value) -> Cell {
+        serde_json::from_value(json!({
+            "id": "a",
+            "cell_type": "code",
+            "metadata": {},
+            "execution_count": 1,
+            "source": [source],
+            "outputs": outputs,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_code_cell_markdown() {
+        let cell = code_cell(
+            "df.describe()\n",
+            json!([
+                {"output_type": "stream", "name": "stdout", "text": "loading\n"},
+                {
+                    "output_type": "display_data",
+                    "metadata": {},
+                    "data": {"image/png": "iVBORw0KGgo="}
+                },
+                {
+                    "output_type": "execute_result",
+                    "execution_count": 1,
+                    "metadata": {},
+                    "data": {"text/plain": "count    3.0"}
+                }
+            ]),
+        );
+        assert_eq!(
+            cell_markdown(&cell, Some("Python"), false),
+            "```python\ndf.describe()\n```\n"
+        );
+        assert_eq!(
+            cell_markdown(&cell, Some("Python"), true),
+            "```python\ndf.describe()\n```\n\nOutput:\n\n```\nloading\ncount    3.0\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_fences_outlast_backticks_in_the_source() {
+        let cell = code_cell("print(\"```\")", json!([]));
+        assert_eq!(
+            cell_markdown(&cell, None, true),
+            "````\nprint(\"```\")\n````\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_and_raw_cell_markdown() {
+        let markdown: Cell = serde_json::from_value(json!({
+            "id": "b",
+            "cell_type": "markdown",
+            "metadata": {},
+            "source": ["## Results\n", "\n"],
+        }))
+        .unwrap();
+        assert_eq!(cell_markdown(&markdown, None, true), "## Results\n");
+
+        let raw: Cell = serde_json::from_value(json!({
+            "id": "c",
+            "cell_type": "raw",
+            "metadata": {},
+            "source": ["key: value"],
+        }))
+        .unwrap();
+        assert_eq!(cell_markdown(&raw, None, true), "```\nkey: value\n```\n");
+    }
+}
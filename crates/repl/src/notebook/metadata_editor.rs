@@ -0,0 +1,190 @@
+//! # Notebook Metadata Editor
+//!
+//! A modal editing the notebook-level metadata as JSON: the `kernelspec` and `language_info` Zed
+//! picks a kernel and language from, and the keys other tools keep there. The metadata is checked
+//! against the notebook format before it's applied, so a typo can't make the notebook unreadable.
+
+use std::sync::Arc;
+
+use editor::{Editor, EditorMode, MultiBuffer};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Subscription, Task, View,
+    WeakView,
+};
+use language::{Buffer, LanguageRegistry};
+use nbformat::v4::Metadata as NotebookMetadata;
+use serde_json::Value;
+use ui::prelude::*;
+use util::ResultExt as _;
+use workspace::ModalView;
+
+use super::NotebookEditor;
+
+/// Checks edited metadata, returning it both as written and as nbformat parses it.
+pub(crate) fn parse_notebook_metadata(text: &str) -> Result<(Value, NotebookMetadata), String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|error| format!("Invalid JSON: {error}"))?;
+    if !value.is_object() {
+        return Err("Notebook metadata must be a JSON object".into());
+    }
+    let metadata = serde_json::from_value(value.clone())
+        .map_err(|error| format!("Invalid notebook metadata: {error}"))?;
+    Ok((value, metadata))
+}
+
+pub struct NotebookMetadataEditor {
+    notebook: WeakView<NotebookEditor>,
+    editor: View<Editor>,
+    /// Why the metadata couldn't be applied, shown until it's edited again.
+    error: Option<SharedString>,
+    _language_task: Task<()>,
+    _editor_subscription: Subscription,
+}
+
+impl NotebookMetadataEditor {
+    pub fn new(
+        metadata: &Value,
+        languages: Arc<LanguageRegistry>,
+        notebook: WeakView<NotebookEditor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let text = serde_json::to_string_pretty(metadata).unwrap_or_else(|_| "{}".into());
+        let buffer = cx.new_model(|cx| Buffer::local(text, cx));
+        let language_task = cx.spawn({
+            let buffer = buffer.clone();
+            |_, mut cx| async move {
+                let language = languages.language_for_name("JSON").await.log_err();
+                buffer
+                    .update(&mut cx, |buffer, cx| buffer.set_language(language, cx))
+                    .log_err();
+            }
+        });
+
+        let multi_buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::new(
+                EditorMode::AutoHeight { max_lines: 24 },
+                multi_buffer,
+                None,
+                false,
+                cx,
+            );
+            editor.set_show_gutter(false, cx);
+            editor
+        });
+        cx.focus_view(&editor);
+        let editor_subscription = cx.subscribe(&editor, |this, _, event, cx| {
+            if let editor::EditorEvent::BufferEdited = event {
+                if this.error.take().is_some() {
+                    cx.notify();
+                }
+            }
+        });
+
+        Self {
+            notebook,
+            editor,
+            error: None,
+            _language_task: language_task,
+            _editor_subscription: editor_subscription,
+        }
+    }
+
+    fn save(&mut self, cx: &mut ViewContext<Self>) {
+        let text = self.editor.read(cx).text(cx);
+        match parse_notebook_metadata(&text) {
+            Ok((value, metadata)) => {
+                self.notebook
+                    .update(cx, |notebook, cx| {
+                        notebook.set_notebook_metadata(&value, metadata, cx)
+                    })
+                    .ok();
+                cx.emit(DismissEvent);
+            }
+            Err(error) => {
+                self.error = Some(error.into());
+                cx.notify();
+            }
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::SecondaryConfirm, cx: &mut ViewContext<Self>) {
+        self.save(cx);
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
+        cx.emit(DismissEvent);
+    }
+}
+
+impl EventEmitter<DismissEvent> for NotebookMetadataEditor {}
+impl ModalView for NotebookMetadataEditor {}
+
+impl FocusableView for NotebookMetadataEditor {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl Render for NotebookMetadataEditor {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("NotebookMetadataEditor")
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .w(rems(40.))
+            .p_2()
+            .gap_2()
+            .elevation_3(cx)
+            .child(Label::new("Notebook Metadata").size(LabelSize::Small))
+            .child(
+                div()
+                    .p_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(cx.theme().colors().border)
+                    .bg(cx.theme().colors().editor_background)
+                    .child(self.editor.clone()),
+            )
+            .children(
+                self.error
+                    .clone()
+                    .map(|error| Label::new(error).size(LabelSize::Small).color(Color::Error)),
+            )
+            .child(
+                h_flex()
+                    .justify_end()
+                    .gap_1()
+                    .child(
+                        Button::new("cancel-metadata", "Cancel")
+                            .on_click(cx.listener(|_, _, cx| cx.emit(DismissEvent))),
+                    )
+                    .child(
+                        Button::new("save-metadata", "Apply")
+                            .style(ButtonStyle::Filled)
+                            .on_click(cx.listener(|this, _, cx| this.save(cx))),
+                    ),
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_notebook_metadata() {
+        let (value, metadata) = parse_notebook_metadata(
+            r#"{"kernelspec": {"name": "python3", "display_name": "Python 3"}, "toc": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.kernelspec.unwrap().name, "python3");
+        assert_eq!(value["toc"], serde_json::json!({}));
+
+        assert!(parse_notebook_metadata("[]").is_err());
+        assert!(parse_notebook_metadata("{\"kernelspec\": ").is_err());
+        assert!(
+            parse_notebook_metadata(r#"{"kernelspec": {"display_name": "Python 3"}}"#).is_err()
+        );
+    }
+}
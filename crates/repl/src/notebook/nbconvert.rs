@@ -0,0 +1,143 @@
+//! # nbconvert Exports
+//!
+//! Formats Zed doesn't write itself, like HTML or LaTeX, are exported by running
+//! `jupyter nbconvert` with the formats, templates and arguments listed in the
+//! `jupyter.nbconvert_exports` setting. The notebook is exported as it's shown, including edits
+//! that haven't been saved, and the result is written next to the notebook.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context as _, Result};
+use smol::process::Command;
+
+use crate::jupyter_settings::NbconvertExport;
+
+/// The arguments nbconvert is run with to export `input` to `output_dir`, named `output_name`
+/// with the extension of the export's format.
+fn nbconvert_args(
+    export: &NbconvertExport,
+    input: &Path,
+    output_dir: &Path,
+    output_name: &str,
+) -> Vec<String> {
+    let mut args = vec![
+        "nbconvert".to_string(),
+        "--to".to_string(),
+        export.format.clone(),
+    ];
+    if let Some(template) = &export.template {
+        args.extend(["--template".to_string(), template.clone()]);
+    }
+    args.extend(export.args.iter().cloned());
+    args.extend([
+        "--output-dir".to_string(),
+        output_dir.to_string_lossy().to_string(),
+        "--output".to_string(),
+        output_name.to_string(),
+        input.to_string_lossy().to_string(),
+    ]);
+    args
+}
+
+/// The file nbconvert reports writing, e.g. `[NbConvertApp] Writing 1024 bytes to out.html`.
+/// Relative paths are relative to `output_dir`.
+fn written_path(log: &str, output_dir: &Path) -> Option<PathBuf> {
+    let path = log.lines().rev().find_map(|line| {
+        let (_, rest) = line.split_once("] Writing ")?;
+        let (_, path) = rest.split_once(" to ")?;
+        Some(Path::new(path.trim()))
+    })?;
+    Some(output_dir.join(path))
+}
+
+/// Exports `notebook`, the notebook's JSON, with `<command> nbconvert` and returns the path of
+/// the file it wrote. The notebook is handed to nbconvert through a temporary file named
+/// `output_name`, since the notebook on disk may be out of date or not be an `.ipynb` file.
+pub(crate) async fn run_nbconvert(
+    command: String,
+    export: NbconvertExport,
+    notebook: String,
+    output_dir: PathBuf,
+    output_name: String,
+) -> Result<PathBuf> {
+    let input_dir = std::env::temp_dir().join(format!("zed-nbconvert-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&input_dir)?;
+    let input = input_dir.join(format!("{output_name}.ipynb"));
+
+    let output = async {
+        smol::fs::write(&input, notebook).await?;
+        Command::new(&command)
+            .args(nbconvert_args(&export, &input, &output_dir, &output_name))
+            .current_dir(&output_dir)
+            .output()
+            .await
+            .with_context(|| format!("failed to run `{command} nbconvert`"))
+    }
+    .await;
+    std::fs::remove_dir_all(&input_dir).ok();
+    let output = output?;
+
+    let log = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        let error = log
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("nbconvert failed");
+        return Err(anyhow!("{error}"));
+    }
+    written_path(&log, &output_dir).context("nbconvert didn't report the file it wrote")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nbconvert_args() {
+        let export = NbconvertExport {
+            format: "html".to_string(),
+            template: Some("lab".to_string()),
+            args: vec!["--no-input".to_string()],
+        };
+        assert_eq!(
+            nbconvert_args(
+                &export,
+                Path::new("/tmp/input/analysis.ipynb"),
+                Path::new("/work"),
+                "analysis"
+            ),
+            [
+                "nbconvert",
+                "--to",
+                "html",
+                "--template",
+                "lab",
+                "--no-input",
+                "--output-dir",
+                "/work",
+                "--output",
+                "analysis",
+                "/tmp/input/analysis.ipynb",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_written_path() {
+        let log = "[NbConvertApp] Converting notebook analysis.ipynb to html\n\
+                   [NbConvertApp] Writing 283745 bytes to analysis.html\n";
+        assert_eq!(
+            written_path(log, Path::new("/work")),
+            Some(PathBuf::from("/work/analysis.html"))
+        );
+        assert_eq!(
+            written_path(
+                "[NbConvertApp] Writing 12 bytes to /out/analysis.py",
+                Path::new("/work")
+            ),
+            Some(PathBuf::from("/out/analysis.py"))
+        );
+        assert_eq!(written_path("nothing written", Path::new("/work")), None);
+    }
+}
@@ -1,60 +1,257 @@
 #![allow(unused, dead_code)]
+use std::collections::VecDeque;
+use std::env::temp_dir;
 use std::future::Future;
-use std::{path::PathBuf, sync::Arc};
+use std::ops::Range;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{Context as _, Result};
-use client::proto::ViewId;
-use collections::HashMap;
+use client::proto::{self, PeerId, ViewId};
+use client::Client;
+use collections::{HashMap, HashSet};
+use editor::{scroll::Autoscroll, Editor, GotoDefinitionKind};
 use feature_flags::{FeatureFlagAppExt as _, NotebookFeatureFlag};
-use futures::future::Shared;
-use futures::FutureExt;
+use futures::channel::oneshot;
+use futures::future::{LocalBoxFuture, Shared};
+use futures::{FutureExt, StreamExt as _};
 use gpui::{
-    actions, list, prelude::*, AnyElement, AppContext, EventEmitter, FocusHandle, FocusableView,
-    ListScrollEvent, ListState, Model, Point, Task, View,
+    actions, impl_actions, list, prelude::*, AnyElement, AnyWindowHandle, AppContext,
+    BackgroundExecutor, ClipboardItem, DragMoveEvent, EntityId, EventEmitter, FocusHandle,
+    FocusableView, KeyContext, ListOffset, ListScrollEvent, ListState, Model, MouseButton,
+    MouseDownEvent, Point, Subscription, Task, View, WeakView,
+};
+use language::{
+    Bias, Buffer, BufferEvent, DiagnosticEntry, DiagnosticSet, Language, LanguageRegistry,
+    LanguageServerId, PointUtf16, Rope, ToOffset as _, ToPointUtf16 as _, TransactionId, Unclipped,
+};
+use parking_lot::RwLock;
+use picker::Picker;
+use project::lsp_command::SignatureHelp;
+use project::notebook_search::{is_notebook_path, notebook_sources};
+use project::search::SearchQuery;
+use project::{Completion, Fs, LocationLink, Project, ProjectEntryId, ProjectPath, WorktreeId};
+use runtimelib::{
+    CompleteReply, CompleteRequest, ExecuteRequest, ExecutionState, InterruptRequest,
+    JupyterMessage, JupyterMessageContent, ShutdownRequest,
+};
+use serde::{Deserialize, Serialize};
+use settings::{Settings as _, SettingsStore};
+use ui::{prelude::*, ContextMenu, PopoverMenu, Tooltip};
+use util::ResultExt as _;
+use workspace::assistant_delegate::AssistantDelegate;
+use workspace::item::{Dedup, FollowEvent, FollowableItem, ItemEvent, TabContentParams};
+use workspace::notifications::NotificationId;
+use workspace::searchable::{SearchEvent, SearchOptions, SearchableItem, SearchableItemHandle};
+use workspace::{
+    Item, ItemHandle, ItemId, Pane, ProjectItem, SerializableItem, ToolbarItemLocation, Workspace,
+    WorkspaceId,
 };
-use language::{Language, LanguageRegistry};
-use project::{Project, ProjectEntryId, ProjectPath};
-use ui::{prelude::*, Tooltip};
-use workspace::item::{ItemEvent, TabContentParams};
-use workspace::searchable::SearchableItemHandle;
-use workspace::{Item, ItemHandle, ProjectItem, ToolbarItemLocation};
 use workspace::{ToolbarItemEvent, ToolbarItemView};
 
-use super::{Cell, CellPosition, RenderableCell};
+use super::assistant_context::{cell_context, cell_context_title};
+use super::auto_run::{auto_run, set_auto_run};
+use super::blame::{cell_commits, json_lines, BlameLoader, CellCommit};
+use super::cell_document::{cell_document_path, CellDocument};
+use super::collab::{cell_id_from_proto, cell_id_to_proto};
+use super::completions::{
+    code_point_offset, is_python, kernel_completions, magic_completions, CellCompletionProvider,
+};
+use super::duplicate_outputs::{dedupe, resolve_duplicates, DUPLICATE_OUTPUT_MIN_SIZE};
+use super::external_outputs::{
+    external_references, externalize, outputs_dir_name, reference_path, rehydrate,
+    EXTERNAL_OUTPUT_MIN_SIZE,
+};
+use super::filter::{CellFilter, FilteredCell};
+use super::find::{
+    cell_matches, source_ranges_by_cell, MatchLocation, NotebookMatch, NotebookSearchHighlights,
+};
+use super::git_status::{reverted_cell, CommittedCells};
+use super::lazy_outputs::{DeferredOutputs, LAZY_OUTPUT_MIN_SIZE};
+use super::markdown_copy::cell_markdown;
+use super::metadata_editor::NotebookMetadataEditor;
+use super::nbconvert::run_nbconvert;
+use super::outline::{markdown_headings, NotebookOutline, OutlineEntry};
+use super::output_limits::strip_outputs;
+use super::output_spill::{restore_spilled_outputs, OutputSpillDir, SPILL_MIN_SIZE};
+use super::package_install::PackageManager;
+use super::persistence::NOTEBOOK_DB;
+use super::quarto::{default_language, is_markdown_notebook_path, parse_quarto, quarto_text};
+use super::semantics_provider::CellSemanticsProvider;
+use super::slideshow::slide_steps;
+use super::trust::{has_untrusted_outputs, notebook_signature};
+use super::unknown_fields::UnknownFields;
+use super::{
+    apply_cell_editor_settings, new_cell_id, set_nbformat_cell_id, Cell, CellEvent, CellPosition,
+    CellSnapshot, RenderableCell,
+};
+use crate::components::KernelPickerDelegate;
+use crate::jupyter_settings::NbconvertExport;
+use crate::kernels::{
+    kernel_environment, JupyterMessageChannel, Kernel, KernelSpecification, NativeRunningKernel,
+    RunningKernel, SshRunningKernel,
+};
+use crate::outputs::{ExecutionStatus, ExecutionView};
+use crate::plot_theme::PlotTheme;
+use crate::repl_store::ReplStore;
+use crate::JupyterSettings;
+use crate::NotebookSettings;
 
-use nbformat::v4::CellId;
 use nbformat::v4::Metadata as NotebookMetadata;
+use nbformat::v4::{CellId, CellType};
 
 actions!(
     notebook,
     [
         OpenNotebook,
+        NewNotebook,
+        EditCell,
+        GoToNextErrorCell,
+        GoToPreviousErrorCell,
+        EnterCommandMode,
+        InsertCellAbove,
+        InsertCellBelow,
+        DeleteCell,
+        UndoCellOperation,
+        ConvertToCode,
+        ConvertToMarkdown,
+        ConvertToRaw,
+        CutCell,
+        CopyCell,
+        CopyCellAsMarkdown,
+        CopyCellWithOutputAsMarkdown,
+        RevertCellToHead,
+        RevertCellAndOutputsToHead,
+        ShowCellBlame,
+        PasteCellBelow,
+        PasteCellAbove,
+        RunCell,
+        RunAndInsertBelow,
+        RunAndSelectBelow,
         RunAll,
+        RunAllAbove,
+        RunAllBelow,
+        ClearExecutionQueue,
+        InterruptKernel,
+        RestartKernel,
         ClearOutputs,
+        ClearCellOutput,
+        ToggleOutputsCollapsed,
+        ToggleOutputsScrolled,
         MoveCellUp,
         MoveCellDown,
         AddMarkdownBlock,
         AddCodeBlock,
+        AskAssistant,
+        InsertAssistantSuggestion,
+        ToggleGuestExecution,
+        ToggleCellComments,
+        ToggleCellTags,
+        ToggleCellEditable,
+        ToggleCellDeletable,
+        ToggleReadOnly,
+        ToggleAutoRun,
+        ToggleSearchOutputs,
+        ToggleLineNumbers,
+        ToggleCellLineNumbers,
+        FilterErrorCells,
+        FilterCodeCells,
+        ClearCellFilter,
+        EditNotebookMetadata,
+        TrustNotebook,
+        LoadLargeOutputs,
+        StartPresentation,
+        StopPresentation,
+        NextSlide,
+        PreviousSlide,
+        ExportToPdf,
     ]
 );
 
+/// Adds a digit to the count of the next cell motion, e.g. `3 j` in vim mode.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct PushCount(pub usize);
+
+/// Exports the notebook with nbconvert to the first format of the `jupyter.nbconvert_exports`
+/// setting named `format`, or to a format picked from a prompt.
+#[derive(Clone, Default, Deserialize, PartialEq)]
+pub struct ExportWithNbconvert {
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+impl_actions!(notebook, [PushCount, ExportWithNbconvert]);
+
+/// The notebook scrollbar's thumb while it's dragged.
+#[derive(Clone, Render)]
+struct DraggedScrollbarThumb;
+
 pub(crate) const MAX_TEXT_BLOCK_WIDTH: f32 = 9999.0;
 pub(crate) const SMALL_SPACING_SIZE: f32 = 8.0;
 pub(crate) const MEDIUM_SPACING_SIZE: f32 = 12.0;
 pub(crate) const LARGE_SPACING_SIZE: f32 = 16.0;
 pub(crate) const GUTTER_WIDTH: f32 = 19.0;
+pub(crate) const EXECUTION_COUNT_WIDTH: f32 = 52.0;
 pub(crate) const CODE_BLOCK_INSET: f32 = MEDIUM_SPACING_SIZE;
 pub(crate) const CONTROL_SIZE: f32 = 20.0;
+/// How many cells of a freshly loaded notebook are created between frames.
+const CELL_LOAD_BATCH_SIZE: usize = 50;
+pub(crate) const SCROLLBAR_WIDTH: f32 = 8.0;
+/// The minimum height of a marker on the scrollbar, so cells stay visible in long notebooks.
+pub(crate) const SCROLLBAR_MARKER_HEIGHT: f32 = 3.0;
+/// The height of the region `scrolled` outputs are shown in.
+pub(crate) const SCROLLED_OUTPUT_HEIGHT: f32 = 400.0;
+/// A kernel that was just launched, its messages, and a future that resolves with an error if it
+/// dies.
+type LaunchedKernel = (
+    Box<dyn RunningKernel>,
+    JupyterMessageChannel,
+    LocalBoxFuture<'static, Option<String>>,
+);
+
+/// How long a cell stays highlighted after following a collaborator to it.
+const FOLLOWED_CELL_HIGHLIGHT_DURATION: Duration = Duration::from_millis(1500);
 
-pub fn init(cx: &mut AppContext) {
-    if cx.has_flag::<NotebookFeatureFlag>() || std::env::var("LOCAL_NOTEBOOK_DEV").is_ok() {
+/// How long to wait after an edit before sending the cells to language servers.
+const CELL_DOCUMENT_SYNC_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// How long to wait after outputs change before checking them against the memory limit.
+const OUTPUT_MEMORY_LIMIT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// How many changes to the list of cells can be undone. Deleted cells are kept with their
+/// outputs until they fall off the undo stack.
+const MAX_UNDO_OPERATIONS: usize = 100;
+
+pub fn init(client: &Arc<Client>, cx: &mut AppContext) {
+    workspace::FollowableViewRegistry::register::<NotebookEditor>(cx);
+    super::collab::init(&client.clone().into());
+    super::console::init(cx);
+    editor::register_open_excerpts_handler(open_notebook_excerpts, cx);
+
+    if notebooks_enabled(cx) {
         workspace::register_project_item::<NotebookEditor>(cx);
+        workspace::register_serializable_item::<NotebookEditor>(cx);
     }
 
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(|workspace, _: &NewNotebook, cx| {
+            if notebooks_enabled(cx) {
+                NotebookEditor::new_notebook(workspace, cx);
+            }
+        });
+    })
+    .detach();
+
     cx.observe_flag::<NotebookFeatureFlag, _>({
         move |is_enabled, cx| {
             if is_enabled {
                 workspace::register_project_item::<NotebookEditor>(cx);
+                workspace::register_serializable_item::<NotebookEditor>(cx);
             } else {
                 // todo: there is no way to unregister a project item, so if the feature flag
                 // gets turned off they need to restart Zed.
@@ -64,6 +261,116 @@ pub fn init(cx: &mut AppContext) {
     .detach();
 }
 
+fn notebooks_enabled(cx: &AppContext) -> bool {
+    cx.has_flag::<NotebookFeatureFlag>() || std::env::var("LOCAL_NOTEBOOK_DEV").is_ok()
+}
+
+/// Opens a notebook's project search results in the notebook, at the cell of the first match,
+/// instead of in its JSON.
+fn open_notebook_excerpts(
+    buffer: &Model<Buffer>,
+    ranges: &[Range<usize>],
+    pane: &View<Pane>,
+    workspace: &mut Workspace,
+    cx: &mut ViewContext<Workspace>,
+) -> bool {
+    if !notebooks_enabled(cx) {
+        return false;
+    }
+    let buffer = buffer.read(cx);
+    let Some(file) = buffer.file().filter(|file| is_notebook_path(file.path())) else {
+        return false;
+    };
+    let Some(json_offset) = ranges.first().map(|range| range.start.min(range.end)) else {
+        return false;
+    };
+    let json_end = ranges[0].start.max(ranges[0].end);
+    let Some((cell_index, range)) = notebook_sources(&buffer.text())
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|source| {
+            let start = source.source_offset(json_offset)?;
+            let end = source.source_offset(json_end).unwrap_or(source.text.len());
+            Some((source.cell_index, start..end))
+        })
+    else {
+        return false;
+    };
+
+    let project_path = ProjectPath {
+        worktree_id: file.worktree_id(cx),
+        path: file.path().clone(),
+    };
+    let open = workspace.open_path(project_path, Some(pane.downgrade()), true, cx);
+    cx.spawn(|_, mut cx| async move {
+        let item = open.await?;
+        if let Some(notebook) = item.downcast::<NotebookEditor>() {
+            notebook.update(&mut cx, |notebook, cx| {
+                notebook.reveal_source_range(cell_index, range, cx)
+            })?;
+        }
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+    true
+}
+
+/// The kernels a notebook in `project` can be run on. Only kernels on the host of an SSH project
+/// can run its notebooks, and kernels other clients started are never picked on their own.
+fn notebook_kernel_specifications(
+    project: &Model<Project>,
+    worktree_id: Option<WorktreeId>,
+    cx: &AppContext,
+) -> Vec<KernelSpecification> {
+    let is_via_ssh = project.read(cx).is_via_ssh();
+    let store = ReplStore::global(cx).read(cx);
+    let kernel_specifications = match worktree_id {
+        Some(worktree_id) => store
+            .kernel_specifications_for_worktree(worktree_id)
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => store
+            .pure_jupyter_kernel_specifications()
+            .cloned()
+            .collect(),
+    };
+    kernel_specifications
+        .into_iter()
+        .filter(|spec| matches!(spec, KernelSpecification::Ssh(_)) == is_via_ssh)
+        .filter(|spec| !spec.is_external())
+        .collect()
+}
+
+/// A slideshow presented from the notebook, see [`slide_steps`].
+struct Presentation {
+    steps: Vec<Vec<CellId>>,
+    step: usize,
+    /// Whether the window was made full-screen for the presentation, and should be restored.
+    exit_fullscreen: bool,
+}
+
+/// A change to the notebook's list of cells, kept so it can be undone.
+enum CellOperation {
+    Inserted(CellId),
+    /// Holds on to the cell itself so undoing restores its outputs too.
+    Deleted {
+        index: usize,
+        cell_id: CellId,
+        cell: Cell,
+    },
+    Moved {
+        from: usize,
+        to: usize,
+    },
+    /// The cell was replaced with one of another type, or with its committed version.
+    Converted {
+        cell_id: CellId,
+        cell: Cell,
+    },
+    /// Matches of the find bar were replaced, in each cell's buffer by the transaction.
+    Replaced(Vec<(CellId, TransactionId)>),
+}
+
 pub struct NotebookEditor {
     languages: Arc<LanguageRegistry>,
     project: Model<Project>,
@@ -72,11 +379,110 @@ pub struct NotebookEditor {
     notebook_item: Model<NotebookItem>,
 
     remote_id: Option<ViewId>,
+    /// The collaborator being followed in this notebook, whose executions are scrolled to.
+    leader_peer_id: Option<PeerId>,
+    /// A cell briefly highlighted after following the leader to it, cleared by the task.
+    highlighted_cell: Option<(CellId, Task<()>)>,
+    /// The slideshow being presented in place of the cell list, if any.
+    presentation: Option<Presentation>,
     cell_list: ListState,
+    /// The cells currently visible in the cell list, shown as the scrollbar's thumb.
+    visible_cells: Range<usize>,
+    /// When each cell was last on screen. Outputs of the cells seen the longest time ago are
+    /// spilled to disk first, see [`super::output_spill`].
+    cell_last_visible: HashMap<CellId, Instant>,
+    output_memory_limit_task: Option<Task<()>>,
 
     selected_cell_index: usize,
     cell_order: Vec<CellId>,
     cell_map: HashMap<CellId, Cell>,
+    /// The cells as of the last save, by the id of their views. Cells were added, removed,
+    /// reordered or replaced since when the notebook's cells differ from these.
+    saved_cells: Vec<EntityId>,
+    /// Whether the notebook's metadata was edited since the notebook was last saved.
+    metadata_modified: bool,
+    undo_stack: VecDeque<CellOperation>,
+    /// The clipboard text of the last cut cell and its id. Pasting that text once keeps the id,
+    /// so moving a cell by cutting and pasting it doesn't look like a new cell to diff tools.
+    last_cut: Option<(String, CellId)>,
+    /// A count typed in command mode with vim bindings, used by the next cell motion.
+    pending_count: Option<usize>,
+    notebook_language: Shared<Task<Option<Arc<Language>>>>,
+
+    kernel: Kernel,
+    /// Cells waiting on a reply from the kernel, keyed by the id of their execute request.
+    pending_executions: HashMap<String, CellId>,
+    /// Cells waiting for their turn to be sent to the kernel. Only one cell executes at a time so
+    /// that queued cells can be cancelled without interrupting the one currently running.
+    execution_queue: VecDeque<CellId>,
+    /// Cells installing a missing package, keyed by the id of the install's execute request.
+    pending_installs: HashMap<String, CellId>,
+    /// Completion requests sent to the kernel, keyed by the id of their message.
+    pending_completions: HashMap<String, oneshot::Sender<CompleteReply>>,
+    /// Code run on the kernel from outside the notebook's cells, by the console panel or an editor
+    /// attached to the notebook, keyed by the id of its execute request.
+    pending_detached_executions: HashMap<String, WeakView<ExecutionView>>,
+    /// The theme the kernel last styled plots with, see [`PlotTheme`].
+    plot_theme: Option<PlotTheme>,
+    /// The code cell most recently run from the keyboard, kept in view as its outputs arrive.
+    last_run_cell: Option<CellId>,
+    /// Whether guests of a shared project may run cells on this notebook's kernel. Only the host
+    /// can change it; followers are told about it so they know whether running a cell will work.
+    guests_can_run: bool,
+    /// Whether the notebook was made read-only. It's also read-only while the project is.
+    read_only: bool,
+    /// Whether the cells are currently read-only, so they're only updated when that changes.
+    cells_read_only: bool,
+    /// Whether the cells show line numbers in this notebook, overriding
+    /// [`NotebookSettings::show_line_numbers`] when set.
+    show_line_numbers: Option<bool>,
+    /// Cells whose line numbers were shown or hidden on their own, by the id of their view. Like
+    /// in Jupyter, showing or hiding line numbers in the whole notebook resets them.
+    cell_line_numbers: HashMap<EntityId, bool>,
+    /// Whether saved outputs that can carry scripts or load external resources are shown, see
+    /// [`super::trust`].
+    trusted: bool,
+    messaging_task: Option<Task<()>>,
+    process_status_task: Option<Task<()>>,
+    /// The code cells joined into one document for language servers, see [`CellDocument`].
+    cell_document: CellDocument,
+    cell_document_buffer: Option<(Model<Buffer>, Subscription)>,
+    /// Whether IPython magics are commented out in the document, which only applies to Python.
+    cell_document_masks_magics: bool,
+    cell_document_sync_task: Option<Task<()>>,
+    /// The language servers that reported diagnostics for the cells, so they can be cleared.
+    cell_diagnostic_servers: Vec<LanguageServerId>,
+    /// Whether the notebook is still being read from disk, so it has no cells yet.
+    awaiting_notebook: bool,
+    /// Creates the cells of a notebook that just finished loading, a batch at a time.
+    cell_loading_task: Option<Task<Result<()>>>,
+    /// The selected cell and scroll position to restore once the cells are loaded.
+    pending_position: Option<(Option<CellId>, Option<(CellId, f32)>)>,
+    /// The index of a cell and the range of its source to select once the cells are loaded.
+    pending_source_range: Option<(usize, Range<usize>)>,
+    /// Whether the find bar searches the text of outputs as well as the cells' sources.
+    search_outputs: bool,
+    /// The find bar's current query, which cells can be filtered by.
+    find_query: Option<Arc<SearchQuery>>,
+    /// The cells with matches for the find bar's query, marked on the scrollbar.
+    match_cells: HashSet<CellId>,
+    /// How far below the top of the scrollbar's thumb it was grabbed, in cells, while it's dragged.
+    scrollbar_grab: Option<f32>,
+    /// The filter hiding cells, and the cells it hides.
+    cell_filter: Option<CellFilter>,
+    hidden_cells: HashSet<CellId>,
+    /// The cells of the notebook as committed at HEAD, `None` if it isn't committed.
+    committed_cells: Option<CommittedCells>,
+    /// Whether outputs and execution counts are left out of the comparison, see
+    /// [`NotebookSettings::diff_ignores_outputs`].
+    diff_ignores_outputs: bool,
+    committed_cells_task: Option<Task<()>>,
+    /// The subscriptions to each cell, dropped when it's removed or replaced.
+    cell_subscriptions: HashMap<CellId, Vec<Subscription>>,
+    _notebook_item_subscription: Option<Subscription>,
+    _project_subscription: Subscription,
+    _git_subscription: Subscription,
+    _settings_subscription: Subscription,
 }
 
 impl NotebookEditor {
@@ -93,24 +499,25 @@ impl NotebookEditor {
         let notebook_language = notebook_item.read(cx).notebook_language();
         let notebook_language = cx.spawn(|_, _| notebook_language).shared();
 
-        let mut cell_order = vec![]; // Vec<CellId>
-        let mut cell_map = HashMap::default(); // HashMap<CellId, Cell>
-
-        for (index, cell) in notebook_item
-            .read(cx)
-            .notebook
-            .clone()
-            .cells
-            .iter()
-            .enumerate()
-        {
-            let cell_id = cell.id();
-            cell_order.push(cell_id.clone());
-            cell_map.insert(
-                cell_id.clone(),
-                Cell::load(cell, &languages, notebook_language.clone(), cx),
-            );
-        }
+        let notebook = notebook_item.read(cx).notebook.clone();
+        let trusted = notebook_item.read(cx).is_trusted();
+        // A notebook that's still loading has no cells yet. They're created once it's loaded.
+        let awaiting_notebook = notebook_item.read(cx).is_loading();
+        let notebook_item_subscription = awaiting_notebook.then(|| {
+            cx.observe(&notebook_item, |this, notebook_item, cx| {
+                if this.awaiting_notebook && !notebook_item.read(cx).is_loading() {
+                    this.awaiting_notebook = false;
+                    this.load_cells_incrementally(cx);
+                }
+            })
+        });
+        let (cell_order, cell_map, cell_subscriptions) = Self::load_cells(
+            &notebook,
+            &languages,
+            notebook_language.clone(),
+            trusted,
+            cx,
+        );
 
         let view = cx.view().downgrade();
         let cell_count = cell_order.len();
@@ -127,6 +534,7 @@ impl NotebookEditor {
                             notebook
                                 .cell_order
                                 .get(ix)
+                                .filter(|cell_id| !notebook.hidden_cells.contains(*cell_id))
                                 .and_then(|cell_id| notebook.cell_map.get(cell_id))
                                 .map(|cell| notebook.render_cell(ix, cell, cx).into_any_element())
                         })
@@ -134,625 +542,6222 @@ impl NotebookEditor {
                     .unwrap_or_else(|| div().into_any())
             },
         );
+        cell_list.set_scroll_handler({
+            let view = cx.view().downgrade();
+            move |event, cx| {
+                view.update(cx, |this, cx| {
+                    this.visible_cells = event.visible_range.clone();
+                    this.cells_scrolled_into_view(cx);
+                    cx.emit(NotebookEvent::Scrolled);
+                    cx.notify();
+                })
+                .ok();
+            }
+        });
 
-        Self {
+        // The project becomes read-only when it's disconnected or the host revokes write access
+        let project_subscription = cx.observe(&project, |this, _, cx| this.update_read_only(cx));
+        // A commit or checkout changes what the cells are compared against
+        let git_subscription = cx.subscribe(&project, |this, _, event, cx| {
+            if let project::Event::WorktreeUpdatedGitRepositories = event {
+                this.refresh_committed_cells(cx);
+            }
+        });
+        let settings_subscription = cx.observe_global::<SettingsStore>(|this, cx| {
+            let diff_ignores_outputs = NotebookSettings::get_global(cx).diff_ignores_outputs;
+            if this.diff_ignores_outputs != diff_ignores_outputs {
+                this.diff_ignores_outputs = diff_ignores_outputs;
+                for cell_id in this.cell_order.clone() {
+                    this.update_cell_git_status(&cell_id, cx);
+                }
+            }
+            for cell in this.cell_map.values() {
+                this.style_cell_editor(cell, cx);
+            }
+            this.apply_plot_theme(cx);
+            cx.notify();
+        });
+
+        let mut this = Self {
             project,
             languages: languages.clone(),
             focus_handle,
             notebook_item,
             remote_id: None,
+            leader_peer_id: None,
+            highlighted_cell: None,
+            presentation: None,
             cell_list,
+            visible_cells: 0..0,
+            cell_last_visible: HashMap::default(),
+            output_memory_limit_task: None,
             selected_cell_index: 0,
             cell_order: cell_order.clone(),
             cell_map: cell_map.clone(),
+            saved_cells: cell_order
+                .iter()
+                .filter_map(|cell_id| Some(cell_map.get(cell_id)?.entity_id()))
+                .collect(),
+            metadata_modified: false,
+            undo_stack: VecDeque::new(),
+            last_cut: None,
+            pending_count: None,
+            notebook_language,
+            kernel: Kernel::Shutdown,
+            pending_executions: HashMap::default(),
+            execution_queue: VecDeque::new(),
+            pending_installs: HashMap::default(),
+            pending_detached_executions: HashMap::default(),
+            plot_theme: None,
+            pending_completions: HashMap::default(),
+            last_run_cell: None,
+            guests_can_run: false,
+            read_only: false,
+            cells_read_only: false,
+            show_line_numbers: None,
+            cell_line_numbers: HashMap::default(),
+            trusted,
+            messaging_task: None,
+            process_status_task: None,
+            cell_document: CellDocument::default(),
+            cell_document_buffer: None,
+            cell_document_masks_magics: false,
+            cell_document_sync_task: None,
+            cell_diagnostic_servers: Vec::new(),
+            awaiting_notebook,
+            cell_loading_task: None,
+            pending_position: None,
+            pending_source_range: None,
+            search_outputs: false,
+            find_query: None,
+            match_cells: HashSet::default(),
+            scrollbar_grab: None,
+            cell_filter: None,
+            hidden_cells: HashSet::default(),
+            committed_cells: None,
+            diff_ignores_outputs: NotebookSettings::get_global(cx).diff_ignores_outputs,
+            committed_cells_task: None,
+            cell_subscriptions,
+            _notebook_item_subscription: notebook_item_subscription,
+            _project_subscription: project_subscription,
+            _git_subscription: git_subscription,
+            _settings_subscription: settings_subscription,
+        };
+        this.update_read_only(cx);
+        this.open_cell_document(cx);
+        this.refresh_committed_cells(cx);
+        if !awaiting_notebook {
+            // Followers and restored notebooks are only set up once they're created
+            cx.defer(|this, cx| this.run_on_open(cx));
         }
+        this
     }
 
-    fn has_outputs(&self, cx: &ViewContext<Self>) -> bool {
-        self.cell_map.values().any(|cell| {
-            if let Cell::Code(code_cell) = cell {
-                code_cell.read(cx).has_outputs()
-            } else {
-                false
-            }
-        })
+    /// Whether the notebook can only be looked at: cells render and show their outputs, but can't
+    /// be edited, run, added, removed or moved.
+    pub fn is_read_only(&self, cx: &AppContext) -> bool {
+        self.read_only || self.project.read(cx).is_read_only(cx)
     }
 
-    fn is_dirty(&self, cx: &AppContext) -> bool {
-        self.cell_map.values().any(|cell| {
-            if let Cell::Code(code_cell) = cell {
-                code_cell.read(cx).is_dirty(cx)
-            } else {
-                false
-            }
-        })
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut ViewContext<Self>) {
+        if self.read_only == read_only {
+            return;
+        }
+        self.read_only = read_only;
+        self.update_read_only(cx);
+        cx.emit(NotebookEvent::ReadOnlyChanged);
     }
 
-    fn clear_outputs(&mut self, cx: &mut ViewContext<Self>) {
+    fn toggle_read_only(&mut self, cx: &mut ViewContext<Self>) {
+        self.set_read_only(!self.read_only, cx);
+    }
+
+    /// Whether the cells show line numbers, in this notebook or by default.
+    pub fn shows_line_numbers(&self, cx: &AppContext) -> bool {
+        self.show_line_numbers
+            .unwrap_or(NotebookSettings::get_global(cx).show_line_numbers)
+    }
+
+    /// Shows or hides line numbers in this notebook's cells, or follows the
+    /// `notebook.show_line_numbers` setting again with `None`.
+    pub fn set_show_line_numbers(
+        &mut self,
+        show_line_numbers: Option<bool>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.show_line_numbers == show_line_numbers && self.cell_line_numbers.is_empty() {
+            return;
+        }
+        self.show_line_numbers = show_line_numbers;
+        self.cell_line_numbers.clear();
         for cell in self.cell_map.values() {
-            if let Cell::Code(code_cell) = cell {
-                code_cell.update(cx, |cell, _cx| {
-                    cell.clear_outputs();
-                });
-            }
+            self.style_cell_editor(cell, cx);
         }
+        cx.emit(NotebookEvent::LineNumbersChanged);
+        cx.notify();
     }
 
-    fn run_cells(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Cells would all run here, if that was implemented!");
+    fn toggle_line_numbers(&mut self, cx: &mut ViewContext<Self>) {
+        let show_line_numbers = !self.shows_line_numbers(cx);
+        self.set_show_line_numbers(Some(show_line_numbers), cx);
     }
 
-    fn open_notebook(&mut self, _: &OpenNotebook, _cx: &mut ViewContext<Self>) {
-        println!("Open notebook triggered");
+    /// Shows or hides line numbers in the selected cell only.
+    fn toggle_cell_line_numbers(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id).cloned())
+        else {
+            return;
+        };
+        let show_line_numbers = self
+            .cell_line_numbers
+            .get(&cell.entity_id())
+            .copied()
+            .unwrap_or_else(|| self.shows_line_numbers(cx));
+        self.cell_line_numbers
+            .insert(cell.entity_id(), !show_line_numbers);
+        self.style_cell_editor(&cell, cx);
+        cx.notify();
     }
 
-    fn move_cell_up(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Move cell up triggered");
+    /// Styles a cell's editor with the notebook settings, and whether this notebook or the cell
+    /// shows line numbers. Cells style their editors with the settings alone when they're created.
+    fn style_cell_editor(&self, cell: &Cell, cx: &mut WindowContext) {
+        let show_line_numbers = self
+            .cell_line_numbers
+            .get(&cell.entity_id())
+            .copied()
+            .or(self.show_line_numbers);
+        let cell_type = cell.cell_type();
+        cell.editor(cx).update(cx, |editor, cx| {
+            apply_cell_editor_settings(editor, cell_type, show_line_numbers, cx)
+        });
     }
 
-    fn move_cell_down(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Move cell down triggered");
+    fn toggle_search_outputs(&mut self, cx: &mut ViewContext<Self>) {
+        self.search_outputs = !self.search_outputs;
+        cx.emit(SearchEvent::MatchesInvalidated);
+        cx.notify();
     }
 
-    fn add_markdown_block(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Add markdown block triggered");
+    /// Pages through the notebook's slides full-screen, split by the cells' `slideshow` metadata.
+    fn start_presentation(&mut self, cx: &mut ViewContext<Self>) {
+        let cells = self
+            .cell_order
+            .iter()
+            .filter_map(|cell_id| {
+                let cell = self.cell_map.get(cell_id)?;
+                Some((cell_id.clone(), cell.slide_type(cx)))
+            })
+            .collect::<Vec<_>>();
+        let steps = slide_steps(&cells);
+        if steps.is_empty() {
+            return;
+        }
+
+        // Start at the slide showing the selected cell, like Jupyter does
+        let step = self
+            .selected_cell_id()
+            .and_then(|cell_id| steps.iter().position(|step| step.contains(&cell_id)))
+            .unwrap_or(0);
+        let exit_fullscreen = !cx.is_fullscreen();
+        if exit_fullscreen {
+            cx.toggle_fullscreen();
+        }
+
+        self.presentation = Some(Presentation {
+            steps,
+            step,
+            exit_fullscreen,
+        });
+        cx.focus(&self.focus_handle);
+        cx.notify();
     }
 
-    fn add_code_block(&mut self, cx: &mut ViewContext<Self>) {
-        println!("Add code block triggered");
+    fn stop_presentation(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(presentation) = self.presentation.take() else {
+            return;
+        };
+        if presentation.exit_fullscreen && cx.is_fullscreen() {
+            cx.toggle_fullscreen();
+        }
+
+        // Leave the notebook where the presentation was
+        if let Some(cell_id) = presentation.steps[presentation.step].last() {
+            if let Some(index) = self.cell_order.iter().position(|id| id == cell_id) {
+                self.set_selected_index(index, true, cx);
+            }
+        }
+        cx.notify();
     }
 
-    fn cell_count(&self) -> usize {
-        self.cell_map.len()
+    fn go_to_slide_step(&mut self, forward: bool, cx: &mut ViewContext<Self>) {
+        let Some(presentation) = self.presentation.as_mut() else {
+            return;
+        };
+        presentation.step = if forward {
+            (presentation.step + 1).min(presentation.steps.len() - 1)
+        } else {
+            presentation.step.saturating_sub(1)
+        };
+        cx.notify();
     }
 
-    fn selected_index(&self) -> usize {
-        self.selected_cell_index
+    fn update_read_only(&mut self, cx: &mut ViewContext<Self>) {
+        let read_only = self.is_read_only(cx);
+        if read_only == self.cells_read_only {
+            return;
+        }
+        self.cells_read_only = read_only;
+        for cell in self.cell_map.values() {
+            cell.set_read_only(read_only, cx);
+        }
+        if read_only {
+            self.clear_execution_queue(cx);
+        }
+        cx.notify();
     }
 
-    pub fn set_selected_index(
-        &mut self,
-        index: usize,
-        jump_to_index: bool,
+    fn load_cells(
+        notebook: &nbformat::v4::Notebook,
+        languages: &Arc<LanguageRegistry>,
+        notebook_language: Shared<Task<Option<Arc<Language>>>>,
+        trusted: bool,
         cx: &mut ViewContext<Self>,
+    ) -> (
+        Vec<CellId>,
+        HashMap<CellId, Cell>,
+        HashMap<CellId, Vec<Subscription>>,
     ) {
-        // let previous_index = self.selected_cell_index;
-        self.selected_cell_index = index;
-        let current_index = self.selected_cell_index;
+        let mut cell_order = vec![]; // Vec<CellId>
+        let mut cell_map = HashMap::default(); // HashMap<CellId, Cell>
+        let mut cell_subscriptions = HashMap::default();
 
-        // in the future we may have some `on_cell_change` event that we want to fire here
+        for cell in notebook.cells.iter() {
+            let (cell_id, cell) = Self::load_cell(
+                cell,
+                &cell_map,
+                languages,
+                notebook_language.clone(),
+                trusted,
+                cx,
+            );
+            cell_subscriptions.insert(cell_id.clone(), Self::subscribe_to_cell(&cell, cx));
+            cell_order.push(cell_id.clone());
+            cell_map.insert(cell_id, cell);
+        }
 
-        if jump_to_index {
-            self.jump_to_cell(current_index, cx);
+        (cell_order, cell_map, cell_subscriptions)
+    }
+
+    fn load_cell(
+        cell: &nbformat::v4::Cell,
+        cell_map: &HashMap<CellId, Cell>,
+        languages: &Arc<LanguageRegistry>,
+        notebook_language: Shared<Task<Option<Arc<Language>>>>,
+        trusted: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> (CellId, Cell) {
+        // Ids should be unique, but notebooks merged by hand can repeat them. The repeated
+        // cells get new ids, since cells are tracked by id.
+        let mut cell = cell.clone();
+        if cell_map.contains_key(cell.id()) {
+            set_nbformat_cell_id(&mut cell, new_cell_id());
         }
+        let cell_id = cell.id().clone();
+        let cell = Cell::load(&cell, languages, notebook_language, trusted, cx);
+        (cell_id, cell)
     }
 
-    pub fn select_next(&mut self, _: &menu::SelectNext, cx: &mut ViewContext<Self>) {
-        let count = self.cell_count();
-        if count > 0 {
-            let index = self.selected_index();
-            let ix = if index == count - 1 {
-                count - 1
-            } else {
-                index + 1
-            };
-            self.set_selected_index(ix, true, cx);
-            cx.notify();
+    /// Creates the cells of a notebook that finished loading a batch at a time, letting the
+    /// cells created so far render in between, so notebooks with thousands of cells don't freeze
+    /// the UI while they open.
+    fn load_cells_incrementally(&mut self, cx: &mut ViewContext<Self>) {
+        let notebook_item = self.notebook_item.read(cx);
+        let cells = notebook_item.notebook.cells.clone();
+        // The notebook's language and trust are only known now that it's loaded
+        let notebook_language = notebook_item.notebook_language();
+        self.notebook_language = cx.spawn(|_, _| notebook_language).shared();
+        self.trusted = notebook_item.is_trusted();
+        cx.notify();
+
+        self.cell_loading_task = Some(cx.spawn(|this, mut cx| async move {
+            for batch in cells.chunks(CELL_LOAD_BATCH_SIZE) {
+                this.update(&mut cx, |this, cx| this.append_loaded_cells(batch, cx))?;
+                cx.background_executor().timer(Duration::ZERO).await;
+            }
+            this.update(&mut cx, |this, cx| this.finish_loading_cells(cx))
+        }));
+    }
+
+    fn append_loaded_cells(&mut self, cells: &[nbformat::v4::Cell], cx: &mut ViewContext<Self>) {
+        let start = self.cell_order.len();
+        for cell in cells {
+            let (cell_id, cell) = Self::load_cell(
+                cell,
+                &self.cell_map,
+                &self.languages,
+                self.notebook_language.clone(),
+                self.trusted,
+                cx,
+            );
+            cell.set_read_only(self.cells_read_only, cx);
+            self.style_cell_editor(&cell, cx);
+            self.cell_subscriptions
+                .insert(cell_id.clone(), Self::subscribe_to_cell(&cell, cx));
+            self.saved_cells.push(cell.entity_id());
+            self.cell_order.push(cell_id.clone());
+            self.cell_map.insert(cell_id.clone(), cell);
+            self.update_cell_git_status(&cell_id, cx);
         }
+        self.cell_list
+            .splice(start..start, self.cell_order.len() - start);
+        cx.notify();
     }
 
-    pub fn select_previous(&mut self, _: &menu::SelectPrev, cx: &mut ViewContext<Self>) {
-        let count = self.cell_count();
-        if count > 0 {
-            let index = self.selected_index();
-            let ix = if index == 0 { 0 } else { index - 1 };
-            self.set_selected_index(ix, true, cx);
-            cx.notify();
+    fn finish_loading_cells(&mut self, cx: &mut ViewContext<Self>) {
+        self.cell_loading_task = None;
+        // The document was opened before there were cells, and may need another extension
+        self.cell_document_buffer = None;
+        self.open_cell_document(cx);
+        if let Some((selected_cell_id, scroll_top)) = self.pending_position.take() {
+            self.restore_position(selected_cell_id, scroll_top, cx);
+        }
+        if let Some((cell_index, range)) = self.pending_source_range.take() {
+            self.reveal_source_range(cell_index, range, cx);
         }
+        self.schedule_output_memory_limit(cx);
+        self.run_on_open(cx);
+        cx.notify();
     }
 
-    pub fn select_first(&mut self, _: &menu::SelectFirst, cx: &mut ViewContext<Self>) {
-        let count = self.cell_count();
-        if count > 0 {
-            self.set_selected_index(0, true, cx);
-            cx.notify();
+    /// Whether the notebook is still being read from disk, or its cells are still being created.
+    pub fn is_loading(&self) -> bool {
+        self.awaiting_notebook || self.cell_loading_task.is_some()
+    }
+
+    fn render_loading_indicator(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        if let Some(error) = self.notebook_item.read(cx).load_error() {
+            return Some(
+                h_flex()
+                    .w_full()
+                    .my_2()
+                    .px_3()
+                    .py_2()
+                    .gap_2()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(cx.theme().status().error_border)
+                    .bg(cx.theme().status().error_background)
+                    .child(
+                        Icon::new(IconName::Warning)
+                            .size(IconSize::Small)
+                            .color(Color::Error),
+                    )
+                    .child(
+                        Label::new(format!("Failed to open notebook: {error}"))
+                            .size(LabelSize::Small),
+                    ),
+            );
+        }
+        if !self.is_loading() {
+            return None;
         }
+
+        let label = if self.awaiting_notebook {
+            "Loading notebook…".to_string()
+        } else {
+            let cell_count = self.notebook_item.read(cx).notebook.cells.len();
+            format!("Loading cells… {} of {cell_count}", self.cell_order.len())
+        };
+        Some(
+            h_flex()
+                .w_full()
+                .my_2()
+                .px_3()
+                .py_2()
+                .gap_2()
+                .child(
+                    Icon::new(IconName::ArrowCircle)
+                        .size(IconSize::Small)
+                        .color(Color::Muted),
+                )
+                .child(Label::new(label).size(LabelSize::Small).color(Color::Muted)),
+        )
     }
 
-    pub fn select_last(&mut self, _: &menu::SelectLast, cx: &mut ViewContext<Self>) {
-        let count = self.cell_count();
-        if count > 0 {
-            self.set_selected_index(count - 1, true, cx);
-            cx.notify();
+    /// Builds the notebook as it should be written to disk from the current state of every cell.
+    fn to_nbformat(&self, cx: &AppContext) -> nbformat::v4::Notebook {
+        self.snapshot(cx).into_nbformat()
+    }
+
+    /// Takes what [`Self::to_nbformat`] builds, leaving the outputs to be copied off the UI
+    /// thread.
+    fn snapshot(&self, cx: &AppContext) -> NotebookSnapshot {
+        let notebook = &self.notebook_item.read(cx).notebook;
+        NotebookSnapshot {
+            notebook: nbformat::v4::Notebook {
+                metadata: notebook.metadata.clone(),
+                nbformat: notebook.nbformat,
+                // Cells are always written with ids, which nbformat 4.5 introduced
+                nbformat_minor: notebook.nbformat_minor.max(5),
+                cells: Vec::new(),
+            },
+            cells: self
+                .cell_order
+                .iter()
+                .filter_map(|cell_id| self.cell_map.get(cell_id))
+                .map(|cell| cell.snapshot(cx))
+                .collect(),
         }
     }
 
-    fn jump_to_cell(&mut self, index: usize, _cx: &mut ViewContext<Self>) {
-        self.cell_list.scroll_to_reveal_item(index);
+    /// The notebook as it's written to disk, including the fields nbformat couldn't parse and
+    /// the output payloads that haven't been loaded.
+    fn serialized_notebook(&self, cx: &AppContext) -> Result<serde_json::Value> {
+        let item = self.notebook_item.read(cx);
+        saved_notebook(
+            &self.to_nbformat(cx),
+            &item.unknown_fields,
+            &item.deferred_outputs,
+        )
     }
 
-    fn button_group(cx: &ViewContext<Self>) -> Div {
-        v_flex()
-            .gap(DynamicSpacing::Base04.rems(cx))
-            .items_center()
-            .w(px(CONTROL_SIZE + 4.0))
-            .overflow_hidden()
-            .rounded(px(5.))
-            .bg(cx.theme().colors().title_bar_background)
-            .p_px()
-            .border_1()
-            .border_color(cx.theme().colors().border)
+    fn write_notebook(
+        &mut self,
+        project: Model<Project>,
+        abs_path: PathBuf,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        // Saving before every cell is created would drop the rest
+        if self.is_loading() {
+            return Task::ready(Err(anyhow::anyhow!("The notebook is still loading")));
+        }
+        let snapshot = self.snapshot(cx);
+        let item = self.notebook_item.read(cx);
+        let unknown_fields = item.unknown_fields.clone();
+        let deferred_outputs = item.deferred_outputs.clone();
+        let fs = project.read(cx).fs().clone();
+        let external_outputs = NotebookSettings::get_global(cx).external_outputs;
+        let dedupe_outputs = NotebookSettings::get_global(cx).dedupe_outputs;
+        let save_outputs = NotebookSettings::get_global(cx).save_outputs;
+
+        let is_markdown = is_markdown_notebook_path(&abs_path);
+        let default_language = default_language(&abs_path);
+
+        cx.spawn(|this, mut cx| async move {
+            // Serializing can take a while when outputs are large, so it's kept off the UI thread
+            let (notebook, contents, files) = cx
+                .background_executor()
+                .spawn({
+                    let outputs_dir_name = outputs_dir_name(&abs_path);
+                    async move {
+                        let notebook = snapshot.into_nbformat();
+                        if is_markdown {
+                            let contents = quarto_text(&notebook, default_language);
+                            return anyhow::Ok((notebook, contents, HashMap::default()));
+                        }
+                        let mut value =
+                            saved_notebook(&notebook, &unknown_fields, &deferred_outputs)?;
+                        if !save_outputs {
+                            strip_outputs(&mut value);
+                        }
+                        let files = if external_outputs {
+                            externalize(&mut value, &outputs_dir_name, EXTERNAL_OUTPUT_MIN_SIZE)
+                        } else {
+                            HashMap::default()
+                        };
+                        if dedupe_outputs {
+                            dedupe(&mut value, DUPLICATE_OUTPUT_MIN_SIZE);
+                        }
+                        let contents = serialize_notebook(&value)?;
+                        anyhow::Ok((notebook, contents, files))
+                    }
+                })
+                .await?;
+            if external_outputs && !is_markdown {
+                write_external_outputs(fs.as_ref(), &abs_path, files).await?;
+            }
+            fs.atomic_write(abs_path, contents).await?;
+
+            // The notebook only counts as saved once it's been written
+            this.update(&mut cx, |this, cx| {
+                this.notebook_item
+                    .update(cx, |item, _| item.notebook = notebook);
+                this.saved_cells = this.cell_entity_ids();
+                this.metadata_modified = false;
+                // Outputs of a trusted notebook stay trusted once they're saved
+                if this.trusted {
+                    this.remember_trust(cx);
+                }
+                for cell in this.cell_map.values() {
+                    match cell {
+                        Cell::Code(code_cell) => code_cell.update(cx, |cell, cx| cell.did_save(cx)),
+                        Cell::Markdown(markdown_cell) => {
+                            markdown_cell.update(cx, |cell, cx| cell.did_save(cx))
+                        }
+                        Cell::Raw(raw_cell) => raw_cell.update(cx, |cell, cx| cell.did_save(cx)),
+                    }
+                }
+                cx.notify();
+            })
+        })
     }
 
-    fn render_notebook_control(
-        id: impl Into<SharedString>,
-        icon: IconName,
-        _cx: &ViewContext<Self>,
-    ) -> IconButton {
-        let id: ElementId = ElementId::Name(id.into());
-        IconButton::new(id, icon).width(px(CONTROL_SIZE).into())
+    fn cell_source_edited(&mut self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        self.update_cell_git_status(cell_id, cx);
+        cx.emit(SearchEvent::MatchesInvalidated);
     }
 
-    fn render_notebook_controls(&self, cx: &ViewContext<Self>) -> impl IntoElement {
-        let has_outputs = self.has_outputs(cx);
+    fn subscribe_to_cell(cell: &Cell, cx: &mut ViewContext<Self>) -> Vec<Subscription> {
+        // Comments and tags change whether the notebook has unsaved changes
+        let comments_subscription = cx.observe(&cell.comments(cx), |_, _, cx| cx.notify());
+        let tags_subscription = cx.observe(&cell.tags(cx), |_, _, cx| cx.notify());
+        let mut subscriptions = match cell {
+            Cell::Code(code_cell) => {
+                let provider = CellCompletionProvider::new(
+                    cx.view().downgrade(),
+                    code_cell.read(cx).id().clone(),
+                );
+                let semantics_provider = CellSemanticsProvider::new(
+                    cx.view().downgrade(),
+                    code_cell.read(cx).id().clone(),
+                    cx.window_handle(),
+                );
+                code_cell.read(cx).editor().clone().update(cx, |editor, _| {
+                    editor.set_completion_provider(Some(Box::new(provider)));
+                    editor.set_semantics_provider(Some(Rc::new(semantics_provider)));
+                });
 
-        v_flex()
-            .max_w(px(CONTROL_SIZE + 4.0))
-            .items_center()
-            .gap(DynamicSpacing::Base16.rems(cx))
-            .justify_between()
-            .flex_none()
-            .h_full()
-            .py(DynamicSpacing::Base12.px(cx))
-            .child(
-                v_flex()
-                    .gap(DynamicSpacing::Base08.rems(cx))
-                    .child(
-                        Self::button_group(cx)
-                            .child(
-                                Self::render_notebook_control("run-all-cells", IconName::Play, cx)
-                                    .tooltip(move |cx| {
-                                        Tooltip::for_action("Execute all cells", &RunAll, cx)
-                                    })
-                                    .on_click(|_, cx| {
-                                        cx.dispatch_action(Box::new(RunAll));
-                                    }),
-                            )
-                            .child(
-                                Self::render_notebook_control(
-                                    "clear-all-outputs",
-                                    IconName::ListX,
-                                    cx,
-                                )
-                                .disabled(!has_outputs)
-                                .tooltip(move |cx| {
-                                    Tooltip::for_action("Clear all outputs", &ClearOutputs, cx)
-                                })
-                                .on_click(|_, cx| {
-                                    cx.dispatch_action(Box::new(ClearOutputs));
-                                }),
-                            ),
-                    )
-                    .child(
-                        Self::button_group(cx)
-                            .child(
-                                Self::render_notebook_control(
-                                    "move-cell-up",
-                                    IconName::ArrowUp,
-                                    cx,
-                                )
-                                .tooltip(move |cx| {
-                                    Tooltip::for_action("Move cell up", &MoveCellUp, cx)
-                                })
-                                .on_click(|_, cx| {
-                                    cx.dispatch_action(Box::new(MoveCellUp));
-                                }),
-                            )
-                            .child(
-                                Self::render_notebook_control(
-                                    "move-cell-down",
-                                    IconName::ArrowDown,
-                                    cx,
-                                )
-                                .tooltip(move |cx| {
-                                    Tooltip::for_action("Move cell down", &MoveCellDown, cx)
-                                })
-                                .on_click(|_, cx| {
-                                    cx.dispatch_action(Box::new(MoveCellDown));
-                                }),
-                            ),
-                    )
-                    .child(
-                        Self::button_group(cx)
-                            .child(
-                                Self::render_notebook_control(
-                                    "new-markdown-cell",
-                                    IconName::Plus,
-                                    cx,
-                                )
-                                .tooltip(move |cx| {
-                                    Tooltip::for_action("Add markdown block", &AddMarkdownBlock, cx)
-                                })
-                                .on_click(|_, cx| {
-                                    cx.dispatch_action(Box::new(AddMarkdownBlock));
-                                }),
-                            )
-                            .child(
-                                Self::render_notebook_control("new-code-cell", IconName::Code, cx)
-                                    .tooltip(move |cx| {
-                                        Tooltip::for_action("Add code block", &AddCodeBlock, cx)
-                                    })
-                                    .on_click(|_, cx| {
-                                        cx.dispatch_action(Box::new(AddCodeBlock));
-                                    }),
-                            ),
-                    ),
-            )
-            .child(
-                v_flex()
-                    .gap(DynamicSpacing::Base08.rems(cx))
-                    .items_center()
-                    .child(Self::render_notebook_control(
-                        "more-menu",
-                        IconName::Ellipsis,
-                        cx,
-                    ))
-                    .child(
-                        Self::button_group(cx)
-                            .child(IconButton::new("repl", IconName::ReplNeutral)),
-                    ),
-            )
+                let mut subscriptions =
+                    vec![cx.subscribe(code_cell, |this, _, event, cx| match event {
+                        CellEvent::Run(cell_id) => this.execute_cell(cell_id.clone(), cx),
+                        CellEvent::CancelExecution(cell_id) => {
+                            this.cancel_queued_execution(cell_id, cx)
+                        }
+                        CellEvent::SubmitInput(reply) => this.send(reply.clone(), cx),
+                        CellEvent::EditorFocused(cell_id) => this.select_cell(cell_id, cx),
+                        CellEvent::SourceEdited(cell_id) => this.cell_source_edited(cell_id, cx),
+                        CellEvent::AskAssistant(cell_id) => this.ask_assistant(cell_id, cx),
+                        CellEvent::CopyAsMarkdown {
+                            cell_id,
+                            include_output,
+                        } => this.copy_cell_as_markdown(cell_id, *include_output, cx),
+                        CellEvent::RevertToHead {
+                            cell_id,
+                            include_outputs,
+                        } => this.revert_cell_to_head(cell_id, *include_outputs, cx),
+                        CellEvent::ExecutionChanged(cell_id) => {
+                            this.schedule_output_memory_limit(cx);
+                            if !this.diff_ignores_outputs {
+                                this.update_cell_git_status(cell_id, cx);
+                            }
+                            if this.search_outputs {
+                                cx.emit(SearchEvent::MatchesInvalidated);
+                            }
+                            cx.emit(NotebookEvent::CellExecutionChanged(cell_id.clone()))
+                        }
+                        CellEvent::InstallPackage {
+                            cell_id,
+                            package,
+                            manager,
+                        } => this.install_package(cell_id.clone(), package.clone(), *manager, cx),
+                        CellEvent::LoadOutput {
+                            cell_id,
+                            output_index,
+                        } => this.load_deferred_output(cell_id, *output_index, cx),
+                    })];
+                let buffer = code_cell.read(cx).buffer().clone();
+                subscriptions.push(cx.subscribe(&buffer, |this, _, event, cx| {
+                    if let BufferEvent::Edited = event {
+                        this.schedule_cell_document_sync(cx);
+                    }
+                }));
+                subscriptions
+            }
+            Cell::Markdown(markdown_cell) => {
+                vec![
+                    cx.subscribe(markdown_cell, |this, _, event, cx| match event {
+                        CellEvent::EditorFocused(cell_id) => this.select_cell(cell_id, cx),
+                        CellEvent::SourceEdited(cell_id) => this.cell_source_edited(cell_id, cx),
+                        CellEvent::RevertToHead {
+                            cell_id,
+                            include_outputs,
+                        } => this.revert_cell_to_head(cell_id, *include_outputs, cx),
+                        _ => {}
+                    }),
+                ]
+            }
+            Cell::Raw(raw_cell) => {
+                vec![cx.subscribe(raw_cell, |this, _, event, cx| match event {
+                    CellEvent::EditorFocused(cell_id) => this.select_cell(cell_id, cx),
+                    CellEvent::SourceEdited(cell_id) => this.cell_source_edited(cell_id, cx),
+                    CellEvent::RevertToHead {
+                        cell_id,
+                        include_outputs,
+                    } => this.revert_cell_to_head(cell_id, *include_outputs, cx),
+                    _ => {}
+                })]
+            }
+        };
+        subscriptions.push(comments_subscription);
+        subscriptions.push(tags_subscription);
+        subscriptions
     }
 
-    fn cell_position(&self, index: usize) -> CellPosition {
-        match index {
-            0 => CellPosition::First,
-            index if index == self.cell_count() - 1 => CellPosition::Last,
-            _ => CellPosition::Middle,
+    /// Reads the notebook as it's committed at HEAD, to mark the cells changed since in their
+    /// gutters. Notebooks outside a repository or that aren't committed yet have no markers.
+    fn refresh_committed_cells(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(project_path) = self.notebook_item.read(cx).project_path.clone() else {
+            return;
+        };
+        let Some(snapshot) = self
+            .project
+            .read(cx)
+            .worktree_for_id(project_path.worktree_id, cx)
+            .and_then(|worktree| Some(worktree.read(cx).as_local()?.snapshot()))
+        else {
+            return;
+        };
+        let path = project_path.path;
+        let is_markdown = is_markdown_notebook_path(&path);
+        let default_language = default_language(&path);
+
+        self.committed_cells_task = Some(cx.spawn(|this, mut cx| async move {
+            let committed_cells = cx
+                .background_executor()
+                .spawn(async move {
+                    let (repo_entry, local_repo_entry) = snapshot.repo_for_path(&path)?;
+                    let relative_path = repo_entry.relativize(&snapshot, &path).ok()?;
+                    let text = local_repo_entry
+                        .repo()
+                        .load_committed_text(&relative_path)?;
+                    let notebook = if is_markdown {
+                        parse_quarto(&text, default_language)
+                    } else {
+                        NotebookItem::parse_notebook(&text)
+                    };
+                    Some(CommittedCells::new(&notebook.log_err()?))
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.committed_cells = committed_cells;
+                for cell_id in this.cell_order.clone() {
+                    this.update_cell_git_status(&cell_id, cx);
+                }
+            })
+            .ok();
+        }));
+    }
+
+    /// Marks the cell as added or modified since HEAD, comparing it with the committed cells.
+    fn update_cell_git_status(&self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self.cell_map.get(cell_id) else {
+            return;
+        };
+        let status = self.committed_cells.as_ref().and_then(|committed_cells| {
+            let (cell_type, source) = cell.current_source(cx);
+            let outputs = if self.diff_ignores_outputs {
+                None
+            } else {
+                cell.git_outputs(cx)
+            };
+            committed_cells.status(cell_id, cell_type, &source, outputs.as_ref())
+        });
+        cell.set_git_status(status, cx);
+
+        let tracked = self.committed_cells.is_some()
+            && self
+                .notebook_item
+                .read(cx)
+                .project_path
+                .as_ref()
+                .map_or(false, |project_path| {
+                    !is_markdown_notebook_path(&project_path.path)
+                });
+        if cell.has_blame_loader(cx) != tracked {
+            cell.set_blame_loader(tracked.then(|| self.blame_loader(cx)), cx);
         }
     }
 
-    fn render_cell(
+    fn blame_loader(&self, cx: &ViewContext<Self>) -> BlameLoader {
+        let this = cx.view().downgrade();
+        Rc::new(move |cell_id, cx| {
+            this.update(cx, |this, cx| this.cell_blame(cell_id, cx))
+                .unwrap_or_else(|error| Task::ready(Err(error)))
+        })
+    }
+
+    /// Blames the notebook as it would be saved, and keeps the commits that last touched the lines
+    /// holding the cell's source. Unsaved edits are blamed on no commit.
+    fn cell_blame(
         &self,
-        index: usize,
-        cell: &Cell,
+        cell_id: &CellId,
         cx: &mut ViewContext<Self>,
-    ) -> impl IntoElement {
-        let cell_position = self.cell_position(index);
+    ) -> Task<Result<Vec<CellCommit>>> {
+        let Some(project_path) = self.notebook_item.read(cx).project_path.clone() else {
+            return Task::ready(Err(anyhow::anyhow!(
+                "The notebook isn't saved in the project"
+            )));
+        };
+        let Some(snapshot) = self
+            .project
+            .read(cx)
+            .worktree_for_id(project_path.worktree_id, cx)
+            .and_then(|worktree| Some(worktree.read(cx).as_local()?.snapshot()))
+        else {
+            return Task::ready(Err(anyhow::anyhow!(
+                "Blame is only available for local notebooks"
+            )));
+        };
+        let Some(cell_index) = self.cell_order.iter().position(|id| id == cell_id) else {
+            return Task::ready(Err(anyhow::anyhow!("The cell was deleted")));
+        };
+        let json = match self
+            .serialized_notebook(cx)
+            .and_then(|notebook| serialize_notebook(&notebook))
+        {
+            Ok(json) => json,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let path = project_path.path;
 
-        let is_selected = index == self.selected_cell_index;
+        cx.background_executor().spawn(async move {
+            let (repo_entry, local_repo_entry) = snapshot
+                .repo_for_path(&path)
+                .context("The notebook isn't in a git repository")?;
+            let relative_path = repo_entry.relativize(&snapshot, &path)?;
+            let source = notebook_sources(&json)
+                .and_then(|sources| {
+                    sources
+                        .into_iter()
+                        .find(|source| source.cell_index == cell_index)
+                })
+                .context("The cell's source wasn't found in the notebook")?;
+            if source.text.is_empty() {
+                return Ok(Vec::new());
+            }
+            let lines = json_lines(&json, source.json_range(0..source.text.len()));
+            let blame = local_repo_entry
+                .repo()
+                .blame(&relative_path, Rope::from(json.as_str()))?;
+            Ok(cell_commits(&blame, lines))
+        })
+    }
 
-        match cell {
-            Cell::Code(cell) => {
-                cell.update(cx, |cell, _cx| {
-                    cell.set_selected(is_selected)
-                        .set_cell_position(cell_position);
+    /// Puts the committed version of the cell in its place, keeping its metadata. Its outputs are
+    /// only reverted when `include_outputs` is set. Like converting a cell, it can be undone.
+    fn revert_cell_to_head(
+        &mut self,
+        cell_id: &CellId,
+        include_outputs: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(cell) = self.cell_map.get(cell_id) else {
+            return;
+        };
+        if self.is_read_only(cx) || !cell.is_editable(cx) {
+            return;
+        }
+        let Some(committed) = self
+            .committed_cells
+            .as_ref()
+            .and_then(|committed_cells| committed_cells.cell(cell_id))
+        else {
+            return;
+        };
+
+        let nbformat_cell = reverted_cell(&cell.to_nbformat_cell(cx), committed, include_outputs);
+        let reverted = Cell::load(
+            &nbformat_cell,
+            &self.languages,
+            self.notebook_language.clone(),
+            self.trusted,
+            cx,
+        );
+        self.style_cell_editor(&reverted, cx);
+
+        if let Some(previous) = self.replace_cell(cell_id, reverted, cx) {
+            self.push_cell_operation(CellOperation::Converted {
+                cell_id: cell_id.clone(),
+                cell: previous,
+            });
+        }
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    /// Opens the document language servers see the notebook's code cells as. It's named after
+    /// the notebook, with the extension of the notebook's language so language servers pick it up.
+    fn open_cell_document(&mut self, cx: &mut ViewContext<Self>) {
+        let project = self.project.clone();
+        // Untitled notebooks get a document once they're saved
+        let Some(notebook_path) = self.notebook_item.read(cx).project_path.clone() else {
+            return;
+        };
+        let notebook_language = self.notebook_language.clone();
+
+        cx.spawn(|this, mut cx| async move {
+            let Some(language) = notebook_language.await else {
+                return Ok(());
+            };
+            let Some(extension) = language.path_suffixes().first() else {
+                return Ok(());
+            };
+            let path = ProjectPath {
+                worktree_id: notebook_path.worktree_id,
+                path: cell_document_path(&notebook_path.path, extension).into(),
+            };
+
+            let buffer = project
+                .update(&mut cx, |project, cx| project.open_buffer(path, cx))?
+                .await?;
+            // It's never saved, so it's kept out of project search and the open buffers
+            project.update(&mut cx, |project, cx| project.hide_buffer(&buffer, cx))?;
+
+            this.update(&mut cx, |this, cx| {
+                let subscription = cx.subscribe(&buffer, |this, _, event, cx| {
+                    if let BufferEvent::DiagnosticsUpdated = event {
+                        this.update_cell_diagnostics(cx);
+                    }
                 });
-                cell.clone().into_any_element()
+                this.cell_document_buffer = Some((buffer, subscription));
+                this.cell_document_masks_magics = language.name().0.as_ref() == "Python";
+                this.sync_cell_document(cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn schedule_cell_document_sync(&mut self, cx: &mut ViewContext<Self>) {
+        if self.cell_document_buffer.is_none() {
+            return;
+        }
+
+        self.cell_document_sync_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(CELL_DOCUMENT_SYNC_DEBOUNCE)
+                .await;
+            this.update(&mut cx, |this, cx| this.sync_cell_document(cx))
+                .ok();
+        }));
+    }
+
+    /// Rebuilds the document from the code cells, in their current order.
+    fn sync_cell_document(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(buffer) = self
+            .cell_document_buffer
+            .as_ref()
+            .map(|(buffer, _)| buffer.clone())
+        else {
+            return;
+        };
+
+        let document = self.build_cell_document(cx);
+        if buffer.read(cx).text() != document.text() {
+            buffer.update(cx, |buffer, cx| buffer.set_text(document.text(), cx));
+        }
+        self.cell_document = document;
+        self.update_cell_diagnostics(cx);
+    }
+
+    fn build_cell_document(&self, cx: &AppContext) -> CellDocument {
+        let sources = self
+            .cell_order
+            .iter()
+            .filter_map(|cell_id| match self.cell_map.get(cell_id)? {
+                Cell::Code(cell) => Some((cell_id.clone(), cell.read(cx).current_source(cx))),
+                Cell::Markdown(_) | Cell::Raw(_) => None,
+            })
+            .collect::<Vec<_>>();
+        CellDocument::new(
+            sources
+                .iter()
+                .map(|(cell_id, source)| (cell_id.clone(), source.as_str())),
+            self.cell_document_masks_magics,
+        )
+    }
+
+    /// Brings the cell document up to date, and finds the position in it of a position in a cell.
+    /// Cell editors ask for language server features without access to the notebook's view
+    /// context, so this can't go through [`Self::sync_cell_document`].
+    fn document_position(
+        notebook: &View<Self>,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<(Model<Project>, Model<Buffer>, PointUtf16)> {
+        let (project, document_buffer, document) = {
+            let notebook = notebook.read(cx);
+            let (document_buffer, _) = notebook.cell_document_buffer.as_ref()?;
+            (
+                notebook.project.clone(),
+                document_buffer.clone(),
+                notebook.build_cell_document(cx),
+            )
+        };
+
+        if document_buffer.read(cx).text() != document.text() {
+            document_buffer.update(cx, |buffer, cx| buffer.set_text(document.text(), cx));
+        }
+
+        let cell_range = document
+            .cells()
+            .iter()
+            .find(|cell_range| &cell_range.cell_id == cell_id)?;
+        let point = position.to_point_utf16(&buffer.read(cx).snapshot());
+        let document_point = PointUtf16::new(cell_range.start_row + point.row, point.column);
+        Some((project, document_buffer, document_point))
+    }
+
+    /// Asks the language server for signature help at a position in a code cell.
+    pub(crate) fn cell_signature_help(
+        notebook: &View<Self>,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<Task<Vec<SignatureHelp>>> {
+        let (project, document_buffer, point) =
+            Self::document_position(notebook, cell_id, buffer, position, cx)?;
+        Some(project.update(cx, |project, cx| {
+            project.signature_help(&document_buffer, point, cx)
+        }))
+    }
+
+    /// Goes to the definition of the symbol at a position in a code cell. The notebook navigates
+    /// itself, since cell editors can't open other cells, so the editor is always given an empty
+    /// list of definitions.
+    pub(crate) fn cell_definitions(
+        notebook: &View<Self>,
+        window: AnyWindowHandle,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        kind: GotoDefinitionKind,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Vec<LocationLink>>>> {
+        let (project, document_buffer, point) =
+            Self::document_position(notebook, cell_id, buffer, position, cx)?;
+        let definitions = project.update(cx, |project, cx| match kind {
+            GotoDefinitionKind::Symbol => project.definition(&document_buffer, point, cx),
+            GotoDefinitionKind::Declaration => project.declaration(&document_buffer, point, cx),
+            GotoDefinitionKind::Type => project.type_definition(&document_buffer, point, cx),
+            GotoDefinitionKind::Implementation => {
+                project.implementation(&document_buffer, point, cx)
             }
-            Cell::Markdown(cell) => {
-                cell.update(cx, |cell, _cx| {
-                    cell.set_selected(is_selected)
-                        .set_cell_position(cell_position);
-                });
-                cell.clone().into_any_element()
+        });
+
+        let notebook = notebook.downgrade();
+        Some(cx.spawn(|mut cx| async move {
+            let definitions = definitions.await?;
+            cx.update_window(window, |_, cx| {
+                notebook.update(cx, |notebook, cx| {
+                    notebook.go_to_definition(definitions, &document_buffer, cx)
+                })
+            })??;
+            Ok(Vec::new())
+        }))
+    }
+
+    /// Goes to a definition in a cell if there is one, or else opens the file of the first one.
+    fn go_to_definition(
+        &mut self,
+        definitions: Vec<LocationLink>,
+        document_buffer: &Model<Buffer>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // Rows of the document only line up with cells when the document is current
+        self.sync_cell_document(cx);
+
+        let document = document_buffer.read(cx).snapshot();
+        let cell_target = definitions
+            .iter()
+            .filter(|link| link.target.buffer == *document_buffer)
+            .find_map(|link| {
+                let range = link.target.range.start.to_point_utf16(&document)
+                    ..link.target.range.end.to_point_utf16(&document);
+                let (cell_id, range) = self.cell_document.cell_range(range)?;
+                Some((cell_id.clone(), range))
+            });
+        if let Some((cell_id, range)) = cell_target {
+            self.select_in_cell(&cell_id, range, cx);
+            return;
+        }
+
+        let Some(link) = definitions
+            .into_iter()
+            .find(|link| link.target.buffer != *document_buffer)
+        else {
+            return;
+        };
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+
+        let editor = workspace.update(cx, |workspace, cx| {
+            let pane = workspace.active_pane().clone();
+            workspace.open_project_item::<Editor>(pane, link.target.buffer.clone(), true, true, cx)
+        });
+        editor.update(cx, |editor, cx| {
+            let snapshot = link.target.buffer.read(cx).snapshot();
+            let range = link.target.range.start.to_offset(&snapshot)
+                ..link.target.range.end.to_offset(&snapshot);
+            editor.change_selections(Some(Autoscroll::focused()), cx, |selections| {
+                selections.select_ranges([range]);
+            });
+        });
+    }
+
+    /// Selects part of a code cell's source and focuses its editor.
+    fn select_in_cell(
+        &mut self,
+        cell_id: &CellId,
+        range: Range<PointUtf16>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(index) = self.cell_order.iter().position(|id| id == cell_id) else {
+            return;
+        };
+        let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) else {
+            return;
+        };
+
+        let editor = cell.read(cx).editor().clone();
+        let snapshot = cell.read(cx).buffer().read(cx).snapshot();
+        let start = snapshot
+            .point_utf16_to_offset(snapshot.clip_point_utf16(Unclipped(range.start), Bias::Left));
+        let end = snapshot
+            .point_utf16_to_offset(snapshot.clip_point_utf16(Unclipped(range.end), Bias::Left));
+
+        self.set_selected_index(index, true, cx);
+        editor.update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |selections| {
+                selections.select_ranges([start..end]);
+            });
+        });
+        cx.focus_view(&editor);
+        cx.notify();
+    }
+
+    /// Shows the diagnostics reported for the document in the editors of the cells they fall in.
+    fn update_cell_diagnostics(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((buffer, _)) = &self.cell_document_buffer else {
+            return;
+        };
+        let snapshot = buffer.read(cx).snapshot();
+        // Rows in a document that's out of date don't line up with the cells anymore
+        if snapshot.text() != self.cell_document.text() {
+            return;
+        }
+
+        let mut server_ids = Vec::new();
+        let mut cell_diagnostics = HashMap::<CellId, Vec<_>>::default();
+        for (server_id, group) in snapshot.diagnostic_groups(None) {
+            if !server_ids.contains(&server_id) {
+                server_ids.push(server_id);
             }
-            Cell::Raw(cell) => {
-                cell.update(cx, |cell, _cx| {
-                    cell.set_selected(is_selected)
-                        .set_cell_position(cell_position);
-                });
-                cell.clone().into_any_element()
+            for entry in group.entries {
+                let entry = entry.resolve::<PointUtf16>(&snapshot);
+                if let Some((cell_id, range)) = self.cell_document.cell_range(entry.range) {
+                    cell_diagnostics.entry(cell_id.clone()).or_default().push((
+                        server_id,
+                        range,
+                        entry.diagnostic,
+                    ));
+                }
+            }
+        }
+
+        // Servers that no longer report anything still need their diagnostics cleared
+        let previous_server_ids = std::mem::replace(&mut self.cell_diagnostic_servers, server_ids);
+        let mut all_server_ids = self.cell_diagnostic_servers.clone();
+        for server_id in previous_server_ids {
+            if !all_server_ids.contains(&server_id) {
+                all_server_ids.push(server_id);
             }
         }
+
+        for cell_range in self.cell_document.cells() {
+            let Some(Cell::Code(cell)) = self.cell_map.get(&cell_range.cell_id) else {
+                continue;
+            };
+            let cell_buffer = cell.read(cx).buffer().clone();
+            let diagnostics = cell_diagnostics
+                .remove(&cell_range.cell_id)
+                .unwrap_or_default();
+
+            cell_buffer.update(cx, |cell_buffer, cx| {
+                let cell_snapshot = cell_buffer.snapshot();
+                for server_id in &all_server_ids {
+                    let entries = diagnostics.iter().filter(|(id, _, _)| id == server_id).map(
+                        |(_, range, diagnostic)| DiagnosticEntry {
+                            range: cell_snapshot
+                                .clip_point_utf16(Unclipped(range.start), Bias::Left)
+                                ..cell_snapshot.clip_point_utf16(Unclipped(range.end), Bias::Left),
+                            diagnostic: diagnostic.clone(),
+                        },
+                    );
+                    let set = DiagnosticSet::new(entries, &cell_snapshot);
+                    cell_buffer.update_diagnostics(*server_id, set, cx);
+                }
+            });
+        }
     }
-}
 
-impl Render for NotebookEditor {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        div()
-            .key_context("notebook")
-            .track_focus(&self.focus_handle)
-            .on_action(cx.listener(|this, &OpenNotebook, cx| this.open_notebook(&OpenNotebook, cx)))
-            .on_action(cx.listener(|this, &ClearOutputs, cx| this.clear_outputs(cx)))
-            .on_action(cx.listener(|this, &RunAll, cx| this.run_cells(cx)))
-            .on_action(cx.listener(|this, &MoveCellUp, cx| this.move_cell_up(cx)))
-            .on_action(cx.listener(|this, &MoveCellDown, cx| this.move_cell_down(cx)))
-            .on_action(cx.listener(|this, &AddMarkdownBlock, cx| this.add_markdown_block(cx)))
-            .on_action(cx.listener(|this, &AddCodeBlock, cx| this.add_code_block(cx)))
-            .on_action(cx.listener(Self::select_next))
-            .on_action(cx.listener(Self::select_previous))
-            .on_action(cx.listener(Self::select_first))
-            .on_action(cx.listener(Self::select_last))
-            .flex()
-            .items_start()
-            .size_full()
-            .overflow_hidden()
-            .px(DynamicSpacing::Base12.px(cx))
-            .gap(DynamicSpacing::Base12.px(cx))
-            .bg(cx.theme().colors().tab_bar_background)
-            .child(
-                v_flex()
-                    .id("notebook-cells")
-                    .flex_1()
-                    .size_full()
-                    .overflow_y_scroll()
-                    .child(list(self.cell_list.clone()).size_full()),
-            )
-            .child(self.render_notebook_controls(cx))
+    /// Completions for a position in a code cell, from both the kernel and the language server,
+    /// along with common magics in Python notebooks. Completions offered more than once are only
+    /// listed once.
+    pub(crate) fn cell_completions(
+        &mut self,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        context: editor::CompletionContext,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let magic_completions = if is_python(buffer, cx) {
+            magic_completions(&buffer.read(cx).snapshot(), position)
+        } else {
+            Vec::new()
+        };
+        let kernel_completions = self.kernel_completions(buffer, position, cx);
+        let document_completions =
+            self.document_completions(cell_id, buffer, position, context, cx);
+
+        cx.background_executor().spawn(async move {
+            let mut completions = document_completions.await.log_err().unwrap_or_default();
+            let kernel_completions = kernel_completions.await.log_err().unwrap_or_default();
+            for completion in kernel_completions.into_iter().chain(magic_completions) {
+                if !completions
+                    .iter()
+                    .any(|existing| existing.new_text == completion.new_text)
+                {
+                    completions.push(completion);
+                }
+            }
+            Ok(completions)
+        })
     }
-}
 
-impl FocusableView for NotebookEditor {
-    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
-        self.focus_handle.clone()
+    fn kernel_completions(
+        &mut self,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<Vec<Completion>>> {
+        // Kernels handle one request at a time, so they can't reply while a cell is running
+        if !matches!(self.kernel, Kernel::RunningKernel(_)) || !self.pending_executions.is_empty() {
+            return Task::ready(Ok(Vec::new()));
+        }
+
+        let snapshot = buffer.read(cx).snapshot();
+        let code = snapshot.text();
+        let cursor_pos = code_point_offset(&code, position.to_offset(&snapshot));
+        let message: JupyterMessage = CompleteRequest { code, cursor_pos }.into();
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending_completions
+            .insert(message.header.msg_id.clone(), reply_tx);
+        self.send(message, cx);
+
+        cx.background_executor().spawn(async move {
+            let reply = reply_rx.await?;
+            Ok(kernel_completions(&reply, &snapshot))
+        })
     }
-}
 
-// Intended to be a NotebookBuffer
-pub struct NotebookItem {
-    path: PathBuf,
-    project_path: ProjectPath,
-    languages: Arc<LanguageRegistry>,
-    // Raw notebook data
-    notebook: nbformat::v4::Notebook,
-    // Store our version of the notebook in memory (cell_order, cell_map)
-    id: ProjectEntryId,
-}
+    /// Asks the language server for completions at the same position in the cell document.
+    fn document_completions(
+        &mut self,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        context: editor::CompletionContext,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<Vec<Completion>>> {
+        // The document has to include what was just typed
+        self.cell_document_sync_task = None;
+        self.sync_cell_document(cx);
 
-impl project::Item for NotebookItem {
-    fn try_open(
-        project: &Model<Project>,
-        path: &ProjectPath,
-        cx: &mut AppContext,
-    ) -> Option<Task<gpui::Result<Model<Self>>>> {
-        let path = path.clone();
-        let project = project.clone();
-        let fs = project.read(cx).fs().clone();
-        let languages = project.read(cx).languages().clone();
+        let Some((document_buffer, _)) = &self.cell_document_buffer else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        let Some(cell_range) = self
+            .cell_document
+            .cells()
+            .iter()
+            .find(|cell_range| &cell_range.cell_id == cell_id)
+        else {
+            return Task::ready(Ok(Vec::new()));
+        };
+
+        let point = position.to_point_utf16(&buffer.read(cx).snapshot());
+        let document_point = PointUtf16::new(cell_range.start_row + point.row, point.column);
+        let completions = self.project.update(cx, |project, cx| {
+            project.completions(document_buffer, document_point, context, cx)
+        });
+
+        let cell_id = cell_id.clone();
+        let buffer = buffer.clone();
+        cx.spawn(|this, mut cx| async move {
+            let completions = completions.await?;
+            this.update(&mut cx, |this, cx| {
+                this.map_document_completions(completions, &cell_id, &buffer, cx)
+            })
+        })
+    }
+
+    /// Moves the ranges language server completions replace from the cell document to the cell.
+    fn map_document_completions(
+        &self,
+        completions: Vec<Completion>,
+        cell_id: &CellId,
+        buffer: &Model<Buffer>,
+        cx: &AppContext,
+    ) -> Vec<Completion> {
+        let Some((document_buffer, _)) = &self.cell_document_buffer else {
+            return Vec::new();
+        };
+        let document = document_buffer.read(cx).snapshot();
+        let cell = buffer.read(cx).snapshot();
+
+        completions
+            .into_iter()
+            .filter_map(|mut completion| {
+                let range = completion.old_range.start.to_point_utf16(&document)
+                    ..completion.old_range.end.to_point_utf16(&document);
+                let (range_cell_id, range) = self.cell_document.cell_range(range)?;
+                if range_cell_id != cell_id {
+                    return None;
+                }
+
+                let start = cell.clip_point_utf16(Unclipped(range.start), Bias::Left);
+                let end = cell.clip_point_utf16(Unclipped(range.end), Bias::Left);
+                completion.old_range = cell.anchor_before(start)..cell.anchor_after(end);
+                Some(completion)
+            })
+            .collect()
+    }
+
+    pub(crate) fn resolve_cell_completions(
+        &mut self,
+        completion_indices: Vec<usize>,
+        completions: Arc<RwLock<Box<[Completion]>>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<bool>> {
+        let Some((document_buffer, _)) = &self.cell_document_buffer else {
+            return Task::ready(Ok(false));
+        };
+        // Kernel completions are skipped, since no language server has their id
+        self.project.update(cx, |project, cx| {
+            project.resolve_completions(
+                document_buffer.clone(),
+                completion_indices,
+                completions,
+                cx,
+            )
+        })
+    }
+
+    pub(crate) fn is_document_completion_trigger(&self, text: &str, cx: &AppContext) -> bool {
+        self.cell_document_buffer
+            .as_ref()
+            .map_or(false, |(buffer, _)| {
+                buffer.read(cx).completion_triggers().contains(text)
+            })
+    }
+
+    /// Picks the kernel the notebook was saved with, falling back to the default kernel for its
+    /// language from `notebook.default_kernels` or `jupyter.kernel_selections`, and then to any
+    /// kernel for its language.
+    pub(crate) fn kernel_specification(&self, cx: &AppContext) -> Option<KernelSpecification> {
+        let notebook_item = self.notebook_item.read(cx);
+        let kernelspec_name = notebook_item
+            .notebook
+            .metadata
+            .kernelspec
+            .as_ref()
+            .map(|kernelspec| kernelspec.name.to_lowercase());
+        let language_name = notebook_item
+            .language_name()
+            .map(|language_name| language_name.to_lowercase());
+
+        let default_kernel_name = language_name.as_deref().and_then(|language_name| {
+            NotebookSettings::get_global(cx)
+                .default_kernel(language_name)
+                .or_else(|| JupyterSettings::get_global(cx).kernel_selection(language_name))
+                .map(str::to_lowercase)
+        });
+
+        let kernel_specifications =
+            notebook_kernel_specifications(&self.project, self.worktree_id(cx), cx);
+
+        kernel_specifications
+            .iter()
+            .find(|spec| Some(spec.name().to_lowercase()) == kernelspec_name)
+            .or_else(|| {
+                kernel_specifications
+                    .iter()
+                    .find(|spec| Some(spec.name().to_lowercase()) == default_kernel_name)
+            })
+            .or_else(|| {
+                kernel_specifications
+                    .iter()
+                    .find(|spec| Some(spec.language().to_lowercase()) == language_name)
+            })
+            .cloned()
+    }
+
+    fn start_kernel(&mut self, cx: &mut ViewContext<Self>) {
+        if self.is_remote() {
+            return;
+        }
+
+        // The kernels of an SSH project are listed on its host, where listing Python environments
+        // runs each of their interpreters, so they're only listed once a kernel is needed
+        let refresh_kernel_specifications = self
+            .worktree_id(cx)
+            .filter(|_| self.project.read(cx).is_via_ssh())
+            .map(|worktree_id| {
+                let project = self.project.clone();
+                ReplStore::global(cx).update(cx, |store, cx| {
+                    store.refresh_ssh_kernelspecs(worktree_id, &project, cx)
+                })
+            });
+
+        let pending_kernel = cx
+            .spawn(|this, mut cx| async move {
+                if let Some(refresh_kernel_specifications) = refresh_kernel_specifications {
+                    refresh_kernel_specifications.await.log_err();
+                }
+                let Ok(kernel) = this.update(&mut cx, |this, cx| this.launch_kernel(cx)) else {
+                    return;
+                };
+                let kernel = kernel.await;
+
+                this.update(&mut cx, |this, cx| match kernel {
+                    Ok((kernel, mut messages_rx, stopped)) => {
+                        this.kernel = Kernel::RunningKernel(kernel);
+                        this.plot_theme = None;
+                        this.apply_plot_theme(cx);
+
+                        this.process_status_task = Some(cx.spawn(|this, mut cx| async move {
+                            let Some(error_message) = stopped.await else {
+                                return;
+                            };
+
+                            log::error!("{}", error_message);
+
+                            this.update(&mut cx, |this, cx| this.kernel_died(error_message, cx))
+                                .ok();
+                        }));
+
+                        this.messaging_task = Some(cx.spawn(|this, mut cx| async move {
+                            while let Some(message) = messages_rx.next().await {
+                                this.update(&mut cx, |this, cx| this.route(&message, cx))
+                                    .ok();
+                            }
+                        }));
+
+                        cx.notify();
+                    }
+                    Err(err) => {
+                        this.kernel = Kernel::ErroredLaunch(err.to_string());
+                        this.fail_executions(format!("Kernel failed to start: {err}"), cx);
+                        cx.notify();
+                    }
+                })
+                .ok();
+            })
+            .shared();
+
+        self.kernel = Kernel::StartingKernel(pending_kernel);
+        // Cells run while the kernel was restarting or shutting down are sent once it's started
+        self.process_execution_queue(cx);
+    }
+
+    /// Launches the notebook's kernel, on the project's host for SSH projects.
+    fn launch_kernel(&mut self, cx: &mut ViewContext<Self>) -> Task<Result<LaunchedKernel>> {
+        let Some(kernel_specification) = self.kernel_specification(cx) else {
+            return Task::ready(Err(anyhow::anyhow!("No kernel found for this notebook")));
+        };
+
+        let working_directory = self
+            .notebook_item
+            .read(cx)
+            .path
+            .parent()
+            .map(|parent| parent.to_path_buf())
+            .unwrap_or_else(temp_dir);
+
+        match kernel_specification {
+            KernelSpecification::Jupyter(kernel_specification)
+            | KernelSpecification::PythonEnv(kernel_specification) => {
+                let fs = ReplStore::global(cx).read(cx).fs().clone();
+                let environment = kernel_environment(&self.project, self.worktree_id(cx), cx);
+                let kernel = NativeRunningKernel::new(
+                    kernel_specification,
+                    cx.entity_id(),
+                    working_directory,
+                    environment,
+                    fs,
+                    cx,
+                );
+
+                cx.spawn(|_, mut cx| async move {
+                    let (mut kernel, messages_rx) = kernel.await?;
+                    let (status, heartbeat_lost) = cx.update(|cx| {
+                        kernel.log_process_output(cx);
+                        (kernel.process.status(), kernel.heartbeat_lost(cx))
+                    })?;
+
+                    let process_exited = async move {
+                        match status.await {
+                            Ok(status) => {
+                                if status.success() {
+                                    log::info!("kernel process exited successfully");
+                                    return None;
+                                }
+
+                                Some(format!("kernel process exited with status: {:?}", status))
+                            }
+                            Err(err) => {
+                                Some(format!("kernel process exited with error: {:?}", err))
+                            }
+                        }
+                    };
+                    let stopped_responding = async move {
+                        heartbeat_lost.await;
+                        Some("kernel stopped responding to heartbeats".to_string())
+                    };
+                    let stopped = smol::future::or(process_exited, stopped_responding);
+
+                    anyhow::Ok((
+                        Box::new(kernel) as Box<dyn RunningKernel>,
+                        messages_rx,
+                        stopped.boxed_local(),
+                    ))
+                })
+            }
+            KernelSpecification::Ssh(kernel_specification) => {
+                let kernel = SshRunningKernel::new(
+                    kernel_specification,
+                    working_directory,
+                    &self.project,
+                    cx,
+                );
+
+                cx.spawn(|_, _| async move {
+                    let (mut kernel, messages_rx) = kernel.await?;
+                    let stopped = kernel.stopped();
+
+                    anyhow::Ok((
+                        Box::new(kernel) as Box<dyn RunningKernel>,
+                        messages_rx,
+                        stopped.boxed_local(),
+                    ))
+                })
+            }
+            KernelSpecification::Remote(_) => Task::ready(Err(anyhow::anyhow!(
+                "Remote kernels are not supported in notebooks yet"
+            ))),
+            KernelSpecification::External(_) => Task::ready(Err(anyhow::anyhow!(
+                "Kernels started by other clients are not supported in notebooks yet"
+            ))),
+        }
+    }
+
+    /// Marks every running and queued cell as failed. The notebook stays editable and the kernel
+    /// can be started again from the banner or by running a cell.
+    fn kernel_died(&mut self, error_message: String, cx: &mut ViewContext<Self>) {
+        let kernel = std::mem::replace(&mut self.kernel, Kernel::Died(error_message.clone()));
+        self.messaging_task.take();
+        // A kernel that stopped answering heartbeats may still be running, and holding its ports
+        if let Kernel::RunningKernel(mut kernel) = kernel {
+            kernel.force_shutdown().log_err();
+        }
+
+        self.fail_executions(format!("Kernel died: {error_message}"), cx);
+        cx.notify();
+    }
+
+    /// Marks every running and queued cell, install and detached execution as failed, since
+    /// there's no kernel left to run them.
+    fn fail_executions(&mut self, error_message: String, cx: &mut ViewContext<Self>) {
+        self.fail_pending_installs(cx);
+        self.end_detached_executions(ExecutionStatus::KernelErrored(error_message.clone()), cx);
+        let interrupted_cells = self
+            .pending_executions
+            .drain()
+            .map(|(_, cell_id)| cell_id)
+            .chain(self.execution_queue.drain(..))
+            .collect::<Vec<_>>();
+        for cell_id in interrupted_cells {
+            if let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id) {
+                cell.update(cx, |cell, cx| {
+                    cell.fail_execution(error_message.clone(), cx)
+                });
+            }
+        }
+    }
+
+    /// Marks every install still running as failed, since the kernel will never finish it.
+    fn fail_pending_installs(&mut self, cx: &mut ViewContext<Self>) {
+        for (_, cell_id) in self.pending_installs.drain() {
+            if let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id) {
+                cell.update(cx, |cell, cx| cell.fail_package_install(cx));
+            }
+        }
+    }
+
+    /// Stops waiting on code run from outside the notebook's cells, since the kernel will never
+    /// finish it.
+    fn end_detached_executions(&mut self, status: ExecutionStatus, cx: &mut ViewContext<Self>) {
+        for (_, execution) in self.pending_detached_executions.drain() {
+            execution
+                .update(cx, |execution, cx| {
+                    execution.status = status.clone();
+                    cx.notify();
+                })
+                .ok();
+        }
+    }
+
+    /// Stops waiting on everything sent to the kernel, before it's restarted or shut down or the
+    /// cells are reloaded.
+    fn abandon_pending_executions(&mut self, cx: &mut ViewContext<Self>) {
+        self.clear_execution_queue(cx);
+        self.pending_completions.clear();
+        self.fail_pending_installs(cx);
+        self.end_detached_executions(ExecutionStatus::Unknown, cx);
+        for (_, cell_id) in self.pending_executions.drain() {
+            if let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id) {
+                cell.update(cx, |cell, cx| cell.abandon_execution(cx));
+            }
+        }
+    }
+
+    pub(crate) fn kernel(&self) -> &Kernel {
+        &self.kernel
+    }
+
+    /// Interrupts the cell or input the kernel is running. Queued cells still run afterwards.
+    pub(crate) fn interrupt_kernel(&mut self, cx: &mut ViewContext<Self>) {
+        if let Kernel::RunningKernel(_) = &self.kernel {
+            self.send(InterruptRequest {}.into(), cx);
+        }
+    }
+
+    /// Shuts the kernel down. Running a cell starts it again.
+    pub(crate) fn shutdown_kernel(&mut self, cx: &mut ViewContext<Self>) {
+        let kernel = std::mem::replace(&mut self.kernel, Kernel::ShuttingDown);
+        self.abandon_pending_executions(cx);
+        self.messaging_task.take();
+        self.process_status_task.take();
+
+        match kernel {
+            Kernel::RunningKernel(mut kernel) => {
+                let mut request_tx = kernel.request_tx().clone();
+
+                cx.spawn(|this, mut cx| async move {
+                    let message: JupyterMessage = ShutdownRequest { restart: false }.into();
+                    request_tx.try_send(message).ok();
+
+                    // Give the kernel a bit of time to clean up
+                    cx.background_executor().timer(Duration::from_secs(1)).await;
+                    kernel.force_shutdown().ok();
+
+                    this.update(&mut cx, |this, cx| {
+                        // The kernel may have been started again while this one shut down
+                        if !matches!(this.kernel, Kernel::ShuttingDown) {
+                            return;
+                        }
+                        this.kernel = Kernel::Shutdown;
+                        // Cells run while the kernel was shutting down start it again
+                        if !this.execution_queue.is_empty() {
+                            this.start_kernel(cx);
+                        }
+                        cx.notify();
+                    })
+                    .ok();
+                })
+                .detach();
+            }
+            _ => self.kernel = Kernel::Shutdown,
+        }
+        cx.notify();
+    }
+
+    pub(crate) fn restart_kernel(&mut self, cx: &mut ViewContext<Self>) {
+        let kernel = std::mem::replace(&mut self.kernel, Kernel::Restarting);
+        self.abandon_pending_executions(cx);
+
+        match kernel {
+            Kernel::Restarting => {
+                // Do nothing if already restarting
+            }
+            Kernel::RunningKernel(mut kernel) => {
+                let mut request_tx = kernel.request_tx().clone();
+                self.messaging_task.take();
+                self.process_status_task.take();
+
+                cx.spawn(|this, mut cx| async move {
+                    let message: JupyterMessage = ShutdownRequest { restart: true }.into();
+                    request_tx.try_send(message).ok();
+
+                    // Wait for kernel to shutdown
+                    cx.background_executor().timer(Duration::from_secs(1)).await;
+
+                    // Force kill the kernel if it hasn't shut down
+                    kernel.force_shutdown().ok();
+
+                    this.update(&mut cx, |this, cx| {
+                        // The kernel may have been shut down or started again in the meantime
+                        if matches!(this.kernel, Kernel::Restarting) {
+                            this.start_kernel(cx);
+                        }
+                    })
+                    .ok();
+                })
+                .detach();
+            }
+            _ => {
+                self.messaging_task.take();
+                self.process_status_task.take();
+                self.start_kernel(cx);
+            }
+        }
+        cx.notify();
+    }
+
+    /// Shows the notebook's saved outputs in full, and remembers the notebook as trusted until
+    /// it's changed outside of Zed.
+    fn trust_notebook(&mut self, cx: &mut ViewContext<Self>) {
+        // Notebooks without outputs to hide are trusted to run on open too, see `super::auto_run`
+        self.remember_trust(cx);
+        if self.trusted {
+            return;
+        }
+        self.trusted = true;
+        for cell in self.cell_map.values() {
+            if let Cell::Code(code_cell) = cell {
+                code_cell.update(cx, |cell, cx| cell.set_trusted(true, cx));
+            }
+        }
+        cx.notify();
+    }
+
+    /// Loads a saved output whose payloads were set aside when the notebook was opened.
+    fn load_deferred_output(
+        &mut self,
+        cell_id: &CellId,
+        output_index: usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let deferred_outputs = self.notebook_item.read(cx).deferred_outputs.clone();
+        if let Some(Cell::Code(code_cell)) = self.cell_map.get(cell_id) {
+            code_cell.update(cx, |cell, cx| {
+                cell.load_deferred_output(output_index, &deferred_outputs, cx)
+            });
+        }
+    }
+
+    /// Loads every output whose payloads were set aside when the notebook was opened.
+    fn load_deferred_outputs(&mut self, cx: &mut ViewContext<Self>) {
+        let deferred_outputs = self.notebook_item.read(cx).deferred_outputs.clone();
+        if deferred_outputs.is_empty() {
+            return;
+        }
+        for cell in self.cell_map.values() {
+            if let Cell::Code(code_cell) = cell {
+                code_cell.update(cx, |cell, cx| {
+                    cell.load_deferred_outputs(&deferred_outputs, cx)
+                });
+            }
+        }
+    }
+
+    /// Records that the visible cells are on screen, and reads back the outputs they had spilled
+    /// to disk.
+    fn cells_scrolled_into_view(&mut self, cx: &mut ViewContext<Self>) {
+        let now = Instant::now();
+        let visible_cells = self
+            .cell_order
+            .get(self.visible_cells.clone())
+            .unwrap_or_default();
+        for cell_id in visible_cells {
+            self.cell_last_visible.insert(cell_id.clone(), now);
+            if let Some(Cell::Code(code_cell)) = self.cell_map.get(cell_id) {
+                if code_cell.read(cx).has_spilled_outputs() {
+                    code_cell.update(cx, |cell, cx| cell.rehydrate_spilled_outputs(cx));
+                }
+            }
+        }
+    }
+
+    /// Checks the outputs against `notebook.output_memory_limit` once they stop changing.
+    fn schedule_output_memory_limit(&mut self, cx: &mut ViewContext<Self>) {
+        if NotebookSettings::get_global(cx).output_memory_limit == 0 {
+            return;
+        }
+
+        self.output_memory_limit_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(OUTPUT_MEMORY_LIMIT_DEBOUNCE)
+                .await;
+            this.update(&mut cx, |this, cx| this.enforce_output_memory_limit(cx))
+                .ok();
+        }));
+    }
+
+    /// Spills the large outputs of cells that aren't on screen to disk until the notebook's
+    /// outputs fit in `notebook.output_memory_limit`, starting with the cells seen the longest
+    /// time ago.
+    fn enforce_output_memory_limit(&mut self, cx: &mut ViewContext<Self>) {
+        let limit = NotebookSettings::get_global(cx).output_memory_limit * 1024 * 1024;
+        if limit == 0 || self.is_loading() {
+            return;
+        }
+
+        let mut memory = 0;
+        let mut candidates = Vec::new();
+        for (index, cell_id) in self.cell_order.iter().enumerate() {
+            let Some(Cell::Code(code_cell)) = self.cell_map.get(cell_id) else {
+                continue;
+            };
+            let cell_memory = code_cell.update(cx, |cell, _| cell.output_memory());
+            memory += cell_memory;
+            if cell_memory >= SPILL_MIN_SIZE && !self.visible_cells.contains(&index) {
+                let last_visible = self.cell_last_visible.get(cell_id).copied();
+                candidates.push((last_visible, code_cell.clone()));
+            }
+        }
+        if memory <= limit {
+            return;
+        }
+
+        // Cells that were never on screen sort first
+        candidates.sort_by_key(|(last_visible, _)| *last_visible);
+        let spill_dir = self.notebook_item.read(cx).spill_dir.clone();
+        let mut files = Vec::new();
+        for (_, code_cell) in candidates {
+            if memory <= limit {
+                break;
+            }
+            code_cell.update(cx, |cell, cx| {
+                let before = cell.output_memory();
+                files.extend(cell.spill_outputs(&spill_dir, cx));
+                memory -= before.saturating_sub(cell.output_memory());
+            });
+        }
+
+        if !files.is_empty() {
+            cx.background_executor()
+                .spawn(async move { spill_dir.write_outputs(files) })
+                .detach_and_log_err(cx);
+        }
+    }
+
+    /// Saves the signature of the notebook as it is on disk, so it's trusted when reopened.
+    fn remember_trust(&self, cx: &mut ViewContext<Self>) {
+        if let Some(signature) = self.notebook_item.read(cx).signature() {
+            cx.background_executor()
+                .spawn(NOTEBOOK_DB.trust_notebook(signature))
+                .detach_and_log_err(cx);
+        }
+    }
+
+    fn render_trust_banner(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.trusted {
+            return None;
+        }
+
+        Some(
+            h_flex()
+                .w_full()
+                .my_2()
+                .px_3()
+                .py_2()
+                .gap_2()
+                .justify_between()
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().status().warning_border)
+                .bg(cx.theme().status().warning_background)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(
+                            Label::new(
+                                "This notebook isn't trusted, so its saved HTML and chart outputs are hidden",
+                            )
+                            .size(LabelSize::Small)
+                            .single_line(),
+                        ),
+                )
+                .child(
+                    Button::new("trust-notebook", "Trust Notebook")
+                        .style(ButtonStyle::Filled)
+                        .label_size(LabelSize::Small)
+                        .on_click(cx.listener(|this, _, cx| this.trust_notebook(cx))),
+                ),
+        )
+    }
+
+    fn render_filter_banner(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let filter = self.cell_filter.as_ref()?;
+        let total = self.cell_order.len();
+        let shown = self.shown_cell_indices(0..total).count();
+
+        Some(
+            h_flex()
+                .w_full()
+                .my_2()
+                .px_3()
+                .py_2()
+                .gap_2()
+                .justify_between()
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().status().info_border)
+                .bg(cx.theme().status().info_background)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Filter)
+                                .size(IconSize::Small)
+                                .color(Color::Info),
+                        )
+                        .child(
+                            Label::new(format!(
+                                "Showing {shown} of {total} cells: {}",
+                                filter.label()
+                            ))
+                            .size(LabelSize::Small)
+                            .single_line(),
+                        ),
+                )
+                .child(
+                    Button::new("clear-cell-filter", "Show All Cells")
+                        .style(ButtonStyle::Filled)
+                        .label_size(LabelSize::Small)
+                        .on_click(cx.listener(|this, _, cx| this.clear_cell_filter(cx))),
+                ),
+        )
+    }
+
+    fn render_kernel_banner(&self, cx: &ViewContext<Self>) -> Option<impl IntoElement> {
+        let message = match &self.kernel {
+            Kernel::ErroredLaunch(error_message) => {
+                format!("Kernel failed to start: {error_message}")
+            }
+            Kernel::Died(error_message) => format!("Kernel stopped: {error_message}"),
+            _ => return None,
+        };
+
+        Some(
+            h_flex()
+                .w_full()
+                .my_2()
+                .px_3()
+                .py_2()
+                .gap_2()
+                .justify_between()
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().status().error_border)
+                .bg(cx.theme().status().error_background)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .min_w_0()
+                        .child(
+                            Icon::new(IconName::Warning)
+                                .size(IconSize::Small)
+                                .color(Color::Error),
+                        )
+                        .child(Label::new(message).size(LabelSize::Small).single_line()),
+                )
+                .child(
+                    Button::new("restart-kernel", "Restart Kernel")
+                        .style(ButtonStyle::Filled)
+                        .label_size(LabelSize::Small)
+                        .on_click(cx.listener(|this, _, cx| this.restart_kernel(cx))),
+                ),
+        )
+    }
+
+    fn route(&mut self, message: &JupyterMessage, cx: &mut ViewContext<Self>) {
+        let Some(parent_message_id) = message
+            .parent_header
+            .as_ref()
+            .map(|header| header.msg_id.clone())
+        else {
+            return;
+        };
+
+        match &message.content {
+            JupyterMessageContent::Status(status) => {
+                self.kernel.set_execution_state(&status.execution_state);
+                cx.notify();
+            }
+            JupyterMessageContent::KernelInfoReply(reply) => {
+                self.kernel.set_kernel_info(reply);
+                cx.notify();
+            }
+            JupyterMessageContent::CompleteReply(reply) => {
+                if let Some(reply_tx) = self.pending_completions.remove(&parent_message_id) {
+                    reply_tx.send(reply.clone()).ok();
+                }
+                return;
+            }
+            JupyterMessageContent::UpdateDisplayData(update) => {
+                // A display can be updated from any cell, so every cell showing it gets the update
+                if let Some(display_id) = update.transient.display_id.as_deref() {
+                    for cell in self.cell_map.values() {
+                        if let Cell::Code(cell) = cell {
+                            cell.update(cx, |cell, cx| {
+                                cell.update_display_data(&message.content, display_id, cx)
+                            });
+                        }
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(execution) = self.pending_detached_executions.get(&parent_message_id) {
+            execution
+                .update(cx, |execution, cx| {
+                    execution.push_message(&message.content, cx)
+                })
+                .ok();
+            if let JupyterMessageContent::Status(status) = &message.content {
+                if matches!(status.execution_state, ExecutionState::Idle) {
+                    self.pending_detached_executions.remove(&parent_message_id);
+                }
+            }
+            return;
+        }
+
+        if let Some(cell_id) = self.pending_installs.get(&parent_message_id) {
+            if let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) {
+                cell.update(cx, |cell, cx| cell.handle_install_message(message, cx));
+            }
+            if let JupyterMessageContent::Status(status) = &message.content {
+                if matches!(status.execution_state, ExecutionState::Idle) {
+                    self.pending_installs.remove(&parent_message_id);
+                }
+            }
+            return;
+        }
+
+        let Some(cell_id) = self.pending_executions.get(&parent_message_id) else {
+            return;
+        };
+
+        if let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) {
+            cell.update(cx, |cell, cx| cell.handle_message(message, cx));
+        }
+
+        let adds_output = matches!(
+            message.content,
+            JupyterMessageContent::StreamContent(_)
+                | JupyterMessageContent::DisplayData(_)
+                | JupyterMessageContent::ExecuteResult(_)
+                | JupyterMessageContent::ErrorOutput(_)
+        );
+        if adds_output && self.last_run_cell.as_ref() == Some(cell_id) {
+            let cell_id = cell_id.clone();
+            self.reveal_outputs_of(cell_id, cx);
+        }
+
+        // The kernel going idle for a request means it has sent everything it's going to send
+        if let JupyterMessageContent::Status(status) = &message.content {
+            if matches!(status.execution_state, ExecutionState::Idle) {
+                self.pending_executions.remove(&parent_message_id);
+                self.process_execution_queue(cx);
+            }
+        }
+    }
+
+    /// Styles the kernel's plots with the current theme, unless they already are.
+    fn apply_plot_theme(&mut self, cx: &mut ViewContext<Self>) {
+        let Kernel::RunningKernel(_) = &self.kernel else {
+            return;
+        };
+        let plot_theme = self
+            .language_name(cx)
+            .and_then(|language| PlotTheme::for_kernel(&language, cx));
+        if plot_theme == self.plot_theme {
+            return;
+        }
+        if let Some(plot_theme) = &plot_theme {
+            self.send(plot_theme.setup_request(), cx);
+        }
+        self.plot_theme = plot_theme;
+    }
+
+    fn send(&mut self, message: JupyterMessage, cx: &mut ViewContext<Self>) {
+        match &self.kernel {
+            Kernel::RunningKernel(kernel) => {
+                kernel.request_tx().try_send(message).ok();
+            }
+            Kernel::StartingKernel(task) => {
+                // Queue up the message to be sent once the kernel starts
+                let task = task.clone();
+                cx.spawn(|this, mut cx| async move {
+                    task.await;
+                    this.update(&mut cx, |this, cx| {
+                        if let Kernel::RunningKernel(kernel) = &this.kernel {
+                            kernel.request_tx().try_send(message).ok();
+                        }
+                    })
+                    .ok();
+                })
+                .detach();
+            }
+            _ => {}
+        }
+    }
+
+    /// Installs a package into the kernel's environment for a cell that couldn't import it. The
+    /// install is sent right away rather than queued, since the kernel runs requests in order anyway.
+    fn install_package(
+        &mut self,
+        cell_id: CellId,
+        package: String,
+        manager: PackageManager,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.is_remote() || self.is_read_only(cx) {
+            return;
+        }
+        let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id).cloned() else {
+            return;
+        };
+
+        if let Kernel::Shutdown | Kernel::ErroredLaunch(_) | Kernel::Died(_) = &self.kernel {
+            self.start_kernel(cx);
+        }
+
+        let message: JupyterMessage = ExecuteRequest {
+            code: manager.install_code(&package),
+            store_history: false,
+            ..ExecuteRequest::default()
+        }
+        .into();
+
+        cell.update(cx, |cell, cx| {
+            cell.start_package_install(package, manager, cx)
+        });
+        self.pending_installs
+            .insert(message.header.msg_id.clone(), cell_id);
+        self.send(message, cx);
+    }
+
+    /// Runs code from outside the notebook's cells on its kernel, like an input from the console
+    /// panel or code from an attached editor, showing its outputs in `execution`. Like package
+    /// installs, it's sent right away rather than waiting behind queued cells. Returns `false` if
+    /// code can't be run on this notebook's kernel from here.
+    pub(crate) fn execute_detached(
+        &mut self,
+        code: String,
+        execution: WeakView<ExecutionView>,
+        cx: &mut ViewContext<Self>,
+    ) -> bool {
+        if self.is_remote() || self.is_read_only(cx) {
+            return false;
+        }
+
+        if let Kernel::Shutdown | Kernel::ErroredLaunch(_) | Kernel::Died(_) = &self.kernel {
+            self.start_kernel(cx);
+        }
+
+        let message: JupyterMessage = ExecuteRequest {
+            code,
+            allow_stdin: false,
+            store_history: true,
+            ..ExecuteRequest::default()
+        }
+        .into();
+
+        let status = ExecutionStatus::from(&self.kernel);
+        execution
+            .update(cx, |execution, cx| {
+                execution.status = status;
+                cx.notify();
+            })
+            .ok();
+        self.pending_detached_executions
+            .insert(message.header.msg_id.clone(), execution);
+        self.send(message, cx);
+        true
+    }
+
+    /// Queues a cell for execution. It is sent to the kernel once every cell ahead of it has finished.
+    ///
+    /// Followers ask the host to run the cell instead, if it lets guests run cells.
+    fn execute_cell(&mut self, cell_id: CellId, cx: &mut ViewContext<Self>) {
+        if self.is_read_only(cx) {
+            return;
+        }
+        if self.is_remote() {
+            self.request_execution(cell_id, cx);
+            return;
+        }
+
+        // Attribute runs to the host too, so guests can tell who ran what
+        let executed_by = if self.project.read(cx).is_shared() {
+            let user_store = self.project.read(cx).user_store();
+            let user = user_store.read(cx).current_user();
+            user.map(|user| SharedString::from(user.github_login.clone()))
+        } else {
+            None
+        };
+        self.queue_execution(cell_id, executed_by, cx);
+    }
+
+    /// Runs a cell on behalf of a guest of the shared project.
+    pub(crate) fn execute_cell_for_guest(
+        &mut self,
+        cell_id: CellId,
+        login: SharedString,
+        cx: &mut ViewContext<Self>,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            self.guests_can_run && !self.is_read_only(cx),
+            "the host doesn't allow guests to run cells in this notebook"
+        );
+        anyhow::ensure!(
+            matches!(self.cell_map.get(&cell_id), Some(Cell::Code(_))),
+            "no code cell with this id"
+        );
+        self.queue_execution(cell_id, Some(login), cx);
+        Ok(())
+    }
+
+    fn queue_execution(
+        &mut self,
+        cell_id: CellId,
+        executed_by: Option<SharedString>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id).cloned() else {
+            return;
+        };
+
+        if self.execution_queue.contains(&cell_id) {
+            return;
+        }
+
+        if let Kernel::Shutdown | Kernel::ErroredLaunch(_) | Kernel::Died(_) = &self.kernel {
+            self.start_kernel(cx);
+        }
+
+        cell.update(cx, |cell, cx| {
+            cell.set_executed_by(executed_by, cx);
+            cell.set_execution_status(ExecutionStatus::Queued, cx)
+        });
+        self.execution_queue.push_back(cell_id);
+        self.process_execution_queue(cx);
+    }
+
+    /// Asks the host to run a cell of the notebook it's sharing. The cell's state comes back
+    /// with the host's updates, like any other execution.
+    fn request_execution(&mut self, cell_id: CellId, cx: &mut ViewContext<Self>) {
+        if !self.guests_can_run {
+            return;
+        }
+        let Some(Cell::Code(_)) = self.cell_map.get(&cell_id) else {
+            return;
+        };
+        let project = self.project.read(cx);
+        let Some(project_id) = project.remote_id() else {
+            return;
+        };
+        let Some(project_path) = &self.notebook_item.read(cx).project_path else {
+            return;
+        };
+        let request = project.client().request(proto::RunNotebookCell {
+            project_id,
+            worktree_id: project_path.worktree_id.to_proto(),
+            path: project_path.path.to_string_lossy().to_string(),
+            cell_id: cell_id_to_proto(&cell_id),
+        });
+        cx.background_executor()
+            .spawn(async move { request.await.log_err() })
+            .detach();
+    }
+
+    fn toggle_guest_execution(&mut self, cx: &mut ViewContext<Self>) {
+        if self.is_remote() {
+            return;
+        }
+        self.guests_can_run = !self.guests_can_run;
+        cx.emit(NotebookEvent::GuestExecutionChanged);
+        cx.notify();
+    }
+
+    /// Sends the next queued cell once the one running finishes. The queue is held while the
+    /// kernel restarts or shuts down, since its requests would be dropped.
+    fn process_execution_queue(&mut self, cx: &mut ViewContext<Self>) {
+        if !matches!(
+            self.kernel,
+            Kernel::RunningKernel(_) | Kernel::StartingKernel(_)
+        ) {
+            cx.notify();
+            return;
+        }
+
+        while self.pending_executions.is_empty() {
+            let Some(cell_id) = self.execution_queue.pop_front() else {
+                break;
+            };
+            let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id).cloned() else {
+                continue;
+            };
+
+            let code = cell.read(cx).current_source(cx);
+            if code.is_empty() {
+                cell.update(cx, |cell, cx| {
+                    cell.start_execution(ExecutionStatus::Unknown, cx);
+                });
+                continue;
+            }
+
+            let message: JupyterMessage = ExecuteRequest {
+                code,
+                allow_stdin: true,
+                ..ExecuteRequest::default()
+            }
+            .into();
+
+            let status = ExecutionStatus::from(&self.kernel);
+            cell.update(cx, |cell, cx| cell.start_execution(status, cx));
+
+            self.pending_executions
+                .insert(message.header.msg_id.clone(), cell_id);
+            self.send(message, cx);
+        }
+
+        cx.notify();
+    }
+
+    fn cancel_queued_execution(&mut self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        let queue_len = self.execution_queue.len();
+        self.execution_queue
+            .retain(|queued_id| queued_id != cell_id);
+        if self.execution_queue.len() == queue_len {
+            return;
+        }
+
+        if let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) {
+            cell.update(cx, |cell, cx| {
+                cell.set_execution_status(ExecutionStatus::Unknown, cx)
+            });
+        }
+        cx.notify();
+    }
+
+    /// Drops every cell waiting to execute, leaving the one currently running untouched.
+    fn clear_execution_queue(&mut self, cx: &mut ViewContext<Self>) {
+        for cell_id in self.execution_queue.drain(..) {
+            if let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id) {
+                cell.update(cx, |cell, cx| {
+                    cell.set_execution_status(ExecutionStatus::Unknown, cx)
+                });
+            }
+        }
+        cx.notify();
+    }
+
+    fn selected_cell_id(&self) -> Option<CellId> {
+        self.cell_order.get(self.selected_cell_index).cloned()
+    }
+
+    fn run_selected_cell(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell_id) = self.selected_cell_id() else {
+            return;
+        };
+
+        // Running a markdown cell renders it
+        if let Some(Cell::Markdown(cell)) = self.cell_map.get(&cell_id) {
+            cell.update(cx, |cell, cx| cell.finish_editing(cx));
+            cx.focus(&self.focus_handle);
+            return;
+        }
+
+        self.last_run_cell = Some(cell_id.clone());
+        self.execute_cell(cell_id, cx);
+    }
+
+    /// Scrolls new outputs of a cell into view, as long as it is the selected cell or the one
+    /// right above it, and the user hasn't scrolled away from the selection in the meantime.
+    fn reveal_outputs_of(&mut self, cell_id: CellId, cx: &mut ViewContext<Self>) {
+        // The outputs only have a height once the cell list has laid them out
+        cx.on_next_frame(move |this, cx| {
+            let Some(index) = this.cell_order.iter().position(|id| *id == cell_id) else {
+                return;
+            };
+            let selected = this.selected_cell_index;
+            if index != selected && index + 1 != selected {
+                return;
+            }
+            if !this.visible_cells.contains(&selected) {
+                return;
+            }
+
+            this.cell_list.scroll_to_reveal_item(index);
+            this.cell_list.scroll_to_reveal_item(selected);
+            cx.notify();
+        });
+    }
+
+    /// Starts editing the selected cell, showing the source of markdown cells.
+    fn edit_selected_cell(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell_id) = self.selected_cell_id() else {
+            return;
+        };
+
+        match self.cell_map.get(&cell_id) {
+            Some(Cell::Markdown(cell)) => cell.update(cx, |cell, cx| cell.start_editing(cx)),
+            Some(Cell::Code(_)) | Some(Cell::Raw(_)) => self.focus_selected_cell_editor(cx),
+            None => {}
+        }
+    }
+
+    fn run_and_insert_below(&mut self, cx: &mut ViewContext<Self>) {
+        self.run_selected_cell(cx);
+
+        let index = (self.selected_cell_index + 1).min(self.cell_order.len());
+        self.insert_cell(index, CellType::Code, cx);
+        self.set_selected_index(index, true, cx);
+        self.focus_selected_cell_editor(cx);
+        cx.notify();
+    }
+
+    /// Runs the selected cell and selects the one below it, like `shift-enter` in Jupyter. Running
+    /// the last cell adds a code cell to edit next.
+    fn run_and_select_below(&mut self, cx: &mut ViewContext<Self>) {
+        self.run_selected_cell(cx);
+
+        let index = self.selected_cell_index + 1;
+        if index < self.cell_order.len() {
+            self.set_selected_index(index, true, cx);
+            cx.focus(&self.focus_handle);
+        } else {
+            self.insert_cell(index, CellType::Code, cx);
+            self.set_selected_index(index, true, cx);
+            self.focus_selected_cell_editor(cx);
+        }
+        cx.notify();
+    }
+
+    /// Inserts a new, empty cell of the given type at `index` in the cell list.
+    fn insert_cell(
+        &mut self,
+        index: usize,
+        cell_type: CellType,
+        cx: &mut ViewContext<Self>,
+    ) -> CellId {
+        let nbformat_cell = Cell::empty_nbformat_cell(cell_type);
+        self.insert_nbformat_cell(index, &nbformat_cell, cx)
+    }
+
+    fn insert_nbformat_cell(
+        &mut self,
+        index: usize,
+        nbformat_cell: &nbformat::v4::Cell,
+        cx: &mut ViewContext<Self>,
+    ) -> CellId {
+        let cell_id = nbformat_cell.id().clone();
+        let cell = Cell::load(
+            nbformat_cell,
+            &self.languages,
+            self.notebook_language.clone(),
+            self.trusted,
+            cx,
+        );
+
+        self.style_cell_editor(&cell, cx);
+        self.cell_subscriptions
+            .insert(cell_id.clone(), Self::subscribe_to_cell(&cell, cx));
+        self.cell_order.insert(index, cell_id.clone());
+        self.cell_map.insert(cell_id.clone(), cell);
+        self.cell_list.splice(index..index, 1);
+        self.push_cell_operation(CellOperation::Inserted(cell_id.clone()));
+        self.update_cell_git_status(&cell_id, cx);
+        self.schedule_cell_document_sync(cx);
+
+        cell_id
+    }
+
+    /// Removes the cell at `index` from the cell list, returning it so it can be restored. The
+    /// notebook stops waiting on the cell if it's queued or running, so it's restored idle.
+    fn remove_cell(&mut self, index: usize, cx: &mut ViewContext<Self>) -> Option<(CellId, Cell)> {
+        if index >= self.cell_order.len() {
+            return None;
+        }
+
+        let cell_id = self.cell_order.remove(index);
+        let cell = self.cell_map.remove(&cell_id)?;
+        self.cell_subscriptions.remove(&cell_id);
+        let was_queued = self.execution_queue.contains(&cell_id);
+        let was_running = self.pending_executions.values().any(|id| id == &cell_id);
+        self.execution_queue.retain(|queued| queued != &cell_id);
+        self.pending_executions
+            .retain(|_, running| running != &cell_id);
+        if let (Cell::Code(code_cell), true) = (&cell, was_queued || was_running) {
+            code_cell.update(cx, |cell, cx| cell.abandon_execution(cx));
+        }
+        if was_running {
+            self.process_execution_queue(cx);
+        }
+
+        self.cell_list.splice(index..index + 1, 0);
+        self.selected_cell_index = self
+            .selected_cell_index
+            .min(self.cell_order.len().saturating_sub(1));
+
+        Some((cell_id, cell))
+    }
+
+    /// Remembers a change to the list of cells so it can be undone, forgetting the oldest change
+    /// past [`MAX_UNDO_OPERATIONS`].
+    fn push_cell_operation(&mut self, operation: CellOperation) {
+        if self.undo_stack.len() == MAX_UNDO_OPERATIONS {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(operation);
+    }
+
+    fn cell_entity_ids(&self) -> Vec<EntityId> {
+        self.cell_order
+            .iter()
+            .filter_map(|cell_id| Some(self.cell_map.get(cell_id)?.entity_id()))
+            .collect()
+    }
+
+    /// Whether cells were added, removed, reordered or replaced since the notebook was last
+    /// saved. Undoing those changes makes the cells match again.
+    fn structure_modified(&self) -> bool {
+        self.cell_entity_ids() != self.saved_cells
+    }
+
+    fn delete_selected_cell(&mut self, cx: &mut ViewContext<Self>) {
+        let index = self.selected_cell_index;
+        let is_deletable = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+            .map_or(false, |cell| cell.is_deletable(cx));
+        if !is_deletable {
+            return;
+        }
+        let Some((cell_id, cell)) = self.remove_cell(index, cx) else {
+            return;
+        };
+
+        self.push_cell_operation(CellOperation::Deleted {
+            index,
+            cell_id,
+            cell,
+        });
+        self.schedule_cell_document_sync(cx);
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    /// Copies the selected cell to the clipboard, returning the text that was written.
+    fn copy_selected_cell(&mut self, cx: &mut ViewContext<Self>) -> Option<String> {
+        let cell = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))?;
+
+        let text = Cell::clipboard_text(&[cell.to_nbformat_cell(cx)])?;
+        cx.write_to_clipboard(ClipboardItem::new_string(text.clone()));
+        Some(text)
+    }
+
+    /// Copies a cell as markdown to paste into an issue or a chat, see [`cell_markdown`].
+    fn copy_cell_as_markdown(
+        &mut self,
+        cell_id: &CellId,
+        include_output: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(cell) = self.cell_map.get(cell_id) else {
+            return;
+        };
+        let language = self.notebook_item.read(cx).language_name();
+        let markdown = cell_markdown(
+            &cell.to_nbformat_cell(cx),
+            language.as_deref(),
+            include_output,
+        );
+        cx.write_to_clipboard(ClipboardItem::new_string(markdown));
+    }
+
+    fn cut_selected_cell(&mut self, cx: &mut ViewContext<Self>) {
+        let is_deletable = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+            .map_or(false, |cell| cell.is_deletable(cx));
+        if !is_deletable {
+            return;
+        }
+        let cell_id = self.selected_cell_id();
+        let text = self.copy_selected_cell(cx);
+        self.last_cut = text.zip(cell_id);
+        self.delete_selected_cell(cx);
+    }
+
+    /// Pastes cells copied from this or another notebook next to the selected cell.
+    fn paste_cells(&mut self, above: bool, cx: &mut ViewContext<Self>) {
+        let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+            return;
+        };
+        let Some(mut cells) = Cell::nbformat_cells_from_clipboard(&text) else {
+            return;
+        };
+
+        // Pasting a cut cell moves it, so it keeps its id, unless it's back in the notebook
+        if let Some((cut_text, cell_id)) = self.last_cut.take() {
+            if cut_text == text && cells.len() == 1 && !self.cell_map.contains_key(&cell_id) {
+                set_nbformat_cell_id(&mut cells[0], cell_id);
+            }
+        }
+
+        let mut index = if self.cell_order.is_empty() || above {
+            self.selected_cell_index.min(self.cell_order.len())
+        } else {
+            self.selected_cell_index + 1
+        };
+        for cell in &cells {
+            self.insert_nbformat_cell(index, cell, cx);
+            index += 1;
+        }
+
+        self.set_selected_index(index - 1, true, cx);
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    /// Undoes the last insertion or deletion of a cell.
+    fn undo_cell_operation(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(operation) = self.undo_stack.pop_back() else {
+            return;
+        };
+
+        match operation {
+            CellOperation::Inserted(cell_id) => {
+                // A cell that was locked after it was inserted can't be deleted, even by undo
+                let is_deletable = self
+                    .cell_map
+                    .get(&cell_id)
+                    .map_or(false, |cell| cell.is_deletable(cx));
+                if is_deletable {
+                    if let Some(index) = self.cell_order.iter().position(|id| id == &cell_id) {
+                        self.remove_cell(index, cx);
+                    }
+                }
+            }
+            CellOperation::Deleted {
+                index,
+                cell_id,
+                cell,
+            } => {
+                let index = index.min(self.cell_order.len());
+                self.style_cell_editor(&cell, cx);
+                self.cell_subscriptions
+                    .insert(cell_id.clone(), Self::subscribe_to_cell(&cell, cx));
+                self.cell_order.insert(index, cell_id.clone());
+                self.cell_map.insert(cell_id, cell);
+                self.cell_list.splice(index..index, 1);
+                self.set_selected_index(index, true, cx);
+            }
+            CellOperation::Moved { from, to } => self.move_cell(to, from, cx),
+            CellOperation::Converted { cell_id, cell } => {
+                self.replace_cell(&cell_id, cell, cx);
+            }
+            CellOperation::Replaced(transactions) => {
+                for (cell_id, transaction_id) in transactions {
+                    let Some(buffer) = self.cell_map.get(&cell_id).and_then(|cell| cell.buffer(cx))
+                    else {
+                        continue;
+                    };
+                    buffer.update(cx, |buffer, cx| buffer.undo_transaction(transaction_id, cx));
+                }
+            }
+        }
+
+        self.schedule_cell_document_sync(cx);
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    fn focus_selected_cell_editor(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell_id) = self.selected_cell_id() else {
+            return;
+        };
+
+        let editor = match self.cell_map.get(&cell_id) {
+            Some(Cell::Code(cell)) => cell.read(cx).editor().clone(),
+            Some(Cell::Raw(cell)) => cell.read(cx).editor().clone(),
+            Some(Cell::Markdown(_)) | None => return,
+        };
+        cx.focus_view(&editor);
+    }
+
+    fn has_outputs(&self, cx: &ViewContext<Self>) -> bool {
+        self.cell_map.values().any(|cell| {
+            if let Cell::Code(code_cell) = cell {
+                code_cell.read(cx).has_outputs()
+            } else {
+                false
+            }
+        })
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.structure_modified()
+            || self.metadata_modified
+            || self.cell_map.values().any(|cell| match cell {
+                Cell::Code(code_cell) => code_cell.read(cx).is_dirty(cx),
+                Cell::Markdown(markdown_cell) => markdown_cell.read(cx).is_dirty(cx),
+                Cell::Raw(raw_cell) => raw_cell.read(cx).is_dirty(cx),
+            })
+    }
+
+    /// Clears the outputs and execution counts of every cell.
+    fn clear_outputs(&mut self, cx: &mut ViewContext<Self>) {
+        for cell in self.cell_map.values() {
+            if let Cell::Code(code_cell) = cell {
+                code_cell.update(cx, |cell, cx| cell.clear(cx));
+            }
+        }
+        cx.notify();
+    }
+
+    fn clear_selected_cell_output(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(Cell::Code(cell)) = self
+            .selected_cell_id()
+            .and_then(|id| self.cell_map.get(&id))
+        {
+            cell.update(cx, |cell, cx| cell.clear(cx));
+            cx.notify();
+        }
+    }
+
+    fn toggle_selected_outputs_collapsed(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(Cell::Code(cell)) = self
+            .selected_cell_id()
+            .and_then(|id| self.cell_map.get(&id))
+        {
+            cell.update(cx, |cell, cx| cell.toggle_outputs_collapsed(cx));
+        }
+    }
+
+    fn toggle_selected_outputs_scrolled(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(Cell::Code(cell)) = self
+            .selected_cell_id()
+            .and_then(|id| self.cell_map.get(&id))
+        {
+            cell.update(cx, |cell, cx| cell.toggle_outputs_scrolled(cx));
+        }
+    }
+
+    fn run_cells(&mut self, cx: &mut ViewContext<Self>) {
+        self.run_cell_range(0..self.cell_order.len(), cx);
+    }
+
+    /// Runs every cell above the selected one, rebuilding kernel state up to that point.
+    fn run_cells_above(&mut self, cx: &mut ViewContext<Self>) {
+        self.run_cell_range(0..self.selected_cell_index, cx);
+    }
+
+    /// Runs the selected cell and every cell below it.
+    fn run_cells_below(&mut self, cx: &mut ViewContext<Self>) {
+        self.run_cell_range(self.selected_cell_index..self.cell_order.len(), cx);
+    }
+
+    fn run_cell_range(&mut self, range: Range<usize>, cx: &mut ViewContext<Self>) {
+        let start = range.start.min(self.cell_order.len());
+        let end = range.end.min(self.cell_order.len());
+        for cell_id in self.cell_order[start..end].to_vec() {
+            self.execute_cell(cell_id, cx);
+        }
+    }
+
+    fn open_notebook(&mut self, _: &OpenNotebook, _cx: &mut ViewContext<Self>) {
+        println!("Open notebook triggered");
+    }
+
+    fn move_cell_up(&mut self, cx: &mut ViewContext<Self>) {
+        let index = self.selected_cell_index;
+        if index == 0 || index >= self.cell_order.len() {
+            return;
+        }
+
+        self.move_cell(index, index - 1, cx);
+        self.push_cell_operation(CellOperation::Moved {
+            from: index,
+            to: index - 1,
+        });
+        cx.notify();
+    }
+
+    fn move_cell_down(&mut self, cx: &mut ViewContext<Self>) {
+        let index = self.selected_cell_index;
+        if index + 1 >= self.cell_order.len() {
+            return;
+        }
+
+        self.move_cell(index, index + 1, cx);
+        self.push_cell_operation(CellOperation::Moved {
+            from: index,
+            to: index + 1,
+        });
+        cx.notify();
+    }
+
+    /// Changes the type of the selected cell, keeping its source.
+    fn convert_selected_cell(&mut self, cell_type: CellType, cx: &mut ViewContext<Self>) {
+        let Some(cell_id) = self.selected_cell_id() else {
+            return;
+        };
+        let Some(cell) = self.cell_map.get(&cell_id) else {
+            return;
+        };
+        let is_same_type = matches!(
+            (cell, &cell_type),
+            (Cell::Code(_), CellType::Code)
+                | (Cell::Markdown(_), CellType::Markdown)
+                | (Cell::Raw(_), CellType::Raw)
+        );
+        // Converting replaces the cell's source, so locked cells keep their type
+        if is_same_type || !cell.is_editable(cx) {
+            return;
+        }
+
+        let nbformat_cell = cell.to_nbformat_cell_of_type(cell_type, cx);
+        let converted = Cell::load(
+            &nbformat_cell,
+            &self.languages,
+            self.notebook_language.clone(),
+            self.trusted,
+            cx,
+        );
+        self.style_cell_editor(&converted, cx);
+
+        if let Some(previous) = self.replace_cell(&cell_id, converted, cx) {
+            self.push_cell_operation(CellOperation::Converted {
+                cell_id,
+                cell: previous,
+            });
+        }
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    /// Puts a different cell in place of the one with the given id, returning the previous one.
+    fn replace_cell(
+        &mut self,
+        cell_id: &CellId,
+        cell: Cell,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Cell> {
+        let index = self.cell_order.iter().position(|id| id == cell_id)?;
+        self.execution_queue.retain(|queued| queued != cell_id);
+        self.cell_subscriptions
+            .insert(cell_id.clone(), Self::subscribe_to_cell(&cell, cx));
+        let previous = self.cell_map.insert(cell_id.clone(), cell);
+        self.cell_list.splice(index..index + 1, 1);
+        self.update_cell_git_status(cell_id, cx);
+        self.set_selected_index(index, true, cx);
+        self.schedule_cell_document_sync(cx);
+        previous
+    }
+
+    /// Swaps a cell with one of its neighbors, keeping it selected.
+    fn move_cell(&mut self, from: usize, to: usize, cx: &mut ViewContext<Self>) {
+        self.cell_order.swap(from, to);
+        let start = from.min(to);
+        self.cell_list.splice(start..start + 2, 2);
+        self.set_selected_index(to, true, cx);
+        self.schedule_cell_document_sync(cx);
+    }
+
+    /// Stops editing the selected cell, so key bindings apply to the notebook's cells again.
+    fn enter_command_mode(&mut self, cx: &mut ViewContext<Self>) {
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    /// Inserts an empty code cell next to the selected one and selects it, without editing it.
+    fn insert_code_cell(&mut self, above: bool, cx: &mut ViewContext<Self>) {
+        let index = if self.cell_order.is_empty() || above {
+            self.selected_cell_index.min(self.cell_order.len())
+        } else {
+            self.selected_cell_index + 1
+        };
+        self.insert_cell(index, CellType::Code, cx);
+        self.set_selected_index(index, true, cx);
+        cx.notify();
+    }
+
+    fn add_markdown_block(&mut self, cx: &mut ViewContext<Self>) {
+        self.add_cell_below(CellType::Markdown, cx);
+    }
+
+    fn add_code_block(&mut self, cx: &mut ViewContext<Self>) {
+        self.add_cell_below(CellType::Code, cx);
+    }
+
+    /// Inserts an empty cell below the selected one, and starts editing it.
+    fn add_cell_below(&mut self, cell_type: CellType, cx: &mut ViewContext<Self>) {
+        let index = if self.cell_order.is_empty() {
+            0
+        } else {
+            (self.selected_cell_index + 1).min(self.cell_order.len())
+        };
+        self.insert_cell(index, cell_type, cx);
+        self.set_selected_index(index, true, cx);
+        self.edit_selected_cell(cx);
+        cx.notify();
+    }
+
+    fn dispatch_context(&self, cx: &ViewContext<Self>) -> KeyContext {
+        let mut dispatch_context = KeyContext::new_with_defaults();
+        dispatch_context.add("notebook");
+
+        // Like Jupyter, the notebook is in command mode while no cell is being edited, and
+        // shortcuts like `d d` act on the selected cell
+        let identifier = if self.focus_handle.is_focused(cx) {
+            "not_editing"
+        } else {
+            "editing"
+        };
+
+        dispatch_context.add(identifier);
+        if self.presentation.is_some() {
+            dispatch_context.add("presenting");
+        }
+        dispatch_context
+    }
+
+    fn cell_count(&self) -> usize {
+        self.cell_map.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_cell_index
+    }
+
+    pub fn set_selected_index(
+        &mut self,
+        index: usize,
+        jump_to_index: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // let previous_index = self.selected_cell_index;
+        self.selected_cell_index = index;
+        let current_index = self.selected_cell_index;
+
+        // A filtered out cell is shown again once it's selected
+        if let Some(cell_id) = self.cell_order.get(index) {
+            if self.hidden_cells.remove(cell_id) {
+                self.cell_list.splice(index..index + 1, 1);
+            }
+        }
+
+        cx.emit(NotebookEvent::SelectionChanged);
+
+        if jump_to_index {
+            self.jump_to_cell(current_index, cx);
+        }
+    }
+
+    /// Selects the next cell, or the previous one, whose last execution raised an error,
+    /// wrapping around the ends of the notebook.
+    fn go_to_error_cell(&mut self, forward: bool, cx: &mut ViewContext<Self>) {
+        let cell_count = self.cell_order.len();
+        if cell_count == 0 {
+            return;
+        }
+
+        let start = self.selected_cell_index.min(cell_count - 1);
+        let error_cell = (1..=cell_count)
+            .map(|offset| {
+                if forward {
+                    (start + offset) % cell_count
+                } else {
+                    (start + cell_count - offset) % cell_count
+                }
+            })
+            .find_map(|index| {
+                let cell_id = &self.cell_order[index];
+                match self.cell_map.get(cell_id) {
+                    Some(Cell::Code(cell)) if cell.read(cx).has_error() => Some(cell_id.clone()),
+                    _ => None,
+                }
+            });
+
+        if let Some(cell_id) = error_cell {
+            self.reveal_cell(&cell_id, cx);
+        }
+    }
+
+    /// Scrolls to a cell and selects it, leaving the notebook in command mode.
+    pub fn reveal_cell(&mut self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        if let Some(index) = self.cell_order.iter().position(|id| id == cell_id) {
+            self.set_selected_index(index, true, cx);
+            cx.focus(&self.focus_handle);
+            cx.notify();
+        }
+    }
+
+    /// Scrolls to the cell at `cell_index` and selects `range` of its source, e.g. a match found
+    /// by project search. Markdown cells are switched to their source to show the selection.
+    pub fn reveal_source_range(
+        &mut self,
+        cell_index: usize,
+        range: Range<usize>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.is_loading() {
+            self.pending_source_range = Some((cell_index, range));
+            return;
+        }
+        let Some(cell_id) = self.cell_order.get(cell_index) else {
+            return;
+        };
+        let editor = match self.cell_map.get(cell_id) {
+            Some(Cell::Code(cell)) => cell.read(cx).editor().clone(),
+            Some(Cell::Raw(cell)) => cell.read(cx).editor().clone(),
+            Some(Cell::Markdown(cell)) => {
+                cell.update(cx, |cell, cx| cell.start_editing(cx));
+                cell.read(cx).editor().clone()
+            }
+            None => return,
+        };
+
+        self.set_selected_index(cell_index, true, cx);
+        editor.update(cx, |editor, cx| {
+            // The notebook may have changed since it was searched
+            let len = editor.buffer().read(cx).len(cx);
+            let range = range.start.min(len)..range.end.min(len);
+            editor.change_selections(Some(Autoscroll::fit()), cx, |selections| {
+                selections.select_ranges([range]);
+            });
+        });
+        cx.focus_view(&editor);
+        cx.notify();
+    }
+
+    /// The editor showing a cell's source, unless it's a markdown cell that isn't being edited.
+    fn visible_source_editor(&self, cell_id: &CellId, cx: &AppContext) -> Option<View<Editor>> {
+        match self.cell_map.get(cell_id)? {
+            Cell::Markdown(cell) if !cell.read(cx).is_editing() => None,
+            cell => Some(cell.editor(cx)),
+        }
+    }
+
+    /// Selects ranges of a cell's source without focusing it, e.g. to show the find bar's matches.
+    fn select_source_ranges(
+        &self,
+        cell_id: &CellId,
+        ranges: impl IntoIterator<Item = Range<usize>>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(editor) = self.visible_source_editor(cell_id, cx) else {
+            return;
+        };
+        editor.update(cx, |editor, cx| {
+            let len = editor.buffer().read(cx).len(cx);
+            let ranges = ranges
+                .into_iter()
+                .map(|range| range.start.min(len)..range.end.min(len))
+                .collect::<Vec<_>>();
+            editor.change_selections(Some(Autoscroll::fit()), cx, |selections| {
+                selections.select_ranges(ranges);
+            });
+        });
+    }
+
+    /// Replaces matches in the cells' sources with the replacement of `query`, as one operation
+    /// that's undone together. Matches in outputs and in cells that can't be edited are skipped.
+    fn replace_source_matches<'a>(
+        &mut self,
+        matches: impl IntoIterator<Item = &'a NotebookMatch>,
+        query: &SearchQuery,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.is_read_only(cx) {
+            return;
+        }
+
+        let mut transactions = Vec::new();
+        for (cell_id, ranges) in source_ranges_by_cell(matches) {
+            let Some(cell) = self.cell_map.get(&cell_id) else {
+                continue;
+            };
+            if cell.editor(cx).read(cx).read_only(cx) {
+                continue;
+            }
+            let Some(buffer) = cell.buffer(cx) else {
+                continue;
+            };
+            let transaction_id = buffer.update(cx, |buffer, cx| {
+                let len = buffer.len();
+                let edits = ranges
+                    .into_iter()
+                    .filter_map(|range| {
+                        let range = range.start.min(len)..range.end.min(len);
+                        let text = buffer.text_for_range(range.clone()).collect::<String>();
+                        let replacement = query.replacement_for(&text)?;
+                        Some((range, replacement.into_owned()))
+                    })
+                    .collect::<Vec<_>>();
+                if edits.is_empty() {
+                    return None;
+                }
+                // Keep the replacement apart from the edits around it, so it's undone on its own
+                buffer.finalize_last_transaction();
+                buffer.start_transaction();
+                buffer.edit(edits, None, cx);
+                let transaction_id = buffer.end_transaction(cx);
+                buffer.finalize_last_transaction();
+                transaction_id
+            });
+            transactions.extend(transaction_id.map(|transaction_id| (cell_id, transaction_id)));
+        }
+
+        if !transactions.is_empty() {
+            self.push_cell_operation(CellOperation::Replaced(transactions));
+        }
+        cx.notify();
+    }
+
+    /// The headings of markdown cells and the symbols of code cells, in notebook order.
+    fn outline_entries(&self, cx: &AppContext) -> Vec<OutlineEntry> {
+        let mut entries = Vec::new();
+
+        for cell_id in &self.cell_order {
+            match self.cell_map.get(cell_id) {
+                Some(Cell::Markdown(cell)) => {
+                    let source = cell.read(cx).current_source(cx);
+                    entries.extend(markdown_headings(&source).into_iter().map(|(depth, text)| {
+                        OutlineEntry {
+                            cell_id: cell_id.clone(),
+                            text,
+                            depth,
+                            is_heading: true,
+                        }
+                    }));
+                }
+                Some(Cell::Code(cell)) => {
+                    let Some(buffer) = cell
+                        .read(cx)
+                        .editor()
+                        .read(cx)
+                        .buffer()
+                        .read(cx)
+                        .as_singleton()
+                    else {
+                        continue;
+                    };
+                    let Some(outline) = buffer.read(cx).snapshot().outline(None) else {
+                        continue;
+                    };
+                    entries.extend(outline.items.into_iter().map(|item| OutlineEntry {
+                        cell_id: cell_id.clone(),
+                        text: item.text,
+                        depth: item.depth,
+                        is_heading: false,
+                    }));
+                }
+                Some(Cell::Raw(_)) | None => {}
+            }
+        }
+
+        entries
+    }
+
+    /// The language of the notebook's kernel, as saved in its metadata.
+    pub(crate) fn language_name(&self, cx: &AppContext) -> Option<String> {
+        self.notebook_item.read(cx).language_name()
+    }
+
+    /// The name of the notebook's file, as shown in its tab.
+    pub(crate) fn file_name(&self, cx: &AppContext) -> String {
+        let Some(path) = &self.notebook_item.read(cx).path else {
+            return "Untitled".to_string();
+        };
+        path.file_name()
+            .unwrap_or_else(|| path.as_os_str())
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// The worktree the notebook's kernels are picked from: the notebook's own, or the project's
+    /// first one for an untitled notebook.
+    fn worktree_id(&self, cx: &AppContext) -> Option<WorktreeId> {
+        match &self.notebook_item.read(cx).project_path {
+            Some(project_path) => Some(project_path.worktree_id),
+            None => self
+                .project
+                .read(cx)
+                .visible_worktrees(cx)
+                .next()
+                .map(|worktree| worktree.read(cx).id()),
+        }
+    }
+
+    /// Asks for the kernel a new notebook runs on, then opens the notebook untitled. The notebook
+    /// is written wherever the user picks the first time it's saved.
+    fn new_notebook(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+        let project = workspace.project().clone();
+        let worktree_id = project
+            .read(cx)
+            .visible_worktrees(cx)
+            .next()
+            .map(|worktree| worktree.read(cx).id());
+        let kernel_specifications = notebook_kernel_specifications(&project, worktree_id, cx);
+        let workspace_handle = cx.view().downgrade();
+
+        workspace.toggle_modal(cx, |cx| {
+            let delegate = KernelPickerDelegate::new(
+                kernel_specifications,
+                Box::new(move |kernel_specification, cx| {
+                    workspace_handle
+                        .update(cx, |workspace, cx| {
+                            let project = workspace.project().clone();
+                            let languages = project.read(cx).languages().clone();
+                            let notebook_item = cx.new_model(|_| {
+                                NotebookItem::untitled(&kernel_specification, languages)
+                            });
+                            let notebook =
+                                cx.new_view(|cx| NotebookEditor::new(project, notebook_item, cx));
+                            workspace.add_item_to_active_pane(Box::new(notebook), None, true, cx);
+                        })
+                        .log_err();
+                }),
+            );
+            Picker::uniform_list(delegate, cx).max_height(Some(rems(20.).into()))
+        });
+    }
+
+    /// Whether the notebook mirrors one a collaborator has open. Its cells run on the leader's
+    /// machine, so they can only be run from here if the host allows guests to.
+    fn is_remote(&self) -> bool {
+        self.remote_id.is_some()
+    }
+
+    /// Shows the execution state and outputs the leader sent for its code cells.
+    ///
+    /// While following the leader, the view jumps to each cell as it starts executing, so demos
+    /// stay in sync without scrolling along by hand.
+    fn apply_cell_states(&mut self, cells: Vec<proto::NotebookCell>, cx: &mut ViewContext<Self>) {
+        let mut started_cell = None;
+        for cell_state in cells {
+            let Some(cell_id) = cell_id_from_proto(cell_state.id.clone()) else {
+                continue;
+            };
+            if let Some(Cell::Code(cell)) = self.cell_map.get(&cell_id) {
+                let was_executing =
+                    matches!(cell.read(cx).execution_status(), ExecutionStatus::Executing);
+                cell.update(cx, |cell, cx| cell.apply_proto(cell_state, cx));
+                let is_executing =
+                    matches!(cell.read(cx).execution_status(), ExecutionStatus::Executing);
+                if is_executing && !was_executing {
+                    started_cell = Some(cell_id);
+                }
+            }
+        }
+
+        if let Some(cell_id) = started_cell.filter(|_| self.leader_peer_id.is_some()) {
+            self.follow_to_cell(cell_id, cx);
+        }
+        cx.notify();
+    }
+
+    /// Scrolls to a cell the leader started running, and highlights it for a moment.
+    fn follow_to_cell(&mut self, cell_id: CellId, cx: &mut ViewContext<Self>) {
+        let Some(index) = self.cell_order.iter().position(|id| *id == cell_id) else {
+            return;
+        };
+        self.set_selected_index(index, true, cx);
+
+        let highlight_task = cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(FOLLOWED_CELL_HIGHLIGHT_DURATION)
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.highlighted_cell = None;
+                cx.notify();
+            })
+            .ok();
+        });
+        self.highlighted_cell = Some((cell_id, highlight_task));
+        cx.notify();
+    }
+
+    /// The workspace the notebook is open in.
+    fn workspace(&self, cx: &WindowContext) -> Option<View<Workspace>> {
+        cx.window_handle()
+            .downcast::<Workspace>()
+            .and_then(|handle| handle.root(cx).ok())
+    }
+
+    fn toggle_outline(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+
+        let entries = self.outline_entries(cx);
+        let notebook = cx.view().downgrade();
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(cx, |cx| NotebookOutline::new(entries, notebook, cx));
+        });
+    }
+
+    /// Exports the notebook as a PDF document with nbconvert, using the `pdf` or `webpdf` export
+    /// of the `jupyter.nbconvert_exports` setting when there is one, and `webpdf` otherwise.
+    fn export_to_pdf(&mut self, cx: &mut ViewContext<Self>) {
+        let export = JupyterSettings::get_global(cx)
+            .nbconvert_exports
+            .iter()
+            .find(|export| matches!(export.format.as_str(), "pdf" | "webpdf"))
+            .cloned()
+            .unwrap_or_else(NbconvertExport::pdf);
+        self.export_with_nbconvert(export, cx);
+    }
+
+    /// Exports the notebook with the export of the `jupyter.nbconvert_exports` setting for
+    /// `format`. Without a format, the user picks one of the exports.
+    fn export_with_nbconvert_format(&mut self, format: Option<&str>, cx: &mut ViewContext<Self>) {
+        let exports = JupyterSettings::get_global(cx).nbconvert_exports.clone();
+        if let Some(format) = format {
+            match exports.into_iter().find(|export| export.format == format) {
+                Some(export) => self.export_with_nbconvert(export, cx),
+                None => log::error!("no nbconvert export for the {format:?} format"),
+            }
+            return;
+        }
+        if exports.is_empty() {
+            return;
+        }
+
+        let labels = exports
+            .iter()
+            .map(NbconvertExport::label)
+            .collect::<Vec<_>>();
+        let answers = labels
+            .iter()
+            .map(String::as_str)
+            .chain(["Cancel"])
+            .collect::<Vec<_>>();
+        let answer = cx.prompt(
+            gpui::PromptLevel::Info,
+            "Export the notebook as",
+            None,
+            &answers,
+        );
+        cx.spawn(|this, mut cx| async move {
+            let Some(export) = exports.get(answer.await?).cloned() else {
+                return Ok(());
+            };
+            this.update(&mut cx, |this, cx| this.export_with_nbconvert(export, cx))
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Exports the notebook with nbconvert next to the notebook file. Progress and the file that
+    /// was written are shown in the workspace's notifications.
+    fn export_with_nbconvert(&mut self, export: NbconvertExport, cx: &mut ViewContext<Self>) {
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+        let Some(notebook) = self
+            .serialized_notebook(cx)
+            .and_then(|notebook| serialize_notebook(&notebook))
+            .log_err()
+        else {
+            return;
+        };
+        let notebook_path = self.notebook_item.read(cx).path.clone();
+        let output_dir = notebook_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(util::paths::home_dir().as_path())
+            .to_path_buf();
+        let output_name = notebook_path
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map_or_else(
+                || "Untitled".to_string(),
+                |stem| stem.to_string_lossy().to_string(),
+            );
+        let command = JupyterSettings::get_global(cx).nbconvert_command.clone();
+
+        struct NbconvertExportNotification;
+        let notification_id =
+            NotificationId::composite::<NbconvertExportNotification>(cx.view().entity_id());
+        workspace.update(cx, |workspace, cx| {
+            workspace.show_toast(
+                workspace::Toast::new(
+                    notification_id.clone(),
+                    format!("Exporting {} as {}…", self.file_name(cx), export.label()),
+                ),
+                cx,
+            )
+        });
+
+        let workspace = workspace.downgrade();
+        cx.spawn(|_, mut cx| async move {
+            let exported = cx
+                .background_executor()
+                .spawn(run_nbconvert(
+                    command,
+                    export,
+                    notebook,
+                    output_dir,
+                    output_name,
+                ))
+                .await;
+            workspace.update(&mut cx, |workspace, cx| {
+                workspace.dismiss_toast(&notification_id, cx);
+                match exported {
+                    Ok(path) => {
+                        let file_name = path
+                            .file_name()
+                            .unwrap_or(path.as_os_str())
+                            .to_string_lossy()
+                            .to_string();
+                        workspace.show_toast(
+                            workspace::Toast::new(
+                                notification_id,
+                                format!("Exported to {file_name}"),
+                            )
+                            .on_click("Open", move |cx| cx.open_with_system(&path)),
+                            cx,
+                        );
+                    }
+                    Err(error) => {
+                        workspace.show_error(&error.context("Failed to export the notebook"), cx)
+                    }
+                }
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn edit_notebook_metadata(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+
+        let metadata = self.notebook_metadata(cx);
+        let languages = self.languages.clone();
+        let notebook = cx.view().downgrade();
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(cx, |cx| {
+                NotebookMetadataEditor::new(&metadata, languages, notebook, cx)
+            });
+        });
+    }
+
+    /// The notebook's metadata as it's saved, including the keys nbformat doesn't know about.
+    fn notebook_metadata(&self, cx: &AppContext) -> serde_json::Value {
+        let notebook_item = self.notebook_item.read(cx);
+        notebook_item
+            .unknown_fields
+            .notebook_metadata(&notebook_item.notebook.metadata)
+    }
+
+    /// Starts the kernel and runs every cell when the notebook opted in to it and the user trusts
+    /// it, see [`super::auto_run`].
+    fn run_on_open(&mut self, cx: &mut ViewContext<Self>) {
+        if self.is_remote() || self.is_read_only(cx) || self.project.read(cx).is_via_collab() {
+            return;
+        }
+        if !auto_run(&self.notebook_metadata(cx))
+            || !self.notebook_item.read(cx).is_trusted_by_user()
+        {
+            return;
+        }
+        self.run_cells(cx);
+    }
+
+    /// Opts the notebook in or out of running all cells when it's opened. The choice is saved in
+    /// its metadata.
+    fn toggle_auto_run(&mut self, cx: &mut ViewContext<Self>) {
+        let mut value = self.notebook_metadata(cx);
+        set_auto_run(&mut value, !auto_run(&value));
+        let metadata = self.notebook_item.read(cx).notebook.metadata.clone();
+        self.set_notebook_metadata(&value, metadata, cx);
+    }
+
+    /// Applies metadata edited as JSON, where `value` is the metadata as written and `metadata`
+    /// what nbformat parsed from it.
+    pub(crate) fn set_notebook_metadata(
+        &mut self,
+        value: &serde_json::Value,
+        metadata: NotebookMetadata,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.notebook_item.update(cx, |item, _| {
+            item.unknown_fields.set_notebook_metadata(value, &metadata);
+            item.notebook.metadata = metadata;
+        });
+        // The kernelspec or language info may now name another language
+        let notebook_language = self.notebook_item.read(cx).notebook_language();
+        self.notebook_language = cx.spawn(|_, _| notebook_language).shared();
+        self.metadata_modified = true;
+        cx.notify();
+    }
+
+    /// Opens the comment threads of the selected cell, where a new thread can be started.
+    fn toggle_cell_comments(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        cell.comments_menu_handle(cx).toggle(cx);
+    }
+
+    /// Opens the commits that touched the selected cell, when the notebook is tracked by git.
+    fn toggle_cell_blame(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        if cell.has_blame_loader(cx) {
+            cell.blame_menu_handle(cx).toggle(cx);
+        }
+    }
+
+    fn toggle_cell_editable(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        let editable = cell.is_editable(cx);
+        cell.set_editable(!editable, cx);
+    }
+
+    fn toggle_cell_deletable(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        let deletable = cell.is_deletable(cx);
+        cell.set_deletable(!deletable, cx);
+    }
+
+    fn toggle_cell_tags(&mut self, cx: &mut ViewContext<Self>) {
+        if self.is_read_only(cx) {
+            return;
+        }
+        let Some(cell) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        cell.tags_menu_handle(cx).toggle(cx);
+    }
+
+    /// Quotes a code cell into the assistant panel, with its latest outputs and the notebook's
+    /// language.
+    fn ask_assistant(&mut self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) else {
+            return;
+        };
+        let Some(assistant) = <dyn AssistantDelegate>::global(cx) else {
+            return;
+        };
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+
+        let cell = cell.read(cx);
+        let language = self.notebook_item.read(cx).language_name();
+        let context = cell_context(
+            language.as_deref(),
+            cell.execution_count(),
+            &cell.current_source(cx),
+            cell.nbformat_outputs(),
+        );
+        let title = cell_context_title(cell.execution_count());
+        workspace.update(cx, |workspace, cx| {
+            assistant.quote_creases(workspace, vec![(context, title)], cx);
+        });
+    }
+
+    /// Replaces the source of the selected code cell with the code block under the cursor in the
+    /// assistant panel, or the text selected there. The replacement can be undone in the cell.
+    fn insert_assistant_suggestion(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(Cell::Code(cell)) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.cell_map.get(&cell_id))
+        else {
+            return;
+        };
+        let Some(assistant) = <dyn AssistantDelegate>::global(cx) else {
+            return;
+        };
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+        let Some(suggestion) = workspace.update(cx, |workspace, cx| {
+            assistant.selection_or_code_block(workspace, cx)
+        }) else {
+            return;
+        };
+
+        let editor = cell.read(cx).editor().clone();
+        editor.update(cx, |editor, cx| {
+            editor.select_all(&editor::actions::SelectAll, cx);
+            editor.insert(suggestion.trim_end_matches('\n'), cx);
+        });
+        cx.focus_view(&editor);
+    }
+
+    /// Selects a cell without scrolling to it, e.g. when the user clicked into it.
+    fn select_cell(&mut self, cell_id: &CellId, cx: &mut ViewContext<Self>) {
+        if let Some(index) = self.cell_order.iter().position(|id| id == cell_id) {
+            if index != self.selected_cell_index {
+                self.set_selected_index(index, false, cx);
+                cx.notify();
+            }
+        }
+    }
+
+    /// Adds a digit to the count for the next cell motion, as typed in vim mode, e.g. `3 j`.
+    fn push_count(&mut self, digit: usize, _: &mut ViewContext<Self>) {
+        let count = self.pending_count.unwrap_or(0);
+        self.pending_count = Some(count.saturating_mul(10).saturating_add(digit));
+    }
+
+    pub fn select_next(&mut self, _: &menu::SelectNext, cx: &mut ViewContext<Self>) {
+        let steps = self.pending_count.take().unwrap_or(1).max(1);
+        let count = self.cell_count();
+        if count > 0 {
+            let index = self.selected_index();
+            let ix = self
+                .shown_cell_indices(index + 1..count)
+                .take(steps)
+                .last()
+                .unwrap_or(index.min(count - 1));
+            self.set_selected_index(ix, true, cx);
+            cx.notify();
+        }
+    }
+
+    pub fn select_previous(&mut self, _: &menu::SelectPrev, cx: &mut ViewContext<Self>) {
+        let steps = self.pending_count.take().unwrap_or(1).max(1);
+        let count = self.cell_count();
+        if count > 0 {
+            let index = self.selected_index();
+            let ix = self
+                .shown_cell_indices(0..index.min(count))
+                .rev()
+                .take(steps)
+                .last()
+                .unwrap_or(index.min(count - 1));
+            self.set_selected_index(ix, true, cx);
+            cx.notify();
+        }
+    }
+
+    pub fn select_first(&mut self, _: &menu::SelectFirst, cx: &mut ViewContext<Self>) {
+        let count = self.cell_count();
+        if count > 0 {
+            let ix = self.shown_cell_indices(0..count).next().unwrap_or(0);
+            self.set_selected_index(ix, true, cx);
+            cx.notify();
+        }
+    }
+
+    pub fn select_last(&mut self, _: &menu::SelectLast, cx: &mut ViewContext<Self>) {
+        let count = self.cell_count();
+        if count > 0 {
+            let ix = self
+                .shown_cell_indices(0..count)
+                .next_back()
+                .unwrap_or(count - 1);
+            self.set_selected_index(ix, true, cx);
+            cx.notify();
+        }
+    }
+
+    /// The indices in `range` of the cells that aren't hidden by a filter.
+    fn shown_cell_indices(
+        &self,
+        range: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        range.filter(|index| !self.hidden_cells.contains(&self.cell_order[*index]))
+    }
+
+    /// Hides the cells that don't match `filter`, until the filter is cleared.
+    fn filter_cells(&mut self, filter: CellFilter, cx: &mut ViewContext<Self>) {
+        self.hidden_cells = self
+            .cell_order
+            .iter()
+            .filter(|cell_id| {
+                let Some(cell) = self.cell_map.get(*cell_id) else {
+                    return false;
+                };
+                let (cell_type, source) = cell.current_source(cx);
+                let has_error = match cell {
+                    Cell::Code(cell) => cell.read(cx).has_error(),
+                    Cell::Markdown(_) | Cell::Raw(_) => false,
+                };
+                let tags = cell.tags(cx);
+                !filter.matches(&FilteredCell {
+                    cell_type,
+                    has_error,
+                    tags: tags.read(cx).tags(),
+                    source: &source,
+                })
+            })
+            .cloned()
+            .collect();
+        self.cell_filter = Some(filter);
+        self.cell_list.reset(self.cell_order.len());
+
+        let count = self.cell_order.len();
+        let selected = self.selected_cell_index.min(count.saturating_sub(1));
+        let first_shown = self
+            .shown_cell_indices(selected..count)
+            .next()
+            .or_else(|| self.shown_cell_indices(0..selected).next_back());
+        if let Some(index) = first_shown {
+            self.set_selected_index(index, true, cx);
+        }
+        cx.notify();
+    }
+
+    fn clear_cell_filter(&mut self, cx: &mut ViewContext<Self>) {
+        if self.cell_filter.take().is_none() {
+            return;
+        }
+        self.hidden_cells.clear();
+        self.cell_list.reset(self.cell_order.len());
+        self.jump_to_cell(self.selected_cell_index, cx);
+        cx.notify();
+    }
+
+    /// The tags used in the notebook, to filter cells by.
+    fn notebook_tags(&self, cx: &AppContext) -> Vec<String> {
+        let mut tags = self
+            .cell_map
+            .values()
+            .flat_map(|cell| cell.tags(cx).read(cx).tags().to_vec())
+            .collect::<Vec<_>>();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    fn jump_to_cell(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        self.cell_list.scroll_to_reveal_item(index);
+
+        // Cells that were just inserted haven't been measured yet, so reveal the cell again once
+        // the list has been laid out
+        cx.on_next_frame(move |this, cx| {
+            if this.selected_cell_index == index {
+                this.cell_list.scroll_to_reveal_item(index);
+                cx.notify();
+            }
+        });
+    }
+
+    /// A scrollbar for the cell list, with markers for the selected cell, running cells and cells
+    /// that errored. Cells take up equal space on it, and clicking one scrolls to it.
+    fn render_scrollbar(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let cell_count = self.cell_order.len();
+        let position = |index: usize| index as f32 / cell_count.max(1) as f32;
+
+        let visible_start = self.cell_list.logical_scroll_top().item_ix.min(cell_count);
+        let visible_end = (visible_start + self.visible_cells.len().max(1)).min(cell_count);
+        let thumb = (cell_count > 0).then(|| {
+            div()
+                .absolute()
+                .left_0()
+                .top(relative(position(visible_start)))
+                .h(relative(position(visible_end) - position(visible_start)))
+                .w_full()
+                .rounded_sm()
+                .bg(cx.theme().colors().scrollbar_thumb_background)
+        });
+
+        let markers = self
+            .cell_order
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cell_id)| {
+                let code_cell = match self.cell_map.get(cell_id) {
+                    Some(Cell::Code(cell)) => Some(cell.read(cx)),
+                    _ => None,
+                };
+                let color = if index == self.selected_cell_index {
+                    cx.theme().colors().text_accent
+                } else if code_cell.map_or(false, |cell| cell.has_error()) {
+                    cx.theme().status().error
+                } else if code_cell.map_or(false, |cell| {
+                    matches!(cell.execution_status(), ExecutionStatus::Executing)
+                }) {
+                    cx.theme().status().info
+                } else if self.match_cells.contains(cell_id) {
+                    cx.theme().status().warning
+                } else {
+                    return None;
+                };
+
+                Some(
+                    div()
+                        .absolute()
+                        .left_0()
+                        .top(relative(position(index)))
+                        .h(px(SCROLLBAR_MARKER_HEIGHT))
+                        .w_full()
+                        .bg(color),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let segments = (0..cell_count).map(|index| {
+            div()
+                .id(("notebook-scrollbar-cell", index))
+                .flex_1()
+                .w_full()
+                .on_click(cx.listener(move |this, _, cx| {
+                    this.cell_list.scroll_to(ListOffset {
+                        item_ix: index,
+                        offset_in_item: px(0.),
+                    });
+                    cx.notify();
+                }))
+        });
+
+        div()
+            .id("notebook-scrollbar")
+            .relative()
+            .flex_none()
+            .h_full()
+            .w(px(SCROLLBAR_WIDTH))
+            .bg(cx.theme().colors().scrollbar_track_background)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _: &MouseDownEvent, _| this.scrollbar_grab = None),
+            )
+            .on_drag(DraggedScrollbarThumb, |thumb, _, cx| {
+                cx.stop_propagation();
+                cx.new_view(|_| thumb.clone())
+            })
+            .on_drag_move(cx.listener(Self::drag_scrollbar_thumb))
+            .children(thumb)
+            .children(markers)
+            .child(v_flex().size_full().children(segments))
+    }
+
+    /// Scrolls so the thumb stays where it was grabbed under the pointer. Grabbing the track
+    /// outside the thumb centers the thumb on the pointer.
+    fn drag_scrollbar_thumb(
+        &mut self,
+        event: &DragMoveEvent<DraggedScrollbarThumb>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let cell_count = self.cell_order.len();
+        let height = event.bounds.size.height;
+        if cell_count == 0 || height <= px(0.) {
+            return;
+        }
+        let pointer = (event.event.position.y - event.bounds.top()) / height * cell_count as f32;
+        let visible_start = self.cell_list.logical_scroll_top().item_ix.min(cell_count);
+        let visible_len = self.visible_cells.len().max(1) as f32;
+        let grab = *self.scrollbar_grab.get_or_insert_with(|| {
+            let grab = pointer - visible_start as f32;
+            if (0.0..visible_len).contains(&grab) {
+                grab
+            } else {
+                visible_len / 2.
+            }
+        });
+
+        let top = (pointer - grab).clamp(0., (cell_count - 1) as f32);
+        self.cell_list.scroll_to(ListOffset {
+            item_ix: top as usize,
+            offset_in_item: px(0.),
+        });
+        cx.notify();
+    }
+
+    fn button_group(cx: &ViewContext<Self>) -> Div {
+        v_flex()
+            .gap(DynamicSpacing::Base04.rems(cx))
+            .items_center()
+            .w(px(CONTROL_SIZE + 4.0))
+            .overflow_hidden()
+            .rounded(px(5.))
+            .bg(cx.theme().colors().title_bar_background)
+            .p_px()
+            .border_1()
+            .border_color(cx.theme().colors().border)
+    }
+
+    fn render_notebook_control(
+        id: impl Into<SharedString>,
+        icon: IconName,
+        _cx: &ViewContext<Self>,
+    ) -> IconButton {
+        let id: ElementId = ElementId::Name(id.into());
+        IconButton::new(id, icon).width(px(CONTROL_SIZE).into())
+    }
+
+    /// The menu of formats the notebook can be exported to: PDF, and those nbconvert is set up
+    /// to export.
+    fn render_export_menu(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let notebook = cx.view().downgrade();
+
+        PopoverMenu::new("export-menu")
+            .menu(move |cx| {
+                let exports = JupyterSettings::get_global(cx).nbconvert_exports.clone();
+                let notebook = notebook.clone();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    let menu = menu.entry("PDF", Some(Box::new(ExportToPdf)), {
+                        let notebook = notebook.clone();
+                        move |cx| {
+                            notebook
+                                .update(cx, |notebook, cx| notebook.export_to_pdf(cx))
+                                .ok();
+                        }
+                    });
+                    let menu = if exports.is_empty() {
+                        menu
+                    } else {
+                        menu.separator().header("nbconvert")
+                    };
+                    exports.into_iter().fold(menu, |menu, export| {
+                        let notebook = notebook.clone();
+                        menu.entry(export.label(), None, move |cx| {
+                            notebook
+                                .update(cx, |notebook, cx| {
+                                    notebook.export_with_nbconvert(export.clone(), cx)
+                                })
+                                .ok();
+                        })
+                    })
+                }))
+            })
+            .trigger(
+                Self::render_notebook_control("export-menu-trigger", IconName::FileDoc, cx)
+                    .tooltip(|cx| Tooltip::text("Export", cx)),
+            )
+    }
+
+    fn render_filter_menu(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let notebook = cx.view().downgrade();
+        let is_filtered = self.cell_filter.is_some();
+
+        PopoverMenu::new("filter-menu")
+            .menu(move |cx| {
+                let notebook = notebook.upgrade()?;
+                let tags = notebook.read(cx).notebook_tags(cx);
+                let find_query = notebook.read(cx).find_query.clone();
+                let notebook = notebook.downgrade();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    let filter_entry =
+                        |menu: ContextMenu, label: SharedString, filter: CellFilter| {
+                            let notebook = notebook.clone();
+                            menu.entry(label, None, move |cx| {
+                                notebook
+                                    .update(cx, |notebook, cx| {
+                                        notebook.filter_cells(filter.clone(), cx)
+                                    })
+                                    .ok();
+                            })
+                        };
+                    let menu = menu
+                        .action("Cells with Errors", Box::new(FilterErrorCells))
+                        .action("Code Cells", Box::new(FilterCodeCells));
+                    let menu = match find_query {
+                        Some(query) => filter_entry(
+                            menu,
+                            format!("Matching “{}”", query.as_str()).into(),
+                            CellFilter::Query(query),
+                        ),
+                        None => menu,
+                    };
+                    let menu = if tags.is_empty() {
+                        menu
+                    } else {
+                        menu.separator().header("Tags")
+                    };
+                    tags.into_iter()
+                        .fold(menu, |menu, tag| {
+                            filter_entry(menu, tag.clone().into(), CellFilter::Tag(tag))
+                        })
+                        .when(is_filtered, |menu| {
+                            menu.separator()
+                                .action("Show All Cells", Box::new(ClearCellFilter))
+                        })
+                }))
+            })
+            .trigger(
+                Self::render_notebook_control("filter-menu-trigger", IconName::Filter, cx)
+                    .selected(is_filtered)
+                    .tooltip(|cx| Tooltip::text("Filter cells", cx)),
+            )
+    }
+
+    fn render_notebook_controls(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let read_only = self.is_read_only(cx);
+        let has_outputs = self.has_outputs(cx);
+        let is_shared = !self.is_remote() && self.project.read(cx).is_shared();
+        let guests_can_run = self.guests_can_run;
+        let search_outputs = self.search_outputs;
+        let can_move_up = !read_only && self.selected_cell_index > 0;
+        let can_move_down = !read_only && self.selected_cell_index + 1 < self.cell_order.len();
+
+        v_flex()
+            .max_w(px(CONTROL_SIZE + 4.0))
+            .items_center()
+            .gap(DynamicSpacing::Base16.rems(cx))
+            .justify_between()
+            .flex_none()
+            .h_full()
+            .py(DynamicSpacing::Base12.px(cx))
+            .child(
+                v_flex()
+                    .gap(DynamicSpacing::Base08.rems(cx))
+                    .child(
+                        Self::button_group(cx)
+                            .child(
+                                Self::render_notebook_control("run-all-cells", IconName::Play, cx)
+                                    .disabled(read_only)
+                                    .tooltip(move |cx| {
+                                        Tooltip::for_action("Execute all cells", &RunAll, cx)
+                                    })
+                                    .on_click(|_, cx| {
+                                        cx.dispatch_action(Box::new(RunAll));
+                                    }),
+                            )
+                            .child(
+                                Self::render_notebook_control(
+                                    "clear-all-outputs",
+                                    IconName::ListX,
+                                    cx,
+                                )
+                                .disabled(read_only || !has_outputs)
+                                .tooltip(move |cx| {
+                                    Tooltip::for_action("Clear all outputs", &ClearOutputs, cx)
+                                })
+                                .on_click(|_, cx| {
+                                    cx.dispatch_action(Box::new(ClearOutputs));
+                                }),
+                            ),
+                    )
+                    .child(
+                        Self::button_group(cx)
+                            .child(
+                                Self::render_notebook_control(
+                                    "move-cell-up",
+                                    IconName::ArrowUp,
+                                    cx,
+                                )
+                                .disabled(!can_move_up)
+                                .tooltip(move |cx| {
+                                    Tooltip::for_action("Move cell up", &MoveCellUp, cx)
+                                })
+                                .on_click(|_, cx| {
+                                    cx.dispatch_action(Box::new(MoveCellUp));
+                                }),
+                            )
+                            .child(
+                                Self::render_notebook_control(
+                                    "move-cell-down",
+                                    IconName::ArrowDown,
+                                    cx,
+                                )
+                                .disabled(!can_move_down)
+                                .tooltip(move |cx| {
+                                    Tooltip::for_action("Move cell down", &MoveCellDown, cx)
+                                })
+                                .on_click(|_, cx| {
+                                    cx.dispatch_action(Box::new(MoveCellDown));
+                                }),
+                            ),
+                    )
+                    .child(
+                        Self::button_group(cx)
+                            .child(
+                                Self::render_notebook_control(
+                                    "new-markdown-cell",
+                                    IconName::Plus,
+                                    cx,
+                                )
+                                .disabled(read_only)
+                                .tooltip(move |cx| {
+                                    Tooltip::for_action("Add markdown block", &AddMarkdownBlock, cx)
+                                })
+                                .on_click(|_, cx| {
+                                    cx.dispatch_action(Box::new(AddMarkdownBlock));
+                                }),
+                            )
+                            .child(
+                                Self::render_notebook_control("new-code-cell", IconName::Code, cx)
+                                    .disabled(read_only)
+                                    .tooltip(move |cx| {
+                                        Tooltip::for_action("Add code block", &AddCodeBlock, cx)
+                                    })
+                                    .on_click(|_, cx| {
+                                        cx.dispatch_action(Box::new(AddCodeBlock));
+                                    }),
+                            ),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .gap(DynamicSpacing::Base08.rems(cx))
+                    .items_center()
+                    .child(
+                        Self::render_notebook_control("read-only", IconName::FileLock, cx)
+                            .selected(read_only)
+                            // Only the notebook's own read-only mode can be turned off from here
+                            .disabled(read_only && !self.read_only)
+                            .tooltip(move |cx| {
+                                let title = if read_only {
+                                    "Make notebook editable"
+                                } else {
+                                    "Make notebook read-only"
+                                };
+                                Tooltip::for_action(title, &ToggleReadOnly, cx)
+                            })
+                            .on_click(|_, cx| {
+                                cx.dispatch_action(Box::new(ToggleReadOnly));
+                            }),
+                    )
+                    .when(is_shared, |this| {
+                        this.child(
+                            Self::render_notebook_control(
+                                "guest-execution",
+                                IconName::UserGroup,
+                                cx,
+                            )
+                            .selected(guests_can_run)
+                            .tooltip(move |cx| {
+                                let title = if guests_can_run {
+                                    "Stop guests from running cells"
+                                } else {
+                                    "Let guests run cells"
+                                };
+                                Tooltip::for_action(title, &ToggleGuestExecution, cx)
+                            })
+                            .on_click(|_, cx| {
+                                cx.dispatch_action(Box::new(ToggleGuestExecution));
+                            }),
+                        )
+                    })
+                    .child(
+                        Self::render_notebook_control(
+                            "search-outputs",
+                            IconName::MagnifyingGlass,
+                            cx,
+                        )
+                        .selected(search_outputs)
+                        .tooltip(move |cx| {
+                            let title = if search_outputs {
+                                "Only find in cell sources"
+                            } else {
+                                "Find in outputs too"
+                            };
+                            Tooltip::for_action(title, &ToggleSearchOutputs, cx)
+                        })
+                        .on_click(|_, cx| {
+                            cx.dispatch_action(Box::new(ToggleSearchOutputs));
+                        }),
+                    )
+                    .child(
+                        Self::render_notebook_control("present", IconName::Screen, cx)
+                            .tooltip(move |cx| {
+                                Tooltip::for_action("Present slideshow", &StartPresentation, cx)
+                            })
+                            .on_click(|_, cx| {
+                                cx.dispatch_action(Box::new(StartPresentation));
+                            }),
+                    )
+                    .child(self.render_filter_menu(cx))
+                    .child(self.render_export_menu(cx))
+                    .child(Self::render_notebook_control(
+                        "more-menu",
+                        IconName::Ellipsis,
+                        cx,
+                    ))
+                    .child(
+                        Self::button_group(cx)
+                            .child(IconButton::new("repl", IconName::ReplNeutral)),
+                    ),
+            )
+    }
+
+    /// The cells shown at the current step of the presentation, with controls to page through it.
+    fn render_presentation(
+        &self,
+        presentation: &Presentation,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let step = presentation.step;
+        let step_count = presentation.steps.len();
+        let cells = presentation.steps[step]
+            .iter()
+            .filter_map(|cell_id| match self.cell_map.get(cell_id)? {
+                Cell::Code(cell) => {
+                    cell.update(cx, |cell, _| {
+                        cell.set_selected(false);
+                    });
+                    Some(cell.clone().into_any_element())
+                }
+                Cell::Markdown(cell) => {
+                    cell.update(cx, |cell, _| {
+                        cell.set_selected(false);
+                    });
+                    Some(cell.clone().into_any_element())
+                }
+                Cell::Raw(cell) => {
+                    cell.update(cx, |cell, _| {
+                        cell.set_selected(false);
+                    });
+                    Some(cell.clone().into_any_element())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        v_flex()
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .child(
+                v_flex()
+                    .id("notebook-presentation")
+                    .flex_1()
+                    .w_full()
+                    .overflow_y_scroll()
+                    .child(
+                        v_flex()
+                            .w_full()
+                            .max_w(rems(64.))
+                            .mx_auto()
+                            .py(DynamicSpacing::Base32.px(cx))
+                            .text_size(TextSize::Large.rems(cx))
+                            .children(cells),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .w_full()
+                    .justify_end()
+                    .gap_1()
+                    .p_2()
+                    .child(
+                        Label::new(format!("{} / {}", step + 1, step_count))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        IconButton::new("previous-slide", IconName::ChevronLeft)
+                            .disabled(step == 0)
+                            .tooltip(|cx| Tooltip::for_action("Previous slide", &PreviousSlide, cx))
+                            .on_click(|_, cx| cx.dispatch_action(Box::new(PreviousSlide))),
+                    )
+                    .child(
+                        IconButton::new("next-slide", IconName::ChevronRight)
+                            .disabled(step + 1 == step_count)
+                            .tooltip(|cx| Tooltip::for_action("Next slide", &NextSlide, cx))
+                            .on_click(|_, cx| cx.dispatch_action(Box::new(NextSlide))),
+                    )
+                    .child(
+                        IconButton::new("stop-presentation", IconName::Close)
+                            .tooltip(|cx| {
+                                Tooltip::for_action("Stop presenting", &StopPresentation, cx)
+                            })
+                            .on_click(|_, cx| cx.dispatch_action(Box::new(StopPresentation))),
+                    ),
+            )
+    }
+
+    fn cell_position(&self, index: usize) -> CellPosition {
+        match index {
+            0 => CellPosition::First,
+            index if index == self.cell_count() - 1 => CellPosition::Last,
+            _ => CellPosition::Middle,
+        }
+    }
+
+    fn render_cell(
+        &self,
+        index: usize,
+        cell: &Cell,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let cell_position = self.cell_position(index);
+
+        let is_selected = index == self.selected_cell_index;
+        let is_highlighted = self
+            .highlighted_cell
+            .as_ref()
+            .is_some_and(|(cell_id, _)| self.cell_order.get(index) == Some(cell_id));
+
+        let cell_element = match cell {
+            Cell::Code(cell) => {
+                cell.update(cx, |cell, _cx| {
+                    cell.set_selected(is_selected)
+                        .set_cell_position(cell_position);
+                });
+                cell.clone().into_any_element()
+            }
+            Cell::Markdown(cell) => {
+                cell.update(cx, |cell, _cx| {
+                    cell.set_selected(is_selected)
+                        .set_cell_position(cell_position);
+                });
+                cell.clone().into_any_element()
+            }
+            Cell::Raw(cell) => {
+                cell.update(cx, |cell, _cx| {
+                    cell.set_selected(is_selected)
+                        .set_cell_position(cell_position);
+                });
+                cell.clone().into_any_element()
+            }
+        };
+
+        div()
+            .relative()
+            .child(cell_element)
+            .when(is_highlighted, |this| {
+                this.child(
+                    div()
+                        .absolute()
+                        .inset_0()
+                        .rounded_sm()
+                        .border_1()
+                        .border_color(cx.theme().colors().border_focused)
+                        .bg(cx.theme().colors().border_focused.opacity(0.08)),
+                )
+            })
+    }
+}
+
+impl Render for NotebookEditor {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let read_only = self.is_read_only(cx);
+
+        div()
+            .key_context(self.dispatch_context(cx))
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(|this, &OpenNotebook, cx| this.open_notebook(&OpenNotebook, cx)))
+            .on_action(cx.listener(|this, &EditCell, cx| this.edit_selected_cell(cx)))
+            .on_action(cx.listener(|this, &GoToNextErrorCell, cx| this.go_to_error_cell(true, cx)))
+            .on_action(
+                cx.listener(|this, &GoToPreviousErrorCell, cx| this.go_to_error_cell(false, cx)),
+            )
+            .on_action(
+                cx.listener(|this, &editor::actions::ToggleOutline, cx| this.toggle_outline(cx)),
+            )
+            .on_action(cx.listener(|this, PushCount(digit), cx| this.push_count(*digit, cx)))
+            .on_action(cx.listener(|this, &EnterCommandMode, cx| this.enter_command_mode(cx)))
+            .on_action(cx.listener(|this, &CopyCell, cx| {
+                this.copy_selected_cell(cx);
+            }))
+            .on_action(cx.listener(|this, action: &ExportWithNbconvert, cx| {
+                this.export_with_nbconvert_format(action.format.as_deref(), cx)
+            }))
+            .on_action(cx.listener(|this, &CopyCellAsMarkdown, cx| {
+                if let Some(cell_id) = this.selected_cell_id() {
+                    this.copy_cell_as_markdown(&cell_id, false, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, &CopyCellWithOutputAsMarkdown, cx| {
+                if let Some(cell_id) = this.selected_cell_id() {
+                    this.copy_cell_as_markdown(&cell_id, true, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, &RevertCellToHead, cx| {
+                if let Some(cell_id) = this.selected_cell_id() {
+                    this.revert_cell_to_head(&cell_id, false, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, &RevertCellAndOutputsToHead, cx| {
+                if let Some(cell_id) = this.selected_cell_id() {
+                    this.revert_cell_to_head(&cell_id, true, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, &ClearExecutionQueue, cx| this.clear_execution_queue(cx)))
+            .on_action(cx.listener(|this, &AskAssistant, cx| {
+                if let Some(cell_id) = this.selected_cell_id() {
+                    this.ask_assistant(&cell_id, cx);
+                }
+            }))
+            .on_action(
+                cx.listener(|this, &ToggleGuestExecution, cx| this.toggle_guest_execution(cx)),
+            )
+            .on_action(cx.listener(|this, &ToggleCellComments, cx| this.toggle_cell_comments(cx)))
+            .on_action(cx.listener(|this, &ShowCellBlame, cx| this.toggle_cell_blame(cx)))
+            .on_action(cx.listener(|this, &ToggleCellTags, cx| this.toggle_cell_tags(cx)))
+            .on_action(cx.listener(|this, &ToggleReadOnly, cx| this.toggle_read_only(cx)))
+            .on_action(cx.listener(|this, &ToggleSearchOutputs, cx| this.toggle_search_outputs(cx)))
+            .on_action(cx.listener(|this, &ToggleLineNumbers, cx| this.toggle_line_numbers(cx)))
+            .on_action(
+                cx.listener(|this, &ToggleCellLineNumbers, cx| this.toggle_cell_line_numbers(cx)),
+            )
+            .on_action(
+                cx.listener(|this, &FilterErrorCells, cx| {
+                    this.filter_cells(CellFilter::Errors, cx)
+                }),
+            )
+            .on_action(
+                cx.listener(|this, &FilterCodeCells, cx| this.filter_cells(CellFilter::Code, cx)),
+            )
+            .on_action(cx.listener(|this, &ClearCellFilter, cx| this.clear_cell_filter(cx)))
+            .on_action(cx.listener(|this, &TrustNotebook, cx| this.trust_notebook(cx)))
+            .on_action(cx.listener(|this, &LoadLargeOutputs, cx| this.load_deferred_outputs(cx)))
+            .on_action(cx.listener(|this, &StartPresentation, cx| this.start_presentation(cx)))
+            .on_action(cx.listener(|this, &ExportToPdf, cx| this.export_to_pdf(cx)))
+            .on_action(cx.listener(|this, &StopPresentation, cx| this.stop_presentation(cx)))
+            .on_action(cx.listener(|this, &NextSlide, cx| this.go_to_slide_step(true, cx)))
+            .on_action(cx.listener(|this, &PreviousSlide, cx| this.go_to_slide_step(false, cx)))
+            // Cells that were running when the notebook became read-only can still be stopped
+            .on_action(cx.listener(|this, &InterruptKernel, cx| this.interrupt_kernel(cx)))
+            // Everything that edits, runs or rearranges cells
+            .when(!read_only, |this| {
+                this.on_action(cx.listener(|this, &ClearOutputs, cx| this.clear_outputs(cx)))
+                    .on_action(cx.listener(|this, &EditNotebookMetadata, cx| {
+                        this.edit_notebook_metadata(cx)
+                    }))
+                    .on_action(cx.listener(|this, &ToggleAutoRun, cx| this.toggle_auto_run(cx)))
+                    .on_action(
+                        cx.listener(|this, &ToggleCellEditable, cx| this.toggle_cell_editable(cx)),
+                    )
+                    .on_action(
+                        cx.listener(|this, &ToggleCellDeletable, cx| {
+                            this.toggle_cell_deletable(cx)
+                        }),
+                    )
+                    .on_action(
+                        cx.listener(|this, &ClearCellOutput, cx| {
+                            this.clear_selected_cell_output(cx)
+                        }),
+                    )
+                    .on_action(cx.listener(|this, &ToggleOutputsCollapsed, cx| {
+                        this.toggle_selected_outputs_collapsed(cx)
+                    }))
+                    .on_action(cx.listener(|this, &ToggleOutputsScrolled, cx| {
+                        this.toggle_selected_outputs_scrolled(cx)
+                    }))
+                    .on_action(
+                        cx.listener(|this, &InsertCellAbove, cx| this.insert_code_cell(true, cx)),
+                    )
+                    .on_action(
+                        cx.listener(|this, &InsertCellBelow, cx| this.insert_code_cell(false, cx)),
+                    )
+                    .on_action(cx.listener(|this, &DeleteCell, cx| this.delete_selected_cell(cx)))
+                    .on_action(cx.listener(|this, &CutCell, cx| this.cut_selected_cell(cx)))
+                    .on_action(cx.listener(|this, &PasteCellBelow, cx| this.paste_cells(false, cx)))
+                    .on_action(cx.listener(|this, &PasteCellAbove, cx| this.paste_cells(true, cx)))
+                    .on_action(cx.listener(|this, &ConvertToCode, cx| {
+                        this.convert_selected_cell(CellType::Code, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ConvertToMarkdown, cx| {
+                        this.convert_selected_cell(CellType::Markdown, cx)
+                    }))
+                    .on_action(cx.listener(|this, &ConvertToRaw, cx| {
+                        this.convert_selected_cell(CellType::Raw, cx)
+                    }))
+                    .on_action(
+                        cx.listener(|this, &UndoCellOperation, cx| this.undo_cell_operation(cx)),
+                    )
+                    .on_action(cx.listener(|this, &RunCell, cx| this.run_selected_cell(cx)))
+                    .on_action(
+                        cx.listener(|this, &RunAndInsertBelow, cx| this.run_and_insert_below(cx)),
+                    )
+                    .on_action(
+                        cx.listener(|this, &RunAndSelectBelow, cx| this.run_and_select_below(cx)),
+                    )
+                    .on_action(cx.listener(|this, &RunAll, cx| this.run_cells(cx)))
+                    .on_action(cx.listener(|this, &RunAllAbove, cx| this.run_cells_above(cx)))
+                    .on_action(cx.listener(|this, &RunAllBelow, cx| this.run_cells_below(cx)))
+                    .on_action(cx.listener(|this, &RestartKernel, cx| this.restart_kernel(cx)))
+                    .on_action(cx.listener(|this, &MoveCellUp, cx| this.move_cell_up(cx)))
+                    .on_action(cx.listener(|this, &MoveCellDown, cx| this.move_cell_down(cx)))
+                    .on_action(
+                        cx.listener(|this, &AddMarkdownBlock, cx| this.add_markdown_block(cx)),
+                    )
+                    .on_action(cx.listener(|this, &AddCodeBlock, cx| this.add_code_block(cx)))
+                    .on_action(cx.listener(|this, &InsertAssistantSuggestion, cx| {
+                        this.insert_assistant_suggestion(cx)
+                    }))
+            })
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_previous))
+            .on_action(cx.listener(Self::select_first))
+            .on_action(cx.listener(Self::select_last))
+            .flex()
+            .items_start()
+            .size_full()
+            .overflow_hidden()
+            .px(DynamicSpacing::Base12.px(cx))
+            .gap(DynamicSpacing::Base12.px(cx))
+            .bg(cx.theme().colors().tab_bar_background)
+            .map(|this| match &self.presentation {
+                Some(presentation) => this.child(self.render_presentation(presentation, cx)),
+                None => this
+                    .child(
+                        v_flex()
+                            .id("notebook-cells")
+                            .flex_1()
+                            .size_full()
+                            .overflow_y_scroll()
+                            .children(self.render_loading_indicator(cx))
+                            .children(self.render_trust_banner(cx))
+                            .children(self.render_kernel_banner(cx))
+                            .children(self.render_filter_banner(cx))
+                            .child(list(self.cell_list.clone()).size_full()),
+                    )
+                    .child(self.render_scrollbar(cx))
+                    .child(self.render_notebook_controls(cx)),
+            })
+    }
+}
+
+impl FocusableView for NotebookEditor {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+// Intended to be a NotebookBuffer
+pub struct NotebookItem {
+    /// Where the notebook is saved, or `None` for a new notebook that hasn't been saved yet.
+    path: Option<PathBuf>,
+    project_path: Option<ProjectPath>,
+    languages: Arc<LanguageRegistry>,
+    // Raw notebook data
+    notebook: nbformat::v4::Notebook,
+    unknown_fields: UnknownFields,
+    /// Output payloads that are loaded when they're shown, or when the notebook is saved.
+    deferred_outputs: Arc<DeferredOutputs>,
+    /// Where outputs are spilled to when the notebook's outputs outgrow the memory limit.
+    spill_dir: Arc<OutputSpillDir>,
+    /// Reads the notebook from disk. The notebook is empty until it's done.
+    load_task: Option<Task<()>>,
+    load_error: Option<SharedString>,
+    // Store our version of the notebook in memory (cell_order, cell_map)
+    id: Option<ProjectEntryId>,
+}
+
+impl project::Item for NotebookItem {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<gpui::Result<Model<Self>>>> {
+        let path = path.clone();
+        let project = project.clone();
+        let fs = project.read(cx).fs().clone();
+        let languages = project.read(cx).languages().clone();
+
+        if path.path.extension().unwrap_or_default() == "ipynb"
+            || is_markdown_notebook_path(&path.path)
+        {
+            Some(cx.spawn(|mut cx| async move {
+                let abs_path = project
+                    .read_with(&cx, |project, cx| project.absolute_path(&path, cx))?
+                    .ok_or_else(|| anyhow::anyhow!("Failed to find the absolute path"))?;
+
+                let id = project
+                    .update(&mut cx, |project, cx| project.entry_for_path(&path, cx))?
+                    .context("Entry not found")?
+                    .id;
+
+                // The notebook opens right away, and its cells appear once it's been parsed
+                // todo: watch for changes to the file
+                cx.new_model(|cx| {
+                    let load_task = cx.spawn({
+                        let abs_path = abs_path.clone();
+                        |item, mut cx| async move {
+                            let executor = cx.background_executor().clone();
+                            let loaded =
+                                NotebookItem::load_notebook(fs.as_ref(), &abs_path, executor).await;
+                            item.update(&mut cx, |item, cx| {
+                                match loaded {
+                                    Ok((notebook, unknown_fields, deferred_outputs)) => {
+                                        item.notebook = notebook;
+                                        item.unknown_fields = unknown_fields;
+                                        item.deferred_outputs = Arc::new(deferred_outputs);
+                                    }
+                                    Err(error) => {
+                                        log::error!("failed to open notebook: {error:?}");
+                                        item.load_error = Some(format!("{error:#}").into());
+                                    }
+                                }
+                                item.load_task = None;
+                                cx.notify();
+                            })
+                            .ok();
+                        }
+                    });
+
+                    NotebookItem {
+                        path: Some(abs_path),
+                        project_path: Some(path),
+                        languages,
+                        notebook: NotebookItem::empty_notebook(),
+                        unknown_fields: UnknownFields::default(),
+                        deferred_outputs: Arc::default(),
+                        spill_dir: Arc::new(OutputSpillDir::new()),
+                        load_task: Some(load_task),
+                        load_error: None,
+                        id: Some(id),
+                    }
+                })
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        self.id
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        self.project_path.clone()
+    }
+}
+
+impl NotebookItem {
+    /// Loads a notebook from disk, reading back in any outputs stored outside of it, along with
+    /// the fields nbformat drops so they can be saved again. Large output payloads are set aside
+    /// until they're shown, see [`super::lazy_outputs`].
+    ///
+    /// Parsing is done on the background executor, since large notebooks take long enough to
+    /// parse that they'd freeze the UI.
+    async fn load_notebook(
+        fs: &dyn Fs,
+        abs_path: &Path,
+        executor: BackgroundExecutor,
+    ) -> Result<(nbformat::v4::Notebook, UnknownFields, DeferredOutputs)> {
+        let file_content = fs.load(abs_path).await?;
+        if is_markdown_notebook_path(abs_path) {
+            let notebook = parse_quarto(&file_content, default_language(abs_path))?;
+            return Ok((
+                notebook,
+                UnknownFields::default(),
+                DeferredOutputs::default(),
+            ));
+        }
+
+        let parsed = executor
+            .spawn(async move {
+                // Large output payloads are set aside, so only the rest of the notebook is parsed
+                let (mut value, deferred_outputs) =
+                    match DeferredOutputs::parse(&file_content, LAZY_OUTPUT_MIN_SIZE) {
+                        Ok(parsed) => parsed,
+                        // Let the notebook parser report what's wrong with the file
+                        Err(_) => {
+                            let notebook = Self::parse_notebook(&file_content)?;
+                            return Ok(Ok((
+                                notebook,
+                                UnknownFields::default(),
+                                DeferredOutputs::default(),
+                            )));
+                        }
+                    };
+                // The file isn't needed anymore, and can be hundreds of megabytes
+                drop(file_content);
+                resolve_duplicates(&mut value, &deferred_outputs);
+                let references = external_references(&mut value);
+                if !references.is_empty() {
+                    return Ok(Err((value, deferred_outputs, references)));
+                }
+
+                let notebook = Self::parse_notebook(&value.to_string())?;
+                let unknown_fields = UnknownFields::capture(&value, &notebook);
+                anyhow::Ok(Ok((notebook, unknown_fields, deferred_outputs)))
+            })
+            .await?;
+        let (mut value, deferred_outputs, references) = match parsed {
+            Ok(loaded) => return Ok(loaded),
+            Err(external) => external,
+        };
+
+        // Outputs stored next to the notebook are read back in before it's parsed
+        let mut files = HashMap::default();
+        for reference in references {
+            let path = reference_path(abs_path, &reference);
+            if let Some(bytes) = fs.load_bytes(&path).await.log_err() {
+                files.insert(reference, bytes);
+            }
+        }
+        executor
+            .spawn(async move {
+                rehydrate(&mut value, &files);
+                let notebook = Self::parse_notebook(&value.to_string())?;
+                let unknown_fields = UnknownFields::capture(&value, &notebook);
+                Ok((notebook, unknown_fields, deferred_outputs))
+            })
+            .await
+    }
+
+    /// A new notebook with one empty code cell, set up to run on `kernel_specification`.
+    fn untitled(
+        kernel_specification: &KernelSpecification,
+        languages: Arc<LanguageRegistry>,
+    ) -> Self {
+        let name = kernel_specification.name();
+        let language = kernel_specification.language();
+        let notebook = serde_json::from_value(serde_json::json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {
+                "kernelspec": {
+                    "name": name,
+                    "display_name": name,
+                    "language": language,
+                },
+                "language_info": { "name": language },
+            },
+            "cells": [{
+                "id": uuid::Uuid::new_v4().to_string(),
+                "cell_type": "code",
+                "metadata": {},
+                "source": [],
+                "execution_count": null,
+                "outputs": [],
+            }],
+        }))
+        .expect("a new notebook is valid");
+
+        Self {
+            path: None,
+            project_path: None,
+            languages,
+            notebook,
+            unknown_fields: UnknownFields::default(),
+            deferred_outputs: Arc::default(),
+            spill_dir: Arc::new(OutputSpillDir::new()),
+            load_task: None,
+            load_error: None,
+            id: None,
+        }
+    }
+
+    /// A notebook without cells, standing in for the notebook while it's loading.
+    fn empty_notebook() -> nbformat::v4::Notebook {
+        serde_json::from_value(serde_json::json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {},
+            "cells": []
+        }))
+        .expect("an empty notebook is valid")
+    }
+
+    /// Whether the notebook is still being read from disk.
+    pub fn is_loading(&self) -> bool {
+        self.load_task.is_some()
+    }
+
+    /// Why the notebook couldn't be read, if it couldn't.
+    pub fn load_error(&self) -> Option<&SharedString> {
+        self.load_error.as_ref()
+    }
+
+    fn parse_notebook(file_content: &str) -> Result<nbformat::v4::Notebook> {
+        match nbformat::parse_notebook(file_content) {
+            Ok(nbformat::Notebook::V4(notebook)) => Ok(notebook),
+            // 4.1 - 4.4 are converted to 4.5
+            Ok(nbformat::Notebook::Legacy(legacy_notebook)) => {
+                // todo!(): Decide if we want to mutate the notebook by including Cell IDs
+                // and any other conversions
+                let notebook = nbformat::upgrade_legacy_notebook(legacy_notebook)?;
+                Ok(notebook)
+            }
+            // Bad notebooks and notebooks v4.0 and below are not supported
+            Err(e) => {
+                anyhow::bail!("Failed to parse notebook: {:?}", e);
+            }
+        }
+    }
+
+    /// The signature of the notebook as it was loaded or last saved, see [`super::trust`].
+    fn signature(&self) -> Option<String> {
+        let mut value = serde_json::to_value(&self.notebook).log_err()?;
+        restore_spilled_outputs(&mut value);
+        self.unknown_fields.restore(&mut value);
+        self.deferred_outputs.restore(&mut value);
+        notebook_signature(&value)
+    }
+
+    /// Whether the notebook's saved outputs can be shown in full: either none of them need
+    /// trust, or the user trusted the notebook as it is.
+    fn is_trusted(&self) -> bool {
+        !has_untrusted_outputs(&self.notebook) || self.is_trusted_by_user()
+    }
+
+    /// Whether the user trusted the notebook, or saved it in Zed, and it hasn't changed since.
+    fn is_trusted_by_user(&self) -> bool {
+        self.signature()
+            .and_then(|signature| NOTEBOOK_DB.trusted_notebook(signature).log_err().flatten())
+            .is_some()
+    }
+
+    pub fn language_name(&self) -> Option<String> {
+        self.notebook
+            .metadata
+            .language_info
+            .as_ref()
+            .map(|l| l.name.clone())
+            .or(self
+                .notebook
+                .metadata
+                .kernelspec
+                .as_ref()
+                .and_then(|spec| spec.language.clone()))
+    }
+
+    /// The language to highlight code cells with, based on the notebook's `language_info` and
+    /// falling back to its kernelspec.
+    pub fn notebook_language(&self) -> impl Future<Output = Option<Arc<Language>>> {
+        let metadata = &self.notebook.metadata;
+        let mut candidates = metadata
+            .language_info
+            .as_ref()
+            .map(|info| info.name.clone())
+            .into_iter()
+            .chain(
+                metadata
+                    .kernelspec
+                    .as_ref()
+                    .and_then(|spec| spec.language.clone()),
+            )
+            .map(|name| zed_language_name(&name).to_string())
+            .collect::<Vec<_>>();
+        candidates.dedup();
+        let languages = self.languages.clone();
+
+        async move {
+            for name in candidates {
+                if let Ok(language) = languages.language_for_name_or_extension(&name).await {
+                    return Some(language);
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Maps the language names kernels report to the names of Zed's languages. Matching is
+/// case-insensitive, so only names that differ by more than case need an entry.
+fn zed_language_name(kernel_language: &str) -> &str {
+    match kernel_language.to_lowercase().as_str() {
+        "python3" | "ipython" | "ipython3" => "Python",
+        "javascript" | "node" | "nodejs" => "JavaScript",
+        "typescript" | "deno" => "TypeScript",
+        "c++" | "c++11" | "c++14" | "c++17" | "c++20" | "xcpp" => "C++",
+        "bash" | "sh" | "zsh" => "Shell Script",
+        "r" => "R",
+        _ => kernel_language,
+    }
+}
+
+/// A notebook as it's written to disk, with the fields nbformat couldn't parse, the output
+/// payloads that haven't been loaded and the outputs spilled to disk put back in.
+fn saved_notebook(
+    notebook: &nbformat::v4::Notebook,
+    unknown_fields: &UnknownFields,
+    deferred_outputs: &DeferredOutputs,
+) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(notebook)?;
+    restore_spilled_outputs(&mut value);
+    unknown_fields.restore(&mut value);
+    deferred_outputs.restore(&mut value);
+    Ok(value)
+}
+
+/// The notebook being saved, with the outputs of its cells still shared with the cells.
+struct NotebookSnapshot {
+    /// The notebook without its cells.
+    notebook: nbformat::v4::Notebook,
+    cells: Vec<CellSnapshot>,
+}
+
+impl NotebookSnapshot {
+    fn into_nbformat(self) -> nbformat::v4::Notebook {
+        let mut notebook = self.notebook;
+        notebook.cells = self
+            .cells
+            .into_iter()
+            .map(CellSnapshot::into_nbformat_cell)
+            .collect();
+        notebook
+    }
+}
+
+/// Serializes a notebook the way Jupyter writes it: one space indentation and a trailing newline.
+fn serialize_notebook(notebook: &impl Serialize) -> Result<String> {
+    let mut contents = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+    notebook.serialize(&mut serializer)?;
+    contents.push(b'\n');
+    Ok(String::from_utf8(contents)?)
+}
+
+/// Writes the outputs moved out of a notebook to its outputs directory.
+async fn write_external_outputs(
+    fs: &dyn Fs,
+    notebook_path: &Path,
+    files: HashMap<String, Vec<u8>>,
+) -> Result<()> {
+    for (reference, bytes) in files {
+        let path = reference_path(notebook_path, &reference);
+        // Files are named after their contents, so an existing one is already up to date
+        if fs.is_file(&path).await {
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs.create_dir(dir).await?;
+        }
+        let mut content = futures::io::Cursor::new(bytes);
+        fs.create_file_with(&path, Pin::new(&mut content)).await?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub enum NotebookEvent {
+    /// The execution state or outputs of a code cell changed, which is sent to followers.
+    CellExecutionChanged(CellId),
+    /// The cell list scrolled, which is saved with the workspace.
+    Scrolled,
+    /// Another cell was selected, which is saved with the workspace.
+    SelectionChanged,
+    /// The host allowed or stopped guests running cells, which is sent to followers.
+    GuestExecutionChanged,
+    /// The notebook was made read-only or editable, which is saved with the workspace.
+    ReadOnlyChanged,
+    /// Line numbers were shown or hidden in the notebook, which is saved with the workspace.
+    LineNumbersChanged,
+}
+
+impl EventEmitter<NotebookEvent> for NotebookEditor {}
+
+// pub struct NotebookControls {
+//     pane_focused: bool,
+//     active_item: Option<Box<dyn ItemHandle>>,
+//     // subscription: Option<Subscription>,
+// }
+
+// impl NotebookControls {
+//     pub fn new() -> Self {
+//         Self {
+//             pane_focused: false,
+//             active_item: Default::default(),
+//             // subscription: Default::default(),
+//         }
+//     }
+// }
+
+// impl EventEmitter<ToolbarItemEvent> for NotebookControls {}
+
+// impl Render for NotebookControls {
+//     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+//         div().child("notebook controls")
+//     }
+// }
+
+// impl ToolbarItemView for NotebookControls {
+//     fn set_active_pane_item(
+//         &mut self,
+//         active_pane_item: Option<&dyn workspace::ItemHandle>,
+//         cx: &mut ViewContext<Self>,
+//     ) -> workspace::ToolbarItemLocation {
+//         cx.notify();
+//         self.active_item = None;
+
+//         let Some(item) = active_pane_item else {
+//             return ToolbarItemLocation::Hidden;
+//         };
+
+//         ToolbarItemLocation::PrimaryLeft
+//     }
+
+//     fn pane_focus_update(&mut self, pane_focused: bool, _: &mut ViewContext<Self>) {
+//         self.pane_focused = pane_focused;
+//     }
+// }
+
+impl Item for NotebookEditor {
+    type Event = NotebookEvent;
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: Option<workspace::WorkspaceId>,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<gpui::View<Self>>
+    where
+        Self: Sized,
+    {
+        Some(cx.new_view(|cx| Self::new(self.project.clone(), self.notebook_item.clone(), cx)))
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(EntityId, &dyn project::Item),
+    ) {
+        f(self.notebook_item.entity_id(), self.notebook_item.read(cx))
+    }
+
+    fn is_singleton(&self, _cx: &AppContext) -> bool {
+        true
+    }
+
+    fn tab_content(&self, params: TabContentParams, cx: &WindowContext) -> AnyElement {
+        Label::new(self.file_name(cx))
+            .single_line()
+            .color(params.text_color())
+            .italic(params.preview)
+            .into_any_element()
+    }
+
+    fn tab_icon(&self, _cx: &ui::WindowContext) -> Option<Icon> {
+        Some(IconName::Book.into())
+    }
+
+    fn show_toolbar(&self) -> bool {
+        false
+    }
+
+    // TODO
+    fn pixel_position_of_cursor(&self, _: &AppContext) -> Option<Point<Pixels>> {
+        None
+    }
+
+    fn as_searchable(&self, handle: &View<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(handle.clone()))
+    }
+
+    fn set_nav_history(&mut self, _: workspace::ItemNavHistory, _: &mut ViewContext<Self>) {
+        // TODO
+    }
+
+    fn can_save(&self, cx: &AppContext) -> bool {
+        // Untitled notebooks are saved as, which asks where to save them
+        self.notebook_item.read(cx).path.is_some()
+    }
+
+    fn save(
+        &mut self,
+        _format: bool,
+        project: Model<Project>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let Some(abs_path) = self.notebook_item.read(cx).path.clone() else {
+            return Task::ready(Err(anyhow::anyhow!("The notebook hasn't been saved yet")));
+        };
+        self.write_notebook(project, abs_path, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        project: Model<Project>,
+        path: ProjectPath,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let Some(abs_path) = project.read(cx).absolute_path(&path, cx) else {
+            return Task::ready(Err(anyhow::anyhow!("Failed to find the absolute path")));
+        };
+
+        let save = self.write_notebook(project.clone(), abs_path.clone(), cx);
+        cx.spawn(|this, mut cx| async move {
+            save.await?;
+            this.update(&mut cx, |this, cx| {
+                let entry_id = project
+                    .read(cx)
+                    .entry_for_path(&path, cx)
+                    .map(|entry| entry.id);
+                this.notebook_item.update(cx, |item, _| {
+                    item.path = Some(abs_path);
+                    item.project_path = Some(path);
+                    item.id = entry_id.or(item.id);
+                });
+                // A notebook saved for the first time can now have its document
+                if this.cell_document_buffer.is_none() {
+                    this.open_cell_document(cx);
+                }
+                this.refresh_committed_cells(cx);
+                cx.notify();
+            })
+        })
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        // The notebook being loaded is already the latest
+        if self.is_loading() {
+            return Task::ready(Ok(()));
+        }
+        let Some(abs_path) = self.notebook_item.read(cx).path.clone() else {
+            return Task::ready(Ok(()));
+        };
+        let fs = project.read(cx).fs().clone();
+
+        cx.spawn(|this, mut cx| async move {
+            let executor = cx.background_executor().clone();
+            let (notebook, unknown_fields, deferred_outputs) =
+                NotebookItem::load_notebook(fs.as_ref(), &abs_path, executor).await?;
+
+            this.update(&mut cx, |this, cx| {
+                // Replies for the cells being replaced have nowhere to go, so nothing may be
+                // left waiting on them
+                this.abandon_pending_executions(cx);
+
+                this.notebook_item.update(cx, |item, _| {
+                    item.notebook = notebook.clone();
+                    item.unknown_fields = unknown_fields;
+                    item.deferred_outputs = Arc::new(deferred_outputs);
+                });
+                // The file may now declare a different language
+                let notebook_language = this.notebook_item.read(cx).notebook_language();
+                this.notebook_language = cx.spawn(|_, _| notebook_language).shared();
+                // Changes made elsewhere have to be trusted again
+                this.trusted = this.notebook_item.read(cx).is_trusted();
+
+                let (cell_order, cell_map, cell_subscriptions) = Self::load_cells(
+                    &notebook,
+                    &this.languages,
+                    this.notebook_language.clone(),
+                    this.trusted,
+                    cx,
+                );
+
+                let previous_cell_count = this.cell_order.len();
+                this.cell_order = cell_order;
+                this.cell_map = cell_map;
+                this.cell_subscriptions = cell_subscriptions;
+                for cell in this.cell_map.values() {
+                    cell.set_read_only(this.cells_read_only, cx);
+                    this.style_cell_editor(cell, cx);
+                }
+                for cell_id in this.cell_order.clone() {
+                    this.update_cell_git_status(&cell_id, cx);
+                }
+                this.saved_cells = this.cell_entity_ids();
+                this.metadata_modified = false;
+                this.undo_stack.clear();
+                this.cell_list
+                    .splice(0..previous_cell_count, this.cell_order.len());
+                this.selected_cell_index = this
+                    .selected_cell_index
+                    .min(this.cell_order.len().saturating_sub(1));
+                // The document may need another extension for the new language
+                this.cell_document_buffer = None;
+                this.open_cell_document(cx);
+                cx.notify();
+            })
+        })
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.is_dirty(cx)
+    }
+}
+
+impl EventEmitter<SearchEvent> for NotebookEditor {}
+
+impl SearchableItem for NotebookEditor {
+    type Match = NotebookMatch;
+
+    fn supported_options() -> SearchOptions {
+        SearchOptions {
+            case: true,
+            word: true,
+            regex: true,
+            replacement: true,
+            selection: false,
+        }
+    }
+
+    fn clear_matches(&mut self, cx: &mut ViewContext<Self>) {
+        self.find_query = None;
+        self.match_cells.clear();
+        cx.notify();
+        for cell in self.cell_map.values() {
+            cell.editor(cx).update(cx, |editor, cx| {
+                editor.clear_background_highlights::<NotebookSearchHighlights>(cx);
+            });
+        }
+    }
+
+    fn update_matches(&mut self, matches: &[NotebookMatch], cx: &mut ViewContext<Self>) {
+        self.match_cells = matches.iter().map(|mat| mat.cell_id.clone()).collect();
+        cx.notify();
+        let mut ranges_by_cell = source_ranges_by_cell(matches);
+
+        for (cell_id, cell) in &self.cell_map {
+            let ranges = ranges_by_cell.remove(cell_id).unwrap_or_default();
+            cell.editor(cx).update(cx, |editor, cx| {
+                if ranges.is_empty() {
+                    editor.clear_background_highlights::<NotebookSearchHighlights>(cx);
+                    return;
+                }
+                let snapshot = editor.buffer().read(cx).snapshot(cx);
+                let len = snapshot.len();
+                let ranges = ranges
+                    .into_iter()
+                    .map(|range| {
+                        snapshot.anchor_before(range.start.min(len))
+                            ..snapshot.anchor_after(range.end.min(len))
+                    })
+                    .collect::<Vec<_>>();
+                editor.highlight_background::<NotebookSearchHighlights>(
+                    &ranges,
+                    |theme| theme.search_match_background,
+                    cx,
+                );
+            });
+        }
+    }
+
+    fn query_suggestion(&mut self, cx: &mut ViewContext<Self>) -> String {
+        let Some(editor) = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.visible_source_editor(&cell_id, cx))
+        else {
+            return String::new();
+        };
+        editor.update(cx, |editor, cx| editor.query_suggestion(cx))
+    }
+
+    fn activate_match(
+        &mut self,
+        index: usize,
+        matches: &[NotebookMatch],
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(mat) = matches.get(index) else {
+            return;
+        };
+        let Some(cell_index) = self.cell_order.iter().position(|id| *id == mat.cell_id) else {
+            return;
+        };
+        self.set_selected_index(cell_index, true, cx);
+        if let MatchLocation::Source(range) = &mat.location {
+            self.select_source_ranges(&mat.cell_id, [range.clone()], cx);
+        }
+        cx.notify();
+    }
+
+    fn select_matches(&mut self, matches: &[NotebookMatch], cx: &mut ViewContext<Self>) {
+        let ranges_by_cell = source_ranges_by_cell(matches);
+        for (cell_id, ranges) in ranges_by_cell {
+            self.select_source_ranges(&cell_id, ranges, cx);
+        }
+    }
+
+    fn replace(&mut self, mat: &NotebookMatch, query: &SearchQuery, cx: &mut ViewContext<Self>) {
+        self.replace_source_matches([mat], query, cx);
+    }
+
+    fn replace_all(
+        &mut self,
+        matches: &mut dyn Iterator<Item = &NotebookMatch>,
+        query: &SearchQuery,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.replace_source_matches(matches, query, cx);
+    }
+
+    fn find_matches(
+        &mut self,
+        query: Arc<SearchQuery>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Vec<NotebookMatch>> {
+        self.find_query = (!query.is_empty()).then(|| query.clone());
+        let cells = self
+            .cell_order
+            .iter()
+            .filter_map(|cell_id| {
+                let cell = self.cell_map.get(cell_id)?;
+                let (_, source) = cell.current_source(cx);
+                let outputs = self.search_outputs.then(|| cell.output_texts(cx)).flatten();
+                Some((cell_id.clone(), source, outputs))
+            })
+            .collect::<Vec<_>>();
+        cx.background_executor().spawn(async move {
+            cells
+                .iter()
+                .flat_map(|(cell_id, source, outputs)| {
+                    cell_matches(&query, cell_id, source, outputs.as_deref())
+                })
+                .collect()
+        })
+    }
+
+    fn active_match_index(
+        &mut self,
+        matches: &[NotebookMatch],
+        cx: &mut ViewContext<Self>,
+    ) -> Option<usize> {
+        if matches.is_empty() {
+            return None;
+        }
+        let cell_indices = self
+            .cell_order
+            .iter()
+            .enumerate()
+            .map(|(index, cell_id)| (cell_id, index))
+            .collect::<HashMap<_, _>>();
+        let selected = self.selected_cell_index;
+        let cursor = self
+            .selected_cell_id()
+            .and_then(|cell_id| self.visible_source_editor(&cell_id, cx))
+            .map_or(0, |editor| {
+                editor.update(cx, |editor, cx| {
+                    editor.selections.newest::<usize>(cx).head()
+                })
+            });
+
+        // The first match at or after the cursor in the selected cell, or in a later cell
+        let index = matches.iter().position(|mat| {
+            let Some(&cell_index) = cell_indices.get(&mat.cell_id) else {
+                return false;
+            };
+            match &mat.location {
+                _ if cell_index != selected => cell_index > selected,
+                MatchLocation::Source(range) => range.end >= cursor,
+                MatchLocation::Output(_) => true,
+            }
+        });
+        Some(index.unwrap_or(0))
+    }
+}
+
+impl NotebookEditor {
+    /// Selects and scrolls back to the cells a notebook was left at, if they're still there.
+    fn restore_position(
+        &mut self,
+        selected_cell_id: Option<CellId>,
+        scroll_top: Option<(CellId, f32)>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.is_loading() {
+            self.pending_position = Some((selected_cell_id, scroll_top));
+            return;
+        }
+        let index_of = |cell_id: &CellId| self.cell_order.iter().position(|id| id == cell_id);
+
+        if let Some(index) = selected_cell_id.as_ref().and_then(index_of) {
+            self.set_selected_index(index, false, cx);
+        }
+        if let Some((index, offset)) =
+            scroll_top.and_then(|(cell_id, offset)| Some((index_of(&cell_id)?, offset)))
+        {
+            self.cell_list.scroll_to(ListOffset {
+                item_ix: index,
+                offset_in_item: px(offset),
+            });
+        }
+        cx.notify();
+    }
+}
+
+impl SerializableItem for NotebookEditor {
+    fn serialized_item_kind() -> &'static str {
+        "NotebookEditor"
+    }
+
+    fn deserialize(
+        project: Model<Project>,
+        _workspace: WeakView<Workspace>,
+        workspace_id: WorkspaceId,
+        item_id: ItemId,
+        cx: &mut ViewContext<Pane>,
+    ) -> Task<Result<View<Self>>> {
+        cx.spawn(|_pane, mut cx| async move {
+            let (
+                notebook_path,
+                selected_cell_id,
+                scroll_top_cell_id,
+                scroll_offset,
+                read_only,
+                show_line_numbers,
+            ) = NOTEBOOK_DB
+                .get_notebook(item_id, workspace_id)?
+                .context("No notebook found")?;
+
+            let (worktree, relative_path) = project
+                .update(&mut cx, |project, cx| {
+                    project.find_or_create_worktree(notebook_path, false, cx)
+                })?
+                .await
+                .context("Path not found")?;
+            let project_path = ProjectPath {
+                worktree_id: worktree.update(&mut cx, |worktree, _| worktree.id())?,
+                path: relative_path.into(),
+            };
+
+            let notebook_item = cx
+                .update(|cx| {
+                    <NotebookItem as project::Item>::try_open(&project, &project_path, cx)
+                })?
+                .context("Not a notebook")?
+                .await?;
+
+            let selected_cell_id = selected_cell_id.and_then(cell_id_from_proto);
+            let scroll_top = scroll_top_cell_id
+                .and_then(cell_id_from_proto)
+                .map(|cell_id| (cell_id, scroll_offset));
+            cx.update(|cx| {
+                cx.new_view(|cx| {
+                    let mut editor = Self::new(project, notebook_item, cx);
+                    editor.set_read_only(read_only, cx);
+                    editor.set_show_line_numbers(show_line_numbers, cx);
+                    editor.restore_position(selected_cell_id, scroll_top, cx);
+                    editor
+                })
+            })
+        })
+    }
+
+    fn cleanup(
+        workspace_id: WorkspaceId,
+        alive_items: Vec<ItemId>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<()>> {
+        cx.spawn(|_| NOTEBOOK_DB.delete_unloaded_items(workspace_id, alive_items))
+    }
+
+    fn serialize(
+        &mut self,
+        workspace: &mut Workspace,
+        item_id: ItemId,
+        _closing: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        // Followers see the leader's notebook, which isn't theirs to reopen
+        if self.is_remote() {
+            return None;
+        }
+        let workspace_id = workspace.database_id()?;
+        // Untitled notebooks have nowhere to be reopened from
+        let notebook_path = self.notebook_item.read(cx).path.clone()?;
+
+        let selected_cell_id = self
+            .cell_order
+            .get(self.selected_cell_index)
+            .map(cell_id_to_proto);
+        let scroll_top = self.cell_list.logical_scroll_top();
+        let scroll_top_cell_id = self
+            .cell_order
+            .get(scroll_top.item_ix)
+            .map(cell_id_to_proto);
+        let scroll_offset = scroll_top.offset_in_item.0;
+        let read_only = self.read_only;
+        let show_line_numbers = self.show_line_numbers;
+
+        Some(cx.background_executor().spawn(async move {
+            NOTEBOOK_DB
+                .save_notebook(
+                    item_id,
+                    workspace_id,
+                    notebook_path,
+                    selected_cell_id,
+                    scroll_top_cell_id,
+                    scroll_offset,
+                    read_only,
+                    show_line_numbers,
+                )
+                .await
+        }))
+    }
+
+    fn should_serialize(&self, event: &Self::Event) -> bool {
+        matches!(
+            event,
+            NotebookEvent::Scrolled
+                | NotebookEvent::SelectionChanged
+                | NotebookEvent::ReadOnlyChanged
+                | NotebookEvent::LineNumbersChanged
+        )
+    }
+}
+
+impl FollowableItem for NotebookEditor {
+    fn remote_id(&self) -> Option<ViewId> {
+        self.remote_id
+    }
 
-        if path.path.extension().unwrap_or_default() == "ipynb" {
-            Some(cx.spawn(|mut cx| async move {
-                let abs_path = project
-                    .read_with(&cx, |project, cx| project.absolute_path(&path, cx))?
-                    .ok_or_else(|| anyhow::anyhow!("Failed to find the absolute path"))?;
+    fn to_state_proto(&self, cx: &WindowContext) -> Option<proto::view::Variant> {
+        // Followers would get an incomplete notebook
+        if self.is_loading() {
+            return None;
+        }
+        // Followers open the notebook by its path, which untitled notebooks don't have yet
+        let project_path = self.notebook_item.read(cx).project_path.clone()?;
+        let content = self
+            .serialized_notebook(cx)
+            .and_then(|notebook| serialize_notebook(&notebook))
+            .log_err()?;
+        let cells = self
+            .cell_map
+            .values()
+            .filter_map(|cell| match cell {
+                Cell::Code(cell) => Some(cell.read(cx).to_proto()),
+                _ => None,
+            })
+            .collect();
 
-                // todo: watch for changes to the file
-                let file_content = fs.load(&abs_path.as_path()).await?;
-                let notebook = nbformat::parse_notebook(&file_content);
-
-                let notebook = match notebook {
-                    Ok(nbformat::Notebook::V4(notebook)) => notebook,
-                    // 4.1 - 4.4 are converted to 4.5
-                    Ok(nbformat::Notebook::Legacy(legacy_notebook)) => {
-                        // todo!(): Decide if we want to mutate the notebook by including Cell IDs
-                        // and any other conversions
-                        let notebook = nbformat::upgrade_legacy_notebook(legacy_notebook)?;
-                        notebook
-                    }
-                    // Bad notebooks and notebooks v4.0 and below are not supported
-                    Err(e) => {
-                        anyhow::bail!("Failed to parse notebook: {:?}", e);
-                    }
-                };
+        Some(proto::view::Variant::Notebook(proto::view::Notebook {
+            worktree_id: project_path.worktree_id.to_proto(),
+            path: project_path.path.to_string_lossy().to_string(),
+            content,
+            cells,
+            guests_can_run: self.guests_can_run,
+        }))
+    }
+
+    fn from_state_proto(
+        workspace: View<Workspace>,
+        id: ViewId,
+        state: &mut Option<proto::view::Variant>,
+        cx: &mut WindowContext,
+    ) -> Option<Task<Result<View<Self>>>> {
+        let proto::view::Variant::Notebook(_) = state.as_ref()? else {
+            return None;
+        };
+        let Some(proto::view::Variant::Notebook(state)) = state.take() else {
+            unreachable!()
+        };
+
+        let project = workspace.read(cx).project().clone();
+        let project_path = ProjectPath {
+            worktree_id: WorktreeId::from_proto(state.worktree_id),
+            path: Path::new(&state.path).into(),
+        };
 
+        Some(cx.spawn(|mut cx| async move {
+            // The file is on the leader's machine, so the notebook comes with the state
+            let content = state.content.clone();
+            let (notebook, unknown_fields) = cx
+                .background_executor()
+                .spawn(async move {
+                    let notebook = NotebookItem::parse_notebook(&content)?;
+                    let unknown_fields = serde_json::from_str(&content)
+                        .map(|value| UnknownFields::capture(&value, &notebook))
+                        .unwrap_or_default();
+                    anyhow::Ok((notebook, unknown_fields))
+                })
+                .await?;
+            let notebook_item = project.update(&mut cx, |project, cx| {
                 let id = project
-                    .update(&mut cx, |project, cx| project.entry_for_path(&path, cx))?
+                    .entry_for_path(&project_path, cx)
                     .context("Entry not found")?
                     .id;
-
-                cx.new_model(|_| NotebookItem {
-                    path: abs_path,
-                    project_path: path,
+                let path = project
+                    .absolute_path(&project_path, cx)
+                    .unwrap_or_else(|| project_path.path.to_path_buf());
+                let languages = project.languages().clone();
+                anyhow::Ok(cx.new_model(|_| NotebookItem {
+                    path: Some(path),
+                    project_path: Some(project_path),
                     languages,
                     notebook,
-                    id,
-                })
-            }))
-        } else {
-            None
-        }
+                    unknown_fields,
+                    deferred_outputs: Arc::default(),
+                    spill_dir: Arc::new(OutputSpillDir::new()),
+                    load_task: None,
+                    load_error: None,
+                    id: Some(id),
+                }))
+            })??;
+
+            cx.new_view(|cx| {
+                let mut editor = NotebookEditor::new(project, notebook_item, cx);
+                editor.remote_id = Some(id);
+                editor.guests_can_run = state.guests_can_run;
+                editor.apply_cell_states(state.cells, cx);
+                editor
+            })
+        }))
     }
 
-    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
-        Some(self.id)
+    fn to_follow_event(_event: &Self::Event) -> Option<FollowEvent> {
+        None
     }
 
-    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
-        Some(self.project_path.clone())
+    fn add_event_to_update_proto(
+        &self,
+        event: &Self::Event,
+        update: &mut Option<proto::update_view::Variant>,
+        cx: &WindowContext,
+    ) -> bool {
+        let cell = match event {
+            NotebookEvent::CellExecutionChanged(cell_id) => {
+                let Some(Cell::Code(cell)) = self.cell_map.get(cell_id) else {
+                    return false;
+                };
+                Some(cell.read(cx).to_proto())
+            }
+            NotebookEvent::GuestExecutionChanged => None,
+            NotebookEvent::Scrolled
+            | NotebookEvent::SelectionChanged
+            | NotebookEvent::ReadOnlyChanged
+            | NotebookEvent::LineNumbersChanged => return false,
+        };
+
+        let update =
+            update.get_or_insert_with(|| proto::update_view::Variant::Notebook(Default::default()));
+        match update {
+            proto::update_view::Variant::Notebook(update) => {
+                if let Some(cell) = cell {
+                    // Only the latest state of a cell matters when several changes are sent at once
+                    update.cells.retain(|existing| existing.id != cell.id);
+                    update.cells.push(cell);
+                } else {
+                    update.guests_can_run = Some(self.guests_can_run);
+                }
+                true
+            }
+            _ => false,
+        }
     }
-}
 
-impl NotebookItem {
-    pub fn language_name(&self) -> Option<String> {
-        self.notebook
-            .metadata
-            .language_info
-            .as_ref()
-            .map(|l| l.name.clone())
-            .or(self
-                .notebook
-                .metadata
-                .kernelspec
-                .as_ref()
-                .and_then(|spec| spec.language.clone()))
+    fn apply_update_proto(
+        &mut self,
+        _project: &Model<Project>,
+        message: proto::update_view::Variant,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let proto::update_view::Variant::Notebook(message) = message else {
+            return Task::ready(Ok(()));
+        };
+        if let Some(guests_can_run) = message.guests_can_run {
+            self.guests_can_run = guests_can_run;
+        }
+        self.apply_cell_states(message.cells, cx);
+        Task::ready(Ok(()))
     }
 
-    pub fn notebook_language(&self) -> impl Future<Output = Option<Arc<Language>>> {
-        let language_name = self.language_name();
-        let languages = self.languages.clone();
+    fn is_project_item(&self, _cx: &WindowContext) -> bool {
+        true
+    }
 
-        async move {
-            if let Some(language_name) = language_name {
-                languages.language_for_name(&language_name).await.ok()
-            } else {
-                None
-            }
+    fn set_leader_peer_id(&mut self, leader_peer_id: Option<PeerId>, cx: &mut ViewContext<Self>) {
+        self.leader_peer_id = leader_peer_id;
+        if leader_peer_id.is_none() {
+            self.highlighted_cell = None;
+            cx.notify();
+        }
+    }
+
+    fn dedup(&self, existing: &Self, cx: &WindowContext) -> Option<Dedup> {
+        // Untitled notebooks are never the same notebook
+        let project_path = self.notebook_item.read(cx).project_path.as_ref()?;
+        if existing.notebook_item.read(cx).project_path.as_ref() == Some(project_path) {
+            Some(Dedup::KeepExisting)
+        } else {
+            None
         }
     }
 }
 
-impl EventEmitter<()> for NotebookEditor {}
+impl ProjectItem for NotebookEditor {
+    type Item = NotebookItem;
 
-// pub struct NotebookControls {
-//     pane_focused: bool,
-//     active_item: Option<Box<dyn ItemHandle>>,
-//     // subscription: Option<Subscription>,
-// }
+    fn for_project_item(
+        project: Model<Project>,
+        item: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(project, item, cx)
+    }
+}
 
-// impl NotebookControls {
-//     pub fn new() -> Self {
-//         Self {
-//             pane_focused: false,
-//             active_item: Default::default(),
-//             // subscription: Default::default(),
-//         }
-//     }
-// }
+#[cfg(test)]
+mod test {
+    use futures::channel::mpsc;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::FakeFs;
+    use runtimelib::{KernelInfoReply, Status, StreamContent};
+    use serde_json::json;
 
-// impl EventEmitter<ToolbarItemEvent> for NotebookControls {}
+    use super::*;
 
-// impl Render for NotebookControls {
-//     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-//         div().child("notebook controls")
-//     }
-// }
+    #[derive(Debug)]
+    struct FakeKernel {
+        request_tx: mpsc::Sender<JupyterMessage>,
+        working_directory: PathBuf,
+        execution_state: ExecutionState,
+        kernel_info: Option<KernelInfoReply>,
+    }
 
-// impl ToolbarItemView for NotebookControls {
-//     fn set_active_pane_item(
-//         &mut self,
-//         active_pane_item: Option<&dyn workspace::ItemHandle>,
-//         cx: &mut ViewContext<Self>,
-//     ) -> workspace::ToolbarItemLocation {
-//         cx.notify();
-//         self.active_item = None;
+    impl RunningKernel for FakeKernel {
+        fn request_tx(&self) -> mpsc::Sender<JupyterMessage> {
+            self.request_tx.clone()
+        }
 
-//         let Some(item) = active_pane_item else {
-//             return ToolbarItemLocation::Hidden;
-//         };
+        fn working_directory(&self) -> &PathBuf {
+            &self.working_directory
+        }
 
-//         ToolbarItemLocation::PrimaryLeft
-//     }
+        fn execution_state(&self) -> &ExecutionState {
+            &self.execution_state
+        }
 
-//     fn pane_focus_update(&mut self, pane_focused: bool, _: &mut ViewContext<Self>) {
-//         self.pane_focused = pane_focused;
-//     }
-// }
+        fn set_execution_state(&mut self, state: ExecutionState) {
+            self.execution_state = state;
+        }
 
-impl Item for NotebookEditor {
-    type Event = ();
+        fn kernel_info(&self) -> Option<&KernelInfoReply> {
+            self.kernel_info.as_ref()
+        }
 
-    fn clone_on_split(
-        &self,
-        _workspace_id: Option<workspace::WorkspaceId>,
-        cx: &mut ViewContext<Self>,
-    ) -> Option<gpui::View<Self>>
-    where
-        Self: Sized,
-    {
-        Some(cx.new_view(|cx| Self::new(self.project.clone(), self.notebook_item.clone(), cx)))
+        fn set_kernel_info(&mut self, info: KernelInfoReply) {
+            self.kernel_info = Some(info);
+        }
+
+        /// Closes the request channel, the way a stopped process would.
+        fn force_shutdown(&mut self) -> Result<()> {
+            self.request_tx.close_channel();
+            Ok(())
+        }
+
+        fn connection_path(&self) -> Option<&PathBuf> {
+            None
+        }
     }
 
-    fn for_each_project_item(
-        &self,
-        cx: &AppContext,
-        f: &mut dyn FnMut(gpui::EntityId, &dyn project::Item),
-    ) {
-        f(self.notebook_item.entity_id(), self.notebook_item.read(cx))
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings = SettingsStore::test(cx);
+            cx.set_global(settings);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            client::init_settings(cx);
+            editor::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            JupyterSettings::register(cx);
+            NotebookSettings::register(cx);
+        });
     }
 
-    fn is_singleton(&self, _cx: &AppContext) -> bool {
-        true
+    /// Opens an untitled notebook with a code cell for each of `sources`.
+    async fn open_notebook<'a>(
+        sources: &[&str],
+        cx: &'a mut TestAppContext,
+    ) -> (View<NotebookEditor>, &'a mut VisualTestContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, [], cx).await;
+        let cells = sources
+            .iter()
+            .enumerate()
+            .map(|(index, source)| {
+                json!({
+                    "cell_type": "code",
+                    "id": format!("cell-{index}"),
+                    "metadata": {},
+                    "source": source,
+                    "execution_count": null,
+                    "outputs": []
+                })
+            })
+            .collect::<Vec<_>>();
+        let notebook = NotebookItem::parse_notebook(
+            &json!({ "nbformat": 4, "nbformat_minor": 5, "metadata": {}, "cells": cells })
+                .to_string(),
+        )
+        .unwrap();
+        let languages = project.read_with(cx, |project, _| project.languages().clone());
+        let notebook_item = cx.new_model(|_| NotebookItem {
+            path: None,
+            project_path: None,
+            languages,
+            notebook,
+            unknown_fields: UnknownFields::default(),
+            deferred_outputs: Arc::default(),
+            spill_dir: Arc::new(OutputSpillDir::new()),
+            load_task: None,
+            load_error: None,
+            id: None,
+        });
+        let (editor, cx) = cx.add_window_view(|cx| NotebookEditor::new(project, notebook_item, cx));
+        cx.run_until_parked();
+        (editor, cx)
     }
 
-    fn tab_content(&self, params: TabContentParams, cx: &WindowContext) -> AnyElement {
-        let path = &self.notebook_item.read(cx).path;
-        let title = path
-            .file_name()
-            .unwrap_or_else(|| path.as_os_str())
-            .to_string_lossy()
-            .to_string();
-        Label::new(title)
-            .single_line()
-            .color(params.text_color())
-            .italic(params.preview)
-            .into_any_element()
+    /// Connects the notebook to a kernel that only records the requests it's sent.
+    fn connect_fake_kernel(
+        editor: &View<NotebookEditor>,
+        cx: &mut VisualTestContext,
+    ) -> mpsc::Receiver<JupyterMessage> {
+        let (request_tx, request_rx) = mpsc::channel(100);
+        editor.update(cx, |editor, _| {
+            editor.kernel = Kernel::RunningKernel(Box::new(FakeKernel {
+                request_tx,
+                working_directory: PathBuf::from("/"),
+                execution_state: ExecutionState::Idle,
+                kernel_info: None,
+            }));
+        });
+        request_rx
     }
 
-    fn tab_icon(&self, _cx: &ui::WindowContext) -> Option<Icon> {
-        Some(IconName::Book.into())
+    /// The code of the execute requests the kernel was sent since the last call.
+    fn sent_code(request_rx: &mut mpsc::Receiver<JupyterMessage>) -> Vec<(JupyterMessage, String)> {
+        let mut sent = Vec::new();
+        while let Ok(Some(message)) = request_rx.try_next() {
+            if let JupyterMessageContent::ExecuteRequest(request) = &message.content {
+                let code = request.code.clone();
+                sent.push((message, code));
+            }
+        }
+        sent
     }
 
-    fn show_toolbar(&self) -> bool {
-        false
+    fn idle(request: &JupyterMessage) -> JupyterMessage {
+        JupyterMessage::new(
+            Status {
+                execution_state: ExecutionState::Idle,
+            },
+            Some(request),
+        )
     }
 
-    // TODO
-    fn pixel_position_of_cursor(&self, _: &AppContext) -> Option<Point<Pixels>> {
-        None
+    fn execution_status(
+        editor: &View<NotebookEditor>,
+        index: usize,
+        cx: &mut VisualTestContext,
+    ) -> ExecutionStatus {
+        editor.update(cx, |editor, cx| {
+            let cell_id = &editor.cell_order[index];
+            match editor.cell_map.get(cell_id) {
+                Some(Cell::Code(cell)) => cell.read(cx).execution_status().clone(),
+                _ => panic!("no code cell at {index}"),
+            }
+        })
     }
 
-    // TODO
-    fn as_searchable(&self, _: &View<Self>) -> Option<Box<dyn SearchableItemHandle>> {
-        None
+    /// The code of the execute requests the kernel was sent since the last call, in order.
+    fn sent_sources(request_rx: &mut mpsc::Receiver<JupyterMessage>) -> Vec<String> {
+        sent_code(request_rx)
+            .into_iter()
+            .map(|(_, code)| code)
+            .collect()
     }
 
-    fn set_nav_history(&mut self, _: workspace::ItemNavHistory, _: &mut ViewContext<Self>) {
-        // TODO
+    #[gpui::test]
+    async fn test_cells_run_in_queue_order(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2", "c = 3"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| editor.run_cells(cx));
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, "a = 1");
+        assert_eq!(execution_status(&editor, 1, cx), ExecutionStatus::Queued);
+        assert_eq!(execution_status(&editor, 2, cx), ExecutionStatus::Queued);
+
+        // Running a cell that's already queued doesn't queue it twice
+        editor.update(cx, |editor, cx| {
+            let cell_id = editor.cell_order[1].clone();
+            editor.queue_execution(cell_id, None, cx);
+            assert_eq!(editor.execution_queue.len(), 2);
+        });
+
+        editor.update(cx, |editor, cx| editor.route(&idle(&sent[0].0), cx));
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, "b = 2");
+
+        editor.update(cx, |editor, cx| editor.route(&idle(&sent[0].0), cx));
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, "c = 3");
+
+        editor.update(cx, |editor, cx| {
+            editor.route(&idle(&sent[0].0), cx);
+            assert!(editor.pending_executions.is_empty());
+            assert!(editor.execution_queue.is_empty());
+        });
+        assert!(sent_sources(&mut request_rx).is_empty());
     }
 
-    // TODO
-    fn can_save(&self, _cx: &AppContext) -> bool {
-        false
+    #[gpui::test]
+    async fn test_cancel_queued_execution(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2", "c = 3"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| editor.run_cells(cx));
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+
+        editor.update(cx, |editor, cx| {
+            // Only queued cells can be cancelled, the running one is left to finish
+            let running_cell_id = editor.cell_order[0].clone();
+            editor.cancel_queued_execution(&running_cell_id, cx);
+            assert_eq!(editor.pending_executions.len(), 1);
+
+            let queued_cell_id = editor.cell_order[1].clone();
+            editor.cancel_queued_execution(&queued_cell_id, cx);
+            assert_eq!(editor.execution_queue.len(), 1);
+        });
+        assert_eq!(execution_status(&editor, 1, cx), ExecutionStatus::Unknown);
+        assert_eq!(execution_status(&editor, 2, cx), ExecutionStatus::Queued);
+
+        // The cancelled cell is skipped once the running one finishes
+        editor.update(cx, |editor, cx| editor.route(&idle(&sent[0].0), cx));
+        assert_eq!(sent_sources(&mut request_rx), ["c = 3"]);
+        assert_eq!(execution_status(&editor, 1, cx), ExecutionStatus::Unknown);
     }
-    // TODO
-    fn save(
-        &mut self,
-        _format: bool,
-        _project: Model<Project>,
-        _cx: &mut ViewContext<Self>,
-    ) -> Task<Result<()>> {
-        unimplemented!("save() must be implemented if can_save() returns true")
+
+    #[gpui::test]
+    async fn test_kernel_died_while_cells_are_queued(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2", "c = 3"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| editor.run_cells(cx));
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+
+        editor.update(cx, |editor, cx| {
+            editor.kernel_died("Kernel heartbeat timed out".to_string(), cx);
+            assert!(editor.pending_executions.is_empty());
+            assert!(editor.execution_queue.is_empty());
+        });
+        for index in 0..3 {
+            assert_eq!(
+                execution_status(&editor, index, cx),
+                ExecutionStatus::KernelErrored("Kernel died: Kernel heartbeat timed out".into())
+            );
+        }
+
+        // A reply that arrives after the kernel died doesn't start anything else
+        editor.update(cx, |editor, cx| {
+            editor.route(&idle(&sent[0].0), cx);
+            assert!(editor.pending_executions.is_empty());
+        });
+        assert!(sent_sources(&mut request_rx).is_empty());
     }
 
-    // TODO
-    fn save_as(
-        &mut self,
-        _project: Model<Project>,
-        _path: ProjectPath,
-        _cx: &mut ViewContext<Self>,
-    ) -> Task<Result<()>> {
-        unimplemented!("save_as() must be implemented if can_save() returns true")
+    #[gpui::test]
+    async fn test_run_cell_while_restarting(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| {
+            let cell_id = editor.cell_order[0].clone();
+            editor.queue_execution(cell_id, None, cx);
+        });
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(sent.len(), 1);
+
+        // A cell run while the kernel restarts waits for it rather than being sent to nothing
+        editor.update(cx, |editor, cx| {
+            editor.restart_kernel(cx);
+            let cell_id = editor.cell_order[1].clone();
+            editor.queue_execution(cell_id, None, cx);
+            assert!(editor.pending_executions.is_empty());
+            assert_eq!(editor.execution_queue.len(), 1);
+
+            // Replies from before the restart don't release it either
+            editor.route(&idle(&sent[0].0), cx);
+            assert_eq!(editor.execution_queue.len(), 1);
+        });
+        assert_eq!(execution_status(&editor, 1, cx), ExecutionStatus::Queued);
+
+        // It's sent once the kernel is back
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+        editor.update(cx, |editor, cx| editor.process_execution_queue(cx));
+        assert_eq!(sent_sources(&mut request_rx), ["b = 2"]);
+        editor.update(cx, |editor, _| {
+            assert_eq!(editor.pending_executions.len(), 1)
+        });
     }
-    // TODO
-    fn reload(
-        &mut self,
-        _project: Model<Project>,
-        _cx: &mut ViewContext<Self>,
-    ) -> Task<Result<()>> {
-        unimplemented!("reload() must be implemented if can_save() returns true")
+
+    #[gpui::test]
+    async fn test_delete_queued_and_running_cells(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2", "c = 3"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| editor.run_cells(cx));
+        assert_eq!(
+            sent_code(&mut request_rx)
+                .into_iter()
+                .map(|(_, code)| code)
+                .collect::<Vec<_>>(),
+            ["a = 1"]
+        );
+        assert_eq!(execution_status(&editor, 2, cx), ExecutionStatus::Queued);
+
+        // A deleted queued cell is dropped from the queue, and comes back idle
+        editor.update(cx, |editor, cx| {
+            editor.set_selected_index(2, false, cx);
+            editor.delete_selected_cell(cx);
+            assert_eq!(editor.execution_queue.len(), 1);
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.execution_queue.len(), 1);
+        });
+        assert_eq!(execution_status(&editor, 2, cx), ExecutionStatus::Unknown);
+
+        // Deleting the running cell moves on to the next queued one
+        editor.update(cx, |editor, cx| {
+            editor.set_selected_index(0, false, cx);
+            editor.delete_selected_cell(cx);
+        });
+        let sent = sent_code(&mut request_rx);
+        assert_eq!(
+            sent.iter()
+                .map(|(_, code)| code.as_str())
+                .collect::<Vec<_>>(),
+            ["b = 2"]
+        );
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.pending_executions.len(), 1);
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.pending_executions.len(), 1);
+        });
+        assert_eq!(execution_status(&editor, 0, cx), ExecutionStatus::Unknown);
+
+        editor.update(cx, |editor, cx| editor.route(&idle(&sent[0].0), cx));
+        editor.update(cx, |editor, _| {
+            assert!(editor.pending_executions.is_empty())
+        });
     }
 
-    fn is_dirty(&self, cx: &AppContext) -> bool {
-        // self.is_dirty(cx) TODO
-        false
+    #[gpui::test]
+    async fn test_reload_resets_running_and_queued_cells(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
+
+        editor.update(cx, |editor, cx| editor.run_cells(cx));
+        assert_eq!(sent_code(&mut request_rx).len(), 1);
+        let previous_cells = editor.update(cx, |editor, _| {
+            editor
+                .cell_order
+                .iter()
+                .filter_map(|cell_id| match editor.cell_map.get(cell_id) {
+                    Some(Cell::Code(cell)) => Some(cell.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let project = editor.update(cx, |editor, cx| {
+            editor.notebook_item.update(cx, |item, _| {
+                item.path = Some(PathBuf::from("/notebook.ipynb"));
+            });
+            editor.project.clone()
+        });
+        let contents = editor.update(cx, |editor, cx| editor.serialized_notebook(cx).unwrap());
+        let fs = project.update(cx, |project, _| project.fs().clone());
+        fs.atomic_write(PathBuf::from("/notebook.ipynb"), contents.to_string())
+            .await
+            .unwrap();
+
+        editor
+            .update(cx, |editor, cx| Item::reload(editor, project, cx))
+            .await
+            .unwrap();
+
+        editor.update(cx, |editor, _| {
+            assert!(editor.pending_executions.is_empty());
+            assert!(editor.execution_queue.is_empty());
+        });
+        // The replaced cells don't keep waiting on the kernel either
+        for cell in previous_cells {
+            cell.update(cx, |cell, _| {
+                assert_eq!(cell.execution_status(), &ExecutionStatus::Unknown)
+            });
+        }
+        assert_eq!(execution_status(&editor, 0, cx), ExecutionStatus::Unknown);
+        assert_eq!(execution_status(&editor, 1, cx), ExecutionStatus::Unknown);
     }
-}
 
-// TODO: Implement this to allow us to persist to the database, etc:
-// impl SerializableItem for NotebookEditor {}
+    #[gpui::test]
+    async fn test_kernel_died_shuts_the_kernel_down(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1"], cx).await;
+        let mut request_rx = connect_fake_kernel(&editor, cx);
 
-impl ProjectItem for NotebookEditor {
-    type Item = NotebookItem;
+        editor.update(cx, |editor, cx| {
+            editor.kernel_died("Kernel heartbeat timed out".to_string(), cx);
+            match &editor.kernel {
+                Kernel::Died(error) => assert_eq!(error, "Kernel heartbeat timed out"),
+                _ => panic!("the kernel should be marked as dead"),
+            }
+            assert!(editor.render_kernel_banner(cx).is_some());
+        });
+        assert!(matches!(request_rx.try_next(), Ok(None)));
+    }
 
-    fn for_project_item(
-        project: Model<Project>,
-        item: Model<Self::Item>,
-        cx: &mut ViewContext<Self>,
-    ) -> Self
-    where
-        Self: Sized,
-    {
-        Self::new(project, item, cx)
+    #[gpui::test]
+    async fn test_snapshot_keeps_outputs_while_they_change(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["print(1)"], cx).await;
+        let stream: StreamContent =
+            serde_json::from_value(json!({ "name": "stdout", "text": "1\n" })).unwrap();
+
+        let snapshot = editor.update(cx, |editor, cx| {
+            let Some(Cell::Code(cell)) = editor.cell_map.get(&editor.cell_order[0]).cloned() else {
+                panic!("no code cell");
+            };
+            cell.update(cx, |cell, cx| {
+                cell.handle_message(&JupyterMessage::new(stream, None), cx)
+            });
+            let snapshot = editor.snapshot(cx);
+            cell.update(cx, |cell, _| cell.clear_outputs());
+            snapshot
+        });
+
+        let outputs = |notebook: nbformat::v4::Notebook| match &notebook.cells[0] {
+            nbformat::v4::Cell::Code { outputs, .. } => outputs.len(),
+            _ => panic!("no code cell"),
+        };
+        assert_eq!(outputs(snapshot.into_nbformat()), 1);
+        assert_eq!(
+            outputs(editor.update(cx, |editor, cx| editor.to_nbformat(cx))),
+            0
+        );
+    }
+
+    #[gpui::test]
+    async fn test_toggle_cell_line_numbers(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+
+        editor.update(cx, |editor, cx| {
+            let shown = editor.shows_line_numbers(cx);
+            editor.set_selected_index(1, false, cx);
+            editor.toggle_cell_line_numbers(cx);
+            let cell = editor.cell_map[&editor.cell_order[1]].clone();
+            assert_eq!(
+                editor.cell_line_numbers.get(&cell.entity_id()),
+                Some(&!shown)
+            );
+            assert_eq!(editor.shows_line_numbers(cx), shown);
+
+            editor.toggle_cell_line_numbers(cx);
+            assert_eq!(
+                editor.cell_line_numbers.get(&cell.entity_id()),
+                Some(&shown)
+            );
+
+            // Toggling the whole notebook resets the cells toggled on their own
+            editor.toggle_cell_line_numbers(cx);
+            editor.toggle_line_numbers(cx);
+            assert!(editor.cell_line_numbers.is_empty());
+            assert_eq!(editor.shows_line_numbers(cx), !shown);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_undo_back_to_saved_cells(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+
+        editor.update(cx, |editor, cx| {
+            assert!(!editor.is_dirty(cx));
+            let cell_order = editor.cell_order.clone();
+
+            editor.set_selected_index(0, false, cx);
+            editor.delete_selected_cell(cx);
+            assert!(editor.is_dirty(cx));
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.cell_order, cell_order);
+            assert!(!editor.is_dirty(cx));
+
+            editor.set_selected_index(0, false, cx);
+            editor.move_cell_down(cx);
+            assert_ne!(editor.cell_order, cell_order);
+            assert!(editor.is_dirty(cx));
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.cell_order, cell_order);
+            assert!(!editor.is_dirty(cx));
+
+            editor.insert_cell(1, CellType::Code, cx);
+            assert!(editor.is_dirty(cx));
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.cell_order, cell_order);
+            assert!(!editor.is_dirty(cx));
+
+            // Undo doesn't delete an inserted cell that's been locked since
+            editor.insert_cell(1, CellType::Code, cx);
+            editor.set_selected_index(1, false, cx);
+            editor.toggle_cell_deletable(cx);
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.cell_order.len(), 3);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_cell_subscriptions_follow_cells(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+
+        editor.update(cx, |editor, cx| {
+            editor.insert_cell(1, CellType::Code, cx);
+            editor.set_selected_index(0, false, cx);
+            editor.convert_selected_cell(CellType::Markdown, cx);
+            editor.convert_selected_cell(CellType::Code, cx);
+            assert_eq!(editor.cell_subscriptions.len(), 3);
+
+            editor.set_selected_index(1, false, cx);
+            editor.delete_selected_cell(cx);
+            assert_eq!(editor.cell_subscriptions.len(), 2);
+            editor.undo_cell_operation(cx);
+            assert_eq!(editor.cell_subscriptions.len(), 3);
+            assert!(editor
+                .cell_order
+                .iter()
+                .all(|cell_id| editor.cell_subscriptions.contains_key(cell_id)));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_undo_stack_is_capped(cx: &mut TestAppContext) {
+        let (editor, cx) = open_notebook(&["a = 1", "b = 2"], cx).await;
+
+        editor.update(cx, |editor, cx| {
+            for _ in 0..MAX_UNDO_OPERATIONS + 10 {
+                editor.set_selected_index(0, false, cx);
+                editor.move_cell_down(cx);
+            }
+            assert_eq!(editor.undo_stack.len(), MAX_UNDO_OPERATIONS);
+        });
     }
 }
@@ -0,0 +1,200 @@
+//! # Notebook Outline
+//!
+//! `outline: toggle` for notebooks, listing the headings of markdown cells and the symbols defined
+//! in code cells. Confirming an entry scrolls to its cell and selects it.
+
+use std::sync::Arc;
+
+use gpui::{DismissEvent, EventEmitter, FocusHandle, FocusableView, Task, View, WeakView};
+use nbformat::v4::CellId;
+use picker::{Picker, PickerDelegate};
+use ui::{prelude::*, ListItem, ListItemSpacing};
+use util::ResultExt as _;
+use workspace::ModalView;
+
+use super::NotebookEditor;
+
+/// A heading or symbol in the notebook.
+#[derive(Clone)]
+pub struct OutlineEntry {
+    pub cell_id: CellId,
+    pub text: String,
+    pub depth: usize,
+    /// Whether the entry is a markdown heading, rather than a symbol from a code cell.
+    pub is_heading: bool,
+}
+
+/// The headings in a markdown cell's source, with their depth starting at 0 for `#`.
+/// Lines inside fenced code blocks are skipped, since they're usually comments.
+pub(crate) fn markdown_headings(source: &str) -> Vec<(usize, String)> {
+    let mut headings = Vec::new();
+    let mut in_code_block = false;
+
+    for line in source.lines() {
+        let line = line.trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        let level = line.chars().take_while(|ch| *ch == '#').count();
+        if !(1..=6).contains(&level) {
+            continue;
+        }
+        let rest = &line[level..];
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            continue;
+        }
+        let text = rest.trim().trim_end_matches('#').trim_end();
+        if !text.is_empty() {
+            headings.push((level - 1, text.to_string()));
+        }
+    }
+
+    headings
+}
+
+pub struct NotebookOutline {
+    picker: View<Picker<NotebookOutlineDelegate>>,
+}
+
+impl NotebookOutline {
+    pub fn new(
+        entries: Vec<OutlineEntry>,
+        notebook: WeakView<NotebookEditor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let delegate = NotebookOutlineDelegate {
+            notebook,
+            matches: (0..entries.len()).collect(),
+            entries,
+            selected_index: 0,
+        };
+        let picker = cx.new_view(|cx| Picker::uniform_list(delegate, cx));
+        Self { picker }
+    }
+}
+
+impl FocusableView for NotebookOutline {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for NotebookOutline {}
+impl ModalView for NotebookOutline {}
+
+impl Render for NotebookOutline {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+pub struct NotebookOutlineDelegate {
+    notebook: WeakView<NotebookEditor>,
+    entries: Vec<OutlineEntry>,
+    /// Indices into `entries` of the entries matching the query.
+    matches: Vec<usize>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for NotebookOutlineDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _cx: &mut WindowContext) -> Arc<str> {
+        "Search notebook headings and symbols...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _cx: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, _cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.text.to_lowercase().contains(&query))
+            .map(|(index, _)| index)
+            .collect();
+        self.selected_index = 0;
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, _secondary: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(entry) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|index| self.entries.get(*index))
+        else {
+            return;
+        };
+
+        let cell_id = entry.cell_id.clone();
+        self.notebook
+            .update(cx, |notebook, cx| notebook.reveal_cell(&cell_id, cx))
+            .log_err();
+        cx.emit(DismissEvent);
+    }
+
+    fn dismissed(&mut self, _cx: &mut ViewContext<Picker<Self>>) {}
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let entry = self.entries.get(*self.matches.get(ix)?)?;
+
+        let icon = if entry.is_heading {
+            IconName::Hash
+        } else {
+            IconName::Code
+        };
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(
+                    h_flex()
+                        .pl(rems(entry.depth as f32))
+                        .gap_2()
+                        .child(Icon::new(icon).size(IconSize::Small).color(Color::Muted))
+                        .child(Label::new(entry.text.clone())),
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_markdown_headings() {
+        let source = "# Analysis\n\nSome text\n\n## Loading data ##\n```python\n# not a heading\n```\n#hashtag\n### Results";
+        assert_eq!(
+            markdown_headings(source),
+            vec![
+                (0, "Analysis".to_string()),
+                (1, "Loading data".to_string()),
+                (2, "Results".to_string()),
+            ]
+        );
+    }
+}
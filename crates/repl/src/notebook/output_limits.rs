@@ -0,0 +1,141 @@
+//! # Output Limits
+//!
+//! A cell stuck printing in a loop can stream more text than a notebook can reasonably show or
+//! save. With `notebook.output_max_lines` or `notebook.output_max_bytes` set, a cell keeps the
+//! stream output of each run up to the limit, and the rest is dropped with a note saying so.
+//! Rich outputs, like plots, aren't limited.
+//!
+//! With `notebook.save_outputs` turned off, outputs and execution counts are left out of the
+//! notebook file altogether, like `nbstripout` does, while still being shown.
+
+/// Counts the stream output of a cell's run against the limits.
+#[derive(Debug, Default)]
+pub(crate) struct StreamLimiter {
+    lines: usize,
+    bytes: usize,
+    truncated: bool,
+}
+
+impl StreamLimiter {
+    /// Starts counting again, for a new run or after the outputs were cleared.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The part of `text` that's within the limits, followed by a note the first time output is
+    /// dropped. `None` once the limits were reached. A limit of `0` doesn't limit anything.
+    pub(crate) fn limit(
+        &mut self,
+        text: &str,
+        max_lines: usize,
+        max_bytes: usize,
+    ) -> Option<String> {
+        if self.truncated {
+            return None;
+        }
+
+        let mut end = text.len();
+        if max_lines > 0 {
+            let remaining_lines = max_lines.saturating_sub(self.lines);
+            if remaining_lines == 0 {
+                end = 0;
+            } else if let Some((ix, _)) = text.match_indices('\n').nth(remaining_lines - 1) {
+                end = ix + 1;
+            }
+        }
+        if max_bytes > 0 && self.bytes + end > max_bytes {
+            let mut byte_end = max_bytes.saturating_sub(self.bytes);
+            while !text.is_char_boundary(byte_end) {
+                byte_end -= 1;
+            }
+            end = end.min(byte_end);
+        }
+
+        let kept = &text[..end];
+        self.lines += kept.matches('\n').count();
+        self.bytes += kept.len();
+        if end == text.len() {
+            return Some(text.to_string());
+        }
+
+        self.truncated = true;
+        let mut limited = kept.to_string();
+        if !limited.is_empty() && !limited.ends_with('\n') {
+            limited.push('\n');
+        }
+        limited.push_str("[Output truncated: it exceeded the notebook's output limits]\n");
+        Some(limited)
+    }
+}
+
+/// Empties the outputs and execution counts of the code cells in a notebook's JSON.
+pub(crate) fn strip_outputs(notebook: &mut serde_json::Value) {
+    let Some(cells) = notebook
+        .get_mut("cells")
+        .and_then(serde_json::Value::as_array_mut)
+    else {
+        return;
+    };
+    for cell in cells {
+        if cell.get("cell_type").and_then(serde_json::Value::as_str) != Some("code") {
+            continue;
+        }
+        if let Some(cell) = cell.as_object_mut() {
+            cell.insert("outputs".into(), serde_json::Value::Array(Vec::new()));
+            cell.insert("execution_count".into(), serde_json::Value::Null);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_stream_limiter_lines() {
+        let mut limiter = StreamLimiter::default();
+        assert_eq!(limiter.limit("1\n2\n", 3, 0).as_deref(), Some("1\n2\n"));
+        let limited = limiter.limit("3\n4\n5\n", 3, 0).unwrap();
+        assert!(limited.starts_with("3\n[Output truncated"));
+        assert_eq!(limiter.limit("6\n", 3, 0), None);
+
+        limiter.reset();
+        assert_eq!(
+            limiter.limit("1\n2\n3\n4\n", 0, 0).as_deref(),
+            Some("1\n2\n3\n4\n")
+        );
+    }
+
+    #[test]
+    fn test_stream_limiter_bytes() {
+        let mut limiter = StreamLimiter::default();
+        assert_eq!(limiter.limit("abc", 0, 5).as_deref(), Some("abc"));
+        // Output is never cut in the middle of a character
+        let limited = limiter.limit("déf", 0, 5).unwrap();
+        assert!(limited.starts_with("d\n[Output truncated"));
+        assert_eq!(limiter.limit("g", 0, 5), None);
+    }
+
+    #[test]
+    fn test_strip_outputs() {
+        let mut notebook = json!({
+            "cells": [
+                {
+                    "cell_type": "code",
+                    "execution_count": 4,
+                    "metadata": {},
+                    "outputs": [{"output_type": "stream", "name": "stdout", "text": "42\n"}],
+                    "source": "print(42)",
+                },
+                {"cell_type": "markdown", "metadata": {}, "source": "# Results"},
+            ],
+        });
+        strip_outputs(&mut notebook);
+        assert_eq!(notebook["cells"][0]["outputs"], json!([]));
+        assert_eq!(notebook["cells"][0]["execution_count"], json!(null));
+        assert_eq!(notebook["cells"][0]["source"], "print(42)");
+        assert!(notebook["cells"][1].get("outputs").is_none());
+    }
+}
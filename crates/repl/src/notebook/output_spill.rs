@@ -0,0 +1,200 @@
+//! # Output Spilling
+//!
+//! Outputs pile up over a long session, and a few plots or tables per cell can make a notebook
+//! hold gigabytes. When a notebook's outputs take more than `notebook.output_memory_limit`
+//! megabytes, the large outputs of the cells that were on screen the longest time ago are written
+//! to a temporary directory and dropped from memory. A spilled output keeps a placeholder pointing
+//! at its file:
+//!
+//! ```json
+//! "metadata": { "zed": { "spilled_output": "/tmp/zed-notebook-outputs-…/9b1c….json" } }
+//! ```
+//!
+//! Spilled outputs are read back in when their cell is scrolled into view, and when the notebook
+//! is saved, so placeholders never reach the notebook file.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nbformat::v4::Output;
+use serde_json::{Map, Value};
+use util::ResultExt as _;
+use uuid::Uuid;
+
+use super::external_outputs::outputs_mut;
+
+/// Outputs smaller than this are never spilled, since reading them back isn't worth what they save.
+pub(crate) const SPILL_MIN_SIZE: usize = 256 * 1024;
+
+/// The field under the output's `zed` metadata with the path of the file it was spilled to.
+const SPILLED_OUTPUT: &str = "spilled_output";
+
+/// The temporary directory a notebook's outputs are spilled to, removed along with the notebook.
+pub(crate) struct OutputSpillDir {
+    path: PathBuf,
+}
+
+impl OutputSpillDir {
+    pub(crate) fn new() -> Self {
+        Self {
+            path: std::env::temp_dir().join(format!("zed-notebook-outputs-{}", Uuid::new_v4())),
+        }
+    }
+
+    /// A new file for an output to be spilled to.
+    pub(crate) fn output_path(&self) -> PathBuf {
+        self.path.join(format!("{}.json", Uuid::new_v4()))
+    }
+
+    /// Writes spilled outputs to their files. This blocks, so it's done on the background executor.
+    pub(crate) fn write_outputs(&self, files: Vec<(PathBuf, Vec<u8>)>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.path)?;
+        for (path, contents) in files {
+            std::fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputSpillDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            std::fs::remove_dir_all(&self.path).log_err();
+        }
+    }
+}
+
+/// Counts the bytes written to it, to measure outputs without serializing them into memory.
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0 += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How many bytes an output takes as it's saved, which is roughly what it takes in memory.
+pub(crate) fn output_size(output: &Output) -> usize {
+    let mut counter = ByteCounter::default();
+    serde_json::to_writer(&mut counter, output).log_err();
+    counter.0
+}
+
+/// Builds the placeholder kept in place of an output spilled to `path`, along with the contents
+/// of the file. Only display data and execution results are spilled.
+pub(crate) fn spill_output(output: &Output, path: &Path) -> Option<(Output, Vec<u8>)> {
+    if !matches!(output, Output::DisplayData(_) | Output::ExecuteResult(_)) {
+        return None;
+    }
+
+    let contents = serde_json::to_vec(output).log_err()?;
+    let mut placeholder = serde_json::to_value(output).log_err()?;
+    let fields = placeholder.as_object_mut()?;
+    fields.insert("data".into(), Value::Object(Map::new()));
+    fields.insert(
+        "metadata".into(),
+        serde_json::json!({ "zed": { SPILLED_OUTPUT: path } }),
+    );
+    let placeholder = serde_json::from_value(placeholder).log_err()?;
+    Some((placeholder, contents))
+}
+
+/// The file an output was spilled to, if it's a placeholder.
+pub(crate) fn spilled_output_path(output: &Output) -> Option<PathBuf> {
+    let metadata = match output {
+        Output::DisplayData(display_data) => serde_json::to_value(&display_data.metadata),
+        Output::ExecuteResult(execute_result) => serde_json::to_value(&execute_result.metadata),
+        Output::Stream { .. } | Output::Error(_) => return None,
+    };
+    spilled_path(&metadata.ok()?)
+}
+
+fn spilled_path(metadata: &Value) -> Option<PathBuf> {
+    metadata
+        .get("zed")?
+        .get(SPILLED_OUTPUT)?
+        .as_str()
+        .map(PathBuf::from)
+}
+
+/// Reads a spilled output back in. This blocks, so it's done on the background executor.
+pub(crate) fn read_spilled_output(path: &Path) -> anyhow::Result<Output> {
+    let contents = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+/// Reads every spilled output of a serialized notebook back in. This blocks, so it's done on the
+/// background executor.
+pub(crate) fn restore_spilled_outputs(notebook: &mut Value) {
+    for output in outputs_mut(notebook) {
+        let Some(path) = output.get("metadata").and_then(spilled_path) else {
+            continue;
+        };
+        let restored = std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_slice::<Map<String, Value>>(&contents)?));
+        if let Some(restored) = restored.log_err() {
+            *output = restored;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_spill_and_restore_output() {
+        let output = json!({
+            "output_type": "execute_result",
+            "execution_count": 3,
+            "metadata": { "isolated": true },
+            "data": { "text/plain": ["   a  b\n0  1  2"], "text/html": ["<table></table>"] }
+        });
+        let parsed: Output = serde_json::from_value(output.clone()).unwrap();
+        assert!(output_size(&parsed) > 0);
+
+        let dir = OutputSpillDir::new();
+        let path = dir.output_path();
+        let (placeholder, contents) = spill_output(&parsed, &path).unwrap();
+        assert_eq!(spilled_output_path(&placeholder), Some(path.clone()));
+        assert_eq!(spilled_output_path(&parsed), None);
+        assert!(output_size(&placeholder) < output_size(&parsed));
+
+        dir.write_outputs(vec![(path.clone(), contents)]).unwrap();
+        let mut notebook = json!({
+            "cells": [{
+                "cell_type": "code",
+                "outputs": [serde_json::to_value(&placeholder).unwrap()]
+            }]
+        });
+        restore_spilled_outputs(&mut notebook);
+        assert_eq!(
+            notebook["cells"][0]["outputs"][0],
+            serde_json::to_value(&parsed).unwrap()
+        );
+
+        let dir_path = path.parent().unwrap().to_path_buf();
+        drop(dir);
+        assert!(!dir_path.exists());
+    }
+
+    #[test]
+    fn test_streams_are_not_spilled() {
+        let stream: Output = serde_json::from_value(json!({
+            "output_type": "stream",
+            "name": "stdout",
+            "text": ["epoch 1\n"]
+        }))
+        .unwrap();
+        assert!(spill_output(&stream, Path::new("/tmp/output.json")).is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! # Package Install
+//!
+//! A cell that fails with `ModuleNotFoundError: No module named 'x'` offers to install `x`. The
+//! install runs in the kernel through IPython's `%pip` and `%conda` magics, which install into the
+//! kernel's own environment, unlike `!pip`, which uses whichever `pip` comes first on the `PATH`.
+//! Its output is shown under the cell until the cell runs again, and isn't saved in the notebook.
+
+use gpui::View;
+
+use crate::outputs::plain::TerminalOutput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManager {
+    Pip,
+    Conda,
+}
+
+impl PackageManager {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pip => "pip",
+            Self::Conda => "conda",
+        }
+    }
+
+    /// The code to execute in the kernel to install a package.
+    pub fn install_code(&self, package: &str) -> String {
+        match self {
+            Self::Pip => format!("%pip install {package}"),
+            Self::Conda => format!("%conda install --yes {package}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstallStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// An install started from a cell, with its output so far.
+pub struct PackageInstall {
+    pub package: String,
+    pub manager: PackageManager,
+    pub output: View<TerminalOutput>,
+    pub status: InstallStatus,
+    /// Whether the output so far reported an error. Package managers don't raise in the kernel when
+    /// they fail, so their output is the only sign of it.
+    pub reported_error: bool,
+}
+
+/// Modules whose package is published under another name.
+fn package_for_module(module: &str) -> &str {
+    match module {
+        "sklearn" => "scikit-learn",
+        "cv2" => "opencv-python",
+        "PIL" => "pillow",
+        "yaml" => "pyyaml",
+        "bs4" => "beautifulsoup4",
+        "dateutil" => "python-dateutil",
+        "dotenv" => "python-dotenv",
+        _ => module,
+    }
+}
+
+/// The package that would provide the module a `ModuleNotFoundError` is about, e.g. `pandas` for
+/// `No module named 'pandas.io'`.
+pub(crate) fn missing_package(ename: &str, evalue: &str) -> Option<String> {
+    if ename != "ModuleNotFoundError" {
+        return None;
+    }
+
+    let module = evalue
+        .strip_prefix("No module named ")?
+        .trim_matches(|ch| ch == '\'' || ch == '"');
+    let top_level = module.split('.').next()?;
+    // The name ends up in code sent to the kernel, so anything but a plain name is left alone
+    let is_plain_name = !top_level.is_empty()
+        && top_level
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    is_plain_name.then(|| package_for_module(top_level).to_string())
+}
+
+/// Whether install output reports a failure, e.g. pip's `ERROR: No matching distribution found`.
+pub(crate) fn reports_install_error(text: &str) -> bool {
+    text.lines()
+        .any(|line| line.starts_with("ERROR:") || line.contains("PackagesNotFoundError"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_package() {
+        assert_eq!(
+            missing_package("ModuleNotFoundError", "No module named 'pandas'"),
+            Some("pandas".to_string())
+        );
+        assert_eq!(
+            missing_package(
+                "ModuleNotFoundError",
+                "No module named 'sklearn.linear_model'"
+            ),
+            Some("scikit-learn".to_string())
+        );
+        assert_eq!(
+            missing_package("ModuleNotFoundError", "No module named 'x; import os'"),
+            None
+        );
+        assert_eq!(
+            missing_package("ImportError", "No module named 'pandas'"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reports_install_error() {
+        assert!(reports_install_error(
+            "Collecting nope\nERROR: No matching distribution found for nope\n"
+        ));
+        assert!(!reports_install_error(
+            "Collecting pandas\nSuccessfully installed pandas-2.2.3\n"
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! # Notebook Persistence
+//!
+//! Notebooks open in a workspace are reopened with it, scrolled to where they were and with the
+//! same cell selected. Cells are remembered by id rather than position, so a notebook that changed
+//! on disk in the meantime is still restored near the right cell. Notebooks made read-only are
+//! reopened read-only, and line numbers toggled in a notebook stay shown or hidden. Kernels aren't
+//! restored: they stop when Zed quits, and start again when a cell is run.
+//!
+//! The signatures of trusted notebooks are kept here too, across workspaces, see
+//! [`super::trust`].
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use db::{define_connection, query, sqlez::statement::Statement, sqlez_macros::sql};
+use workspace::{ItemId, WorkspaceDb, WorkspaceId};
+
+define_connection! {
+    pub static ref NOTEBOOK_DB: NotebookDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE notebook_editors (
+                workspace_id INTEGER,
+                item_id INTEGER UNIQUE,
+
+                notebook_path BLOB,
+                selected_cell_id TEXT,
+                scroll_top_cell_id TEXT,
+                scroll_offset REAL NOT NULL DEFAULT 0,
+
+                PRIMARY KEY(workspace_id, item_id),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+            ) STRICT;
+        ),
+        sql! (
+            ALTER TABLE notebook_editors ADD COLUMN read_only INTEGER NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            CREATE TABLE trusted_notebooks (
+                signature TEXT PRIMARY KEY
+            ) STRICT;
+        ),
+        sql! (
+            ALTER TABLE notebook_editors ADD COLUMN show_line_numbers INTEGER;
+        )];
+}
+
+impl NotebookDb {
+    query! {
+        pub async fn save_notebook(
+            item_id: ItemId,
+            workspace_id: WorkspaceId,
+            notebook_path: PathBuf,
+            selected_cell_id: Option<String>,
+            scroll_top_cell_id: Option<String>,
+            scroll_offset: f32,
+            read_only: bool,
+            show_line_numbers: Option<bool>
+        ) -> Result<()> {
+            INSERT OR REPLACE INTO notebook_editors(
+                item_id, workspace_id, notebook_path, selected_cell_id, scroll_top_cell_id, scroll_offset, read_only, show_line_numbers
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        }
+    }
+
+    query! {
+        pub fn get_notebook(
+            item_id: ItemId,
+            workspace_id: WorkspaceId
+        ) -> Result<Option<(PathBuf, Option<String>, Option<String>, f32, bool, Option<bool>)>> {
+            SELECT notebook_path, selected_cell_id, scroll_top_cell_id, scroll_offset, read_only, show_line_numbers
+            FROM notebook_editors
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
+
+    query! {
+        pub async fn trust_notebook(signature: String) -> Result<()> {
+            INSERT OR IGNORE INTO trusted_notebooks(signature)
+            VALUES (?)
+        }
+    }
+
+    query! {
+        pub fn trusted_notebook(signature: String) -> Result<Option<String>> {
+            SELECT signature
+            FROM trusted_notebooks
+            WHERE signature = ?
+        }
+    }
+
+    pub async fn delete_unloaded_items(
+        &self,
+        workspace: WorkspaceId,
+        alive_items: Vec<ItemId>,
+    ) -> Result<()> {
+        let placeholders = alive_items
+            .iter()
+            .map(|_| "?")
+            .collect::<Vec<&str>>()
+            .join(", ");
+
+        let query = format!(
+            "DELETE FROM notebook_editors WHERE workspace_id = ? AND item_id NOT IN ({placeholders})"
+        );
+
+        self.write(move |conn| {
+            let mut statement = Statement::prepare(conn, query)?;
+            let mut next_index = statement.bind(&workspace, 1)?;
+            for id in alive_items {
+                next_index = statement.bind(&id, next_index)?;
+            }
+            statement.exec()
+        })
+        .await
+    }
+}
@@ -0,0 +1,387 @@
+//! # Quarto and R Markdown Documents
+//!
+//! Quarto documents (`.qmd`) and R Markdown documents (`.Rmd`) are markdown with executable code
+//! chunks, like ```` ```{python} ```` or ```` ```{r} ````.
+//! They open as notebooks, split the way `quarto convert` splits them: the YAML front matter is a
+//! raw cell at the top, each chunk is a code cell, and the markdown between chunks is a markdown
+//! cell. Saving writes the cells back as Quarto markdown.
+//!
+//! These documents don't store outputs, so outputs are only kept while the notebook is open.
+//! Options written in a chunk's header, like `{r setup, echo=FALSE}`, are kept in the cell's
+//! metadata so the header is written back as it was. Options written as `#|` comments are part
+//! of the cell's source.
+
+use std::path::Path;
+
+use anyhow::Result;
+use nbformat::v4::{Cell, CellMetadata, Notebook};
+use serde_json::{json, Value};
+
+pub(crate) fn is_quarto_path(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == "qmd")
+}
+
+pub(crate) fn is_r_markdown_path(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("rmd"))
+}
+
+/// Whether the file at `path` is a markdown document that opens as a notebook.
+pub(crate) fn is_markdown_notebook_path(path: &Path) -> bool {
+    is_quarto_path(path) || is_r_markdown_path(path)
+}
+
+/// The language of a document's chunks when it doesn't name one: R for R Markdown, and Python
+/// for Quarto.
+pub(crate) fn default_language(path: &Path) -> &'static str {
+    if is_r_markdown_path(path) {
+        "r"
+    } else {
+        "python"
+    }
+}
+
+enum Block<'a> {
+    FrontMatter(String),
+    Markdown(String),
+    Chunk {
+        header: &'a str,
+        language: &'a str,
+        source: String,
+    },
+}
+
+/// The fence, header and language of a line opening an executable chunk, e.g. ```` ``` ````,
+/// `{python, echo=false}` and `python`. Fences like ```` ```python ```` or ```` ```{.python} ````
+/// only display code, so they're left in the markdown.
+fn chunk_header(line: &str) -> Option<(&str, &str, &str)> {
+    let line = line.trim_end();
+    let fence_len = line.len() - line.trim_start_matches('`').len();
+    if fence_len < 3 {
+        return None;
+    }
+    let (fence, header) = line.split_at(fence_len);
+    let language = header
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .split(|char: char| char == ',' || char.is_whitespace())
+        .next()?;
+    let is_executable = language
+        .chars()
+        .next()
+        .map_or(false, |char| char.is_ascii_alphabetic());
+    is_executable.then_some((fence, header, language))
+}
+
+fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    if lines.peek() == Some(&"---") {
+        let mut front_matter = Vec::new();
+        for line in lines.by_ref() {
+            front_matter.push(line);
+            if front_matter.len() > 1 && (line == "---" || line == "...") {
+                break;
+            }
+        }
+        blocks.push(Block::FrontMatter(front_matter.join("\n")));
+    }
+
+    let mut markdown = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some((fence, header, language)) = chunk_header(line) else {
+            markdown.push(line);
+            continue;
+        };
+
+        blocks.push(Block::Markdown(std::mem::take(&mut markdown).join("\n")));
+        let mut source = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim() == fence {
+                break;
+            }
+            source.push(line);
+        }
+        blocks.push(Block::Chunk {
+            header,
+            language,
+            source: source.join("\n"),
+        });
+    }
+    blocks.push(Block::Markdown(markdown.join("\n")));
+    blocks
+}
+
+/// The kernel named by the front matter's `jupyter` option, e.g. `jupyter: python3`.
+fn front_matter_kernel(front_matter: &str) -> Option<&str> {
+    front_matter
+        .lines()
+        .find_map(|line| line.strip_prefix("jupyter:"))
+        .map(str::trim)
+        .filter(|kernel| !kernel.is_empty())
+}
+
+fn cell_json(cell_type: &str, source: &str, metadata: Value) -> Value {
+    let mut cell = json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "cell_type": cell_type,
+        "metadata": metadata,
+        "source": source.split_inclusive('\n').collect::<Vec<_>>(),
+    });
+    if cell_type == "code" {
+        cell["execution_count"] = Value::Null;
+        cell["outputs"] = json!([]);
+    }
+    cell
+}
+
+/// Reads a Quarto or R Markdown document as a notebook. The notebook's language is the language
+/// of its first chunk.
+pub(crate) fn parse_quarto(text: &str, default_language: &str) -> Result<Notebook> {
+    let blocks = blocks(text);
+    let language = blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Chunk { language, .. } => Some(*language),
+            _ => None,
+        })
+        .unwrap_or(default_language);
+    let default_header = format!("{{{language}}}");
+
+    let mut metadata = json!({});
+    let mut cells = Vec::new();
+    for block in &blocks {
+        match block {
+            Block::FrontMatter(front_matter) => {
+                if let Some(kernel) = front_matter_kernel(front_matter) {
+                    metadata["kernelspec"] = json!({
+                        "name": kernel,
+                        "display_name": kernel,
+                        "language": language,
+                    });
+                }
+                cells.push(cell_json("raw", front_matter, json!({})));
+            }
+            Block::Markdown(markdown) => {
+                let markdown = markdown.trim_matches('\n');
+                if !markdown.trim().is_empty() {
+                    cells.push(cell_json("markdown", markdown, json!({})));
+                }
+            }
+            Block::Chunk { header, source, .. } => {
+                let metadata = if *header == default_header {
+                    json!({})
+                } else {
+                    json!({ "zed": { "chunk_header": header } })
+                };
+                cells.push(cell_json("code", source, metadata));
+            }
+        }
+    }
+    metadata["language_info"] = json!({ "name": language });
+
+    Ok(serde_json::from_value(json!({
+        "nbformat": 4,
+        "nbformat_minor": 5,
+        "metadata": metadata,
+        "cells": cells,
+    }))?)
+}
+
+/// The header a code cell was read with, if it wasn't the notebook's default.
+fn saved_chunk_header(metadata: &CellMetadata) -> Option<String> {
+    let metadata = serde_json::to_value(metadata).ok()?;
+    Some(
+        metadata
+            .get("zed")?
+            .get("chunk_header")?
+            .as_str()?
+            .to_string(),
+    )
+}
+
+/// Writes a notebook as a Quarto or R Markdown document. Outputs aren't written.
+pub(crate) fn quarto_text(notebook: &Notebook, default_language: &str) -> String {
+    let language = notebook
+        .metadata
+        .language_info
+        .as_ref()
+        .map(|info| info.name.clone())
+        .or_else(|| {
+            notebook
+                .metadata
+                .kernelspec
+                .as_ref()
+                .and_then(|spec| spec.language.clone())
+        })
+        .unwrap_or_else(|| default_language.to_string())
+        .to_lowercase();
+
+    let blocks = notebook
+        .cells
+        .iter()
+        .filter_map(|cell| match cell {
+            Cell::Raw { source, .. } | Cell::Markdown { source, .. } => {
+                let text = source.join("");
+                let text = text.trim_matches('\n');
+                (!text.trim().is_empty()).then(|| text.to_string())
+            }
+            Cell::Code {
+                source, metadata, ..
+            } => {
+                let header =
+                    saved_chunk_header(metadata).unwrap_or_else(|| format!("{{{language}}}"));
+                let source = source.join("");
+                Some(format!(
+                    "```{header}\n{}\n```",
+                    source.trim_end_matches('\n')
+                ))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut text = blocks.join("\n\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use super::*;
+
+    const DOCUMENT: &str = indoc! {r#"
+        ---
+        title: "Penguins"
+        jupyter: python3
+        ---
+
+        ## Data
+
+        Load the data, then plot it.
+
+        ```{python}
+        #| label: load
+        import pandas as pd
+        df = pd.read_csv("penguins.csv")
+        ```
+
+        ```python
+        # Only displayed
+        ```
+
+        ```{python, echo=false}
+        df.plot()
+        ```
+    "#};
+
+    fn sources(notebook: &Notebook) -> Vec<(&'static str, String)> {
+        notebook
+            .cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Raw { source, .. } => ("raw", source.join("")),
+                Cell::Markdown { source, .. } => ("markdown", source.join("")),
+                Cell::Code { source, .. } => ("code", source.join("")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_quarto() {
+        let notebook = parse_quarto(DOCUMENT, "python").unwrap();
+        assert_eq!(
+            sources(&notebook),
+            vec![
+                (
+                    "raw",
+                    "---\ntitle: \"Penguins\"\njupyter: python3\n---".to_string()
+                ),
+                (
+                    "markdown",
+                    "## Data\n\nLoad the data, then plot it.".to_string()
+                ),
+                (
+                    "code",
+                    "#| label: load\nimport pandas as pd\ndf = pd.read_csv(\"penguins.csv\")"
+                        .to_string()
+                ),
+                ("markdown", "```python\n# Only displayed\n```".to_string()),
+                ("code", "df.plot()".to_string()),
+            ]
+        );
+        assert_eq!(
+            notebook.metadata.kernelspec.as_ref().unwrap().name,
+            "python3"
+        );
+        assert_eq!(notebook.metadata.language_info.unwrap().name, "python");
+    }
+
+    #[test]
+    fn test_quarto_round_trip() {
+        let notebook = parse_quarto(DOCUMENT, "python").unwrap();
+        assert_eq!(quarto_text(&notebook, "python"), DOCUMENT);
+    }
+
+    #[test]
+    fn test_r_markdown() {
+        let document = indoc! {r#"
+            ---
+            title: "Penguins"
+            output: html_document
+            ---
+
+            ```{r setup, include=FALSE}
+            library(ggplot2)
+            ```
+
+            Mean body mass: `r mean(penguins$body_mass_g)`.
+
+            ```{r}
+            ggplot(penguins, aes(body_mass_g)) + geom_histogram()
+            ```
+        "#};
+        let notebook = parse_quarto(document, "r").unwrap();
+        assert_eq!(
+            sources(&notebook)
+                .into_iter()
+                .map(|(cell_type, _)| cell_type)
+                .collect::<Vec<_>>(),
+            ["raw", "code", "markdown", "code"]
+        );
+        assert!(notebook.metadata.kernelspec.is_none());
+        assert_eq!(notebook.metadata.language_info.as_ref().unwrap().name, "r");
+        assert_eq!(quarto_text(&notebook, "r"), document);
+
+        let empty = parse_quarto("# Notes\n", "r").unwrap();
+        assert_eq!(empty.metadata.language_info.unwrap().name, "r");
+    }
+
+    #[test]
+    fn test_chunk_header() {
+        assert_eq!(
+            chunk_header("```{r, fig.width=4}"),
+            Some(("```", "{r, fig.width=4}", "r"))
+        );
+        assert_eq!(
+            chunk_header("````{python}"),
+            Some(("````", "{python}", "python"))
+        );
+        assert_eq!(chunk_header("```python"), None);
+        assert_eq!(chunk_header("```{.python}"), None);
+        assert_eq!(chunk_header("```{=html}"), None);
+    }
+
+    #[test]
+    fn test_is_quarto_path() {
+        assert!(is_quarto_path(Path::new("reports/penguins.qmd")));
+        assert!(!is_quarto_path(Path::new("reports/penguins.ipynb")));
+        assert!(is_r_markdown_path(Path::new("reports/penguins.Rmd")));
+        assert!(is_markdown_notebook_path(Path::new("reports/penguins.rmd")));
+        assert_eq!(default_language(Path::new("penguins.Rmd")), "r");
+        assert_eq!(default_language(Path::new("penguins.qmd")), "python");
+    }
+}
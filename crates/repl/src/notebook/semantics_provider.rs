@@ -0,0 +1,129 @@
+//! # Cell Semantics Provider
+//!
+//! Code cell editors aren't backed by a project buffer, so the language server features their
+//! editors ask for are answered from the notebook's [`CellDocument`](super::cell_document)
+//! instead. Signature help and go-to-definition are supported so far.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use editor::{GotoDefinitionKind, SemanticsProvider};
+use gpui::{AnyWindowHandle, AppContext, Model, Task, WeakView};
+use language::{Buffer, LanguageServerId};
+use nbformat::v4::CellId;
+use project::lsp_command::SignatureHelp;
+use project::{DocumentHighlight, Hover, InlayHint, LocationLink, ProjectTransaction};
+
+use super::NotebookEditor;
+
+pub(crate) struct CellSemanticsProvider {
+    notebook: WeakView<NotebookEditor>,
+    cell_id: CellId,
+    /// The window of the notebook, which navigates to definitions once they've been found.
+    window: AnyWindowHandle,
+}
+
+impl CellSemanticsProvider {
+    pub fn new(
+        notebook: WeakView<NotebookEditor>,
+        cell_id: CellId,
+        window: AnyWindowHandle,
+    ) -> Self {
+        Self {
+            notebook,
+            cell_id,
+            window,
+        }
+    }
+}
+
+impl SemanticsProvider for CellSemanticsProvider {
+    fn hover(
+        &self,
+        _buffer: &Model<Buffer>,
+        _position: language::Anchor,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Vec<Hover>>> {
+        None
+    }
+
+    fn signature_help(
+        &self,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        cx: &mut AppContext,
+    ) -> Option<Task<Vec<SignatureHelp>>> {
+        let notebook = self.notebook.upgrade()?;
+        NotebookEditor::cell_signature_help(&notebook, &self.cell_id, buffer, position, cx)
+    }
+
+    fn inlay_hints(
+        &self,
+        _buffer_handle: Model<Buffer>,
+        _range: Range<language::Anchor>,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Result<Vec<InlayHint>>>> {
+        None
+    }
+
+    fn resolve_inlay_hint(
+        &self,
+        _hint: InlayHint,
+        _buffer_handle: Model<Buffer>,
+        _server_id: LanguageServerId,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Result<InlayHint>>> {
+        None
+    }
+
+    fn supports_inlay_hints(&self, _buffer: &Model<Buffer>, _cx: &AppContext) -> bool {
+        false
+    }
+
+    fn document_highlights(
+        &self,
+        _buffer: &Model<Buffer>,
+        _position: language::Anchor,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Result<Vec<DocumentHighlight>>>> {
+        None
+    }
+
+    fn definitions(
+        &self,
+        buffer: &Model<Buffer>,
+        position: language::Anchor,
+        kind: GotoDefinitionKind,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Vec<LocationLink>>>> {
+        let notebook = self.notebook.upgrade()?;
+        NotebookEditor::cell_definitions(
+            &notebook,
+            self.window,
+            &self.cell_id,
+            buffer,
+            position,
+            kind,
+            cx,
+        )
+    }
+
+    fn range_for_rename(
+        &self,
+        _buffer: &Model<Buffer>,
+        _position: language::Anchor,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Result<Option<Range<language::Anchor>>>>> {
+        None
+    }
+
+    fn perform_rename(
+        &self,
+        _buffer: &Model<Buffer>,
+        _position: language::Anchor,
+        _new_name: String,
+        _cx: &mut AppContext,
+    ) -> Option<Task<Result<ProjectTransaction>>> {
+        None
+    }
+}
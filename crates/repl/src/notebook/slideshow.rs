@@ -0,0 +1,184 @@
+//! # Slideshows
+//!
+//! Jupyter's slideshow extension, and tools like RISE and `nbconvert --to slides`, read how a
+//! notebook is split into slides from the `slideshow` cell metadata:
+//!
+//! ```json
+//! "metadata": { "slideshow": { "slide_type": "fragment" } }
+//! ```
+//!
+//! A `slide` or `subslide` cell starts a new slide, a `fragment` is revealed on the current slide
+//! one step at a time, `skip` and `notes` cells aren't shown, and cells without a type continue
+//! the current slide. Cells before the first slide make up a slide of their own.
+
+use nbformat::v4::{CellId, CellMetadata};
+use serde_json::Value;
+use util::ResultExt as _;
+
+/// The part a cell plays in a slideshow, from its `slideshow.slide_type` metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlideType {
+    Slide,
+    Subslide,
+    Fragment,
+    Skip,
+    Notes,
+}
+
+impl SlideType {
+    pub const ALL: [SlideType; 5] = [
+        SlideType::Slide,
+        SlideType::Subslide,
+        SlideType::Fragment,
+        SlideType::Skip,
+        SlideType::Notes,
+    ];
+
+    /// The value saved in the cell's metadata.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlideType::Slide => "slide",
+            SlideType::Subslide => "subslide",
+            SlideType::Fragment => "fragment",
+            SlideType::Skip => "skip",
+            SlideType::Notes => "notes",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SlideType::Slide => "Slide",
+            SlideType::Subslide => "Sub-Slide",
+            SlideType::Fragment => "Fragment",
+            SlideType::Skip => "Skip",
+            SlideType::Notes => "Notes",
+        }
+    }
+
+    /// Parses the value saved in a cell's metadata. `-`, which Jupyter saves for cells that
+    /// continue the current slide, isn't a slide type.
+    pub fn parse(slide_type: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == slide_type)
+    }
+}
+
+/// Reads a cell's slide type from its metadata.
+pub(crate) fn cell_slide_type(metadata: &CellMetadata) -> Option<SlideType> {
+    let metadata = serde_json::to_value(metadata).log_err()?;
+    metadata
+        .get("slideshow")?
+        .get("slide_type")?
+        .as_str()
+        .and_then(SlideType::parse)
+}
+
+/// Saves a cell's slide type in its metadata, keeping any other `slideshow` fields. The
+/// `slideshow` field is left out when it would be empty.
+pub(crate) fn set_cell_slide_type(metadata: &mut CellMetadata, slide_type: Option<SlideType>) {
+    let Some(mut value) = serde_json::to_value(&*metadata).log_err() else {
+        return;
+    };
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+
+    let mut slideshow = fields
+        .remove("slideshow")
+        .and_then(|slideshow| match slideshow {
+            Value::Object(slideshow) => Some(slideshow),
+            _ => None,
+        })
+        .unwrap_or_default();
+    match slide_type {
+        Some(slide_type) => {
+            slideshow.insert("slide_type".into(), slide_type.as_str().into());
+        }
+        None => {
+            slideshow.remove("slide_type");
+        }
+    }
+    if !slideshow.is_empty() {
+        fields.insert("slideshow".into(), Value::Object(slideshow));
+    }
+
+    if let Some(updated) = serde_json::from_value(value).log_err() {
+        *metadata = updated;
+    }
+}
+
+/// Splits cells into the steps of a presentation, each listing the cells shown at that step.
+/// A slide's fragments each add a step showing the slide so far.
+pub(crate) fn slide_steps(cells: &[(CellId, Option<SlideType>)]) -> Vec<Vec<CellId>> {
+    let mut steps: Vec<Vec<CellId>> = Vec::new();
+
+    for (cell_id, slide_type) in cells {
+        match slide_type {
+            Some(SlideType::Skip) | Some(SlideType::Notes) => {}
+            Some(SlideType::Slide) | Some(SlideType::Subslide) => {
+                steps.push(vec![cell_id.clone()]);
+            }
+            Some(SlideType::Fragment) => {
+                let mut step = steps.last().cloned().unwrap_or_default();
+                step.push(cell_id.clone());
+                steps.push(step);
+            }
+            None => match steps.last_mut() {
+                Some(step) => step.push(cell_id.clone()),
+                None => steps.push(vec![cell_id.clone()]),
+            },
+        }
+    }
+
+    steps
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::collab::test_cell_id;
+    use super::*;
+
+    #[test]
+    fn test_slide_type_round_trips_through_metadata() {
+        let mut metadata: CellMetadata = serde_json::from_value(serde_json::json!({
+            "slideshow": { "slide_type": "-", "theme": "dark" }
+        }))
+        .unwrap();
+        assert_eq!(cell_slide_type(&metadata), None);
+
+        set_cell_slide_type(&mut metadata, Some(SlideType::Fragment));
+        assert_eq!(cell_slide_type(&metadata), Some(SlideType::Fragment));
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value["slideshow"]["theme"], "dark");
+
+        set_cell_slide_type(&mut metadata, None);
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value["slideshow"], serde_json::json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn test_slide_steps() {
+        let cells = [
+            (test_cell_id("title"), Some(SlideType::Slide)),
+            (test_cell_id("intro"), None),
+            (test_cell_id("note"), Some(SlideType::Notes)),
+            (test_cell_id("point"), Some(SlideType::Fragment)),
+            (test_cell_id("setup"), Some(SlideType::Skip)),
+            (test_cell_id("detail"), Some(SlideType::Subslide)),
+        ];
+
+        assert_eq!(
+            slide_steps(&cells),
+            vec![
+                vec![test_cell_id("title"), test_cell_id("intro")],
+                vec![
+                    test_cell_id("title"),
+                    test_cell_id("intro"),
+                    test_cell_id("point")
+                ],
+                vec![test_cell_id("detail")],
+            ]
+        );
+    }
+}
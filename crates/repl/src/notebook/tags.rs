@@ -0,0 +1,270 @@
+//! # Cell Tags
+//!
+//! Jupyter saves a cell's tags in the standard `tags` cell metadata field. Tools key off them:
+//! papermill injects parameters after the cell tagged `parameters`, and nbconvert and Jupyter Book
+//! skip or hide cells tagged `skip` or `hide-input`. A cell's tags are shown as chips under it, and
+//! edited in a popover opened from those chips.
+
+use editor::Editor;
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model, ModelContext,
+    Subscription, View,
+};
+use nbformat::v4::CellMetadata;
+use ui::{prelude::*, IconButtonShape, Tooltip};
+use util::ResultExt as _;
+
+/// Tags other tools look for, suggested in the editor when the cell doesn't have them yet.
+pub const COMMON_TAGS: &[&str] = &["parameters", "skip", "hide-input", "hide-output"];
+
+/// Reads the tags saved in a cell's metadata. Tags that aren't strings are skipped.
+pub(crate) fn cell_tags(metadata: &CellMetadata) -> Vec<String> {
+    let Some(metadata) = serde_json::to_value(metadata).log_err() else {
+        return Vec::new();
+    };
+    let Some(tags) = metadata.get("tags").and_then(|tags| tags.as_array()) else {
+        return Vec::new();
+    };
+
+    tags.iter()
+        .filter_map(|tag| tag.as_str().map(ToString::to_string))
+        .collect()
+}
+
+/// Saves tags in a cell's metadata, leaving out the `tags` field when there are none.
+pub(crate) fn set_cell_tags(metadata: &mut CellMetadata, tags: &[String]) {
+    let Some(mut value) = serde_json::to_value(&*metadata).log_err() else {
+        return;
+    };
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+
+    if tags.is_empty() {
+        fields.remove("tags");
+    } else {
+        fields.insert("tags".into(), tags.into());
+    }
+
+    if let Some(updated) = serde_json::from_value(value).log_err() {
+        *metadata = updated;
+    }
+}
+
+/// The tags of a cell, shared between the cell and its popover.
+pub struct CellTags {
+    tags: Vec<String>,
+    /// Whether the tags changed since the notebook was last saved.
+    modified: bool,
+}
+
+impl CellTags {
+    pub fn new(metadata: &CellMetadata) -> Self {
+        Self {
+            tags: cell_tags(metadata),
+            modified: false,
+        }
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn did_save(&mut self) {
+        self.modified = false;
+    }
+
+    /// Writes the tags into the metadata the cell is saved with.
+    pub fn write_to(&self, metadata: &mut CellMetadata) {
+        set_cell_tags(metadata, &self.tags);
+    }
+
+    /// Adds a tag, unless the cell already has it. Tags can't contain whitespace, so it's replaced
+    /// with dashes.
+    pub fn add(&mut self, tag: &str, cx: &mut ModelContext<Self>) {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+        if tag.is_empty() || self.tags.contains(&tag) {
+            return;
+        }
+        self.tags.push(tag);
+        self.modified = true;
+        cx.notify();
+    }
+
+    pub fn remove(&mut self, tag: &str, cx: &mut ModelContext<Self>) {
+        let tag_count = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        if self.tags.len() != tag_count {
+            self.modified = true;
+            cx.notify();
+        }
+    }
+}
+
+/// The popover editing a cell's tags, with an editor to add one and the common tags to pick from.
+pub struct CellTagsView {
+    tags: Model<CellTags>,
+    editor: View<Editor>,
+    _subscription: Subscription,
+}
+
+impl CellTagsView {
+    pub fn new(tags: Model<CellTags>, cx: &mut ViewContext<Self>) -> Self {
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Add a tag…", cx);
+            editor
+        });
+        cx.focus_view(&editor);
+        let subscription = cx.observe(&tags, |_, _, cx| cx.notify());
+
+        Self {
+            tags,
+            editor,
+            _subscription: subscription,
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let tag = self.editor.read(cx).text(cx);
+        self.tags.update(cx, |tags, cx| tags.add(&tag, cx));
+        self.editor.update(cx, |editor, cx| editor.clear(cx));
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
+        cx.emit(DismissEvent);
+    }
+}
+
+impl EventEmitter<DismissEvent> for CellTagsView {}
+
+impl FocusableView for CellTagsView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl Render for CellTagsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let tags = self.tags.read(cx).tags().to_vec();
+        let suggestions = COMMON_TAGS
+            .iter()
+            .filter(|tag| !tags.iter().any(|existing| existing == *tag))
+            .collect::<Vec<_>>();
+
+        v_flex()
+            .key_context("CellTags")
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .w(rems(18.))
+            .p_2()
+            .gap_2()
+            .elevation_2(cx)
+            .child(
+                div()
+                    .px_1()
+                    .py_0p5()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(cx.theme().colors().border)
+                    .bg(cx.theme().colors().editor_background)
+                    .child(self.editor.clone()),
+            )
+            .when(!tags.is_empty(), |this| {
+                this.child(
+                    h_flex()
+                        .flex_wrap()
+                        .gap_1()
+                        .children(tags.iter().map(|tag| {
+                            let tag = tag.clone();
+                            tag_chip(tag.clone(), cx).child(
+                                IconButton::new(
+                                    SharedString::from(format!("remove-tag-{tag}")),
+                                    IconName::Close,
+                                )
+                                .shape(IconButtonShape::Square)
+                                .icon_size(IconSize::XSmall)
+                                .tooltip(|cx| Tooltip::text("Remove Tag", cx))
+                                .on_click(cx.listener(
+                                    move |this, _, cx| {
+                                        this.tags.update(cx, |tags, cx| tags.remove(&tag, cx));
+                                    },
+                                )),
+                            )
+                        })),
+                )
+            })
+            .when(!suggestions.is_empty(), |this| {
+                this.child(
+                    v_flex()
+                        .gap_1()
+                        .child(
+                            Label::new("Common Tags")
+                                .size(LabelSize::XSmall)
+                                .color(Color::Muted),
+                        )
+                        .child(
+                            h_flex()
+                                .flex_wrap()
+                                .gap_1()
+                                .children(suggestions.into_iter().map(|tag| {
+                                    Button::new(SharedString::from(format!("add-tag-{tag}")), *tag)
+                                        .label_size(LabelSize::Small)
+                                        .on_click(cx.listener(move |this, _, cx| {
+                                            this.tags.update(cx, |tags, cx| tags.add(tag, cx));
+                                        }))
+                                })),
+                        ),
+                )
+            })
+    }
+}
+
+/// A tag shown as a chip, under its cell and in the tag editor.
+pub(crate) fn tag_chip(tag: impl Into<SharedString>, cx: &WindowContext) -> Div {
+    h_flex()
+        .gap_0p5()
+        .px_1p5()
+        .rounded_md()
+        .border_1()
+        .border_color(cx.theme().colors().border_variant)
+        .bg(cx.theme().colors().element_background)
+        .child(
+            Label::new(tag.into())
+                .size(LabelSize::XSmall)
+                .color(Color::Muted),
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(value: serde_json::Value) -> CellMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_tags_round_trip_through_metadata() {
+        let mut cell_metadata = metadata(serde_json::json!({"collapsed": true}));
+        let tags = vec!["parameters".to_string(), "hide-input".to_string()];
+
+        set_cell_tags(&mut cell_metadata, &tags);
+        assert_eq!(cell_tags(&cell_metadata), tags);
+
+        let value = serde_json::to_value(&cell_metadata).unwrap();
+        assert_eq!(value["collapsed"], serde_json::json!(true));
+    }
+
+    #[test]
+    fn test_removing_the_last_tag_drops_the_tags_field() {
+        let mut cell_metadata = metadata(serde_json::json!({"tags": ["skip"]}));
+        set_cell_tags(&mut cell_metadata, &[]);
+        let value = serde_json::to_value(&cell_metadata).unwrap();
+        assert!(value.get("tags").is_none());
+    }
+}
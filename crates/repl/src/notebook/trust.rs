@@ -0,0 +1,134 @@
+//! # Notebook Trust
+//!
+//! Like Jupyter, Zed only shows outputs that can carry scripts or load external resources, like
+//! HTML and charts, from notebooks the user trusts. A notebook is trusted when:
+//!
+//! - the user trusted it, or saved it in Zed, and it hasn't changed since, or
+//! - none of its saved outputs need trust.
+//!
+//! Trusted notebooks are remembered by a signature of their contents, so a notebook changed by
+//! someone else, or replaced by a download, has to be trusted again. Outputs produced while the
+//! notebook is open are always shown, since they come from code the user ran.
+//!
+//! Untrusted outputs fall back to another representation saved with them, usually plain text.
+//! Cells never run on their own when a notebook is opened, unless it opted in to running them and
+//! the user trusted it as it is, see [`super::auto_run`].
+
+use nbformat::v4::{Cell, Notebook, Output};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use util::ResultExt as _;
+
+use super::lazy_outputs::deferred_payloads;
+use crate::outputs::requires_trust;
+
+/// A signature of a serialized notebook's contents. Jupyter's own `signature` metadata is left
+/// out, since Jupyter updates it without changing the notebook.
+pub(crate) fn notebook_signature(notebook: &Value) -> Option<String> {
+    let mut notebook = notebook.clone();
+    if let Some(metadata) = notebook.get_mut("metadata").and_then(Value::as_object_mut) {
+        metadata.remove("signature");
+    }
+    let bytes = serde_json::to_vec(&notebook).log_err()?;
+    Some(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// Whether any output saved in the notebook is only shown once it's trusted, including payloads
+/// that haven't been loaded yet.
+pub(crate) fn has_untrusted_outputs(notebook: &Notebook) -> bool {
+    notebook.cells.iter().any(|cell| match cell {
+        Cell::Code { outputs, .. } => outputs.iter().any(|output| {
+            let loaded = match output {
+                Output::DisplayData(display_data) => {
+                    display_data.data.content.iter().any(requires_trust)
+                }
+                Output::ExecuteResult(execute_result) => {
+                    execute_result.data.content.iter().any(requires_trust)
+                }
+                Output::Stream { .. } | Output::Error(_) => false,
+            };
+            loaded
+                || deferred_payloads(output)
+                    .iter()
+                    .any(|payload| mime_type_requires_trust(&payload.mime_type))
+        }),
+        Cell::Markdown { .. } | Cell::Raw { .. } => false,
+    })
+}
+
+/// Whether a payload needs trust, by its MIME type alone. See [`requires_trust`].
+fn mime_type_requires_trust(mime_type: &str) -> bool {
+    mime_type == "text/html" || mime_type.starts_with("application/vnd.vegalite")
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn notebook(output: Value) -> Value {
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {},
+            "cells": [{
+                "id": "a",
+                "cell_type": "code",
+                "execution_count": 1,
+                "metadata": {},
+                "source": ["df"],
+                "outputs": [output]
+            }]
+        })
+    }
+
+    #[test]
+    fn test_notebook_signature() {
+        let html = json!({
+            "output_type": "execute_result",
+            "execution_count": 1,
+            "metadata": {},
+            "data": { "text/html": ["<b>1</b>"], "text/plain": ["1"] }
+        });
+        let value = notebook(html.clone());
+        let signature = notebook_signature(&value).unwrap();
+
+        let mut signed = value.clone();
+        signed["metadata"]["signature"] = json!("sha256:abc");
+        assert_eq!(notebook_signature(&signed).unwrap(), signature);
+
+        let mut changed = value.clone();
+        changed["cells"][0]["outputs"][0]["data"]["text/html"] = json!(["<script></script>"]);
+        assert_ne!(notebook_signature(&changed).unwrap(), signature);
+    }
+
+    #[test]
+    fn test_has_untrusted_outputs() {
+        let html = notebook(json!({
+            "output_type": "display_data",
+            "metadata": {},
+            "data": { "text/html": ["<b>1</b>"], "text/plain": ["1"] }
+        }));
+        let html: Notebook = serde_json::from_value(html).unwrap();
+        assert!(has_untrusted_outputs(&html));
+
+        let stream = notebook(json!({
+            "output_type": "stream",
+            "name": "stdout",
+            "text": ["1\n"]
+        }));
+        let stream: Notebook = serde_json::from_value(stream).unwrap();
+        assert!(!has_untrusted_outputs(&stream));
+
+        let deferred_html = notebook(json!({
+            "output_type": "display_data",
+            "metadata": {
+                "zed": { "deferred_data": { "text/html": { "payload": 0, "size": 2097152 } } }
+            },
+            "data": { "text/plain": ["<Figure>"] }
+        }));
+        let deferred_html: Notebook = serde_json::from_value(deferred_html).unwrap();
+        assert!(has_untrusted_outputs(&deferred_html));
+    }
+}
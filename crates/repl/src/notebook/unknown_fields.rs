@@ -0,0 +1,317 @@
+//! # Unknown Fields
+//!
+//! nbformat only models the notebook, cell, and output fields from the Jupyter schema, and drops
+//! everything else when a notebook is parsed. Tools keep a lot in those fields: widget state,
+//! papermill parameters, and metadata from other extensions. To avoid destroying it on save, the
+//! fields lost while parsing are captured when a notebook is loaded, and written back into the
+//! notebook when it's saved.
+//!
+//! Fields are only restored where the saved notebook doesn't already have them, so anything Zed
+//! writes wins. Output fields are restored only while the cell still has the outputs it was
+//! loaded with, since they describe those outputs. Zed's own metadata, under `zed`, is always
+//! written from the notebook's current state, so it's never restored.
+
+use collections::HashMap;
+use nbformat::v4::{CellId, Metadata, Notebook};
+use serde_json::{Map, Value};
+use util::ResultExt as _;
+
+/// The fields of a notebook file that nbformat couldn't represent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct UnknownFields {
+    notebook: Option<Map<String, Value>>,
+    cells: HashMap<CellId, UnknownCellFields>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct UnknownCellFields {
+    cell: Option<Map<String, Value>>,
+    /// The outputs as nbformat parsed them, to tell whether the cell still has them.
+    outputs: Vec<Value>,
+    output_fields: Vec<Option<Map<String, Value>>>,
+}
+
+impl UnknownFields {
+    /// Compares a notebook file with what nbformat parsed from it, and keeps what was lost.
+    pub(crate) fn capture(raw: &Value, notebook: &Notebook) -> Self {
+        let Some(parsed) = serde_json::to_value(notebook).log_err() else {
+            return Self::default();
+        };
+
+        let notebook_fields =
+            missing_fields(&without(raw, &["cells"]), &without(&parsed, &["cells"]));
+        let raw_cells = raw.get("cells").and_then(Value::as_array);
+        let parsed_cells = parsed.get("cells").and_then(Value::as_array);
+
+        let mut cells = HashMap::default();
+        for (raw_cell, (cell, parsed_cell)) in raw_cells.into_iter().flatten().zip(
+            notebook
+                .cells
+                .iter()
+                .zip(parsed_cells.into_iter().flatten()),
+        ) {
+            let outputs = parsed_cell
+                .get("outputs")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            let output_fields = raw_cell
+                .get("outputs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .zip(&outputs)
+                .map(|(raw_output, output)| missing_fields(raw_output, output))
+                .collect::<Vec<_>>();
+            let mut cell_fields = missing_fields(
+                &without(raw_cell, &["source", "outputs"]),
+                &without(parsed_cell, &["source", "outputs"]),
+            );
+            if let Some(fields) = &mut cell_fields {
+                let metadata = fields.get_mut("metadata").and_then(Value::as_object_mut);
+                if let Some(metadata) = metadata {
+                    metadata.remove("zed");
+                    if metadata.is_empty() {
+                        fields.remove("metadata");
+                    }
+                }
+            }
+            let cell_fields = cell_fields.filter(|fields| !fields.is_empty());
+
+            if cell_fields.is_some() || output_fields.iter().any(Option::is_some) {
+                cells.insert(
+                    cell.id().clone(),
+                    UnknownCellFields {
+                        cell: cell_fields,
+                        outputs,
+                        output_fields,
+                    },
+                );
+            }
+        }
+
+        Self {
+            notebook: notebook_fields,
+            cells,
+        }
+    }
+
+    /// The notebook's metadata as it's saved, including the fields nbformat couldn't parse.
+    pub(crate) fn notebook_metadata(&self, metadata: &Metadata) -> Value {
+        let mut value = serde_json::to_value(metadata)
+            .log_err()
+            .unwrap_or_else(|| Value::Object(Map::new()));
+        let fields = self
+            .notebook
+            .as_ref()
+            .and_then(|notebook| notebook.get("metadata"))
+            .and_then(Value::as_object);
+        if let Some(fields) = fields {
+            restore_fields(&mut value, fields);
+        }
+        value
+    }
+
+    /// Replaces the notebook metadata fields after the metadata was edited, keeping those of the
+    /// edited metadata nbformat couldn't parse. Fields removed in the edit aren't restored.
+    pub(crate) fn set_notebook_metadata(&mut self, edited: &Value, parsed: &Metadata) {
+        let Some(parsed) = serde_json::to_value(parsed).log_err() else {
+            return;
+        };
+        let notebook = self.notebook.get_or_insert_with(Map::new);
+        notebook.remove("metadata");
+        if let Some(fields) = missing_fields(edited, &parsed) {
+            notebook.insert("metadata".into(), Value::Object(fields));
+        }
+        if notebook.is_empty() {
+            self.notebook = None;
+        }
+    }
+
+    /// Writes the captured fields back into a serialized notebook.
+    pub(crate) fn restore(&self, notebook: &mut Value) {
+        if let Some(fields) = &self.notebook {
+            restore_fields(notebook, fields);
+        }
+
+        let Some(cells) = notebook.get_mut("cells").and_then(Value::as_array_mut) else {
+            return;
+        };
+        for cell in cells {
+            let Some(unknown) = cell
+                .get("id")
+                .and_then(|id| serde_json::from_value::<CellId>(id.clone()).ok())
+                .and_then(|id| self.cells.get(&id))
+            else {
+                continue;
+            };
+
+            if let Some(fields) = &unknown.cell {
+                restore_fields(cell, fields);
+            }
+
+            let Some(outputs) = cell.get_mut("outputs").and_then(Value::as_array_mut) else {
+                continue;
+            };
+            if *outputs != unknown.outputs {
+                continue;
+            }
+            for (output, fields) in outputs.iter_mut().zip(&unknown.output_fields) {
+                if let Some(fields) = fields {
+                    restore_fields(output, fields);
+                }
+            }
+        }
+    }
+}
+
+/// A copy of an object without the given keys.
+fn without(value: &Value, keys: &[&str]) -> Value {
+    let mut value = value.clone();
+    if let Some(fields) = value.as_object_mut() {
+        for key in keys {
+            fields.remove(*key);
+        }
+    }
+    value
+}
+
+/// The fields of `raw` that aren't in `parsed`, including those of nested objects.
+fn missing_fields(raw: &Value, parsed: &Value) -> Option<Map<String, Value>> {
+    let raw = raw.as_object()?;
+    let parsed = parsed.as_object();
+
+    let mut missing = Map::new();
+    for (key, raw_value) in raw {
+        match parsed.and_then(|parsed| parsed.get(key)) {
+            Some(parsed_value) => {
+                if let Some(fields) = missing_fields(raw_value, parsed_value) {
+                    missing.insert(key.clone(), Value::Object(fields));
+                }
+            }
+            None => {
+                missing.insert(key.clone(), raw_value.clone());
+            }
+        }
+    }
+
+    (!missing.is_empty()).then_some(missing)
+}
+
+/// Adds fields to an object where it doesn't have them yet.
+fn restore_fields(value: &mut Value, fields: &Map<String, Value>) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+
+    for (key, field) in fields {
+        match object.get_mut(key) {
+            Some(existing @ Value::Object(_)) => {
+                if let Value::Object(nested) = field {
+                    restore_fields(existing, nested);
+                }
+            }
+            None => {
+                object.insert(key.clone(), field.clone());
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn notebook() -> Value {
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {
+                "kernelspec": { "name": "python3", "display_name": "Python 3", "language": "python" },
+                "widgets": { "application/vnd.jupyter.widget-state+json": { "version_major": 2 } }
+            },
+            "cells": [{
+                "id": "a",
+                "cell_type": "code",
+                "execution_count": 1,
+                "metadata": {
+                    "tags": ["parameters"],
+                    "papermill": { "duration": 0.5 }
+                },
+                "source": ["x = 1"],
+                "outputs": [{
+                    "output_type": "stream",
+                    "name": "stdout",
+                    "text": ["1\n"],
+                    "tool_specific": true
+                }]
+            }]
+        })
+    }
+
+    #[test]
+    fn test_restore_unknown_fields() {
+        let raw = notebook();
+        let parsed: Notebook = serde_json::from_value(raw.clone()).unwrap();
+        let unknown = UnknownFields::capture(&raw, &parsed);
+
+        let mut saved = serde_json::to_value(&parsed).unwrap();
+        unknown.restore(&mut saved);
+
+        assert_eq!(saved["metadata"]["widgets"], raw["metadata"]["widgets"],);
+        assert_eq!(saved["cells"][0]["metadata"]["papermill"]["duration"], 0.5);
+        assert_eq!(saved["cells"][0]["metadata"]["tags"], json!(["parameters"]));
+        assert_eq!(saved["cells"][0]["outputs"][0]["tool_specific"], true);
+    }
+
+    #[test]
+    fn test_output_fields_dropped_with_outputs() {
+        let raw = notebook();
+        let parsed: Notebook = serde_json::from_value(raw.clone()).unwrap();
+        let unknown = UnknownFields::capture(&raw, &parsed);
+
+        let mut saved = serde_json::to_value(&parsed).unwrap();
+        saved["cells"][0]["outputs"][0]["text"] = json!(["2\n"]);
+        unknown.restore(&mut saved);
+
+        assert!(saved["cells"][0]["outputs"][0]
+            .get("tool_specific")
+            .is_none());
+        assert_eq!(saved["cells"][0]["metadata"]["papermill"]["duration"], 0.5);
+    }
+
+    #[test]
+    fn test_saved_fields_take_precedence() {
+        let raw = json!({ "a": 1, "b": { "c": 2, "d": 3 } });
+        let fields = missing_fields(&raw, &json!({ "b": { "c": 2 } })).unwrap();
+
+        let mut saved = json!({ "a": 5, "b": { "c": 4 } });
+        restore_fields(&mut saved, &fields);
+        assert_eq!(saved, json!({ "a": 5, "b": { "c": 4, "d": 3 } }));
+    }
+
+    #[test]
+    fn test_edited_notebook_metadata() {
+        let raw = notebook();
+        let parsed: Notebook = serde_json::from_value(raw.clone()).unwrap();
+        let mut unknown = UnknownFields::capture(&raw, &parsed);
+
+        let metadata = unknown.notebook_metadata(&parsed.metadata);
+        assert_eq!(metadata["widgets"], raw["metadata"]["widgets"]);
+        assert_eq!(metadata["kernelspec"]["name"], "python3");
+
+        let edited = json!({
+            "kernelspec": { "name": "python3", "display_name": "Python 3", "language": "python" },
+            "toc": { "number_sections": false }
+        });
+        let edited_metadata: Metadata = serde_json::from_value(edited.clone()).unwrap();
+        unknown.set_notebook_metadata(&edited, &edited_metadata);
+
+        let metadata = unknown.notebook_metadata(&edited_metadata);
+        assert!(metadata.get("widgets").is_none());
+        assert_eq!(metadata["toc"], json!({ "number_sections": false }));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use gpui::Pixels;
+use language::language_settings::SoftWrap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+#[derive(Debug)]
+pub struct NotebookSettings {
+    pub default_kernels: HashMap<String, String>,
+    pub output_max_lines: usize,
+    pub output_max_bytes: usize,
+    pub save_outputs: bool,
+    pub code_font_size: Option<Pixels>,
+    pub markdown_font_size: Option<Pixels>,
+    pub show_line_numbers: bool,
+    pub collapse_outputs: bool,
+    pub code_soft_wrap: SoftWrap,
+    pub markdown_soft_wrap: SoftWrap,
+    pub output_soft_wrap: bool,
+    pub output_mime_type_priority: Vec<String>,
+    pub external_outputs: bool,
+    pub dedupe_outputs: bool,
+    pub output_memory_limit: usize,
+    pub diff_ignores_outputs: bool,
+}
+
+impl NotebookSettings {
+    /// The name of the kernel to run notebooks in `language` on, if one is set.
+    /// Languages are matched case-insensitively.
+    pub fn default_kernel(&self, language: &str) -> Option<&str> {
+        self.default_kernels
+            .iter()
+            .find(|(kernel_language, _)| kernel_language.eq_ignore_ascii_case(language))
+            .map(|(_, kernel)| kernel.as_str())
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct NotebookSettingsContent {
+    /// The kernel to run notebooks on, for each language, when the kernel a
+    /// notebook was saved with isn't available. Keys are language names, like
+    /// `python`, and values are kernel names, like `conda-base`. Languages
+    /// without one use their kernel from `jupyter.kernel_selections`.
+    ///
+    /// Default: `{}`
+    pub default_kernels: Option<HashMap<String, String>>,
+    /// How many lines of stream output, like `print`s, a cell keeps each time
+    /// it's run. Lines past this are dropped. `0` keeps every line.
+    ///
+    /// Default: `0`
+    pub output_max_lines: Option<usize>,
+    /// How many bytes of stream output a cell keeps each time it's run.
+    /// Output past this is dropped. `0` keeps all of it.
+    ///
+    /// Default: `0`
+    pub output_max_bytes: Option<usize>,
+    /// Whether outputs and execution counts are written to the notebook file
+    /// when it's saved. They're still shown until the notebook is closed.
+    ///
+    /// Default: `true`
+    pub save_outputs: Option<bool>,
+    /// The font size of the cells' source, in pixels. Uses the buffer font
+    /// size when unset.
+    ///
+    /// Default: `null`
+    pub code_font_size: Option<f32>,
+    /// The font size of rendered Markdown cells, in pixels. Uses the UI font
+    /// size when unset.
+    ///
+    /// Default: `null`
+    pub markdown_font_size: Option<f32>,
+    /// Whether line numbers are shown next to the cells' source.
+    ///
+    /// Default: `false`
+    pub show_line_numbers: Option<bool>,
+    /// Whether the outputs of cells that don't say whether they're collapsed
+    /// start out collapsed.
+    ///
+    /// Default: `false`
+    pub collapse_outputs: Option<bool>,
+    /// How the source of code and raw cells is soft wrapped.
+    ///
+    /// Default: `none`
+    pub code_soft_wrap: Option<SoftWrap>,
+    /// How the source of Markdown cells is soft wrapped while they're edited.
+    ///
+    /// Default: `editor_width`
+    pub markdown_soft_wrap: Option<SoftWrap>,
+    /// Whether long lines of text outputs, like `print`s and tracebacks, wrap.
+    /// Unwrapped outputs scroll sideways instead.
+    ///
+    /// Default: `true`
+    pub output_soft_wrap: Option<bool>,
+    /// MIME types to prefer when an output comes with several representations,
+    /// most preferred first. Unlisted types fall back to Zed's built-in order.
+    ///
+    /// Default: `[]`
+    pub output_mime_type_priority: Option<Vec<String>>,
+    /// Whether to store large outputs, like plots, in a directory next to the
+    /// notebook instead of embedding them in the `.ipynb` file.
+    ///
+    /// Default: `false`
+    pub external_outputs: Option<bool>,
+    /// Whether to save output payloads, like plots, that an earlier output already has
+    /// as a reference to it instead of another copy.
+    ///
+    /// Default: `false`
+    pub dedupe_outputs: Option<bool>,
+    /// How many megabytes of outputs a notebook keeps in memory. Past this, large
+    /// outputs of cells that aren't on screen are moved to temporary files until
+    /// they're scrolled back into view. `0` keeps every output in memory.
+    ///
+    /// Default: `1024`
+    pub output_memory_limit: Option<usize>,
+    /// Whether changes to outputs and execution counts are left out when cells are
+    /// compared with the last commit, so only changes to their source are marked.
+    ///
+    /// Default: `true`
+    pub diff_ignores_outputs: Option<bool>,
+}
+
+impl Settings for NotebookSettings {
+    const KEY: Option<&'static str> = Some("notebook");
+
+    type FileContent = NotebookSettingsContent;
+
+    fn load(
+        sources: SettingsSources<Self::FileContent>,
+        _cx: &mut gpui::AppContext,
+    ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut settings = NotebookSettings {
+            default_kernels: HashMap::default(),
+            output_max_lines: 0,
+            output_max_bytes: 0,
+            save_outputs: true,
+            code_font_size: None,
+            markdown_font_size: None,
+            show_line_numbers: false,
+            collapse_outputs: false,
+            code_soft_wrap: SoftWrap::None,
+            markdown_soft_wrap: SoftWrap::EditorWidth,
+            output_soft_wrap: true,
+            output_mime_type_priority: Vec::new(),
+            external_outputs: false,
+            dedupe_outputs: false,
+            output_memory_limit: 1024,
+            diff_ignores_outputs: true,
+        };
+
+        for value in sources.defaults_and_customizations() {
+            if let Some(source) = &value.default_kernels {
+                for (k, v) in source {
+                    settings.default_kernels.insert(k.clone(), v.clone());
+                }
+            }
+
+            if let Some(source) = value.output_max_lines {
+                settings.output_max_lines = source;
+            }
+
+            if let Some(source) = value.output_max_bytes {
+                settings.output_max_bytes = source;
+            }
+
+            if let Some(source) = value.save_outputs {
+                settings.save_outputs = source;
+            }
+
+            if let Some(source) = value.code_font_size {
+                settings.code_font_size = Some(gpui::px(source));
+            }
+
+            if let Some(source) = value.markdown_font_size {
+                settings.markdown_font_size = Some(gpui::px(source));
+            }
+
+            if let Some(source) = value.show_line_numbers {
+                settings.show_line_numbers = source;
+            }
+
+            if let Some(source) = value.collapse_outputs {
+                settings.collapse_outputs = source;
+            }
+
+            if let Some(source) = value.code_soft_wrap {
+                settings.code_soft_wrap = source;
+            }
+
+            if let Some(source) = value.markdown_soft_wrap {
+                settings.markdown_soft_wrap = source;
+            }
+
+            if let Some(source) = value.output_soft_wrap {
+                settings.output_soft_wrap = source;
+            }
+
+            if let Some(source) = &value.output_mime_type_priority {
+                settings.output_mime_type_priority = source.clone();
+            }
+
+            if let Some(source) = value.external_outputs {
+                settings.external_outputs = source;
+            }
+
+            if let Some(source) = value.dedupe_outputs {
+                settings.dedupe_outputs = source;
+            }
+
+            if let Some(source) = value.output_memory_limit {
+                settings.output_memory_limit = source;
+            }
+
+            if let Some(source) = value.diff_ignores_outputs {
+                settings.diff_ignores_outputs = source;
+            }
+        }
+
+        Ok(settings)
+    }
+}
@@ -14,8 +14,12 @@
 //! The module supports several output types, including:
 //! - Plain text
 //! - Markdown
+//! - HTML
+//! - LaTeX
+//! - JSON
 //! - Images (PNG and JPEG)
 //! - Tables
+//! - Vega-Lite charts
 //! - Error messages
 //!
 //! ## Clipboard Support
@@ -33,46 +37,137 @@
 //! This module is designed to work with Jupyter message protocols,
 //! interpreting and displaying various types of Jupyter output.
 
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 
 use editor::{Editor, MultiBuffer};
 use gpui::{
-    percentage, Animation, AnimationExt, AnyElement, ClipboardItem, Model, Render, Transformation,
-    View, WeakView,
+    percentage, Animation, AnimationExt, AnyElement, ClipboardItem, Image, Model, Render,
+    Transformation, View, WeakView,
 };
 use language::Buffer;
 use runtimelib::{ExecutionState, JupyterMessageContent, MimeBundle, MimeType};
-use ui::{div, prelude::*, v_flex, IntoElement, Styled, Tooltip, ViewContext};
+use ui::{
+    div, prelude::*, right_click_menu, v_flex, ContextMenu, IntoElement, Styled, Tooltip,
+    ViewContext,
+};
+
+mod display_data;
+use display_data::DisplayDataView;
+
+mod html;
+use html::HtmlView;
 
-mod image;
+pub(crate) mod image;
 use image::ImageView;
 
-mod markdown;
+mod json;
+use json::JsonView;
+
+mod latex;
+use latex::LatexView;
+
+pub(crate) mod markdown;
 use markdown::MarkdownView;
 
 mod table;
 use table::TableView;
 
+mod vega_lite;
+use vega_lite::VegaLiteView;
+
 pub mod plain;
 use plain::TerminalOutput;
 
 pub(crate) mod user_error;
+use settings::Settings as _;
 use user_error::ErrorView;
 use workspace::Workspace;
 
-/// When deciding what to render from a collection of mediatypes, we need to rank them in order of importance
-fn rank_mime_type(mimetype: &MimeType) -> usize {
+use crate::{Kernel, NotebookSettings};
+
+/// When deciding what to render from a collection of mediatypes, we need to rank them in order of importance.
+///
+/// Media types listed in `priority` (from the `notebook.output_mime_type_priority` setting)
+/// outrank every other type, in the order they are listed.
+fn rank_mime_type(mimetype: &MimeType, priority: &[String]) -> usize {
+    let preferred = mime_type_name(mimetype)
+        .and_then(|name| priority.iter().position(|preferred| preferred == name));
+    if let Some(index) = preferred {
+        return 100 + priority.len() - index;
+    }
+
     match mimetype {
-        MimeType::DataTable(_) => 6,
-        MimeType::Png(_) => 4,
-        MimeType::Jpeg(_) => 3,
-        MimeType::Markdown(_) => 2,
+        MimeType::VegaLiteV4(_) | MimeType::VegaLiteV5(_) => 10,
+        MimeType::DataTable(_) => 9,
+        MimeType::Png(_) => 7,
+        MimeType::Jpeg(_) => 6,
+        MimeType::Html(_) => 5,
+        MimeType::Latex(_) => 4,
+        MimeType::Markdown(_) => 3,
+        MimeType::Json(_) => 2,
         MimeType::Plain(_) => 1,
         // All other media types are not supported in Zed at this time
         _ => 0,
     }
 }
 
+/// The MIME type of a media type Zed can display.
+pub(crate) fn mime_type_name(mimetype: &MimeType) -> Option<&'static str> {
+    match mimetype {
+        MimeType::Plain(_) => Some("text/plain"),
+        MimeType::Markdown(_) => Some("text/markdown"),
+        MimeType::Html(_) => Some("text/html"),
+        MimeType::Latex(_) => Some("text/latex"),
+        MimeType::Json(_) => Some("application/json"),
+        MimeType::Png(_) => Some("image/png"),
+        MimeType::Jpeg(_) => Some("image/jpeg"),
+        MimeType::DataTable(_) => Some("application/vnd.dataresource+json"),
+        MimeType::VegaLiteV4(_) => Some("application/vnd.vegalite.v4+json"),
+        MimeType::VegaLiteV5(_) => Some("application/vnd.vegalite.v5+json"),
+        _ => None,
+    }
+}
+
+/// Whether a media type can carry scripts or load external resources, so it's only shown from
+/// notebooks the user trusts. See [`crate::notebook::NotebookEditor`].
+pub(crate) fn requires_trust(mimetype: &MimeType) -> bool {
+    matches!(
+        mimetype,
+        MimeType::Html(_) | MimeType::VegaLiteV4(_) | MimeType::VegaLiteV5(_)
+    )
+}
+
+/// A short, human readable name for a MIME type, used in the "Show As" menu.
+pub(crate) fn mime_type_display_name(name: &str) -> &str {
+    match name {
+        "text/plain" => "Plain Text",
+        "text/markdown" => "Markdown",
+        "text/html" => "HTML",
+        "text/latex" => "LaTeX",
+        "application/json" => "JSON",
+        "image/png" => "PNG",
+        "image/jpeg" => "JPEG",
+        "application/vnd.dataresource+json" => "Table",
+        "application/vnd.vegalite.v4+json" | "application/vnd.vegalite.v5+json" => "Chart",
+        _ => name,
+    }
+}
+
+/// The media types of a bundle that Zed can display, most preferred first.
+fn ranked_mime_types(data: &MimeBundle, priority: &[String]) -> Vec<MimeType> {
+    let mut representations = data
+        .content
+        .iter()
+        .filter(|mimetype| mime_type_name(mimetype).is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    representations.sort_by_key(|mimetype| std::cmp::Reverse(rank_mime_type(mimetype, priority)));
+    representations
+}
+
 pub(crate) trait OutputContent {
     fn clipboard_content(&self, cx: &WindowContext) -> Option<ClipboardItem>;
     fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
@@ -84,6 +179,14 @@ pub(crate) trait OutputContent {
     fn buffer_content(&mut self, _cx: &mut WindowContext) -> Option<Model<Buffer>> {
         None
     }
+    /// Other ways of copying the output, offered in its context menu, e.g. a table as TSV.
+    fn clipboard_alternatives(&self, _cx: &WindowContext) -> Vec<(SharedString, ClipboardItem)> {
+        Vec::new()
+    }
+    /// The image shown by the output, for saving it to a file.
+    fn image(&self, _cx: &WindowContext) -> Option<Arc<Image>> {
+        None
+    }
 }
 
 impl<V: OutputContent + 'static> OutputContent for View<V> {
@@ -102,6 +205,14 @@ impl<V: OutputContent + 'static> OutputContent for View<V> {
     fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
         self.update(cx, |item, cx| item.buffer_content(cx))
     }
+
+    fn clipboard_alternatives(&self, cx: &WindowContext) -> Vec<(SharedString, ClipboardItem)> {
+        self.read(cx).clipboard_alternatives(cx)
+    }
+
+    fn image(&self, cx: &WindowContext) -> Option<Arc<Image>> {
+        self.read(cx).image(cx)
+    }
 }
 
 pub enum Output {
@@ -126,6 +237,27 @@ pub enum Output {
         content: View<MarkdownView>,
         display_id: Option<String>,
     },
+    Html {
+        content: View<HtmlView>,
+        display_id: Option<String>,
+    },
+    Latex {
+        content: View<LatexView>,
+        display_id: Option<String>,
+    },
+    Json {
+        content: View<JsonView>,
+        display_id: Option<String>,
+    },
+    Chart {
+        content: View<VegaLiteView>,
+        display_id: Option<String>,
+    },
+    /// Display data with several representations the user can switch between.
+    DisplayData {
+        content: View<DisplayDataView>,
+        display_id: Option<String>,
+    },
     ClearOutputWaitMarker,
 }
 
@@ -173,25 +305,8 @@ impl Output {
                                 let buffer_content =
                                     v.update(cx, |item, cx| item.buffer_content(cx));
 
-                                if let Some(buffer_content) = buffer_content.as_ref() {
-                                    let buffer = buffer_content.clone();
-                                    let editor = Box::new(cx.new_view(|cx| {
-                                        let multibuffer = cx.new_model(|cx| {
-                                            let mut multi_buffer =
-                                                MultiBuffer::singleton(buffer.clone(), cx);
-
-                                            multi_buffer.set_title("REPL Output".to_string(), cx);
-                                            multi_buffer
-                                        });
-
-                                        Editor::for_multibuffer(multibuffer, None, false, cx)
-                                    }));
-                                    workspace
-                                        .update(cx, |workspace, cx| {
-                                            workspace
-                                                .add_item_to_active_pane(editor, None, true, cx);
-                                        })
-                                        .ok();
+                                if let Some(buffer_content) = buffer_content {
+                                    open_output_in_editor(buffer_content, workspace.upgrade(), cx);
                                 }
                             }
                         })),
@@ -201,22 +316,114 @@ impl Output {
         )
     }
 
-    pub fn render(
-        &self,
-
-        workspace: WeakView<Workspace>,
-        cx: &mut ViewContext<ExecutionView>,
-    ) -> impl IntoElement {
-        let content = match self {
+    /// Renders the content of the output, without its controls.
+    pub fn content(&self, cx: &mut WindowContext) -> Option<AnyElement> {
+        match self {
             Self::Plain { content, .. } => Some(content.clone().into_any_element()),
             Self::Markdown { content, .. } => Some(content.clone().into_any_element()),
+            Self::Html { content, .. } => Some(content.clone().into_any_element()),
+            Self::Latex { content, .. } => Some(content.clone().into_any_element()),
+            Self::Json { content, .. } => Some(content.clone().into_any_element()),
+            Self::Chart { content, .. } => Some(content.clone().into_any_element()),
+            Self::DisplayData { content, .. } => Some(content.clone().into_any_element()),
             Self::Stream { content, .. } => Some(content.clone().into_any_element()),
             Self::Image { content, .. } => Some(content.clone().into_any_element()),
             Self::Message(message) => Some(div().child(message.clone()).into_any_element()),
             Self::Table { content, .. } => Some(content.clone().into_any_element()),
             Self::ErrorOutput(error_view) => error_view.render(cx),
             Self::ClearOutputWaitMarker => None,
+        }
+    }
+
+    /// The view behind the output, for copying it or opening it in a buffer.
+    pub(crate) fn content_view(&self) -> Option<Box<dyn OutputContent>> {
+        match self {
+            Self::Plain { content, .. } => Some(Box::new(content.clone())),
+            Self::Markdown { content, .. } => Some(Box::new(content.clone())),
+            Self::Html { content, .. } => Some(Box::new(content.clone())),
+            Self::Latex { content, .. } => Some(Box::new(content.clone())),
+            Self::Json { content, .. } => Some(Box::new(content.clone())),
+            Self::Chart { content, .. } => Some(Box::new(content.clone())),
+            Self::DisplayData { content, .. } => Some(Box::new(content.clone())),
+            Self::Stream { content, .. } => Some(Box::new(content.clone())),
+            Self::Image { content, .. } => Some(Box::new(content.clone())),
+            Self::Table { content, .. } => Some(Box::new(content.clone())),
+            Self::ErrorOutput(error_view) => Some(Box::new(error_view.traceback.clone())),
+            Self::Message(_) | Self::ClearOutputWaitMarker => None,
+        }
+    }
+
+    /// Wraps rendered output content in a context menu for copying, saving, or opening the output.
+    pub(crate) fn with_context_menu(
+        &self,
+        id: impl Into<ElementId>,
+        content: AnyElement,
+        cx: &WindowContext,
+    ) -> AnyElement {
+        let Some(view) = self.content_view() else {
+            return content;
         };
+        if !view.has_clipboard_content(cx)
+            && !view.has_buffer_content(cx)
+            && view.clipboard_alternatives(cx).is_empty()
+            && view.image(cx).is_none()
+        {
+            return content;
+        }
+        let view = Rc::new(RefCell::new(view));
+
+        right_click_menu(id)
+            .trigger(div().w_full().child(content))
+            .menu(move |cx| {
+                let (entries, image, has_buffer_content) = {
+                    let view = view.borrow();
+                    let mut entries = Vec::new();
+                    if view.has_clipboard_content(cx) {
+                        entries.extend(
+                            view.clipboard_content(cx)
+                                .map(|item| (SharedString::from("Copy Output"), item)),
+                        );
+                    }
+                    entries.extend(view.clipboard_alternatives(cx));
+                    (entries, view.image(cx), view.has_buffer_content(cx))
+                };
+                let view = view.clone();
+
+                ContextMenu::build(cx, move |menu, _| {
+                    let mut menu = entries.into_iter().fold(menu, |menu, (label, item)| {
+                        menu.entry(label, None, move |cx| cx.write_to_clipboard(item.clone()))
+                    });
+
+                    if has_buffer_content {
+                        menu = menu
+                            .separator()
+                            .entry("Open Output in Editor", None, move |cx| {
+                                if let Some(buffer) = view.borrow_mut().buffer_content(cx) {
+                                    open_output_in_editor(buffer, None, cx);
+                                }
+                            });
+                    }
+
+                    match image {
+                        Some(image) => menu.separator().entry("Save Image As…", None, move |cx| {
+                            image::save_image_as(image.clone(), cx)
+                        }),
+                        None => menu,
+                    }
+                })
+            })
+            .into_any_element()
+    }
+
+    pub fn render(
+        &self,
+        index: usize,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<ExecutionView>,
+    ) -> impl IntoElement {
+        let content = self
+            .content(cx)
+            .map(|content| self.with_context_menu(("output-context-menu", index), content, cx));
 
         h_flex()
             .w_full()
@@ -229,6 +436,21 @@ impl Output {
                 Self::Markdown { content, .. } => {
                     Self::render_output_controls(content.clone(), workspace.clone(), cx)
                 }
+                Self::Html { content, .. } => {
+                    Self::render_output_controls(content.clone(), workspace.clone(), cx)
+                }
+                Self::Latex { content, .. } => {
+                    Self::render_output_controls(content.clone(), workspace.clone(), cx)
+                }
+                Self::Json { content, .. } => {
+                    Self::render_output_controls(content.clone(), workspace.clone(), cx)
+                }
+                Self::Chart { content, .. } => {
+                    Self::render_output_controls(content.clone(), workspace.clone(), cx)
+                }
+                Self::DisplayData { content, .. } => {
+                    Self::render_output_controls(content.clone(), workspace.clone(), cx)
+                }
                 Self::Stream { content, .. } => {
                     Self::render_output_controls(content.clone(), workspace.clone(), cx)
                 }
@@ -255,31 +477,99 @@ impl Output {
             Output::Message(_) => None,
             Output::Table { display_id, .. } => display_id.clone(),
             Output::Markdown { display_id, .. } => display_id.clone(),
+            Output::Html { display_id, .. } => display_id.clone(),
+            Output::Latex { display_id, .. } => display_id.clone(),
+            Output::Json { display_id, .. } => display_id.clone(),
+            Output::Chart { display_id, .. } => display_id.clone(),
+            Output::DisplayData { display_id, .. } => display_id.clone(),
             Output::ClearOutputWaitMarker => None,
         }
     }
 
     pub fn new(data: &MimeBundle, display_id: Option<String>, cx: &mut WindowContext) -> Self {
-        match data.richest(rank_mime_type) {
-            Some(MimeType::Plain(text)) => Output::Plain {
+        let priority = &NotebookSettings::get_global(cx).output_mime_type_priority;
+        let representations = ranked_mime_types(data, priority);
+        Self::from_representations(representations, display_id, cx)
+    }
+
+    /// Creates the output for display data saved in a notebook that isn't trusted, leaving out
+    /// the representations that require trust.
+    pub fn untrusted(data: &MimeBundle, cx: &mut WindowContext) -> Self {
+        let priority = &NotebookSettings::get_global(cx).output_mime_type_priority;
+        let mut representations = ranked_mime_types(data, priority);
+        let representation_count = representations.len();
+        representations.retain(|mimetype| !requires_trust(mimetype));
+
+        if representations.is_empty() && representation_count > 0 {
+            return Output::Message("Output hidden until the notebook is trusted".to_string());
+        }
+        Self::from_representations(representations, None, cx)
+    }
+
+    fn from_representations(
+        mut representations: Vec<MimeType>,
+        display_id: Option<String>,
+        cx: &mut WindowContext,
+    ) -> Self {
+        match representations.len() {
+            0 => Output::Message("Unsupported media type".to_string()),
+            1 => Output::from_mime_type(&representations.remove(0), display_id, cx),
+            _ => Output::DisplayData {
+                content: cx
+                    .new_view(|cx| DisplayDataView::new(representations, display_id.clone(), cx)),
+                display_id,
+            },
+        }
+    }
+
+    /// Creates the output for a single representation of display data.
+    pub(crate) fn from_mime_type(
+        mimetype: &MimeType,
+        display_id: Option<String>,
+        cx: &mut WindowContext,
+    ) -> Self {
+        match mimetype {
+            MimeType::Plain(text) => Output::Plain {
                 content: cx.new_view(|cx| TerminalOutput::from(text, cx)),
                 display_id,
             },
-            Some(MimeType::Markdown(text)) => {
+            MimeType::Markdown(text) => {
                 let view = cx.new_view(|cx| MarkdownView::from(text.clone(), cx));
                 Output::Markdown {
                     content: view,
                     display_id,
                 }
             }
-            Some(MimeType::Png(data)) | Some(MimeType::Jpeg(data)) => match ImageView::from(data) {
+            MimeType::Html(html) => match html::parse_dataframe(html) {
+                Some(table) => Output::Table {
+                    content: cx.new_view(|cx| TableView::new(&table, cx)),
+                    display_id,
+                },
+                None => Output::Html {
+                    content: cx.new_view(|cx| HtmlView::from(html.clone(), cx)),
+                    display_id,
+                },
+            },
+            MimeType::Json(json) => Output::Json {
+                content: cx.new_view(|cx| JsonView::new(json.clone(), cx)),
+                display_id,
+            },
+            MimeType::Latex(text) => Output::Latex {
+                content: cx.new_view(|_| LatexView::from(text.clone())),
+                display_id,
+            },
+            MimeType::Png(data) | MimeType::Jpeg(data) => match ImageView::from(data) {
                 Ok(view) => Output::Image {
                     content: cx.new_view(|_| view),
                     display_id,
                 },
                 Err(error) => Output::Message(format!("Failed to load image: {}", error)),
             },
-            Some(MimeType::DataTable(data)) => Output::Table {
+            MimeType::VegaLiteV4(spec) | MimeType::VegaLiteV5(spec) => Output::Chart {
+                content: cx.new_view(|_| VegaLiteView::from(spec)),
+                display_id,
+            },
+            MimeType::DataTable(data) => Output::Table {
                 content: cx.new_view(|cx| TableView::new(data, cx)),
                 display_id,
             },
@@ -289,7 +579,40 @@ impl Output {
     }
 }
 
-#[derive(Default, Clone, Debug)]
+/// Opens an output's buffer in a read-only editor tab, so it can be searched, folded and copied from.
+///
+/// Uses the workspace of the current window when none is given.
+pub(crate) fn open_output_in_editor(
+    buffer: Model<Buffer>,
+    workspace: Option<View<Workspace>>,
+    cx: &mut WindowContext,
+) {
+    let Some(workspace) = workspace.or_else(|| {
+        cx.window_handle()
+            .downcast::<Workspace>()
+            .and_then(|handle| handle.root(cx).ok())
+    }) else {
+        return;
+    };
+
+    let editor = Box::new(cx.new_view(|cx| {
+        let multibuffer = cx.new_model(|cx| {
+            let mut multi_buffer = MultiBuffer::singleton(buffer.clone(), cx);
+
+            multi_buffer.set_title("REPL Output".to_string(), cx);
+            multi_buffer
+        });
+
+        let mut editor = Editor::for_multibuffer(multibuffer, None, false, cx);
+        editor.set_read_only(true);
+        editor
+    }));
+    workspace.update(cx, |workspace, cx| {
+        workspace.add_item_to_active_pane(editor, None, true, cx);
+    });
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
 pub enum ExecutionStatus {
     #[default]
     Unknown,
@@ -328,78 +651,9 @@ impl ExecutionView {
 
     /// Accept a Jupyter message belonging to this execution
     pub fn push_message(&mut self, message: &JupyterMessageContent, cx: &mut ViewContext<Self>) {
-        let output: Output = match message {
-            JupyterMessageContent::ExecuteResult(result) => Output::new(
-                &result.data,
-                result.transient.as_ref().and_then(|t| t.display_id.clone()),
-                cx,
-            ),
-            JupyterMessageContent::DisplayData(result) => {
-                Output::new(&result.data, result.transient.display_id.clone(), cx)
-            }
-            JupyterMessageContent::StreamContent(result) => {
-                // Previous stream data will combine together, handling colors, carriage returns, etc
-                if let Some(new_terminal) = self.apply_terminal_text(&result.text, cx) {
-                    new_terminal
-                } else {
-                    return;
-                }
-            }
-            JupyterMessageContent::ErrorOutput(result) => {
-                let terminal =
-                    cx.new_view(|cx| TerminalOutput::from(&result.traceback.join("\n"), cx));
-
-                Output::ErrorOutput(ErrorView {
-                    ename: result.ename.clone(),
-                    evalue: result.evalue.clone(),
-                    traceback: terminal,
-                })
-            }
-            JupyterMessageContent::ExecuteReply(reply) => {
-                for payload in reply.payload.iter() {
-                    if let runtimelib::Payload::Page { data, .. } = payload {
-                        let output = Output::new(data, None, cx);
-                        self.outputs.push(output);
-                    }
-                }
-                cx.notify();
-                return;
-            }
-            JupyterMessageContent::ClearOutput(options) => {
-                if !options.wait {
-                    self.outputs.clear();
-                    cx.notify();
-                    return;
-                }
-
-                // Create a marker to clear the output after we get in a new output
-                Output::ClearOutputWaitMarker
-            }
-            JupyterMessageContent::Status(status) => {
-                match status.execution_state {
-                    ExecutionState::Busy => {
-                        self.status = ExecutionStatus::Executing;
-                    }
-                    ExecutionState::Idle => self.status = ExecutionStatus::Finished,
-                }
-                cx.notify();
-                return;
-            }
-            _msg => {
-                return;
-            }
-        };
-
-        // Check for a clear output marker as the previous output, so we can clear it out
-        if let Some(output) = self.outputs.last() {
-            if let Output::ClearOutputWaitMarker = output {
-                self.outputs.clear();
-            }
+        if push_message(&mut self.outputs, &mut self.status, message, cx) {
+            cx.notify();
         }
-
-        self.outputs.push(output);
-
-        cx.notify();
     }
 
     pub fn update_display_data(
@@ -408,42 +662,152 @@ impl ExecutionView {
         display_id: &str,
         cx: &mut ViewContext<Self>,
     ) {
-        let mut any = false;
+        if update_display_data(&mut self.outputs, data, display_id, cx) {
+            cx.notify();
+        }
+    }
+}
+
+impl From<&Kernel> for ExecutionStatus {
+    fn from(kernel: &Kernel) -> Self {
+        match kernel {
+            Kernel::Restarting => ExecutionStatus::Restarting,
+            Kernel::RunningKernel(_) => ExecutionStatus::Queued,
+            Kernel::StartingKernel(_) => ExecutionStatus::ConnectingToKernel,
+            Kernel::ErroredLaunch(error) | Kernel::Died(error) => {
+                ExecutionStatus::KernelErrored(error.clone())
+            }
+            Kernel::ShuttingDown => ExecutionStatus::ShuttingDown,
+            Kernel::Shutdown => ExecutionStatus::Shutdown,
+        }
+    }
+}
 
-        self.outputs.iter_mut().for_each(|output| {
-            if let Some(other_display_id) = output.display_id().as_ref() {
-                if other_display_id == display_id {
-                    *output = Output::new(data, Some(display_id.to_owned()), cx);
-                    any = true;
+/// Applies a Jupyter message to a list of outputs and the status of the execution they belong to.
+///
+/// This is shared between the inline editor outputs and notebook cells.
+/// Returns `true` if anything changed and the owning view should be notified.
+pub(crate) fn push_message(
+    outputs: &mut Vec<Output>,
+    status: &mut ExecutionStatus,
+    message: &JupyterMessageContent,
+    cx: &mut WindowContext,
+) -> bool {
+    let output: Output = match message {
+        JupyterMessageContent::ExecuteResult(result) => Output::new(
+            &result.data,
+            result.transient.as_ref().and_then(|t| t.display_id.clone()),
+            cx,
+        ),
+        JupyterMessageContent::DisplayData(result) => {
+            // Showing a display that is already on screen again updates it in place
+            if let Some(display_id) = result.transient.display_id.as_deref() {
+                if update_display_data(outputs, &result.data, display_id, cx) {
+                    return true;
                 }
             }
-        });
+            Output::new(&result.data, result.transient.display_id.clone(), cx)
+        }
+        JupyterMessageContent::StreamContent(result) => {
+            // Previous stream data will combine together, handling colors, carriage returns, etc
+            if let Some(new_terminal) = apply_terminal_text(outputs, &result.text, cx) {
+                new_terminal
+            } else {
+                return true;
+            }
+        }
+        JupyterMessageContent::ErrorOutput(result) => Output::ErrorOutput(ErrorView::new(
+            &result.ename,
+            &result.evalue,
+            &result.traceback,
+            cx,
+        )),
+        JupyterMessageContent::ExecuteReply(reply) => {
+            for payload in reply.payload.iter() {
+                if let runtimelib::Payload::Page { data, .. } = payload {
+                    let output = Output::new(data, None, cx);
+                    outputs.push(output);
+                }
+            }
+            return true;
+        }
+        JupyterMessageContent::ClearOutput(options) => {
+            if !options.wait {
+                outputs.clear();
+                return true;
+            }
 
-        if any {
-            cx.notify();
+            // Create a marker to clear the output after we get in a new output
+            Output::ClearOutputWaitMarker
+        }
+        JupyterMessageContent::Status(message_status) => {
+            match message_status.execution_state {
+                ExecutionState::Busy => {
+                    *status = ExecutionStatus::Executing;
+                }
+                ExecutionState::Idle => *status = ExecutionStatus::Finished,
+            }
+            return true;
+        }
+        _msg => {
+            return false;
+        }
+    };
+
+    // Check for a clear output marker as the previous output, so we can clear it out
+    if let Some(output) = outputs.last() {
+        if let Output::ClearOutputWaitMarker = output {
+            outputs.clear();
         }
     }
 
-    fn apply_terminal_text(&mut self, text: &str, cx: &mut ViewContext<Self>) -> Option<Output> {
-        if let Some(last_output) = self.outputs.last_mut() {
-            if let Output::Stream {
-                content: last_stream,
-            } = last_output
-            {
-                // Don't need to add a new output, we already have a terminal output
-                // and can just update the most recent terminal output
-                last_stream.update(cx, |last_stream, cx| {
-                    last_stream.append_text(text, cx);
-                    cx.notify();
-                });
-                return None;
+    outputs.push(output);
+
+    true
+}
+
+/// Replaces every output that was displayed with `display_id` by the new data.
+pub(crate) fn update_display_data(
+    outputs: &mut [Output],
+    data: &MimeBundle,
+    display_id: &str,
+    cx: &mut WindowContext,
+) -> bool {
+    let mut any = false;
+
+    outputs.iter_mut().for_each(|output| {
+        if let Some(other_display_id) = output.display_id().as_ref() {
+            if other_display_id == display_id {
+                *output = Output::new(data, Some(display_id.to_owned()), cx);
+                any = true;
             }
         }
+    });
 
-        Some(Output::Stream {
-            content: cx.new_view(|cx| TerminalOutput::from(text, cx)),
-        })
+    any
+}
+
+fn apply_terminal_text(
+    outputs: &mut [Output],
+    text: &str,
+    cx: &mut WindowContext,
+) -> Option<Output> {
+    if let Some(last_output) = outputs.last_mut() {
+        if let Output::Stream {
+            content: last_stream,
+        } = last_output
+        {
+            // Don't need to add a new output, we already have a terminal output
+            // and can just update the most recent terminal output
+            // The owner of the outputs is notified, at a rate it picks
+            last_stream.update(cx, |last_stream, cx| last_stream.append_text(text, cx));
+            return None;
+        }
     }
+
+    Some(Output::Stream {
+        content: cx.new_view(|cx| TerminalOutput::from(text, cx)),
+    })
 }
 
 impl Render for ExecutionView {
@@ -502,7 +866,8 @@ impl Render for ExecutionView {
             .children(
                 self.outputs
                     .iter()
-                    .map(|output| output.render(self.workspace.clone(), cx)),
+                    .enumerate()
+                    .map(|(index, output)| output.render(index, self.workspace.clone(), cx)),
             )
             .children(match self.status {
                 ExecutionStatus::Executing => vec![status],
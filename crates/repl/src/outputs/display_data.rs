@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use gpui::{AnyElement, ClipboardItem, Image, Model, WindowContext};
+use language::Buffer;
+use runtimelib::MimeType;
+use ui::{prelude::*, ContextMenu, PopoverMenu, Tooltip};
+
+use crate::outputs::{mime_type_display_name, mime_type_name, Output, OutputContent};
+
+/// Display data that came with more than one representation, e.g. HTML, PNG and plain text.
+/// It shows the preferred one, and lets the user switch to any of the others.
+pub struct DisplayDataView {
+    /// The representations Zed can display, most preferred first.
+    representations: Vec<MimeType>,
+    selected: usize,
+    output: Output,
+    display_id: Option<String>,
+}
+
+impl DisplayDataView {
+    pub fn new(
+        representations: Vec<MimeType>,
+        display_id: Option<String>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let output = Output::from_mime_type(&representations[0], display_id.clone(), cx);
+        Self {
+            representations,
+            selected: 0,
+            output,
+            display_id,
+        }
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    fn show_as(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(mime_type) = self.representations.get(index) else {
+            return;
+        };
+        self.output = Output::from_mime_type(mime_type, self.display_id.clone(), cx);
+        self.selected = index;
+        cx.notify();
+    }
+
+    fn render_show_as_menu(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let this = cx.view().downgrade();
+        let options = self
+            .representations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, mime_type)| {
+                let name = mime_type_name(mime_type)?;
+                Some((index, mime_type_display_name(name), index == self.selected))
+            })
+            .collect::<Vec<_>>();
+
+        PopoverMenu::new("show-output-as")
+            .menu(move |cx| {
+                let options = options.clone();
+                let this = this.clone();
+                Some(ContextMenu::build(cx, move |menu, _| {
+                    options.into_iter().fold(
+                        menu.header("Show As"),
+                        |menu, (index, label, selected)| {
+                            let this = this.clone();
+                            menu.toggleable_entry(
+                                label,
+                                selected,
+                                IconPosition::Start,
+                                None,
+                                move |cx| {
+                                    this.update(cx, |this, cx| this.show_as(index, cx)).ok();
+                                },
+                            )
+                        },
+                    )
+                }))
+            })
+            .trigger(
+                IconButton::new("show-output-as-trigger", IconName::Ellipsis)
+                    .style(ButtonStyle::Transparent)
+                    .icon_size(IconSize::XSmall)
+                    .tooltip(|cx| Tooltip::text("Show As…", cx)),
+            )
+    }
+}
+
+impl OutputContent for DisplayDataView {
+    fn clipboard_content(&self, cx: &WindowContext) -> Option<ClipboardItem> {
+        self.output.content_view()?.clipboard_content(cx)
+    }
+
+    fn has_clipboard_content(&self, cx: &WindowContext) -> bool {
+        self.output
+            .content_view()
+            .map_or(false, |content| content.has_clipboard_content(cx))
+    }
+
+    fn has_buffer_content(&self, cx: &WindowContext) -> bool {
+        self.output
+            .content_view()
+            .map_or(false, |content| content.has_buffer_content(cx))
+    }
+
+    fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
+        self.output.content_view()?.buffer_content(cx)
+    }
+
+    fn image(&self, cx: &WindowContext) -> Option<Arc<Image>> {
+        self.output.content_view()?.image(cx)
+    }
+
+    fn clipboard_alternatives(&self, cx: &WindowContext) -> Vec<(SharedString, ClipboardItem)> {
+        let mut alternatives = self
+            .output
+            .content_view()
+            .map(|content| content.clipboard_alternatives(cx))
+            .unwrap_or_default();
+
+        // Rich outputs usually come with a plain text version, which pastes well anywhere
+        let plain_text =
+            self.representations.iter().enumerate().find_map(
+                |(index, mime_type)| match mime_type {
+                    MimeType::Plain(text) if index != self.selected => Some(text.clone()),
+                    _ => None,
+                },
+            );
+        if let Some(text) = plain_text {
+            alternatives.push(("Copy as Plain Text".into(), ClipboardItem::new_string(text)));
+        }
+
+        alternatives
+    }
+}
+
+impl Render for DisplayDataView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content: Option<AnyElement> = self.output.content(cx);
+
+        h_flex()
+            .w_full()
+            .items_start()
+            .child(div().flex_1().children(content))
+            .child(self.render_show_as_menu(cx))
+    }
+}
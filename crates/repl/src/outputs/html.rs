@@ -0,0 +1,1122 @@
+//! # HTML Output
+//!
+//! Renders `text/html` outputs, like the tables pandas produces or `IPython.display.HTML`,
+//! natively in GPUI instead of in a browser engine.
+//!
+//! Only a safe subset of HTML is understood: headings, paragraphs, lists, tables, preformatted
+//! text, and inline color, background, bold and italic styles. Scripts are never run. Anything
+//! outside of the subset (scripts, images, widgets, embedded frames) is left out, and the output
+//! offers to open the original HTML in the browser instead.
+
+use std::ops::Range;
+
+use anyhow::{Context as _, Result};
+use gpui::{
+    ClipboardItem, FontStyle, FontWeight, HighlightStyle, Hsla, Model, Rgba, StyledText, Task,
+    TextRun, TextStyle,
+};
+use html5ever::{
+    driver::ParseOpts, parse_document, tendril::TendrilSink as _, tree_builder::TreeBuilderOpts,
+    Attribute,
+};
+use language::Buffer;
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use runtimelib::media::datatable::TabularDataResource;
+use serde_json::{json, Map, Value};
+use ui::{h_flex, prelude::*, v_flex};
+use util::ResultExt as _;
+
+use crate::outputs::{table::rows_to_tsv, OutputContent};
+
+const TABLE_CELL_PADDING: f32 = 22.;
+
+pub struct HtmlView {
+    raw_html: String,
+    contents: Option<HtmlDocument>,
+    parsing_html_task: Option<Task<Result<()>>>,
+}
+
+impl HtmlView {
+    pub fn from(html: String, cx: &mut ViewContext<Self>) -> Self {
+        let task = cx.spawn(|html_view, mut cx| {
+            let html = html.clone();
+            let parsed = cx
+                .background_executor()
+                .spawn(async move { parse_html(&html) });
+
+            async move {
+                let mut document = parsed.await.log_err().unwrap_or_else(|| HtmlDocument {
+                    blocks: Vec::new(),
+                    has_unsupported_content: true,
+                });
+
+                html_view.update(&mut cx, |html_view, cx| {
+                    measure_tables(&mut document.blocks, cx);
+                    html_view.parsing_html_task.take();
+                    html_view.contents = Some(document);
+                    cx.notify();
+                })
+            }
+        });
+
+        Self {
+            raw_html: html,
+            contents: None,
+            parsing_html_task: Some(task),
+        }
+    }
+
+    fn open_in_browser(&mut self, cx: &mut ViewContext<Self>) {
+        let html = self.raw_html.clone();
+        let path = std::env::temp_dir().join(format!("zed-output-{}.html", cx.entity_id()));
+
+        cx.spawn(|_, mut cx| async move {
+            cx.background_executor()
+                .spawn({
+                    let path = path.clone();
+                    async move { std::fs::write(&path, html) }
+                })
+                .await?;
+            cx.update(|cx| cx.open_with_system(&path))?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn render_open_in_browser(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        h_flex()
+            .gap_2()
+            .child(
+                Label::new("Some of this output can't be displayed in Zed")
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Button::new("open-html-in-browser", "Open in Browser")
+                    .icon(IconName::ArrowUpRight)
+                    .icon_position(IconPosition::End)
+                    .icon_size(IconSize::XSmall)
+                    .label_size(LabelSize::Small)
+                    .on_click(cx.listener(|this, _, cx| this.open_in_browser(cx))),
+            )
+    }
+}
+
+impl OutputContent for HtmlView {
+    fn clipboard_content(&self, _cx: &WindowContext) -> Option<ClipboardItem> {
+        Some(ClipboardItem::new_string(self.raw_html.clone()))
+    }
+
+    fn clipboard_alternatives(&self, _cx: &WindowContext) -> Vec<(SharedString, ClipboardItem)> {
+        let Some(document) = self.contents.as_ref() else {
+            return Vec::new();
+        };
+
+        let tables = document.tables_as_tsv();
+        let count = tables.len();
+        tables
+            .into_iter()
+            .enumerate()
+            .map(|(ix, tsv)| {
+                let label = if count == 1 {
+                    "Copy Table as TSV".into()
+                } else {
+                    format!("Copy Table {} as TSV", ix + 1).into()
+                };
+                (label, ClipboardItem::new_string(tsv))
+            })
+            .collect()
+    }
+
+    fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn has_buffer_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
+        let buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::local(self.raw_html.clone(), cx)
+                .with_language(language::PLAIN_TEXT.clone(), cx);
+            buffer.set_capability(language::Capability::ReadOnly, cx);
+            buffer
+        });
+        Some(buffer)
+    }
+}
+
+impl Render for HtmlView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let Some(document) = self.contents.as_ref() else {
+            return div().into_any_element();
+        };
+
+        let text_style = cx.text_style();
+
+        v_flex()
+            .gap_2()
+            .children(
+                document
+                    .blocks
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, block)| render_block(ix, block, &text_style, cx)),
+            )
+            .when(
+                document.has_unsupported_content || document.blocks.is_empty(),
+                |this| this.child(self.render_open_in_browser(cx)),
+            )
+            .into_any_element()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SpanStyle {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    color: Option<Hsla>,
+    background: Option<Hsla>,
+}
+
+/// Text with inline styles applied to ranges of it.
+#[derive(Debug, Default, PartialEq)]
+struct StyledSpans {
+    text: String,
+    styles: Vec<(Range<usize>, SpanStyle)>,
+}
+
+impl StyledSpans {
+    fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    /// Appends text, collapsing whitespace the way HTML does.
+    fn push_text(&mut self, text: &str, style: SpanStyle) {
+        let mut collapsed = String::with_capacity(text.len());
+        let mut previous_was_space =
+            self.text.is_empty() || self.text.ends_with(char::is_whitespace);
+
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if !previous_was_space {
+                    collapsed.push(' ');
+                }
+                previous_was_space = true;
+            } else {
+                collapsed.push(ch);
+                previous_was_space = false;
+            }
+        }
+
+        self.push_raw(&collapsed, style);
+    }
+
+    fn push_raw(&mut self, text: &str, style: SpanStyle) {
+        if text.is_empty() {
+            return;
+        }
+
+        let start = self.text.len();
+        self.text.push_str(text);
+        if style != SpanStyle::default() {
+            self.styles.push((start..self.text.len(), style));
+        }
+    }
+
+    fn trim_end(&mut self) {
+        let len = self.text.trim_end().len();
+        self.text.truncate(len);
+        self.styles.retain_mut(|(range, _)| {
+            range.end = range.end.min(len);
+            range.start < range.end
+        });
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum HtmlBlock {
+    Heading {
+        level: usize,
+        content: StyledSpans,
+    },
+    Paragraph(StyledSpans),
+    Preformatted(String),
+    List {
+        ordered: bool,
+        items: Vec<Vec<HtmlBlock>>,
+    },
+    Table {
+        rows: Vec<HtmlTableRow>,
+        /// Filled in on the main thread once the document is parsed, since it needs the text system.
+        column_widths: Vec<Pixels>,
+    },
+    Rule,
+}
+
+#[derive(Debug, PartialEq)]
+struct HtmlTableRow {
+    is_header: bool,
+    cells: Vec<StyledSpans>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct HtmlDocument {
+    blocks: Vec<HtmlBlock>,
+    /// Whether anything was left out because it can't be displayed natively.
+    has_unsupported_content: bool,
+}
+
+impl HtmlDocument {
+    /// Every top level table in the document, as tab-separated values.
+    fn tables_as_tsv(&self) -> Vec<String> {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                HtmlBlock::Table { rows, .. } => Some(rows_to_tsv(rows.iter().map(|row| {
+                    row.cells
+                        .iter()
+                        .map(|cell| cell.text.trim().to_string())
+                        .collect()
+                }))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_html(html: &str) -> Result<HtmlDocument> {
+    let parse_options = ParseOpts {
+        tree_builder: TreeBuilderOpts {
+            drop_doctype: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let dom = parse_document(RcDom::default(), parse_options)
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .context("failed to parse HTML")?;
+
+    let mut converter = HtmlConverter::default();
+    let mut blocks = Vec::new();
+    let mut paragraph = StyledSpans::default();
+    converter.visit_block(
+        &dom.document,
+        SpanStyle::default(),
+        &mut blocks,
+        &mut paragraph,
+    );
+    flush_paragraph(&mut blocks, &mut paragraph);
+
+    Ok(HtmlDocument {
+        blocks,
+        has_unsupported_content: converter.has_unsupported_content,
+    })
+}
+
+/// Recognizes the HTML tables pandas renders DataFrames as, and converts them to a table resource
+/// so they can be shown with the interactive table view.
+pub(crate) fn parse_dataframe(html: &str) -> Option<TabularDataResource> {
+    if !html.contains("dataframe") {
+        return None;
+    }
+
+    let document = parse_html(html).ok()?;
+    let mut tables = document.blocks.iter().filter_map(|block| match block {
+        HtmlBlock::Table { rows, .. } => Some(rows),
+        _ => None,
+    });
+    let rows = tables.next()?;
+    // Besides the table, pandas only adds a line like "5 rows × 3 columns"
+    let has_other_content = document
+        .blocks
+        .iter()
+        .any(|block| !matches!(block, HtmlBlock::Table { .. } | HtmlBlock::Paragraph(_)));
+    if tables.next().is_some() || has_other_content || document.has_unsupported_content {
+        return None;
+    }
+
+    let header_rows = rows
+        .iter()
+        .take_while(|row| row.is_header)
+        .collect::<Vec<_>>();
+    let body_rows = &rows[header_rows.len()..];
+    let column_count = rows.iter().map(|row| row.cells.len()).max()?;
+
+    // The last header row has the column names, but the index name is on a row of its own
+    let mut names = Vec::<String>::with_capacity(column_count);
+    for column in 0..column_count {
+        let name = header_rows
+            .iter()
+            .rev()
+            .filter_map(|row| row.cells.get(column))
+            .map(|cell| cell.text.trim())
+            .find(|name| !name.is_empty())
+            .unwrap_or(if column == 0 { "index" } else { "" });
+        let mut unique_name = name.to_string();
+        let mut suffix = 1;
+        while unique_name.is_empty() || names.contains(&unique_name) {
+            unique_name = format!("{name}_{suffix}");
+            suffix += 1;
+        }
+        names.push(unique_name);
+    }
+
+    let columns = (0..column_count)
+        .map(|column| {
+            body_rows
+                .iter()
+                .map(|row| {
+                    row.cells
+                        .get(column)
+                        .map(|cell| cell.text.trim().to_string())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut fields = Vec::with_capacity(column_count);
+    let mut data = vec![Map::new(); body_rows.len()];
+    for (name, cells) in names.iter().zip(columns) {
+        // Truncated frames have rows and columns of "..."
+        let values = cells
+            .iter()
+            .filter(|cell| !cell.is_empty() && cell.as_str() != "...");
+        let field_type = if values.clone().all(|cell| cell.parse::<i64>().is_ok()) {
+            "integer"
+        } else if values.clone().all(|cell| cell.parse::<f64>().is_ok()) {
+            "number"
+        } else {
+            "string"
+        };
+        fields.push(json!({"name": name, "type": field_type}));
+
+        for (row, cell) in data.iter_mut().zip(cells) {
+            let value = match field_type {
+                "integer" => cell.parse::<i64>().map(Value::from).ok(),
+                "number" => cell.parse::<f64>().ok().map(Value::from),
+                _ => None,
+            };
+            row.insert(name.clone(), value.unwrap_or(Value::String(cell)));
+        }
+    }
+
+    serde_json::from_value(json!({
+        "schema": {"fields": fields},
+        "data": data,
+    }))
+    .log_err()
+}
+
+/// Elements whose contents are never displayed, as they describe the document rather than being part of it.
+fn is_hidden_element(tag: &str) -> bool {
+    matches!(
+        tag,
+        "head" | "title" | "meta" | "link" | "style" | "template"
+    )
+}
+
+/// Elements that need a browser to display, which are left out of the native rendering.
+fn is_unsupported_element(tag: &str) -> bool {
+    matches!(
+        tag,
+        "script"
+            | "iframe"
+            | "object"
+            | "embed"
+            | "canvas"
+            | "svg"
+            | "math"
+            | "img"
+            | "picture"
+            | "video"
+            | "audio"
+            | "form"
+            | "input"
+            | "button"
+            | "select"
+            | "textarea"
+    )
+}
+
+fn is_block_element(tag: &str) -> bool {
+    matches!(
+        tag,
+        "html"
+            | "body"
+            | "p"
+            | "div"
+            | "section"
+            | "article"
+            | "aside"
+            | "header"
+            | "footer"
+            | "main"
+            | "nav"
+            | "blockquote"
+            | "figure"
+            | "figcaption"
+            | "details"
+            | "summary"
+            | "center"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "li"
+            | "caption"
+    )
+}
+
+fn flush_paragraph(blocks: &mut Vec<HtmlBlock>, paragraph: &mut StyledSpans) {
+    let mut paragraph = std::mem::take(paragraph);
+    paragraph.trim_end();
+    if !paragraph.is_empty() {
+        blocks.push(HtmlBlock::Paragraph(paragraph));
+    }
+}
+
+fn attribute(attrs: &[Attribute], name: &str) -> Option<String> {
+    attrs
+        .iter()
+        .find(|attr| &*attr.name.local == name)
+        .map(|attr| attr.value.to_string())
+}
+
+#[derive(Default)]
+struct HtmlConverter {
+    has_unsupported_content: bool,
+}
+
+impl HtmlConverter {
+    fn visit_children_block(
+        &mut self,
+        node: &Handle,
+        style: SpanStyle,
+        blocks: &mut Vec<HtmlBlock>,
+        paragraph: &mut StyledSpans,
+    ) {
+        for child in node.children.borrow().iter() {
+            self.visit_block(child, style, blocks, paragraph);
+        }
+    }
+
+    fn visit_block(
+        &mut self,
+        node: &Handle,
+        style: SpanStyle,
+        blocks: &mut Vec<HtmlBlock>,
+        paragraph: &mut StyledSpans,
+    ) {
+        let (tag, style) = match &node.data {
+            NodeData::Document => {
+                self.visit_children_block(node, style, blocks, paragraph);
+                return;
+            }
+            NodeData::Text { contents } => {
+                paragraph.push_text(&contents.borrow(), style);
+                return;
+            }
+            NodeData::Element { name, attrs, .. } => {
+                let tag = name.local.to_string();
+                let style = element_style(&tag, &attrs.borrow(), style);
+                (tag, style)
+            }
+            NodeData::Doctype { .. }
+            | NodeData::Comment { .. }
+            | NodeData::ProcessingInstruction { .. } => return,
+        };
+
+        match tag.as_str() {
+            tag if is_hidden_element(tag) => {}
+            tag if is_unsupported_element(tag) => self.has_unsupported_content = true,
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                flush_paragraph(blocks, paragraph);
+                let mut content = StyledSpans::default();
+                self.visit_children_inline(node, style, &mut content);
+                content.trim_end();
+                if !content.is_empty() {
+                    blocks.push(HtmlBlock::Heading {
+                        level: tag[1..].parse().unwrap_or(1),
+                        content,
+                    });
+                }
+            }
+            "br" => paragraph.push_raw("\n", style),
+            "hr" => {
+                flush_paragraph(blocks, paragraph);
+                blocks.push(HtmlBlock::Rule);
+            }
+            "pre" => {
+                flush_paragraph(blocks, paragraph);
+                let mut text = String::new();
+                self.collect_text(node, &mut text);
+                let text = text.trim_end_matches('\n');
+                if !text.is_empty() {
+                    blocks.push(HtmlBlock::Preformatted(text.to_string()));
+                }
+            }
+            "ul" | "ol" => {
+                flush_paragraph(blocks, paragraph);
+                let items = node
+                    .children
+                    .borrow()
+                    .iter()
+                    .filter(|child| element_name(child).as_deref() == Some("li"))
+                    .map(|item| {
+                        let mut item_blocks = Vec::new();
+                        let mut item_paragraph = StyledSpans::default();
+                        self.visit_children_block(
+                            item,
+                            style,
+                            &mut item_blocks,
+                            &mut item_paragraph,
+                        );
+                        flush_paragraph(&mut item_blocks, &mut item_paragraph);
+                        item_blocks
+                    })
+                    .collect::<Vec<_>>();
+                if !items.is_empty() {
+                    blocks.push(HtmlBlock::List {
+                        ordered: tag == "ol",
+                        items,
+                    });
+                }
+            }
+            "table" => {
+                flush_paragraph(blocks, paragraph);
+                let mut rows = Vec::new();
+                self.collect_table_rows(node, style, false, &mut rows);
+                if !rows.is_empty() {
+                    blocks.push(HtmlBlock::Table {
+                        rows,
+                        column_widths: Vec::new(),
+                    });
+                }
+            }
+            tag if is_block_element(tag) => {
+                flush_paragraph(blocks, paragraph);
+                self.visit_children_block(node, style, blocks, paragraph);
+                flush_paragraph(blocks, paragraph);
+            }
+            _ => self.visit_children_block(node, style, blocks, paragraph),
+        }
+    }
+
+    fn visit_children_inline(&mut self, node: &Handle, style: SpanStyle, spans: &mut StyledSpans) {
+        for child in node.children.borrow().iter() {
+            self.visit_inline(child, style, spans);
+        }
+    }
+
+    /// Collects the text of headings and table cells, where nested blocks are flattened into one line.
+    fn visit_inline(&mut self, node: &Handle, style: SpanStyle, spans: &mut StyledSpans) {
+        match &node.data {
+            NodeData::Text { contents } => spans.push_text(&contents.borrow(), style),
+            NodeData::Element { name, attrs, .. } => {
+                let tag = name.local.to_string();
+                let style = element_style(&tag, &attrs.borrow(), style);
+
+                if is_hidden_element(&tag) {
+                } else if is_unsupported_element(&tag) {
+                    self.has_unsupported_content = true;
+                } else if tag == "br" {
+                    spans.push_raw("\n", style);
+                } else {
+                    self.visit_children_inline(node, style, spans);
+                    if is_block_element(&tag) {
+                        spans.push_text(" ", style);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_table_rows(
+        &mut self,
+        node: &Handle,
+        style: SpanStyle,
+        in_header: bool,
+        rows: &mut Vec<HtmlTableRow>,
+    ) {
+        for child in node.children.borrow().iter() {
+            let NodeData::Element { name, attrs, .. } = &child.data else {
+                continue;
+            };
+            let tag = name.local.to_string();
+            let style = element_style(&tag, &attrs.borrow(), style);
+
+            match tag.as_str() {
+                "thead" => self.collect_table_rows(child, style, true, rows),
+                "tbody" | "tfoot" => self.collect_table_rows(child, style, false, rows),
+                "tr" => {
+                    let mut all_header_cells = true;
+                    let cells = child
+                        .children
+                        .borrow()
+                        .iter()
+                        .filter_map(|cell| {
+                            let NodeData::Element { name, attrs, .. } = &cell.data else {
+                                return None;
+                            };
+                            let tag = name.local.to_string();
+                            if tag != "th" && tag != "td" {
+                                return None;
+                            }
+                            all_header_cells &= tag == "th";
+
+                            let style = element_style(&tag, &attrs.borrow(), style);
+                            let mut content = StyledSpans::default();
+                            self.visit_children_inline(cell, style, &mut content);
+                            content.trim_end();
+                            Some(content)
+                        })
+                        .collect::<Vec<_>>();
+
+                    if !cells.is_empty() {
+                        rows.push(HtmlTableRow {
+                            is_header: in_header || all_header_cells,
+                            cells,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn collect_text(&mut self, node: &Handle, text: &mut String) {
+        for child in node.children.borrow().iter() {
+            match &child.data {
+                NodeData::Text { contents } => text.push_str(&contents.borrow()),
+                NodeData::Element { name, .. } => {
+                    let tag = name.local.to_string();
+                    if is_hidden_element(&tag) {
+                    } else if is_unsupported_element(&tag) {
+                        self.has_unsupported_content = true;
+                    } else if tag == "br" {
+                        text.push('\n');
+                    } else {
+                        self.collect_text(child, text);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn element_name(node: &Handle) -> Option<String> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(name.local.to_string()),
+        _ => None,
+    }
+}
+
+fn element_style(tag: &str, attrs: &[Attribute], parent_style: SpanStyle) -> SpanStyle {
+    let mut style = parent_style;
+
+    match tag {
+        "b" | "strong" | "th" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => style.bold = true,
+        "i" | "em" | "cite" | "var" => style.italic = true,
+        "code" | "tt" | "kbd" | "samp" => style.code = true,
+        "font" => {
+            if let Some(color) = attribute(attrs, "color").and_then(|color| parse_css_color(&color))
+            {
+                style.color = Some(color);
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(css) = attribute(attrs, "style") {
+        apply_inline_css(&mut style, &css);
+    }
+
+    style
+}
+
+fn apply_inline_css(style: &mut SpanStyle, css: &str) {
+    for declaration in css.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_end_matches("!important").trim();
+
+        match property.trim().to_ascii_lowercase().as_str() {
+            "color" => {
+                if let Some(color) = parse_css_color(value) {
+                    style.color = Some(color);
+                }
+            }
+            "background-color" | "background" => {
+                if let Some(color) = parse_css_color(value) {
+                    style.background = Some(color);
+                }
+            }
+            "font-weight" => {
+                style.bold = matches!(value, "bold" | "bolder")
+                    || value.parse::<u16>().map_or(false, |weight| weight >= 600);
+            }
+            "font-style" => style.italic = matches!(value, "italic" | "oblique"),
+            _ => {}
+        }
+    }
+}
+
+/// Parses the CSS color syntaxes that show up in notebook outputs: hex, `rgb()`/`rgba()`, and basic named colors.
+pub(crate) fn parse_css_color(value: &str) -> Option<Hsla> {
+    let value = value.trim().to_ascii_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if !hex.is_ascii() {
+            return None;
+        }
+        let channel = |range: Range<usize>| {
+            let digits = &hex[range];
+            let digits = if digits.len() == 1 {
+                digits.repeat(2)
+            } else {
+                digits.to_string()
+            };
+            u8::from_str_radix(&digits, 16).ok()
+        };
+
+        let (r, g, b, a) = match hex.len() {
+            3 => (channel(0..1)?, channel(1..2)?, channel(2..3)?, 255),
+            6 => (channel(0..2)?, channel(2..4)?, channel(4..6)?, 255),
+            8 => (
+                channel(0..2)?,
+                channel(2..4)?,
+                channel(4..6)?,
+                channel(6..8)?,
+            ),
+            _ => return None,
+        };
+        return Some(rgba_color(r, g, b, a as f32 / 255.));
+    }
+
+    if let Some(arguments) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|arguments| arguments.strip_suffix(')'))
+    {
+        let arguments = arguments
+            .split(|ch: char| ch == ',' || ch == '/' || ch.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .collect::<Vec<_>>();
+        if arguments.len() < 3 {
+            return None;
+        }
+
+        let channel = |argument: &str| -> Option<u8> {
+            let value = match argument.strip_suffix('%') {
+                Some(percentage) => percentage.parse::<f32>().ok()? * 2.55,
+                None => argument.parse::<f32>().ok()?,
+            };
+            Some(value.round().clamp(0., 255.) as u8)
+        };
+        let alpha = match arguments.get(3) {
+            Some(argument) => match argument.strip_suffix('%') {
+                Some(percentage) => percentage.parse::<f32>().ok()? / 100.,
+                None => argument.parse::<f32>().ok()?,
+            },
+            None => 1.,
+        };
+
+        return Some(rgba_color(
+            channel(arguments[0])?,
+            channel(arguments[1])?,
+            channel(arguments[2])?,
+            alpha.clamp(0., 1.),
+        ));
+    }
+
+    let hex = match value.as_str() {
+        "black" => 0x000000,
+        "white" => 0xffffff,
+        "red" => 0xff0000,
+        "green" => 0x008000,
+        "blue" => 0x0000ff,
+        "yellow" => 0xffff00,
+        "orange" => 0xffa500,
+        "purple" => 0x800080,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xc0c0c0,
+        "maroon" => 0x800000,
+        "olive" => 0x808000,
+        "lime" => 0x00ff00,
+        "aqua" | "cyan" => 0x00ffff,
+        "teal" => 0x008080,
+        "navy" => 0x000080,
+        "fuchsia" | "magenta" => 0xff00ff,
+        _ => return None,
+    };
+    Some(gpui::rgb(hex).into())
+}
+
+fn rgba_color(r: u8, g: u8, b: u8, a: f32) -> Hsla {
+    Rgba {
+        r: r as f32 / 255.,
+        g: g as f32 / 255.,
+        b: b as f32 / 255.,
+        a,
+    }
+    .into()
+}
+
+/// Sizes each table column to fit its widest cell, like the `application/vnd.dataresource+json` table view.
+fn measure_tables(blocks: &mut [HtmlBlock], cx: &WindowContext) {
+    let text_style = cx.text_style();
+    let font_size = text_style.font_size.to_pixels(cx.rem_size());
+
+    for block in blocks {
+        match block {
+            HtmlBlock::Table {
+                rows,
+                column_widths,
+            } => {
+                for row in rows.iter() {
+                    let mut font = text_style.font();
+                    if row.is_header {
+                        font.weight = FontWeight::BOLD;
+                    }
+
+                    for (ix, cell) in row.cells.iter().enumerate() {
+                        let width = cell
+                            .text
+                            .lines()
+                            .map(|line| {
+                                let runs = [TextRun {
+                                    len: line.len(),
+                                    font: font.clone(),
+                                    color: text_style.color,
+                                    background_color: None,
+                                    underline: None,
+                                    strikethrough: None,
+                                }];
+                                cx.text_system()
+                                    .layout_line(line, font_size, &runs)
+                                    .map(|layout| layout.width)
+                                    .unwrap_or(px(0.))
+                            })
+                            .fold(px(0.), |max_width, width| max_width.max(width));
+
+                        if column_widths.len() <= ix {
+                            column_widths.resize(ix + 1, px(0.));
+                        }
+                        column_widths[ix] = column_widths[ix].max(width);
+                    }
+                }
+            }
+            HtmlBlock::List { items, .. } => {
+                for item in items {
+                    measure_tables(item, cx);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn render_styled_text(
+    spans: &StyledSpans,
+    text_style: &TextStyle,
+    cx: &WindowContext,
+) -> StyledText {
+    let code_background = cx.theme().colors().editor_background;
+
+    StyledText::new(spans.text.clone()).with_highlights(
+        text_style,
+        spans.styles.iter().map(|(range, style)| {
+            let highlight = HighlightStyle {
+                color: style.color,
+                font_weight: style.bold.then_some(FontWeight::BOLD),
+                font_style: style.italic.then_some(FontStyle::Italic),
+                background_color: style.background.or(style.code.then_some(code_background)),
+                ..Default::default()
+            };
+            (range.clone(), highlight)
+        }),
+    )
+}
+
+fn render_block(
+    ix: usize,
+    block: &HtmlBlock,
+    text_style: &TextStyle,
+    cx: &WindowContext,
+) -> AnyElement {
+    let colors = cx.theme().colors();
+
+    match block {
+        HtmlBlock::Heading { level, content } => div()
+            .map(|this| match level {
+                1 => this.text_xl(),
+                2 => this.text_lg(),
+                _ => this,
+            })
+            .child(render_styled_text(content, text_style, cx))
+            .into_any_element(),
+        HtmlBlock::Paragraph(content) => div()
+            .child(render_styled_text(content, text_style, cx))
+            .into_any_element(),
+        HtmlBlock::Preformatted(text) => div()
+            .w_full()
+            .p_2()
+            .rounded_md()
+            .bg(colors.editor_background)
+            .font_buffer(cx)
+            .child(text.clone())
+            .into_any_element(),
+        HtmlBlock::List { ordered, items } => v_flex()
+            .gap_1()
+            .children(items.iter().enumerate().map(|(item_ix, item)| {
+                let marker = if *ordered {
+                    format!("{}.", item_ix + 1)
+                } else {
+                    "•".to_string()
+                };
+
+                h_flex()
+                    .items_start()
+                    .gap_2()
+                    .child(div().flex_none().child(marker))
+                    .child(
+                        v_flex().gap_1().children(
+                            item.iter()
+                                .enumerate()
+                                .map(|(ix, block)| render_block(ix, block, text_style, cx)),
+                        ),
+                    )
+            }))
+            .into_any_element(),
+        HtmlBlock::Table {
+            rows,
+            column_widths,
+        } => v_flex()
+            .id(("html-table", ix))
+            .overflow_x_scroll()
+            .w_full()
+            .children(rows.iter().map(|row| {
+                h_flex().children(row.cells.iter().enumerate().map(|(cell_ix, cell)| {
+                    let width = column_widths.get(cell_ix).copied().unwrap_or_default()
+                        + px(TABLE_CELL_PADDING);
+
+                    div()
+                        .flex_none()
+                        .min_w(width)
+                        .w(width)
+                        .px_2()
+                        .py_0p5()
+                        .border_1()
+                        .border_color(colors.border)
+                        .when(row.is_header, |this| this.bg(colors.border_focused))
+                        .child(render_styled_text(cell, text_style, cx))
+                }))
+            }))
+            .into_any_element(),
+        HtmlBlock::Rule => div().w_full().h_px().bg(colors.border).into_any_element(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_html_dataframe() {
+        let document = parse_html(
+            r#"<div>
+            <style scoped>.dataframe tbody tr th { vertical-align: top; }</style>
+            <table border="1" class="dataframe">
+              <thead><tr style="text-align: right;"><th></th><th>a</th></tr></thead>
+              <tbody><tr><th>0</th><td>1</td></tr></tbody>
+            </table>
+            </div>"#,
+        )
+        .unwrap();
+
+        assert!(!document.has_unsupported_content);
+        let [HtmlBlock::Table { rows, .. }] = document.blocks.as_slice() else {
+            panic!("expected a single table, got {:?}", document.blocks);
+        };
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].is_header);
+        assert!(!rows[1].is_header);
+        assert_eq!(rows[1].cells[1].text, "1");
+    }
+
+    #[test]
+    fn test_tables_as_tsv() {
+        let document = parse_html(
+            "<table><tr><th>name</th><th>note</th></tr><tr><td>a</td><td>tab\there</td></tr></table>",
+        )
+        .unwrap();
+
+        assert_eq!(
+            document.tables_as_tsv(),
+            vec!["name\tnote\na\ttab here".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_html_unsupported_content() {
+        let document =
+            parse_html("<h2>Title</h2><script>alert('hi')</script><p>Some <b>bold</b> text</p>")
+                .unwrap();
+
+        assert!(document.has_unsupported_content);
+        assert_eq!(document.blocks.len(), 2);
+        let HtmlBlock::Paragraph(paragraph) = &document.blocks[1] else {
+            panic!("expected a paragraph, got {:?}", document.blocks[1]);
+        };
+        assert_eq!(paragraph.text, "Some bold text");
+        assert_eq!(paragraph.styles.len(), 1);
+        assert_eq!(paragraph.styles[0].0, 5..9);
+    }
+
+    #[test]
+    fn test_parse_dataframe() {
+        let table = parse_dataframe(
+            r#"<table border="1" class="dataframe">
+              <thead>
+                <tr style="text-align: right;"><th></th><th>name</th><th>age</th></tr>
+              </thead>
+              <tbody>
+                <tr><th>0</th><td>Alice</td><td>30</td></tr>
+                <tr><th>1</th><td>Bob</td><td>28</td></tr>
+              </tbody>
+            </table>
+            <p>2 rows × 2 columns</p>"#,
+        )
+        .unwrap();
+
+        let names = table
+            .schema
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["index", "name", "age"]);
+        let data = table.data.unwrap();
+        assert_eq!(data[1]["name"], "Bob");
+        assert_eq!(data[1]["age"], 28);
+
+        assert!(parse_dataframe("<table><tr><td>not pandas</td></tr></table>").is_none());
+    }
+
+    #[test]
+    fn test_parse_css_color() {
+        assert_eq!(parse_css_color("#f00"), parse_css_color("red"));
+        assert_eq!(
+            parse_css_color("rgb(255, 0, 0)"),
+            parse_css_color("#ff0000")
+        );
+        assert_eq!(parse_css_color("not-a-color"), None);
+    }
+}
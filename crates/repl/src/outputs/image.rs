@@ -4,18 +4,69 @@ use base64::{
     engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
     Engine as _,
 };
-use gpui::{img, ClipboardItem, Image, ImageFormat, Pixels, RenderImage, WindowContext};
-use std::sync::Arc;
-use ui::{div, prelude::*, IntoElement, Styled};
+use collections::HashMap;
+use gpui::{
+    img, AnyElement, ClipboardItem, Image, ImageFormat, Pixels, RenderImage, Task, WindowContext,
+};
+use image::RgbaImage;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::sync::{Arc, LazyLock, Weak};
+use ui::{div, prelude::*, IntoElement, Styled, Tooltip};
+use util::ResultExt as _;
 
 use crate::outputs::OutputContent;
 
+/// Images are drawn at most this many pixels wide or tall. Larger plots are downscaled when
+/// they're decoded, to bound the memory their textures take, but are still copied and saved at
+/// full resolution, and can be clicked to view them at full resolution.
+const MAX_RENDERED_IMAGE_SIZE: u32 = 2048;
+
+/// Images viewed at full resolution are drawn in tiles at most this many pixels wide or tall, so
+/// no single texture is larger than a downscaled image's.
+const IMAGE_TILE_SIZE: u32 = MAX_RENDERED_IMAGE_SIZE;
+
+/// How tall an image viewed at full resolution gets before it scrolls.
+const FULL_RESOLUTION_MAX_HEIGHT: Pixels = px(800.);
+
+/// Images that were decoded for outputs that are still shown, keyed by a hash of their base64
+/// data. Outputs are recreated with the same data whenever a notebook is reloaded or trusted, or a
+/// display is updated, and decoding large plots again each time would stall the UI.
+static DECODED_IMAGES: LazyLock<Mutex<HashMap<String, CachedImage>>> =
+    LazyLock::new(|| Mutex::new(HashMap::default()));
+
+/// A decoded image, held weakly so it's dropped along with the last output showing it.
+struct CachedImage {
+    clipboard_image: Weak<Image>,
+    image: Weak<RenderImage>,
+    height: u32,
+    width: u32,
+}
+
 /// ImageView renders an image inline in an editor, adapting to the line height to fit the image.
 pub struct ImageView {
     clipboard_image: Arc<Image>,
+    /// The size of the original image, which `image` may be a downscaled version of.
     height: u32,
     width: u32,
     image: Arc<RenderImage>,
+    /// The image at full resolution, once a downscaled image was clicked.
+    full_resolution: Option<FullResolution>,
+}
+
+enum FullResolution {
+    Decoding { _task: Task<()> },
+    Decoded(Vec<ImageTile>),
+}
+
+/// A part of an image decoded at full resolution, positioned from the image's top left corner.
+struct ImageTile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    image: Arc<RenderImage>,
 }
 
 pub const STANDARD_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
@@ -26,24 +77,61 @@ pub const STANDARD_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
 );
 
 impl ImageView {
+    /// Decodes a base64 encoded image, reusing the decoded image of an output already showing the
+    /// same data.
     pub fn from(base64_encoded_data: &str) -> Result<Self> {
+        let key = format!("{:x}", Sha256::digest(base64_encoded_data.as_bytes()));
+        if let Some(view) = Self::cached(&key) {
+            return Ok(view);
+        }
+
+        let view = Self::decode(base64_encoded_data)?;
+        let mut cache = DECODED_IMAGES.lock();
+        cache.retain(|_, cached| cached.image.strong_count() > 0);
+        cache.insert(
+            key,
+            CachedImage {
+                clipboard_image: Arc::downgrade(&view.clipboard_image),
+                image: Arc::downgrade(&view.image),
+                height: view.height,
+                width: view.width,
+            },
+        );
+        Ok(view)
+    }
+
+    fn cached(key: &str) -> Option<Self> {
+        let cache = DECODED_IMAGES.lock();
+        let cached = cache.get(key)?;
+        Some(ImageView {
+            clipboard_image: cached.clipboard_image.upgrade()?,
+            height: cached.height,
+            width: cached.width,
+            image: cached.image.upgrade()?,
+            full_resolution: None,
+        })
+    }
+
+    fn decode(base64_encoded_data: &str) -> Result<Self> {
         let filtered =
             base64_encoded_data.replace(&[' ', '\n', '\t', '\r', '\x0b', '\x0c'][..], "");
         let bytes = STANDARD_INDIFFERENT.decode(filtered)?;
 
         let format = image::guess_format(&bytes)?;
 
-        let mut data = image::load_from_memory_with_format(&bytes, format)?.into_rgba8();
-
-        // Convert from RGBA to BGRA.
-        for pixel in data.chunks_exact_mut(4) {
-            pixel.swap(0, 2);
-        }
-
-        let height = data.height();
-        let width = data.width();
-
-        let gpui_image_data = RenderImage::new(vec![image::Frame::new(data)]);
+        let decoded = image::load_from_memory_with_format(&bytes, format)?;
+        let height = decoded.height();
+        let width = decoded.width();
+        let decoded = if width > MAX_RENDERED_IMAGE_SIZE || height > MAX_RENDERED_IMAGE_SIZE {
+            decoded.resize(
+                MAX_RENDERED_IMAGE_SIZE,
+                MAX_RENDERED_IMAGE_SIZE,
+                image::imageops::FilterType::Triangle,
+            )
+        } else {
+            decoded
+        };
+        let gpui_image_data = render_image(decoded.into_rgba8());
 
         let format = match format {
             image::ImageFormat::Png => ImageFormat::Png,
@@ -69,34 +157,274 @@ impl ImageView {
             height,
             width,
             image: Arc::new(gpui_image_data),
+            full_resolution: None,
         })
     }
+
+    fn is_downscaled(&self) -> bool {
+        let size = self.image.size(0);
+        size.width.0 as u32 != self.width || size.height.0 as u32 != self.height
+    }
+
+    /// Switches between the downscaled image and the image at full resolution, which is decoded
+    /// again each time so it's only held in memory while it's shown.
+    fn toggle_full_resolution(&mut self, cx: &mut ViewContext<Self>) {
+        if self.full_resolution.take().is_some() {
+            cx.notify();
+            return;
+        }
+
+        let image = self.clipboard_image.clone();
+        let tiles = cx
+            .background_executor()
+            .spawn(async move { decode_tiles(&image.bytes) });
+        let task = cx.spawn(|this, mut cx| async move {
+            let tiles = tiles.await.log_err();
+            this.update(&mut cx, |this, cx| {
+                this.full_resolution = tiles.map(FullResolution::Decoded);
+                cx.notify();
+            })
+            .ok();
+        });
+        self.full_resolution = Some(FullResolution::Decoding { _task: task });
+    }
+
+    fn render_full_resolution(
+        &self,
+        tiles: &[ImageTile],
+        cx: &mut ViewContext<Self>,
+    ) -> AnyElement {
+        div()
+            .id("full-resolution-image")
+            .max_w_full()
+            .max_h(FULL_RESOLUTION_MAX_HEIGHT)
+            .overflow_scroll()
+            .cursor_pointer()
+            .tooltip(|cx| Tooltip::text("Fit Image", cx))
+            .on_click(cx.listener(|this, _, cx| this.toggle_full_resolution(cx)))
+            .child(
+                div()
+                    .relative()
+                    .w(px(self.width as f32))
+                    .h(px(self.height as f32))
+                    .children(tiles.iter().map(|tile| {
+                        div()
+                            .absolute()
+                            .left(px(tile.x as f32))
+                            .top(px(tile.y as f32))
+                            .w(px(tile.width as f32))
+                            .h(px(tile.height as f32))
+                            .child(img(tile.image.clone()))
+                    })),
+            )
+            .into_any_element()
+    }
+}
+
+/// Converts decoded pixels to the BGRA layout GPUI draws.
+fn render_image(mut data: RgbaImage) -> RenderImage {
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    RenderImage::new(vec![image::Frame::new(data)])
+}
+
+/// The ratio an image keeps when it's scaled down, or `None` for an image without an area, which
+/// can't be scaled and is laid out at its intrinsic size instead.
+fn aspect_ratio(width: f32, height: f32) -> Option<f32> {
+    (width > 0. && height > 0.).then(|| width / height)
+}
+
+/// Decodes an image at full resolution, split into tiles that each fit in a texture.
+fn decode_tiles(bytes: &[u8]) -> Result<Vec<ImageTile>> {
+    let decoded = image::load_from_memory(bytes)?;
+    let (width, height) = (decoded.width(), decoded.height());
+
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(IMAGE_TILE_SIZE as usize) {
+        for x in (0..width).step_by(IMAGE_TILE_SIZE as usize) {
+            let tile_width = IMAGE_TILE_SIZE.min(width - x);
+            let tile_height = IMAGE_TILE_SIZE.min(height - y);
+            let tile = decoded.crop_imm(x, y, tile_width, tile_height);
+            tiles.push(ImageTile {
+                x,
+                y,
+                width: tile_width,
+                height: tile_height,
+                image: Arc::new(render_image(tile.into_rgba8())),
+            });
+        }
+    }
+    Ok(tiles)
 }
 
 impl Render for ImageView {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        if let Some(FullResolution::Decoded(tiles)) = self.full_resolution.as_ref() {
+            return self.render_full_resolution(tiles, cx);
+        }
+
+        // Downscaled images are laid out at the size they were downscaled to
+        let size = self.image.size(0);
+        let (image_height, image_width) = (size.height.0 as f32, size.width.0 as f32);
         let line_height = cx.line_height();
 
-        let (height, width) = if self.height as f32 / line_height.0 == u8::MAX as f32 {
+        let (height, width) = if image_height / line_height.0 == u8::MAX as f32 {
             let height = u8::MAX as f32 * line_height.0;
-            let width = self.width as f32 * height / self.height as f32;
+            let width = image_width * height / image_height;
             (height, width)
         } else {
-            (self.height as f32, self.width as f32)
+            (image_height, image_width)
         };
 
-        let image = self.image.clone();
+        let mut image = div().child(img(self.image.clone()).size_full());
+        if let Some(aspect_ratio) = aspect_ratio(width, height) {
+            // Images wider than the output area, like plots under a line of code in an editor,
+            // are scaled down to fit it
+            image = image.w_full().max_w(Pixels(width)).max_h(Pixels(height));
+            image.style().aspect_ratio = Some(Some(aspect_ratio));
+        } else {
+            image = image.w(Pixels(width)).h(Pixels(height));
+        }
+        if !self.is_downscaled() {
+            return image.into_any_element();
+        }
+
+        div()
+            .id("downscaled-image")
+            .cursor_pointer()
+            .tooltip(|cx| Tooltip::text("View at Full Resolution", cx))
+            .on_click(cx.listener(|this, _, cx| this.toggle_full_resolution(cx)))
+            .child(image)
+            .into_any_element()
+    }
+}
+
+impl ImageView {
+    /// The image encoded as PNG, which is what most apps accept when pasting an image.
+    fn png_image(&self) -> Result<Image> {
+        if self.clipboard_image.format == ImageFormat::Png {
+            return Ok(self.clipboard_image.as_ref().clone());
+        }
+
+        let mut bytes = Vec::new();
+        image::load_from_memory(&self.clipboard_image.bytes)?
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)?;
 
-        div().h(Pixels(height)).w(Pixels(width)).child(img(image))
+        Ok(Image {
+            format: ImageFormat::Png,
+            bytes,
+            id: self.clipboard_image.id,
+        })
     }
 }
 
+/// The file extension for an image format.
+fn image_extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Webp => "webp",
+        ImageFormat::Gif => "gif",
+        ImageFormat::Svg => "svg",
+        ImageFormat::Bmp => "bmp",
+        ImageFormat::Tiff => "tiff",
+    }
+}
+
+/// Asks the user where to save an image, then writes its original bytes there at full resolution.
+pub(crate) fn save_image_as(image: Arc<Image>, cx: &mut WindowContext) {
+    let path = cx.prompt_for_new_path(util::paths::home_dir());
+
+    cx.spawn(|cx| async move {
+        let Some(mut path) = path.await?? else {
+            return anyhow::Ok(());
+        };
+        let extension = image_extension(image.format);
+        if path.extension().is_none() {
+            path.set_extension(extension);
+        }
+
+        cx.background_executor()
+            .spawn(async move { std::fs::write(&path, &image.bytes) })
+            .await?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
 impl OutputContent for ImageView {
     fn clipboard_content(&self, _cx: &WindowContext) -> Option<ClipboardItem> {
-        Some(ClipboardItem::new_image(self.clipboard_image.as_ref()))
+        let image = self.png_image().log_err()?;
+        Some(ClipboardItem::new_image(&image))
     }
 
     fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
         true
     }
+
+    fn image(&self, _cx: &WindowContext) -> Option<Arc<Image>> {
+        Some(self.clipboard_image.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base64::engine::general_purpose::STANDARD;
+
+    use super::*;
+
+    fn encoded_png(width: u32, height: u32) -> String {
+        let mut bytes = Vec::new();
+        image::DynamicImage::new_rgba8(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        STANDARD.encode(bytes)
+    }
+
+    #[test]
+    fn test_decoded_images_are_reused() {
+        let data = encoded_png(3, 2);
+        let first = ImageView::from(&data).unwrap();
+        let second = ImageView::from(&data).unwrap();
+        assert!(Arc::ptr_eq(&first.image, &second.image));
+
+        let other = ImageView::from(&encoded_png(2, 3)).unwrap();
+        assert!(!Arc::ptr_eq(&first.image, &other.image));
+    }
+
+    #[test]
+    fn test_large_images_are_downscaled() {
+        let view = ImageView::from(&encoded_png(4096, 1024)).unwrap();
+        assert_eq!((view.width, view.height), (4096, 1024));
+
+        let size = view.image.size(0);
+        assert_eq!((size.width.0, size.height.0), (2048, 512));
+        assert!(view.is_downscaled());
+        assert!(!ImageView::from(&encoded_png(3, 2)).unwrap().is_downscaled());
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        assert_eq!(aspect_ratio(300., 150.), Some(2.));
+        assert_eq!(aspect_ratio(300., 0.), None);
+        assert_eq!(aspect_ratio(0., 150.), None);
+    }
+
+    #[test]
+    fn test_decode_tiles() {
+        let mut bytes = Vec::new();
+        image::DynamicImage::new_rgba8(IMAGE_TILE_SIZE + 10, 20)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        let tiles = decode_tiles(&bytes).unwrap();
+        let layout = tiles
+            .iter()
+            .map(|tile| (tile.x, tile.y, tile.width, tile.height))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            layout,
+            vec![(0, 0, IMAGE_TILE_SIZE, 20), (IMAGE_TILE_SIZE, 0, 10, 20)]
+        );
+    }
 }
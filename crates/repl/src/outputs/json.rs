@@ -0,0 +1,446 @@
+//! # JSON Output
+//!
+//! Renders `application/json` outputs as a tree that can be expanded and collapsed one level at
+//! a time, like the JSON viewers in browser developer tools. Keys can be searched, and the path to
+//! any value can be copied as a chain of subscripts, e.g. `["items"][0]["name"]`, which works as is
+//! in Python and JavaScript.
+
+use std::collections::HashSet;
+
+use editor::{Editor, EditorEvent};
+use gpui::{ClipboardItem, Hsla, Model, Subscription, View};
+use language::Buffer;
+use serde_json::Value;
+use ui::{prelude::*, Tooltip};
+
+use crate::outputs::OutputContent;
+
+/// Levels of the tree that start out expanded.
+const INITIALLY_EXPANDED_DEPTH: usize = 1;
+/// Rows past this are left out, so a huge response doesn't stall rendering.
+const MAX_VISIBLE_ROWS: usize = 2000;
+const INDENT_WIDTH: f32 = 16.;
+
+#[derive(Clone, Debug, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Formats a path as subscripts, e.g. `["items"][0]["name"]`.
+fn format_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => format!("[{}]", Value::String(key.clone())),
+            PathSegment::Index(index) => format!("[{index}]"),
+        })
+        .collect()
+}
+
+fn children(value: &Value) -> Vec<(PathSegment, &Value)> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (PathSegment::Key(key.clone()), value))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (PathSegment::Index(index), value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+/// A short description of a value, shown next to its key.
+fn summary(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.len() == 1 => "{1 key}".to_string(),
+        Value::Object(map) => format!("{{{} keys}}", map.len()),
+        Value::Array(items) if items.len() == 1 => "[1 item]".to_string(),
+        Value::Array(items) => format!("[{} items]", items.len()),
+        value => value.to_string(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct TreeRow {
+    path: Vec<PathSegment>,
+    depth: usize,
+    expandable: bool,
+    expanded: bool,
+    /// Whether the row's key matches the search query.
+    matches_query: bool,
+    summary: String,
+}
+
+/// Whether the key of `segment`, or of anything below `value`, contains the query.
+fn contains_match(segment: Option<&PathSegment>, value: &Value, query: &str) -> bool {
+    if matches!(segment, Some(PathSegment::Key(key)) if key.to_lowercase().contains(query)) {
+        return true;
+    }
+    children(value)
+        .iter()
+        .any(|(segment, value)| contains_match(Some(segment), value, query))
+}
+
+/// Flattens the parts of the tree that are expanded into rows.
+///
+/// While searching, only rows whose key matches, or that lead to one, are kept, and their
+/// ancestors are expanded so the matches are visible.
+fn visible_rows(value: &Value, expanded: &HashSet<String>, query: &str) -> Vec<TreeRow> {
+    let query = query.trim().to_lowercase();
+    let mut rows = Vec::new();
+    collect_rows(value, &mut Vec::new(), expanded, &query, &mut rows);
+    rows
+}
+
+fn collect_rows(
+    value: &Value,
+    path: &mut Vec<PathSegment>,
+    expanded: &HashSet<String>,
+    query: &str,
+    rows: &mut Vec<TreeRow>,
+) {
+    for (segment, child) in children(value) {
+        if rows.len() >= MAX_VISIBLE_ROWS {
+            return;
+        }
+        if !query.is_empty() && !contains_match(Some(&segment), child, query) {
+            continue;
+        }
+
+        let matches_query = !query.is_empty()
+            && matches!(&segment, PathSegment::Key(key) if key.to_lowercase().contains(query));
+        path.push(segment);
+
+        let expandable = is_container(child) && !children(child).is_empty();
+        let is_expanded = expandable
+            && if query.is_empty() {
+                expanded.contains(&format_path(path))
+            } else {
+                children(child)
+                    .iter()
+                    .any(|(segment, value)| contains_match(Some(segment), value, query))
+            };
+
+        rows.push(TreeRow {
+            path: path.clone(),
+            depth: path.len() - 1,
+            expandable,
+            expanded: is_expanded,
+            matches_query,
+            summary: summary(child),
+        });
+
+        if is_expanded {
+            collect_rows(child, path, expanded, query, rows);
+        }
+        path.pop();
+    }
+}
+
+fn initially_expanded(value: &Value) -> HashSet<String> {
+    fn visit(value: &Value, path: &mut Vec<PathSegment>, expanded: &mut HashSet<String>) {
+        for (segment, child) in children(value) {
+            path.push(segment);
+            if is_container(child) && path.len() <= INITIALLY_EXPANDED_DEPTH {
+                expanded.insert(format_path(path));
+                visit(child, path, expanded);
+            }
+            path.pop();
+        }
+    }
+
+    let mut expanded = HashSet::new();
+    visit(value, &mut Vec::new(), &mut expanded);
+    expanded
+}
+
+pub struct JsonView {
+    value: Value,
+    pretty_json: String,
+    expanded: HashSet<String>,
+    search_editor: View<Editor>,
+    query: String,
+    _search_subscription: Subscription,
+}
+
+impl JsonView {
+    pub fn new(value: Value, cx: &mut ViewContext<Self>) -> Self {
+        let search_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Search keys…", cx);
+            editor
+        });
+        let search_subscription =
+            cx.subscribe(&search_editor, |this, editor, event: &EditorEvent, cx| {
+                if let EditorEvent::BufferEdited = event {
+                    this.query = editor.read(cx).text(cx);
+                    cx.notify();
+                }
+            });
+
+        Self {
+            pretty_json: serde_json::to_string_pretty(&value).unwrap_or_else(|_| value.to_string()),
+            expanded: initially_expanded(&value),
+            value,
+            search_editor,
+            query: String::new(),
+            _search_subscription: search_subscription,
+        }
+    }
+
+    fn toggle(&mut self, path: &[PathSegment], cx: &mut ViewContext<Self>) {
+        let path = format_path(path);
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        cx.notify();
+    }
+
+    fn value_color(value: &Value, cx: &WindowContext) -> Hsla {
+        let syntax = cx.theme().syntax();
+        match value {
+            Value::String(_) => syntax.color("string"),
+            Value::Number(_) => syntax.color("number"),
+            Value::Bool(_) => syntax.color("boolean"),
+            Value::Null => syntax.color("constant"),
+            Value::Object(_) | Value::Array(_) => cx.theme().colors().text_muted,
+        }
+    }
+
+    fn render_row(&self, ix: usize, row: TreeRow, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let value = row
+            .path
+            .iter()
+            .fold(&self.value, |value, segment| match segment {
+                PathSegment::Key(key) => &value[key.as_str()],
+                PathSegment::Index(index) => &value[*index],
+            });
+        let value_color = Self::value_color(value, cx);
+        let key_label = match row.path.last() {
+            Some(PathSegment::Key(key)) => key.clone(),
+            Some(PathSegment::Index(index)) => index.to_string(),
+            None => String::new(),
+        };
+        let is_index = matches!(row.path.last(), Some(PathSegment::Index(_)));
+        let copied_path = format_path(&row.path);
+        let group = SharedString::from(format!("json-row-{ix}"));
+
+        h_flex()
+            .id(("json-row", ix))
+            .group(group.clone())
+            .w_full()
+            .gap_1()
+            .pl(px(row.depth as f32 * INDENT_WIDTH))
+            .font_buffer(cx)
+            .text_buffer(cx)
+            .child(
+                div()
+                    .w(px(INDENT_WIDTH))
+                    .flex_none()
+                    .when(row.expandable, |this| {
+                        this.child(
+                            Icon::new(if row.expanded {
+                                IconName::ChevronDown
+                            } else {
+                                IconName::ChevronRight
+                            })
+                            .size(IconSize::XSmall)
+                            .color(Color::Muted),
+                        )
+                    }),
+            )
+            .child(
+                div()
+                    .when(is_index, |this| {
+                        this.text_color(cx.theme().colors().text_muted)
+                    })
+                    .when(!is_index, |this| {
+                        this.text_color(cx.theme().syntax().color("property"))
+                    })
+                    .when(row.matches_query, |this| {
+                        this.bg(cx.theme().colors().search_match_background)
+                    })
+                    .child(format!("{key_label}:")),
+            )
+            .child(
+                div()
+                    .text_color(value_color)
+                    .whitespace_nowrap()
+                    .overflow_hidden()
+                    .text_ellipsis()
+                    .child(row.summary.clone()),
+            )
+            .child(
+                IconButton::new(("copy-json-path", ix), IconName::Copy)
+                    .icon_size(IconSize::XSmall)
+                    .style(ButtonStyle::Transparent)
+                    .visible_on_hover(group)
+                    .tooltip(move |cx| Tooltip::text("Copy Path", cx))
+                    .on_click(move |_, cx| {
+                        cx.write_to_clipboard(ClipboardItem::new_string(copied_path.clone()))
+                    }),
+            )
+            .when(row.expandable, |this| {
+                let path = row.path.clone();
+                this.cursor_pointer()
+                    .on_click(cx.listener(move |this, _, cx| this.toggle(&path, cx)))
+            })
+    }
+}
+
+impl OutputContent for JsonView {
+    fn clipboard_content(&self, _cx: &WindowContext) -> Option<ClipboardItem> {
+        Some(ClipboardItem::new_string(self.pretty_json.clone()))
+    }
+
+    fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn has_buffer_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
+        let buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::local(self.pretty_json.clone(), cx)
+                .with_language(language::PLAIN_TEXT.clone(), cx);
+            buffer.set_capability(language::Capability::ReadOnly, cx);
+            buffer
+        });
+        Some(buffer)
+    }
+}
+
+impl Render for JsonView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        // Scalars don't need a tree
+        if !is_container(&self.value) {
+            return div()
+                .font_buffer(cx)
+                .text_buffer(cx)
+                .text_color(Self::value_color(&self.value, cx))
+                .child(self.pretty_json.clone())
+                .into_any_element();
+        }
+
+        let rows = visible_rows(&self.value, &self.expanded, &self.query);
+        let is_truncated = rows.len() >= MAX_VISIBLE_ROWS;
+        let is_empty = rows.is_empty();
+
+        v_flex()
+            .w_full()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(
+                        Label::new(summary(&self.value))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        div()
+                            .w(px(200.))
+                            .px_1()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(cx.theme().colors().border)
+                            .child(self.search_editor.clone()),
+                    ),
+            )
+            .children(
+                rows.into_iter()
+                    .enumerate()
+                    .map(|(ix, row)| self.render_row(ix, row, cx).into_any_element())
+                    .collect::<Vec<_>>(),
+            )
+            .when(is_empty && !self.query.trim().is_empty(), |this| {
+                this.child(
+                    Label::new("No matching keys")
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+            })
+            .when(is_truncated, |this| {
+                this.child(
+                    Label::new(
+                        "Some rows are hidden. Open the output in an editor to see all of it.",
+                    )
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+                )
+            })
+            .into_any_element()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_format_path() {
+        let path = vec![
+            PathSegment::Key("items".into()),
+            PathSegment::Index(0),
+            PathSegment::Key("say \"hi\"".into()),
+        ];
+        assert_eq!(format_path(&path), r#"["items"][0]["say \"hi\""]"#);
+    }
+
+    #[test]
+    fn test_visible_rows_initially_expands_top_level() {
+        let value = json!({"a": {"b": {"c": 1}}, "d": [1, 2]});
+        let rows = visible_rows(&value, &initially_expanded(&value), "");
+
+        let paths = rows
+            .iter()
+            .map(|row| format_path(&row.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                r#"["a"]"#,
+                r#"["a"]["b"]"#,
+                r#"["d"]"#,
+                r#"["d"][0]"#,
+                r#"["d"][1]"#
+            ]
+        );
+        assert!(rows[0].expanded);
+        assert!(!rows[1].expanded);
+        assert_eq!(rows[1].summary, "{1 key}");
+        assert_eq!(rows[3].summary, "1");
+    }
+
+    #[test]
+    fn test_visible_rows_search_expands_to_matches() {
+        let value = json!({"user": {"profile": {"Name": "Ada"}, "id": 7}, "other": true});
+        let rows = visible_rows(&value, &HashSet::new(), "name");
+
+        let paths = rows
+            .iter()
+            .map(|row| format_path(&row.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                r#"["user"]"#,
+                r#"["user"]["profile"]"#,
+                r#"["user"]["profile"]["Name"]"#
+            ]
+        );
+        assert!(rows[2].matches_query);
+        assert!(!rows[0].matches_query);
+    }
+}
@@ -0,0 +1,1348 @@
+//! # LaTeX Output
+//!
+//! Renders `text/latex` outputs, like the equations sympy produces, by laying out the math natively
+//! instead of showing the TeX source.
+//!
+//! The supported subset covers what kernels commonly emit: symbols and Greek letters, superscripts
+//! and subscripts, fractions, roots, `\left`/`\right` delimiters, accents, font commands, and
+//! matrix-like environments. Commands that aren't understood are shown as written.
+
+use gpui::{AnyElement, ClipboardItem, FontWeight, Hsla, Model, Pixels, WindowContext};
+use language::Buffer;
+use ui::{h_flex, prelude::*, v_flex};
+
+use crate::outputs::OutputContent;
+
+/// How much smaller each level of superscripts and subscripts is than the level it's attached to.
+const SCRIPT_SCALE: f32 = 0.7;
+const MIN_SCALE: f32 = 0.5;
+
+pub struct LatexView {
+    raw_text: String,
+    segments: Vec<LatexSegment>,
+}
+
+impl LatexView {
+    pub fn from(text: String) -> Self {
+        Self {
+            segments: parse_segments(&text),
+            raw_text: text,
+        }
+    }
+}
+
+impl OutputContent for LatexView {
+    fn clipboard_content(&self, _cx: &WindowContext) -> Option<ClipboardItem> {
+        Some(ClipboardItem::new_string(self.raw_text.clone()))
+    }
+
+    fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn has_buffer_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
+        let buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::local(self.raw_text.clone(), cx)
+                .with_language(language::PLAIN_TEXT.clone(), cx);
+            buffer.set_capability(language::Capability::ReadOnly, cx);
+            buffer
+        });
+        Some(buffer)
+    }
+}
+
+impl Render for LatexView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let font_size = cx.text_style().font_size.to_pixels(cx.rem_size());
+        let color = cx.theme().colors().text;
+        let renderer = MathRenderer { font_size, color };
+
+        h_flex()
+            .flex_wrap()
+            .items_center()
+            .gap_1()
+            .children(self.segments.iter().map(|segment| {
+                match segment {
+                    LatexSegment::Text(text) => div().child(text.clone()).into_any_element(),
+                    LatexSegment::Math { node, display } => div()
+                        .when(*display, |this| this.w_full().py_1())
+                        .child(renderer.render(node, 0))
+                        .into_any_element(),
+                }
+            }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum LatexSegment {
+    Text(String),
+    Math { node: MathNode, display: bool },
+}
+
+/// Splits `text/latex` content into prose and math, delimited by `$`, `$$`, `\(...\)` or `\[...\]`.
+/// Content without any delimiters is treated as math, as kernels sometimes leave them out.
+fn parse_segments(text: &str) -> Vec<LatexSegment> {
+    let spans = find_math_spans(text);
+    if spans.is_empty() {
+        return vec![LatexSegment::Math {
+            node: parse_math(text),
+            display: true,
+        }];
+    }
+
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for span in spans {
+        let prose = text[offset..span.start].trim();
+        if !prose.is_empty() {
+            segments.push(LatexSegment::Text(prose.to_string()));
+        }
+        segments.push(LatexSegment::Math {
+            node: parse_math(&text[span.content.clone()]),
+            display: span.display,
+        });
+        offset = span.end;
+    }
+    let prose = text[offset..].trim();
+    if !prose.is_empty() {
+        segments.push(LatexSegment::Text(prose.to_string()));
+    }
+    segments
+}
+
+#[derive(Debug, PartialEq)]
+struct MathSpan {
+    start: usize,
+    end: usize,
+    content: std::ops::Range<usize>,
+    display: bool,
+}
+
+/// Finds the math in `text`. A lone `$` only opens math when followed by a non-space character and
+/// only closes it when preceded by one and not followed by a digit, so prices like "$5 and $10"
+/// stay prose.
+fn find_math_spans(text: &str) -> Vec<MathSpan> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut ix = 0;
+
+    while ix < bytes.len() {
+        let rest = &text[ix..];
+        let delimiters = if rest.starts_with("$$") {
+            Some(("$$", "$$", true))
+        } else if rest.starts_with("\\[") {
+            Some(("\\[", "\\]", true))
+        } else if rest.starts_with("\\(") {
+            Some(("\\(", "\\)", false))
+        } else if rest.starts_with('$') {
+            Some(("$", "$", false))
+        } else {
+            None
+        };
+
+        if bytes[ix] == b'\\' && delimiters.is_none() {
+            // Skip escaped characters, like `\$`
+            ix += 1 + text[ix + 1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+
+        let Some((open, close, display)) = delimiters else {
+            ix += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+
+        let content_start = ix + open.len();
+        let content_end = if open == "$" {
+            find_closing_dollar(text, content_start)
+        } else {
+            text[content_start..]
+                .find(close)
+                .map(|position| content_start + position)
+        };
+
+        match content_end {
+            Some(content_end) if content_end > content_start => {
+                spans.push(MathSpan {
+                    start: ix,
+                    end: content_end + close.len(),
+                    content: content_start..content_end,
+                    display,
+                });
+                ix = content_end + close.len();
+            }
+            _ => ix += open.len(),
+        }
+    }
+
+    spans
+}
+
+fn find_closing_dollar(text: &str, content_start: usize) -> Option<usize> {
+    if text[content_start..]
+        .chars()
+        .next()
+        .map_or(true, char::is_whitespace)
+    {
+        return None;
+    }
+
+    let mut previous = None;
+    let mut chars = text[content_start..].char_indices().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '\\' => {
+                chars.next();
+                previous = Some('\\');
+                continue;
+            }
+            '\n' if previous == Some('\n') => return None,
+            '$' => {
+                let next = chars.peek().map(|(_, ch)| *ch);
+                if previous.map_or(false, |ch| !ch.is_whitespace())
+                    && !next.map_or(false, |ch| ch.is_ascii_digit())
+                {
+                    return Some(content_start + offset);
+                }
+            }
+            _ => {}
+        }
+        previous = Some(ch);
+    }
+    None
+}
+
+/// Replaces inline and display math in markdown with a linear Unicode approximation, like `x² + y²`,
+/// leaving code spans and fenced code blocks untouched.
+pub fn replace_math_with_unicode(markdown: &str) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut prose = String::new();
+    let mut in_fence = false;
+
+    for line in markdown.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with("```");
+        if in_fence || is_fence {
+            result.push_str(&replace_prose_math(&std::mem::take(&mut prose)));
+            result.push_str(line);
+            if is_fence {
+                in_fence = !in_fence;
+            }
+        } else {
+            prose.push_str(line);
+        }
+    }
+    result.push_str(&replace_prose_math(&prose));
+    result
+}
+
+fn replace_prose_math(prose: &str) -> String {
+    let mut result = String::with_capacity(prose.len());
+    let mut offset = 0;
+
+    // Code spans can contain dollar signs, so only look for math between them
+    for (ix, part) in prose.split('`').enumerate() {
+        let end = offset + part.len();
+        if ix % 2 == 1 {
+            result.push('`');
+            result.push_str(part);
+            if end < prose.len() {
+                result.push('`');
+            }
+        } else {
+            let mut last = 0;
+            for span in find_math_spans(part) {
+                result.push_str(&part[last..span.start]);
+                result.push_str(&parse_math(&part[span.content.clone()]).to_unicode());
+                last = span.end;
+            }
+            result.push_str(&part[last..]);
+        }
+        offset = end + 1;
+    }
+
+    result
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SymbolStyle {
+    /// Single-letter variables are set in italics.
+    Italic,
+    Upright,
+    Bold,
+    /// Operators like `\sum` and `\lim`, whose scripts go above and below them.
+    Limits,
+}
+
+#[derive(Debug, PartialEq)]
+enum MathNode {
+    Symbol {
+        text: String,
+        style: SymbolStyle,
+    },
+    Row(Vec<MathNode>),
+    Scripts {
+        base: Box<MathNode>,
+        superscript: Option<Box<MathNode>>,
+        subscript: Option<Box<MathNode>>,
+    },
+    Fraction {
+        numerator: Box<MathNode>,
+        denominator: Box<MathNode>,
+        /// Binomial coefficients are fractions without the rule.
+        has_rule: bool,
+    },
+    Root {
+        index: Option<Box<MathNode>>,
+        radicand: Box<MathNode>,
+    },
+    Accent {
+        accent: char,
+        body: Box<MathNode>,
+    },
+    Delimited {
+        left: String,
+        right: String,
+        body: Box<MathNode>,
+    },
+    Table {
+        left: String,
+        right: String,
+        rows: Vec<Vec<MathNode>>,
+    },
+    Space(f32),
+}
+
+impl MathNode {
+    fn symbol(text: impl Into<String>, style: SymbolStyle) -> Self {
+        Self::Symbol {
+            text: text.into(),
+            style,
+        }
+    }
+
+    /// The height of the node in lines, used to size the delimiters around it.
+    fn height(&self) -> f32 {
+        match self {
+            MathNode::Row(children) => children.iter().map(MathNode::height).fold(1., f32::max),
+            MathNode::Scripts {
+                base,
+                superscript,
+                subscript,
+            } => {
+                base.height()
+                    + superscript.as_ref().map_or(0., |_| 0.3)
+                    + subscript.as_ref().map_or(0., |_| 0.3)
+            }
+            MathNode::Fraction {
+                numerator,
+                denominator,
+                ..
+            } => (numerator.height() + denominator.height()) * 0.85,
+            MathNode::Root { radicand, .. } => radicand.height() + 0.1,
+            MathNode::Accent { body, .. } => body.height() + 0.2,
+            MathNode::Delimited { body, .. } => body.height(),
+            MathNode::Table { rows, .. } => rows
+                .iter()
+                .map(|row| row.iter().map(MathNode::height).fold(1., f32::max))
+                .sum(),
+            MathNode::Symbol { .. } | MathNode::Space(_) => 1.,
+        }
+    }
+
+    /// A linear rendering of the math in plain Unicode text, for places that can't lay out math.
+    fn to_unicode(&self) -> String {
+        match self {
+            MathNode::Symbol { text, style } => match style {
+                SymbolStyle::Limits if text.chars().all(char::is_alphabetic) => format!("{text} "),
+                _ => text.clone(),
+            },
+            MathNode::Row(children) => children.iter().map(MathNode::to_unicode).collect(),
+            MathNode::Scripts {
+                base,
+                superscript,
+                subscript,
+            } => {
+                let mut text = base.to_unicode();
+                if let Some(subscript) = subscript {
+                    text.push_str(&script_to_unicode(subscript, '_', subscript_char));
+                }
+                if let Some(superscript) = superscript {
+                    text.push_str(&script_to_unicode(superscript, '^', superscript_char));
+                }
+                text
+            }
+            MathNode::Fraction {
+                numerator,
+                denominator,
+                has_rule,
+            } => {
+                let numerator = parenthesize(numerator.to_unicode());
+                let denominator = parenthesize(denominator.to_unicode());
+                if *has_rule {
+                    format!("{numerator}/{denominator}")
+                } else {
+                    format!("C({numerator}, {denominator})")
+                }
+            }
+            MathNode::Root { index, radicand } => {
+                let index = match index.as_ref().map(|index| index.to_unicode()).as_deref() {
+                    Some("3") => "∛".to_string(),
+                    Some("4") => "∜".to_string(),
+                    Some(index) => format!("{}√", script_text(index, superscript_char)),
+                    None => "√".to_string(),
+                };
+                format!("{index}{}", parenthesize(radicand.to_unicode()))
+            }
+            MathNode::Accent { accent, body } => {
+                let body = body.to_unicode();
+                if body.chars().count() == 1 {
+                    format!("{body}{accent}")
+                } else {
+                    body
+                }
+            }
+            MathNode::Delimited { left, right, body } => {
+                format!("{left}{}{right}", body.to_unicode())
+            }
+            MathNode::Table { left, right, rows } => {
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(MathNode::to_unicode)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                format!("{left}{rows}{right}")
+            }
+            MathNode::Space(width) if *width >= 0.25 => " ".to_string(),
+            MathNode::Space(_) => String::new(),
+        }
+    }
+}
+
+fn parenthesize(text: String) -> String {
+    if text.chars().count() <= 1 || text.chars().all(|ch| ch.is_alphanumeric() || ch == '.') {
+        text
+    } else {
+        format!("({text})")
+    }
+}
+
+fn script_to_unicode(script: &MathNode, marker: char, map: fn(char) -> Option<char>) -> String {
+    let text = script.to_unicode();
+    match script_text(&text, map) {
+        converted if !converted.is_empty() => converted,
+        _ if text.chars().count() == 1 => format!("{marker}{text}"),
+        _ => format!("{marker}({text})"),
+    }
+}
+
+/// Converts `text` to superscript or subscript characters, or returns an empty string if any
+/// character doesn't have one.
+fn script_text(text: &str, map: fn(char) -> Option<char>) -> String {
+    text.chars()
+        .map(map)
+        .collect::<Option<String>>()
+        .unwrap_or_default()
+}
+
+fn superscript_char(ch: char) -> Option<char> {
+    Some(match ch {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'n' => 'ⁿ',
+        'i' => 'ⁱ',
+        'T' => 'ᵀ',
+        '*' | '∗' => '*',
+        '′' => '′',
+        _ => return None,
+    })
+}
+
+fn subscript_char(ch: char) -> Option<char> {
+    Some(match ch {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'n' => 'ₙ',
+        'x' => 'ₓ',
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Command(String),
+    Char(char),
+    BeginGroup,
+    EndGroup,
+    Superscript,
+    Subscript,
+    ColumnSeparator,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphabetic()) {
+                    name.push(ch);
+                }
+                if name.is_empty() {
+                    match chars.next() {
+                        Some(ch) => name.push(ch),
+                        None => continue,
+                    }
+                } else {
+                    while chars.next_if(|ch| *ch == ' ').is_some() {}
+                }
+                Token::Command(name)
+            }
+            '{' => Token::BeginGroup,
+            '}' => Token::EndGroup,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '&' => Token::ColumnSeparator,
+            '%' => {
+                while chars.next_if(|ch| *ch != '\n').is_some() {}
+                continue;
+            }
+            '\'' => Token::Char('′'),
+            ch if ch.is_whitespace() => continue,
+            ch => Token::Char(ch),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn parse_math(source: &str) -> MathNode {
+    let mut parser = MathParser {
+        tokens: tokenize(source),
+        position: 0,
+    };
+    let rows = parser.parse_rows();
+    single_cell(rows).unwrap_or_else(|rows| MathNode::Table {
+        left: String::new(),
+        right: String::new(),
+        rows,
+    })
+}
+
+/// Unwraps rows that only hold one cell, which is what any content without `&` or `\\` parses to.
+fn single_cell(mut rows: Vec<Vec<MathNode>>) -> Result<MathNode, Vec<Vec<MathNode>>> {
+    if rows.len() == 1 && rows[0].len() == 1 {
+        Ok(rows.remove(0).remove(0))
+    } else {
+        Err(rows)
+    }
+}
+
+/// What ended a sequence of math.
+#[derive(Debug, PartialEq)]
+enum Terminator {
+    EndOfInput,
+    EndGroup,
+    ColumnSeparator,
+    RowSeparator,
+    Right(String),
+    End,
+}
+
+struct MathParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl MathParser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Parses cells separated by `&` and rows separated by `\\`, until the end of the input or of
+    /// the enclosing environment.
+    fn parse_rows(&mut self) -> Vec<Vec<MathNode>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+
+        loop {
+            let (cell, terminator) = self.parse_sequence();
+            row.push(cell);
+            match terminator {
+                Terminator::ColumnSeparator => {}
+                Terminator::RowSeparator => {
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => break,
+            }
+        }
+
+        // A trailing `\\` leaves an empty row behind
+        if !(row.len() == 1 && row[0] == MathNode::Row(Vec::new()) && !rows.is_empty()) {
+            rows.push(row);
+        }
+        rows
+    }
+
+    fn parse_sequence(&mut self) -> (MathNode, Terminator) {
+        let mut nodes = Vec::new();
+
+        let terminator = loop {
+            let Some(token) = self.next() else {
+                break Terminator::EndOfInput;
+            };
+
+            let node = match token {
+                Token::EndGroup => break Terminator::EndGroup,
+                Token::ColumnSeparator => break Terminator::ColumnSeparator,
+                Token::Command(name) if name == "\\" || name == "cr" => {
+                    break Terminator::RowSeparator
+                }
+                Token::Command(name) if name == "right" => {
+                    break Terminator::Right(self.parse_delimiter())
+                }
+                Token::Command(name) if name == "end" => {
+                    self.parse_group_text();
+                    break Terminator::End;
+                }
+                Token::Superscript | Token::Subscript => {
+                    // A script with nothing before it, e.g. `{}^{14}C`
+                    self.position -= 1;
+                    MathNode::Row(Vec::new())
+                }
+                token => match self.parse_token(token) {
+                    Some(node) => node,
+                    None => continue,
+                },
+            };
+
+            nodes.push(self.parse_scripts(node));
+        };
+
+        let node = if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            MathNode::Row(nodes)
+        };
+        (node, terminator)
+    }
+
+    fn parse_scripts(&mut self, base: MathNode) -> MathNode {
+        let mut superscript = None;
+        let mut subscript = None;
+
+        loop {
+            match self.peek() {
+                Some(Token::Superscript) if superscript.is_none() => {
+                    self.position += 1;
+                    superscript = Some(Box::new(self.parse_argument()));
+                }
+                Some(Token::Subscript) if subscript.is_none() => {
+                    self.position += 1;
+                    subscript = Some(Box::new(self.parse_argument()));
+                }
+                Some(Token::Char('′')) if superscript.is_none() => {
+                    self.position += 1;
+                    superscript = Some(Box::new(MathNode::symbol("′", SymbolStyle::Upright)));
+                }
+                _ => break,
+            }
+        }
+
+        if superscript.is_none() && subscript.is_none() {
+            base
+        } else {
+            MathNode::Scripts {
+                base: Box::new(base),
+                superscript,
+                subscript,
+            }
+        }
+    }
+
+    /// Parses the argument of a command or script: a group, or a single token.
+    fn parse_argument(&mut self) -> MathNode {
+        loop {
+            match self.next() {
+                Some(Token::BeginGroup) => return self.parse_sequence().0,
+                Some(token @ (Token::Command(_) | Token::Char(_))) => {
+                    if let Some(node) = self.parse_token(token) {
+                        return node;
+                    }
+                }
+                _ => return MathNode::Row(Vec::new()),
+            }
+        }
+    }
+
+    /// Parses an optional `[...]` argument, like the index of `\sqrt[3]{x}`.
+    fn parse_optional_argument(&mut self) -> Option<MathNode> {
+        if self.peek() != Some(&Token::Char('[')) {
+            return None;
+        }
+        self.position += 1;
+
+        let start = self.position;
+        while let Some(token) = self.next() {
+            if token == Token::Char(']') {
+                let mut parser = MathParser {
+                    tokens: self.tokens[start..self.position - 1].to_vec(),
+                    position: 0,
+                };
+                return Some(parser.parse_sequence().0);
+            }
+        }
+        None
+    }
+
+    /// Reads a group as plain text, for `\text{...}` and environment names.
+    fn parse_group_text(&mut self) -> String {
+        let mut text = String::new();
+        if self.peek() != Some(&Token::BeginGroup) {
+            if let Some(token) = self.next() {
+                push_token_text(&mut text, &token);
+            }
+            return text;
+        }
+        self.position += 1;
+
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                Token::BeginGroup => depth += 1,
+                Token::EndGroup if depth == 0 => break,
+                Token::EndGroup => depth -= 1,
+                token => push_token_text(&mut text, &token),
+            }
+        }
+        text
+    }
+
+    fn parse_delimiter(&mut self) -> String {
+        match self.next() {
+            Some(Token::Char('.')) | None => String::new(),
+            Some(Token::Char(ch)) => ch.to_string(),
+            Some(Token::Command(name)) => match name.as_str() {
+                "{" | "lbrace" => "{".to_string(),
+                "}" | "rbrace" => "}".to_string(),
+                "|" | "Vert" => "‖".to_string(),
+                "langle" => "⟨".to_string(),
+                "rangle" => "⟩".to_string(),
+                "lfloor" => "⌊".to_string(),
+                "rfloor" => "⌋".to_string(),
+                "lceil" => "⌈".to_string(),
+                "rceil" => "⌉".to_string(),
+                "vert" => "|".to_string(),
+                _ => String::new(),
+            },
+            Some(_) => String::new(),
+        }
+    }
+
+    fn parse_token(&mut self, token: Token) -> Option<MathNode> {
+        match token {
+            Token::BeginGroup => Some(self.parse_sequence().0),
+            Token::Char(ch) => Some(char_symbol(ch)),
+            Token::Command(name) => self.parse_command(&name),
+            Token::EndGroup | Token::Superscript | Token::Subscript | Token::ColumnSeparator => {
+                None
+            }
+        }
+    }
+
+    fn parse_command(&mut self, name: &str) -> Option<MathNode> {
+        let node = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => MathNode::Fraction {
+                numerator: Box::new(self.parse_argument()),
+                denominator: Box::new(self.parse_argument()),
+                has_rule: true,
+            },
+            "binom" | "dbinom" | "tbinom" => MathNode::Delimited {
+                left: "(".to_string(),
+                right: ")".to_string(),
+                body: Box::new(MathNode::Fraction {
+                    numerator: Box::new(self.parse_argument()),
+                    denominator: Box::new(self.parse_argument()),
+                    has_rule: false,
+                }),
+            },
+            "sqrt" => MathNode::Root {
+                index: self.parse_optional_argument().map(Box::new),
+                radicand: Box::new(self.parse_argument()),
+            },
+            "left" => {
+                let left = self.parse_delimiter();
+                let (body, terminator) = self.parse_sequence();
+                let right = match terminator {
+                    Terminator::Right(right) => right,
+                    _ => String::new(),
+                };
+                MathNode::Delimited {
+                    left,
+                    right,
+                    body: Box::new(body),
+                }
+            }
+            "begin" => self.parse_environment(),
+            "text" | "textrm" | "textnormal" | "mbox" | "mathrm" | "operatorname" | "textsf"
+            | "mathsf" | "texttt" | "mathtt" => {
+                MathNode::symbol(self.parse_group_text(), SymbolStyle::Upright)
+            }
+            "textit" | "mathit" => MathNode::symbol(self.parse_group_text(), SymbolStyle::Italic),
+            "textbf" | "mathbf" | "boldsymbol" | "bm" => {
+                MathNode::symbol(self.parse_group_text(), SymbolStyle::Bold)
+            }
+            "mathbb" => MathNode::symbol(
+                self.parse_group_text()
+                    .chars()
+                    .map(double_struck)
+                    .collect::<String>(),
+                SymbolStyle::Upright,
+            ),
+            "mathcal" | "mathscr" => MathNode::symbol(
+                self.parse_group_text()
+                    .chars()
+                    .map(script_letter)
+                    .collect::<String>(),
+                SymbolStyle::Upright,
+            ),
+            "hat" | "widehat" => self.parse_accent('\u{302}'),
+            "bar" | "overline" => self.parse_accent('\u{304}'),
+            "tilde" | "widetilde" => self.parse_accent('\u{303}'),
+            "vec" | "overrightarrow" => self.parse_accent('\u{20d7}'),
+            "dot" => self.parse_accent('\u{307}'),
+            "ddot" => self.parse_accent('\u{308}'),
+            "acute" => self.parse_accent('\u{301}'),
+            "grave" => self.parse_accent('\u{300}'),
+            "check" => self.parse_accent('\u{30c}'),
+            "breve" => self.parse_accent('\u{306}'),
+            "quad" => MathNode::Space(1.),
+            "qquad" => MathNode::Space(2.),
+            "," | "thinspace" => MathNode::Space(0.17),
+            ":" | ">" | "medspace" => MathNode::Space(0.22),
+            ";" | "thickspace" => MathNode::Space(0.28),
+            " " | "~" => MathNode::Space(0.25),
+            "!" | "negthinspace" => MathNode::Space(0.),
+            // Style and spacing hints that don't change the layout here
+            "displaystyle" | "textstyle" | "scriptstyle" | "limits" | "nolimits" | "mathstrut"
+            | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+            | "biggr" | "Biggl" | "Biggr" | "nonumber" | "notag" => return None,
+            "label" | "tag" => {
+                self.parse_group_text();
+                return None;
+            }
+            name => command_symbol(name),
+        };
+        Some(node)
+    }
+
+    fn parse_accent(&mut self, accent: char) -> MathNode {
+        MathNode::Accent {
+            accent,
+            body: Box::new(self.parse_argument()),
+        }
+    }
+
+    fn parse_environment(&mut self) -> MathNode {
+        let name = self.parse_group_text();
+        let (left, right) = match name.trim_end_matches('*') {
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("‖", "‖"),
+            "cases" => ("{", ""),
+            "array" | "tabular" => {
+                // Skip the column specification
+                self.parse_group_text();
+                ("", "")
+            }
+            _ => ("", ""),
+        };
+
+        match single_cell(self.parse_rows()) {
+            Ok(body) if left.is_empty() && right.is_empty() => body,
+            Ok(body) => MathNode::Table {
+                left: left.to_string(),
+                right: right.to_string(),
+                rows: vec![vec![body]],
+            },
+            Err(rows) => MathNode::Table {
+                left: left.to_string(),
+                right: right.to_string(),
+                rows,
+            },
+        }
+    }
+}
+
+fn push_token_text(text: &mut String, token: &Token) {
+    match token {
+        Token::Char(ch) => text.push(*ch),
+        Token::Command(name) if name == " " || name == "," || name == ";" => text.push(' '),
+        Token::Command(name) => {
+            if let MathNode::Symbol { text: symbol, .. } = command_symbol(name) {
+                text.push_str(&symbol);
+            }
+        }
+        Token::Superscript => text.push('^'),
+        Token::Subscript => text.push('_'),
+        Token::ColumnSeparator => text.push('&'),
+        Token::BeginGroup | Token::EndGroup => {}
+    }
+}
+
+fn char_symbol(ch: char) -> MathNode {
+    match ch {
+        ch if ch.is_alphabetic() => MathNode::symbol(ch, SymbolStyle::Italic),
+        '-' => MathNode::symbol(" − ", SymbolStyle::Upright),
+        '+' | '=' | '<' | '>' => MathNode::symbol(format!(" {ch} "), SymbolStyle::Upright),
+        '*' => MathNode::symbol("∗", SymbolStyle::Upright),
+        ',' | ';' => MathNode::symbol(format!("{ch} "), SymbolStyle::Upright),
+        ch => MathNode::symbol(ch, SymbolStyle::Upright),
+    }
+}
+
+fn command_symbol(name: &str) -> MathNode {
+    let (text, style) = match name {
+        // Lowercase Greek
+        "alpha" => ("α", SymbolStyle::Italic),
+        "beta" => ("β", SymbolStyle::Italic),
+        "gamma" => ("γ", SymbolStyle::Italic),
+        "delta" => ("δ", SymbolStyle::Italic),
+        "epsilon" => ("ϵ", SymbolStyle::Italic),
+        "varepsilon" => ("ε", SymbolStyle::Italic),
+        "zeta" => ("ζ", SymbolStyle::Italic),
+        "eta" => ("η", SymbolStyle::Italic),
+        "theta" => ("θ", SymbolStyle::Italic),
+        "vartheta" => ("ϑ", SymbolStyle::Italic),
+        "iota" => ("ι", SymbolStyle::Italic),
+        "kappa" => ("κ", SymbolStyle::Italic),
+        "lambda" => ("λ", SymbolStyle::Italic),
+        "mu" => ("μ", SymbolStyle::Italic),
+        "nu" => ("ν", SymbolStyle::Italic),
+        "xi" => ("ξ", SymbolStyle::Italic),
+        "pi" => ("π", SymbolStyle::Italic),
+        "varpi" => ("ϖ", SymbolStyle::Italic),
+        "rho" => ("ρ", SymbolStyle::Italic),
+        "varrho" => ("ϱ", SymbolStyle::Italic),
+        "sigma" => ("σ", SymbolStyle::Italic),
+        "varsigma" => ("ς", SymbolStyle::Italic),
+        "tau" => ("τ", SymbolStyle::Italic),
+        "upsilon" => ("υ", SymbolStyle::Italic),
+        "phi" => ("ϕ", SymbolStyle::Italic),
+        "varphi" => ("φ", SymbolStyle::Italic),
+        "chi" => ("χ", SymbolStyle::Italic),
+        "psi" => ("ψ", SymbolStyle::Italic),
+        "omega" => ("ω", SymbolStyle::Italic),
+        // Uppercase Greek
+        "Gamma" => ("Γ", SymbolStyle::Upright),
+        "Delta" => ("Δ", SymbolStyle::Upright),
+        "Theta" => ("Θ", SymbolStyle::Upright),
+        "Lambda" => ("Λ", SymbolStyle::Upright),
+        "Xi" => ("Ξ", SymbolStyle::Upright),
+        "Pi" => ("Π", SymbolStyle::Upright),
+        "Sigma" => ("Σ", SymbolStyle::Upright),
+        "Upsilon" => ("Υ", SymbolStyle::Upright),
+        "Phi" => ("Φ", SymbolStyle::Upright),
+        "Psi" => ("Ψ", SymbolStyle::Upright),
+        "Omega" => ("Ω", SymbolStyle::Upright),
+        // Binary operators and relations, which get space around them
+        "cdot" => (" · ", SymbolStyle::Upright),
+        "times" => (" × ", SymbolStyle::Upright),
+        "div" => (" ÷ ", SymbolStyle::Upright),
+        "pm" => (" ± ", SymbolStyle::Upright),
+        "mp" => (" ∓ ", SymbolStyle::Upright),
+        "circ" => (" ∘ ", SymbolStyle::Upright),
+        "ast" => (" ∗ ", SymbolStyle::Upright),
+        "star" => (" ⋆ ", SymbolStyle::Upright),
+        "oplus" => (" ⊕ ", SymbolStyle::Upright),
+        "otimes" => (" ⊗ ", SymbolStyle::Upright),
+        "cup" => (" ∪ ", SymbolStyle::Upright),
+        "cap" => (" ∩ ", SymbolStyle::Upright),
+        "wedge" | "land" => (" ∧ ", SymbolStyle::Upright),
+        "vee" | "lor" => (" ∨ ", SymbolStyle::Upright),
+        "setminus" => (" ∖ ", SymbolStyle::Upright),
+        "leq" | "le" => (" ≤ ", SymbolStyle::Upright),
+        "geq" | "ge" => (" ≥ ", SymbolStyle::Upright),
+        "neq" | "ne" => (" ≠ ", SymbolStyle::Upright),
+        "approx" => (" ≈ ", SymbolStyle::Upright),
+        "equiv" => (" ≡ ", SymbolStyle::Upright),
+        "sim" => (" ∼ ", SymbolStyle::Upright),
+        "simeq" => (" ≃ ", SymbolStyle::Upright),
+        "cong" => (" ≅ ", SymbolStyle::Upright),
+        "propto" => (" ∝ ", SymbolStyle::Upright),
+        "ll" => (" ≪ ", SymbolStyle::Upright),
+        "gg" => (" ≫ ", SymbolStyle::Upright),
+        "in" => (" ∈ ", SymbolStyle::Upright),
+        "notin" => (" ∉ ", SymbolStyle::Upright),
+        "ni" => (" ∋ ", SymbolStyle::Upright),
+        "subset" => (" ⊂ ", SymbolStyle::Upright),
+        "supset" => (" ⊃ ", SymbolStyle::Upright),
+        "subseteq" => (" ⊆ ", SymbolStyle::Upright),
+        "supseteq" => (" ⊇ ", SymbolStyle::Upright),
+        "mid" => (" ∣ ", SymbolStyle::Upright),
+        "parallel" => (" ∥ ", SymbolStyle::Upright),
+        "perp" => (" ⊥ ", SymbolStyle::Upright),
+        "to" | "rightarrow" => (" → ", SymbolStyle::Upright),
+        "leftarrow" | "gets" => (" ← ", SymbolStyle::Upright),
+        "leftrightarrow" => (" ↔ ", SymbolStyle::Upright),
+        "Rightarrow" | "implies" => (" ⇒ ", SymbolStyle::Upright),
+        "Leftarrow" => (" ⇐ ", SymbolStyle::Upright),
+        "Leftrightarrow" | "iff" => (" ⇔ ", SymbolStyle::Upright),
+        "mapsto" => (" ↦ ", SymbolStyle::Upright),
+        // Ordinary symbols
+        "infty" => ("∞", SymbolStyle::Upright),
+        "partial" => ("∂", SymbolStyle::Upright),
+        "nabla" => ("∇", SymbolStyle::Upright),
+        "forall" => ("∀", SymbolStyle::Upright),
+        "exists" => ("∃", SymbolStyle::Upright),
+        "emptyset" | "varnothing" => ("∅", SymbolStyle::Upright),
+        "neg" | "lnot" => ("¬", SymbolStyle::Upright),
+        "hbar" => ("ℏ", SymbolStyle::Italic),
+        "ell" => ("ℓ", SymbolStyle::Italic),
+        "Re" => ("ℜ", SymbolStyle::Upright),
+        "Im" => ("ℑ", SymbolStyle::Upright),
+        "aleph" => ("ℵ", SymbolStyle::Upright),
+        "angle" => ("∠", SymbolStyle::Upright),
+        "degree" => ("°", SymbolStyle::Upright),
+        "prime" => ("′", SymbolStyle::Upright),
+        "dots" | "ldots" => ("…", SymbolStyle::Upright),
+        "cdots" => ("⋯", SymbolStyle::Upright),
+        "vdots" => ("⋮", SymbolStyle::Upright),
+        "ddots" => ("⋱", SymbolStyle::Upright),
+        "langle" => ("⟨", SymbolStyle::Upright),
+        "rangle" => ("⟩", SymbolStyle::Upright),
+        "lfloor" => ("⌊", SymbolStyle::Upright),
+        "rfloor" => ("⌋", SymbolStyle::Upright),
+        "lceil" => ("⌈", SymbolStyle::Upright),
+        "rceil" => ("⌉", SymbolStyle::Upright),
+        "{" | "lbrace" => ("{", SymbolStyle::Upright),
+        "}" | "rbrace" => ("}", SymbolStyle::Upright),
+        "|" | "Vert" => ("‖", SymbolStyle::Upright),
+        "vert" => ("|", SymbolStyle::Upright),
+        "$" | "%" | "&" | "#" | "_" => (name, SymbolStyle::Upright),
+        "backslash" => ("\\", SymbolStyle::Upright),
+        // Large operators, whose limits go above and below them
+        "sum" => ("∑", SymbolStyle::Limits),
+        "prod" => ("∏", SymbolStyle::Limits),
+        "coprod" => ("∐", SymbolStyle::Limits),
+        "bigcup" => ("⋃", SymbolStyle::Limits),
+        "bigcap" => ("⋂", SymbolStyle::Limits),
+        "bigoplus" => ("⨁", SymbolStyle::Limits),
+        "bigotimes" => ("⨂", SymbolStyle::Limits),
+        "int" => ("∫", SymbolStyle::Upright),
+        "iint" => ("∬", SymbolStyle::Upright),
+        "iiint" => ("∭", SymbolStyle::Upright),
+        "oint" => ("∮", SymbolStyle::Upright),
+        "lim" | "max" | "min" | "sup" | "inf" | "limsup" | "liminf" | "det" | "gcd" => {
+            (name, SymbolStyle::Limits)
+        }
+        // Functions, which are set upright
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "coth" | "log" | "ln" | "lg" | "exp" | "arg" | "deg" | "dim"
+        | "ker" | "hom" | "Pr" | "sgn" | "tr" => (name, SymbolStyle::Upright),
+        // Unknown commands are shown as written
+        _ => return MathNode::symbol(format!("\\{name}"), SymbolStyle::Upright),
+    };
+    MathNode::symbol(text, style)
+}
+
+fn double_struck(ch: char) -> char {
+    match ch {
+        'C' => 'ℂ',
+        'H' => 'ℍ',
+        'N' => 'ℕ',
+        'P' => 'ℙ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'Z' => 'ℤ',
+        ch @ 'A'..='Z' => char::from_u32('𝔸' as u32 + (ch as u32 - 'A' as u32)).unwrap_or(ch),
+        ch => ch,
+    }
+}
+
+fn script_letter(ch: char) -> char {
+    match ch {
+        'B' => 'ℬ',
+        'E' => 'ℰ',
+        'F' => 'ℱ',
+        'H' => 'ℋ',
+        'I' => 'ℐ',
+        'L' => 'ℒ',
+        'M' => 'ℳ',
+        'R' => 'ℛ',
+        ch @ 'A'..='Z' => char::from_u32('𝒜' as u32 + (ch as u32 - 'A' as u32)).unwrap_or(ch),
+        ch => ch,
+    }
+}
+
+/// Lays out math with nested flex elements, shrinking the text at each level of scripts.
+struct MathRenderer {
+    font_size: Pixels,
+    color: Hsla,
+}
+
+impl MathRenderer {
+    fn size(&self, level: usize) -> Pixels {
+        self.font_size * SCRIPT_SCALE.powi(level as i32).max(MIN_SCALE)
+    }
+
+    fn render(&self, node: &MathNode, level: usize) -> AnyElement {
+        match node {
+            MathNode::Symbol { text, style } => div()
+                .flex_none()
+                .text_size(self.size(level))
+                .whitespace_nowrap()
+                .map(|this| match style {
+                    SymbolStyle::Italic => this.italic(),
+                    SymbolStyle::Bold => this.font_weight(FontWeight::BOLD),
+                    SymbolStyle::Limits if text.chars().count() == 1 => {
+                        this.text_size(self.size(level) * 1.4)
+                    }
+                    SymbolStyle::Upright | SymbolStyle::Limits => this,
+                })
+                .child(text.clone())
+                .into_any_element(),
+            MathNode::Row(children) => h_flex()
+                .flex_none()
+                .items_center()
+                .children(children.iter().map(|child| self.render(child, level)))
+                .into_any_element(),
+            MathNode::Scripts {
+                base,
+                superscript,
+                subscript,
+            } => {
+                let script_level = level + 1;
+                let render_script = |script: &Option<Box<MathNode>>| match script {
+                    Some(script) => self.render(script, script_level),
+                    // Keeps the other script above or below the middle of the base
+                    None => div()
+                        .text_size(self.size(script_level))
+                        .child("\u{a0}")
+                        .into_any_element(),
+                };
+
+                if matches!(
+                    **base,
+                    MathNode::Symbol {
+                        style: SymbolStyle::Limits,
+                        ..
+                    }
+                ) {
+                    v_flex()
+                        .flex_none()
+                        .items_center()
+                        .children(
+                            superscript
+                                .as_ref()
+                                .map(|sup| self.render(sup, script_level)),
+                        )
+                        .child(self.render(base, level))
+                        .children(subscript.as_ref().map(|sub| self.render(sub, script_level)))
+                        .into_any_element()
+                } else {
+                    h_flex()
+                        .flex_none()
+                        .items_center()
+                        .child(self.render(base, level))
+                        .child(
+                            v_flex()
+                                .flex_none()
+                                .child(render_script(superscript))
+                                .child(render_script(subscript)),
+                        )
+                        .into_any_element()
+                }
+            }
+            MathNode::Fraction {
+                numerator,
+                denominator,
+                has_rule,
+            } => v_flex()
+                .flex_none()
+                .items_center()
+                .px_0p5()
+                .child(self.render(numerator, level))
+                .child(
+                    div()
+                        .w_full()
+                        .h_px()
+                        .when(*has_rule, |this| this.bg(self.color)),
+                )
+                .child(self.render(denominator, level))
+                .into_any_element(),
+            MathNode::Root { index, radicand } => h_flex()
+                .flex_none()
+                .items_end()
+                .children(index.as_ref().map(|index| self.render(index, level + 2)))
+                .child(
+                    div()
+                        .text_size(self.size(level) * radicand.height())
+                        .child("√"),
+                )
+                .child(
+                    div()
+                        .border_t_1()
+                        .border_color(self.color)
+                        .child(self.render(radicand, level)),
+                )
+                .into_any_element(),
+            MathNode::Accent { accent, body } => match &**body {
+                MathNode::Symbol { text, style } if text.chars().count() == 1 => {
+                    self.render(&MathNode::symbol(format!("{text}{accent}"), *style), level)
+                }
+                body => v_flex()
+                    .flex_none()
+                    .items_center()
+                    .child(
+                        div()
+                            .h(self.size(level + 1))
+                            .text_size(self.size(level))
+                            .child(format!("\u{a0}{accent}")),
+                    )
+                    .child(self.render(body, level))
+                    .into_any_element(),
+            },
+            MathNode::Delimited { left, right, body } => {
+                let delimiter_size = self.size(level) * body.height();
+                h_flex()
+                    .flex_none()
+                    .items_center()
+                    .child(div().text_size(delimiter_size).child(left.clone()))
+                    .child(self.render(body, level))
+                    .child(div().text_size(delimiter_size).child(right.clone()))
+                    .into_any_element()
+            }
+            MathNode::Table { left, right, rows } => {
+                let delimiter_size = self.size(level) * node.height();
+                let column_count = rows.iter().map(Vec::len).max().unwrap_or(0);
+                // `cases` and aligned equations line up on the left, matrices are centered
+                let align_start = right.is_empty();
+
+                h_flex()
+                    .flex_none()
+                    .items_center()
+                    .child(div().text_size(delimiter_size).child(left.clone()))
+                    .child(h_flex().flex_none().items_start().gap_3().children(
+                        (0..column_count).map(|column| {
+                            v_flex()
+                                .flex_none()
+                                .map(|this| {
+                                    if align_start {
+                                        this.items_start()
+                                    } else {
+                                        this.items_center()
+                                    }
+                                })
+                                .children(rows.iter().map(|row| match row.get(column) {
+                                    Some(cell) => self.render(cell, level),
+                                    None => div().into_any_element(),
+                                }))
+                        }),
+                    ))
+                    .child(div().text_size(delimiter_size).child(right.clone()))
+                    .into_any_element()
+            }
+            MathNode::Space(width) => div()
+                .flex_none()
+                .w(self.size(level) * *width)
+                .into_any_element(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_math_to_unicode() {
+        assert_eq!(parse_math("x^2 + y_1").to_unicode(), "x² + y₁");
+        assert_eq!(parse_math(r"\frac{a+b}{2}").to_unicode(), "(a + b)/2");
+        assert_eq!(parse_math(r"\sqrt{\alpha}").to_unicode(), "√α");
+        assert_eq!(parse_math(r"e^{i \pi}").to_unicode(), "e^(iπ)");
+    }
+
+    #[test]
+    fn test_parse_sympy_output() {
+        let segments =
+            parse_segments(r"$\displaystyle \left[\begin{matrix}1 & 2\\3 & 4\end{matrix}\right]$");
+        let [LatexSegment::Math {
+            node,
+            display: false,
+        }] = segments.as_slice()
+        else {
+            panic!("expected a single inline math segment, got {segments:?}");
+        };
+        let MathNode::Delimited { left, right, body } = node else {
+            panic!("expected delimited math, got {node:?}");
+        };
+        assert_eq!((left.as_str(), right.as_str()), ("[", "]"));
+        let MathNode::Table { rows, .. } = &**body else {
+            panic!("expected a matrix, got {body:?}");
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].len(), 2);
+    }
+
+    #[test]
+    fn test_replace_math_with_unicode() {
+        assert_eq!(
+            replace_math_with_unicode("The area is $\\pi r^2$, costs $5 and $10.\n"),
+            "The area is πr², costs $5 and $10.\n"
+        );
+        assert_eq!(
+            replace_math_with_unicode("`$x^2$` and\n```\n$y^2$\n```\n"),
+            "`$x^2$` and\n```\n$y^2$\n```\n"
+        );
+    }
+}
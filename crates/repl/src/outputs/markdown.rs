@@ -1,13 +1,13 @@
 use anyhow::Result;
-use gpui::{div, prelude::*, ClipboardItem, Model, Task, ViewContext, WindowContext};
+use gpui::{div, AnyElement, ClipboardItem, Model, Task, ViewContext, WindowContext};
 use language::Buffer;
 use markdown_preview::{
     markdown_elements::ParsedMarkdown, markdown_parser::parse_markdown,
     markdown_renderer::render_markdown_block,
 };
-use ui::v_flex;
+use ui::{prelude::*, v_flex};
 
-use crate::outputs::OutputContent;
+use crate::outputs::{latex::replace_math_with_unicode, OutputContent};
 
 pub struct MarkdownView {
     raw_text: String,
@@ -19,9 +19,9 @@ impl MarkdownView {
     pub fn from(text: String, cx: &mut ViewContext<Self>) -> Self {
         let task = cx.spawn(|markdown_view, mut cx| {
             let text = text.clone();
-            let parsed = cx
-                .background_executor()
-                .spawn(async move { parse_markdown(&text, None, None).await });
+            let parsed = cx.background_executor().spawn(async move {
+                parse_markdown(&replace_math_with_unicode(&text), None, None).await
+            });
 
             async move {
                 let content = parsed.await;
@@ -73,19 +73,29 @@ impl Render for MarkdownView {
             return div().into_any_element();
         };
 
-        let mut markdown_render_context =
-            markdown_preview::markdown_renderer::RenderContext::new(None, cx);
-
-        v_flex()
-            .gap_3()
-            .py_4()
-            .children(parsed.children.iter().map(|child| {
-                div().relative().child(
-                    div()
-                        .relative()
-                        .child(render_markdown_block(child, &mut markdown_render_context)),
-                )
-            }))
-            .into_any_element()
+        render_parsed_markdown(parsed, cx)
     }
 }
+
+/// Renders parsed markdown the way notebook markdown cells do, so `text/markdown` outputs
+/// look the same as the cells around them.
+pub(crate) fn render_parsed_markdown(
+    parsed: &ParsedMarkdown,
+    cx: &mut WindowContext,
+) -> AnyElement {
+    let mut markdown_render_context =
+        markdown_preview::markdown_renderer::RenderContext::new(None, cx);
+
+    v_flex()
+        .w_full()
+        .font_ui(cx)
+        .text_size(TextSize::Default.rems(cx))
+        .children(parsed.children.iter().map(|child| {
+            div().relative().child(
+                div()
+                    .relative()
+                    .child(render_markdown_block(child, &mut markdown_render_context)),
+            )
+        }))
+        .into_any_element()
+}
@@ -15,22 +15,19 @@
 //! - Error tracebacks
 //!
 
-use alacritty_terminal::{
-    grid::Dimensions as _,
-    index::{Column, Line, Point},
-    term::Config,
-    vte::ansi::Processor,
-};
-use gpui::{canvas, size, ClipboardItem, FontStyle, Model, TextStyle, WhiteSpace};
-use language::Buffer;
+use alacritty_terminal::{grid::Dimensions as _, term::Config, vte::ansi::Processor};
+use gpui::{canvas, size, AppContext, ClipboardItem, FontStyle, Model, TextStyle, WhiteSpace};
+use language::{Buffer, Rope};
 use settings::Settings as _;
 use std::mem;
+use std::rc::Rc;
 use terminal::ZedListener;
-use terminal_view::terminal_element::TerminalElement;
+use terminal_view::terminal_element::{LayoutCell, LayoutRect, TerminalElement};
 use theme::ThemeSettings;
 use ui::{prelude::*, IntoElement};
 
 use crate::outputs::OutputContent;
+use crate::NotebookSettings;
 
 /// The `TerminalOutput` struct handles the parsing and rendering of text input,
 /// simulating a basic terminal environment within REPL output.
@@ -51,10 +48,146 @@ pub struct TerminalOutput {
     parser: Processor,
     /// Alacritty terminal instance that manages the terminal state and content.
     handler: alacritty_terminal::Term<ZedListener>,
+    /// The line currently being written, which a carriage return can overwrite.
+    current_line: LineOverwrite,
+    /// The grid row `current_line` starts on, counted from the top of the scrollback.
+    current_line_start: usize,
+    /// Every line written so far, without escape sequences. The terminal only keeps a limited
+    /// scrollback, and streams can run to tens of thousands of lines.
+    lines: Rope,
+    /// The laid out grid from the last render, reused until more text is written.
+    layout: Option<Rc<GridLayout>>,
+    /// How many columns the grid has, which changes with `notebook.output_soft_wrap`.
+    columns: usize,
+}
+
+/// The terminal grid shaped for painting, which only covers the visible tail of the output.
+struct GridLayout {
+    text_style: TextStyle,
+    cells: Vec<LayoutCell>,
+    rects: Vec<LayoutRect>,
+}
+
+/// A line of output with carriage return semantics: `\r` moves back to the start of the line
+/// and the text that follows overwrites what was there, like progress bars from tqdm expect.
+#[derive(Default)]
+struct LineOverwrite {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineOverwrite {
+    fn is_at_end(&self) -> bool {
+        self.cursor == self.chars.len()
+    }
+
+    /// Applies text that contains no newlines to the line.
+    fn write(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch == '\r' {
+                self.cursor = 0;
+            } else if self.cursor < self.chars.len() {
+                self.chars[self.cursor] = ch;
+                self.cursor += 1;
+            } else {
+                self.chars.push(ch);
+                self.cursor += 1;
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn clear(&mut self) {
+        self.chars.clear();
+        self.cursor = 0;
+    }
+}
+
+/// Resolves carriage returns in `text` the way a terminal would display it, so that a progress
+/// bar which redrew itself thousands of times is kept as its final state.
+///
+/// If the text ends partway through overwriting a line, the result ends with a `\r` followed by
+/// what has been overwritten so far, so that text appended later continues from the same place.
+pub fn collapse_carriage_returns(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut line = LineOverwrite::default();
+
+    for segment in text.split_inclusive('\n') {
+        match segment.strip_suffix('\n') {
+            Some(segment) => {
+                // `\r\n` ends a line without overwriting it
+                line.write(segment.strip_suffix('\r').unwrap_or(segment));
+                collapsed.push_str(&line.text());
+                collapsed.push('\n');
+                line.clear();
+            }
+            None => line.write(segment),
+        }
+    }
+
+    collapsed.push_str(&line.text());
+    if !line.is_at_end() {
+        collapsed.push('\r');
+        collapsed.extend(&line.chars[..line.cursor]);
+    }
+    collapsed
+}
+
+/// Removes ANSI escape sequences, like colors and cursor movement, from text.
+pub fn strip_escape_sequences(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            stripped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            // Control sequences end with a byte in `@`..=`~`
+            Some('[') => {
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        break;
+                    }
+                }
+            }
+            // Operating system commands end with a bell or `ESC \`
+            Some(']') => {
+                while let Some(ch) = chars.next() {
+                    if ch == '\x07' {
+                        break;
+                    }
+                    if ch == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stripped
 }
 
 const DEFAULT_NUM_LINES: usize = 32;
 const DEFAULT_NUM_COLUMNS: usize = 128;
+/// How many columns outputs have when they aren't wrapped. Lines longer than this still wrap, so
+/// the grid of a long output stays a reasonable size.
+const UNWRAPPED_NUM_COLUMNS: usize = 512;
+
+/// How many columns the grid of an output has, depending on whether its lines are wrapped.
+fn output_columns(cx: &AppContext) -> usize {
+    if NotebookSettings::get_global(cx).output_soft_wrap {
+        DEFAULT_NUM_COLUMNS
+    } else {
+        UNWRAPPED_NUM_COLUMNS
+    }
+}
 
 /// Returns the default text style for the terminal output.
 pub fn text_style(cx: &mut WindowContext) -> TextStyle {
@@ -103,7 +236,7 @@ pub fn terminal_size(cx: &mut WindowContext) -> terminal::TerminalSize {
         .width;
 
     let num_lines = DEFAULT_NUM_LINES;
-    let columns = DEFAULT_NUM_COLUMNS;
+    let columns = output_columns(cx);
 
     // Reversed math from terminal::TerminalSize to get pixel width according to terminal width
     let width = columns as f32 * cell_width;
@@ -135,6 +268,11 @@ impl TerminalOutput {
             parser: Processor::new(),
             handler: term,
             full_buffer: None,
+            current_line: LineOverwrite::default(),
+            current_line_start: 0,
+            lines: Rope::new(),
+            layout: None,
+            columns: output_columns(cx),
         }
     }
 
@@ -179,64 +317,101 @@ impl TerminalOutput {
     /// ```
     /// Resulting in a single output of "Hello, world!".
     ///
+    /// A carriage return moves back to the start of the current line, and the text after it
+    /// overwrites the line in place. This is how progress bars (tqdm, keras) update themselves,
+    /// so they stay on a single line instead of printing a new one for every update. This holds
+    /// even when the line is long enough to wrap in the terminal grid.
+    ///
     /// # Arguments
     ///
     /// * `text` - A string slice containing the text to be appended.
+    ///
+    /// Only the line being written is redrawn, so appending stays fast however long the output
+    /// gets.
     pub fn append_text(&mut self, text: &str, cx: &mut WindowContext) {
-        for byte in text.as_bytes() {
-            if *byte == b'\n' {
-                // Dirty (?) hack to move the cursor down
-                self.parser.advance(&mut self.handler, b'\r');
-                self.parser.advance(&mut self.handler, b'\n');
+        // Where the line being written starts in the full text
+        let tail_start = self.lines.len();
+
+        for segment in text.split_inclusive('\n') {
+            let (line, ends_line) = match segment.strip_suffix('\n') {
+                // `\r\n` ends a line without overwriting it
+                Some(line) => (line.strip_suffix('\r').unwrap_or(line), true),
+                None => (segment, false),
+            };
+
+            if !line.contains('\r') && self.current_line.is_at_end() {
+                self.current_line.write(line);
+                self.feed(line);
             } else {
-                self.parser.advance(&mut self.handler, *byte);
+                self.current_line.write(line);
+                self.rewrite_current_line();
+            }
+
+            if ends_line {
+                self.feed("\n");
+                self.lines
+                    .push(&strip_escape_sequences(&self.current_line.text()));
+                self.lines.push("\n");
+                self.current_line.clear();
+                self.current_line_start = self.cursor_row();
             }
         }
+        self.layout = None;
 
-        // This will keep the buffer up to date, though with some terminal codes it won't be perfect
+        // Only the lines written since the last append change in the buffer
         if let Some(buffer) = self.full_buffer.as_ref() {
+            let mut tail = self
+                .lines
+                .chunks_in_range(tail_start..self.lines.len())
+                .collect::<String>();
+            tail.push_str(&strip_escape_sequences(&self.current_line.text()));
             buffer.update(cx, |buffer, cx| {
-                buffer.edit([(buffer.len()..buffer.len(), text)], None, cx);
+                let tail_start = tail_start.min(buffer.len());
+                buffer.edit([(tail_start..buffer.len(), tail)], None, cx);
             });
         }
     }
 
-    fn full_text(&self) -> String {
-        let mut full_text = String::new();
-
-        // Get the total number of lines, including history
-        let total_lines = self.handler.grid().total_lines();
-        let visible_lines = self.handler.screen_lines();
-        let history_lines = total_lines - visible_lines;
-
-        // Capture history lines in correct order (oldest to newest)
-        for line in (0..history_lines).rev() {
-            let line_index = Line(-(line as i32) - 1);
-            let start = Point::new(line_index, Column(0));
-            let end = Point::new(line_index, Column(self.handler.columns() - 1));
-            let line_content = self.handler.bounds_to_string(start, end);
-
-            if !line_content.trim().is_empty() {
-                full_text.push_str(&line_content);
-                full_text.push('\n');
+    fn feed(&mut self, text: &str) {
+        for byte in text.as_bytes() {
+            if *byte == b'\n' {
+                // Dirty (?) hack to move the cursor down
+                self.parser.advance(&mut self.handler, b'\r');
+                self.parser.advance(&mut self.handler, b'\n');
+            } else {
+                self.parser.advance(&mut self.handler, *byte);
             }
         }
+    }
 
-        // Capture visible lines
-        for line in 0..visible_lines {
-            let line_index = Line(line as i32);
-            let start = Point::new(line_index, Column(0));
-            let end = Point::new(line_index, Column(self.handler.columns() - 1));
-            let line_content = self.handler.bounds_to_string(start, end);
+    fn cursor_row(&self) -> usize {
+        let cursor_line = self.handler.grid().cursor.point.line.0.max(0) as usize;
+        self.handler.history_size() + cursor_line
+    }
 
-            if !line_content.trim().is_empty() {
-                full_text.push_str(&line_content);
-                full_text.push('\n');
-            }
+    /// Erases the current line from the grid, including any rows it wrapped onto, and draws it again.
+    fn rewrite_current_line(&mut self) {
+        let rows_up = self.cursor_row().saturating_sub(self.current_line_start);
+        if rows_up > 0 {
+            // Cursor up
+            self.feed(&format!("\x1b[{rows_up}A"));
         }
+        // Back to the first column and erase everything below the cursor
+        self.feed("\r\x1b[J");
+
+        let text = self.current_line.text();
+        self.feed(&text);
+    }
 
-        // Trim any trailing newlines
-        full_text.trim_end().to_string()
+    /// Everything written so far, without escape sequences.
+    fn text(&self) -> String {
+        let mut text = self.lines.to_string();
+        text.push_str(&strip_escape_sequences(&self.current_line.text()));
+        text
+    }
+
+    fn full_text(&self) -> String {
+        self.text().trim_end().to_string()
     }
 }
 
@@ -247,22 +422,48 @@ impl Render for TerminalOutput {
     /// the layout of the terminal grid, calculates the dimensions of the output, and
     /// creates a canvas element that paints the terminal cells and background rectangles.
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let columns = output_columns(cx);
+        if columns != self.columns {
+            // Resizing the grid wraps or unwraps the lines written so far
+            self.columns = columns;
+            self.handler.resize(terminal_size(cx));
+            let current_line_width = strip_escape_sequences(&self.current_line.text())
+                .chars()
+                .count();
+            let current_line_rows = current_line_width.saturating_sub(1) / columns;
+            self.current_line_start = self.cursor_row().saturating_sub(current_line_rows);
+            self.layout = None;
+        }
+
         let text_style = text_style(cx);
         let text_system = cx.text_system();
 
-        let grid = self
-            .handler
-            .renderable_content()
-            .display_iter
-            .map(|ic| terminal::IndexedCell {
-                point: ic.point,
-                cell: ic.cell.clone(),
-            });
-        let (cells, rects) = TerminalElement::layout_grid(grid, &text_style, text_system, None, cx);
+        // Laying out the grid shapes every line, so it's only done when the output or the
+        // font changed
+        let layout = match &self.layout {
+            Some(layout) if layout.text_style == text_style => layout.clone(),
+            _ => {
+                let grid = self.handler.renderable_content().display_iter.map(|ic| {
+                    terminal::IndexedCell {
+                        point: ic.point,
+                        cell: ic.cell.clone(),
+                    }
+                });
+                let (cells, rects) =
+                    TerminalElement::layout_grid(grid, &text_style, text_system, None, cx);
+                let layout = Rc::new(GridLayout {
+                    text_style: text_style.clone(),
+                    cells,
+                    rects,
+                });
+                self.layout = Some(layout.clone());
+                layout
+            }
+        };
 
         // lines are 0-indexed, so we must add 1 to get the number of lines
         let text_line_height = text_style.line_height_in_pixels(cx.rem_size());
-        let num_lines = cells.iter().map(|c| c.point.line).max().unwrap_or(0) + 1;
+        let num_lines = layout.cells.iter().map(|c| c.point.line).max().unwrap_or(0) + 1;
         let height = num_lines as f32 * text_line_height;
 
         let font_pixels = text_style.font_size.to_pixels(cx.rem_size());
@@ -273,12 +474,21 @@ impl Render for TerminalOutput {
             .map(|advance| advance.width)
             .unwrap_or(Pixels(0.0));
 
-        canvas(
+        let num_columns = layout
+            .cells
+            .iter()
+            .map(|c| c.point.column)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let width = num_columns as f32 * cell_width;
+
+        let output = canvas(
             // prepaint
             move |_bounds, _| {},
             // paint
             move |bounds, _, cx| {
-                for rect in rects {
+                for rect in &layout.rects {
                     rect.paint(
                         bounds.origin,
                         &terminal::TerminalSize {
@@ -290,7 +500,7 @@ impl Render for TerminalOutput {
                     );
                 }
 
-                for cell in cells {
+                for cell in &layout.cells {
                     cell.paint(
                         bounds.origin,
                         &terminal::TerminalSize {
@@ -305,7 +515,17 @@ impl Render for TerminalOutput {
             },
         )
         // We must set the height explicitly for the editor block to size itself correctly
-        .h(height)
+        .h(height);
+
+        if NotebookSettings::get_global(cx).output_soft_wrap {
+            return output.into_any_element();
+        }
+        // Unwrapped lines run past the output's width, so it scrolls sideways
+        div()
+            .id("unwrapped-output")
+            .overflow_x_scroll()
+            .child(output.w(width))
+            .into_any_element()
     }
 }
 
@@ -327,9 +547,10 @@ impl OutputContent for TerminalOutput {
             return self.full_buffer.clone();
         }
 
+        // The buffer has the full text, so what's appended later can be written at the end of it
         let buffer = cx.new_model(|cx| {
             let mut buffer =
-                Buffer::local(self.full_text(), cx).with_language(language::PLAIN_TEXT.clone(), cx);
+                Buffer::local(self.text(), cx).with_language(language::PLAIN_TEXT.clone(), cx);
             buffer.set_capability(language::Capability::ReadOnly, cx);
             buffer
         });
@@ -338,3 +559,43 @@ impl OutputContent for TerminalOutput {
         Some(buffer)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_collapse_carriage_returns() {
+        assert_eq!(collapse_carriage_returns("no returns\n"), "no returns\n");
+        assert_eq!(
+            collapse_carriage_returns(" 10%|#  |\r 50%|## |\r100%|###|\ndone\n"),
+            "100%|###|\ndone\n"
+        );
+        assert_eq!(
+            collapse_carriage_returns("windows\r\nline\r\n"),
+            "windows\nline\n"
+        );
+        // Shorter text only overwrites the start of the line
+        assert_eq!(collapse_carriage_returns("abcdef\rxy"), "xycdef\rxy");
+        assert_eq!(collapse_carriage_returns("progress\r"), "progress\r");
+    }
+
+    #[test]
+    fn test_strip_escape_sequences() {
+        assert_eq!(
+            strip_escape_sequences("\x1b[1;31mError\x1b[0m: failed"),
+            "Error: failed"
+        );
+        assert_eq!(
+            strip_escape_sequences("\x1b]8;;https://zed.dev\x07link\x1b]8;;\x1b\\ text"),
+            "link text"
+        );
+        assert_eq!(strip_escape_sequences("epoch 1/10"), "epoch 1/10");
+    }
+
+    #[test]
+    fn test_collapse_carriage_returns_across_chunks() {
+        let first = collapse_carriage_returns("abcdef\rxy");
+        assert_eq!(collapse_carriage_returns(&(first + "z\n")), "xyzdef\n");
+    }
+}
@@ -54,6 +54,15 @@
 //! # Display the DataFrame
 //! display(df)
 //! ```
+//!
+//! Pandas' HTML tables are recognized as well, so DataFrames display as tables even without the
+//! table schema option.
+//!
+//! # Large Tables
+//!
+//! Rows are shown a page at a time, and clicking a column heading sorts the rows by that column.
+use std::cmp::Ordering;
+
 use gpui::{AnyElement, ClipboardItem, TextRun};
 use runtimelib::datatable::TableSchema;
 use runtimelib::media::datatable::TabularDataResource;
@@ -64,28 +73,83 @@ use ui::{div, prelude::*, v_flex, IntoElement, Styled};
 
 use crate::outputs::OutputContent;
 
-/// TableView renders a static table inline in a buffer.
+/// TableView renders a table inline in a buffer, a page of rows at a time.
 /// It uses the https://specs.frictionlessdata.io/tabular-data-resource/ specification for data interchange.
 pub struct TableView {
     pub table: TabularDataResource,
     pub widths: Vec<Pixels>,
     cached_clipboard_content: ClipboardItem,
+    sort: Option<TableSort>,
+    /// The indices of the rows in `table.data`, in the order they are displayed.
+    row_order: Vec<usize>,
+    page: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TableSort {
+    column: usize,
+    ascending: bool,
 }
 
 fn cell_content(row: &Value, field: &str) -> String {
-    match row.get(field) {
-        Some(Value::String(s)) => s.clone(),
-        Some(Value::Number(n)) => n.to_string(),
-        Some(Value::Bool(b)) => b.to_string(),
-        Some(Value::Array(arr)) => format!("{:?}", arr),
-        Some(Value::Object(obj)) => format!("{:?}", obj),
-        Some(Value::Null) | None => String::new(),
+    row.get(field).map(value_content).unwrap_or_default()
+}
+
+fn value_content(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Array(arr) => format!("{:?}", arr),
+        Value::Object(obj) => format!("{:?}", obj),
+        Value::Null => String::new(),
+    }
+}
+
+/// Joins rows of cells into tab-separated values, which spreadsheets and chat apps paste as a table.
+pub(crate) fn rows_to_tsv(rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    rows.into_iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.replace(['\t', '\n', '\r'], " "))
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Orders cells numerically when both are numbers, and by their text otherwise. Empty cells go last.
+fn compare_cells(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let is_empty = |value: Option<&Value>| matches!(value, None | Some(Value::Null));
+    match (is_empty(a), is_empty(b)) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+
+    match (a.and_then(Value::as_f64), b.and_then(Value::as_f64)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => {
+            let a = a.map(value_content).unwrap_or_default();
+            let b = b.map(value_content).unwrap_or_default();
+            a.cmp(&b)
+        }
     }
 }
 
 // Declare constant for the padding multiple on the line height
 const TABLE_Y_PADDING_MULTIPLE: f32 = 0.5;
 
+const ROWS_PER_PAGE: usize = 50;
+
+/// How many rows are measured to size the columns, so that huge tables don't stall the UI.
+const MEASURED_ROW_COUNT: usize = 1000;
+
+/// Room in each heading for the sort indicator.
+const SORT_ICON_WIDTH: f32 = 16.;
+
 impl TableView {
     pub fn new(table: &TabularDataResource, cx: &mut WindowContext) -> Self {
         let mut widths = Vec::with_capacity(table.schema.fields.len());
@@ -107,7 +171,7 @@ impl TableView {
             runs[0].len = field.name.len();
             let mut width = text_system
                 .layout_line(&field.name, font_size, &runs)
-                .map(|layout| layout.width)
+                .map(|layout| layout.width + px(SORT_ICON_WIDTH))
                 .unwrap_or(px(0.));
 
             let Some(data) = table.data.as_ref() else {
@@ -115,7 +179,7 @@ impl TableView {
                 continue;
             };
 
-            for row in data {
+            for row in data.iter().take(MEASURED_ROW_COUNT) {
                 let content = cell_content(row, &field.name);
                 runs[0].len = content.len();
                 let cell_width = cx
@@ -133,12 +197,164 @@ impl TableView {
         let cached_clipboard_content = Self::create_clipboard_content(table);
 
         Self {
+            row_order: (0..table.data.as_ref().map_or(0, Vec::len)).collect(),
             table: table.clone(),
             widths,
             cached_clipboard_content: ClipboardItem::new_string(cached_clipboard_content),
+            sort: None,
+            page: 0,
         }
     }
 
+    fn row_count(&self) -> usize {
+        self.row_order.len()
+    }
+
+    fn page_count(&self) -> usize {
+        self.row_count().div_ceil(ROWS_PER_PAGE).max(1)
+    }
+
+    fn set_page(&mut self, page: usize, cx: &mut ViewContext<Self>) {
+        self.page = page.min(self.page_count() - 1);
+        cx.notify();
+    }
+
+    /// Cycles a column between ascending, descending, and the original order.
+    fn toggle_sort(&mut self, column: usize, cx: &mut ViewContext<Self>) {
+        self.sort = match self.sort {
+            Some(sort) if sort.column == column && sort.ascending => Some(TableSort {
+                column,
+                ascending: false,
+            }),
+            Some(sort) if sort.column == column => None,
+            _ => Some(TableSort {
+                column,
+                ascending: true,
+            }),
+        };
+        self.sort_rows();
+        self.page = 0;
+        cx.notify();
+    }
+
+    fn sort_rows(&mut self) {
+        let Some(data) = self.table.data.as_ref() else {
+            return;
+        };
+        self.row_order = (0..data.len()).collect();
+
+        let Some(sort) = self.sort else {
+            return;
+        };
+        let Some(field) = self.table.schema.fields.get(sort.column) else {
+            return;
+        };
+
+        // A stable sort keeps the original order among equal cells
+        self.row_order.sort_by(|a, b| {
+            let ordering = compare_cells(data[*a].get(&field.name), data[*b].get(&field.name));
+            let is_empty =
+                |ix: &usize| matches!(data[*ix].get(&field.name), None | Some(Value::Null));
+            if sort.ascending || is_empty(a) || is_empty(b) {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+    }
+
+    fn render_header(&self, cx: &mut ViewContext<Self>) -> AnyElement {
+        let theme = cx.theme();
+        let line_height = cx.line_height();
+
+        let cells = self
+            .table
+            .schema
+            .fields
+            .iter()
+            .zip(self.widths.iter())
+            .enumerate()
+            .map(|(column, (field, width))| {
+                let sort_icon = match self.sort {
+                    Some(sort) if sort.column == column && sort.ascending => {
+                        Some(IconName::ArrowUp)
+                    }
+                    Some(sort) if sort.column == column => Some(IconName::ArrowDown),
+                    _ => None,
+                };
+
+                h_flex()
+                    .id(("table-heading", column))
+                    .justify_between()
+                    .min_w(*width + px(22.))
+                    .w(*width + px(22.))
+                    .px_2()
+                    .py((TABLE_Y_PADDING_MULTIPLE / 2.0) * line_height)
+                    .border_1()
+                    .border_color(theme.colors().border)
+                    .bg(theme.colors().border_focused)
+                    .cursor_pointer()
+                    .child(field.name.clone())
+                    .children(sort_icon.map(|icon| Icon::new(icon).size(IconSize::XSmall)))
+                    .on_click(cx.listener(move |this, _, cx| this.toggle_sort(column, cx)))
+            })
+            .collect::<Vec<_>>();
+
+        h_flex()
+            .w(self.total_width())
+            .children(cells)
+            .into_any_element()
+    }
+
+    fn render_pagination(&self, cx: &mut ViewContext<Self>) -> Option<AnyElement> {
+        if self.page_count() <= 1 {
+            return None;
+        }
+
+        let first_row = self.page * ROWS_PER_PAGE;
+        let last_row = (first_row + ROWS_PER_PAGE).min(self.row_count());
+
+        Some(
+            h_flex()
+                .gap_1()
+                .pt_1()
+                .child(
+                    IconButton::new("table-previous-page", IconName::ChevronLeft)
+                        .icon_size(IconSize::Small)
+                        .disabled(self.page == 0)
+                        .on_click(cx.listener(|this, _, cx| {
+                            this.set_page(this.page.saturating_sub(1), cx)
+                        })),
+                )
+                .child(
+                    Label::new(format!(
+                        "Rows {}–{} of {}",
+                        first_row + 1,
+                        last_row,
+                        self.row_count()
+                    ))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+                )
+                .child(
+                    IconButton::new("table-next-page", IconName::ChevronRight)
+                        .icon_size(IconSize::Small)
+                        .disabled(self.page + 1 >= self.page_count())
+                        .on_click(cx.listener(|this, _, cx| this.set_page(this.page + 1, cx))),
+                )
+                .into_any_element(),
+        )
+    }
+
+    fn total_width(&self) -> Pixels {
+        let mut total_width = px(0.);
+        for width in self.widths.iter() {
+            // Width fudge factor: border + 2 (heading), padding
+            total_width += *width + px(22.);
+        }
+        total_width
+    }
+
     fn escape_markdown(s: &str) -> String {
         s.replace('|', "\\|")
             .replace('*', "\\*")
@@ -194,6 +410,25 @@ impl TableView {
         markdown
     }
 
+    /// The table as tab-separated values, in the order the rows are displayed.
+    fn to_tsv(&self) -> String {
+        let fields = &self.table.schema.fields;
+        let data = self.table.data.as_deref().unwrap_or_default();
+
+        let header = fields.iter().map(|field| field.name.clone()).collect();
+        let rows = self.row_order.iter().filter_map(|&ix| {
+            let row = data.get(ix)?;
+            Some(
+                fields
+                    .iter()
+                    .map(|field| cell_content(row, &field.name))
+                    .collect(),
+            )
+        });
+
+        rows_to_tsv(std::iter::once(header).chain(rows))
+    }
+
     pub fn render_row(
         &self,
         schema: &TableSchema,
@@ -244,14 +479,8 @@ impl TableView {
             })
             .collect::<Vec<_>>();
 
-        let mut total_width = px(0.);
-        for width in self.widths.iter() {
-            // Width fudge factor: border + 2 (heading), padding
-            total_width += *width + px(22.);
-        }
-
         h_flex()
-            .w(total_width)
+            .w(self.total_width())
             .children(row_cells)
             .into_any_element()
     }
@@ -264,22 +493,28 @@ impl Render for TableView {
             None => return div().into_any_element(),
         };
 
-        let mut headings = serde_json::Map::new();
-        for field in &self.table.schema.fields {
-            headings.insert(field.name.clone(), Value::String(field.name.clone()));
-        }
-        let header = self.render_row(&self.table.schema, true, &Value::Object(headings), cx);
+        let header = self.render_header(cx);
 
-        let body = data
+        // Only the current page is laid out, however many rows the table has
+        let body = self
+            .row_order
             .iter()
-            .map(|row| self.render_row(&self.table.schema, false, row, cx));
+            .skip(self.page * ROWS_PER_PAGE)
+            .take(ROWS_PER_PAGE)
+            .map(|ix| self.render_row(&self.table.schema, false, &data[*ix], cx))
+            .collect::<Vec<_>>();
 
         v_flex()
-            .id("table")
-            .overflow_x_scroll()
             .w_full()
-            .child(header)
-            .children(body)
+            .child(
+                v_flex()
+                    .id("table")
+                    .overflow_x_scroll()
+                    .w_full()
+                    .child(header)
+                    .children(body),
+            )
+            .children(self.render_pagination(cx))
             .into_any_element()
     }
 }
@@ -292,4 +527,11 @@ impl OutputContent for TableView {
     fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
         true
     }
+
+    fn clipboard_alternatives(&self, _cx: &WindowContext) -> Vec<(SharedString, ClipboardItem)> {
+        vec![(
+            "Copy as TSV".into(),
+            ClipboardItem::new_string(self.to_tsv()),
+        )]
+    }
 }
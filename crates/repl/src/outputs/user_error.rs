@@ -1,5 +1,6 @@
+use collections::HashSet;
 use gpui::{AnyElement, FontWeight, View, WindowContext};
-use ui::{h_flex, prelude::*, v_flex, Label};
+use ui::{h_flex, prelude::*, v_flex, Disclosure, Label};
 
 use crate::outputs::plain::TerminalOutput;
 
@@ -7,10 +8,25 @@ use crate::outputs::plain::TerminalOutput;
 pub struct ErrorView {
     pub ename: String,
     pub evalue: String,
+    /// The whole traceback, used when copying the error or opening it in a buffer.
     pub traceback: View<TerminalOutput>,
+    frames: View<TracebackView>,
 }
 
 impl ErrorView {
+    pub fn new(ename: &str, evalue: &str, traceback: &[String], cx: &mut WindowContext) -> Self {
+        let ename = strip_ansi_codes(ename);
+        let evalue = strip_ansi_codes(evalue);
+        let frames = cx.new_view(|cx| TracebackView::new(&ename, &evalue, traceback, cx));
+
+        Self {
+            traceback: cx.new_view(|cx| TerminalOutput::from(&traceback.join("\n"), cx)),
+            ename,
+            evalue,
+            frames,
+        }
+    }
+
     pub fn render(&self, cx: &mut WindowContext) -> Option<AnyElement> {
         let theme = cx.theme();
 
@@ -41,9 +57,143 @@ impl ErrorView {
                         .py(padding)
                         .border_l_1()
                         .border_color(theme.status().error_border)
-                        .child(self.traceback.clone()),
+                        .child(self.frames.clone()),
                 )
                 .into_any_element(),
         )
     }
 }
+
+/// One entry of a traceback, which for IPython kernels is one stack frame.
+struct TracebackFrame {
+    /// The first line of the frame, e.g. `Cell In[3], line 2`, shown while the frame is collapsed.
+    summary: SharedString,
+    /// The rest of the frame, with its ANSI colors interpreted.
+    body: Option<View<TerminalOutput>>,
+}
+
+/// Shows a traceback as a list of frames that can be expanded and collapsed individually.
+/// Only the innermost frame, where the error was raised, starts out expanded.
+pub struct TracebackView {
+    frames: Vec<TracebackFrame>,
+    expanded_frames: HashSet<usize>,
+}
+
+impl TracebackView {
+    fn new(ename: &str, evalue: &str, traceback: &[String], cx: &mut WindowContext) -> Self {
+        let error_line = format!("{ename}: {evalue}");
+
+        let frames = traceback
+            .iter()
+            .filter(|entry| strip_ansi_codes(entry).trim() != error_line)
+            .filter_map(|entry| {
+                let mut lines = entry.lines();
+                // IPython separates the traceback from the cell output with a line of dashes
+                let summary = lines.by_ref().map(strip_ansi_codes).find(|line| {
+                    let line = line.trim();
+                    !line.is_empty() && !line.chars().all(|ch| ch == '-')
+                })?;
+                let body = lines.collect::<Vec<_>>().join("\n");
+                let body = (!body.trim().is_empty())
+                    .then(|| cx.new_view(|cx| TerminalOutput::from(&body, cx)));
+
+                Some(TracebackFrame {
+                    summary: summary.trim().to_string().into(),
+                    body,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let expanded_frames = frames
+            .iter()
+            .rposition(|frame| frame.body.is_some())
+            .into_iter()
+            .collect();
+
+        Self {
+            frames,
+            expanded_frames,
+        }
+    }
+
+    fn toggle_frame(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        if !self.expanded_frames.remove(&index) {
+            self.expanded_frames.insert(index);
+        }
+        cx.notify();
+    }
+}
+
+impl Render for TracebackView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .gap_1()
+            .font_buffer(cx)
+            .children(self.frames.iter().enumerate().map(|(index, frame)| {
+                let is_expanded = self.expanded_frames.contains(&index);
+
+                v_flex()
+                    .child(
+                        h_flex()
+                            .gap_1()
+                            .map(|this| match &frame.body {
+                                Some(_) => this.child(
+                                    Disclosure::new(("traceback-frame", index), is_expanded)
+                                        .on_click(cx.listener(move |this, _, cx| {
+                                            this.toggle_frame(index, cx)
+                                        })),
+                                ),
+                                // Keep summaries aligned with the ones that can be expanded
+                                None => this.child(div().w_5()),
+                            })
+                            .child(
+                                Label::new(frame.summary.clone())
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted)
+                                    .single_line(),
+                            ),
+                    )
+                    .when_some(frame.body.clone().filter(|_| is_expanded), |this, body| {
+                        this.child(div().pl_6().child(body))
+                    })
+            }))
+    }
+}
+
+/// Removes ANSI escape sequences, such as the colors IPython adds to tracebacks, from `text`.
+pub fn strip_ansi_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            stripped.push(ch);
+            continue;
+        }
+
+        // Control sequences are `ESC [`, then parameters, ending with a byte in `@`..=`~`
+        if chars.next_if_eq(&'[').is_some() {
+            for ch in chars.by_ref() {
+                if ('@'..='~').contains(&ch) {
+                    break;
+                }
+            }
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        assert_eq!(
+            strip_ansi_codes("\x1b[0;31mZeroDivisionError\x1b[0m: division by zero"),
+            "ZeroDivisionError: division by zero"
+        );
+        assert_eq!(strip_ansi_codes("no escapes"), "no escapes");
+    }
+}
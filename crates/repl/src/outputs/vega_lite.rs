@@ -0,0 +1,1452 @@
+//! # Vega-Lite Output
+//!
+//! Renders the `application/vnd.vegalite.v4+json` and `v5+json` charts that Altair emits natively,
+//! without a webview.
+//!
+//! A useful subset of Vega-Lite is supported: bar, line, point and area marks, with quantitative,
+//! temporal and categorical fields on the `x`, `y` and `color` channels, simple aggregates, layers,
+//! concatenation, and tooltips when hovering over a mark. Specs outside of the subset are reported
+//! instead of being drawn incorrectly.
+
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use gpui::{
+    canvas, fill, point, px, size, AnyElement, Bounds, ClipboardItem, Hsla, Model, MouseMoveEvent,
+    Path, Pixels, Point, Size, WindowContext,
+};
+use language::Buffer;
+use serde_json::{Map, Value};
+use ui::{h_flex, prelude::*, v_flex};
+
+use crate::outputs::{html::parse_css_color, OutputContent};
+
+/// The default size of a continuous axis, and of each step of a discrete one, like Vega-Lite's.
+const CONTINUOUS_SIZE: f32 = 300.;
+const DISCRETE_STEP: f32 = 20.;
+const Y_AXIS_WIDTH: f32 = 48.;
+const X_AXIS_HEIGHT: f32 = 32.;
+const POINT_RADIUS: f32 = 3.5;
+const HOVER_DISTANCE: f32 = 8.;
+const TICK_COUNT: usize = 5;
+
+/// Vega's `tableau10` scheme, which it uses for categorical colors.
+const CATEGORY_COLORS: [u32; 10] = [
+    0x4c78a8, 0xf58518, 0xe45756, 0x72b7b2, 0x54a24b, 0xeeca3b, 0xb279a2, 0xff9da6, 0x9d755d,
+    0xbab0ac,
+];
+
+pub struct VegaLiteView {
+    raw_spec: String,
+    charts: Result<Vec<Chart>>,
+    hovered_mark: Option<(usize, usize)>,
+    /// Where each chart was last painted, to relate the mouse position to the marks.
+    chart_origins: Vec<Rc<Cell<Point<Pixels>>>>,
+}
+
+impl VegaLiteView {
+    pub fn from(spec: &Value) -> Self {
+        let charts = parse_charts(spec, &Map::new());
+        let chart_count = charts.as_ref().map_or(0, Vec::len);
+
+        Self {
+            raw_spec: serde_json::to_string_pretty(spec).unwrap_or_default(),
+            charts,
+            hovered_mark: None,
+            chart_origins: (0..chart_count)
+                .map(|_| Rc::new(Cell::new(Point::default())))
+                .collect(),
+        }
+    }
+
+    fn update_hovered_mark(&mut self, position: Point<Pixels>, cx: &mut ViewContext<Self>) {
+        let Ok(charts) = self.charts.as_ref() else {
+            return;
+        };
+
+        let hovered_mark = charts.iter().enumerate().find_map(|(chart_ix, chart)| {
+            let local = position - self.chart_origins[chart_ix].get();
+            chart
+                .layout()
+                .hit_test(local)
+                .map(|mark_ix| (chart_ix, mark_ix))
+        });
+
+        if hovered_mark != self.hovered_mark {
+            self.hovered_mark = hovered_mark;
+            cx.notify();
+        }
+    }
+
+    fn render_chart(&self, chart_ix: usize, chart: &Chart, cx: &ViewContext<Self>) -> AnyElement {
+        let layout = chart.layout();
+        let colors = cx.theme().colors();
+        let text_color = colors.text_muted;
+        let grid_color = colors.border_variant;
+        let origin = self.chart_origins[chart_ix].clone();
+
+        let marks = layout.marks.clone();
+        let plot = layout.plot;
+        let x_ticks = layout.x_ticks.clone();
+        let y_ticks = layout.y_ticks.clone();
+
+        let hovered_mark = self
+            .hovered_mark
+            .filter(|(hovered_chart, _)| *hovered_chart == chart_ix)
+            .and_then(|(_, mark_ix)| layout.marks.get(mark_ix));
+
+        v_flex()
+            .gap_1()
+            .when_some(chart.title.clone(), |this, title| {
+                this.child(Label::new(title).weight(gpui::FontWeight::BOLD))
+            })
+            .when_some(chart.y_title.clone(), |this, title| {
+                this.child(Label::new(title).size(LabelSize::XSmall))
+            })
+            .child(
+                div()
+                    .relative()
+                    .flex_none()
+                    .w(layout.size.width)
+                    .h(layout.size.height)
+                    .child(
+                        canvas(
+                            move |bounds, _| origin.set(bounds.origin),
+                            move |bounds, _, cx| {
+                                paint_chart(
+                                    bounds.origin,
+                                    plot,
+                                    &x_ticks,
+                                    &y_ticks,
+                                    &marks,
+                                    grid_color,
+                                    cx,
+                                )
+                            },
+                        )
+                        .size_full(),
+                    )
+                    .children(layout.y_ticks.iter().map(|tick| {
+                        div()
+                            .absolute()
+                            .left_0()
+                            .w(px(Y_AXIS_WIDTH - 6.))
+                            .top(plot.origin.y + tick.position - px(8.))
+                            .flex()
+                            .justify_end()
+                            .child(
+                                Label::new(tick.label.clone())
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted),
+                            )
+                    }))
+                    .children(layout.x_ticks.iter().map(|tick| {
+                        div()
+                            .absolute()
+                            .top(plot.origin.y + plot.size.height + px(2.))
+                            .left(plot.origin.x + tick.position - px(40.))
+                            .w(px(80.))
+                            .flex()
+                            .justify_center()
+                            .child(
+                                Label::new(tick.label.clone())
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted)
+                                    .single_line(),
+                            )
+                    }))
+                    .when_some(chart.x_title.clone(), |this, title| {
+                        this.child(
+                            div()
+                                .absolute()
+                                .bottom_0()
+                                .left(plot.origin.x)
+                                .w(plot.size.width)
+                                .flex()
+                                .justify_center()
+                                .child(Label::new(title).size(LabelSize::XSmall)),
+                        )
+                    })
+                    .when_some(hovered_mark, |this, mark| {
+                        this.child(render_tooltip(mark, plot, cx))
+                    }),
+            )
+            .when(!chart.legend.is_empty(), |this| {
+                this.child(
+                    h_flex()
+                        .flex_wrap()
+                        .gap_3()
+                        .children(chart.legend.iter().map(|(label, color)| {
+                            h_flex()
+                                .gap_1()
+                                .child(div().size_2().rounded_full().bg(*color))
+                                .child(
+                                    Label::new(label.clone())
+                                        .size(LabelSize::XSmall)
+                                        .color(Color::Muted),
+                                )
+                        })),
+                )
+            })
+            .text_color(text_color)
+            .into_any_element()
+    }
+}
+
+impl OutputContent for VegaLiteView {
+    fn clipboard_content(&self, _cx: &WindowContext) -> Option<ClipboardItem> {
+        Some(ClipboardItem::new_string(self.raw_spec.clone()))
+    }
+
+    fn has_clipboard_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn has_buffer_content(&self, _cx: &WindowContext) -> bool {
+        true
+    }
+
+    fn buffer_content(&mut self, cx: &mut WindowContext) -> Option<Model<Buffer>> {
+        let buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::local(self.raw_spec.clone(), cx)
+                .with_language(language::PLAIN_TEXT.clone(), cx);
+            buffer.set_capability(language::Capability::ReadOnly, cx);
+            buffer
+        });
+        Some(buffer)
+    }
+}
+
+impl Render for VegaLiteView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let charts = match self.charts.as_ref() {
+            Ok(charts) => charts,
+            Err(error) => {
+                return div()
+                    .child(
+                        Label::new(format!("Unable to display chart: {error}")).color(Color::Muted),
+                    )
+                    .into_any_element()
+            }
+        };
+
+        v_flex()
+            .id("vega-lite-chart")
+            .gap_4()
+            .overflow_x_scroll()
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, cx| {
+                this.update_hovered_mark(event.position, cx)
+            }))
+            .on_hover(cx.listener(|this, hovered: &bool, cx| {
+                if !hovered && this.hovered_mark.take().is_some() {
+                    cx.notify();
+                }
+            }))
+            .children(
+                charts
+                    .iter()
+                    .enumerate()
+                    .map(|(chart_ix, chart)| self.render_chart(chart_ix, chart, cx)),
+            )
+            .into_any_element()
+    }
+}
+
+fn render_tooltip(
+    mark: &MarkGeometry,
+    plot: Bounds<Pixels>,
+    cx: &ViewContext<VegaLiteView>,
+) -> impl IntoElement {
+    let anchor = mark.anchor();
+
+    v_flex()
+        .absolute()
+        .left(plot.origin.x + anchor.x + px(8.))
+        .top(plot.origin.y + anchor.y + px(8.))
+        .px_2()
+        .py_1()
+        .rounded_md()
+        .border_1()
+        .border_color(cx.theme().colors().border)
+        .bg(cx.theme().colors().elevated_surface_background)
+        .children(mark.tooltip.iter().map(|(name, value)| {
+            h_flex()
+                .gap_2()
+                .child(
+                    Label::new(name.clone())
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+                .child(Label::new(value.clone()).size(LabelSize::XSmall))
+        }))
+}
+
+fn paint_chart(
+    origin: Point<Pixels>,
+    plot: Bounds<Pixels>,
+    x_ticks: &[Tick],
+    y_ticks: &[Tick],
+    marks: &[MarkGeometry],
+    grid_color: Hsla,
+    cx: &mut WindowContext,
+) {
+    let plot = Bounds::new(origin + plot.origin, plot.size);
+
+    for tick in y_ticks {
+        cx.paint_quad(fill(
+            Bounds::new(
+                point(plot.origin.x, plot.origin.y + tick.position),
+                size(plot.size.width, px(1.)),
+            ),
+            grid_color,
+        ));
+    }
+    for tick in x_ticks {
+        cx.paint_quad(fill(
+            Bounds::new(
+                point(
+                    plot.origin.x + tick.position,
+                    plot.origin.y + plot.size.height,
+                ),
+                size(px(1.), px(4.)),
+            ),
+            grid_color,
+        ));
+    }
+    cx.paint_quad(fill(
+        Bounds::new(
+            point(plot.origin.x, plot.origin.y + plot.size.height),
+            size(plot.size.width, px(1.)),
+        ),
+        grid_color,
+    ));
+
+    for mark in marks {
+        match &mark.shape {
+            MarkShape::Rect(bounds) => {
+                cx.paint_quad(fill(
+                    Bounds::new(plot.origin + bounds.origin, bounds.size),
+                    mark.color,
+                ));
+            }
+            MarkShape::Point(center) => {
+                let radius = px(POINT_RADIUS);
+                cx.paint_quad(
+                    fill(
+                        Bounds::new(
+                            plot.origin + *center - point(radius, radius),
+                            size(radius * 2., radius * 2.),
+                        ),
+                        mark.color,
+                    )
+                    .corner_radii(radius),
+                );
+            }
+            MarkShape::Segment { from, to, baseline } => match baseline {
+                Some(baseline) => paint_polygon(
+                    &[
+                        plot.origin + *from,
+                        plot.origin + *to,
+                        plot.origin + point(to.x, *baseline),
+                        plot.origin + point(from.x, *baseline),
+                    ],
+                    mark.color.opacity(0.7),
+                    cx,
+                ),
+                None => paint_line(plot.origin + *from, plot.origin + *to, mark.color, cx),
+            },
+        }
+    }
+}
+
+fn paint_line(from: Point<Pixels>, to: Point<Pixels>, color: Hsla, cx: &mut WindowContext) {
+    let delta = to - from;
+    let length = (delta.x.0 * delta.x.0 + delta.y.0 * delta.y.0).sqrt();
+    if length == 0. {
+        return;
+    }
+
+    // Offset both ends perpendicular to the segment to give the line its width
+    let half_width = 1.;
+    let offset = point(
+        px(-delta.y.0 / length * half_width),
+        px(delta.x.0 / length * half_width),
+    );
+    paint_polygon(
+        &[from + offset, to + offset, to - offset, from - offset],
+        color,
+        cx,
+    );
+}
+
+fn paint_polygon(points: &[Point<Pixels>], color: Hsla, cx: &mut WindowContext) {
+    let Some((first, rest)) = points.split_first() else {
+        return;
+    };
+    let mut path = Path::new(*first);
+    for point in rest {
+        path.line_to(*point);
+    }
+    path.line_to(*first);
+    cx.paint_path(path, color);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MarkType {
+    Bar,
+    Line,
+    Point,
+    Area,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FieldType {
+    Quantitative,
+    Temporal,
+    Nominal,
+    Ordinal,
+}
+
+impl FieldType {
+    fn is_discrete(&self) -> bool {
+        matches!(self, FieldType::Nominal | FieldType::Ordinal)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Aggregate {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Channel {
+    field: Option<String>,
+    field_type: FieldType,
+    aggregate: Option<Aggregate>,
+    title: String,
+}
+
+impl Channel {
+    fn parse(definition: &Value) -> Result<Option<Self>> {
+        let Some(definition) = definition.as_object() else {
+            return Ok(None);
+        };
+        if definition.contains_key("value") {
+            return Ok(None);
+        }
+
+        let field = definition
+            .get("field")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let aggregate = match definition.get("aggregate").and_then(Value::as_str) {
+            None => None,
+            Some("count") => Some(Aggregate::Count),
+            Some("sum") => Some(Aggregate::Sum),
+            Some("mean") | Some("average") => Some(Aggregate::Mean),
+            Some("min") => Some(Aggregate::Min),
+            Some("max") => Some(Aggregate::Max),
+            Some(aggregate) => return Err(anyhow!("the `{aggregate}` aggregate isn't supported")),
+        };
+        if field.is_none() && aggregate != Some(Aggregate::Count) {
+            return Ok(None);
+        }
+        if definition.contains_key("bin") {
+            return Err(anyhow!("binned fields aren't supported"));
+        }
+
+        let field_type = match definition.get("type").and_then(Value::as_str) {
+            Some("quantitative") => FieldType::Quantitative,
+            Some("temporal") => FieldType::Temporal,
+            Some("ordinal") => FieldType::Ordinal,
+            Some("nominal") => FieldType::Nominal,
+            _ if aggregate.is_some() => FieldType::Quantitative,
+            _ => FieldType::Nominal,
+        };
+
+        let title = match definition.get("title").and_then(Value::as_str) {
+            Some(title) => title.to_string(),
+            None => match (&field, aggregate) {
+                (_, Some(Aggregate::Count)) => "Count of Records".to_string(),
+                (Some(field), Some(Aggregate::Mean)) => format!("Average of {field}"),
+                (Some(field), Some(aggregate)) => format!("{aggregate:?} of {field}"),
+                (Some(field), None) => field.clone(),
+                (None, None) => String::new(),
+            },
+        };
+
+        Ok(Some(Self {
+            field,
+            field_type,
+            aggregate,
+            title,
+        }))
+    }
+
+    fn value(&self, row: &Map<String, Value>) -> Option<DatumValue> {
+        let value = row.get(self.field.as_ref()?)?;
+        match self.field_type {
+            FieldType::Quantitative => number_value(value).map(DatumValue::Number),
+            FieldType::Temporal => temporal_value(value).map(DatumValue::Number),
+            FieldType::Nominal | FieldType::Ordinal => {
+                Some(DatumValue::Category(display_value(value)))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DatumValue {
+    Number(f64),
+    Category(String),
+}
+
+impl DatumValue {
+    fn format(&self, field_type: FieldType) -> String {
+        match self {
+            DatumValue::Number(value) if field_type == FieldType::Temporal => {
+                format_timestamp(*value, 0.)
+            }
+            DatumValue::Number(value) => format_number(*value),
+            DatumValue::Category(category) => category.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Datum {
+    x: DatumValue,
+    y: DatumValue,
+    color: Option<String>,
+    tooltip: Vec<(SharedString, SharedString)>,
+}
+
+#[derive(Debug)]
+struct Layer {
+    mark: MarkType,
+    color: Hsla,
+    x: Channel,
+    y: Channel,
+    color_channel: Option<Channel>,
+    data: Vec<Datum>,
+}
+
+#[derive(Debug)]
+struct Chart {
+    title: Option<SharedString>,
+    width: Option<f32>,
+    height: Option<f32>,
+    x_title: Option<SharedString>,
+    y_title: Option<SharedString>,
+    layers: Vec<Layer>,
+    legend: Vec<(SharedString, Hsla)>,
+}
+
+/// Parses a spec into the charts to show, one for each view of a concatenated spec.
+/// Properties like `data` and `encoding` are inherited from the enclosing spec.
+fn parse_charts(spec: &Value, inherited: &Map<String, Value>) -> Result<Vec<Chart>> {
+    let spec = spec.as_object().context("the chart spec isn't an object")?;
+    let properties = inherit(inherited, spec);
+
+    for key in ["hconcat", "vconcat", "concat"] {
+        if let Some(views) = spec.get(key).and_then(Value::as_array) {
+            let mut charts = Vec::new();
+            for view in views {
+                charts.extend(parse_charts(view, &properties)?);
+            }
+            return Ok(charts);
+        }
+    }
+    for key in ["facet", "repeat"] {
+        if spec.contains_key(key) {
+            return Err(anyhow!("`{key}` charts aren't supported"));
+        }
+    }
+
+    let layer_specs = match spec.get("layer").and_then(Value::as_array) {
+        Some(layers) => layers
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|layer| inherit(&properties, layer))
+            .collect(),
+        None => vec![properties.clone()],
+    };
+
+    let layers = layer_specs
+        .iter()
+        .map(|layer_spec| parse_layer(layer_spec, &properties))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Categories get their colors in sorted order, like Vega-Lite's default ascending sort
+    let legend = layers
+        .iter()
+        .flat_map(|layer| layer.data.iter().filter_map(|datum| datum.color.clone()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(ix, category)| {
+            let color: Hsla = gpui::rgb(CATEGORY_COLORS[ix % CATEGORY_COLORS.len()]).into();
+            (SharedString::from(category), color)
+        })
+        .collect::<Vec<_>>();
+
+    let first_layer = layers.first().context("the chart has no layers")?;
+    let title = match spec.get("title") {
+        Some(Value::String(title)) => Some(title.clone().into()),
+        Some(Value::Object(title)) => title
+            .get("text")
+            .and_then(Value::as_str)
+            .map(|title| title.to_string().into()),
+        _ => None,
+    };
+
+    Ok(vec![Chart {
+        title,
+        width: properties
+            .get("width")
+            .and_then(Value::as_f64)
+            .map(|width| width as f32),
+        height: properties
+            .get("height")
+            .and_then(Value::as_f64)
+            .map(|height| height as f32),
+        x_title: Some(first_layer.x.title.clone().into()).filter(|t: &SharedString| !t.is_empty()),
+        y_title: Some(first_layer.y.title.clone().into()).filter(|t: &SharedString| !t.is_empty()),
+        legend,
+        layers,
+    }])
+}
+
+/// Merges a view's properties over the ones it inherits, merging `encoding` channel by channel.
+fn inherit(inherited: &Map<String, Value>, spec: &Map<String, Value>) -> Map<String, Value> {
+    let mut properties = inherited.clone();
+    for key in ["layer", "hconcat", "vconcat", "concat", "title"] {
+        properties.remove(key);
+    }
+
+    for (key, value) in spec {
+        match (key.as_str(), properties.get_mut(key), value) {
+            ("encoding", Some(Value::Object(encoding)), Value::Object(overrides)) => {
+                encoding.extend(overrides.clone());
+            }
+            _ => {
+                properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    properties
+}
+
+fn parse_layer(spec: &Map<String, Value>, root: &Map<String, Value>) -> Result<Layer> {
+    let (mark_type, mark_properties) = match spec.get("mark") {
+        Some(Value::String(mark)) => (mark.as_str(), None),
+        Some(Value::Object(mark)) => (
+            mark.get("type").and_then(Value::as_str).unwrap_or_default(),
+            Some(mark),
+        ),
+        _ => return Err(anyhow!("the chart has no mark")),
+    };
+    let mark = match mark_type {
+        "bar" => MarkType::Bar,
+        "line" => MarkType::Line,
+        "point" | "circle" | "square" => MarkType::Point,
+        "area" => MarkType::Area,
+        mark => return Err(anyhow!("`{mark}` marks aren't supported")),
+    };
+
+    let encoding = spec
+        .get("encoding")
+        .and_then(Value::as_object)
+        .context("the chart has no encoding")?;
+    let channel = |name: &str| -> Result<Option<Channel>> {
+        encoding.get(name).map_or(Ok(None), Channel::parse)
+    };
+    let x = channel("x")?.context("the chart has no `x` field")?;
+    let y = channel("y")?.context("the chart has no `y` field")?;
+    let color_channel = channel("color")?;
+
+    let mark_color = mark_properties
+        .and_then(|mark| mark.get("color"))
+        .or_else(|| encoding.get("color").and_then(|color| color.get("value")))
+        .and_then(Value::as_str)
+        .and_then(parse_css_color)
+        .unwrap_or_else(|| gpui::rgb(CATEGORY_COLORS[0]).into());
+
+    let tooltip_channels = match encoding.get("tooltip") {
+        Some(Value::Array(channels)) => channels
+            .iter()
+            .filter_map(|channel| Channel::parse(channel).ok().flatten())
+            .collect(),
+        Some(channel) => Channel::parse(channel)?.into_iter().collect(),
+        None if mark_properties
+            .and_then(|mark| mark.get("tooltip"))
+            .map_or(false, |tooltip| tooltip != &Value::Bool(false)) =>
+        {
+            [Some(&x), Some(&y), color_channel.as_ref()]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let rows = resolve_data(spec, root)?;
+    let data = if x.aggregate.is_some() || y.aggregate.is_some() {
+        aggregate_data(&rows, &x, &y, color_channel.as_ref())
+    } else {
+        rows.iter()
+            .filter_map(|row| {
+                Some(Datum {
+                    x: x.value(row)?,
+                    y: y.value(row)?,
+                    color: color_channel
+                        .as_ref()
+                        .and_then(|color| color.value(row))
+                        .map(|color| color.format(FieldType::Nominal)),
+                    tooltip: tooltip_channels
+                        .iter()
+                        .filter_map(|channel| {
+                            let value = channel.value(row)?.format(channel.field_type);
+                            Some((channel.title.clone().into(), value.into()))
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    };
+
+    // Aggregated data doesn't have the original rows to show in tooltips, so show the channels
+    let data = if (x.aggregate.is_some() || y.aggregate.is_some()) && !tooltip_channels.is_empty() {
+        data.into_iter()
+            .map(|mut datum| {
+                datum.tooltip = [
+                    Some((x.title.clone().into(), datum.x.format(x.field_type).into())),
+                    Some((y.title.clone().into(), datum.y.format(y.field_type).into())),
+                    color_channel
+                        .as_ref()
+                        .zip(datum.color.clone())
+                        .map(|(channel, color)| (channel.title.clone().into(), color.into())),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                datum
+            })
+            .collect()
+    } else {
+        data
+    };
+
+    Ok(Layer {
+        mark,
+        color: mark_color,
+        x,
+        y,
+        color_channel,
+        data,
+    })
+}
+
+/// Finds the rows of a view, either inline `values` or a named dataset, which is how Altair embeds data.
+fn resolve_data(
+    spec: &Map<String, Value>,
+    root: &Map<String, Value>,
+) -> Result<Vec<Map<String, Value>>> {
+    let data = spec
+        .get("data")
+        .and_then(Value::as_object)
+        .context("the chart has no data")?;
+
+    let values = if let Some(values) = data.get("values") {
+        values
+    } else if let Some(name) = data.get("name").and_then(Value::as_str) {
+        spec.get("datasets")
+            .or_else(|| root.get("datasets"))
+            .and_then(|datasets| datasets.get(name))
+            .with_context(|| format!("the `{name}` dataset is missing"))?
+    } else if data.contains_key("url") {
+        return Err(anyhow!("data loaded from a URL isn't supported"));
+    } else {
+        return Err(anyhow!("the chart has no data"));
+    };
+
+    Ok(values
+        .as_array()
+        .context("the chart data isn't a list of rows")?
+        .iter()
+        .filter_map(|row| row.as_object().cloned())
+        .collect())
+}
+
+fn aggregate_data(
+    rows: &[Map<String, Value>],
+    x: &Channel,
+    y: &Channel,
+    color: Option<&Channel>,
+) -> Vec<Datum> {
+    let (group_channel, measure_channel, measure_is_y) = if y.aggregate.is_some() {
+        (x, y, true)
+    } else {
+        (y, x, false)
+    };
+    let aggregate = measure_channel.aggregate.unwrap_or(Aggregate::Count);
+
+    let mut groups: Vec<((DatumValue, Option<String>), Vec<f64>)> = Vec::new();
+    for row in rows {
+        let Some(group) = group_channel.value(row) else {
+            continue;
+        };
+        let color = color
+            .and_then(|color| color.value(row))
+            .map(|color| color.format(FieldType::Nominal));
+        let measure = match aggregate {
+            Aggregate::Count => 1.,
+            _ => match measure_channel
+                .field
+                .as_ref()
+                .and_then(|field| row.get(field))
+                .and_then(number_value)
+            {
+                Some(measure) => measure,
+                None => continue,
+            },
+        };
+
+        let key = (group, color);
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, values)) => values.push(measure),
+            None => groups.push((key, vec![measure])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((group, color), values)| {
+            let value = match aggregate {
+                Aggregate::Count => values.len() as f64,
+                Aggregate::Sum => values.iter().sum(),
+                Aggregate::Mean => values.iter().sum::<f64>() / values.len() as f64,
+                Aggregate::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+                Aggregate::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            };
+            let value = DatumValue::Number(value);
+            let (x, y) = if measure_is_y {
+                (group, value)
+            } else {
+                (value, group)
+            };
+            Datum {
+                x,
+                y,
+                color,
+                tooltip: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn number_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.parse().ok(),
+        Value::Bool(value) => Some(*value as u8 as f64),
+        _ => None,
+    }
+}
+
+/// Parses a date into milliseconds since the epoch, which is how Vega represents them.
+fn temporal_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => {
+            if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+                return Some(date.timestamp_millis() as f64);
+            }
+            for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+                if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+                    return Some(date.and_utc().timestamp_millis() as f64);
+                }
+            }
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| date.and_utc().timestamp_millis() as f64)
+        }
+        _ => None,
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "null".to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0. && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else if value.abs() >= 1e-3 {
+        let formatted = format!("{value:.3}");
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        format!("{value:e}")
+    }
+}
+
+/// Formats a timestamp with as much precision as the `span` of the axis it's on calls for.
+fn format_timestamp(millis: f64, span: f64) -> String {
+    const DAY: f64 = 24. * 60. * 60. * 1000.;
+
+    let Some(date) = DateTime::from_timestamp_millis(millis as i64) else {
+        return format_number(millis);
+    };
+    let format = if span > 2. * 365. * DAY {
+        "%Y"
+    } else if span > 60. * DAY {
+        "%b %Y"
+    } else if span > 2. * DAY || span == 0. {
+        "%b %d, %Y"
+    } else {
+        "%b %d %H:%M"
+    };
+    date.format(format).to_string()
+}
+
+#[derive(Clone, Debug)]
+struct Tick {
+    /// The offset of the tick from the left or top of the plot area.
+    position: Pixels,
+    label: SharedString,
+}
+
+#[derive(Clone, Debug)]
+enum MarkShape {
+    Rect(Bounds<Pixels>),
+    Point(Point<Pixels>),
+    /// A line segment, filled down to the `baseline` for area marks.
+    Segment {
+        from: Point<Pixels>,
+        to: Point<Pixels>,
+        baseline: Option<Pixels>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct MarkGeometry {
+    shape: MarkShape,
+    color: Hsla,
+    tooltip: Vec<(SharedString, SharedString)>,
+}
+
+impl MarkGeometry {
+    /// Where the mark's tooltip points to.
+    fn anchor(&self) -> Point<Pixels> {
+        match &self.shape {
+            MarkShape::Rect(bounds) => point(bounds.center().x, bounds.origin.y),
+            MarkShape::Point(center) => *center,
+            MarkShape::Segment { to, .. } => *to,
+        }
+    }
+}
+
+/// Where everything in a chart goes, relative to its top left corner.
+struct ChartLayout {
+    size: Size<Pixels>,
+    /// The area the marks are drawn in, within the axes.
+    plot: Bounds<Pixels>,
+    x_ticks: Vec<Tick>,
+    y_ticks: Vec<Tick>,
+    marks: Vec<MarkGeometry>,
+}
+
+impl ChartLayout {
+    /// Finds the mark under `position`, relative to the chart, preferring the closest point.
+    fn hit_test(&self, position: Point<Pixels>) -> Option<usize> {
+        let position = position - self.plot.origin;
+        self.marks
+            .iter()
+            .enumerate()
+            .filter(|(_, mark)| !mark.tooltip.is_empty())
+            .filter_map(|(ix, mark)| {
+                let distance = match &mark.shape {
+                    MarkShape::Rect(bounds) => {
+                        if bounds.contains(&position) {
+                            0.
+                        } else {
+                            return None;
+                        }
+                    }
+                    MarkShape::Point(center) | MarkShape::Segment { to: center, .. } => {
+                        let delta = position - *center;
+                        (delta.x.0 * delta.x.0 + delta.y.0 * delta.y.0).sqrt()
+                    }
+                };
+                (distance <= HOVER_DISTANCE).then_some((ix, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(ix, _)| ix)
+    }
+}
+
+/// Maps data values to positions along one axis.
+enum Scale {
+    Linear {
+        domain: (f64, f64),
+        range: f32,
+        temporal: bool,
+    },
+    Band {
+        categories: Vec<String>,
+        range: f32,
+    },
+}
+
+impl Scale {
+    fn new(channel: &Channel, values: &[&DatumValue], include_zero: bool, range: f32) -> Self {
+        if channel.field_type.is_discrete() {
+            let mut categories = Vec::<String>::new();
+            for value in values {
+                if let DatumValue::Category(category) = value {
+                    if !categories.contains(category) {
+                        categories.push(category.clone());
+                    }
+                }
+            }
+            if channel.field_type == FieldType::Nominal {
+                categories.sort();
+            }
+            return Scale::Band { categories, range };
+        }
+
+        let numbers = values.iter().filter_map(|value| match value {
+            DatumValue::Number(number) => Some(*number),
+            DatumValue::Category(_) => None,
+        });
+        let (mut min, mut max) = numbers
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), n| {
+                (min.min(n), max.max(n))
+            });
+        if min > max {
+            (min, max) = (0., 1.);
+        }
+        let temporal = channel.field_type == FieldType::Temporal;
+        if include_zero && !temporal {
+            min = min.min(0.);
+            max = max.max(0.);
+        }
+        if min == max {
+            min -= 1.;
+            max += 1.;
+        }
+        if !temporal {
+            let step = nice_step(max - min);
+            min = (min / step).floor() * step;
+            max = (max / step).ceil() * step;
+        }
+
+        Scale::Linear {
+            domain: (min, max),
+            range,
+            temporal,
+        }
+    }
+
+    fn position(&self, value: &DatumValue) -> Option<f32> {
+        match (self, value) {
+            (Scale::Linear { domain, range, .. }, DatumValue::Number(value)) => {
+                Some(((value - domain.0) / (domain.1 - domain.0)) as f32 * range)
+            }
+            (Scale::Band { categories, .. }, DatumValue::Category(category)) => {
+                let ix = categories.iter().position(|c| c == category)?;
+                Some((ix as f32 + 0.5) * self.bandwidth())
+            }
+            _ => None,
+        }
+    }
+
+    fn bandwidth(&self) -> f32 {
+        match self {
+            Scale::Linear { .. } => 0.,
+            Scale::Band { categories, range } => range / categories.len().max(1) as f32,
+        }
+    }
+
+    fn ticks(&self) -> Vec<(f32, String)> {
+        match self {
+            Scale::Linear {
+                domain,
+                range,
+                temporal,
+            } => {
+                let span = domain.1 - domain.0;
+                let step = nice_step(span);
+                let first = (domain.0 / step).ceil() * step;
+                (0..)
+                    .map(|ix| first + ix as f64 * step)
+                    .take_while(|value| *value <= domain.1 + step * 1e-9)
+                    .map(|value| {
+                        let position = ((value - domain.0) / span) as f32 * range;
+                        let label = if *temporal {
+                            format_timestamp(value, span)
+                        } else {
+                            format_number(value)
+                        };
+                        (position, label)
+                    })
+                    .collect()
+            }
+            Scale::Band { categories, .. } => categories
+                .iter()
+                .map(|category| {
+                    let position = self
+                        .position(&DatumValue::Category(category.clone()))
+                        .unwrap_or_default();
+                    (position, category.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A round step that divides `span` into about `TICK_COUNT` intervals, like 1, 2 or 5 times a power of ten.
+fn nice_step(span: f64) -> f64 {
+    let raw_step = span / TICK_COUNT as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let normalized = raw_step / magnitude;
+    let nice = if normalized <= 1. {
+        1.
+    } else if normalized <= 2. {
+        2.
+    } else if normalized <= 5. {
+        5.
+    } else {
+        10.
+    };
+    nice * magnitude
+}
+
+impl Chart {
+    fn layout(&self) -> ChartLayout {
+        let first_layer = &self.layers[0];
+        let x_values = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.data.iter().map(|datum| &datum.x))
+            .collect::<Vec<_>>();
+        let y_values = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.data.iter().map(|datum| &datum.y))
+            .collect::<Vec<_>>();
+
+        let default_size = |channel: &Channel, values: &[&DatumValue]| {
+            if channel.field_type.is_discrete() {
+                let mut categories = Vec::new();
+                for value in values {
+                    if !categories.contains(value) {
+                        categories.push(*value);
+                    }
+                }
+                categories.len().max(3) as f32 * DISCRETE_STEP
+            } else {
+                CONTINUOUS_SIZE
+            }
+        };
+        let plot_width = self
+            .width
+            .unwrap_or_else(|| default_size(&first_layer.x, &x_values));
+        let plot_height = self
+            .height
+            .unwrap_or_else(|| default_size(&first_layer.y, &y_values));
+
+        // Bars and areas are measured from zero, so it has to be on the axis
+        let include_zero = self
+            .layers
+            .iter()
+            .any(|layer| matches!(layer.mark, MarkType::Bar | MarkType::Area));
+        let stacks = self.stack_totals();
+        let stacked_values = stacks
+            .iter()
+            .map(|(_, total)| DatumValue::Number(*total))
+            .collect::<Vec<_>>();
+        let horizontal =
+            first_layer.y.field_type.is_discrete() && !first_layer.x.field_type.is_discrete();
+
+        let mut y_domain_values = y_values.clone();
+        let mut x_domain_values = x_values.clone();
+        if horizontal {
+            x_domain_values.extend(stacked_values.iter());
+        } else {
+            y_domain_values.extend(stacked_values.iter());
+        }
+
+        let x_scale = Scale::new(&first_layer.x, &x_domain_values, include_zero, plot_width);
+        let y_scale = Scale::new(&first_layer.y, &y_domain_values, include_zero, plot_height);
+        let x_position = |value: &DatumValue| x_scale.position(value).map(px);
+        let y_position = |value: &DatumValue| y_scale.position(value).map(|y| px(plot_height - y));
+
+        let mut marks = Vec::new();
+        for layer in &self.layers {
+            let datum_color = |datum: &Datum| {
+                datum
+                    .color
+                    .as_ref()
+                    .and_then(|category| {
+                        self.legend
+                            .iter()
+                            .find(|(label, _)| label.as_ref() == category)
+                    })
+                    .map_or(layer.color, |(_, color)| *color)
+            };
+
+            match layer.mark {
+                MarkType::Bar => {
+                    let mut stack_offsets = BTreeMap::<String, f64>::new();
+                    let thickness = |scale: &Scale| px((scale.bandwidth() * 0.9).max(2.));
+
+                    for datum in &layer.data {
+                        let (group, measure) = if horizontal {
+                            (&datum.y, &datum.x)
+                        } else {
+                            (&datum.x, &datum.y)
+                        };
+                        let DatumValue::Number(measure) = measure else {
+                            continue;
+                        };
+                        let offset = stack_offsets.entry(format!("{group:?}")).or_insert(0.);
+                        let (start, end) = (*offset, *offset + measure);
+                        if layer.color_channel.is_some() {
+                            *offset = end;
+                        }
+
+                        let bounds = if horizontal {
+                            let (Some(center), Some(left), Some(right)) = (
+                                y_position(group),
+                                x_position(&DatumValue::Number(start)),
+                                x_position(&DatumValue::Number(end)),
+                            ) else {
+                                continue;
+                            };
+                            let height = thickness(&y_scale);
+                            Bounds::from_corners(
+                                point(left.min(right), center - height / 2.),
+                                point(left.max(right), center + height / 2.),
+                            )
+                        } else {
+                            let (Some(center), Some(bottom), Some(top)) = (
+                                x_position(group),
+                                y_position(&DatumValue::Number(start)),
+                                y_position(&DatumValue::Number(end)),
+                            ) else {
+                                continue;
+                            };
+                            let width = thickness(&x_scale);
+                            Bounds::from_corners(
+                                point(center - width / 2., top.min(bottom)),
+                                point(center + width / 2., top.max(bottom)),
+                            )
+                        };
+
+                        marks.push(MarkGeometry {
+                            shape: MarkShape::Rect(bounds),
+                            color: datum_color(datum),
+                            tooltip: datum.tooltip.clone(),
+                        });
+                    }
+                }
+                MarkType::Point => {
+                    for datum in &layer.data {
+                        let (Some(x), Some(y)) = (x_position(&datum.x), y_position(&datum.y))
+                        else {
+                            continue;
+                        };
+                        marks.push(MarkGeometry {
+                            shape: MarkShape::Point(point(x, y)),
+                            color: datum_color(datum),
+                            tooltip: datum.tooltip.clone(),
+                        });
+                    }
+                }
+                MarkType::Line | MarkType::Area => {
+                    // Each color gets its own series, drawn in the order of the x values
+                    let mut series = BTreeMap::<Option<String>, Vec<&Datum>>::new();
+                    for datum in &layer.data {
+                        series.entry(datum.color.clone()).or_default().push(datum);
+                    }
+
+                    let baseline = (layer.mark == MarkType::Area)
+                        .then(|| y_position(&DatumValue::Number(0.)))
+                        .flatten()
+                        .map(|baseline| baseline.min(px(plot_height)));
+
+                    for (_, mut data) in series {
+                        data.sort_by(|a, b| {
+                            let a = x_scale.position(&a.x).unwrap_or_default();
+                            let b = x_scale.position(&b.x).unwrap_or_default();
+                            a.total_cmp(&b)
+                        });
+                        let points = data
+                            .iter()
+                            .filter_map(|datum| {
+                                Some((point(x_position(&datum.x)?, y_position(&datum.y)?), *datum))
+                            })
+                            .collect::<Vec<_>>();
+
+                        if let Some((first, datum)) = points.first() {
+                            marks.push(MarkGeometry {
+                                shape: MarkShape::Segment {
+                                    from: *first,
+                                    to: *first,
+                                    baseline,
+                                },
+                                color: datum_color(datum),
+                                tooltip: datum.tooltip.clone(),
+                            });
+                        }
+                        for window in points.windows(2) {
+                            let [(from, _), (to, datum)] = window else {
+                                continue;
+                            };
+                            marks.push(MarkGeometry {
+                                shape: MarkShape::Segment {
+                                    from: *from,
+                                    to: *to,
+                                    baseline,
+                                },
+                                color: datum_color(datum),
+                                tooltip: datum.tooltip.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let plot = Bounds::new(
+            point(px(Y_AXIS_WIDTH), px(4.)),
+            size(px(plot_width), px(plot_height)),
+        );
+        let to_ticks = |ticks: Vec<(f32, String)>, flip: bool| {
+            ticks
+                .into_iter()
+                .map(|(position, label)| Tick {
+                    position: px(if flip {
+                        plot_height - position
+                    } else {
+                        position
+                    }),
+                    label: label.into(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        ChartLayout {
+            size: size(
+                px(Y_AXIS_WIDTH + plot_width + 8.),
+                px(4. + plot_height + X_AXIS_HEIGHT),
+            ),
+            plot,
+            x_ticks: to_ticks(x_scale.ticks(), false),
+            y_ticks: to_ticks(y_scale.ticks(), true),
+            marks,
+        }
+    }
+
+    /// The totals of stacked bars, which the measure axis has to fit.
+    fn stack_totals(&self) -> Vec<(String, f64)> {
+        let mut totals = BTreeMap::<String, f64>::new();
+        for layer in &self.layers {
+            if layer.mark != MarkType::Bar || layer.color_channel.is_none() {
+                continue;
+            }
+            let horizontal = layer.y.field_type.is_discrete() && !layer.x.field_type.is_discrete();
+            for datum in &layer.data {
+                let (group, measure) = if horizontal {
+                    (&datum.y, &datum.x)
+                } else {
+                    (&datum.x, &datum.y)
+                };
+                if let DatumValue::Number(measure) = measure {
+                    *totals.entry(format!("{group:?}")).or_insert(0.) += measure;
+                }
+            }
+        }
+        totals.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_altair_bar_chart() {
+        let spec = json!({
+            "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+            "data": {"name": "data-1"},
+            "mark": {"type": "bar", "tooltip": true},
+            "encoding": {
+                "x": {"field": "a", "type": "nominal"},
+                "y": {"field": "b", "type": "quantitative"}
+            },
+            "datasets": {
+                "data-1": [{"a": "A", "b": 28}, {"a": "B", "b": 55}, {"a": "C", "b": 43}]
+            }
+        });
+
+        let charts = parse_charts(&spec, &Map::new()).unwrap();
+        assert_eq!(charts.len(), 1);
+        let layout = charts[0].layout();
+        assert_eq!(layout.marks.len(), 3);
+        assert_eq!(layout.x_ticks.len(), 3);
+        assert_eq!(layout.marks[1].tooltip[1].1.as_ref(), "55");
+
+        let MarkShape::Rect(bar) = &layout.marks[1].shape else {
+            panic!("expected a bar, got {:?}", layout.marks[1].shape);
+        };
+        assert_eq!(layout.hit_test(layout.plot.origin + bar.center()), Some(1));
+    }
+
+    #[test]
+    fn test_aggregate_count() {
+        let spec = json!({
+            "data": {"values": [{"a": "x"}, {"a": "y"}, {"a": "x"}]},
+            "mark": "bar",
+            "encoding": {
+                "x": {"field": "a", "type": "nominal"},
+                "y": {"aggregate": "count", "type": "quantitative"}
+            }
+        });
+
+        let charts = parse_charts(&spec, &Map::new()).unwrap();
+        let data = &charts[0].layers[0].data;
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].y, DatumValue::Number(2.));
+        assert_eq!(charts[0].y_title.as_deref(), Some("Count of Records"));
+    }
+
+    #[test]
+    fn test_unsupported_spec() {
+        let spec = json!({"data": {"url": "data.csv"}, "mark": "bar", "encoding": {
+            "x": {"field": "a", "type": "nominal"},
+            "y": {"field": "b", "type": "quantitative"}
+        }});
+        assert!(parse_charts(&spec, &Map::new()).is_err());
+    }
+}
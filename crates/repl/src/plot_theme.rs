@@ -0,0 +1,163 @@
+//! # Plot Theme
+//!
+//! matplotlib and plotly draw plots on a white background by default, which shows up as a bright
+//! rectangle in a dark theme. With `jupyter.theme_plots` turned on, Python kernels run a short
+//! setup snippet when they start that styles plots with the colors and font of Zed's theme, and
+//! run it again whenever the theme changes. Libraries that aren't installed are skipped, and the
+//! snippet runs silently, so it doesn't show up in the kernel's history or execution counts.
+
+use gpui::{AppContext, Hsla, Rgba};
+use runtimelib::{ExecuteRequest, JupyterMessage};
+use settings::Settings as _;
+use theme::{ActiveTheme as _, ThemeSettings};
+
+use crate::JupyterSettings;
+
+/// Styles plots with `THEME`, a dictionary of colors and the font family.
+const SETUP_CODE: &str = r#"def __zed_plot_theme(theme):
+    from importlib.util import find_spec
+
+    if find_spec("matplotlib") is not None:
+        try:
+            import matplotlib
+            # Importing pyplot sets up the inline backend, whose own defaults would otherwise win
+            import matplotlib.pyplot
+            from matplotlib import font_manager
+
+            matplotlib.rcParams.update({
+                "figure.facecolor": theme["background"],
+                "figure.edgecolor": theme["background"],
+                "savefig.facecolor": theme["background"],
+                "axes.facecolor": theme["background"],
+                "axes.edgecolor": theme["muted"],
+                "axes.labelcolor": theme["foreground"],
+                "axes.titlecolor": theme["foreground"],
+                "text.color": theme["foreground"],
+                "xtick.color": theme["muted"],
+                "ytick.color": theme["muted"],
+                "grid.color": theme["grid"],
+                "legend.facecolor": theme["background"],
+                "legend.edgecolor": theme["grid"],
+            })
+            if any(font.name == theme["font_family"] for font in font_manager.fontManager.ttflist):
+                fonts = [font for font in matplotlib.rcParams["font.sans-serif"] if font != theme["font_family"]]
+                matplotlib.rcParams["font.sans-serif"] = [theme["font_family"], *fonts]
+        except Exception:
+            pass
+
+    if find_spec("plotly") is not None:
+        try:
+            import plotly.graph_objects as go
+            import plotly.io as pio
+
+            template = go.layout.Template(pio.templates[theme["plotly_template"]])
+            template.layout.update(
+                paper_bgcolor=theme["background"],
+                plot_bgcolor=theme["background"],
+                font={"color": theme["foreground"], "family": theme["font_family"]},
+            )
+            pio.templates["zed"] = template
+            pio.templates.default = "zed"
+        except Exception:
+            pass
+
+__zed_plot_theme(THEME)
+del __zed_plot_theme
+"#;
+
+/// The colors and font plots are styled with.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PlotTheme {
+    pub dark: bool,
+    pub background: String,
+    pub foreground: String,
+    pub muted: String,
+    pub grid: String,
+    pub font_family: String,
+}
+
+impl PlotTheme {
+    /// The theme to style plots of a kernel for `language` with, if plots are themed for it.
+    pub(crate) fn for_kernel(language: &str, cx: &AppContext) -> Option<Self> {
+        if !JupyterSettings::get_global(cx).theme_plots || !language.eq_ignore_ascii_case("python")
+        {
+            return None;
+        }
+
+        let theme = cx.theme();
+        let colors = theme.colors();
+        Some(Self {
+            dark: !theme.appearance().is_light(),
+            background: hex_color(colors.editor_background),
+            foreground: hex_color(colors.text),
+            muted: hex_color(colors.text_muted),
+            grid: hex_color(colors.border_variant),
+            font_family: ThemeSettings::get_global(cx).ui_font.family.to_string(),
+        })
+    }
+
+    /// The Python code that styles plots with the theme.
+    pub(crate) fn setup_code(&self) -> String {
+        let plotly_template = if self.dark {
+            "plotly_dark"
+        } else {
+            "plotly_white"
+        };
+        // JSON strings are valid Python string literals
+        let theme = serde_json::json!({
+            "background": self.background,
+            "foreground": self.foreground,
+            "muted": self.muted,
+            "grid": self.grid,
+            "font_family": self.font_family,
+            "plotly_template": plotly_template,
+        });
+        SETUP_CODE.replace("THEME", &theme.to_string())
+    }
+
+    /// A request to run [`Self::setup_code`] without it showing up in the kernel's history.
+    pub(crate) fn setup_request(&self) -> JupyterMessage {
+        ExecuteRequest {
+            code: self.setup_code(),
+            silent: true,
+            store_history: false,
+            allow_stdin: false,
+            ..ExecuteRequest::default()
+        }
+        .into()
+    }
+}
+
+/// Formats a color as `#rrggbb`, ignoring its alpha, which plots don't draw well over.
+fn hex_color(color: Hsla) -> String {
+    let color = Rgba::from(color);
+    let channel = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_setup_code() {
+        let theme = PlotTheme {
+            dark: true,
+            background: hex_color(gpui::black()),
+            foreground: hex_color(gpui::white()),
+            muted: "#808080".to_string(),
+            grid: "#404040".to_string(),
+            font_family: "Zed \"Plex\" Sans".to_string(),
+        };
+        let code = theme.setup_code();
+        assert!(code.contains(r##"__zed_plot_theme({"background":"#000000","##));
+        assert!(code.contains(r#""font_family":"Zed \"Plex\" Sans""#));
+        assert!(code.contains(r#""plotly_template":"plotly_dark""#));
+        assert!(!code.contains("THEME"));
+    }
+}
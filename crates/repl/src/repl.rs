@@ -1,8 +1,13 @@
+mod cell_markers;
 pub mod components;
 mod jupyter_settings;
+mod kernel_sessions_panel;
 pub mod kernels;
+mod magics;
 pub mod notebook;
+mod notebook_settings;
 mod outputs;
+mod plot_theme;
 mod repl_editor;
 mod repl_sessions_ui;
 mod repl_store;
@@ -17,10 +22,13 @@ pub use runtimelib::ExecutionState;
 use settings::Settings as _;
 
 pub use crate::jupyter_settings::JupyterSettings;
+pub use crate::kernel_sessions_panel::{KernelSessionsPanel, ToggleKernelSessions};
 pub use crate::kernels::{Kernel, KernelSpecification, KernelStatus};
+pub use crate::notebook_settings::NotebookSettings;
 pub use crate::repl_editor::*;
 pub use crate::repl_sessions_ui::{
-    ClearOutputs, Interrupt, ReplSessionsPage, Restart, Run, Sessions, Shutdown,
+    AttachToNotebook, ClearOutputs, Interrupt, ReplSessionsPage, Restart, RestartKernel, Run,
+    RunCell, RunFile, RunSelection, Sessions, Shutdown, ShutdownKernel,
 };
 use crate::repl_store::ReplStore;
 pub use crate::session::Session;
@@ -31,8 +39,10 @@ pub const KERNEL_DOCS_URL: &str = "https://zed.dev/docs/repl#changing-kernels";
 pub fn init(fs: Arc<dyn Fs>, telemetry: Arc<Telemetry>, cx: &mut AppContext) {
     set_dispatcher(zed_dispatcher(cx));
     JupyterSettings::register(cx);
+    NotebookSettings::register(cx);
     ::editor::init_settings(cx);
     repl_sessions_ui::init(cx);
+    kernel_sessions_panel::init(cx);
     ReplStore::init(fs, telemetry, cx);
 }
 
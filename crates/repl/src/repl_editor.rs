@@ -4,15 +4,19 @@ use std::ops::Range;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use editor::Editor;
+use collections::HashMap;
+use editor::{Anchor, Editor, ToPoint as _};
 use gpui::{prelude::*, Entity, View, WeakView, WindowContext};
 use language::{BufferSnapshot, Language, LanguageName, Point};
 use project::{Item as _, WorktreeId};
+use workspace::Workspace;
 
+use crate::notebook::NotebookEditor;
 use crate::repl_store::ReplStore;
 use crate::session::SessionEvent;
 use crate::{
-    ClearOutputs, Interrupt, JupyterSettings, KernelSpecification, Restart, Session, Shutdown,
+    ClearOutputs, Interrupt, JupyterSettings, KernelSpecification, Restart, RestartKernel, Session,
+    Shutdown, ShutdownKernel,
 };
 
 pub fn assign_kernelspec(
@@ -46,7 +50,88 @@ pub fn assign_kernelspec(
 
     let session = cx
         .new_view(|cx| Session::new(weak_editor.clone(), fs, telemetry, kernel_specification, cx));
+    insert_session(weak_editor, session, cx);
 
+    Ok(())
+}
+
+/// Runs the editor's code on the kernel of an open notebook in the same language, so it can use
+/// the state the notebook's cells built up. The notebook that was active most recently is used.
+pub fn attach_to_notebook(weak_editor: WeakView<Editor>, cx: &mut WindowContext) -> Result<()> {
+    let store = ReplStore::global(cx);
+    if !store.read(cx).is_enabled() {
+        return Ok(());
+    }
+
+    let editor = weak_editor.upgrade().context("editor was dropped")?;
+    let language = get_language(weak_editor.clone(), cx).context("editor has no language")?;
+    let workspace = editor
+        .read(cx)
+        .workspace()
+        .context("editor is not in a workspace")?;
+    let notebook = recent_notebook(&workspace, &language.name(), cx)
+        .with_context(|| format!("No open {} notebook to attach to", language.name()))?;
+    let kernel_specification = notebook
+        .read(cx)
+        .kernel_specification(cx)
+        .context("No kernel found for the notebook")?;
+
+    if let Some(session) = store.read(cx).get_session(weak_editor.entity_id()).cloned() {
+        session.update(cx, |session, cx| {
+            session.clear_outputs(cx);
+            session.shutdown(cx);
+            cx.notify();
+        });
+    }
+
+    let fs = store.read(cx).fs().clone();
+    let telemetry = store.read(cx).telemetry().clone();
+    let session = cx.new_view(|cx| {
+        Session::attached(
+            weak_editor.clone(),
+            fs,
+            telemetry,
+            kernel_specification,
+            notebook,
+            cx,
+        )
+    });
+    insert_session(weak_editor, session, cx);
+
+    Ok(())
+}
+
+/// The open notebook for a language that was active most recently.
+fn recent_notebook(
+    workspace: &View<Workspace>,
+    language_name: &LanguageName,
+    cx: &WindowContext,
+) -> Option<View<NotebookEditor>> {
+    let workspace = workspace.read(cx);
+    let mut activations = HashMap::default();
+    for pane in workspace.panes() {
+        for entry in pane.read(cx).activation_history() {
+            let timestamp = activations
+                .entry(entry.entity_id)
+                .or_insert(entry.timestamp);
+            *timestamp = entry.timestamp.max(*timestamp);
+        }
+    }
+
+    workspace
+        .items_of_type::<NotebookEditor>(cx)
+        .filter(|notebook| {
+            notebook
+                .read(cx)
+                .language_name(cx)
+                .map_or(false, |name| name.eq_ignore_ascii_case(&language_name.0))
+        })
+        .max_by_key(|notebook| activations.get(&notebook.entity_id()).copied())
+}
+
+/// Remembers the editor's session, and forgets it once the session shuts down.
+fn insert_session(weak_editor: WeakView<Editor>, session: View<Session>, cx: &mut WindowContext) {
+    let store = ReplStore::global(cx);
     weak_editor
         .update(cx, |_editor, cx| {
             cx.notify();
@@ -55,8 +140,9 @@ pub fn assign_kernelspec(
                 let store = store.clone();
                 move |_this, _session, event, cx| match event {
                     SessionEvent::Shutdown(shutdown_event) => {
-                        store.update(cx, |store, _cx| {
+                        store.update(cx, |store, cx| {
                             store.remove_session(shutdown_event.entity_id());
+                            cx.notify();
                         });
                     }
                 }
@@ -65,23 +151,81 @@ pub fn assign_kernelspec(
         })
         .ok();
 
-    store.update(cx, |store, _cx| {
-        store.insert_session(weak_editor.entity_id(), session.clone());
+    store.update(cx, |store, cx| {
+        store.insert_session(weak_editor.entity_id(), session);
+        cx.notify();
     });
+}
 
-    Ok(())
+/// What to run around the newest selection of an editor.
+#[derive(Clone, Copy)]
+enum RunScope {
+    /// The Jupytext cell, markdown code block or lines the selection is in.
+    Snippet,
+    /// Exactly the selected text, or the line with the cursor when nothing is selected.
+    Selection,
+    /// The whole buffer, wherever the selection is.
+    File,
+    /// Only the Jupytext cell the selection starts in.
+    Cell,
 }
 
 pub fn run(editor: WeakView<Editor>, move_down: bool, cx: &mut WindowContext) -> Result<()> {
-    let store = ReplStore::global(cx);
-    if !store.read(cx).is_enabled() {
-        return Ok(());
-    }
+    run_scope(editor, RunScope::Snippet, move_down, cx)
+}
 
+/// Runs the selected text as it is, without extending it to whole lines or cells, and shows the
+/// result below the selection.
+pub fn run_selection(editor: WeakView<Editor>, cx: &mut WindowContext) -> Result<()> {
+    run_scope(editor, RunScope::Selection, false, cx)
+}
+
+/// Runs the whole buffer as a single execution, and shows the result below its last line.
+pub fn run_file(editor: WeakView<Editor>, cx: &mut WindowContext) -> Result<()> {
+    run_scope(editor, RunScope::File, false, cx)
+}
+
+/// Runs the Jupytext cell with the cursor, delimited by `# %%` markers.
+pub fn run_cell(editor: WeakView<Editor>, cx: &mut WindowContext) -> Result<()> {
+    run_scope(editor, RunScope::Cell, false, cx)
+}
+
+/// Runs the Jupytext cell `position` is in, wherever the cursor is.
+pub fn run_cell_at(
+    editor: WeakView<Editor>,
+    position: Anchor,
+    cx: &mut WindowContext,
+) -> Result<()> {
+    let editor = editor.upgrade().context("editor was dropped")?;
+    let point = position.to_point(&editor.read(cx).buffer().read(cx).snapshot(cx));
+    run_range(editor, RunScope::Cell, point..point, false, cx)
+}
+
+fn run_scope(
+    editor: WeakView<Editor>,
+    scope: RunScope,
+    move_down: bool,
+    cx: &mut WindowContext,
+) -> Result<()> {
     let editor = editor.upgrade().context("editor was dropped")?;
     let selected_range = editor
         .update(cx, |editor, cx| editor.selections.newest_adjusted(cx))
         .range();
+    run_range(editor, scope, selected_range, move_down, cx)
+}
+
+fn run_range(
+    editor: View<Editor>,
+    scope: RunScope,
+    selected_range: Range<Point>,
+    move_down: bool,
+    cx: &mut WindowContext,
+) -> Result<()> {
+    let store = ReplStore::global(cx);
+    if !store.read(cx).is_enabled() {
+        return Ok(());
+    }
+
     let multibuffer = editor.read(cx).buffer().clone();
     let Some(buffer) = multibuffer.read(cx).as_singleton() else {
         return Ok(());
@@ -91,8 +235,16 @@ pub fn run(editor: WeakView<Editor>, move_down: bool, cx: &mut WindowContext) ->
         return Ok(());
     };
 
-    let (runnable_ranges, next_cell_point) =
-        runnable_ranges(&buffer.read(cx).snapshot(), selected_range);
+    let snapshot = buffer.read(cx).snapshot();
+    let (runnable_ranges, next_cell_point) = match scope {
+        RunScope::Snippet => runnable_ranges(&snapshot, selected_range),
+        RunScope::Selection => (selection_ranges(&snapshot, selected_range), None),
+        RunScope::File => (file_ranges(&snapshot), None),
+        RunScope::Cell => {
+            let start = selected_range.start;
+            (jupytext_cells(&snapshot, start..start).0, None)
+        }
+    };
 
     for runnable_range in runnable_ranges {
         let Some(language) = multibuffer.read(cx).language_at(runnable_range.start, cx) else {
@@ -112,29 +264,10 @@ pub fn run(editor: WeakView<Editor>, move_down: bool, cx: &mut WindowContext) ->
             session
         } else {
             let weak_editor = editor.downgrade();
-            let session = cx
-                .new_view(|cx| Session::new(weak_editor, fs, telemetry, kernel_specification, cx));
-
-            editor.update(cx, |_editor, cx| {
-                cx.notify();
-
-                cx.subscribe(&session, {
-                    let store = store.clone();
-                    move |_this, _session, event, cx| match event {
-                        SessionEvent::Shutdown(shutdown_event) => {
-                            store.update(cx, |store, _cx| {
-                                store.remove_session(shutdown_event.entity_id());
-                            });
-                        }
-                    }
-                })
-                .detach();
-            });
-
-            store.update(cx, |store, _cx| {
-                store.insert_session(editor.entity_id(), session.clone());
+            let session = cx.new_view(|cx| {
+                Session::new(weak_editor.clone(), fs, telemetry, kernel_specification, cx)
             });
-
+            insert_session(weak_editor, session.clone(), cx);
             session
         };
 
@@ -303,6 +436,32 @@ pub fn setup_editor_session_actions(editor: &mut Editor, editor_handle: WeakView
         })
         .detach();
 
+    editor
+        .register_action({
+            let editor_handle = editor_handle.clone();
+            move |_: &ShutdownKernel, cx| {
+                if !JupyterSettings::enabled(cx) {
+                    return;
+                }
+
+                crate::shutdown(editor_handle.clone(), cx);
+            }
+        })
+        .detach();
+
+    editor
+        .register_action({
+            let editor_handle = editor_handle.clone();
+            move |_: &RestartKernel, cx| {
+                if !JupyterSettings::enabled(cx) {
+                    return;
+                }
+
+                crate::restart(editor_handle.clone(), cx);
+            }
+        })
+        .detach();
+
     editor
         .register_action({
             let editor_handle = editor_handle.clone();
@@ -330,6 +489,36 @@ pub fn setup_editor_session_actions(editor: &mut Editor, editor_handle: WeakView
         .detach();
 }
 
+/// The selected text, or the line with the cursor when nothing is selected. A selection of whole
+/// lines doesn't include the line after them.
+fn selection_ranges(buffer: &BufferSnapshot, range: Range<Point>) -> Vec<Range<Point>> {
+    if range.is_empty() {
+        let row = range.start.row;
+        if buffer.is_line_blank(row) {
+            return Vec::new();
+        }
+        return vec![Point::new(row, 0)..Point::new(row, buffer.line_len(row))];
+    }
+
+    let mut end = range.end;
+    if end.column == 0 && end.row > range.start.row {
+        end = Point::new(end.row - 1, buffer.line_len(end.row - 1));
+    }
+    vec![range.start..end]
+}
+
+/// The whole buffer, without the blank lines at its end.
+fn file_ranges(buffer: &BufferSnapshot) -> Vec<Range<Point>> {
+    let range = cell_range(buffer, 0, buffer.max_point().row);
+    if buffer
+        .text_for_range(range.clone())
+        .all(|chunk| chunk.trim().is_empty())
+    {
+        return Vec::new();
+    }
+    vec![range]
+}
+
 fn cell_range(buffer: &BufferSnapshot, start_row: u32, end_row: u32) -> Range<Point> {
     let mut snippet_end_row = end_row;
     while buffer.is_line_blank(snippet_end_row) && snippet_end_row > start_row {
@@ -338,6 +527,36 @@ fn cell_range(buffer: &BufferSnapshot, start_row: u32, end_row: u32) -> Range<Po
     Point::new(start_row, 0)..Point::new(snippet_end_row, buffer.line_len(snippet_end_row))
 }
 
+/// The prefixes of Jupytext cell markers in the buffer's language, e.g. `# %%` in Python.
+fn jupytext_prefixes(buffer: &BufferSnapshot) -> Vec<String> {
+    let Some(language) = buffer.language() else {
+        return Vec::new();
+    };
+
+    language
+        .default_scope()
+        .line_comment_prefixes()
+        .iter()
+        .map(|comment_prefix| format!("{comment_prefix}%%"))
+        .collect()
+}
+
+/// The rows with a Jupytext cell marker, where each cell starts.
+pub(crate) fn jupytext_marker_rows(buffer: &BufferSnapshot) -> Vec<u32> {
+    let jupytext_prefixes = jupytext_prefixes(buffer);
+    if jupytext_prefixes.is_empty() {
+        return Vec::new();
+    }
+
+    (0..=buffer.max_point().row)
+        .filter(|row| {
+            jupytext_prefixes
+                .iter()
+                .any(|prefix| buffer.contains_str_at(Point::new(*row, 0), prefix))
+        })
+        .collect()
+}
+
 // Returns the ranges of the snippets in the buffer and the next point for moving the cursor to
 fn jupytext_cells(
     buffer: &BufferSnapshot,
@@ -345,21 +564,11 @@ fn jupytext_cells(
 ) -> (Vec<Range<Point>>, Option<Point>) {
     let mut current_row = range.start.row;
 
-    let Some(language) = buffer.language() else {
-        return (Vec::new(), None);
-    };
-
-    let default_scope = language.default_scope();
-    let comment_prefixes = default_scope.line_comment_prefixes();
-    if comment_prefixes.is_empty() {
+    let jupytext_prefixes = jupytext_prefixes(buffer);
+    if jupytext_prefixes.is_empty() {
         return (Vec::new(), None);
     }
 
-    let jupytext_prefixes = comment_prefixes
-        .iter()
-        .map(|comment_prefix| format!("{comment_prefix}%%"))
-        .collect::<Vec<_>>();
-
     let mut snippet_start_row = None;
     loop {
         if jupytext_prefixes
@@ -536,6 +745,71 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_selection_ranges(cx: &mut AppContext) {
+        let buffer = cx.new_model(|cx| {
+            Buffer::local(
+                indoc! { r#"
+                    # %%
+                    x = compute(1) + compute(2)
+                    print(x)
+
+                "# },
+                cx,
+            )
+        });
+        let snapshot = buffer.read(cx).snapshot();
+        let texts = |ranges: Vec<Range<Point>>| {
+            ranges
+                .into_iter()
+                .map(|range| snapshot.text_for_range(range).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+
+        // Only the selected expression runs, not the cell it's in
+        let ranges = selection_ranges(&snapshot, Point::new(1, 4)..Point::new(1, 14));
+        assert_eq!(texts(ranges), vec!["compute(1)"]);
+
+        // Without a selection, the line with the cursor runs
+        let ranges = selection_ranges(&snapshot, Point::new(2, 3)..Point::new(2, 3));
+        assert_eq!(texts(ranges), vec!["print(x)"]);
+        assert!(selection_ranges(&snapshot, Point::new(3, 0)..Point::new(3, 0)).is_empty());
+
+        // Selecting whole lines leaves out the newline after them
+        let ranges = selection_ranges(&snapshot, Point::new(1, 0)..Point::new(3, 0));
+        assert_eq!(
+            texts(ranges),
+            vec![indoc! { r#"
+                x = compute(1) + compute(2)
+                print(x)"# }]
+        );
+    }
+
+    #[gpui::test]
+    fn test_file_ranges(cx: &mut AppContext) {
+        let buffer = cx.new_model(|cx| {
+            Buffer::local(
+                indoc! { r#"
+                    import math
+
+                    # %%
+                    radius = 2
+                    print(math.pi * radius ** 2)
+
+
+                "# },
+                cx,
+            )
+        });
+        let snapshot = buffer.read(cx).snapshot();
+
+        let ranges = file_ranges(&snapshot);
+        assert_eq!(ranges, vec![Point::new(0, 0)..Point::new(4, 28)]);
+
+        let blank = cx.new_model(|cx| Buffer::local("\n\n  \n", cx));
+        assert!(file_ranges(&blank.read(cx).snapshot()).is_empty());
+    }
+
     #[gpui::test]
     fn test_jupytext_snippet_ranges(cx: &mut AppContext) {
         // Create a test language
@@ -612,6 +886,8 @@ mod tests {
                 }
             ]
         );
+
+        assert_eq!(jupytext_marker_rows(&snapshot), vec![1, 6]);
     }
 
     #[gpui::test]
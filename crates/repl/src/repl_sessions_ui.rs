@@ -10,6 +10,7 @@ use workspace::item::ItemEvent;
 use workspace::WorkspaceId;
 use workspace::{item::Item, Workspace};
 
+use crate::cell_markers;
 use crate::jupyter_settings::JupyterSettings;
 use crate::repl_store::ReplStore;
 
@@ -18,9 +19,16 @@ actions!(
     [
         Run,
         RunInPlace,
+        RunSelection,
+        RunFile,
+        RunCell,
+        AttachToNotebook,
         ClearOutputs,
         Sessions,
         Interrupt,
+        ShutdownKernel,
+        RestartKernel,
+        // The former names of `ShutdownKernel` and `RestartKernel`, still bound in user keymaps
         Shutdown,
         Restart,
         RefreshKernelspecs
@@ -115,6 +123,60 @@ pub fn init(cx: &mut AppContext) {
                     }
                 })
                 .detach();
+
+            editor
+                .register_action({
+                    let editor_handle = editor_handle.clone();
+                    move |_: &RunSelection, cx| {
+                        if !JupyterSettings::enabled(cx) {
+                            return;
+                        }
+
+                        crate::run_selection(editor_handle.clone(), cx).log_err();
+                    }
+                })
+                .detach();
+
+            editor
+                .register_action({
+                    let editor_handle = editor_handle.clone();
+                    move |_: &RunFile, cx| {
+                        if !JupyterSettings::enabled(cx) {
+                            return;
+                        }
+
+                        crate::run_file(editor_handle.clone(), cx).log_err();
+                    }
+                })
+                .detach();
+
+            editor
+                .register_action({
+                    let editor_handle = editor_handle.clone();
+                    move |_: &RunCell, cx| {
+                        if !JupyterSettings::enabled(cx) {
+                            return;
+                        }
+
+                        crate::run_cell(editor_handle.clone(), cx).log_err();
+                    }
+                })
+                .detach();
+
+            editor
+                .register_action({
+                    let editor_handle = editor_handle.clone();
+                    move |_: &AttachToNotebook, cx| {
+                        if !JupyterSettings::enabled(cx) {
+                            return;
+                        }
+
+                        crate::attach_to_notebook(editor_handle.clone(), cx).log_err();
+                    }
+                })
+                .detach();
+
+            cell_markers::register(editor, cx);
         });
     })
     .detach();
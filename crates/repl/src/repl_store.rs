@@ -1,19 +1,30 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use client::telemetry::Telemetry;
 use collections::HashMap;
 use command_palette_hooks::CommandPaletteFilter;
+use futures::StreamExt as _;
 use gpui::{
     prelude::*, AppContext, EntityId, Global, Model, ModelContext, Subscription, Task, View,
 };
 use language::Language;
 use project::{Fs, Project, WorktreeId};
+use runtimelib::dirs;
 use settings::{Settings, SettingsStore};
+use util::ResultExt as _;
 
-use crate::kernels::{local_kernel_specifications, python_env_kernel_specifications};
+use crate::kernels::{
+    external_kernel_specifications, is_external_connection_file, local_kernel_specifications,
+    python_env_kernel_specifications,
+};
 use crate::{JupyterSettings, KernelSpecification, Session};
 
+/// How long to wait for more changes to the Jupyter runtime directory before listing its
+/// kernels again.
+const RUNTIME_DIR_WATCH_LATENCY: Duration = Duration::from_millis(500);
+
 struct GlobalReplStore(Model<ReplStore>);
 
 impl Global for GlobalReplStore {}
@@ -25,7 +36,10 @@ pub struct ReplStore {
     kernel_specifications: Vec<KernelSpecification>,
     selected_kernel_for_worktree: HashMap<WorktreeId, KernelSpecification>,
     kernel_specifications_for_worktree: HashMap<WorktreeId, Vec<KernelSpecification>>,
+    /// Live kernels other Jupyter clients started.
+    external_kernel_specifications: Vec<KernelSpecification>,
     telemetry: Arc<Telemetry>,
+    _runtime_dir_watcher: Option<Task<()>>,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -38,6 +52,7 @@ impl ReplStore {
         store
             .update(cx, |store, cx| store.refresh_kernelspecs(cx))
             .detach_and_log_err(cx);
+        store.update(cx, |store, cx| store.watch_runtime_dir(cx));
 
         cx.set_global(GlobalReplStore(store))
     }
@@ -57,6 +72,8 @@ impl ReplStore {
             enabled: JupyterSettings::enabled(cx),
             sessions: HashMap::default(),
             kernel_specifications: Vec::new(),
+            external_kernel_specifications: Vec::new(),
+            _runtime_dir_watcher: None,
             _subscriptions: subscriptions,
             kernel_specifications_for_worktree: HashMap::default(),
             selected_kernel_for_worktree: HashMap::default(),
@@ -86,12 +103,17 @@ impl ReplStore {
             .into_iter()
             .flat_map(|specs| specs.iter())
             .chain(self.kernel_specifications.iter())
+            .chain(self.external_kernel_specifications.iter())
     }
 
     pub fn pure_jupyter_kernel_specifications(&self) -> impl Iterator<Item = &KernelSpecification> {
         self.kernel_specifications.iter()
     }
 
+    pub fn external_kernel_specifications(&self) -> impl Iterator<Item = &KernelSpecification> {
+        self.external_kernel_specifications.iter()
+    }
+
     pub fn sessions(&self) -> impl Iterator<Item = &View<Session>> {
         self.sessions.values()
     }
@@ -141,6 +163,34 @@ impl ReplStore {
         })
     }
 
+    /// Lists the kernels that can run on the host of an SSH project for one of its worktrees.
+    pub fn refresh_ssh_kernelspecs(
+        &mut self,
+        worktree_id: WorktreeId,
+        project: &Model<Project>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        let Some(kernel_store) = project.read(cx).kernel_store() else {
+            return Task::ready(Ok(()));
+        };
+        let kernel_specifications = kernel_store.read(cx).kernel_specifications(worktree_id, cx);
+
+        cx.spawn(move |this, mut cx| async move {
+            let kernel_specifications = kernel_specifications
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to get kernelspecs on the host: {:?}", e))?
+                .into_iter()
+                .map(|specification| KernelSpecification::Ssh(specification.into()))
+                .collect();
+
+            this.update(&mut cx, |this, cx| {
+                this.kernel_specifications_for_worktree
+                    .insert(worktree_id, kernel_specifications);
+                cx.notify();
+            })
+        })
+    }
+
     pub fn refresh_kernelspecs(&mut self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
         let local_kernel_specifications = local_kernel_specifications(self.fs.clone());
 
@@ -154,11 +204,73 @@ impl ReplStore {
 
             this.update(&mut cx, |this, cx| {
                 this.kernel_specifications = kernel_options;
+                // The installed kernelspecs tell the language of external kernels
+                this.refresh_external_kernelspecs(cx).detach_and_log_err(cx);
                 cx.notify();
             })
         })
     }
 
+    /// Replaces the external kernels offered in the kernel picker with the ones running now,
+    /// labelled with the languages of the installed Jupyter kernelspecs.
+    pub fn refresh_external_kernelspecs(
+        &mut self,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        let kernelspecs = self
+            .kernel_specifications
+            .iter()
+            .filter_map(|specification| match specification {
+                KernelSpecification::Jupyter(specification) => Some(specification.clone()),
+                _ => None,
+            })
+            .collect();
+        let external_kernel_specifications = external_kernel_specifications(
+            kernelspecs,
+            self.fs.clone(),
+            cx.background_executor().clone(),
+        );
+
+        cx.spawn(|this, mut cx| async move {
+            let external_kernel_specifications = external_kernel_specifications
+                .await?
+                .into_iter()
+                .map(KernelSpecification::External)
+                .collect();
+
+            this.update(&mut cx, |this, cx| {
+                this.external_kernel_specifications = external_kernel_specifications;
+                cx.notify();
+            })
+        })
+    }
+
+    /// Lists external kernels again whenever a connection file is added to or removed from the
+    /// Jupyter runtime directory.
+    fn watch_runtime_dir(&mut self, cx: &mut ModelContext<Self>) {
+        let fs = self.fs.clone();
+        self._runtime_dir_watcher = Some(cx.spawn(|this, mut cx| async move {
+            let runtime_dir = dirs::runtime_dir();
+            fs.create_dir(&runtime_dir).await.log_err();
+            let (mut events, _watcher) = fs.watch(&runtime_dir, RUNTIME_DIR_WATCH_LATENCY).await;
+
+            while let Some(events) = events.next().await {
+                if !events
+                    .iter()
+                    .any(|event| is_external_connection_file(&event.path))
+                {
+                    continue;
+                }
+                let Ok(refresh) =
+                    this.update(&mut cx, |this, cx| this.refresh_external_kernelspecs(cx))
+                else {
+                    break;
+                };
+                refresh.await.log_err();
+            }
+        }));
+    }
+
     pub fn set_active_kernelspec(
         &mut self,
         worktree_id: WorktreeId,
@@ -227,6 +339,9 @@ impl ReplStore {
                 KernelSpecification::Remote(_) => {
                     unimplemented!()
                 }
+                // Kernels of SSH projects are only ever picked for their worktree, and kernels
+                // other clients started only when they're chosen
+                KernelSpecification::Ssh(_) | KernelSpecification::External(_) => false,
             })
             .cloned()
     }
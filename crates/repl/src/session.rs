@@ -1,8 +1,14 @@
-use crate::components::KernelListItem;
+use crate::components::{kernel_status_color, KernelListItem};
+use crate::magics::highlight_magics;
+use crate::notebook::NotebookEditor;
 use crate::setup_editor_session_actions;
 use crate::{
-    kernels::{Kernel, KernelSpecification, NativeRunningKernel},
+    kernels::{
+        kernel_environment, ExternalKernelSpecification, ExternalRunningKernel,
+        JupyterMessageChannel, Kernel, KernelSpecification, NativeRunningKernel,
+    },
     outputs::{ExecutionStatus, ExecutionView},
+    plot_theme::PlotTheme,
     KernelStatus,
 };
 use client::telemetry::Telemetry;
@@ -15,18 +21,18 @@ use editor::{
     scroll::Autoscroll,
     Anchor, AnchorRangeExt as _, Editor, MultiBuffer, ToPoint,
 };
-use futures::io::BufReader;
-use futures::{AsyncBufReadExt as _, FutureExt as _, StreamExt as _};
+use futures::{FutureExt as _, StreamExt as _};
 use gpui::{
-    div, prelude::*, EventEmitter, Model, Render, Subscription, Task, View, ViewContext, WeakView,
+    div, prelude::*, AppContext, EventEmitter, Model, Render, Subscription, Task, View,
+    ViewContext, WeakView,
 };
 use language::Point;
 use project::Fs;
 use runtimelib::{
-    ExecuteRequest, ExecutionState, InterruptRequest, JupyterMessage, JupyterMessageContent,
-    ShutdownRequest,
+    ExecuteRequest, InterruptRequest, JupyterMessage, JupyterMessageContent, ShutdownRequest,
 };
-use std::{env::temp_dir, ops::Range, sync::Arc, time::Duration};
+use settings::SettingsStore;
+use std::{env::temp_dir, ops::Range, path::PathBuf, sync::Arc, time::Duration};
 use theme::ActiveTheme;
 use ui::{prelude::*, IconButtonShape, Tooltip};
 
@@ -39,7 +45,14 @@ pub struct Session {
     process_status_task: Option<Task<()>>,
     pub kernel_specification: KernelSpecification,
     telemetry: Arc<Telemetry>,
+    /// The notebook whose kernel runs the editor's code, when the editor is attached to one
+    /// instead of starting its own kernel.
+    notebook: Option<WeakView<NotebookEditor>>,
+    /// The theme the kernel last styled plots with, see [`PlotTheme`].
+    plot_theme: Option<PlotTheme>,
     _buffer_subscription: Subscription,
+    _notebook_subscription: Option<Subscription>,
+    _settings_subscription: Subscription,
 }
 
 struct EditorBlock {
@@ -192,7 +205,7 @@ impl EditorBlock {
 }
 
 impl Session {
-    pub fn new(
+    fn for_editor(
         editor: WeakView<Editor>,
         fs: Arc<dyn Fs>,
         telemetry: Arc<Telemetry>,
@@ -215,7 +228,7 @@ impl Session {
             })
             .ok();
 
-        let mut session = Self {
+        let session = Self {
             fs,
             editor,
             kernel: Kernel::StartingKernel(Task::ready(()).shared()),
@@ -223,14 +236,94 @@ impl Session {
             process_status_task: None,
             blocks: HashMap::default(),
             kernel_specification,
+            notebook: None,
+            plot_theme: None,
             _buffer_subscription: subscription,
+            _notebook_subscription: None,
+            _settings_subscription: cx
+                .observe_global::<SettingsStore>(|session, cx| session.apply_plot_theme(cx)),
             telemetry,
         };
+        session.highlight_magics(cx);
+        session
+    }
 
+    pub fn new(
+        editor: WeakView<Editor>,
+        fs: Arc<dyn Fs>,
+        telemetry: Arc<Telemetry>,
+        kernel_specification: KernelSpecification,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let mut session = Self::for_editor(editor, fs, telemetry, kernel_specification, cx);
         session.start_kernel(cx);
         session
     }
 
+    /// A session that runs the editor's code on a notebook's kernel instead of starting its own,
+    /// so it sees the state the notebook's cells built up. The session ends when the notebook is
+    /// closed.
+    pub fn attached(
+        editor: WeakView<Editor>,
+        fs: Arc<dyn Fs>,
+        telemetry: Arc<Telemetry>,
+        kernel_specification: KernelSpecification,
+        notebook: View<NotebookEditor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let mut session = Self::for_editor(editor, fs, telemetry, kernel_specification, cx);
+        session.kernel = Kernel::Shutdown;
+        session.notebook = Some(notebook.downgrade());
+        session._notebook_subscription =
+            Some(cx.observe_release(&notebook, |session, _, cx| session.detach(cx)));
+        session
+    }
+
+    pub fn editor(&self) -> WeakView<Editor> {
+        self.editor.clone()
+    }
+
+    pub(crate) fn attached_notebook(&self) -> Option<View<NotebookEditor>> {
+        self.notebook.as_ref()?.upgrade()
+    }
+
+    /// The file name of the notebook whose kernel runs the editor's code, if it's attached to one.
+    pub fn attached_notebook_name(&self, cx: &AppContext) -> Option<String> {
+        self.attached_notebook()
+            .map(|notebook| notebook.read(cx).file_name(cx))
+    }
+
+    /// Stops running the editor's code on the notebook's kernel. The editor starts its own kernel
+    /// the next time code is run.
+    fn detach(&mut self, cx: &mut ViewContext<Self>) {
+        self.notebook = None;
+        self._notebook_subscription = None;
+        self.clear_outputs(cx);
+        self.kernel(Kernel::Shutdown, cx);
+        cx.notify();
+    }
+
+    /// The environment of the editor's project to start the kernel in, see [`kernel_environment`].
+    fn kernel_environment(&self, cx: &mut ViewContext<Self>) -> Task<HashMap<String, String>> {
+        let Some(editor) = self.editor.upgrade() else {
+            return Task::ready(HashMap::default());
+        };
+        let Some(project) = editor
+            .read(cx)
+            .workspace()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(HashMap::default());
+        };
+        let worktree_id = editor
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()
+            .and_then(|buffer| Some(buffer.read(cx).file()?.worktree_id(cx)));
+        kernel_environment(&project, worktree_id, cx)
+    }
+
     fn start_kernel(&mut self, cx: &mut ViewContext<Self>) {
         let kernel_language = self.kernel_specification.language();
         let entity_id = self.editor.entity_id();
@@ -248,16 +341,33 @@ impl Session {
 
         let kernel = match self.kernel_specification.clone() {
             KernelSpecification::Jupyter(kernel_specification)
-            | KernelSpecification::PythonEnv(kernel_specification) => NativeRunningKernel::new(
-                kernel_specification,
-                entity_id,
-                working_directory,
-                self.fs.clone(),
-                cx,
-            ),
+            | KernelSpecification::PythonEnv(kernel_specification) => {
+                let environment = self.kernel_environment(cx);
+                NativeRunningKernel::new(
+                    kernel_specification,
+                    entity_id,
+                    working_directory,
+                    environment,
+                    self.fs.clone(),
+                    cx,
+                )
+            }
             KernelSpecification::Remote(_remote_kernel_specification) => {
                 unimplemented!()
             }
+            KernelSpecification::Ssh(_) => {
+                self.kernel(
+                    Kernel::ErroredLaunch(
+                        "Kernels on SSH hosts are only supported in notebooks".to_string(),
+                    ),
+                    cx,
+                );
+                return;
+            }
+            KernelSpecification::External(kernel_specification) => {
+                self.connect_external_kernel(kernel_specification, working_directory, cx);
+                return;
+            }
         };
 
         let pending_kernel = cx
@@ -265,36 +375,9 @@ impl Session {
                 let kernel = kernel.await;
 
                 match kernel {
-                    Ok((mut kernel, mut messages_rx)) => {
+                    Ok((mut kernel, messages_rx)) => {
                         this.update(&mut cx, |session, cx| {
-                            let stderr = kernel.process.stderr.take();
-
-                            cx.spawn(|_session, mut _cx| async move {
-                                if stderr.is_none() {
-                                    return;
-                                }
-                                let reader = BufReader::new(stderr.unwrap());
-                                let mut lines = reader.lines();
-                                while let Some(Ok(line)) = lines.next().await {
-                                    // todo!(): Log stdout and stderr to something the session can show
-                                    log::error!("kernel: {}", line);
-                                }
-                            })
-                            .detach();
-
-                            let stdout = kernel.process.stdout.take();
-
-                            cx.spawn(|_session, mut _cx| async move {
-                                if stdout.is_none() {
-                                    return;
-                                }
-                                let reader = BufReader::new(stdout.unwrap());
-                                let mut lines = reader.lines();
-                                while let Some(Ok(line)) = lines.next().await {
-                                    log::info!("kernel: {}", line);
-                                }
-                            })
-                            .detach();
+                            kernel.log_process_output(cx);
 
                             let status = kernel.process.status();
                             session.kernel(Kernel::RunningKernel(Box::new(kernel)), cx);
@@ -318,48 +401,13 @@ impl Session {
 
                                 session
                                     .update(&mut cx, |session, cx| {
-                                        session.kernel(
-                                            Kernel::ErroredLaunch(error_message.clone()),
-                                            cx,
-                                        );
-
-                                        session.blocks.values().for_each(|block| {
-                                            block.execution_view.update(
-                                                cx,
-                                                |execution_view, cx| {
-                                                    match execution_view.status {
-                                                        ExecutionStatus::Finished => {
-                                                            // Do nothing when the output was good
-                                                        }
-                                                        _ => {
-                                                            // All other cases, set the status to errored
-                                                            execution_view.status =
-                                                                ExecutionStatus::KernelErrored(
-                                                                    error_message.clone(),
-                                                                )
-                                                        }
-                                                    }
-                                                    cx.notify();
-                                                },
-                                            );
-                                        });
-
-                                        cx.notify();
+                                        session.kernel_errored(error_message, cx)
                                     })
                                     .ok();
                             });
 
                             session.process_status_task = Some(process_status_task);
-
-                            session.messaging_task = Some(cx.spawn(|session, mut cx| async move {
-                                while let Some(message) = messages_rx.next().await {
-                                    session
-                                        .update(&mut cx, |session, cx| {
-                                            session.route(&message, cx);
-                                        })
-                                        .ok();
-                                }
-                            }));
+                            session.messaging_task = Some(Self::route_messages(messages_rx, cx));
 
                             // todo!(@rgbkrk): send KernelInfoRequest once our shell channel read/writes are split
                             // cx.spawn(|this, mut cx| async move {
@@ -389,6 +437,106 @@ impl Session {
         cx.notify();
     }
 
+    /// Connects to a kernel another client started. Zed doesn't own its process, so it only
+    /// notices the kernel stopped when it no longer answers heartbeats.
+    fn connect_external_kernel(
+        &mut self,
+        kernel_specification: ExternalKernelSpecification,
+        working_directory: PathBuf,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let kernel = ExternalRunningKernel::new(kernel_specification, working_directory, cx);
+
+        let pending_kernel = cx
+            .spawn(|this, mut cx| async move {
+                match kernel.await {
+                    Ok((kernel, messages_rx)) => {
+                        this.update(&mut cx, |session, cx| {
+                            let heartbeat_lost = kernel.heartbeat_lost(cx);
+                            session.kernel(Kernel::RunningKernel(Box::new(kernel)), cx);
+
+                            session.process_status_task =
+                                Some(cx.spawn(|session, mut cx| async move {
+                                    heartbeat_lost.await;
+                                    session
+                                        .update(&mut cx, |session, cx| {
+                                            session.kernel_errored(
+                                                "kernel stopped responding to heartbeats"
+                                                    .to_string(),
+                                                cx,
+                                            )
+                                        })
+                                        .ok();
+                                }));
+                            session.messaging_task = Some(Self::route_messages(messages_rx, cx));
+                            cx.notify();
+                        })
+                        .ok();
+                    }
+                    Err(err) => {
+                        this.update(&mut cx, |session, cx| {
+                            session.kernel(Kernel::ErroredLaunch(err.to_string()), cx);
+                            cx.notify();
+                        })
+                        .ok();
+                    }
+                }
+            })
+            .shared();
+
+        self.kernel(Kernel::StartingKernel(pending_kernel), cx);
+        cx.notify();
+    }
+
+    fn route_messages(
+        mut messages_rx: JupyterMessageChannel,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<()> {
+        cx.spawn(|session, mut cx| async move {
+            while let Some(message) = messages_rx.next().await {
+                session
+                    .update(&mut cx, |session, cx| {
+                        session.route(&message, cx);
+                    })
+                    .ok();
+            }
+        })
+    }
+
+    /// Marks the kernel as failed, along with every execution that hadn't finished.
+    fn kernel_errored(&mut self, error_message: String, cx: &mut ViewContext<Self>) {
+        self.kernel(Kernel::ErroredLaunch(error_message.clone()), cx);
+
+        self.blocks.values().for_each(|block| {
+            block.execution_view.update(cx, |execution_view, cx| {
+                match execution_view.status {
+                    ExecutionStatus::Finished => {
+                        // Do nothing when the output was good
+                    }
+                    _ => {
+                        // All other cases, set the status to errored
+                        execution_view.status =
+                            ExecutionStatus::KernelErrored(error_message.clone())
+                    }
+                }
+                cx.notify();
+            });
+        });
+
+        cx.notify();
+    }
+
+    /// Highlights the IPython magics in the editor when its code runs on a Python kernel.
+    fn highlight_magics(&self, cx: &mut ViewContext<Self>) {
+        if self
+            .kernel_specification
+            .language()
+            .eq_ignore_ascii_case("python")
+        {
+            self.editor.update(cx, highlight_magics).ok();
+        }
+    }
+
     fn on_buffer_event(
         &mut self,
         buffer: Model<MultiBuffer>,
@@ -396,6 +544,7 @@ impl Session {
         cx: &mut ViewContext<Self>,
     ) {
         if let multi_buffer::Event::Edited { .. } = event {
+            self.highlight_magics(cx);
             let snapshot = buffer.read(cx).snapshot(cx);
 
             let mut blocks_to_remove: HashSet<CustomBlockId> = HashSet::default();
@@ -420,6 +569,21 @@ impl Session {
         }
     }
 
+    /// Styles the kernel's plots with the current theme, unless they already are.
+    fn apply_plot_theme(&mut self, cx: &mut ViewContext<Self>) {
+        let Kernel::RunningKernel(_) = &self.kernel else {
+            return;
+        };
+        let plot_theme = PlotTheme::for_kernel(&self.kernel_specification.language(), cx);
+        if plot_theme == self.plot_theme {
+            return;
+        }
+        if let Some(plot_theme) = &plot_theme {
+            self.send(plot_theme.setup_request(), cx).ok();
+        }
+        self.plot_theme = plot_theme;
+    }
+
     fn send(&mut self, message: JupyterMessage, _cx: &mut ViewContext<Self>) -> anyhow::Result<()> {
         if let Kernel::RunningKernel(kernel) = &mut self.kernel {
             kernel.request_tx().try_send(message).ok();
@@ -458,7 +622,7 @@ impl Session {
         }
 
         let execute_request = ExecuteRequest {
-            code,
+            code: code.clone(),
             ..ExecuteRequest::default()
         };
 
@@ -483,13 +647,10 @@ impl Session {
             })
             .ok();
 
-        let status = match &self.kernel {
-            Kernel::Restarting => ExecutionStatus::Restarting,
-            Kernel::RunningKernel(_) => ExecutionStatus::Queued,
-            Kernel::StartingKernel(_) => ExecutionStatus::ConnectingToKernel,
-            Kernel::ErroredLaunch(error) => ExecutionStatus::KernelErrored(error.clone()),
-            Kernel::ShuttingDown => ExecutionStatus::ShuttingDown,
-            Kernel::Shutdown => ExecutionStatus::Shutdown,
+        let notebook = self.attached_notebook();
+        let status = match notebook {
+            Some(_) => ExecutionStatus::Queued,
+            None => ExecutionStatus::from(&self.kernel),
         };
 
         let parent_message_id = message.header.msg_id.clone();
@@ -526,28 +687,46 @@ impl Session {
             editor_block.invalidation_anchor
         };
 
+        let execution_view = editor_block.execution_view.downgrade();
         self.blocks
             .insert(message.header.msg_id.clone(), editor_block);
 
-        match &self.kernel {
-            Kernel::RunningKernel(_) => {
-                self.send(message, cx).ok();
+        if let Some(notebook) = notebook {
+            // The notebook routes the kernel's replies straight to the output block
+            let ran = notebook.update(cx, |notebook, cx| {
+                notebook.execute_detached(code, execution_view.clone(), cx)
+            });
+            if !ran {
+                execution_view
+                    .update(cx, |execution_view, cx| {
+                        execution_view.status = ExecutionStatus::KernelErrored(
+                            "The notebook can't run code from here".to_string(),
+                        );
+                        cx.notify();
+                    })
+                    .ok();
             }
-            Kernel::StartingKernel(task) => {
-                // Queue up the execution as a task to run after the kernel starts
-                let task = task.clone();
-                let message = message.clone();
+        } else {
+            match &self.kernel {
+                Kernel::RunningKernel(_) => {
+                    self.send(message, cx).ok();
+                }
+                Kernel::StartingKernel(task) => {
+                    // Queue up the execution as a task to run after the kernel starts
+                    let task = task.clone();
+                    let message = message.clone();
 
-                cx.spawn(|this, mut cx| async move {
-                    task.await;
-                    this.update(&mut cx, |session, cx| {
-                        session.send(message, cx).ok();
+                    cx.spawn(|this, mut cx| async move {
+                        task.await;
+                        this.update(&mut cx, |session, cx| {
+                            session.send(message, cx).ok();
+                        })
+                        .ok();
                     })
-                    .ok();
-                })
-                .detach();
+                    .detach();
+                }
+                _ => {}
             }
-            _ => {}
         }
 
         if move_down {
@@ -630,12 +809,29 @@ impl Session {
         );
 
         self.kernel = kernel;
+        self.plot_theme = None;
+        self.apply_plot_theme(cx);
     }
 
     pub fn shutdown(&mut self, cx: &mut ViewContext<Self>) {
+        // The notebook's kernel keeps running for the notebook
+        if self.notebook.is_some() {
+            self.detach(cx);
+            return;
+        }
+
         let kernel = std::mem::replace(&mut self.kernel, Kernel::ShuttingDown);
 
         match kernel {
+            // Kernels other clients started keep running for them
+            Kernel::RunningKernel(mut kernel) if self.kernel_specification.is_external() => {
+                self.messaging_task.take();
+                self.process_status_task.take();
+                kernel.force_shutdown().ok();
+                self.clear_outputs(cx);
+                self.kernel(Kernel::Shutdown, cx);
+                cx.notify();
+            }
             Kernel::RunningKernel(mut kernel) => {
                 let mut request_tx = kernel.request_tx().clone();
 
@@ -673,12 +869,26 @@ impl Session {
     }
 
     pub fn restart(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(notebook) = self.attached_notebook() {
+            self.clear_outputs(cx);
+            notebook.update(cx, |notebook, cx| notebook.restart_kernel(cx));
+            return;
+        }
+
         let kernel = std::mem::replace(&mut self.kernel, Kernel::Restarting);
 
         match kernel {
             Kernel::Restarting => {
                 // Do nothing if already restarting
             }
+            // Only the client that started the kernel can restart it, so reconnect instead
+            Kernel::RunningKernel(mut kernel) if self.kernel_specification.is_external() => {
+                self.messaging_task.take();
+                self.process_status_task.take();
+                kernel.force_shutdown().ok();
+                self.clear_outputs(cx);
+                self.start_kernel(cx);
+            }
             Kernel::RunningKernel(mut kernel) => {
                 let mut request_tx = kernel.request_tx().clone();
 
@@ -730,6 +940,20 @@ impl EventEmitter<SessionEvent> for Session {}
 
 impl Render for Session {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        if let Some(notebook_name) = self.attached_notebook_name(cx) {
+            return KernelListItem::new(self.kernel_specification.clone())
+                .status_color(Color::Success)
+                .child(Label::new(self.kernel_specification.name()))
+                .child(Label::new(format!("(Attached to {notebook_name})")))
+                .button(
+                    Button::new("detach", "Detach")
+                        .style(ButtonStyle::Subtle)
+                        .on_click(cx.listener(move |session, _, cx| {
+                            session.detach(cx);
+                        })),
+                );
+        }
+
         let (status_text, interrupt_button) = match &self.kernel {
             Kernel::RunningKernel(kernel) => (
                 kernel
@@ -745,24 +969,14 @@ impl Render for Session {
                 ),
             ),
             Kernel::StartingKernel(_) => (Some("Starting".into()), None),
-            Kernel::ErroredLaunch(err) => (Some(format!("Error: {err}")), None),
+            Kernel::ErroredLaunch(err) | Kernel::Died(err) => (Some(format!("Error: {err}")), None),
             Kernel::ShuttingDown => (Some("Shutting Down".into()), None),
             Kernel::Shutdown => (Some("Shutdown".into()), None),
             Kernel::Restarting => (Some("Restarting".into()), None),
         };
 
         KernelListItem::new(self.kernel_specification.clone())
-            .status_color(match &self.kernel {
-                Kernel::RunningKernel(kernel) => match kernel.execution_state() {
-                    ExecutionState::Idle => Color::Success,
-                    ExecutionState::Busy => Color::Modified,
-                },
-                Kernel::StartingKernel(_) => Color::Modified,
-                Kernel::ErroredLaunch(_) => Color::Error,
-                Kernel::ShuttingDown => Color::Modified,
-                Kernel::Shutdown => Color::Disabled,
-                Kernel::Restarting => Color::Modified,
-            })
+            .status_color(kernel_status_color(&self.kernel))
             .child(Label::new(self.kernel_specification.name()))
             .children(status_text.map(|status_text| Label::new(format!("({status_text})"))))
             .button(
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use gpui::{AppContext, Global, ViewContext};
+
+use crate::Workspace;
+
+/// Lets items that aren't editors, like notebooks, talk to the assistant panel without
+/// depending on the crate that implements it. The assistant registers itself with
+/// [`AssistantDelegate::set_global`] when it's initialized.
+pub trait AssistantDelegate {
+    /// Quotes text into the active context, or a new one, folded under the title paired with it.
+    fn quote_creases(
+        &self,
+        workspace: &mut Workspace,
+        creases: Vec<(String, String)>,
+        cx: &mut ViewContext<Workspace>,
+    );
+
+    /// The selected text in the active context, or the code block under the cursor.
+    fn selection_or_code_block(
+        &self,
+        workspace: &mut Workspace,
+        cx: &mut ViewContext<Workspace>,
+    ) -> Option<String>;
+}
+
+struct GlobalAssistantDelegate(Arc<dyn AssistantDelegate>);
+
+impl Global for GlobalAssistantDelegate {}
+
+impl dyn AssistantDelegate {
+    /// The registered assistant, if there is one.
+    pub fn global(cx: &AppContext) -> Option<Arc<Self>> {
+        cx.try_global::<GlobalAssistantDelegate>()
+            .map(|delegate| delegate.0.clone())
+    }
+
+    pub fn set_global(delegate: Arc<Self>, cx: &mut AppContext) {
+        cx.set_global(GlobalAssistantDelegate(delegate));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod assistant_delegate;
 pub mod dock;
 pub mod item;
 mod modal_layer;
@@ -433,7 +433,7 @@ fn main() {
         app_state.languages.set_theme(cx.theme().clone());
         editor::init(cx);
         image_viewer::init(cx);
-        repl::notebook::init(cx);
+        repl::notebook::init(&app_state.client, cx);
         diagnostics::init(cx);
 
         audio::init(Assets, cx);
@@ -3512,7 +3512,7 @@ mod tests {
                 app_state.client.telemetry().clone(),
                 cx,
             );
-            repl::notebook::init(cx);
+            repl::notebook::init(&app_state.client, cx);
             tasks_ui::init(cx);
             initialize_workspace(app_state.clone(), prompt_builder, cx);
             search::init(cx);